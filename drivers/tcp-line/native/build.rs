@@ -1,3 +1,11 @@
 fn main() {
   napi_build::setup();
+  // Vendored so building the addon doesn't need protoc installed.
+  std::env::set_var("PROTOC", protoc_bin_vendored::protoc_bin_path().expect("vendored protoc"));
+  tonic_prost_build::configure()
+    .build_client(false)
+    .compile_protos(&["proto/telemetry.proto"], &["proto"])
+    .expect("compile proto/telemetry.proto");
+  // napi_build's rerun-if-env-changed lines switch off cargo's default of rerunning on any file change.
+  println!("cargo:rerun-if-changed=proto/telemetry.proto");
 }
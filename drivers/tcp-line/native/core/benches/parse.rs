@@ -0,0 +1,119 @@
+//! Criterion benches of `TcpLineParser::parse_line` by format, in lines per second, plus the heap allocations per
+//! line of a plain pass printed ahead of each.
+//!
+//! `cargo bench -p tcp-line-core --bench parse`; set `PARSE_BENCH_LINES` to change the lines per format (default
+//! 1000).
+
+use std::alloc::{GlobalAlloc, Layout, System};
+use std::hint::black_box;
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+use criterion::{criterion_group, criterion_main, Criterion, Throughput};
+use tcp_line_core::throughput;
+use tcp_line_core::{ParserConfig, TcpLineParser};
+
+struct Counting;
+
+static ALLOCATIONS: AtomicUsize = AtomicUsize::new(0);
+
+unsafe impl GlobalAlloc for Counting {
+  unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+    ALLOCATIONS.fetch_add(1, Ordering::Relaxed);
+    System.alloc(layout)
+  }
+
+  unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+    System.dealloc(ptr, layout)
+  }
+
+  unsafe fn realloc(&self, ptr: *mut u8, layout: Layout, new_size: usize) -> *mut u8 {
+    ALLOCATIONS.fetch_add(1, Ordering::Relaxed);
+    System.realloc(ptr, layout, new_size)
+  }
+}
+
+#[global_allocator]
+static GLOBAL: Counting = Counting;
+
+const BASE: &str = r#"{
+  "format": "jsonl",
+  "csv": { "hasHeader": false, "columns": [], "delimiter": "," },
+  "offsets": { "btC": 0, "etC": 0 }
+}"#;
+
+fn parser(format: &str, extra: serde_json::Value) -> ParserConfig {
+  let mut config: serde_json::Value = serde_json::from_str(BASE).expect("base config");
+  config["format"] = format.into();
+  if let (Some(config), Some(extra)) = (config.as_object_mut(), extra.as_object()) {
+    config.extend(extra.clone());
+  }
+  serde_json::from_value(config).expect("parser config")
+}
+
+fn run(c: &mut Criterion, name: &str, config: ParserConfig, lines: &[String]) {
+  throughput::measure(config.clone(), lines, 1);
+  let allocations = ALLOCATIONS.load(Ordering::Relaxed);
+  let measured = throughput::measure(config.clone(), lines, 1);
+  let allocations = ALLOCATIONS.load(Ordering::Relaxed) - allocations;
+  assert_eq!(measured.errors, 0, "{name}: every line parses");
+  println!("{name}: {:.1} allocations/line", allocations as f64 / measured.lines as f64);
+
+  let mut group = c.benchmark_group("parse_line");
+  group.throughput(Throughput::Elements(lines.len() as u64));
+  group.bench_function(name, |b| {
+    let mut parser = TcpLineParser::new(config.clone());
+    b.iter(|| {
+      for line in lines {
+        let _ = black_box(parser.parse_line(black_box(line)));
+      }
+    })
+  });
+  group.finish();
+}
+
+/// A timestamp and two temperatures that differ from line to line.
+fn reading(i: usize) -> (String, usize, usize) {
+  (format!("2024-05-01T10:00:{:02}.{:03}Z", i / 1000 % 60, i % 1000), 150 + i % 50, 200 + i % 50)
+}
+
+fn parse_line(c: &mut Criterion) {
+  let count = std::env::var("PARSE_BENCH_LINES").ok().and_then(|lines| lines.parse().ok()).unwrap_or(1000);
+  let lines = |line: fn(usize) -> String| (0..count).map(line).collect::<Vec<_>>();
+
+  let jsonl = lines(|i| {
+    let (ts, bt, et) = reading(i);
+    let channels = r#""powerPct":80,"fanPct":45,"drumRpm":60"#;
+    format!(r#"{{"ts":"{ts}","btC":{bt}.5,"etC":{et}.25,{channels},"roR":9.5,"phase":"drying"}}"#)
+  });
+  run(c, "jsonl", parser("jsonl", serde_json::json!({})), &jsonl);
+
+  let csv = lines(|i| {
+    let (ts, bt, et) = reading(i);
+    format!("{ts},{bt}.5,{et}.25,80,45,60")
+  });
+  run(c, "csv", parser("csv", serde_json::json!({})), &csv);
+
+  let columns = ["ts", "btC", "etC", "powerPct", "fanPct", "drumRpm", "roR"];
+  let csv_columns = serde_json::json!({ "csv": { "hasHeader": false, "columns": columns, "delimiter": "," } });
+  let csv_extras = lines(|i| {
+    let (ts, bt, et) = reading(i);
+    format!("{ts},{bt}.5,{et}.25,80,45,60,9.5")
+  });
+  run(c, "csv+extra", parser("csv", csv_columns), &csv_extras);
+
+  let key_value = lines(|i| {
+    let (_, bt, et) = reading(i);
+    format!("btC={bt}.5 etC={et}.25 powerPct=80 fanPct=45 drumRpm=60 roR=9.5")
+  });
+  run(c, "keyValue", parser("keyValue", serde_json::json!({})), &key_value);
+
+  let regex = serde_json::json!({ "regex": { "pattern": r"BT:(?P<btC>[-\d.]+) ET:(?P<etC>[-\d.]+)" } });
+  let regex_lines = lines(|i| {
+    let (_, bt, et) = reading(i);
+    format!("BT:{bt}.5 ET:{et}.25")
+  });
+  run(c, "regex", parser("regex", regex), &regex_lines);
+}
+
+criterion_group!(benches, parse_line);
+criterion_main!(benches);
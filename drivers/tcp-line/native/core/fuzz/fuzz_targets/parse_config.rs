@@ -0,0 +1,15 @@
+//! A config and device output together, split at the first NUL byte; configs that don't validate are skipped.
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use tcp_line_core::fuzz::parse_line_for_fuzzing;
+
+fuzz_target!(|data: &[u8]| {
+  let Some(split) = data.iter().position(|byte| *byte == 0) else {
+    return;
+  };
+  let Ok(config) = std::str::from_utf8(&data[..split]) else {
+    return;
+  };
+  let _ = parse_line_for_fuzzing(config, &data[split + 1..]);
+});
@@ -0,0 +1,31 @@
+//! Device output against a fixed config per format: the first byte picks the config, the rest is the lines.
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use tcp_line_core::fuzz::parse_line_for_fuzzing;
+
+const CONFIGS: &[&str] = &[
+  r#"{"format":"jsonl","csv":{"hasHeader":false,"columns":[],"delimiter":","},"offsets":{"btC":0,"etC":0}}"#,
+  r#"{"format":"csv","csv":{"hasHeader":true,"columns":[],"delimiter":";"},"offsets":{"btC":0,"etC":0}}"#,
+  r#"{"format":"csv","csv":{"hasHeader":false,"columns":["ts","btC","x"],"delimiter":"\\t","columnMismatch":"strict"},
+     "offsets":{"btC":0,"etC":0},"timestamp":{"format":"%Y-%m-%d %H:%M:%S","timezone":"Europe/Berlin"}}"#,
+  r#"{"format":"keyValue","csv":{"hasHeader":false,"columns":[],"delimiter":","},"offsets":{"btC":0,"etC":0},
+     "checksum":"nmea","timestamp":{"format":"epochMillis"}}"#,
+  r#"{"format":"autodetect","csv":{"hasHeader":false,"columns":[],"delimiter":","},"offsets":{"btC":0,"etC":0},
+     "timestamp":{"format":"%H:%M:%S","timezone":"+14:00"}}"#,
+  r#"{"format":"regex","regex":{"pattern":"(?P<ts>\\S+) (?P<btC>\\S*) ?(?P<note>.*)"},
+     "csv":{"hasHeader":false,"columns":[],"delimiter":","},"offsets":{"btC":0,"etC":0},"checksum":"crc16",
+     "timestamp":{"format":"epochSeconds"}}"#,
+  r#"{"format":"xml","xml":{"fields":{"btC":"/r/@bt","ts":"/r/ts","note":"/*/note"}},
+     "csv":{"hasHeader":false,"columns":[],"delimiter":","},"offsets":{"btC":0,"etC":0}}"#,
+  r#"{"format":"csv","csv":{"hasHeader":false,"columns":[],"delimiter":","},"offsets":{"btC":0,"etC":0},
+     "assembly":{"startMarker":"S","endMarker":"E","separator":",","maxLines":4},"extras":{"maxPerSample":1}}"#,
+];
+
+fuzz_target!(|data: &[u8]| {
+  let Some((pick, lines)) = data.split_first() else {
+    return;
+  };
+  let config = CONFIGS[*pick as usize % CONFIGS.len()];
+  parse_line_for_fuzzing(config, lines).expect("the fixed configs are valid");
+});
@@ -0,0 +1,98 @@
+use schemars::JsonSchema;
+use serde::Deserialize;
+
+#[derive(Debug, Clone, PartialEq, Deserialize, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct AssemblyConfig {
+  /// Line that opens a record; lines outside a record are dropped when `endMarker` is also set. Without
+  /// `endMarker`, the next start closes the previous record.
+  #[serde(default)]
+  pub start_marker: Option<String>,
+  /// Line that closes a record.
+  #[serde(default)]
+  pub end_marker: Option<String>,
+  /// Closes a record after this many lines.
+  #[serde(default)]
+  pub line_count: Option<u32>,
+  /// Joins a record's lines before parsing; use the CSV delimiter for CSV records.
+  #[serde(default = "default_separator")]
+  pub separator: String,
+  /// Lines buffered before an unterminated record is dropped as a parse error.
+  #[serde(default = "default_max_lines")]
+  pub max_lines: u32,
+}
+
+fn default_separator() -> String {
+  " ".to_string()
+}
+
+fn default_max_lines() -> u32 {
+  64
+}
+
+#[derive(Debug, PartialEq, Eq)]
+pub enum Assembled {
+  Pending,
+  Record(String),
+  Overflow,
+}
+
+/// Buffers lines into records per `AssemblyConfig`. Marker lines are matched after trimming and not part of the
+/// record.
+pub struct RecordAssembler {
+  config: AssemblyConfig,
+  lines: Vec<String>,
+  open: bool,
+}
+
+impl RecordAssembler {
+  pub fn new(config: AssemblyConfig) -> Self {
+    Self { config, lines: Vec::new(), open: false }
+  }
+
+  pub fn config(&self) -> &AssemblyConfig {
+    &self.config
+  }
+
+  /// Drops a partial record, e.g. after a reconnect.
+  pub fn reset(&mut self) {
+    self.lines.clear();
+    self.open = false;
+  }
+
+  pub fn push(&mut self, line: &str) -> Assembled {
+    let line = line.trim();
+    if self.config.start_marker.as_deref() == Some(line) {
+      let previous = if self.config.end_marker.is_none() { self.take() } else { Assembled::Pending };
+      self.lines.clear();
+      self.open = true;
+      return previous;
+    }
+    if self.config.end_marker.as_deref() == Some(line) {
+      if self.config.start_marker.is_some() && !self.open {
+        return Assembled::Pending;
+      }
+      return self.take();
+    }
+    if self.config.start_marker.is_some() && !self.open {
+      return Assembled::Pending;
+    }
+    self.lines.push(line.to_string());
+    if self.config.line_count.is_some_and(|count| self.lines.len() >= count as usize) {
+      return self.take();
+    }
+    if self.lines.len() > self.config.max_lines as usize {
+      self.reset();
+      return Assembled::Overflow;
+    }
+    Assembled::Pending
+  }
+
+  fn take(&mut self) -> Assembled {
+    self.open = false;
+    if self.lines.is_empty() {
+      return Assembled::Pending;
+    }
+    Assembled::Record(std::mem::take(&mut self.lines).join(&self.config.separator))
+  }
+}
@@ -0,0 +1,54 @@
+use schemars::JsonSchema;
+use serde::Deserialize;
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Deserialize, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub enum Checksum {
+  #[default]
+  None,
+  /// `$payload*hh`: XOR of the payload bytes as two hex digits. The leading `$` is optional and stripped too.
+  Nmea,
+  /// `payload*hhhh`: CRC-16/CCITT-FALSE of the payload as four hex digits.
+  Crc16,
+}
+
+#[derive(Debug, PartialEq, Eq)]
+pub enum ChecksumError {
+  Missing,
+  Mismatch,
+}
+
+/// Checks and strips the trailer, returning the payload to parse.
+pub fn verify(kind: Checksum, line: &str) -> Result<&str, ChecksumError> {
+  let digits = match kind {
+    Checksum::None => return Ok(line),
+    Checksum::Nmea => 2,
+    Checksum::Crc16 => 4,
+  };
+  let (payload, trailer) = line.rsplit_once('*').ok_or(ChecksumError::Missing)?;
+  if trailer.len() != digits {
+    return Err(ChecksumError::Missing);
+  }
+  let expected = u16::from_str_radix(trailer, 16).map_err(|_| ChecksumError::Missing)?;
+  let (payload, actual) = match kind {
+    Checksum::Nmea => {
+      let payload = payload.strip_prefix('$').unwrap_or(payload);
+      (payload, payload.bytes().fold(0u8, |acc, byte| acc ^ byte) as u16)
+    }
+    _ => (payload, crc16(payload.as_bytes())),
+  };
+  if actual != expected {
+    return Err(ChecksumError::Mismatch);
+  }
+  Ok(payload)
+}
+
+fn crc16(bytes: &[u8]) -> u16 {
+  bytes.iter().fold(0xFFFF, |mut crc: u16, byte| {
+    crc ^= (*byte as u16) << 8;
+    for _ in 0..8 {
+      crc = if crc & 0x8000 != 0 { (crc << 1) ^ 0x1021 } else { crc << 1 };
+    }
+    crc
+  })
+}
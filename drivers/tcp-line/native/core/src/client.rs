@@ -0,0 +1,382 @@
+//! A standalone tokio client over the parser, for the bindings that don't go through Node (Python, C). It covers one
+//! `host:port` endpoint with reconnects, newline framing and the latest sample; the Node binding has its own driver
+//! with sinks, listen mode and commands. Must run inside a tokio runtime.
+
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use chrono::{DateTime, SecondsFormat, Utc};
+use parking_lot::Mutex;
+use serde::Deserialize;
+use serde_json::Value;
+use tokio::io::{AsyncBufReadExt, AsyncReadExt, BufReader};
+use tokio::net::TcpStream;
+use tokio::sync::Notify;
+use tokio::task::JoinHandle;
+
+use crate::validation;
+use crate::{
+  Backoff, DriverMetrics, DriverState, ExtraEntry, ParseError, ParserConfig, RawTelemetrySample, ReconnectConfig,
+  TcpLineParser,
+};
+
+/// Longest line accepted; longer ones are discarded as parse errors, as the Node driver does by default.
+const MAX_LINE_BYTES: usize = 64 * 1024;
+/// How long `read_telemetry` waits for a first sample.
+const SAMPLE_TIMEOUT_MS: u64 = 500;
+
+/// The part of a driver config this binding acts on; the rest of a Node driver config (sinks, listen mode,
+/// commands, ...) is ignored, so the same JSON works for both.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DriverConfig {
+  pub host: String,
+  pub port: u16,
+  #[serde(flatten)]
+  pub parser: ParserConfig,
+  pub reconnect: ReconnectConfig,
+  /// Bound on each TCP connect attempt; `0` leaves it to the OS.
+  #[serde(default = "default_connect_timeout_ms")]
+  pub connect_timeout_ms: u64,
+}
+
+fn default_connect_timeout_ms() -> u64 {
+  5000
+}
+
+/// Parses and checks a config, naming the first offending field on failure.
+pub fn parse_config(config_json: &str) -> Result<DriverConfig, String> {
+  let value: Value = serde_json::from_str(config_json).map_err(|err| format!("invalid config: {}", err))?;
+  if let Some(issue) = validation::validate_parser_config(&value).first() {
+    return Err(format!("invalid config: {}", issue.describe()));
+  }
+  let config: DriverConfig =
+    validation::deserialize(value).map_err(|issue| format!("invalid config: {}", issue.describe()))?;
+  let reconnect = &config.reconnect;
+  if config.port == 0 {
+    return Err("invalid config: port: must be between 1 and 65535".to_string());
+  }
+  if reconnect.min_backoff_ms > reconnect.max_backoff_ms {
+    return Err("invalid config: reconnect.minBackoffMs: must not exceed reconnect.maxBackoffMs".to_string());
+  }
+  Ok(config)
+}
+
+/// A sample as `read_telemetry` returns it, with the channel names of the Node driver's `TelemetryPoint`.
+#[derive(Debug, Clone)]
+pub struct Point {
+  pub ts: String,
+  /// `ts` as Unix milliseconds.
+  pub ts_ms: i64,
+  pub machine_id: String,
+  pub elapsed_seconds: f64,
+  pub bt_c: Option<f64>,
+  pub et_c: Option<f64>,
+  pub gas_pct: Option<f64>,
+  pub fan_pct: Option<f64>,
+  pub drum_rpm: Option<f64>,
+  pub extras: Vec<ExtraEntry>,
+}
+
+/// What a listener set with `Driver::set_listener` is told about.
+pub enum DriverEvent<'a> {
+  State(DriverState),
+  Telemetry(&'a Point),
+}
+
+/// Called on the runtime's worker threads, so it should return quickly.
+pub type Listener = Arc<dyn Fn(DriverEvent<'_>) + Send + Sync>;
+
+/// A single-endpoint TCP driver: connects to `host:port`, parses every line with the core parser and keeps the
+/// latest sample, reconnecting with the core backoff. Sinks, listen mode and commands stay with the Node driver.
+pub struct Driver {
+  config: DriverConfig,
+  machine_id: String,
+  parser: Mutex<TcpLineParser>,
+  state: Mutex<DriverState>,
+  metrics: Mutex<DriverMetrics>,
+  latest_sample: Mutex<Option<RawTelemetrySample>>,
+  /// Timestamp of the first sample, the baseline of `elapsed_seconds`.
+  start_ts: Mutex<Option<DateTime<Utc>>>,
+  backoff: Mutex<Backoff>,
+  stop_flag: AtomicBool,
+  /// Signalled on every state change and accepted sample.
+  notify: Notify,
+  handle: Mutex<Option<JoinHandle<()>>>,
+  listener: Mutex<Option<Listener>>,
+}
+
+impl Driver {
+  pub fn new(config: DriverConfig, machine_id: String) -> Arc<Self> {
+    let mut backoff = Backoff::new(0, 0);
+    backoff.apply(&config.reconnect);
+    Arc::new(Self {
+      parser: Mutex::new(TcpLineParser::new(config.parser.clone())),
+      config,
+      machine_id,
+      state: Mutex::new(DriverState::DISCONNECTED),
+      metrics: Mutex::new(DriverMetrics::default()),
+      latest_sample: Mutex::new(None),
+      start_ts: Mutex::new(None),
+      backoff: Mutex::new(backoff),
+      stop_flag: AtomicBool::new(false),
+      notify: Notify::new(),
+      handle: Mutex::new(None),
+      listener: Mutex::new(None),
+    })
+  }
+
+  /// Starts the connection loop unless it is running, and waits for the connection. Without reconnects a failed
+  /// attempt is an error; with them, only an exhausted reconnect policy is.
+  pub async fn connect(self: &Arc<Self>) -> Result<(), String> {
+    {
+      let mut handle = self.handle.lock();
+      if handle.as_ref().is_none_or(JoinHandle::is_finished) {
+        self.stop_flag.store(false, Ordering::Relaxed);
+        self.backoff.lock().reset();
+        // Set before spawning so the wait below can't mistake the idle state for the attempt's outcome.
+        self.set_state(DriverState::CONNECTING);
+        *handle = Some(tokio::spawn(Arc::clone(self).run_loop()));
+      }
+    }
+    loop {
+      let notified = self.notify.notified();
+      tokio::pin!(notified);
+      notified.as_mut().enable();
+      match *self.state.lock() {
+        DriverState::CONNECTED | DriverState::PAUSED => return Ok(()),
+        DriverState::STOPPED => return Err("driver stopped".to_string()),
+        DriverState::FAILED => return Err(self.last_error("reconnect attempts exhausted")),
+        DriverState::DISCONNECTED if !self.config.reconnect.enabled => return Err(self.last_error("disconnected")),
+        _ => {}
+      }
+      notified.await;
+    }
+  }
+
+  pub async fn disconnect(&self) {
+    self.stop_flag.store(true, Ordering::Relaxed);
+    let handle = self.handle.lock().take();
+    if let Some(handle) = handle {
+      handle.abort();
+      let _ = handle.await;
+    }
+    self.set_state(DriverState::STOPPED);
+  }
+
+  /// The latest sample, waiting briefly for the first one.
+  pub async fn read_telemetry(&self) -> Result<Point, String> {
+    let deadline = tokio::time::Instant::now() + Duration::from_millis(SAMPLE_TIMEOUT_MS);
+    loop {
+      let notified = self.notify.notified();
+      if let Some(point) = self.latest() {
+        return Ok(point);
+      }
+      if self.stop_flag.load(Ordering::Relaxed) {
+        return Err("driver stopped".to_string());
+      }
+      if tokio::time::timeout_at(deadline, notified).await.is_err() {
+        return Err("no telemetry yet".to_string());
+      }
+    }
+  }
+
+  /// The latest sample without waiting; `None` before the first one.
+  pub fn latest(&self) -> Option<Point> {
+    let sample = self.latest_sample.lock().clone()?;
+    self.count_emitted();
+    Some(self.point(sample))
+  }
+
+  /// Replaces the listener told about state changes and every accepted sample, which then counts as emitted; `None`
+  /// removes it.
+  pub fn set_listener(&self, listener: Option<Listener>) {
+    *self.listener.lock() = listener;
+  }
+
+  pub fn status(&self) -> (DriverState, DriverMetrics) {
+    let state = *self.state.lock();
+    (state, self.metrics.lock().clone())
+  }
+
+  fn point(&self, sample: RawTelemetrySample) -> Point {
+    let base = *self.start_ts.lock().get_or_insert(sample.ts);
+    let elapsed_ms = sample.ts.signed_duration_since(base).num_milliseconds().max(0) as f64;
+    Point {
+      ts: sample.ts.to_rfc3339_opts(SecondsFormat::Millis, true),
+      ts_ms: sample.ts.timestamp_millis(),
+      machine_id: self.machine_id.clone(),
+      elapsed_seconds: elapsed_ms / 1000.0,
+      bt_c: sample.bt_c,
+      et_c: sample.et_c,
+      gas_pct: sample.power_pct,
+      fan_pct: sample.fan_pct,
+      drum_rpm: sample.drum_rpm,
+      extras: sample.extras.unwrap_or_default(),
+    }
+  }
+
+  fn set_state(&self, state: DriverState) {
+    let changed = std::mem::replace(&mut *self.state.lock(), state) != state;
+    self.notify.notify_waiters();
+    if changed {
+      self.tell(DriverEvent::State(state));
+    }
+  }
+
+  fn tell(&self, event: DriverEvent<'_>) {
+    // Cloned out so the listener may call back into the driver.
+    let listener = self.listener.lock().clone();
+    if let Some(listener) = listener {
+      listener(event);
+    }
+  }
+
+  fn count_emitted(&self) {
+    let mut metrics = self.metrics.lock();
+    metrics.telemetryEmitted = metrics.telemetryEmitted.saturating_add(1);
+  }
+
+  fn last_error(&self, fallback: &str) -> String {
+    self.metrics.lock().lastError.clone().unwrap_or_else(|| fallback.to_string())
+  }
+
+  async fn run_loop(self: Arc<Self>) {
+    let mut attempts: u32 = 0;
+    let mut outage_started: Option<Instant> = None;
+    let mut exhausted = false;
+    loop {
+      self.set_state(DriverState::CONNECTING);
+      match self.open().await {
+        Ok(stream) => {
+          attempts = 0;
+          outage_started = None;
+          self.backoff.lock().reset();
+          self.set_state(DriverState::CONNECTED);
+          self.read_lines(stream).await;
+          // A CSV header or partial record belongs to the connection that sent it.
+          self.parser.lock().reset();
+        }
+        Err(message) => self.metrics.lock().lastError = Some(message),
+      }
+      if self.stop_flag.load(Ordering::Relaxed) || !self.config.reconnect.enabled {
+        break;
+      }
+      attempts = attempts.saturating_add(1);
+      let outage = outage_started.get_or_insert_with(Instant::now).elapsed();
+      if self.config.reconnect.exhausted(attempts, outage) {
+        exhausted = true;
+        break;
+      }
+      {
+        let mut metrics = self.metrics.lock();
+        metrics.reconnects = metrics.reconnects.saturating_add(1);
+      }
+      self.set_state(DriverState::DISCONNECTED);
+      let delay = { self.backoff.lock().next_delay() };
+      tokio::time::sleep(Duration::from_millis(delay)).await;
+    }
+    self.set_state(if self.stop_flag.load(Ordering::Relaxed) {
+      DriverState::STOPPED
+    } else if exhausted {
+      DriverState::FAILED
+    } else {
+      DriverState::DISCONNECTED
+    });
+  }
+
+  async fn open(&self) -> Result<TcpStream, String> {
+    let endpoint = format!("{}:{}", self.config.host, self.config.port);
+    let connect = TcpStream::connect((self.config.host.as_str(), self.config.port));
+    let connected = match self.config.connect_timeout_ms {
+      0 => connect.await,
+      timeout_ms => match tokio::time::timeout(Duration::from_millis(timeout_ms), connect).await {
+        Ok(connected) => connected,
+        Err(_) => return Err(format!("connection failure: {}: timed out after {} ms", endpoint, timeout_ms)),
+      },
+    };
+    connected.map_err(|err| format!("connection failure: {}: {}", endpoint, err))
+  }
+
+  async fn read_lines(&self, stream: TcpStream) {
+    let mut reader = BufReader::new(stream);
+    let mut frame = Vec::new();
+    loop {
+      frame.clear();
+      match (&mut reader).take(MAX_LINE_BYTES as u64 + 1).read_until(b'\n', &mut frame).await {
+        Ok(0) => {
+          self.metrics.lock().lastError = Some("socket closed".to_string());
+          return;
+        }
+        Ok(_) => {}
+        Err(err) => {
+          self.metrics.lock().lastError = Some(format!("read failure: {}", err));
+          return;
+        }
+      }
+      {
+        let mut metrics = self.metrics.lock();
+        metrics.linesReceived = metrics.linesReceived.saturating_add(1);
+        metrics.bytesReceived = metrics.bytesReceived.saturating_add(frame.len() as i64);
+      }
+      if frame.len() > MAX_LINE_BYTES {
+        // Skip the rest of the oversized line before counting it.
+        if !frame.ends_with(b"\n") {
+          let mut rest = Vec::new();
+          let _ = reader.read_until(b'\n', &mut rest).await;
+        }
+        self.parse_error(&ParseError::InvalidFrame("line exceeds maxLineBytes"));
+        continue;
+      }
+      let line = String::from_utf8_lossy(&frame);
+      let (parsed, batch) = {
+        let mut parser = self.parser.lock();
+        let parsed = parser.parse_line(line.trim_end());
+        let field_errors = parser.field_errors().len() as i64;
+        if field_errors > 0 {
+          let mut metrics = self.metrics.lock();
+          metrics.fieldErrors = metrics.fieldErrors.saturating_add(field_errors);
+        }
+        (parsed, parser.take_batch())
+      };
+      if let (Ok(first), Some(batch)) = (&parsed, &batch) {
+        let mut metrics = self.metrics.lock();
+        metrics.batchLines = metrics.batchLines.saturating_add(1);
+        metrics.batchSamples = metrics.batchSamples.saturating_add((first.is_some() as usize + batch.len()) as i64);
+      }
+      match parsed {
+        Ok(Some(sample)) => {
+          for sample in std::iter::once(sample).chain(batch.into_iter().flatten()) {
+            self.accept(sample);
+          }
+        }
+        Ok(None) => {}
+        Err(err) => self.parse_error(&err),
+      }
+    }
+  }
+
+  fn accept(&self, sample: RawTelemetrySample) {
+    {
+      let mut metrics = self.metrics.lock();
+      metrics.linesParsed = metrics.linesParsed.saturating_add(1);
+      metrics.lastLineAt = Some(sample.ts.to_rfc3339_opts(SecondsFormat::Millis, true));
+    }
+    *self.latest_sample.lock() = Some(sample.clone());
+    self.notify.notify_waiters();
+    if self.listener.lock().is_some() {
+      self.count_emitted();
+      self.tell(DriverEvent::Telemetry(&self.point(sample)));
+    }
+  }
+
+  fn parse_error(&self, err: &ParseError) {
+    let mut metrics = self.metrics.lock();
+    metrics.parseErrors = metrics.parseErrors.saturating_add(1);
+    if matches!(err, ParseError::MissingChecksum | ParseError::ChecksumMismatch) {
+      metrics.checksumErrors = metrics.checksumErrors.saturating_add(1);
+    }
+    metrics.lastError = Some(err.to_string());
+  }
+}
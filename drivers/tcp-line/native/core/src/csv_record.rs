@@ -0,0 +1,75 @@
+use std::borrow::Cow;
+
+use schemars::JsonSchema;
+use serde::Deserialize;
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Deserialize, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub enum ColumnMismatch {
+  /// Extra fields are ignored and missing ones left unset.
+  #[default]
+  Lenient,
+  /// A row whose field count differs from the columns is a parse error.
+  Strict,
+}
+
+#[derive(Debug, PartialEq, Eq)]
+pub struct UnterminatedQuote;
+
+/// The delimiter to split on; a literal `\t` (as typed into an env var) means a tab.
+pub fn delimiter(configured: &str) -> Cow<'_, str> {
+  if configured.contains("\\t") {
+    Cow::Owned(configured.replace("\\t", "\t"))
+  } else {
+    Cow::Borrowed(configured)
+  }
+}
+
+/// Splits one CSV record (RFC 4180): a field starting with `"` runs to the matching closing quote, may contain the
+/// delimiter, and reads `""` as a literal quote. Unquoted fields are trimmed; quoted ones keep their inner whitespace.
+pub fn split_record(line: &str, delimiter: &str) -> Result<Vec<String>, UnterminatedQuote> {
+  Ok(split_fields(line, delimiter)?.into_iter().map(Cow::into_owned).collect())
+}
+
+/// `split_record`, borrowing the unquoted fields from `line`.
+pub fn split_fields<'a>(line: &'a str, delimiter: &str) -> Result<Vec<Cow<'a, str>>, UnterminatedQuote> {
+  let mut fields = Vec::new();
+  let mut rest = line;
+  loop {
+    let mut field = None::<String>;
+    let quoted = rest.trim_start().starts_with('"');
+    if quoted {
+      let field = field.insert(String::new());
+      let mut body = &rest.trim_start()[1..];
+      loop {
+        let end = body.find('"').ok_or(UnterminatedQuote)?;
+        field.push_str(&body[..end]);
+        body = &body[end + 1..];
+        match body.strip_prefix('"') {
+          Some(after) => {
+            field.push('"');
+            body = after;
+          }
+          None => break,
+        }
+      }
+      rest = body;
+    }
+    let (segment, next) = match rest.find(delimiter).filter(|_| !delimiter.is_empty()) {
+      Some(idx) => (&rest[..idx], Some(&rest[idx + delimiter.len()..])),
+      None => (rest, None),
+    };
+    // Anything between a closing quote and the delimiter is kept rather than rejected.
+    fields.push(match field {
+      Some(mut field) => {
+        field.push_str(segment.trim());
+        Cow::Owned(field)
+      }
+      None => Cow::Borrowed(segment.trim()),
+    });
+    match next {
+      Some(next) => rest = next,
+      None => return Ok(fields),
+    }
+  }
+}
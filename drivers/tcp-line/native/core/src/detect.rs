@@ -0,0 +1,120 @@
+use crate::{csv_record, key_value};
+
+/// Delimiters tried when sniffing CSV, in order of preference on a tie.
+const CSV_DELIMITERS: &[&str] = &[",", ";", "\t", "|"];
+
+/// What a handful of lines from a device look like.
+#[derive(Debug, Clone, PartialEq)]
+pub enum DetectedFormat {
+  Jsonl,
+  Csv { delimiter: String, has_header: bool },
+  KeyValue,
+}
+
+/// The format `autodetect` settled on, as reported by `getStatus().detectedFormat`.
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "napi", napi_derive::napi(object))]
+pub struct FormatDetection {
+  /// `jsonl`, `csv` or `keyValue`.
+  pub format: String,
+  /// CSV delimiter.
+  pub delimiter: Option<String>,
+  /// The first CSV line of a connection is a header.
+  pub hasHeader: Option<bool>,
+}
+
+impl DetectedFormat {
+  pub fn name(&self) -> &'static str {
+    match self {
+      Self::Jsonl => "jsonl",
+      Self::Csv { .. } => "csv",
+      Self::KeyValue => "keyValue",
+    }
+  }
+
+  pub fn status(&self) -> FormatDetection {
+    let (delimiter, has_header) = match self {
+      Self::Csv { delimiter, has_header } => (Some(delimiter.clone()), Some(*has_header)),
+      _ => (None, None),
+    };
+    FormatDetection { format: self.name().to_string(), delimiter, hasHeader: has_header }
+  }
+}
+
+/// Guesses the format of `lines`, ignoring blank ones: JSON objects (or arrays of them) are JSONL and lines made only
+/// of `key=value` pairs are key=value. Otherwise the delimiter that splits every line into the same number (at least
+/// two) of fields, the most fields winning, makes CSV. A first row without numbers ahead of rows with them is a
+/// header. `None` when nothing fits.
+pub fn detect(lines: &[String]) -> Option<DetectedFormat> {
+  let lines: Vec<&str> = lines.iter().map(|line| line.trim()).filter(|line| !line.is_empty()).collect();
+  let first = lines.first()?;
+  if first.starts_with(['{', '[']) {
+    let objects = lines.iter().all(|line| serde_json::from_str::<serde_json::Value>(line).is_ok_and(|v| records(&v)));
+    return objects.then_some(DetectedFormat::Jsonl);
+  }
+  if lines.iter().all(|line| key_value::split_pairs(line).is_some()) {
+    return Some(DetectedFormat::KeyValue);
+  }
+
+  let mut best: Option<(&str, Vec<Vec<String>>)> = None;
+  for delimiter in CSV_DELIMITERS {
+    let rows: Result<Vec<_>, _> = lines.iter().map(|line| csv_record::split_record(line, delimiter)).collect();
+    let Ok(rows) = rows else {
+      continue;
+    };
+    let width = rows[0].len();
+    if width < 2 || rows.iter().any(|row| row.len() != width) {
+      continue;
+    }
+    if best.as_ref().is_none_or(|(_, best_rows)| width > best_rows[0].len()) {
+      best = Some((delimiter, rows));
+    }
+  }
+  let (delimiter, rows) = best?;
+  let numeric = |row: &Vec<String>| row.iter().any(|field| field.parse::<f64>().is_ok());
+  let has_header = !numeric(&rows[0]) && (rows.len() == 1 || rows[1..].iter().any(numeric));
+  Some(DetectedFormat::Csv { delimiter: delimiter.to_string(), has_header })
+}
+
+/// A JSON object, or a non-empty array of them.
+fn records(value: &serde_json::Value) -> bool {
+  match value {
+    serde_json::Value::Array(elements) => !elements.is_empty() && elements.iter().all(serde_json::Value::is_object),
+    value => value.is_object(),
+  }
+}
+
+/// Field names in `lines` for the detected format: every JSON or key=value key seen, or the CSV header.
+pub fn columns(format: &DetectedFormat, lines: &[String]) -> Vec<String> {
+  let mut columns: Vec<String> = Vec::new();
+  match format {
+    DetectedFormat::Jsonl => {
+      for line in lines {
+        let objects = match serde_json::from_str::<serde_json::Value>(line.trim()) {
+          Ok(serde_json::Value::Object(map)) => vec![map],
+          Ok(serde_json::Value::Array(elements)) => elements.iter().filter_map(|e| e.as_object().cloned()).collect(),
+          _ => continue,
+        };
+        for key in objects.iter().flat_map(|map| map.keys()) {
+          if !columns.contains(key) {
+            columns.push(key.clone());
+          }
+        }
+      }
+    }
+    DetectedFormat::Csv { delimiter, has_header: true } => {
+      if let Some(header) = lines.iter().find(|line| !line.trim().is_empty()) {
+        columns = csv_record::split_record(header, delimiter).unwrap_or_default();
+      }
+    }
+    DetectedFormat::KeyValue => {
+      for (key, _) in lines.iter().filter_map(|line| key_value::split_pairs(line)).flatten() {
+        if !columns.contains(&key) {
+          columns.push(key);
+        }
+      }
+    }
+    DetectedFormat::Csv { has_header: false, .. } => {}
+  }
+  columns
+}
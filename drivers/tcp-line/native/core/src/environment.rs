@@ -0,0 +1,67 @@
+use schemars::JsonSchema;
+use serde::Deserialize;
+
+/// The unit a device sends `ambientC` in.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Deserialize, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub enum TemperatureUnit {
+  /// Degrees Celsius.
+  #[default]
+  C,
+  /// Degrees Fahrenheit.
+  F,
+  /// Kelvin.
+  K,
+}
+
+/// The unit a device sends `pressureHpa` in.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Deserialize, JsonSchema)]
+pub enum PressureUnit {
+  /// Hectopascal, the same as millibar.
+  #[default]
+  #[serde(rename = "hPa")]
+  HPa,
+  #[serde(rename = "kPa")]
+  KPa,
+  #[serde(rename = "Pa")]
+  Pa,
+  /// Inches of mercury, as US weather stations report.
+  #[serde(rename = "inHg")]
+  InHg,
+  #[serde(rename = "mmHg")]
+  MmHg,
+  #[serde(rename = "psi")]
+  Psi,
+}
+
+/// How the ambient channels (`ambientC`, `humidityPct`, `pressureHpa`) of a roastery weather station are read. They
+/// are reported in °C, % and hPa whatever the device sends; `offsets` applies after the conversion.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Deserialize, JsonSchema)]
+#[serde(rename_all = "camelCase", default)]
+pub struct EnvironmentConfig {
+  pub temperature_unit: TemperatureUnit,
+  pub pressure_unit: PressureUnit,
+}
+
+impl EnvironmentConfig {
+  /// `value`, in `temperatureUnit`, in °C.
+  pub fn temperature(&self, value: f64) -> f64 {
+    match self.temperature_unit {
+      TemperatureUnit::C => value,
+      TemperatureUnit::F => (value - 32.0) * 5.0 / 9.0,
+      TemperatureUnit::K => value - 273.15,
+    }
+  }
+
+  /// `value`, in `pressureUnit`, in hPa.
+  pub fn pressure(&self, value: f64) -> f64 {
+    match self.pressure_unit {
+      PressureUnit::HPa => value,
+      PressureUnit::KPa => value * 10.0,
+      PressureUnit::Pa => value / 100.0,
+      PressureUnit::InHg => value * 33.863_886,
+      PressureUnit::MmHg => value * 1.333_224,
+      PressureUnit::Psi => value * 68.947_573,
+    }
+  }
+}
@@ -0,0 +1,271 @@
+//! The fields of one record as the parser reads them, borrowing their keys and text from the line where it can: a
+//! JSON object is read straight into `(key, Field)` pairs rather than a `serde_json::Value` tree, and CSV cells, regex
+//! captures and `key=value` pairs point into the line. Only what a sample keeps (extra names and text) is copied.
+use std::borrow::Cow;
+use std::fmt;
+
+use serde::de::{Deserialize, DeserializeSeed, Deserializer, IgnoredAny, MapAccess, SeqAccess, Visitor};
+
+/// A record's fields, in the order the sample is built from them.
+pub type Fields<'a> = Vec<(Cow<'a, str>, Field<'a>)>;
+
+/// One field value: a JSON value, a CSV cell, a capture group.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Field<'a> {
+  Null,
+  Number(f64),
+  Text(Cow<'a, str>),
+  /// Never a reading, but kept as a boolean extra.
+  Bool(bool),
+  /// An array or object, which is never a reading.
+  Other,
+}
+
+impl Field<'_> {
+  pub fn is_null(&self) -> bool {
+    matches!(self, Field::Null)
+  }
+
+  pub fn as_str(&self) -> Option<&str> {
+    match self {
+      Field::Text(text) => Some(text),
+      _ => None,
+    }
+  }
+
+  /// A number, or non-empty text that parses as one.
+  pub fn number(&self) -> Option<f64> {
+    match self {
+      Field::Number(number) => Some(*number),
+      Field::Text(text) if !text.is_empty() => text.parse::<f64>().ok(),
+      _ => None,
+    }
+  }
+}
+
+impl<'a> From<&'a serde_json::Value> for Field<'a> {
+  fn from(value: &'a serde_json::Value) -> Self {
+    match value {
+      serde_json::Value::Null => Field::Null,
+      serde_json::Value::Number(number) => number.as_f64().map_or(Field::Other, Field::Number),
+      serde_json::Value::String(text) => Field::Text(Cow::Borrowed(text)),
+      serde_json::Value::Bool(value) => Field::Bool(*value),
+      _ => Field::Other,
+    }
+  }
+}
+
+/// The fields of a JSON object line, sorted by key with the last of a repeated key winning, as a parsed
+/// `serde_json::Map` would have them. Objects nested up to `max_depth` levels deep are read as fields under their
+/// path, the keys joined by `separator` (`temps.bt`); deeper ones are `Other`. `None` if the line isn't a JSON object.
+pub fn json_object<'a>(line: &'a str, separator: &str, max_depth: u32) -> Option<Fields<'a>> {
+  let mut deserializer = serde_json::Deserializer::from_str(line);
+  let fields = Record { separator, depth: max_depth }.deserialize(&mut deserializer).ok()?;
+  deserializer.end().ok()?;
+  Some(fields)
+}
+
+/// The fields of each object of a JSON array line, read like `json_object`. `None` if the line isn't an array of
+/// objects.
+pub fn json_array<'a>(line: &'a str, separator: &str, max_depth: u32) -> Option<Vec<Fields<'a>>> {
+  let mut deserializer = serde_json::Deserializer::from_str(line);
+  let records = (&mut deserializer).deserialize_seq(Records { separator, depth: max_depth }).ok()?;
+  deserializer.end().ok()?;
+  Some(records)
+}
+
+/// One object, as sorted fields.
+struct Record<'s> {
+  separator: &'s str,
+  depth: u32,
+}
+
+impl<'de> DeserializeSeed<'de> for Record<'_> {
+  type Value = Fields<'de>;
+
+  fn deserialize<D: Deserializer<'de>>(self, deserializer: D) -> Result<Fields<'de>, D::Error> {
+    let mut fields = Vec::new();
+    deserializer.deserialize_map(Object { separator: self.separator, depth: self.depth, fields: &mut fields })?;
+    fields.sort_unstable_by(|(a, _), (b, _)| a.cmp(b));
+    Ok(fields)
+  }
+}
+
+struct Records<'s> {
+  separator: &'s str,
+  depth: u32,
+}
+
+impl<'de> Visitor<'de> for Records<'_> {
+  type Value = Vec<Fields<'de>>;
+
+  fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+    formatter.write_str("an array of JSON objects")
+  }
+
+  fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> Result<Self::Value, A::Error> {
+    let mut records = Vec::with_capacity(seq.size_hint().unwrap_or(4));
+    while let Some(fields) = seq.next_element_seed(Record { separator: self.separator, depth: self.depth })? {
+      records.push(fields);
+    }
+    Ok(records)
+  }
+}
+
+/// Sets `key`, replacing an earlier value of it.
+fn push<'a>(fields: &mut Fields<'a>, key: Cow<'a, str>, value: Field<'a>) {
+  match fields.iter_mut().find(|(existing, _)| *existing == key) {
+    Some(field) => field.1 = value,
+    None => fields.push((key, value)),
+  }
+}
+
+/// An object's entries, read into `fields`.
+struct Object<'s, 'de> {
+  separator: &'s str,
+  depth: u32,
+  fields: &'s mut Fields<'de>,
+}
+
+impl<'de> Visitor<'de> for Object<'_, 'de> {
+  type Value = ();
+
+  fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+    formatter.write_str("a JSON object")
+  }
+
+  fn visit_map<A: MapAccess<'de>>(self, mut map: A) -> Result<(), A::Error> {
+    let fields = self.fields;
+    fields.reserve(map.size_hint().unwrap_or(8));
+    while let Some(Key(key)) = map.next_key()? {
+      map.next_value_seed(Entry { path: key, separator: self.separator, depth: self.depth, fields: &mut *fields })?;
+    }
+    Ok(())
+  }
+}
+
+/// The value at `path`, which sets that field or, for an object with `depth` levels left, the fields below it.
+struct Entry<'s, 'de> {
+  path: Cow<'de, str>,
+  separator: &'s str,
+  depth: u32,
+  fields: &'s mut Fields<'de>,
+}
+
+impl<'de> DeserializeSeed<'de> for Entry<'_, 'de> {
+  type Value = ();
+
+  fn deserialize<D: Deserializer<'de>>(self, deserializer: D) -> Result<(), D::Error> {
+    deserializer.deserialize_any(self)
+  }
+}
+
+impl<'de> Entry<'_, 'de> {
+  fn set(self, value: Field<'de>) {
+    push(self.fields, self.path, value);
+  }
+}
+
+impl<'de> Visitor<'de> for Entry<'_, 'de> {
+  type Value = ();
+
+  fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+    formatter.write_str("a JSON value")
+  }
+
+  fn visit_unit<E>(self) -> Result<(), E> {
+    self.set(Field::Null);
+    Ok(())
+  }
+
+  fn visit_none<E>(self) -> Result<(), E> {
+    self.set(Field::Null);
+    Ok(())
+  }
+
+  fn visit_bool<E>(self, value: bool) -> Result<(), E> {
+    self.set(Field::Bool(value));
+    Ok(())
+  }
+
+  fn visit_i64<E>(self, value: i64) -> Result<(), E> {
+    self.set(Field::Number(value as f64));
+    Ok(())
+  }
+
+  fn visit_u64<E>(self, value: u64) -> Result<(), E> {
+    self.set(Field::Number(value as f64));
+    Ok(())
+  }
+
+  fn visit_f64<E>(self, value: f64) -> Result<(), E> {
+    self.set(Field::Number(value));
+    Ok(())
+  }
+
+  fn visit_borrowed_str<E>(self, value: &'de str) -> Result<(), E> {
+    self.set(Field::Text(Cow::Borrowed(value)));
+    Ok(())
+  }
+
+  fn visit_str<E>(self, value: &str) -> Result<(), E> {
+    self.set(Field::Text(Cow::Owned(value.to_string())));
+    Ok(())
+  }
+
+  fn visit_string<E>(self, value: String) -> Result<(), E> {
+    self.set(Field::Text(Cow::Owned(value)));
+    Ok(())
+  }
+
+  fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> Result<(), A::Error> {
+    while seq.next_element::<IgnoredAny>()?.is_some() {}
+    self.set(Field::Other);
+    Ok(())
+  }
+
+  fn visit_map<A: MapAccess<'de>>(self, mut map: A) -> Result<(), A::Error> {
+    if self.depth == 0 {
+      while map.next_entry::<IgnoredAny, IgnoredAny>()?.is_some() {}
+      self.set(Field::Other);
+      return Ok(());
+    }
+    let fields = self.fields;
+    while let Some(Key(key)) = map.next_key()? {
+      let path = Cow::Owned(format!("{}{}{}", self.path, self.separator, key));
+      map.next_value_seed(Entry { path, separator: self.separator, depth: self.depth - 1, fields: &mut *fields })?;
+    }
+    Ok(())
+  }
+}
+
+/// An object key, borrowed unless it has escapes.
+struct Key<'a>(Cow<'a, str>);
+
+impl<'de> Deserialize<'de> for Key<'de> {
+  fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+    deserializer.deserialize_str(TextVisitor).map(Key)
+  }
+}
+
+struct TextVisitor;
+
+impl<'de> Visitor<'de> for TextVisitor {
+  type Value = Cow<'de, str>;
+
+  fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+    formatter.write_str("a string")
+  }
+
+  fn visit_borrowed_str<E>(self, value: &'de str) -> Result<Self::Value, E> {
+    Ok(Cow::Borrowed(value))
+  }
+
+  fn visit_str<E>(self, value: &str) -> Result<Self::Value, E> {
+    Ok(Cow::Owned(value.to_string()))
+  }
+
+  fn visit_string<E>(self, value: String) -> Result<Self::Value, E> {
+    Ok(Cow::Owned(value))
+  }
+}
@@ -0,0 +1,42 @@
+//! The parser's fuzzing entry point, shared by the cargo-fuzz targets in `core/fuzz` and the Node binding's
+//! `parseLineForFuzzing`. Any input may be rejected, but none may panic: a panic here is a parser bug.
+use serde_json::Value;
+
+use crate::parser::{ParserConfig, TcpLineParser};
+use crate::validation::{self, ConfigIssue};
+
+/// What the lines of one fuzz input parsed into.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "napi", napi_derive::napi(object))]
+pub struct FuzzOutcome {
+  pub samples: u32,
+  /// Lines that completed no sample, e.g. a CSV header or part of a record.
+  pub skipped: u32,
+  pub errors: u32,
+}
+
+/// Checks `config_json` like `validate_parser_config` (a parser or a full driver config), then splits `bytes` at
+/// `\n` as newline framing does, decodes each line lossily and runs them all through one parser. `Err` is the first
+/// issue of the config.
+pub fn parse_line_for_fuzzing(config_json: &str, bytes: &[u8]) -> Result<FuzzOutcome, ConfigIssue> {
+  let value: Value =
+    serde_json::from_str(config_json).map_err(|err| ConfigIssue::new("", format!("invalid json: {}", err)))?;
+  if let Some(issue) = validation::validate_parser_config(&value).into_iter().next() {
+    return Err(issue);
+  }
+  let mut parser = TcpLineParser::new(validation::deserialize::<ParserConfig>(value)?);
+  Ok(parse_lines(&mut parser, bytes))
+}
+
+/// Runs the `\n`-separated lines of `bytes` through `parser`, for fuzz targets with a prebuilt parser.
+pub fn parse_lines(parser: &mut TcpLineParser, bytes: &[u8]) -> FuzzOutcome {
+  let mut outcome = FuzzOutcome::default();
+  for line in bytes.split(|byte| *byte == b'\n') {
+    match parser.parse_line(&String::from_utf8_lossy(line)) {
+      Ok(Some(_)) => outcome.samples += 1,
+      Ok(None) => outcome.skipped += 1,
+      Err(_) => outcome.errors += 1,
+    }
+  }
+  outcome
+}
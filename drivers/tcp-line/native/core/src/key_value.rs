@@ -0,0 +1,20 @@
+/// Splits a `key=value` line, e.g. `BT=196.4 ET=214.9` or `bt=196.4,et=214.9;fan=45`, into its pairs. Pairs are
+/// separated by whitespace, `,` or `;`; keys and values are trimmed. `None` when a pair has no `=` or an empty key.
+pub fn split_pairs(line: &str) -> Option<Vec<(String, String)>> {
+  let pairs = pairs(line)?;
+  Some(pairs.into_iter().map(|(key, value)| (key.to_string(), value.to_string())).collect())
+}
+
+/// `split_pairs`, borrowing the keys and values from `line`.
+pub fn pairs(line: &str) -> Option<Vec<(&str, &str)>> {
+  let pairs: Option<Vec<_>> = line
+    .split(|c: char| c.is_whitespace() || c == ',' || c == ';')
+    .filter(|pair| !pair.is_empty())
+    .map(|pair| {
+      let (key, value) = pair.split_once('=')?;
+      let key = key.trim();
+      (!key.is_empty()).then(|| (key, value.trim()))
+    })
+    .collect();
+  pairs.filter(|pairs| !pairs.is_empty())
+}
@@ -0,0 +1,43 @@
+//! The transport-free half of the tcp-line driver: line parsing (JSONL, CSV, regex, XML, key=value, autodetect),
+//! checksums, record assembly, timestamps, config validation, the reconnect backoff, and the state and metrics types
+//! the driver reports. The Node binding (`tcp_line_native`) wraps it with sockets, sinks and napi; other Rust services
+//! can use it directly. The `napi` feature adds the napi conversions for `DriverState`, `DriverMetrics`,
+//! `ExtraEntry`, `FormatDetection` and `ConfigIssue` so the binding can return them unchanged. The `client` feature
+//! adds a standalone tokio client for the Python and C bindings.
+#![allow(non_snake_case)]
+
+pub mod assembly;
+pub mod checksum;
+#[cfg(feature = "client")]
+pub mod client;
+pub mod csv_record;
+pub mod detect;
+pub mod environment;
+pub mod field;
+pub mod fuzz;
+pub mod key_value;
+pub mod metrics;
+pub mod parser;
+pub mod probes;
+pub mod quality;
+pub mod reconnect;
+pub mod registry;
+pub mod rng;
+pub mod state;
+pub mod throughput;
+pub mod timestamp;
+pub mod validation;
+pub mod xml;
+
+pub use crate::environment::{EnvironmentConfig, PressureUnit, TemperatureUnit};
+pub use crate::metrics::DriverMetrics;
+pub use crate::parser::{
+  channel_key, parse_timestamp, CsvConfig, EnumValue, ExtraEntry, ExtrasConfig, FieldError, FlattenConfig, FrameFormat,
+  JsonConfig, Offsets, ParseError, ParserConfig, RawTelemetrySample, RegexConfig, ReservedPolicy, Strictness,
+  TcpLineParser, CHANNELS,
+};
+pub use crate::probes::{ProbeChannel, ProbeCombine, ProbeConfig, ProbeReading, ProbesConfig};
+pub use crate::quality::Quality;
+pub use crate::reconnect::{Backoff, Jitter, ReconnectConfig};
+pub use crate::registry::{ChannelRegistry, RegisteredChannel, REGISTERED_CHANNELS};
+pub use crate::state::DriverState;
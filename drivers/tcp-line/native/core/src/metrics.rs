@@ -0,0 +1,46 @@
+#[derive(Debug, Clone, Default, serde::Serialize)]
+#[cfg_attr(feature = "napi", napi_derive::napi(object))]
+pub struct DriverMetrics {
+  pub linesReceived: i64,
+  pub linesParsed: i64,
+  pub parseErrors: i64,
+  /// Lines rejected for a missing or wrong checksum; also counted in `parseErrors`.
+  pub checksumErrors: i64,
+  /// Channel fields that weren't numbers, left out of their samples under `strictness: warn`.
+  pub fieldErrors: i64,
+  /// Lines holding a JSON array of readings.
+  pub batchLines: i64,
+  /// Samples those lines completed, one per object.
+  pub batchSamples: i64,
+  /// Jumps of the `sequence` counter past the next value.
+  pub sequenceGaps: i64,
+  /// Frames those jumps skipped.
+  pub sequenceMissing: i64,
+  /// Frames repeating the counter of the frame before.
+  pub sequenceDuplicates: i64,
+  /// Frames arriving after one with a higher counter.
+  pub sequenceReordered: i64,
+  /// Samples timestamped before the latest one, whatever `ordering` did with them.
+  pub outOfOrderSamples: i64,
+  pub telemetryEmitted: i64,
+  pub reconnects: i64,
+  pub commandsSent: i64,
+  pub staleTimeouts: i64,
+  /// Samples discarded because the `queue`, `spool`, `influx` or `kafka` buffer was full.
+  pub droppedSamples: i64,
+  /// Points acknowledged by InfluxDB.
+  pub influxPointsWritten: i64,
+  /// Influx write requests that were rejected or exhausted their retries; each drops its batch.
+  pub influxWriteErrors: i64,
+  /// Messages acknowledged by the Kafka brokers.
+  pub kafkaMessagesDelivered: i64,
+  /// Messages dropped after their batch could not be delivered within `deliveryTimeoutMs`.
+  pub kafkaDeliveryErrors: i64,
+  pub bytesReceived: i64,
+  /// Bytes written to the device: the handshake, commands and heartbeats.
+  pub bytesSent: i64,
+  /// Lines received per second over the last minute.
+  pub linesPerSecond: f64,
+  pub lastError: Option<String>,
+  pub lastLineAt: Option<String>,
+}
@@ -0,0 +1,1006 @@
+use std::borrow::Cow;
+use std::collections::BTreeMap;
+
+use chrono::{DateTime, Utc};
+use regex::Regex;
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+
+use crate::assembly::{Assembled, AssemblyConfig, RecordAssembler};
+use crate::checksum::{self, Checksum, ChecksumError};
+use crate::csv_record::{self, ColumnMismatch};
+use crate::detect::{self, DetectedFormat};
+use crate::environment::EnvironmentConfig;
+use crate::field::{self, Field, Fields};
+use crate::key_value;
+use crate::probes::{ProbeChannel, ProbeReading, ProbesConfig};
+use crate::quality::Quality;
+use crate::registry::{self, ChannelRegistry};
+use crate::timestamp::{TimestampConfig, TimestampParser};
+use crate::validation::ConfigIssue;
+use crate::xml::{self, XmlConfig, XmlPath};
+
+/// Keys that fill the sample itself and so never become extras, unless `extras.reserved` says otherwise.
+pub const RESERVED_KEYS: &[&str] =
+  &["ts", "btC", "etC", "powerPct", "fanPct", "drumRpm", "ambientC", "humidityPct", "pressureHpa"];
+/// Sample keys of the channels, in the order `RawTelemetrySample::channels` lists them: the reserved ones, then
+/// `REGISTERED_CHANNELS`.
+pub const CHANNELS: [&str; 12] = [
+  "btC",
+  "etC",
+  "powerPct",
+  "fanPct",
+  "drumRpm",
+  "ambientC",
+  "humidityPct",
+  "pressureHpa",
+  "exhaustC",
+  "inletC",
+  "drumPressurePa",
+  "airflowPct",
+];
+/// Other names a channel is taken under, on input and as a config key: `gasPct`, which points report `powerPct` as
+/// by default, stands for `powerPct`.
+pub const CHANNEL_ALIASES: &[(&str, &str)] = &[("gasPct", "powerPct")];
+/// Lines `autodetect` looks at before deciding on a format.
+pub const AUTODETECT_LINES: usize = 3;
+/// Columns of a CSV line when neither `csv.columns` nor a header names them.
+const DEFAULT_CSV_COLUMNS: [&str; 6] = ["ts", "btC", "etC", "powerPct", "fanPct", "drumRpm"];
+
+/// The settings that decide how a line becomes a sample. Field names and defaults match the driver config, so a
+/// full driver config deserializes into one, keeping only these fields.
+#[derive(Debug, Clone, PartialEq, Deserialize, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct ParserConfig {
+  pub format: FrameFormat,
+  pub csv: CsvConfig,
+  /// Required when `format` is `regex`.
+  #[serde(default)]
+  pub regex: Option<RegexConfig>,
+  /// Required when `format` is `xml`.
+  #[serde(default)]
+  pub xml: Option<XmlConfig>,
+  /// How `jsonl` lines with nested objects are read.
+  #[serde(default)]
+  pub json: JsonConfig,
+  /// Trailer each line must carry; it is verified and stripped before parsing.
+  #[serde(default)]
+  pub checksum: Checksum,
+  /// Joins several lines into one record before parsing, for devices that split a reading across lines.
+  #[serde(default)]
+  pub assembly: Option<AssemblyConfig>,
+  pub offsets: Offsets,
+  /// Several calibrated probes per channel, e.g. `bt1`/`bt2`; a channel with probes takes its value from them, and
+  /// `offsets` doesn't apply to it.
+  #[serde(default)]
+  pub probes: ProbesConfig,
+  /// Units of the ambient channels.
+  #[serde(default)]
+  pub environment: EnvironmentConfig,
+  /// The `REGISTERED_CHANNELS` to read, keyed by channel, and the device keys they come from.
+  #[serde(default)]
+  pub channel_registry: ChannelRegistry,
+  /// Which non-channel keys become extras, and under what name.
+  #[serde(default)]
+  pub extras: ExtrasConfig,
+  /// How `ts` is encoded; lines without one are stamped with the receive time.
+  #[serde(default)]
+  pub timestamp: TimestampConfig,
+  /// What becomes of a line whose `btC`, `etC`, `powerPct`, `fanPct`, `drumRpm` or ambient channel isn't a number. An
+  /// empty field or a null is a missing reading, not a malformed one.
+  #[serde(default)]
+  pub strictness: Strictness,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Deserialize, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub enum Strictness {
+  /// The field is left out of the sample.
+  #[default]
+  Lenient,
+  /// The field is left out of the sample and reported; see `TcpLineParser::field_errors`.
+  Warn,
+  /// The whole line is rejected with an `invalidField` error.
+  Strict,
+}
+
+#[derive(Debug, Clone, PartialEq, Deserialize, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub enum FrameFormat {
+  #[serde(rename = "jsonl")]
+  Jsonl,
+  #[serde(rename = "csv")]
+  Csv,
+  /// Lines matched against `regex.pattern`, for firmware that wraps readings in prose.
+  #[serde(rename = "regex")]
+  Regex,
+  /// One XML document per line, read through `xml.fields`.
+  #[serde(rename = "xml")]
+  Xml,
+  /// `key=value` pairs separated by whitespace, `,` or `;`, e.g. `BT=196.4 ET=214.9`.
+  #[serde(rename = "keyValue")]
+  KeyValue,
+  /// JSONL, CSV or key=value, decided from the first lines and then kept; see `DriverStatus::detectedFormat`.
+  #[serde(rename = "autodetect")]
+  Autodetect,
+}
+
+#[derive(Debug, Clone, PartialEq, Deserialize, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct CsvConfig {
+  pub has_header: bool,
+  pub columns: Vec<String>,
+  /// Any string, e.g. `,`, `|` or `\t`; fields containing it must be quoted.
+  pub delimiter: String,
+  /// What to do with a row whose field count differs from the columns.
+  #[serde(default)]
+  pub column_mismatch: ColumnMismatch,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Deserialize, JsonSchema)]
+#[serde(rename_all = "camelCase", default)]
+pub struct JsonConfig {
+  /// Reads nested objects as fields under their path, e.g. `temps.bt` for `{"temps":{"bt":200}}`; unset, a nested
+  /// object is no field at all.
+  pub flatten: Option<FlattenConfig>,
+  /// Field name (`btC`, `etC`, ..., `ts`, or an extra) to the key or flattened path holding it, e.g.
+  /// `{"btC": "temps.bt"}`. Keys not mapped keep their name.
+  pub fields: BTreeMap<String, String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Deserialize, JsonSchema)]
+#[serde(rename_all = "camelCase", default)]
+pub struct FlattenConfig {
+  /// Levels of nesting read; objects below that are ignored.
+  pub max_depth: u32,
+  /// Joins the keys of a path.
+  pub separator: String,
+}
+
+impl Default for FlattenConfig {
+  fn default() -> Self {
+    Self { max_depth: 3, separator: ".".to_string() }
+  }
+}
+
+#[derive(Debug, Clone, PartialEq, Deserialize, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct RegexConfig {
+  /// Each named group becomes a field: `btC`, `etC`, `powerPct`, `fanPct`, `drumRpm` and `ts` fill the sample, any
+  /// other name an extra. E.g. `T1: (?<btC>[\d.]+)C T2: (?<etC>[\d.]+)C`.
+  pub pattern: String,
+}
+
+impl RegexConfig {
+  /// The compiled `pattern`; an error if it doesn't compile or has no named group.
+  pub fn compile(&self) -> std::result::Result<Regex, String> {
+    let regex = Regex::new(&self.pattern).map_err(|err| err.to_string())?;
+    if regex.capture_names().flatten().next().is_none() {
+      return Err("must have at least one named capture group".to_string());
+    }
+    Ok(regex)
+  }
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Deserialize, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct Offsets {
+  pub bt_c: f64,
+  pub et_c: f64,
+  /// In °C, % and hPa, after `environment` converted the reading.
+  #[serde(default)]
+  pub ambient_c: f64,
+  #[serde(default)]
+  pub humidity_pct: f64,
+  #[serde(default)]
+  pub pressure_hpa: f64,
+}
+
+#[derive(Debug, Clone, PartialEq, Deserialize, JsonSchema)]
+#[serde(rename_all = "camelCase", default)]
+pub struct ExtrasConfig {
+  /// Only these keys become extras; all do when unset.
+  pub include: Option<Vec<String>>,
+  /// Keys dropped even when included.
+  pub exclude: Vec<String>,
+  /// Device key to the name it is reported under. Include/exclude match the device key.
+  pub rename: BTreeMap<String, String>,
+  /// Extras kept per sample (the first ones by key for jsonl, by column for csv); the rest are dropped.
+  pub max_per_sample: Option<u32>,
+  /// Keys that never become extras; `ts` and the channels fill the sample only while listed, so leaving out `ts`
+  /// turns a device's `ts` label into an ordinary extra. Defaults to `RESERVED_KEYS`.
+  pub reserved: Vec<String>,
+  /// What becomes of a field that would be an extra under a reserved key or rename target.
+  pub on_reserved: ReservedPolicy,
+  /// Device key to the texts it sends and what each stands for (`{"heater": {"ON": true, "OFF": false}}`), so an
+  /// enumerated state is reported as a boolean or number. Texts not listed stay text.
+  pub enums: BTreeMap<String, BTreeMap<String, EnumValue>>,
+}
+
+impl Default for ExtrasConfig {
+  fn default() -> Self {
+    Self {
+      include: None,
+      exclude: Vec::new(),
+      rename: BTreeMap::new(),
+      max_per_sample: None,
+      reserved: RESERVED_KEYS.iter().map(|key| key.to_string()).collect(),
+      on_reserved: ReservedPolicy::Drop,
+      enums: BTreeMap::new(),
+    }
+  }
+}
+
+/// The value an enumerated text stands for.
+#[derive(Debug, Clone, PartialEq, Deserialize, JsonSchema)]
+#[serde(untagged, expecting = "must be a boolean, number or string")]
+pub enum EnumValue {
+  Bool(bool),
+  Number(f64),
+  Text(String),
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Deserialize, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub enum ReservedPolicy {
+  /// The field is left out.
+  #[default]
+  Drop,
+  /// The field becomes an extra named `x_<name>`.
+  Prefix,
+  /// The line is rejected with a `reservedKey` error.
+  Error,
+}
+
+impl ExtrasConfig {
+  /// An alias is reserved along with its channel.
+  pub fn is_reserved(&self, key: &str) -> bool {
+    let key = channel_key(key);
+    self.reserved.iter().any(|reserved| reserved == key)
+  }
+
+  /// The name `key` is reported under, or `None` if it is filtered out.
+  pub fn map_key(&self, key: &str) -> Option<String> {
+    let listed = |keys: &[String]| keys.iter().any(|listed| listed == key);
+    if self.include.as_deref().is_some_and(|include| !listed(include)) || listed(&self.exclude) {
+      return None;
+    }
+    Some(self.rename.get(key).cloned().unwrap_or_else(|| key.to_string()))
+  }
+
+  /// The extra `value` of device key `key` makes under `name`, or `None` for empty text and arrays or objects.
+  fn extra(&self, key: &str, name: String, value: &Field) -> Option<ExtraEntry> {
+    let entry = |number_value, text_value, bool_value| ExtraEntry { key: name, number_value, text_value, bool_value };
+    let listed = value.as_str().and_then(|text| self.enums.get(key)?.get(text.trim()));
+    match (listed, value) {
+      (Some(EnumValue::Bool(flag)), _) => Some(entry(None, None, Some(*flag))),
+      (Some(EnumValue::Number(number)), _) => Some(entry(Some(*number), None, None)),
+      (Some(EnumValue::Text(text)), _) => Some(entry(None, Some(text.clone()), None)),
+      (None, Field::Bool(flag)) => Some(entry(None, None, Some(*flag))),
+      (None, value) => match (value.number(), value.as_str().map(str::trim)) {
+        (Some(number), _) => Some(entry(Some(number), None, None)),
+        (None, Some(text)) if !text.is_empty() => Some(entry(None, Some(text.to_string()), None)),
+        _ => None,
+      },
+    }
+  }
+}
+
+#[derive(Debug, Clone)]
+pub struct RawTelemetrySample {
+  pub ts: DateTime<Utc>,
+  /// `ts` came from the line rather than the receive time.
+  pub device_ts: bool,
+  pub bt_c: Option<f64>,
+  pub et_c: Option<f64>,
+  pub power_pct: Option<f64>,
+  pub fan_pct: Option<f64>,
+  pub drum_rpm: Option<f64>,
+  /// Ambient channels, in °C, % and hPa.
+  pub ambient_c: Option<f64>,
+  pub humidity_pct: Option<f64>,
+  pub pressure_hpa: Option<f64>,
+  /// Registered channels, unset unless `channelRegistry` promotes them.
+  pub exhaust_c: Option<f64>,
+  pub inlet_c: Option<f64>,
+  pub drum_pressure_pa: Option<f64>,
+  pub airflow_pct: Option<f64>,
+  pub extras: Option<Vec<ExtraEntry>>,
+  /// Calibrated readings of the `probes` sensors the line carried.
+  pub probes: Vec<ProbeReading>,
+  /// Gateway time the line (or the last line of the record) arrived.
+  pub received_at: DateTime<Utc>,
+  /// The same on a monotonic clock, unmoved by host clock steps; set by drivers that keep one.
+  pub host_received_at: Option<DateTime<Utc>>,
+  /// Channels and extras whose value isn't fresh from the device, keyed like `downsample.channels` (`powerPct`).
+  pub quality: BTreeMap<String, Quality>,
+}
+
+impl RawTelemetrySample {
+  pub fn same_values(&self, other: &Self) -> bool {
+    self.channels() == other.channels() && self.extras == other.extras && self.probes == other.probes
+  }
+
+  /// The channel readings by sample key, in `CHANNELS` order.
+  pub fn channels(&self) -> [(&'static str, Option<f64>); CHANNELS.len()] {
+    let values = [
+      self.bt_c,
+      self.et_c,
+      self.power_pct,
+      self.fan_pct,
+      self.drum_rpm,
+      self.ambient_c,
+      self.humidity_pct,
+      self.pressure_hpa,
+      self.exhaust_c,
+      self.inlet_c,
+      self.drum_pressure_pa,
+      self.airflow_pct,
+    ];
+    std::array::from_fn(|idx| (CHANNELS[idx], values[idx]))
+  }
+
+  /// `channels`, to set.
+  pub fn channels_mut(&mut self) -> impl Iterator<Item = (&'static str, &mut Option<f64>)> {
+    let slots = [
+      &mut self.bt_c,
+      &mut self.et_c,
+      &mut self.power_pct,
+      &mut self.fan_pct,
+      &mut self.drum_rpm,
+      &mut self.ambient_c,
+      &mut self.humidity_pct,
+      &mut self.pressure_hpa,
+      &mut self.exhaust_c,
+      &mut self.inlet_c,
+      &mut self.drum_pressure_pa,
+      &mut self.airflow_pct,
+    ];
+    CHANNELS.into_iter().zip(slots)
+  }
+
+  /// The reading of the channel `key` (a sample key), or `None` if `key` isn't a channel.
+  pub fn channel_mut(&mut self, key: &str) -> Option<&mut Option<f64>> {
+    self.channels_mut().find(|(channel, _)| *channel == key).map(|(_, slot)| slot)
+  }
+
+  /// Whether any channel has a reading.
+  pub fn has_channels(&self) -> bool {
+    self.channels().iter().any(|(_, value)| value.is_some())
+  }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+#[cfg_attr(feature = "napi", napi_derive::napi(object))]
+pub struct ExtraEntry {
+  pub key: String,
+  pub number_value: Option<f64>,
+  pub text_value: Option<String>,
+  pub bool_value: Option<bool>,
+}
+
+/// Turns lines into samples per a `ParserConfig`. It keeps the state that spans lines (a learned CSV header, a
+/// partly assembled record, the detected format), so use one per connection.
+pub struct TcpLineParser {
+  config: ParserConfig,
+  timestamps: TimestampParser,
+  regex: Option<Regex>,
+  xml_paths: Vec<(String, XmlPath)>,
+  assembler: Option<RecordAssembler>,
+  csv_header_parsed: bool,
+  csv_columns: Vec<String>,
+  /// What `autodetect` settled on; kept across reconnects until the format settings change.
+  detected: Option<DetectedFormat>,
+  /// Lines `autodetect` is still deciding on.
+  detect_lines: Vec<String>,
+  /// The first issue of an unvalidated config, which fails every line instead of parsing them by guesswork.
+  config_issue: Option<String>,
+  /// The malformed fields of the last sample built under `strictness: warn`.
+  field_errors: Vec<FieldError>,
+  /// The samples after the first of the last line, when it was a JSON array.
+  batch: Option<Vec<RawTelemetrySample>>,
+}
+
+/// A channel field whose value isn't a number.
+#[derive(Debug, Clone, PartialEq)]
+pub struct FieldError {
+  pub field: String,
+  /// The text it held; `None` for a JSON bool, array or object.
+  pub value: Option<String>,
+}
+
+impl std::fmt::Display for FieldError {
+  fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+    match &self.value {
+      Some(value) => write!(f, "{}: {value:?} is not a number", self.field),
+      None => write!(f, "{}: not a number", self.field),
+    }
+  }
+}
+
+/// A built sample with the malformed fields it left out.
+type Built = (Option<RawTelemetrySample>, Vec<FieldError>);
+
+impl TcpLineParser {
+  pub fn new(config: ParserConfig) -> Self {
+    // Configs are validated before they get here; the defaults and `config_issue` cover sources that skip validation.
+    let timestamps = TimestampParser::new(&config.timestamp).unwrap_or_default();
+    let regex = config.regex.as_ref().and_then(|regex| regex.compile().ok());
+    let xml_paths = compile_xml(&config);
+    let assembler = config.assembly.clone().map(RecordAssembler::new);
+    Self {
+      csv_columns: config.csv.columns.clone(),
+      csv_header_parsed: false,
+      detected: None,
+      detect_lines: Vec::new(),
+      config_issue: first_issue(&config),
+      field_errors: Vec::new(),
+      batch: None,
+      timestamps,
+      regex,
+      xml_paths,
+      assembler,
+      config,
+    }
+  }
+
+  pub fn config(&self) -> &ParserConfig {
+    &self.config
+  }
+
+  /// What `autodetect` settled on, `None` until it has decided or for the other formats.
+  pub fn detected(&self) -> Option<&DetectedFormat> {
+    self.detected.as_ref()
+  }
+
+  /// The fields of the last line or record that weren't numbers and were left out of its sample, under
+  /// `strictness: warn`; empty otherwise.
+  pub fn field_errors(&self) -> &[FieldError] {
+    &self.field_errors
+  }
+
+  /// The rest of the last line's samples when it was a JSON array of readings, of which `parse_line` returned the
+  /// first; `None` for any other line.
+  pub fn take_batch(&mut self) -> Option<Vec<RawTelemetrySample>> {
+    self.batch.take()
+  }
+
+  /// Forgets what was learned from the current connection (CSV header, partial record, undecided lines).
+  pub fn reset(&mut self) {
+    self.csv_header_parsed = false;
+    self.csv_columns = self.config.csv.columns.clone();
+    self.detect_lines.clear();
+    if let Some(assembler) = self.assembler.as_mut() {
+      assembler.reset();
+    }
+  }
+
+  /// Swaps in new settings; a learned CSV header is kept unless the framing itself changed.
+  pub fn update_config(&mut self, config: ParserConfig) {
+    let reframed = config.format != self.config.format || config.csv != self.config.csv;
+    self.timestamps = TimestampParser::new(&config.timestamp).unwrap_or_default();
+    self.config_issue = first_issue(&config);
+    if config.regex != self.config.regex {
+      self.regex = config.regex.as_ref().and_then(|regex| regex.compile().ok());
+    }
+    if config.xml != self.config.xml {
+      self.xml_paths = compile_xml(&config);
+    }
+    if self.assembler.as_ref().map(RecordAssembler::config) != config.assembly.as_ref() {
+      self.assembler = config.assembly.clone().map(RecordAssembler::new);
+    }
+    self.config = config;
+    if reframed {
+      self.detected = None;
+      self.reset();
+    }
+  }
+
+  /// `Ok(None)` for lines that parse without completing a sample: blanks, a CSV header, part of a record. A JSON array
+  /// line completes one sample per object: this returns the first and `take_batch` the rest.
+  pub fn parse_line(&mut self, line: &str) -> std::result::Result<Option<RawTelemetrySample>, ParseError> {
+    self.field_errors.clear();
+    self.batch = None;
+    if line.trim().is_empty() {
+      return Ok(None);
+    }
+    if let Some(issue) = &self.config_issue {
+      return Err(ParseError::InvalidConfig(issue.clone()));
+    }
+    let line = checksum::verify(self.config.checksum, line.trim_end_matches(['\r', '\n'])).map_err(|err| match err {
+      ChecksumError::Missing => ParseError::MissingChecksum,
+      ChecksumError::Mismatch => ParseError::ChecksumMismatch,
+    })?;
+    let record;
+    let line = match self.assembler.as_mut().map(|assembler| assembler.push(line)) {
+      None => line,
+      Some(Assembled::Pending) => return Ok(None),
+      Some(Assembled::Overflow) => return Err(ParseError::RecordTooLong),
+      Some(Assembled::Record(joined)) => {
+        record = joined;
+        record.as_str()
+      }
+    };
+    let trimmed = line.trim();
+    match self.config.format {
+      FrameFormat::Jsonl => self.parse_json_line(trimmed),
+      // Only the line ending is stripped: with a tab delimiter, leading whitespace can be an empty first field.
+      FrameFormat::Csv => self.parse_csv_line(line),
+      FrameFormat::Regex => self.parse_regex_line(trimmed),
+      FrameFormat::Xml => self.parse_xml_line(trimmed),
+      FrameFormat::KeyValue => self.parse_key_value_line(trimmed),
+      FrameFormat::Autodetect => self.parse_detected_line(line),
+    }
+  }
+
+  /// Collects the first `AUTODETECT_LINES` lines to guess from, then parses the last of them and everything after as
+  /// the format they looked like; the earlier ones only inform the guess. A CSV header among them becomes the columns.
+  fn parse_detected_line(&mut self, line: &str) -> std::result::Result<Option<RawTelemetrySample>, ParseError> {
+    let Some(detected) = &self.detected else {
+      self.detect_lines.push(line.to_string());
+      if self.detect_lines.len() < AUTODETECT_LINES {
+        return Ok(None);
+      }
+      let lines = std::mem::take(&mut self.detect_lines);
+      let detected = detect::detect(&lines).ok_or(ParseError::FormatUndetected)?;
+      if let DetectedFormat::Csv { delimiter, has_header: true } = &detected {
+        self.csv_columns = csv_record::split_record(&lines[0], delimiter).unwrap_or_default();
+        self.csv_header_parsed = true;
+      }
+      self.detected = Some(detected);
+      return self.parse_detected_line(line);
+    };
+    match detected {
+      DetectedFormat::Jsonl => self.parse_json_line(line.trim()),
+      DetectedFormat::Csv { .. } => self.parse_csv_line(line),
+      DetectedFormat::KeyValue => self.parse_key_value_line(line.trim()),
+    }
+  }
+
+  fn parse_key_value_line(&mut self, line: &str) -> std::result::Result<Option<RawTelemetrySample>, ParseError> {
+    let pairs = key_value::pairs(line).ok_or(ParseError::InvalidKeyValue)?;
+    let fields = pairs.into_iter().map(|(key, value)| (key.into(), Field::Text(value.into()))).collect();
+    Ok(self.keep_field_errors(self.fields_to_sample(fields)?))
+  }
+
+  fn parse_xml_line(&mut self, line: &str) -> std::result::Result<Option<RawTelemetrySample>, ParseError> {
+    let fields = xml::extract(line, &self.xml_paths).ok_or(ParseError::InvalidXml)?;
+    Ok(self.keep_field_errors(self.fields_to_sample(fields)?))
+  }
+
+  fn parse_regex_line(&mut self, line: &str) -> std::result::Result<Option<RawTelemetrySample>, ParseError> {
+    let regex = self.regex.as_ref().ok_or(ParseError::NoMatch)?;
+    let captures = regex.captures(line).ok_or(ParseError::NoMatch)?;
+    let fields = regex
+      .capture_names()
+      .flatten()
+      .filter_map(|name| captures.name(name).map(|m| (name.into(), Field::Text(m.as_str().into()))))
+      .collect();
+    Ok(self.keep_field_errors(self.fields_to_sample(fields)?))
+  }
+
+  fn parse_json_line(&mut self, line: &str) -> std::result::Result<Option<RawTelemetrySample>, ParseError> {
+    let (separator, max_depth) = match &self.config.json.flatten {
+      Some(flatten) => (flatten.separator.as_str(), flatten.max_depth),
+      None => ("", 0),
+    };
+    if line.starts_with('[') {
+      let records = field::json_array(line, separator, max_depth).ok_or(ParseError::InvalidJson)?;
+      let mut samples = Vec::with_capacity(records.len());
+      let mut field_errors = Vec::new();
+      for fields in records {
+        let (sample, errors) = self.fields_to_sample(self.map_json_fields(fields))?;
+        samples.extend(sample);
+        field_errors.extend(errors);
+      }
+      let mut samples = samples.into_iter();
+      let first = self.keep_field_errors((samples.next(), field_errors));
+      self.batch = Some(samples.collect());
+      return Ok(first);
+    }
+    let fields = field::json_object(line, separator, max_depth).ok_or(ParseError::InvalidJson)?;
+    Ok(self.keep_field_errors(self.fields_to_sample(self.map_json_fields(fields))?))
+  }
+
+  /// Renames the keys `json.fields` maps to a field.
+  fn map_json_fields<'a>(&'a self, mut fields: Fields<'a>) -> Fields<'a> {
+    let mapped = &self.config.json.fields;
+    if !mapped.is_empty() {
+      for (key, _) in fields.iter_mut() {
+        if let Some((name, _)) = mapped.iter().find(|(_, path)| *path == key) {
+          *key = Cow::Borrowed(name.as_str());
+        }
+      }
+      fields.sort_by(|(a, _), (b, _)| a.cmp(b));
+    }
+    fields
+  }
+
+  fn parse_csv_line(&mut self, line: &str) -> std::result::Result<Option<RawTelemetrySample>, ParseError> {
+    let (delimiter, has_header) = match &self.detected {
+      Some(DetectedFormat::Csv { delimiter, has_header }) => (Cow::Owned(delimiter.clone()), *has_header),
+      _ => (csv_record::delimiter(&self.config.csv.delimiter), self.config.csv.has_header),
+    };
+    let parts = csv_record::split_fields(line, &delimiter).map_err(|_| ParseError::UnterminatedQuote)?;
+    if has_header && !self.csv_header_parsed {
+      self.csv_columns = parts.into_iter().map(Cow::into_owned).collect();
+      self.csv_header_parsed = true;
+      return Ok(None);
+    }
+
+    let expected = if self.csv_columns.is_empty() { DEFAULT_CSV_COLUMNS.len() } else { self.csv_columns.len() };
+    if self.config.csv.column_mismatch == ColumnMismatch::Strict && parts.len() != expected {
+      return Err(ParseError::ColumnMismatch { expected, found: parts.len() });
+    }
+
+    let cells = parts.into_iter().map(Field::Text);
+    let fields = if self.csv_columns.is_empty() {
+      DEFAULT_CSV_COLUMNS.into_iter().map(Cow::Borrowed).zip(cells).collect()
+    } else {
+      self.csv_columns.iter().map(|column| Cow::Borrowed(column.as_str())).zip(cells).collect()
+    };
+
+    Ok(self.keep_field_errors(self.fields_to_sample(fields)?))
+  }
+
+  /// Builds a sample from field/value pairs, for sources that read fields instead of lines.
+  pub fn to_sample(
+    &mut self,
+    record: Vec<(String, serde_json::Value)>,
+  ) -> std::result::Result<Option<RawTelemetrySample>, ParseError> {
+    self.field_errors.clear();
+    let fields = record.iter().map(|(key, value)| (Cow::Borrowed(key.as_str()), Field::from(value))).collect();
+    Ok(self.keep_field_errors(self.fields_to_sample(fields)?))
+  }
+
+  fn keep_field_errors(&mut self, (sample, field_errors): Built) -> Option<RawTelemetrySample> {
+    self.field_errors = field_errors;
+    sample
+  }
+
+  fn fields_to_sample(&self, fields: Fields<'_>) -> std::result::Result<Built, ParseError> {
+    let now = Utc::now();
+    let extras_config = &self.config.extras;
+    let mut ts_value: Option<DateTime<Utc>> = None;
+    for (key, value) in fields.iter() {
+      // An empty csv field is a missing `ts`, like a JSON null.
+      if key == "ts" && extras_config.is_reserved(key) && !value.is_null() && value.as_str() != Some("") {
+        ts_value = Some(self.timestamps.parse(value, now).ok_or(ParseError::InvalidTimestamp)?);
+      }
+    }
+
+    let ts = ts_value.unwrap_or(now);
+
+    let mut extras = Vec::<ExtraEntry>::new();
+    let mut sample = RawTelemetrySample {
+      ts,
+      device_ts: ts_value.is_some(),
+      bt_c: None,
+      et_c: None,
+      power_pct: None,
+      fan_pct: None,
+      drum_rpm: None,
+      ambient_c: None,
+      humidity_pct: None,
+      pressure_hpa: None,
+      exhaust_c: None,
+      inlet_c: None,
+      drum_pressure_pa: None,
+      airflow_pct: None,
+      extras: None,
+      probes: Vec::new(),
+      received_at: now,
+      host_received_at: None,
+      quality: BTreeMap::new(),
+    };
+
+    let mut field_errors = Vec::new();
+    let mut reading = |key: &str, value: &Field| {
+      let number = value.number();
+      let missing = value.is_null() || value.as_str().is_some_and(|text| text.trim().is_empty());
+      if number.is_none() && !missing && self.config.strictness != Strictness::Lenient {
+        field_errors.push(FieldError { field: key.to_string(), value: value.as_str().map(str::to_string) });
+      }
+      number
+    };
+    for (key, value) in fields.iter() {
+      if let Some(probe) = self.config.probes.find(key) {
+        sample.probes.extend(reading(key, value).map(|value| probe.read(value)));
+        continue;
+      }
+      if let Some((name, channel)) = registry::registered(&self.config.channel_registry, key) {
+        let value = reading(key, value).map(|value| channel.read(value));
+        if let Some(slot) = sample.channel_mut(name) {
+          *slot = value;
+        }
+        continue;
+      }
+      match Some(channel_key(key.as_ref())).filter(|key| extras_config.is_reserved(key)) {
+        Some("btC") => sample.bt_c = reading(key, value).map(|v| v + self.config.offsets.bt_c),
+        Some("etC") => sample.et_c = reading(key, value).map(|v| v + self.config.offsets.et_c),
+        Some("powerPct") => sample.power_pct = reading(key, value),
+        Some("fanPct") => sample.fan_pct = reading(key, value),
+        Some("drumRpm") => sample.drum_rpm = reading(key, value),
+        Some("ambientC") => {
+          let celsius = reading(key, value).map(|v| self.config.environment.temperature(v));
+          sample.ambient_c = celsius.map(|v| v + self.config.offsets.ambient_c);
+        }
+        Some("humidityPct") => sample.humidity_pct = reading(key, value).map(|v| v + self.config.offsets.humidity_pct),
+        Some("pressureHpa") => {
+          let hpa = reading(key, value).map(|v| self.config.environment.pressure(v));
+          sample.pressure_hpa = hpa.map(|v| v + self.config.offsets.pressure_hpa);
+        }
+        Some("ts") => {}
+        _ => {
+          let Some(mut name) = extras_config.map_key(key) else {
+            continue;
+          };
+          // A registered channel read from another key keeps its name to itself, like the reserved ones.
+          let registered = self.config.channel_registry.contains_key(&name);
+          if extras_config.is_reserved(key) || extras_config.is_reserved(&name) || registered {
+            match extras_config.on_reserved {
+              ReservedPolicy::Drop => continue,
+              ReservedPolicy::Prefix => name.insert_str(0, "x_"),
+              ReservedPolicy::Error => return Err(ParseError::ReservedKey(key.to_string())),
+            }
+          }
+          if extras_config.max_per_sample.is_some_and(|max| extras.len() >= max as usize) {
+            continue;
+          }
+          extras.extend(extras_config.extra(key, name, value));
+        }
+      }
+    }
+
+    if self.config.strictness == Strictness::Strict && !field_errors.is_empty() {
+      return Err(ParseError::InvalidField(field_errors));
+    }
+    let probes = &self.config.probes;
+    if probes.feeds(ProbeChannel::BtC) {
+      sample.bt_c = probes.combine(ProbeChannel::BtC, &sample.probes);
+    }
+    if probes.feeds(ProbeChannel::EtC) {
+      sample.et_c = probes.combine(ProbeChannel::EtC, &sample.probes);
+    }
+
+    let has_channels = sample.has_channels();
+
+    if !extras.is_empty() {
+      sample.extras = Some(extras);
+    }
+
+    if !has_channels && sample.extras.is_none() {
+      return Ok((None, field_errors));
+    }
+
+    Ok((Some(sample), field_errors))
+  }
+}
+
+fn first_issue(config: &ParserConfig) -> Option<String> {
+  config.issues().first().map(ConfigIssue::describe)
+}
+
+fn compile_xml(config: &ParserConfig) -> Vec<(String, XmlPath)> {
+  config.xml.as_ref().and_then(|xml| xml::compile(xml).ok()).unwrap_or_default()
+}
+
+/// The channel `key` is an alias of (`powerPct` for `gasPct`), or `key` itself.
+pub fn channel_key(key: &str) -> &str {
+  CHANNEL_ALIASES.iter().find(|(alias, _)| *alias == key).map_or(key, |(_, channel)| channel)
+}
+
+/// Parses an RFC 3339 timestamp.
+pub fn parse_timestamp(value: &str) -> std::result::Result<DateTime<Utc>, ParseError> {
+  DateTime::parse_from_rfc3339(value)
+    .map(|dt| dt.with_timezone(&Utc))
+    .map_err(|_| ParseError::InvalidTimestamp)
+}
+
+#[derive(Debug, Error)]
+pub enum ParseError {
+  #[error("invalid json")]
+  InvalidJson,
+  #[error("invalid timestamp")]
+  InvalidTimestamp,
+  #[error("invalid frame: {0}")]
+  InvalidFrame(&'static str),
+  #[error("invalid utf-8")]
+  InvalidUtf8,
+  #[error("record exceeds assembly.maxLines")]
+  RecordTooLong,
+  #[error("missing checksum")]
+  MissingChecksum,
+  #[error("checksum mismatch")]
+  ChecksumMismatch,
+  #[error("invalid xml")]
+  InvalidXml,
+  #[error("line does not match regex.pattern")]
+  NoMatch,
+  #[error("unterminated quoted field")]
+  UnterminatedQuote,
+  #[error("expected {expected} fields, found {found}")]
+  ColumnMismatch { expected: usize, found: usize },
+  #[error("invalid key=value pair")]
+  InvalidKeyValue,
+  #[error("format not detected: lines are neither jsonl, csv nor key=value")]
+  FormatUndetected,
+  /// The parser was built from a config that skipped validation and fails it, e.g. an empty `csv.delimiter`.
+  #[error("invalid parser config: {0}")]
+  InvalidConfig(String),
+  /// Under `strictness: strict`, the channel fields of the line that aren't numbers.
+  #[error("invalid field {}", describe_fields(.0))]
+  InvalidField(Vec<FieldError>),
+  /// Under `extras.onReserved: error`, a field that would have been an extra under a reserved key.
+  #[error("field {0} collides with a reserved key")]
+  ReservedKey(String),
+}
+
+fn describe_fields(field_errors: &[FieldError]) -> String {
+  field_errors.iter().map(FieldError::to_string).collect::<Vec<_>>().join("; ")
+}
+
+impl ParseError {
+  /// Stable category name for `getRecentErrors`.
+  pub fn kind(&self) -> &'static str {
+    match self {
+      ParseError::InvalidJson => "invalidJson",
+      ParseError::InvalidTimestamp => "invalidTimestamp",
+      ParseError::InvalidFrame(_) => "invalidFrame",
+      ParseError::InvalidUtf8 => "invalidUtf8",
+      ParseError::RecordTooLong => "recordTooLong",
+      ParseError::MissingChecksum => "missingChecksum",
+      ParseError::ChecksumMismatch => "checksumMismatch",
+      ParseError::InvalidXml => "invalidXml",
+      ParseError::NoMatch => "noMatch",
+      ParseError::UnterminatedQuote => "unterminatedQuote",
+      ParseError::ColumnMismatch { .. } => "columnMismatch",
+      ParseError::InvalidKeyValue => "invalidKeyValue",
+      ParseError::FormatUndetected => "formatUndetected",
+      ParseError::InvalidConfig(_) => "invalidConfig",
+      ParseError::InvalidField(_) => "invalidField",
+      ParseError::ReservedKey(_) => "reservedKey",
+    }
+  }
+}
+
+
+#[cfg(test)]
+mod tests {
+  use serde_json::{json, Value};
+
+  use super::*;
+
+  /// A jsonl config with `overrides` merged over it.
+  fn config(overrides: Value) -> ParserConfig {
+    let mut config = json!({
+      "format": "jsonl",
+      "csv": { "hasHeader": false, "columns": [], "delimiter": "," },
+      "offsets": { "btC": 0, "etC": 0 },
+    });
+    for (key, value) in overrides.as_object().expect("overrides") {
+      config[key] = value.clone();
+    }
+    serde_json::from_value(config).expect("config")
+  }
+
+  fn parse(parser: &mut TcpLineParser, line: &str) -> RawTelemetrySample {
+    parser.parse_line(line).expect("parses").expect("completes a sample")
+  }
+
+  fn extras(sample: &RawTelemetrySample) -> Vec<(&str, Option<f64>, Option<&str>)> {
+    let extras = sample.extras.iter().flatten();
+    extras.map(|extra| (extra.key.as_str(), extra.number_value, extra.text_value.as_deref())).collect()
+  }
+
+  #[test]
+  fn parses_jsonl() {
+    let mut parser = TcpLineParser::new(config(json!({ "offsets": { "btC": 1.5, "etC": 0 } })));
+    let line = r#"{"ts":"2025-01-01T06:00:00Z","btC":200,"etC":"210.5","gasPct":40,"mode":"roast"}"#;
+    let sample = parse(&mut parser, line);
+    assert_eq!(sample.ts, parse_timestamp("2025-01-01T06:00:00Z").unwrap());
+    assert!(sample.device_ts);
+    assert_eq!((sample.bt_c, sample.et_c, sample.power_pct), (Some(201.5), Some(210.5), Some(40.0)));
+    assert_eq!(extras(&sample), [("mode", None, Some("roast"))]);
+    assert!(matches!(parser.parse_line("{\"btC\":"), Err(ParseError::InvalidJson)));
+    assert!(parser.parse_line("  ").unwrap().is_none());
+  }
+
+  #[test]
+  fn parses_csv_under_its_header() {
+    let csv = json!({ "hasHeader": true, "columns": [], "delimiter": ";" });
+    let mut parser = TcpLineParser::new(config(json!({ "format": "csv", "csv": csv })));
+    assert!(parser.parse_line("etC;btC;rssi").unwrap().is_none());
+    let sample = parse(&mut parser, "210;200;-60");
+    assert_eq!((sample.bt_c, sample.et_c), (Some(200.0), Some(210.0)));
+    assert_eq!(extras(&sample), [("rssi", Some(-60.0), None)]);
+    assert!(!sample.device_ts);
+  }
+
+  #[test]
+  fn parses_key_value_pairs() {
+    let mut parser = TcpLineParser::new(config(json!({ "format": "keyValue" })));
+    let sample = parse(&mut parser, "btC=196.4, etC=214.9; phase=dry");
+    assert_eq!((sample.bt_c, sample.et_c), (Some(196.4), Some(214.9)));
+    assert_eq!(extras(&sample), [("phase", None, Some("dry"))]);
+    assert!(matches!(parser.parse_line("btC"), Err(ParseError::InvalidKeyValue)));
+  }
+
+  #[test]
+  fn autodetect_decides_on_the_first_lines() {
+    let mut parser = TcpLineParser::new(config(json!({ "format": "autodetect" })));
+    assert!(parser.parse_line("ts,btC,etC").unwrap().is_none());
+    assert!(parser.parse_line("2025-01-01T06:00:00Z,200,210").unwrap().is_none());
+    assert_eq!(parser.detected(), None);
+    // The third line settles it and is parsed; the header among the first ones named the columns.
+    let sample = parse(&mut parser, "2025-01-01T06:00:01Z,201,211");
+    assert_eq!(parser.detected(), Some(&DetectedFormat::Csv { delimiter: ",".to_string(), has_header: true }));
+    assert_eq!((sample.bt_c, sample.et_c), (Some(201.0), Some(211.0)));
+    assert_eq!(parse(&mut parser, "2025-01-01T06:00:02Z,202,212").bt_c, Some(202.0));
+
+    let mut parser = TcpLineParser::new(config(json!({ "format": "autodetect" })));
+    for line in ["btC=200 etC=210", "btC=201 etC=211"] {
+      assert!(parser.parse_line(line).unwrap().is_none());
+    }
+    assert_eq!(parse(&mut parser, "btC=202 etC=212").bt_c, Some(202.0));
+    assert_eq!(parser.detected(), Some(&DetectedFormat::KeyValue));
+  }
+
+  #[test]
+  fn update_config_keeps_the_header_unless_the_framing_changes() {
+    let csv = json!({ "hasHeader": true, "columns": [], "delimiter": "," });
+    let mut parser = TcpLineParser::new(config(json!({ "format": "csv", "csv": csv })));
+    assert!(parser.parse_line("etC,btC").unwrap().is_none());
+
+    parser.update_config(config(json!({ "format": "csv", "csv": csv, "offsets": { "btC": 1, "etC": 0 } })));
+    let sample = parse(&mut parser, "210,200");
+    assert_eq!((sample.bt_c, sample.et_c), (Some(201.0), Some(210.0)));
+
+    let csv = json!({ "hasHeader": true, "columns": [], "delimiter": ";" });
+    parser.update_config(config(json!({ "format": "csv", "csv": csv })));
+    assert!(parser.parse_line("btC;etC").unwrap().is_none(), "a new delimiter reads a new header");
+    assert_eq!(parse(&mut parser, "200;210").bt_c, Some(200.0));
+  }
+
+  #[test]
+  fn strictness_decides_what_becomes_of_a_malformed_channel() {
+    let line = r#"{"btC":"ERR","etC":210,"fanPct":null}"#;
+
+    let mut parser = TcpLineParser::new(config(json!({})));
+    let sample = parse(&mut parser, line);
+    assert_eq!((sample.bt_c, sample.et_c), (None, Some(210.0)));
+    assert!(parser.field_errors().is_empty());
+
+    let mut parser = TcpLineParser::new(config(json!({ "strictness": "warn" })));
+    let sample = parse(&mut parser, line);
+    assert_eq!((sample.bt_c, sample.et_c), (None, Some(210.0)));
+    let reported = FieldError { field: "btC".to_string(), value: Some("ERR".to_string()) };
+    assert_eq!(parser.field_errors(), std::slice::from_ref(&reported));
+
+    let mut parser = TcpLineParser::new(config(json!({ "strictness": "strict" })));
+    match parser.parse_line(line) {
+      Err(ParseError::InvalidField(fields)) => assert_eq!(fields, [reported]),
+      other => panic!("expected invalidField, got {:?}", other.map(|sample| sample.is_some())),
+    }
+    assert!(parser.parse_line(r#"{"btC":200}"#).unwrap().is_some());
+  }
+
+  #[test]
+  fn reserved_policy_handles_a_field_under_a_reserved_key() {
+    let line = r#"{"btC":200,"mode":"roast"}"#;
+    let extras_with = |policy: &str| json!({ "extras": { "reserved": ["ts", "btC", "mode"], "onReserved": policy } });
+
+    let mut parser = TcpLineParser::new(config(extras_with("drop")));
+    let sample = parse(&mut parser, line);
+    assert_eq!(sample.bt_c, Some(200.0));
+    assert_eq!(extras(&sample), []);
+
+    let mut parser = TcpLineParser::new(config(extras_with("prefix")));
+    assert_eq!(extras(&parse(&mut parser, line)), [("x_mode", None, Some("roast"))]);
+
+    let mut parser = TcpLineParser::new(config(extras_with("error")));
+    assert!(matches!(parser.parse_line(line), Err(ParseError::ReservedKey(key)) if key == "mode"));
+  }
+}
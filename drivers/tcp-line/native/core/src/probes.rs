@@ -0,0 +1,112 @@
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+
+/// The channel a probe measures.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, JsonSchema)]
+pub enum ProbeChannel {
+  #[serde(rename = "btC")]
+  BtC,
+  #[serde(rename = "etC")]
+  EtC,
+}
+
+impl ProbeChannel {
+  pub fn as_str(self) -> &'static str {
+    match self {
+      Self::BtC => "btC",
+      Self::EtC => "etC",
+    }
+  }
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Deserialize, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub enum ProbeCombine {
+  /// The reading of the first listed probe that has one.
+  #[default]
+  First,
+  /// The mean of the probes that have a reading.
+  Mean,
+  /// The lowest reading.
+  Min,
+  /// The highest reading.
+  Max,
+}
+
+/// One of the probes on a channel, e.g. the second bean probe of a dual-probe roaster.
+#[derive(Debug, Clone, PartialEq, Deserialize, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct ProbeConfig {
+  /// The key the device sends the reading under, e.g. `bt2`; also the probe's name in points. May be a channel key,
+  /// to keep the device's own `btC` as one of the probes.
+  pub key: String,
+  pub channel: ProbeChannel,
+  /// Calibration: the probe reports `reading * gain + offset`.
+  #[serde(default = "default_gain")]
+  pub gain: f64,
+  #[serde(default)]
+  pub offset: f64,
+}
+
+impl ProbeConfig {
+  /// `value` as read by the device, calibrated.
+  pub fn read(&self, value: f64) -> ProbeReading {
+    let value = value * self.gain + self.offset;
+    ProbeReading { name: self.key.clone(), channel: self.channel.as_str().to_string(), value }
+  }
+}
+
+fn default_gain() -> f64 {
+  1.0
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Deserialize, JsonSchema)]
+#[serde(rename_all = "camelCase", default)]
+pub struct ProbesConfig {
+  pub sensors: Vec<ProbeConfig>,
+  /// How a channel with probes gets its value from their readings.
+  pub combine: ProbeCombine,
+}
+
+impl ProbesConfig {
+  pub fn find(&self, key: &str) -> Option<&ProbeConfig> {
+    self.sensors.iter().find(|probe| probe.key == key)
+  }
+
+  /// Whether `channel` takes its value from probes rather than its own key.
+  pub fn feeds(&self, channel: ProbeChannel) -> bool {
+    self.sensors.iter().any(|probe| probe.channel == channel)
+  }
+
+  /// `channel`'s value from the `readings` of its probes; `None` if none of them read.
+  pub fn combine(&self, channel: ProbeChannel, readings: &[ProbeReading]) -> Option<f64> {
+    // In config order, so `first` doesn't depend on the order of the line's fields.
+    let values = self
+      .sensors
+      .iter()
+      .filter(|probe| probe.channel == channel)
+      .filter_map(|probe| readings.iter().find(|reading| reading.name == probe.key))
+      .map(|reading| reading.value);
+    match self.combine {
+      ProbeCombine::First => values.into_iter().next(),
+      ProbeCombine::Mean => {
+        let (sum, count) = values.fold((0.0, 0), |(sum, count), value| (sum + value, count + 1));
+        (count > 0).then(|| sum / count as f64)
+      }
+      ProbeCombine::Min => values.reduce(f64::min),
+      ProbeCombine::Max => values.reduce(f64::max),
+    }
+  }
+}
+
+/// A calibrated probe reading.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+#[cfg_attr(feature = "napi", napi_derive::napi(object))]
+pub struct ProbeReading {
+  /// The probe's `key`.
+  pub name: String,
+  /// `btC` or `etC`.
+  pub channel: String,
+  pub value: f64,
+}
@@ -0,0 +1,27 @@
+/// How a channel value in a sample came about.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Quality {
+  /// Read from the device in this sample.
+  Fresh,
+  /// Carried over from an earlier sample.
+  Held,
+  /// Computed from the samples around it.
+  Interpolated,
+  /// Read from the device, but limited to the configured range.
+  Clamped,
+}
+
+impl Quality {
+  pub fn as_str(self) -> &'static str {
+    match self {
+      Self::Fresh => "fresh",
+      Self::Held => "held",
+      Self::Interpolated => "interpolated",
+      Self::Clamped => "clamped",
+    }
+  }
+
+  pub fn from_name(name: &str) -> Option<Self> {
+    [Self::Fresh, Self::Held, Self::Interpolated, Self::Clamped].into_iter().find(|quality| quality.as_str() == name)
+  }
+}
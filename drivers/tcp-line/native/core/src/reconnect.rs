@@ -0,0 +1,138 @@
+use std::time::Duration;
+
+use schemars::JsonSchema;
+use serde::Deserialize;
+
+use crate::rng::Rng;
+
+#[derive(Debug, Clone, PartialEq, Deserialize, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct ReconnectConfig {
+  pub enabled: bool,
+  pub min_backoff_ms: u64,
+  pub max_backoff_ms: u64,
+  #[serde(default)]
+  pub jitter: Jitter,
+  /// Consecutive failed attempts before giving up with FAILED.
+  #[serde(default)]
+  pub max_attempts: Option<u32>,
+  /// Longest outage (since the connection was lost) before giving up with FAILED.
+  #[serde(default)]
+  pub max_total_duration_ms: Option<u64>,
+}
+
+impl ReconnectConfig {
+  /// For sources that never reconnect (replay, simulator).
+  pub fn disabled() -> Self {
+    Self {
+      enabled: false,
+      min_backoff_ms: 0,
+      max_backoff_ms: 0,
+      jitter: Jitter::None,
+      max_attempts: None,
+      max_total_duration_ms: None,
+    }
+  }
+
+  pub fn exhausted(&self, attempts: u32, outage: Duration) -> bool {
+    self.max_attempts.is_some_and(|max| attempts > max)
+      || self.max_total_duration_ms.is_some_and(|max| outage >= Duration::from_millis(max))
+  }
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Deserialize, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub enum Jitter {
+  #[default]
+  None,
+  /// Uniform in `[0, delay]`.
+  Full,
+  /// `delay / 2` plus uniform in `[0, delay / 2]`.
+  Equal,
+}
+
+/// Delays between reconnect attempts: doubling from `minBackoffMs` up to `maxBackoffMs`, with optional jitter.
+pub struct Backoff {
+  current: u64,
+  min: u64,
+  max: u64,
+  jitter: Jitter,
+  rng: Rng,
+}
+
+impl Backoff {
+  pub fn new(min: u64, max: u64) -> Self {
+    Self { current: min, min, max, jitter: Jitter::None, rng: Rng::from_time() }
+  }
+
+  /// Takes the bounds and jitter of `reconnect`; the delay in progress is kept.
+  pub fn apply(&mut self, reconnect: &ReconnectConfig) {
+    self.min = reconnect.min_backoff_ms;
+    self.max = reconnect.max_backoff_ms;
+    self.jitter = reconnect.jitter;
+  }
+
+  pub fn next_delay(&mut self) -> u64 {
+    let value = self.current;
+    self.current = self.current.saturating_mul(2).clamp(self.min, self.max);
+    match self.jitter {
+      Jitter::None => value,
+      Jitter::Full => (value as f64 * self.rng.next_f64()) as u64,
+      Jitter::Equal => value / 2 + ((value / 2) as f64 * self.rng.next_f64()) as u64,
+    }
+  }
+
+  pub fn reset(&mut self) {
+    self.current = self.min;
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  fn delays(backoff: &mut Backoff, count: usize) -> Vec<u64> {
+    (0..count).map(|_| backoff.next_delay()).collect()
+  }
+
+  #[test]
+  fn backoff_doubles_up_to_the_cap() {
+    let mut backoff = Backoff::new(100, 1000);
+    assert_eq!(delays(&mut backoff, 6), [100, 200, 400, 800, 1000, 1000]);
+    backoff.reset();
+    assert_eq!(backoff.next_delay(), 100);
+
+    let mut backoff = Backoff::new(u64::MAX / 2 + 1, u64::MAX);
+    assert_eq!(delays(&mut backoff, 3), [u64::MAX / 2 + 1, u64::MAX, u64::MAX]);
+  }
+
+  #[test]
+  fn jitter_stays_within_the_delay() {
+    let reconnect = |jitter| ReconnectConfig { jitter, min_backoff_ms: 100, ..reconnect_config() };
+    let mut full = Backoff::new(0, 0);
+    full.apply(&reconnect(Jitter::Full));
+    full.reset();
+    let mut equal = Backoff::new(0, 0);
+    equal.apply(&reconnect(Jitter::Equal));
+    equal.reset();
+    for cap in [100, 200, 400, 800, 1600, 1600] {
+      assert!(full.next_delay() <= cap);
+      assert!((cap / 2..=cap).contains(&equal.next_delay()));
+    }
+  }
+
+  #[test]
+  fn gives_up_after_max_attempts_or_max_total_duration() {
+    let attempts = ReconnectConfig { max_attempts: Some(3), ..reconnect_config() };
+    assert!(!attempts.exhausted(3, Duration::from_secs(3600)));
+    assert!(attempts.exhausted(4, Duration::ZERO));
+    let outage = ReconnectConfig { max_total_duration_ms: Some(5000), ..reconnect_config() };
+    assert!(!outage.exhausted(100, Duration::from_millis(4999)));
+    assert!(outage.exhausted(1, Duration::from_millis(5000)));
+    assert!(!reconnect_config().exhausted(u32::MAX, Duration::MAX));
+  }
+
+  fn reconnect_config() -> ReconnectConfig {
+    ReconnectConfig { enabled: true, min_backoff_ms: 250, max_backoff_ms: 1600, ..ReconnectConfig::disabled() }
+  }
+}
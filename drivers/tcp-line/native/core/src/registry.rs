@@ -0,0 +1,43 @@
+use std::collections::BTreeMap;
+
+use schemars::JsonSchema;
+use serde::Deserialize;
+
+/// Channels beyond the core set that `channelRegistry` can promote from a device key to a point field. Until one is
+/// registered its key stays an ordinary extra.
+pub const REGISTERED_CHANNELS: [&str; 4] = ["exhaustC", "inletC", "drumPressurePa", "airflowPct"];
+
+/// Where a registered channel is read from, and how.
+#[derive(Debug, Clone, PartialEq, Deserialize, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct RegisteredChannel {
+  /// The key the device sends the reading under; the channel's own name when unset.
+  #[serde(default)]
+  pub key: Option<String>,
+  /// The channel reports `reading * scale + offset`, e.g. `scale: 100` for a drum pressure sent in hPa.
+  #[serde(default = "default_scale")]
+  pub scale: f64,
+  #[serde(default)]
+  pub offset: f64,
+}
+
+impl RegisteredChannel {
+  pub fn read(&self, value: f64) -> f64 {
+    value * self.scale + self.offset
+  }
+}
+
+fn default_scale() -> f64 {
+  1.0
+}
+
+/// The registered channels a config promotes, keyed by channel name.
+pub type ChannelRegistry = BTreeMap<String, RegisteredChannel>;
+
+/// The registered channel read from the device key `key`, with its name.
+pub fn registered<'a>(registry: &'a ChannelRegistry, key: &str) -> Option<(&'a str, &'a RegisteredChannel)> {
+  registry
+    .iter()
+    .find(|(name, channel)| channel.key.as_deref().unwrap_or(name) == key)
+    .map(|(name, channel)| (name.as_str(), channel))
+}
@@ -0,0 +1,32 @@
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// Small xorshift64* generator for simulation noise and backoff jitter, so neither needs `rand`.
+pub struct Rng {
+  state: u64,
+}
+
+impl Rng {
+  pub fn new(seed: u64) -> Self {
+    Self { state: seed.max(1) }
+  }
+
+  pub fn from_time() -> Self {
+    Self::new(SystemTime::now().duration_since(UNIX_EPOCH).map(|d| d.as_nanos() as u64).unwrap_or(1))
+  }
+
+  /// Uniform sample in `[0, 1)`.
+  pub fn next_f64(&mut self) -> f64 {
+    self.state ^= self.state >> 12;
+    self.state ^= self.state << 25;
+    self.state ^= self.state >> 27;
+    let value = self.state.wrapping_mul(0x2545_F491_4F6C_DD1D);
+    (value >> 11) as f64 / (1u64 << 53) as f64
+  }
+
+  /// Standard normal sample via Box-Muller.
+  pub fn gaussian(&mut self) -> f64 {
+    let u1 = self.next_f64().max(f64::MIN_POSITIVE);
+    let u2 = self.next_f64();
+    (-2.0 * u1.ln()).sqrt() * (2.0 * std::f64::consts::PI * u2).cos()
+  }
+}
@@ -0,0 +1,66 @@
+/// Connection state of a driver.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DriverState {
+  DISCONNECTED,
+  CONNECTING,
+  CONNECTED,
+  /// Connected, but `pause()` holds back samples; lines are still read and counted.
+  PAUSED,
+  STOPPED,
+  /// Reconnect policy exhausted; `lastError` holds the final failure. `connect()` starts over.
+  FAILED,
+}
+
+impl DriverState {
+  pub fn as_str(self) -> &'static str {
+    match self {
+      Self::DISCONNECTED => "DISCONNECTED",
+      Self::CONNECTING => "CONNECTING",
+      Self::CONNECTED => "CONNECTED",
+      Self::PAUSED => "PAUSED",
+      Self::STOPPED => "STOPPED",
+      Self::FAILED => "FAILED",
+    }
+  }
+
+  pub fn from_name(name: &str) -> Option<Self> {
+    [Self::DISCONNECTED, Self::CONNECTING, Self::CONNECTED, Self::PAUSED, Self::STOPPED, Self::FAILED]
+      .into_iter()
+      .find(|state| state.as_str() == name)
+  }
+}
+
+// Written out rather than derived with `#[napi(string_enum)]`: the derive also registers a JS enum object from a
+// constructor, which would leave napi symbols unresolved in the binding's test build. JS only sees the strings.
+#[cfg(feature = "napi")]
+mod napi_value {
+  use napi::bindgen_prelude::*;
+
+  use super::DriverState;
+
+  impl TypeName for DriverState {
+    fn type_name() -> &'static str {
+      "DriverState"
+    }
+
+    fn value_type() -> ValueType {
+      ValueType::String
+    }
+  }
+
+  impl ValidateNapiValue for DriverState {}
+
+  impl ToNapiValue for DriverState {
+    unsafe fn to_napi_value(env: sys::napi_env, state: Self) -> Result<sys::napi_value> {
+      <&str>::to_napi_value(env, state.as_str())
+    }
+  }
+
+  impl FromNapiValue for DriverState {
+    unsafe fn from_napi_value(env: sys::napi_env, value: sys::napi_value) -> Result<Self> {
+      let name = String::from_napi_value(env, value)?;
+      DriverState::from_name(&name)
+        .ok_or_else(|| Error::new(Status::InvalidArg, format!("unknown DriverState \"{}\"", name)))
+    }
+  }
+}
@@ -0,0 +1,50 @@
+//! Parser throughput for a config and a set of sample lines, for sizing hosts and catching parse regressions.
+use std::time::{Duration, Instant};
+
+use crate::parser::{ParserConfig, TcpLineParser};
+
+/// What `measure` counted, and how long the parsing took.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct Throughput {
+  /// Lines parsed: every sample line once per iteration.
+  pub lines: u64,
+  pub bytes: u64,
+  /// Lines that completed a sample.
+  pub samples: u64,
+  /// Lines that completed none, e.g. a CSV header or part of a record.
+  pub skipped: u64,
+  pub errors: u64,
+  pub elapsed: Duration,
+}
+
+impl Throughput {
+  pub fn lines_per_second(&self) -> f64 {
+    self.lines as f64 / self.elapsed.as_secs_f64().max(f64::MIN_POSITIVE)
+  }
+
+  pub fn bytes_per_second(&self) -> f64 {
+    self.bytes as f64 / self.elapsed.as_secs_f64().max(f64::MIN_POSITIVE)
+  }
+}
+
+/// Parses `lines` `iterations` times with one parser, reset before each pass as on a new connection, so a CSV header
+/// among them is read as the header every time.
+pub fn measure(config: ParserConfig, lines: &[String], iterations: u32) -> Throughput {
+  let mut parser = TcpLineParser::new(config);
+  let mut throughput = Throughput::default();
+  let started = Instant::now();
+  for _ in 0..iterations {
+    parser.reset();
+    for line in lines {
+      match parser.parse_line(line) {
+        Ok(Some(_)) => throughput.samples += 1,
+        Ok(None) => throughput.skipped += 1,
+        Err(_) => throughput.errors += 1,
+      }
+    }
+  }
+  throughput.elapsed = started.elapsed();
+  throughput.lines = lines.len() as u64 * iterations as u64;
+  throughput.bytes = lines.iter().map(|line| line.len() as u64).sum::<u64>() * iterations as u64;
+  throughput
+}
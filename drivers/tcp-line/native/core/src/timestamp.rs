@@ -0,0 +1,133 @@
+use chrono::format::{Item, StrftimeItems};
+use chrono::{DateTime, Duration, FixedOffset, Local, NaiveDate, NaiveDateTime, NaiveTime, TimeZone, Utc};
+use schemars::JsonSchema;
+use serde::Deserialize;
+
+use crate::field::Field;
+
+#[derive(Debug, Clone, PartialEq, Deserialize, JsonSchema)]
+#[serde(rename_all = "camelCase", default)]
+pub struct TimestampConfig {
+  /// `rfc3339`, `epochSeconds`, `epochMillis`, or a strftime pattern such as `%H:%M:%S` or `%Y-%m-%d %H:%M:%S`.
+  pub format: String,
+  /// Zone for patterns without an offset: `UTC`, `local` (the host's), a fixed offset like `+02:00`, or an IANA
+  /// name like `Europe/Berlin`.
+  pub timezone: String,
+}
+
+impl Default for TimestampConfig {
+  fn default() -> Self {
+    Self { format: "rfc3339".to_string(), timezone: "UTC".to_string() }
+  }
+}
+
+#[derive(Debug, Clone)]
+enum Format {
+  Rfc3339,
+  EpochSeconds,
+  EpochMillis,
+  Pattern(String),
+}
+
+#[derive(Debug, Clone)]
+enum Zone {
+  Utc,
+  Local,
+  Fixed(FixedOffset),
+  Named(chrono_tz::Tz),
+}
+
+/// A compiled `TimestampConfig`.
+#[derive(Debug, Clone)]
+pub struct TimestampParser {
+  format: Format,
+  zone: Zone,
+}
+
+impl Default for TimestampParser {
+  fn default() -> Self {
+    Self { format: Format::Rfc3339, zone: Zone::Utc }
+  }
+}
+
+impl TimestampParser {
+  /// Fails with a message naming the bad setting; reported per field by config validation.
+  pub fn new(config: &TimestampConfig) -> std::result::Result<Self, (&'static str, String)> {
+    let format = match config.format.as_str() {
+      "rfc3339" => Format::Rfc3339,
+      "epochSeconds" => Format::EpochSeconds,
+      "epochMillis" => Format::EpochMillis,
+      pattern if !pattern.contains('%') || StrftimeItems::new(pattern).any(|item| matches!(item, Item::Error)) => {
+        return Err(("format", format!("not rfc3339, epochSeconds, epochMillis or a strftime pattern: {}", pattern)));
+      }
+      pattern => Format::Pattern(pattern.to_string()),
+    };
+    let zone = match config.timezone.as_str() {
+      "UTC" | "utc" | "Z" => Zone::Utc,
+      "local" => Zone::Local,
+      name if name.starts_with(['+', '-']) => {
+        Zone::Fixed(name.parse().map_err(|_| ("timezone", format!("invalid offset: {}", name)))?)
+      }
+      name => Zone::Named(name.parse().map_err(|_| ("timezone", format!("unknown timezone: {}", name)))?),
+    };
+    Ok(Self { format, zone })
+  }
+
+  /// Parses a `ts` value. `now` anchors time-only patterns to a date: the sample is placed on whichever day puts it
+  /// within 12 hours of `now`, so lines around midnight land on the right side of it.
+  pub fn parse(&self, value: &Field<'_>, now: DateTime<Utc>) -> Option<DateTime<Utc>> {
+    match &self.format {
+      Format::Rfc3339 => DateTime::parse_from_rfc3339(value.as_str()?).ok().map(|dt| dt.with_timezone(&Utc)),
+      Format::EpochSeconds => DateTime::from_timestamp_micros((epoch_number(value)? * 1_000_000.0).round() as i64),
+      Format::EpochMillis => DateTime::from_timestamp_micros((epoch_number(value)? * 1_000.0).round() as i64),
+      Format::Pattern(pattern) => self.parse_pattern(value.as_str()?.trim(), pattern, now),
+    }
+  }
+
+  fn parse_pattern(&self, value: &str, pattern: &str, now: DateTime<Utc>) -> Option<DateTime<Utc>> {
+    if let Ok(dt) = DateTime::parse_from_str(value, pattern) {
+      return Some(dt.with_timezone(&Utc));
+    }
+    if let Ok(naive) = NaiveDateTime::parse_from_str(value, pattern) {
+      return self.localize(naive);
+    }
+    let time = NaiveTime::parse_from_str(value, pattern).ok()?;
+    let at = self.localize(self.today(now).and_time(time))?;
+    let half_day = Duration::hours(12);
+    Some(if at - now > half_day {
+      at - Duration::days(1)
+    } else if now - at > half_day {
+      at + Duration::days(1)
+    } else {
+      at
+    })
+  }
+
+  fn localize(&self, naive: NaiveDateTime) -> Option<DateTime<Utc>> {
+    // `earliest` picks the first of an ambiguous (DST fall-back) local time and rejects one that doesn't exist.
+    match &self.zone {
+      Zone::Utc => Some(naive.and_utc()),
+      Zone::Local => Local.from_local_datetime(&naive).earliest().map(|dt| dt.with_timezone(&Utc)),
+      Zone::Fixed(offset) => offset.from_local_datetime(&naive).earliest().map(|dt| dt.with_timezone(&Utc)),
+      Zone::Named(tz) => tz.from_local_datetime(&naive).earliest().map(|dt| dt.with_timezone(&Utc)),
+    }
+  }
+
+  fn today(&self, now: DateTime<Utc>) -> NaiveDate {
+    match &self.zone {
+      Zone::Utc => now.date_naive(),
+      Zone::Local => now.with_timezone(&Local).date_naive(),
+      Zone::Fixed(offset) => now.with_timezone(offset).date_naive(),
+      Zone::Named(tz) => now.with_timezone(tz).date_naive(),
+    }
+  }
+}
+
+fn epoch_number(value: &Field<'_>) -> Option<f64> {
+  match value {
+    Field::Number(n) => Some(*n),
+    Field::Text(s) => s.trim().parse::<f64>().ok(),
+    _ => None,
+  }
+  .filter(|v| v.is_finite())
+}
@@ -0,0 +1,174 @@
+use schemars::gen::SchemaSettings;
+use schemars::JsonSchema;
+use serde::de::DeserializeOwned;
+use serde_json::{Map, Value};
+
+use crate::parser::{channel_key, FrameFormat, ParserConfig, RESERVED_KEYS};
+use crate::registry::REGISTERED_CHANNELS;
+use crate::timestamp::TimestampParser;
+use crate::xml;
+
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "napi", napi_derive::napi(object))]
+pub struct ConfigIssue {
+  /// Dotted path of the offending field, e.g. `reconnect.minBackoffMs`; empty for the config as a whole.
+  pub path: String,
+  pub message: String,
+}
+
+impl ConfigIssue {
+  pub fn new(path: impl Into<String>, message: impl Into<String>) -> Self {
+    Self { path: path.into(), message: message.into() }
+  }
+
+  /// `path: message`, or the message alone for the config as a whole.
+  pub fn describe(&self) -> String {
+    if self.path.is_empty() {
+      self.message.clone()
+    } else {
+      format!("{}: {}", self.path, self.message)
+    }
+  }
+}
+
+/// Checks the parsing settings alone, as found in a `ParserConfig` or a full driver config. Keys outside the
+/// parsing sections are left alone, so a driver config can be checked as it is.
+pub fn validate_parser_config(value: &Value) -> Vec<ConfigIssue> {
+  let mut issues = Vec::new();
+  let schema = schema_value::<ParserConfig>();
+  if let (Some(properties), Value::Object(fields)) = (object_properties(&schema), value) {
+    for (key, field) in fields {
+      if let Some(field_schema) = properties.get(key) {
+        unknown_keys(field_schema, field, key, &mut issues);
+      }
+    }
+  }
+  match deserialize::<ParserConfig>(value.clone()) {
+    Ok(config) => issues.extend(config.issues()),
+    Err(issue) => issues.push(issue),
+  }
+  issues
+}
+
+/// JSON Schema (draft 7, subschemas inlined) of `T`.
+pub fn schema_value<T: JsonSchema>() -> Value {
+  let schema = SchemaSettings::draft07()
+    .with(|settings| settings.inline_subschemas = true)
+    .into_generator()
+    .into_root_schema_for::<T>();
+  serde_json::to_value(schema).unwrap_or(Value::Null)
+}
+
+/// Deserializes a config, naming the offending field on failure.
+pub fn deserialize<T: DeserializeOwned>(value: Value) -> Result<T, ConfigIssue> {
+  serde_path_to_error::deserialize(value).map_err(|err| {
+    let path = err.path().to_string();
+    let path = if path == "." { String::new() } else { path };
+    ConfigIssue::new(path, err.into_inner().to_string())
+  })
+}
+
+/// serde ignores keys it doesn't know, so a typo like `minBackofMs` would silently fall back to the default.
+pub fn unknown_keys(schema: &Value, value: &Value, path: &str, issues: &mut Vec<ConfigIssue>) {
+  let (Some(properties), Value::Object(fields)) = (object_properties(schema), value) else {
+    return;
+  };
+  for (key, field) in fields {
+    let field_path = if path.is_empty() { key.clone() } else { format!("{}.{}", path, key) };
+    match properties.get(key) {
+      Some(field_schema) => unknown_keys(field_schema, field, &field_path, issues),
+      None => issues.push(ConfigIssue::new(field_path, "unknown key")),
+    }
+  }
+}
+
+/// The `properties` of an object schema, looking through the `anyOf` schemars wraps optional objects in.
+fn object_properties(schema: &Value) -> Option<&Map<String, Value>> {
+  if let Some(properties) = schema.get("properties").and_then(Value::as_object) {
+    return Some(properties);
+  }
+  schema.get("anyOf").and_then(Value::as_array)?.iter().find_map(object_properties)
+}
+
+impl ParserConfig {
+  /// Range and consistency checks of the parsing settings, beyond what deserializing enforces.
+  pub fn issues(&self) -> Vec<ConfigIssue> {
+    let mut issues = Vec::new();
+    let mut check = |ok: bool, path: &str, message: &str| {
+      if !ok {
+        issues.push(ConfigIssue::new(path, message));
+      }
+    };
+    check(!self.csv.delimiter.is_empty(), "csv.delimiter", "must not be empty");
+    check(!self.csv.delimiter.contains('"'), "csv.delimiter", "must not contain a quote");
+    check(self.format != FrameFormat::Regex || self.regex.is_some(), "regex", "required when format is regex");
+    check(self.format != FrameFormat::Xml || self.xml.is_some(), "xml", "required when format is xml");
+    for (i, key) in self.extras.reserved.iter().enumerate() {
+      check(!key.is_empty(), &format!("extras.reserved.{}", i), "must not be empty");
+    }
+    for (from, to) in &self.extras.rename {
+      let path = format!("extras.rename.{}", from);
+      check(!to.is_empty(), &path, "must not be empty");
+      check(!RESERVED_KEYS.contains(&channel_key(to)), &path, "must not be a channel key");
+    }
+    for (i, probe) in self.probes.sensors.iter().enumerate() {
+      let path = format!("probes.sensors.{}", i);
+      let key = channel_key(&probe.key);
+      check(!probe.key.is_empty(), &format!("{}.key", path), "must not be empty");
+      check(
+        !RESERVED_KEYS.contains(&key) || key == "btC" || key == "etC",
+        &format!("{}.key", path),
+        "must not be ts or a channel other than btC and etC",
+      );
+      let repeated = self.probes.sensors[..i].iter().any(|other| other.key == probe.key);
+      check(!repeated, &format!("{}.key", path), "must be unique");
+      check(probe.gain.is_finite() && probe.gain != 0.0, &format!("{}.gain", path), "must be finite and non-zero");
+      check(probe.offset.is_finite(), &format!("{}.offset", path), "must be finite");
+    }
+    for (i, (name, channel)) in self.channel_registry.iter().enumerate() {
+      let path = format!("channelRegistry.{}", name);
+      let key = channel.key.as_deref().unwrap_or(name);
+      let known = REGISTERED_CHANNELS.contains(&name.as_str());
+      check(known, &path, "must be exhaustC, inletC, drumPressurePa or airflowPct");
+      check(!key.is_empty(), &format!("{}.key", path), "must not be empty");
+      check(!RESERVED_KEYS.contains(&channel_key(key)), &format!("{}.key", path), "must not be ts or a channel key");
+      check(self.probes.find(key).is_none(), &format!("{}.key", path), "is already a probe's key");
+      let taken = self.channel_registry.iter().take(i).any(|(name, other)| other.key.as_deref().unwrap_or(name) == key);
+      check(!taken, &format!("{}.key", path), "is already another channel's key");
+      let scale_ok = channel.scale.is_finite() && channel.scale != 0.0;
+      check(scale_ok, &format!("{}.scale", path), "must be finite and non-zero");
+      check(channel.offset.is_finite(), &format!("{}.offset", path), "must be finite");
+    }
+    if let Some(assembly) = &self.assembly {
+      check(
+        assembly.start_marker.is_some() || assembly.end_marker.is_some() || assembly.line_count.is_some(),
+        "assembly",
+        "needs startMarker, endMarker or lineCount",
+      );
+      check(assembly.start_marker.as_deref() != Some(""), "assembly.startMarker", "must not be empty");
+      check(assembly.end_marker.as_deref() != Some(""), "assembly.endMarker", "must not be empty");
+      check(assembly.line_count != Some(0), "assembly.lineCount", "must be positive");
+      check(assembly.max_lines > 0, "assembly.maxLines", "must be positive");
+    }
+    if let Some(flatten) = &self.json.flatten {
+      check(!flatten.separator.is_empty(), "json.flatten.separator", "must not be empty");
+      check(flatten.max_depth > 0, "json.flatten.maxDepth", "must be positive");
+    }
+    for (field, path) in &self.json.fields {
+      check(!path.is_empty(), &format!("json.fields.{}", field), "must not be empty");
+    }
+    if let Some(xml) = &self.xml {
+      check(!xml.fields.is_empty(), "xml.fields", "must map at least one field");
+      if let Err((field, message)) = xml::compile(xml) {
+        issues.push(ConfigIssue::new(format!("xml.fields.{}", field), message));
+      }
+    }
+    if let Some(Err(message)) = self.regex.as_ref().map(|regex| regex.compile()) {
+      issues.push(ConfigIssue::new("regex.pattern", message));
+    }
+    if let Err((field, message)) = TimestampParser::new(&self.timestamp) {
+      issues.push(ConfigIssue::new(format!("timestamp.{}", field), message));
+    }
+    issues
+  }
+}
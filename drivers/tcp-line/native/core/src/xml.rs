@@ -0,0 +1,80 @@
+use std::collections::BTreeMap;
+
+use roxmltree::{Document, Node};
+use schemars::JsonSchema;
+use serde::Deserialize;
+
+use crate::field::{Field, Fields};
+
+#[derive(Debug, Clone, Default, PartialEq, Deserialize, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct XmlConfig {
+  /// Field name (`btC`, `etC`, ..., `ts`, or an extra) to the path of the element or attribute holding it, e.g.
+  /// `/reading/temps/bt` (element text) or `/reading/@ts` (attribute). A `*` step matches any element.
+  pub fields: BTreeMap<String, String>,
+}
+
+/// One compiled `XmlConfig` path: element steps from the root, optionally ending in an attribute.
+#[derive(Debug, Clone)]
+pub struct XmlPath {
+  steps: Vec<String>,
+  attribute: Option<String>,
+}
+
+impl XmlPath {
+  pub fn parse(path: &str) -> Result<Self, String> {
+    let mut steps = path.strip_prefix('/').unwrap_or(path).split('/').map(str::to_string).collect::<Vec<_>>();
+    let attribute = match steps.last().and_then(|step| step.strip_prefix('@')) {
+      Some(name) => {
+        let name = name.to_string();
+        steps.pop();
+        Some(name)
+      }
+      None => None,
+    };
+    if steps.is_empty() || steps.iter().chain(attribute.iter()).any(|step| step.is_empty() || step.contains('@')) {
+      return Err(format!("invalid path: {}", path));
+    }
+    Ok(Self { steps, attribute })
+  }
+
+  /// The trimmed text or attribute value at this path, taking the first match of each step.
+  fn select(&self, document: &Document) -> Option<String> {
+    let root = document.root_element();
+    if !step_matches(&root, &self.steps[0]) {
+      return None;
+    }
+    let mut node = root;
+    for step in &self.steps[1..] {
+      node = node.children().find(|child| child.is_element() && step_matches(child, step))?;
+    }
+    let value = match &self.attribute {
+      Some(name) => node.attribute(name.as_str())?,
+      None => node.text()?,
+    };
+    Some(value.trim().to_string()).filter(|value| !value.is_empty())
+  }
+}
+
+fn step_matches(node: &Node, step: &str) -> bool {
+  step == "*" || node.tag_name().name() == step
+}
+
+pub fn compile(config: &XmlConfig) -> Result<Vec<(String, XmlPath)>, (String, String)> {
+  config
+    .fields
+    .iter()
+    .map(|(field, path)| XmlPath::parse(path).map(|path| (field.clone(), path)).map_err(|err| (field.clone(), err)))
+    .collect()
+}
+
+/// The mapped fields present in one XML document; `None` if the line isn't well-formed XML.
+pub fn extract<'a>(line: &str, paths: &'a [(String, XmlPath)]) -> Option<Fields<'a>> {
+  let document = Document::parse(line).ok()?;
+  Some(
+    paths
+      .iter()
+      .filter_map(|(field, path)| Some((field.as_str().into(), Field::Text(path.select(&document)?.into()))))
+      .collect(),
+  )
+}
@@ -0,0 +1,88 @@
+/*
+ * C API of the tcp-line driver (libtcp_line, built from drivers/tcp-line/native/ffi).
+ *
+ * Mirrors ffi/src/lib.rs; see that file for the details of each call. Every call is synchronous and may come from
+ * any thread. Callbacks run on the library's own I/O threads, must return quickly, and must not call
+ * tcp_line_driver_connect, tcp_line_driver_disconnect or tcp_line_driver_destroy.
+ */
+#ifndef TCP_LINE_H
+#define TCP_LINE_H
+
+#include <stddef.h>
+#include <stdint.h>
+
+#ifdef __cplusplus
+extern "C" {
+#endif
+
+/* Compare with tcp_line_abi_version() at startup; the struct layouts below only change with it. */
+#define TCP_LINE_ABI_VERSION 1
+
+#define TCP_LINE_OK 0
+#define TCP_LINE_NO_DATA 1
+#define TCP_LINE_ERR_INVALID_ARGUMENT (-1)
+#define TCP_LINE_ERR_CONNECT (-2)
+#define TCP_LINE_ERR_PANIC (-3)
+
+#define TCP_LINE_STATE_DISCONNECTED 0
+#define TCP_LINE_STATE_CONNECTING 1
+#define TCP_LINE_STATE_CONNECTED 2
+#define TCP_LINE_STATE_PAUSED 3
+#define TCP_LINE_STATE_STOPPED 4
+#define TCP_LINE_STATE_FAILED 5
+
+typedef struct TcpLineDriver TcpLineDriver;
+
+/* A sample; channels the device didn't report are NaN. */
+typedef struct TcpLineTelemetry {
+  int64_t ts_ms; /* Unix milliseconds */
+  double elapsed_seconds;
+  double bt_c;
+  double et_c;
+  double gas_pct;
+  double fan_pct;
+  double drum_rpm;
+} TcpLineTelemetry;
+
+typedef struct TcpLineStatus {
+  int32_t state; /* TCP_LINE_STATE_* */
+  int64_t lines_received;
+  int64_t lines_parsed;
+  int64_t parse_errors;
+  int64_t checksum_errors;
+  int64_t telemetry_emitted;
+  int64_t reconnects;
+  int64_t bytes_received;
+} TcpLineStatus;
+
+typedef void (*TcpLineTelemetryCallback)(const TcpLineTelemetry *point, void *user_data);
+typedef void (*TcpLineStateCallback)(int32_t state, void *user_data);
+
+uint32_t tcp_line_abi_version(void);
+
+/* NULL on an invalid config, with the reason in error (may be NULL). */
+TcpLineDriver *tcp_line_driver_create(const char *config_json, const char *machine_id, char *error,
+                                      size_t error_len);
+void tcp_line_driver_destroy(TcpLineDriver *driver);
+
+/* Blocks until connected. */
+int32_t tcp_line_driver_connect(TcpLineDriver *driver);
+/* Starts connecting and returns at once. */
+int32_t tcp_line_driver_start(TcpLineDriver *driver);
+int32_t tcp_line_driver_disconnect(TcpLineDriver *driver);
+
+/* The latest sample, without waiting; TCP_LINE_NO_DATA before the first one. */
+int32_t tcp_line_driver_poll(TcpLineDriver *driver, TcpLineTelemetry *out);
+int32_t tcp_line_driver_status(TcpLineDriver *driver, TcpLineStatus *out);
+/* Full length of the last error (0 if none); copies it into buf, truncated and NUL-terminated. */
+size_t tcp_line_driver_last_error(TcpLineDriver *driver, char *buf, size_t len);
+
+/* Either callback may be NULL; both NULL removes them. */
+int32_t tcp_line_driver_set_callbacks(TcpLineDriver *driver, TcpLineTelemetryCallback on_telemetry,
+                                      TcpLineStateCallback on_state, void *user_data);
+
+#ifdef __cplusplus
+}
+#endif
+
+#endif /* TCP_LINE_H */
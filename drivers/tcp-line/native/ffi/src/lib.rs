@@ -0,0 +1,327 @@
+//! C API of the tcp-line driver, for embedding telemetry collection in native applications (e.g. a C++ HMI). It runs
+//! the core crate's standalone client, so it covers one `host:port` endpoint per driver with the Node driver's
+//! parsing and reconnect settings. `include/tcp_line.h` declares everything here and is kept in step by hand; the
+//! layouts of `TcpLineTelemetry` and `TcpLineStatus` only change together with `TCP_LINE_ABI_VERSION`.
+//!
+//! Every call is synchronous and may come from any thread. The I/O runs on a tokio runtime shared by all drivers,
+//! whose worker threads also run the callbacks.
+
+use std::ffi::{c_char, c_void, CStr};
+use std::panic::{catch_unwind, AssertUnwindSafe};
+use std::sync::{Arc, OnceLock};
+
+use tcp_line_core::client::{self, Driver, DriverEvent, Point};
+use tcp_line_core::DriverState;
+use tokio::runtime::Runtime;
+
+pub const TCP_LINE_ABI_VERSION: u32 = 1;
+
+pub const TCP_LINE_OK: i32 = 0;
+/// `tcp_line_driver_poll` before the first sample.
+pub const TCP_LINE_NO_DATA: i32 = 1;
+pub const TCP_LINE_ERR_INVALID_ARGUMENT: i32 = -1;
+/// The connect attempt failed, the reconnect policy is exhausted, or the driver was stopped meanwhile.
+pub const TCP_LINE_ERR_CONNECT: i32 = -2;
+/// A Rust panic was caught at the boundary, e.g. a blocking call made from a callback.
+pub const TCP_LINE_ERR_PANIC: i32 = -3;
+
+pub type TcpLineTelemetryCallback = extern "C" fn(point: *const TcpLineTelemetry, user_data: *mut c_void);
+pub type TcpLineStateCallback = extern "C" fn(state: i32, user_data: *mut c_void);
+
+/// Opaque driver handle.
+pub struct TcpLineDriver {
+  driver: Arc<Driver>,
+}
+
+/// A sample; channels the device didn't report are NaN. Extras stay with the Node and Python bindings.
+#[repr(C)]
+pub struct TcpLineTelemetry {
+  /// Unix milliseconds.
+  pub ts_ms: i64,
+  pub elapsed_seconds: f64,
+  pub bt_c: f64,
+  pub et_c: f64,
+  pub gas_pct: f64,
+  pub fan_pct: f64,
+  pub drum_rpm: f64,
+}
+
+impl From<&Point> for TcpLineTelemetry {
+  fn from(point: &Point) -> Self {
+    let channel = |value: Option<f64>| value.unwrap_or(f64::NAN);
+    Self {
+      ts_ms: point.ts_ms,
+      elapsed_seconds: point.elapsed_seconds,
+      bt_c: channel(point.bt_c),
+      et_c: channel(point.et_c),
+      gas_pct: channel(point.gas_pct),
+      fan_pct: channel(point.fan_pct),
+      drum_rpm: channel(point.drum_rpm),
+    }
+  }
+}
+
+/// The connection state (a `TCP_LINE_STATE_*` value) and counters.
+#[repr(C)]
+pub struct TcpLineStatus {
+  pub state: i32,
+  pub lines_received: i64,
+  pub lines_parsed: i64,
+  pub parse_errors: i64,
+  pub checksum_errors: i64,
+  pub telemetry_emitted: i64,
+  pub reconnects: i64,
+  pub bytes_received: i64,
+}
+
+/// The `TCP_LINE_STATE_*` value of a state, in declaration order.
+fn state_code(state: DriverState) -> i32 {
+  match state {
+    DriverState::DISCONNECTED => 0,
+    DriverState::CONNECTING => 1,
+    DriverState::CONNECTED => 2,
+    DriverState::PAUSED => 3,
+    DriverState::STOPPED => 4,
+    DriverState::FAILED => 5,
+  }
+}
+
+/// The C caller's context pointer, handed back untouched on the runtime threads.
+#[derive(Clone, Copy)]
+struct UserData(*mut c_void);
+
+// The header documents that callbacks run on other threads; what the pointer refers to is the caller's concern.
+unsafe impl Send for UserData {}
+unsafe impl Sync for UserData {}
+
+fn runtime() -> &'static Runtime {
+  static RUNTIME: OnceLock<Runtime> = OnceLock::new();
+  RUNTIME.get_or_init(|| {
+    tokio::runtime::Builder::new_multi_thread()
+      .thread_name("tcp-line")
+      .enable_all()
+      .build()
+      .expect("failed to start the tcp-line runtime")
+  })
+}
+
+/// Runs `f`, turning a panic into `TCP_LINE_ERR_PANIC` so it doesn't unwind into C.
+fn guard(f: impl FnOnce() -> i32) -> i32 {
+  catch_unwind(AssertUnwindSafe(f)).unwrap_or(TCP_LINE_ERR_PANIC)
+}
+
+/// Copies `text` into a caller buffer of `len` bytes, truncating and NUL-terminating it. Returns the full length.
+unsafe fn write_text(text: &str, buf: *mut c_char, len: usize) -> usize {
+  if !buf.is_null() && len > 0 {
+    let copied = text.len().min(len - 1);
+    std::ptr::copy_nonoverlapping(text.as_ptr().cast::<c_char>(), buf, copied);
+    *buf.add(copied) = 0;
+  }
+  text.len()
+}
+
+unsafe fn read_text<'a>(text: *const c_char) -> Option<&'a str> {
+  if text.is_null() {
+    return None;
+  }
+  CStr::from_ptr(text).to_str().ok()
+}
+
+#[no_mangle]
+pub extern "C" fn tcp_line_abi_version() -> u32 {
+  TCP_LINE_ABI_VERSION
+}
+
+/// Creates a driver from a driver config in JSON, as the Node driver takes it; only `host`, `port`, `reconnect`,
+/// `connectTimeoutMs` and the parsing settings apply. Returns NULL on an invalid config or argument, with the
+/// reason in `error` (NUL-terminated, truncated to `error_len` bytes) when it is given.
+///
+/// # Safety
+/// `config_json` and `machine_id` must be NUL-terminated strings; `error` is NULL or points to `error_len` bytes.
+#[no_mangle]
+pub unsafe extern "C" fn tcp_line_driver_create(
+  config_json: *const c_char,
+  machine_id: *const c_char,
+  error: *mut c_char,
+  error_len: usize,
+) -> *mut TcpLineDriver {
+  let created = catch_unwind(|| {
+    let (Some(config_json), Some(machine_id)) = (read_text(config_json), read_text(machine_id)) else {
+      return Err("config_json and machine_id must be UTF-8 strings".to_string());
+    };
+    let config = client::parse_config(config_json)?;
+    Ok(Driver::new(config, machine_id.to_string()))
+  });
+  match created {
+    Ok(Ok(driver)) => Box::into_raw(Box::new(TcpLineDriver { driver })),
+    Ok(Err(message)) => {
+      write_text(&message, error, error_len);
+      std::ptr::null_mut()
+    }
+    Err(_) => {
+      write_text("panic while creating the driver", error, error_len);
+      std::ptr::null_mut()
+    }
+  }
+}
+
+/// Disconnects and frees a driver. No callback runs once it returns, and the handle must not be used again.
+///
+/// # Safety
+/// `driver` is NULL or a handle from `tcp_line_driver_create` not yet destroyed; not to be called from a callback.
+#[no_mangle]
+pub unsafe extern "C" fn tcp_line_driver_destroy(driver: *mut TcpLineDriver) {
+  if driver.is_null() {
+    return;
+  }
+  let handle = Box::from_raw(driver);
+  let _ = catch_unwind(AssertUnwindSafe(|| {
+    handle.driver.set_listener(None);
+    runtime().block_on(handle.driver.disconnect());
+  }));
+}
+
+/// Connects, blocking until the connection is up. Without reconnects a failed attempt returns
+/// `TCP_LINE_ERR_CONNECT`; with them, only an exhausted reconnect policy does.
+///
+/// # Safety
+/// `driver` is a live handle; not to be called from a callback.
+#[no_mangle]
+pub unsafe extern "C" fn tcp_line_driver_connect(driver: *mut TcpLineDriver) -> i32 {
+  let Some(handle) = driver.as_ref() else {
+    return TCP_LINE_ERR_INVALID_ARGUMENT;
+  };
+  guard(|| match runtime().block_on(handle.driver.connect()) {
+    Ok(()) => TCP_LINE_OK,
+    Err(_) => TCP_LINE_ERR_CONNECT,
+  })
+}
+
+/// Starts connecting and returns at once; follow the outcome with the state callback or `tcp_line_driver_status`.
+///
+/// # Safety
+/// `driver` is a live handle.
+#[no_mangle]
+pub unsafe extern "C" fn tcp_line_driver_start(driver: *mut TcpLineDriver) -> i32 {
+  let Some(handle) = driver.as_ref() else {
+    return TCP_LINE_ERR_INVALID_ARGUMENT;
+  };
+  let driver = Arc::clone(&handle.driver);
+  guard(|| {
+    runtime().spawn(async move {
+      let _ = driver.connect().await;
+    });
+    TCP_LINE_OK
+  })
+}
+
+/// Closes the connection and stops reconnecting; blocks until the connection loop has ended.
+///
+/// # Safety
+/// `driver` is a live handle; not to be called from a callback.
+#[no_mangle]
+pub unsafe extern "C" fn tcp_line_driver_disconnect(driver: *mut TcpLineDriver) -> i32 {
+  let Some(handle) = driver.as_ref() else {
+    return TCP_LINE_ERR_INVALID_ARGUMENT;
+  };
+  guard(|| {
+    runtime().block_on(handle.driver.disconnect());
+    TCP_LINE_OK
+  })
+}
+
+/// Writes the latest sample to `out` without waiting. Returns `TCP_LINE_NO_DATA` before the first one.
+///
+/// # Safety
+/// `driver` is a live handle and `out` points to a `TcpLineTelemetry`.
+#[no_mangle]
+pub unsafe extern "C" fn tcp_line_driver_poll(driver: *mut TcpLineDriver, out: *mut TcpLineTelemetry) -> i32 {
+  let (Some(handle), false) = (driver.as_ref(), out.is_null()) else {
+    return TCP_LINE_ERR_INVALID_ARGUMENT;
+  };
+  guard(|| match handle.driver.latest() {
+    Some(point) => {
+      out.write(TcpLineTelemetry::from(&point));
+      TCP_LINE_OK
+    }
+    None => TCP_LINE_NO_DATA,
+  })
+}
+
+/// Writes the connection state and counters to `out`.
+///
+/// # Safety
+/// `driver` is a live handle and `out` points to a `TcpLineStatus`.
+#[no_mangle]
+pub unsafe extern "C" fn tcp_line_driver_status(driver: *mut TcpLineDriver, out: *mut TcpLineStatus) -> i32 {
+  let (Some(handle), false) = (driver.as_ref(), out.is_null()) else {
+    return TCP_LINE_ERR_INVALID_ARGUMENT;
+  };
+  guard(|| {
+    let (state, metrics) = handle.driver.status();
+    out.write(TcpLineStatus {
+      state: state_code(state),
+      lines_received: metrics.linesReceived,
+      lines_parsed: metrics.linesParsed,
+      parse_errors: metrics.parseErrors,
+      checksum_errors: metrics.checksumErrors,
+      telemetry_emitted: metrics.telemetryEmitted,
+      reconnects: metrics.reconnects,
+      bytes_received: metrics.bytesReceived,
+    });
+    TCP_LINE_OK
+  })
+}
+
+/// Copies the last connection or parse error into `buf` like `tcp_line_driver_create` does, and returns its full
+/// length in bytes; 0 when there was none.
+///
+/// # Safety
+/// `driver` is a live handle; `buf` is NULL or points to `len` bytes.
+#[no_mangle]
+pub unsafe extern "C" fn tcp_line_driver_last_error(driver: *mut TcpLineDriver, buf: *mut c_char, len: usize) -> usize {
+  let Some(handle) = driver.as_ref() else {
+    return 0;
+  };
+  let (_, metrics) = handle.driver.status();
+  write_text(metrics.lastError.as_deref().unwrap_or(""), buf, len)
+}
+
+/// Sets the callbacks for every accepted sample and every state change, replacing earlier ones; either may be
+/// NULL. They run on the runtime's threads with `user_data`, must return quickly, and must not call
+/// `tcp_line_driver_connect`, `_disconnect` or `_destroy`. While callbacks are set, every sample counts as emitted.
+///
+/// # Safety
+/// `driver` is a live handle; `user_data` stays valid until the callbacks are replaced or the driver destroyed.
+#[no_mangle]
+pub unsafe extern "C" fn tcp_line_driver_set_callbacks(
+  driver: *mut TcpLineDriver,
+  on_telemetry: Option<TcpLineTelemetryCallback>,
+  on_state: Option<TcpLineStateCallback>,
+  user_data: *mut c_void,
+) -> i32 {
+  let Some(handle) = driver.as_ref() else {
+    return TCP_LINE_ERR_INVALID_ARGUMENT;
+  };
+  if on_telemetry.is_none() && on_state.is_none() {
+    handle.driver.set_listener(None);
+    return TCP_LINE_OK;
+  }
+  let user_data = UserData(user_data);
+  handle.driver.set_listener(Some(Arc::new(move |event: DriverEvent<'_>| {
+    let context = user_data;
+    match event {
+      DriverEvent::State(state) => {
+        if let Some(on_state) = on_state {
+          on_state(state_code(state), context.0);
+        }
+      }
+      DriverEvent::Telemetry(point) => {
+        if let Some(on_telemetry) = on_telemetry {
+          on_telemetry(&TcpLineTelemetry::from(point), context.0);
+        }
+      }
+    }
+  })));
+  TCP_LINE_OK
+}
@@ -0,0 +1,160 @@
+//! Python bindings for the tcp-line driver, for notebooks and analytics jobs that want the same parsing and
+//! reconnect behaviour as the Node gateway. Built with maturin; the module is only linked as a Python extension with
+//! the `extension-module` feature. The coroutines run on a tokio runtime shared by every driver, so they can be
+//! awaited from any asyncio loop.
+
+use std::sync::Arc;
+
+use pyo3::exceptions::{PyConnectionError, PyRuntimeError, PyValueError};
+use pyo3::prelude::*;
+use tcp_line_core::client::{self, Driver, Point};
+use tcp_line_core::{DriverMetrics, ExtraEntry};
+
+/// `TcpLineDriver(config_json, machine_id)`: one device connection. `config_json` is a driver config as the Node
+/// driver takes it; only `host`, `port`, `reconnect`, `connectTimeoutMs` and the parsing settings apply.
+#[pyclass(module = "tcp_line", frozen)]
+struct TcpLineDriver {
+  inner: Arc<Driver>,
+}
+
+#[pymethods]
+impl TcpLineDriver {
+  #[new]
+  fn new(config_json: &str, machine_id: String) -> PyResult<Self> {
+    let config = client::parse_config(config_json).map_err(PyValueError::new_err)?;
+    Ok(Self { inner: Driver::new(config, machine_id) })
+  }
+
+  /// Awaitable; resolves once connected. Raises `ConnectionError` when the attempt fails without reconnects, or
+  /// when the reconnect policy is exhausted.
+  fn connect<'py>(&self, py: Python<'py>) -> PyResult<Bound<'py, PyAny>> {
+    let inner = Arc::clone(&self.inner);
+    pyo3_async_runtimes::tokio::future_into_py(py, async move {
+      inner.connect().await.map_err(PyConnectionError::new_err)
+    })
+  }
+
+  /// Awaitable; closes the connection and stops reconnecting.
+  fn disconnect<'py>(&self, py: Python<'py>) -> PyResult<Bound<'py, PyAny>> {
+    let inner = Arc::clone(&self.inner);
+    pyo3_async_runtimes::tokio::future_into_py(py, async move {
+      inner.disconnect().await;
+      Ok(())
+    })
+  }
+
+  /// Awaitable; the latest sample as a `TelemetryPoint`. Raises `RuntimeError` when none arrived within 500 ms.
+  fn read_telemetry<'py>(&self, py: Python<'py>) -> PyResult<Bound<'py, PyAny>> {
+    let inner = Arc::clone(&self.inner);
+    pyo3_async_runtimes::tokio::future_into_py(py, async move {
+      let point = inner.read_telemetry().await.map_err(PyRuntimeError::new_err)?;
+      Ok(TelemetryPoint::from(point))
+    })
+  }
+
+  /// The connection state and counters.
+  fn status(&self) -> DriverStatus {
+    let (state, metrics) = self.inner.status();
+    DriverStatus { state: state.as_str().to_string(), metrics: Metrics::from(metrics) }
+  }
+}
+
+#[pyclass(module = "tcp_line", frozen, get_all)]
+#[derive(Clone)]
+struct TelemetryPoint {
+  ts: String,
+  machine_id: String,
+  elapsed_seconds: f64,
+  bt_c: Option<f64>,
+  et_c: Option<f64>,
+  gas_pct: Option<f64>,
+  fan_pct: Option<f64>,
+  drum_rpm: Option<f64>,
+  extras: Vec<Extra>,
+}
+
+impl From<Point> for TelemetryPoint {
+  fn from(point: Point) -> Self {
+    Self {
+      ts: point.ts,
+      machine_id: point.machine_id,
+      elapsed_seconds: point.elapsed_seconds,
+      bt_c: point.bt_c,
+      et_c: point.et_c,
+      gas_pct: point.gas_pct,
+      fan_pct: point.fan_pct,
+      drum_rpm: point.drum_rpm,
+      extras: point.extras.into_iter().map(Extra::from).collect(),
+    }
+  }
+}
+
+/// A non-channel field of a sample; exactly one of `number_value`, `text_value` and `bool_value` is set.
+#[pyclass(module = "tcp_line", frozen, get_all)]
+#[derive(Clone)]
+struct Extra {
+  key: String,
+  number_value: Option<f64>,
+  text_value: Option<String>,
+  bool_value: Option<bool>,
+}
+
+impl From<ExtraEntry> for Extra {
+  fn from(entry: ExtraEntry) -> Self {
+    Self {
+      key: entry.key,
+      number_value: entry.number_value,
+      text_value: entry.text_value,
+      bool_value: entry.bool_value,
+    }
+  }
+}
+
+#[pyclass(module = "tcp_line", frozen, get_all)]
+#[derive(Clone)]
+struct DriverStatus {
+  /// `DISCONNECTED`, `CONNECTING`, `CONNECTED`, `STOPPED` or `FAILED`.
+  state: String,
+  metrics: Metrics,
+}
+
+/// The counters this binding maintains, named like the Node driver's metrics in snake case.
+#[pyclass(module = "tcp_line", frozen, get_all)]
+#[derive(Clone)]
+struct Metrics {
+  lines_received: i64,
+  lines_parsed: i64,
+  parse_errors: i64,
+  checksum_errors: i64,
+  telemetry_emitted: i64,
+  reconnects: i64,
+  bytes_received: i64,
+  last_error: Option<String>,
+  last_line_at: Option<String>,
+}
+
+impl From<DriverMetrics> for Metrics {
+  fn from(metrics: DriverMetrics) -> Self {
+    Self {
+      lines_received: metrics.linesReceived,
+      lines_parsed: metrics.linesParsed,
+      parse_errors: metrics.parseErrors,
+      checksum_errors: metrics.checksumErrors,
+      telemetry_emitted: metrics.telemetryEmitted,
+      reconnects: metrics.reconnects,
+      bytes_received: metrics.bytesReceived,
+      last_error: metrics.lastError,
+      last_line_at: metrics.lastLineAt,
+    }
+  }
+}
+
+#[pymodule]
+fn tcp_line(m: &Bound<'_, PyModule>) -> PyResult<()> {
+  m.add_class::<TcpLineDriver>()?;
+  m.add_class::<TelemetryPoint>()?;
+  m.add_class::<Extra>()?;
+  m.add_class::<DriverStatus>()?;
+  m.add_class::<Metrics>()?;
+  Ok(())
+}
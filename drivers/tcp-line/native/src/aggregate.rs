@@ -0,0 +1,158 @@
+use std::collections::VecDeque;
+
+use chrono::{DateTime, SecondsFormat, Utc};
+use napi_derive::napi;
+
+use crate::downsample::Channel;
+use crate::RawTelemetrySample;
+
+/// Samples kept for `readAggregate`; windows reaching further back only cover what is retained.
+const HISTORY_SAMPLES: usize = 8192;
+
+#[derive(Debug, Clone)]
+#[napi(object)]
+pub struct ChannelAggregate {
+  pub min: f64,
+  pub max: f64,
+  pub mean: f64,
+  /// Samples in the window that carried this channel.
+  pub count: u32,
+}
+
+#[derive(Debug, Clone)]
+#[napi(object)]
+pub struct ExtraAggregate {
+  pub key: String,
+  pub min: f64,
+  pub max: f64,
+  pub mean: f64,
+  pub count: u32,
+}
+
+/// Per-channel statistics over the samples of the last `windowSeconds`; channels absent from every sample are unset.
+#[derive(Debug, Clone)]
+#[napi(object)]
+pub struct TelemetryAggregate {
+  pub machineId: String,
+  pub windowSeconds: f64,
+  pub sampleCount: u32,
+  /// Timestamps of the oldest and newest sample in the window; absent when it is empty.
+  pub from: Option<String>,
+  pub to: Option<String>,
+  pub btC: Option<ChannelAggregate>,
+  pub etC: Option<ChannelAggregate>,
+  pub gasPct: Option<ChannelAggregate>,
+  pub fanPct: Option<ChannelAggregate>,
+  pub drumRpm: Option<ChannelAggregate>,
+  pub ambientC: Option<ChannelAggregate>,
+  pub humidityPct: Option<ChannelAggregate>,
+  pub pressureHpa: Option<ChannelAggregate>,
+  pub exhaustC: Option<ChannelAggregate>,
+  pub inletC: Option<ChannelAggregate>,
+  pub drumPressurePa: Option<ChannelAggregate>,
+  pub airflowPct: Option<ChannelAggregate>,
+  /// Numeric extras only.
+  pub extras: Vec<ExtraAggregate>,
+}
+
+/// The most recent accepted samples, raw (before downsampling).
+#[derive(Default)]
+pub(crate) struct SampleHistory {
+  samples: VecDeque<RawTelemetrySample>,
+}
+
+impl SampleHistory {
+  pub fn record(&mut self, sample: RawTelemetrySample) {
+    if self.samples.len() == HISTORY_SAMPLES {
+      self.samples.pop_front();
+    }
+    self.samples.push_back(sample);
+  }
+
+  pub fn reset(&mut self) {
+    self.samples.clear();
+  }
+
+  /// Aggregates the samples within `window_seconds` of the newest one, by sample timestamp.
+  pub fn aggregate(&self, machine_id: &str, window_seconds: f64) -> TelemetryAggregate {
+    let window = self.samples.back().map(|newest| {
+      let span = chrono::Duration::try_milliseconds((window_seconds * 1000.0) as i64).unwrap_or(chrono::Duration::MAX);
+      // Device timestamps can sit near the end of the representable range.
+      let cutoff = newest.ts.checked_sub_signed(span).unwrap_or(DateTime::<Utc>::MIN_UTC);
+      self.samples.iter().filter(move |sample| sample.ts > cutoff && sample.ts <= newest.ts)
+    });
+    let mut bt_c = Channel::default();
+    let mut et_c = Channel::default();
+    let mut power_pct = Channel::default();
+    let mut fan_pct = Channel::default();
+    let mut drum_rpm = Channel::default();
+    let mut ambient_c = Channel::default();
+    let mut humidity_pct = Channel::default();
+    let mut pressure_hpa = Channel::default();
+    let mut exhaust_c = Channel::default();
+    let mut inlet_c = Channel::default();
+    let mut drum_pressure_pa = Channel::default();
+    let mut airflow_pct = Channel::default();
+    let mut extras: Vec<(String, Channel)> = Vec::new();
+    let mut count = 0;
+    let mut span: Option<(DateTime<Utc>, DateTime<Utc>)> = None;
+    for sample in window.into_iter().flatten() {
+      count += 1;
+      span = Some(span.map_or((sample.ts, sample.ts), |(from, to)| (from.min(sample.ts), to.max(sample.ts))));
+      bt_c.add(sample.bt_c);
+      et_c.add(sample.et_c);
+      power_pct.add(sample.power_pct);
+      fan_pct.add(sample.fan_pct);
+      drum_rpm.add(sample.drum_rpm);
+      ambient_c.add(sample.ambient_c);
+      humidity_pct.add(sample.humidity_pct);
+      pressure_hpa.add(sample.pressure_hpa);
+      exhaust_c.add(sample.exhaust_c);
+      inlet_c.add(sample.inlet_c);
+      drum_pressure_pa.add(sample.drum_pressure_pa);
+      airflow_pct.add(sample.airflow_pct);
+      for entry in sample.extras.iter().flatten().filter(|entry| entry.number_value.is_some()) {
+        match extras.iter_mut().find(|(key, _)| *key == entry.key) {
+          Some((_, channel)) => channel.add(entry.number_value),
+          None => {
+            let mut channel = Channel::default();
+            channel.add(entry.number_value);
+            extras.push((entry.key.clone(), channel));
+          }
+        }
+      }
+    }
+    let format = |ts: DateTime<Utc>| ts.to_rfc3339_opts(SecondsFormat::Millis, true);
+    TelemetryAggregate {
+      machineId: machine_id.to_string(),
+      windowSeconds: window_seconds,
+      sampleCount: count,
+      from: span.map(|(from, _)| format(from)),
+      to: span.map(|(_, to)| format(to)),
+      btC: bt_c.summary(),
+      etC: et_c.summary(),
+      gasPct: power_pct.summary(),
+      fanPct: fan_pct.summary(),
+      drumRpm: drum_rpm.summary(),
+      ambientC: ambient_c.summary(),
+      humidityPct: humidity_pct.summary(),
+      pressureHpa: pressure_hpa.summary(),
+      exhaustC: exhaust_c.summary(),
+      inletC: inlet_c.summary(),
+      drumPressurePa: drum_pressure_pa.summary(),
+      airflowPct: airflow_pct.summary(),
+      extras: extras
+        .into_iter()
+        .filter_map(|(key, channel)| {
+          channel.summary().map(|summary| ExtraAggregate {
+            key,
+            min: summary.min,
+            max: summary.max,
+            mean: summary.mean,
+            count: summary.count,
+          })
+        })
+        .collect(),
+    }
+  }
+}
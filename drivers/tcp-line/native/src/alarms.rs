@@ -0,0 +1,266 @@
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use chrono::{DateTime, SecondsFormat, Utc};
+use napi_derive::napi;
+use schemars::JsonSchema;
+use serde::Deserialize;
+use tokio::task::JoinHandle;
+
+use crate::{channels, DriverInner, RawTelemetrySample};
+
+/// How often the watchdog looks for a `staleDataAlarmMs` that was set after it started.
+const IDLE_POLL: Duration = Duration::from_secs(1);
+
+#[derive(Debug, Clone)]
+#[napi(object)]
+pub struct AlarmEvent {
+  pub ts: String,
+  /// `staleData`, or the `name` of an `alarms` rule.
+  pub alarm: String,
+  /// `true` when the alarm is raised, `false` when it clears.
+  pub active: bool,
+  pub message: String,
+  /// The rule's channel and threshold, and the reading that raised or cleared it; unset for `staleData`.
+  pub channel: Option<String>,
+  pub value: Option<f64>,
+  pub threshold: Option<f64>,
+}
+
+impl AlarmEvent {
+  pub fn new(alarm: &str, active: bool, message: String) -> Self {
+    Self {
+      ts: Utc::now().to_rfc3339_opts(SecondsFormat::Millis, true),
+      alarm: alarm.to_string(),
+      active,
+      message,
+      channel: None,
+      value: None,
+      threshold: None,
+    }
+  }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Deserialize, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub(crate) enum Comparator {
+  /// Raised while the reading is above `threshold`.
+  Above,
+  /// Raised while the reading is below `threshold`.
+  Below,
+}
+
+impl Comparator {
+  fn as_str(self) -> &'static str {
+    match self {
+      Comparator::Above => "above",
+      Comparator::Below => "below",
+    }
+  }
+}
+
+#[derive(Debug, Clone, PartialEq, Deserialize, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct AlarmRule {
+  /// Reported as the event's `alarm`; unique within `alarms`.
+  pub name: String,
+  /// A channel (`btC`, `etC`, `powerPct`, `fanPct`, `drumRpm`, `ambientC`, ...) or a numeric extra, read after
+  /// clamping.
+  pub channel: String,
+  pub comparator: Comparator,
+  pub threshold: f64,
+  /// How far back past `threshold` the reading must come before the alarm clears, e.g. `5` to clear an `above 240`
+  /// alarm at 235.
+  #[serde(default)]
+  pub hysteresis: f64,
+  /// How long, on sample timestamps, a breach (or a recovery) must hold before the alarm is raised (or cleared).
+  #[serde(default)]
+  pub debounce_ms: u64,
+  /// Written to the device straight from the native loop when the rule is raised, e.g. a line cutting the gas to 0%,
+  /// ahead of queued commands and within `commands.interlockTimeoutMs`. Logged in the connection history.
+  #[serde(default)]
+  pub command: Option<String>,
+}
+
+impl AlarmRule {
+  fn breached(&self, value: f64) -> bool {
+    match self.comparator {
+      Comparator::Above => value > self.threshold,
+      Comparator::Below => value < self.threshold,
+    }
+  }
+
+  fn recovered(&self, value: f64) -> bool {
+    match self.comparator {
+      Comparator::Above => value <= self.threshold - self.hysteresis,
+      Comparator::Below => value >= self.threshold + self.hysteresis,
+    }
+  }
+
+  fn event(&self, active: bool, value: Option<f64>, message: String) -> AlarmEvent {
+    AlarmEvent {
+      channel: Some(self.channel.clone()),
+      value,
+      threshold: Some(self.threshold),
+      ..AlarmEvent::new(&self.name, active, message)
+    }
+  }
+}
+
+#[derive(Debug, Default)]
+struct RuleState {
+  active: bool,
+  /// When the reading first crossed towards the other state, while waiting out `debounceMs`.
+  pending_since: Option<DateTime<Utc>>,
+}
+
+/// The `alarms` rules with whether each is raised, checked against every accepted sample.
+#[derive(Debug, Default)]
+pub(crate) struct AlarmRules {
+  rules: Vec<(AlarmRule, RuleState)>,
+}
+
+impl AlarmRules {
+  pub fn new(rules: &[AlarmRule]) -> Self {
+    Self { rules: rules.iter().map(|rule| (rule.clone(), RuleState::default())).collect() }
+  }
+
+  /// Moves to `rules`, keeping the state of those that didn't change; returns the clearing events of raised rules
+  /// that were removed or changed.
+  pub fn update(&mut self, rules: &[AlarmRule]) -> Vec<AlarmEvent> {
+    let mut previous = std::mem::take(&mut self.rules);
+    self.rules = rules
+      .iter()
+      .map(|rule| match previous.iter().position(|(old, _)| old == rule) {
+        Some(index) => previous.swap_remove(index),
+        None => (rule.clone(), RuleState::default()),
+      })
+      .collect();
+    previous
+      .into_iter()
+      .filter(|(_, state)| state.active)
+      .map(|(rule, _)| rule.event(false, None, "rule changed".to_string()))
+      .collect()
+  }
+
+  /// Returns an event for each rule `sample` raised or cleared. Rules whose channel the sample lacks keep their state.
+  pub fn observe(&mut self, sample: &RawTelemetrySample) -> Vec<AlarmEvent> {
+    let mut events = Vec::new();
+    for (rule, state) in &mut self.rules {
+      let Some(value) = channels::reading(sample, &rule.channel) else {
+        continue;
+      };
+      let crossing = if state.active { rule.recovered(value) } else { rule.breached(value) };
+      if !crossing {
+        state.pending_since = None;
+        continue;
+      }
+      let since = *state.pending_since.get_or_insert(sample.ts);
+      if (sample.ts - since).num_milliseconds() < rule.debounce_ms as i64 {
+        continue;
+      }
+      state.active = !state.active;
+      state.pending_since = None;
+      let message = if state.active {
+        format!("{} {} {} {}", rule.channel, value, rule.comparator.as_str(), rule.threshold)
+      } else {
+        format!("{} {} no longer {} {}", rule.channel, value, rule.comparator.as_str(), rule.threshold)
+      };
+      events.push(rule.event(state.active, Some(value), message));
+    }
+    events
+  }
+
+  /// Clears every raised rule, returning their events.
+  pub fn clear(&mut self, message: &str) -> Vec<AlarmEvent> {
+    let mut events = Vec::new();
+    for (rule, state) in &mut self.rules {
+      state.pending_since = None;
+      if std::mem::take(&mut state.active) {
+        events.push(rule.event(false, None, message.to_string()));
+      }
+    }
+    events
+  }
+}
+
+pub(crate) enum Staleness {
+  /// Nothing to watch: not connected yet, or already raised.
+  Idle,
+  /// A sample arrived within the window, which runs out at `due`.
+  Fresh { due: Instant },
+  /// Just raised.
+  Stale,
+}
+
+/// When the connection last delivered a valid sample (or came up), for `staleDataAlarmMs`.
+#[derive(Debug, Default)]
+pub(crate) struct StaleWatch {
+  since: Option<Instant>,
+  raised: bool,
+}
+
+impl StaleWatch {
+  /// Starts the window of a connection that just came up; a raised alarm stays raised until a sample arrives.
+  pub fn start(&mut self, now: Instant) {
+    self.since = Some(now);
+  }
+
+  /// Starts the window over on a valid sample; returns whether a raised alarm cleared.
+  pub fn touch(&mut self, now: Instant) -> bool {
+    self.since = Some(now);
+    std::mem::take(&mut self.raised)
+  }
+
+  /// Raises the alarm once `window` has passed since the last touch.
+  pub fn check(&mut self, window: Duration, now: Instant) -> Staleness {
+    let Some(since) = self.since.filter(|_| !self.raised) else {
+      return Staleness::Idle;
+    };
+    if now < since + window {
+      return Staleness::Fresh { due: since + window };
+    }
+    self.raised = true;
+    Staleness::Stale
+  }
+
+  /// Forgets the connection; returns whether a raised alarm cleared.
+  pub fn reset(&mut self) -> bool {
+    self.since = None;
+    std::mem::take(&mut self.raised)
+  }
+
+  pub fn raised(&self) -> bool {
+    self.raised
+  }
+}
+
+/// Raises the `staleData` alarm of a connected driver that has gone `staleDataAlarmMs` without a valid sample,
+/// until dropped.
+pub(crate) struct StaleWatchdog {
+  handle: JoinHandle<()>,
+}
+
+impl StaleWatchdog {
+  pub fn spawn(inner: Arc<DriverInner>) -> Self {
+    let handle = tokio::spawn(async move {
+      loop {
+        let Some(window) = inner.config().stale_data_alarm_ms.map(Duration::from_millis) else {
+          tokio::time::sleep(IDLE_POLL).await;
+          continue;
+        };
+        match inner.check_stale(window) {
+          Some(due) => tokio::time::sleep_until(due.into()).await,
+          None => tokio::time::sleep(window.min(IDLE_POLL)).await,
+        }
+      }
+    });
+    Self { handle }
+  }
+}
+
+impl Drop for StaleWatchdog {
+  fn drop(&mut self) {
+    self.handle.abort();
+  }
+}
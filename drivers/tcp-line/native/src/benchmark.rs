@@ -0,0 +1,52 @@
+use napi::bindgen_prelude::*;
+use napi_derive::napi;
+
+use tcp_line_core::throughput;
+
+use crate::validation::parse_config;
+
+/// What `benchmarkParser` measured.
+#[derive(Debug, Clone)]
+#[napi(object)]
+pub struct ParserBenchmark {
+  /// Lines parsed: every sample line once per iteration.
+  pub lines: i64,
+  /// Lines that completed a sample.
+  pub samples: i64,
+  /// Lines that completed none, e.g. a CSV header or part of a record.
+  pub skipped: i64,
+  pub errors: i64,
+  pub elapsedMs: f64,
+  pub linesPerSecond: f64,
+  pub bytesPerSecond: f64,
+}
+
+/// Parses `sample_lines` `iterations` times with the parsing settings of a TCP driver config, on a blocking thread,
+/// and reports the throughput. Each pass starts like a new connection, so a CSV header among the lines is read as the
+/// header every time. Only the parser is timed: no socket, decoding, downsampling or sinks.
+#[napi]
+pub async fn benchmark_parser(
+  config_json: String,
+  sample_lines: Vec<String>,
+  iterations: u32,
+) -> Result<ParserBenchmark> {
+  let config = parse_config(&config_json)?;
+  if sample_lines.is_empty() {
+    return Err(Error::from_reason("sampleLines must not be empty"));
+  }
+  if iterations == 0 {
+    return Err(Error::from_reason("iterations must be positive"));
+  }
+  let measured = tokio::task::spawn_blocking(move || throughput::measure(config.parser(), &sample_lines, iterations))
+    .await
+    .map_err(|err| Error::from_reason(format!("benchmark failed: {}", err)))?;
+  Ok(ParserBenchmark {
+    lines: measured.lines as i64,
+    samples: measured.samples as i64,
+    skipped: measured.skipped as i64,
+    errors: measured.errors as i64,
+    elapsedMs: measured.elapsed.as_secs_f64() * 1000.0,
+    linesPerSecond: measured.lines_per_second(),
+    bytesPerSecond: measured.bytes_per_second(),
+  })
+}
@@ -0,0 +1,649 @@
+use std::collections::BTreeMap;
+use std::pin::Pin;
+use std::str::FromStr;
+use std::sync::atomic::Ordering;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use btleplug::api::{
+  bleuuid::uuid_from_u16, BDAddr, Central, CentralEvent, CharPropFlags, Manager as _, Peripheral as _, ScanFilter,
+  ValueNotification,
+};
+use btleplug::platform::{Adapter, Manager, Peripheral};
+use napi::bindgen_prelude::*;
+use napi_derive::napi;
+use serde::Deserialize;
+use tokio::time::sleep;
+use tokio_stream::{Stream, StreamExt};
+use tracing::{debug, error, info};
+use uuid::Uuid;
+
+use crate::aggregate::TelemetryAggregate;
+use crate::cancel::{self, Cancel};
+use crate::csv_record::ColumnMismatch;
+use crate::events::{DriverError, DriverErrorCode};
+use crate::phases::SessionSummary;
+use crate::recent_errors::RecentParseError;
+use crate::session_log::SessionLogSummary;
+use crate::stats::DetailedMetrics;
+use crate::stream::TelemetryStream;
+use crate::subscription::TelemetrySubscription;
+use crate::{
+  CsvConfig, DriverInner, DriverState, DriverStatus, FrameFormat, ParseError, PipelineConfig, ReconnectConfig,
+  TcpLineDriverConfig, TelemetryPoint,
+};
+
+/// How a field is laid out in a notification.
+#[derive(Debug, Clone, Copy, PartialEq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+enum FieldType {
+  U8,
+  I8,
+  U16le,
+  U16be,
+  I16le,
+  I16be,
+  U32le,
+  U32be,
+  I32le,
+  I32be,
+  F32le,
+  F32be,
+}
+
+impl FieldType {
+  fn size(self) -> usize {
+    match self {
+      Self::U8 | Self::I8 => 1,
+      Self::U16le | Self::U16be | Self::I16le | Self::I16be => 2,
+      _ => 4,
+    }
+  }
+
+  /// The raw value at the start of `bytes`, which must hold at least `size()` bytes.
+  fn read(self, bytes: &[u8]) -> f64 {
+    let two = || [bytes[0], bytes[1]];
+    let four = || [bytes[0], bytes[1], bytes[2], bytes[3]];
+    match self {
+      Self::U8 => bytes[0] as f64,
+      Self::I8 => bytes[0] as i8 as f64,
+      Self::U16le => u16::from_le_bytes(two()) as f64,
+      Self::U16be => u16::from_be_bytes(two()) as f64,
+      Self::I16le => i16::from_le_bytes(two()) as f64,
+      Self::I16be => i16::from_be_bytes(two()) as f64,
+      Self::U32le => u32::from_le_bytes(four()) as f64,
+      Self::U32be => u32::from_be_bytes(four()) as f64,
+      Self::I32le => i32::from_le_bytes(four()) as f64,
+      Self::I32be => i32::from_be_bytes(four()) as f64,
+      Self::F32le => f32::from_le_bytes(four()) as f64,
+      Self::F32be => f32::from_be_bytes(four()) as f64,
+    }
+  }
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct FieldConfig {
+  /// `btC`, `etC`, ..., or any other name for an extra.
+  channel: String,
+  /// Position of the field in the notification.
+  #[serde(default)]
+  byte: usize,
+  #[serde(rename = "type")]
+  kind: FieldType,
+  /// The reading is `raw * scale + offset`.
+  #[serde(default = "default_scale")]
+  scale: f64,
+  #[serde(default)]
+  offset: f64,
+}
+
+fn default_scale() -> f64 {
+  1.0
+}
+
+impl FieldConfig {
+  /// The scaled reading, or `None` if `data` is too short for the field.
+  fn decode(&self, data: &[u8]) -> Option<f64> {
+    let bytes = data.get(self.byte..)?.get(..self.kind.size())?;
+    Some(self.kind.read(bytes) * self.scale + self.offset)
+  }
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct CharacteristicConfig {
+  /// Full UUID, or the 16-bit short form (`fff1`) for SIG-based ones.
+  uuid: String,
+  fields: Vec<FieldConfig>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct BleDriverConfig {
+  /// Device address (`AA:BB:CC:DD:EE:FF`); matched together with `namePrefix` when both are set.
+  #[serde(default)]
+  address: Option<String>,
+  /// Start of the advertised local name, e.g. `iGrill`.
+  #[serde(default)]
+  name_prefix: Option<String>,
+  /// Service holding the characteristics below.
+  service_uuid: String,
+  /// Characteristics to subscribe to and how to decode their notifications.
+  characteristics: Vec<CharacteristicConfig>,
+  /// How long to scan for a matching device per attempt.
+  #[serde(default = "default_scan_timeout_ms")]
+  scan_timeout_ms: u64,
+  /// Connecting, service discovery and subscribing together.
+  #[serde(default = "default_connect_timeout_ms")]
+  connect_timeout_ms: u64,
+  reconnect: ReconnectConfig,
+  #[serde(flatten)]
+  pipeline: PipelineConfig,
+}
+
+fn default_scan_timeout_ms() -> u64 {
+  30_000
+}
+
+fn default_connect_timeout_ms() -> u64 {
+  10_000
+}
+
+/// The validated device filter and characteristics.
+#[derive(Debug, Clone)]
+struct Target {
+  address: Option<BDAddr>,
+  name_prefix: Option<String>,
+  service: Uuid,
+  characteristics: Vec<(Uuid, Vec<FieldConfig>)>,
+}
+
+impl BleDriverConfig {
+  fn line_config(&self) -> TcpLineDriverConfig {
+    TcpLineDriverConfig {
+      reconnect: self.reconnect.clone(),
+      ..TcpLineDriverConfig::for_source(
+        FrameFormat::Jsonl,
+        CsvConfig {
+          has_header: false,
+          columns: Vec::new(),
+          delimiter: ",".to_string(),
+          column_mismatch: ColumnMismatch::default(),
+        },
+        self.pipeline.clone(),
+      )
+    }
+  }
+
+  fn validate(&self) -> std::result::Result<Target, String> {
+    if self.address.is_none() && self.name_prefix.is_none() {
+      return Err("address or namePrefix is required".to_string());
+    }
+    let address = match &self.address {
+      Some(address) => {
+        Some(BDAddr::from_str(address).map_err(|_| format!("address: invalid bluetooth address {:?}", address))?)
+      }
+      None => None,
+    };
+    if self.name_prefix.as_deref() == Some("") {
+      return Err("namePrefix must not be empty".to_string());
+    }
+    let service =
+      parse_uuid(&self.service_uuid).ok_or_else(|| format!("serviceUuid: invalid uuid {:?}", self.service_uuid))?;
+    if self.scan_timeout_ms == 0 {
+      return Err("scanTimeoutMs must be positive".to_string());
+    }
+    if self.connect_timeout_ms == 0 {
+      return Err("connectTimeoutMs must be positive".to_string());
+    }
+    if self.characteristics.is_empty() {
+      return Err("characteristics must list at least one characteristic".to_string());
+    }
+    let mut channels: Vec<&str> = Vec::new();
+    let mut characteristics = Vec::with_capacity(self.characteristics.len());
+    for (idx, characteristic) in self.characteristics.iter().enumerate() {
+      let uuid = parse_uuid(&characteristic.uuid)
+        .ok_or_else(|| format!("characteristics[{}].uuid: invalid uuid {:?}", idx, characteristic.uuid))?;
+      if characteristic.fields.is_empty() {
+        return Err(format!("characteristics[{}].fields must decode at least one channel", idx));
+      }
+      for field in &characteristic.fields {
+        if channels.contains(&field.channel.as_str()) {
+          return Err(format!("channel {} is decoded more than once", field.channel));
+        }
+        if !(field.scale.is_finite() && field.offset.is_finite()) {
+          return Err(format!("characteristics[{}]: scale and offset of {} must be finite", idx, field.channel));
+        }
+        channels.push(&field.channel);
+      }
+      characteristics.push((uuid, characteristic.fields.clone()));
+    }
+    Ok(Target { address, name_prefix: self.name_prefix.clone(), service, characteristics })
+  }
+}
+
+/// A full UUID, or a 16-bit short one expanded onto the Bluetooth base UUID.
+fn parse_uuid(value: &str) -> Option<Uuid> {
+  let short = value.strip_prefix("0x").unwrap_or(value);
+  if short.len() == 4 {
+    return u16::from_str_radix(short, 16).ok().map(uuid_from_u16);
+  }
+  Uuid::parse_str(value).ok()
+}
+
+/// Subscribes to a Bluetooth LE device's GATT notifications and feeds the decoded fields through the same sample
+/// pipeline as `TcpLineDriverNative`: every notification becomes one sample carrying the latest value of each field.
+#[napi]
+pub struct BleDriverNative {
+  config: BleDriverConfig,
+  target: Target,
+  pub(crate) inner: Arc<DriverInner>,
+}
+
+#[napi]
+impl BleDriverNative {
+  #[napi(constructor)]
+  pub fn new(config_json: String, machine_id: String) -> Result<Self> {
+    let config: BleDriverConfig = serde_json::from_str(&config_json)
+      .map_err(|err| Error::from_reason(format!("invalid config: {}", err)))?;
+    let target = config.validate().map_err(|err| Error::from_reason(format!("invalid config: {}", err)))?;
+    let inner = DriverInner::new(config.line_config(), machine_id);
+    Ok(Self { config, target, inner })
+  }
+
+  #[napi(ts_args_type = "timeoutMs?: number, cancel?: CancelToken")]
+  pub async fn connect(&self, timeout_ms: Option<u32>, cancel: Option<Cancel>) -> Result<()> {
+    let config = self.config.clone();
+    let target = self.target.clone();
+    let since = self.inner.connected_seq.load(Ordering::Relaxed);
+    self.inner.ensure_source(move |inner| run_ble(inner, config, target));
+    cancel::bounded("connect", self.inner.wait_for_connected(since), timeout_ms, cancel).await
+  }
+
+  #[napi(ts_args_type = "timeoutMs?: number, cancel?: CancelToken")]
+  pub async fn read_telemetry(&self, timeout_ms: Option<u32>, cancel: Option<Cancel>) -> Result<TelemetryPoint> {
+    self.inner.read_telemetry(timeout_ms, cancel).await
+  }
+
+  #[napi(ts_args_type = "timeoutMs: number, cancel?: CancelToken")]
+  pub async fn read_telemetry_with_timeout(
+    &self,
+    timeout_ms: u32,
+    cancel: Option<Cancel>,
+  ) -> Result<Option<TelemetryPoint>> {
+    self.inner.read_telemetry_with_timeout(timeout_ms, cancel).await
+  }
+
+  #[napi]
+  pub async fn peek_telemetry(&self) -> Result<TelemetryPoint> {
+    self.inner.peek_telemetry().await
+  }
+
+  #[napi]
+  pub async fn disconnect(&self) -> Result<()> {
+    self.inner.disconnect().await;
+    Ok(())
+  }
+
+  #[napi]
+  pub fn get_status(&self) -> Result<DriverStatus> {
+    Ok(self.inner.get_status())
+  }
+
+  #[napi]
+  pub fn get_metrics_detailed(&self) -> DetailedMetrics {
+    self.inner.get_metrics_detailed()
+  }
+
+  /// The last 50 parse failures, oldest first, with the offending line cut to 256 characters.
+  #[napi]
+  pub fn get_recent_errors(&self) -> Vec<RecentParseError> {
+    self.inner.get_recent_errors()
+  }
+
+  /// Async-iterator-shaped stream of samples; see `TelemetryStream`.
+  #[napi]
+  pub fn telemetry_stream(&self) -> TelemetryStream {
+    TelemetryStream::new(Arc::clone(&self.inner))
+  }
+
+  /// A handle of its own onto every point published from now on; see `TelemetrySubscription`.
+  #[napi]
+  pub fn subscribe(&self) -> TelemetrySubscription {
+    TelemetrySubscription::new(Arc::clone(&self.inner))
+  }
+
+  /// Takes up to `maxSamples` (default all) queued samples, oldest first; empty unless `queue` is configured.
+  #[napi]
+  pub fn drain_samples(&self, max_samples: Option<u32>) -> Vec<TelemetryPoint> {
+    self.inner.drain_samples(max_samples)
+  }
+
+  /// Per-channel min/max/mean/count over the samples of the last `windowSeconds` (by sample timestamp).
+  #[napi]
+  pub fn read_aggregate(&self, window_seconds: f64) -> Result<TelemetryAggregate> {
+    self.inner.read_aggregate(window_seconds)
+  }
+
+  /// The current roast's phases from CHARGE, with their durations, end temperatures and mean RoR; `null` before
+  /// CHARGE. Needs `roastEvents`.
+  #[napi]
+  pub fn get_session_summary(&self) -> Option<SessionSummary> {
+    self.inner.session_summary()
+  }
+
+  /// Collects every emitted point from now on and writes them to `path` on `stopLogging`. `format` is `csv` or
+  /// `parquet` (default: from the extension, else csv); the header lists the point fields followed by one column per
+  /// extra.
+  #[napi]
+  pub fn start_logging(&self, path: String, format: Option<String>) -> Result<()> {
+    self.inner.start_logging(path, format)
+  }
+
+  /// Writes the log begun by `startLogging` and returns what was written (`null` when not logging).
+  #[napi]
+  pub fn stop_logging(&self) -> Result<Option<SessionLogSummary>> {
+    self.inner.stop_logging()
+  }
+
+  /// Registers a callback for state transitions (CONNECTING, CONNECTED, DISCONNECTED, ...).
+  #[napi(ts_args_type = "callback: (event: StateChangeEvent) => void")]
+  pub fn on_state_change(&self, env: Env, callback: JsFunction) -> Result<()> {
+    self.inner.state_events.subscribe(&env, callback)
+  }
+
+  /// Registers a callback for connection failures and lost devices.
+  #[napi(ts_args_type = "callback: (error: DriverError) => void")]
+  pub fn on_error(&self, env: Env, callback: JsFunction) -> Result<()> {
+    self.inner.error_events.subscribe(&env, callback)
+  }
+
+  /// Registers a callback for detected roast events (requires `roastEvents` in the config).
+  #[napi(ts_args_type = "callback: (event: RoastEvent) => void")]
+  pub fn on_event(&self, env: Env, callback: JsFunction) -> Result<()> {
+    self.inner.roast_events.subscribe(&env, callback)
+  }
+}
+
+/// Scans, connects and subscribes again after every loss, paced by `reconnect` like the TCP loop.
+async fn run_ble(inner: Arc<DriverInner>, config: BleDriverConfig, target: Target) {
+  inner.apply_backoff(&config.reconnect);
+  inner.backoff.lock().reset();
+  let mut attempts: u32 = 0;
+  let mut outage_started: Option<Instant> = None;
+  let mut exhausted = false;
+  loop {
+    if inner.stop_flag.load(Ordering::Relaxed) {
+      break;
+    }
+    inner.set_state(DriverState::CONNECTING);
+    inner.reset_connection_state();
+    inner.reset_roast_events();
+
+    debug!(machine_id = %inner.machine_id, attempt = attempts + 1, "scanning");
+    let (connected, err) = run_session(&inner, &config, &target).await;
+    if connected {
+      attempts = 0;
+      outage_started = None;
+    }
+    inner.handle_failure(err).await;
+
+    if inner.stop_flag.load(Ordering::Relaxed) || !config.reconnect.enabled {
+      break;
+    }
+    attempts = attempts.saturating_add(1);
+    let outage = outage_started.get_or_insert_with(Instant::now).elapsed();
+    if config.reconnect.exhausted(attempts, outage) {
+      let outage_ms = outage.as_millis() as u64;
+      error!(machine_id = %inner.machine_id, attempts, outage_ms, "reconnect policy exhausted");
+      exhausted = true;
+      break;
+    }
+    {
+      let mut metrics = inner.metrics.lock();
+      metrics.reconnects = metrics.reconnects.saturating_add(1);
+    }
+    let delay = { inner.backoff.lock().next_delay() };
+    debug!(machine_id = %inner.machine_id, delay_ms = delay, attempt = attempts, "reconnecting after backoff");
+    sleep(Duration::from_millis(delay)).await;
+  }
+
+  inner.set_state(if inner.stop_flag.load(Ordering::Relaxed) {
+    DriverState::STOPPED
+  } else if exhausted {
+    DriverState::FAILED
+  } else {
+    DriverState::DISCONNECTED
+  });
+}
+
+const DISCONNECT_TIMEOUT: Duration = Duration::from_secs(2);
+
+type Events = Pin<Box<dyn Stream<Item = CentralEvent> + Send>>;
+
+/// One connection from scanning until the device is lost. Returns whether it got as far as CONNECTED, and why it
+/// ended.
+async fn run_session(inner: &Arc<DriverInner>, config: &BleDriverConfig, target: &Target) -> (bool, DriverError) {
+  let adapter = match first_adapter().await {
+    Ok(adapter) => adapter,
+    Err(message) => return (false, connect_failed(message)),
+  };
+  let mut events = match adapter.events().await {
+    Ok(events) => events,
+    Err(err) => return (false, connect_failed(err.to_string())),
+  };
+  let scan_timeout = Duration::from_millis(config.scan_timeout_ms);
+  let scan = tokio::time::timeout(scan_timeout, find_device(&adapter, &mut events, target)).await;
+  let _ = adapter.stop_scan().await;
+  let peripheral = match scan {
+    Ok(Ok(peripheral)) => peripheral,
+    Ok(Err(message)) => return (false, connect_failed(message)),
+    Err(_) => {
+      let message = format!("connection failure: no matching device found within {} ms", config.scan_timeout_ms);
+      return (false, DriverError::new(DriverErrorCode::Timeout, message));
+    }
+  };
+  let outcome = serve_device(inner, config, target, &peripheral, &mut events).await;
+  let _ = tokio::time::timeout(DISCONNECT_TIMEOUT, peripheral.disconnect()).await;
+  outcome
+}
+
+async fn first_adapter() -> std::result::Result<Adapter, String> {
+  let manager = Manager::new().await.map_err(|err| format!("bluetooth unavailable: {}", err))?;
+  let adapters = manager.adapters().await.map_err(|err| format!("bluetooth unavailable: {}", err))?;
+  adapters.into_iter().next().ok_or_else(|| "no bluetooth adapter".to_string())
+}
+
+/// Scans until a device matching `target` shows up; devices the adapter already knows are checked first.
+async fn find_device(
+  adapter: &Adapter,
+  events: &mut Events,
+  target: &Target,
+) -> std::result::Result<Peripheral, String> {
+  adapter.start_scan(ScanFilter::default()).await.map_err(|err| format!("scan failed: {}", err))?;
+  for peripheral in adapter.peripherals().await.unwrap_or_default() {
+    if matches_target(&peripheral, target).await {
+      return Ok(peripheral);
+    }
+  }
+  while let Some(event) = events.next().await {
+    let (CentralEvent::DeviceDiscovered(id) | CentralEvent::DeviceUpdated(id)) = event else {
+      continue;
+    };
+    if let Ok(peripheral) = adapter.peripheral(&id).await {
+      if matches_target(&peripheral, target).await {
+        return Ok(peripheral);
+      }
+    }
+  }
+  Err("adapter stopped reporting devices".to_string())
+}
+
+async fn matches_target(peripheral: &Peripheral, target: &Target) -> bool {
+  let Ok(Some(properties)) = peripheral.properties().await else {
+    return false;
+  };
+  let address_matches = target.address.is_none_or(|address| address == properties.address);
+  let name_matches = match &target.name_prefix {
+    Some(prefix) => properties.local_name.is_some_and(|name| name.starts_with(prefix.as_str())),
+    None => true,
+  };
+  address_matches && name_matches
+}
+
+/// Connects, subscribes, then feeds samples until the device goes away.
+async fn serve_device(
+  inner: &Arc<DriverInner>,
+  config: &BleDriverConfig,
+  target: &Target,
+  peripheral: &Peripheral,
+  events: &mut Events,
+) -> (bool, DriverError) {
+  let setup = async {
+    peripheral.connect().await.map_err(|err| err.to_string())?;
+    peripheral.discover_services().await.map_err(|err| format!("service discovery failed: {}", err))?;
+    let available = peripheral.characteristics();
+    // Open the stream before subscribing so the first notifications aren't missed.
+    let notifications = peripheral.notifications().await.map_err(|err| err.to_string())?;
+    for (uuid, _) in &target.characteristics {
+      let characteristic = available
+        .iter()
+        .find(|characteristic| characteristic.uuid == *uuid && characteristic.service_uuid == target.service)
+        .ok_or_else(|| format!("characteristic {} not found in service {}", uuid, target.service))?;
+      if !characteristic.properties.intersects(CharPropFlags::NOTIFY | CharPropFlags::INDICATE) {
+        return Err(format!("characteristic {} does not notify", uuid));
+      }
+      peripheral.subscribe(characteristic).await.map_err(|err| format!("subscribe to {} failed: {}", uuid, err))?;
+    }
+    Ok(notifications)
+  };
+  let mut notifications = match tokio::time::timeout(Duration::from_millis(config.connect_timeout_ms), setup).await {
+    Ok(Ok(notifications)) => notifications,
+    Ok(Err(message)) => return (false, connect_failed(message)),
+    Err(_) => {
+      let message = format!("connection failure: timed out after {} ms", config.connect_timeout_ms);
+      return (false, DriverError::new(DriverErrorCode::Timeout, message));
+    }
+  };
+
+  inner.backoff.lock().reset();
+  inner.metrics.lock().lastError = None;
+  info!(machine_id = %inner.machine_id, address = %peripheral.address(), "connected");
+  inner.set_state(DriverState::CONNECTED);
+
+  let id = peripheral.id();
+  let mut values = BTreeMap::new();
+  let reason = loop {
+    tokio::select! {
+      notification = notifications.next() => {
+        let Some(notification) = notification else {
+          break "notifications stopped".to_string();
+        };
+        inner.wait_for_queue_space().await;
+        accept_notification(inner, target, &mut values, notification);
+      }
+      event = events.next() => match event {
+        Some(CentralEvent::DeviceDisconnected(lost)) if lost == id => break "device disconnected".to_string(),
+        Some(_) => {}
+        None => break "adapter stopped reporting devices".to_string(),
+      },
+    }
+  };
+  (true, DriverError::new(DriverErrorCode::SocketClosed, format!("connection lost: {}", reason)))
+}
+
+fn connect_failed(message: String) -> DriverError {
+  DriverError::new(DriverErrorCode::ConnectFailed, format!("connection failure: {}", message))
+}
+
+/// Decodes `notification` into the latest values and emits them as one sample; a notification too short for its
+/// fields is a parse error and changes nothing.
+fn accept_notification(
+  inner: &DriverInner,
+  target: &Target,
+  values: &mut BTreeMap<String, f64>,
+  notification: ValueNotification,
+) {
+  let Some((_, fields)) = target.characteristics.iter().find(|(uuid, _)| *uuid == notification.uuid) else {
+    return;
+  };
+  let data = &notification.value;
+  inner.count_line(data.len());
+  let raw = || data.iter().map(|byte| format!("{:02x}", byte)).collect::<String>();
+  let Some(decoded) = fields.iter().map(|field| field.decode(data)).collect::<Option<Vec<f64>>>() else {
+    inner.count_parse_error(&ParseError::InvalidFrame("notification too short"), &raw());
+    return;
+  };
+  for (field, value) in fields.iter().zip(decoded) {
+    if value.is_finite() {
+      values.insert(field.channel.clone(), value);
+    } else {
+      values.remove(&field.channel);
+    }
+  }
+  let record = values.iter().map(|(channel, value)| (channel.clone(), serde_json::Value::from(*value))).collect();
+  let parsed = inner.record_to_sample(record, raw);
+  match parsed {
+    Ok(Some(sample)) => inner.accept_sample(sample),
+    Ok(None) => {}
+    Err(err) => inner.count_parse_error(&err, &raw()),
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  fn field(field: serde_json::Value) -> FieldConfig {
+    let mut config = serde_json::json!({ "channel": "btC" });
+    config.as_object_mut().expect("object").extend(field.as_object().expect("object").clone());
+    serde_json::from_value(config).expect("field config")
+  }
+
+  #[test]
+  fn reads_each_layout_at_its_byte() {
+    let data = [0x01, 0xff, 0x38, 0x34, 0x12, 0x80];
+    let cases = [
+      ("u8", 1, 255.0),
+      ("i8", 1, -1.0),
+      ("u16le", 2, 13368.0),
+      ("u16be", 2, 14388.0),
+      ("i16le", 0, -255.0),
+      ("i16be", 1, -200.0),
+      ("u32le", 2, 2_148_676_664.0),
+      ("u32be", 0, 33_503_284.0),
+      ("i32le", 2, -2_146_290_632.0),
+      ("i32be", 1, -13_093_870.0),
+    ];
+    for (kind, byte, expected) in cases {
+      assert_eq!(field(serde_json::json!({ "type": kind, "byte": byte })).decode(&data), Some(expected), "{kind}");
+    }
+  }
+
+  #[test]
+  fn reads_floats_in_either_order() {
+    let bytes = 201.5f32.to_le_bytes();
+    assert_eq!(field(serde_json::json!({ "type": "f32le" })).decode(&bytes), Some(201.5));
+    let bytes = 201.5f32.to_be_bytes();
+    assert_eq!(field(serde_json::json!({ "type": "f32be" })).decode(&bytes), Some(201.5));
+  }
+
+  #[test]
+  fn scales_and_offsets_the_raw_value() {
+    // A signed tenth of a degree with a -40 °C offset, as many roaster probes report it.
+    let field = field(serde_json::json!({ "type": "i16le", "byte": 1, "scale": 0.1, "offset": -40.0 }));
+    let reading = field.decode(&[0x00, 0x2c, 0x01]).expect("a reading");
+    assert!((reading - -10.0).abs() < 1e-9, "{reading}");
+    let reading = field.decode(&[0x00, 0x9c, 0xff]).expect("a reading");
+    assert!((reading - -50.0).abs() < 1e-9, "{reading}");
+  }
+
+  #[test]
+  fn needs_the_whole_field_in_the_notification() {
+    let field = field(serde_json::json!({ "type": "u16be", "byte": 2 }));
+    assert_eq!(field.decode(&[0, 0, 1, 2]), Some(258.0));
+    assert_eq!(field.decode(&[0, 0, 1]), None);
+    assert_eq!(field.decode(&[]), None);
+  }
+}
@@ -0,0 +1,249 @@
+use std::collections::VecDeque;
+use std::fs;
+use std::path::Path;
+use std::time::Instant;
+
+use chrono::{DateTime, SecondsFormat, Utc};
+use napi_derive::napi;
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Map, Value};
+
+use crate::recorder::RecordedLine;
+use crate::{DriverMetrics, TelemetryPoint};
+
+/// The config keys a replay driver shares with the TCP driver, copied into the bundle's `replay.json`.
+const REPLAY_KEYS: [&str; 23] = [
+  "format",
+  "csv",
+  "regex",
+  "xml",
+  "json",
+  "checksum",
+  "encoding",
+  "assembly",
+  "emitIntervalMs",
+  "downsample",
+  "dedupeWithinMs",
+  "dedupeStrategy",
+  "channels",
+  "powerField",
+  "offsets",
+  "probes",
+  "environment",
+  "channelRegistry",
+  "extras",
+  "timestamp",
+  "strictness",
+  "roastEvents",
+  "queue",
+];
+const LINES_FILE: &str = "lines.ndjson";
+const REDACTED: &str = "[redacted]";
+/// The tar block size.
+const BLOCK: usize = 512;
+
+#[derive(Debug, Clone, PartialEq, Deserialize, JsonSchema)]
+#[serde(rename_all = "camelCase", default)]
+pub(crate) struct CaptureConfig {
+  /// Raw lines and points kept each; the oldest go first.
+  pub max_entries: usize,
+  /// How often the metrics are snapshotted while lines arrive.
+  pub metrics_interval_ms: u64,
+}
+
+impl Default for CaptureConfig {
+  fn default() -> Self {
+    Self { max_entries: 100_000, metrics_interval_ms: 1000 }
+  }
+}
+
+/// Returned by `exportSession`.
+#[derive(Debug, Clone)]
+#[napi(object)]
+pub struct SessionBundleSummary {
+  pub path: String,
+  pub sessionId: Option<String>,
+  pub lines: u32,
+  pub points: u32,
+  pub metricsSnapshots: u32,
+  /// Lines and points that fell off the front of the capture for `maxEntries`.
+  pub droppedEntries: u32,
+  pub bytes: f64,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct MetricsSnapshot {
+  at: String,
+  metrics: DriverMetrics,
+}
+
+/// What `capture` has kept since the driver started or the latest `startSession`, for `exportSession`.
+pub(crate) struct SessionCapture {
+  started_at: DateTime<Utc>,
+  session_id: Option<String>,
+  lines: VecDeque<RecordedLine>,
+  points: VecDeque<TelemetryPoint>,
+  metrics: VecDeque<MetricsSnapshot>,
+  last_snapshot: Option<Instant>,
+  dropped: u32,
+}
+
+impl SessionCapture {
+  pub fn new(session_id: Option<String>) -> Self {
+    Self {
+      started_at: Utc::now(),
+      session_id,
+      lines: VecDeque::new(),
+      points: VecDeque::new(),
+      metrics: VecDeque::new(),
+      last_snapshot: None,
+      dropped: 0,
+    }
+  }
+
+  pub fn frame(&mut self, config: &CaptureConfig, received_at: DateTime<Utc>, frame: &[u8]) {
+    let line = RecordedLine::new(received_at, frame);
+    self.dropped += push_capped(&mut self.lines, config.max_entries, line);
+  }
+
+  pub fn point(&mut self, config: &CaptureConfig, point: &TelemetryPoint) {
+    self.dropped += push_capped(&mut self.points, config.max_entries, point.clone());
+  }
+
+  /// Whether a metrics snapshot is due, `metricsIntervalMs` after the last.
+  pub fn metrics_due(&self, config: &CaptureConfig) -> bool {
+    self.last_snapshot.is_none_or(|at| at.elapsed().as_millis() >= u128::from(config.metrics_interval_ms))
+  }
+
+  pub fn snapshot(&mut self, config: &CaptureConfig, metrics: DriverMetrics) {
+    self.last_snapshot = Some(Instant::now());
+    let snapshot = MetricsSnapshot { at: format_ts(Utc::now()), metrics };
+    push_capped(&mut self.metrics, config.max_entries, snapshot);
+  }
+
+  /// Writes the capture to `path` as a tar archive: `manifest.json`, `config.json` (the driver config, secrets
+  /// redacted), `replay.json` (a replay driver config for `lines.ndjson`), `lines.ndjson` (the raw lines, in the
+  /// `record` format), `points.ndjson` and `metrics.ndjson` (`{ at, metrics }` snapshots).
+  pub fn export(&self, path: &str, machine_id: &str, config: Option<&Value>) -> std::io::Result<SessionBundleSummary> {
+    let exported_at = Utc::now();
+    let config = config.cloned().map(redact).unwrap_or(Value::Null);
+    let mut replay: Map<String, Value> = REPLAY_KEYS
+      .iter()
+      .filter_map(|key| Some((key.to_string(), config.get(key)?.clone())))
+      .collect();
+    replay.insert("path".to_string(), json!(LINES_FILE));
+    let manifest = json!({
+      "version": 1,
+      "machineId": machine_id,
+      "sessionId": self.session_id,
+      "capturedFrom": format_ts(self.started_at),
+      "exportedAt": format_ts(exported_at),
+      "lines": self.lines.len(),
+      "points": self.points.len(),
+      "metricsSnapshots": self.metrics.len(),
+      "droppedEntries": self.dropped,
+    });
+    let entries = [
+      ("manifest.json", pretty(&manifest)?),
+      ("config.json", pretty(&config)?),
+      ("replay.json", pretty(&Value::Object(replay))?),
+      (LINES_FILE, ndjson(&self.lines)?),
+      ("points.ndjson", ndjson(&self.points)?),
+      ("metrics.ndjson", ndjson(&self.metrics)?),
+    ];
+    let archive = tar(&entries, exported_at.timestamp().max(0) as u64);
+
+    let file_path = Path::new(path);
+    if let Some(parent) = file_path.parent() {
+      if !parent.as_os_str().is_empty() {
+        fs::create_dir_all(parent)?;
+      }
+    }
+    fs::write(file_path, &archive)?;
+    Ok(SessionBundleSummary {
+      path: path.to_string(),
+      sessionId: self.session_id.clone(),
+      lines: self.lines.len() as u32,
+      points: self.points.len() as u32,
+      metricsSnapshots: self.metrics.len() as u32,
+      droppedEntries: self.dropped,
+      bytes: archive.len() as f64,
+    })
+  }
+}
+
+/// Appends `entry`, dropping from the front past `max`; returns how many were dropped.
+fn push_capped<T>(entries: &mut VecDeque<T>, max: usize, entry: T) -> u32 {
+  entries.push_back(entry);
+  let mut dropped = 0;
+  while entries.len() > max.max(1) {
+    entries.pop_front();
+    dropped += 1;
+  }
+  dropped
+}
+
+/// `config` with every `token` and `*password` value replaced.
+fn redact(config: Value) -> Value {
+  match config {
+    Value::Object(fields) => Value::Object(
+      fields
+        .into_iter()
+        .map(|(key, value)| {
+          let lower = key.to_ascii_lowercase();
+          let secret = (lower == "token" || lower.ends_with("password")) && !value.is_null();
+          (key, if secret { json!(REDACTED) } else { redact(value) })
+        })
+        .collect(),
+    ),
+    Value::Array(values) => Value::Array(values.into_iter().map(redact).collect()),
+    other => other,
+  }
+}
+
+fn format_ts(ts: DateTime<Utc>) -> String {
+  ts.to_rfc3339_opts(SecondsFormat::Millis, true)
+}
+
+fn pretty(value: &Value) -> std::io::Result<Vec<u8>> {
+  let mut encoded = serde_json::to_vec_pretty(value).map_err(std::io::Error::other)?;
+  encoded.push(b'\n');
+  Ok(encoded)
+}
+
+fn ndjson<'a, T: Serialize + 'a>(entries: impl IntoIterator<Item = &'a T>) -> std::io::Result<Vec<u8>> {
+  let mut encoded = Vec::new();
+  for entry in entries {
+    serde_json::to_writer(&mut encoded, entry).map_err(std::io::Error::other)?;
+    encoded.push(b'\n');
+  }
+  Ok(encoded)
+}
+
+/// A ustar archive of regular files, as `tar -xf` reads it.
+fn tar(entries: &[(&str, Vec<u8>)], mtime: u64) -> Vec<u8> {
+  let mut archive = Vec::new();
+  for (name, data) in entries {
+    let mut header = [0u8; BLOCK];
+    let mut field = |at: usize, len: usize, value: &[u8]| header[at..at + value.len().min(len)].copy_from_slice(value);
+    field(0, 100, name.as_bytes());
+    field(100, 8, b"0000644\0");
+    field(108, 8, b"0000000\0");
+    field(116, 8, b"0000000\0");
+    field(124, 12, format!("{:011o}\0", data.len()).as_bytes());
+    field(136, 12, format!("{:011o}\0", mtime).as_bytes());
+    field(148, 8, b"        ");
+    field(156, 1, b"0");
+    field(257, 8, b"ustar\x0000");
+    let checksum: u32 = header.iter().map(|byte| u32::from(*byte)).sum();
+    header[148..156].copy_from_slice(format!("{:06o}\0 ", checksum).as_bytes());
+    archive.extend_from_slice(&header);
+    archive.extend_from_slice(data);
+    archive.resize(archive.len().next_multiple_of(BLOCK), 0);
+  }
+  // Two zero blocks end the archive.
+  archive.resize(archive.len() + 2 * BLOCK, 0);
+  archive
+}
@@ -0,0 +1,376 @@
+use std::collections::BTreeMap;
+use std::sync::atomic::Ordering;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use napi::bindgen_prelude::*;
+use napi_derive::napi;
+use serde::Deserialize;
+use socketcan::id::{CAN_EFF_FLAG, CAN_EFF_MASK, CAN_RTR_FLAG, CAN_SFF_MASK};
+use socketcan::tokio::CanSocket;
+use socketcan::{CanFilter, CanFrame, EmbeddedFrame, Frame, SocketOptions};
+use tokio::time::sleep;
+use tracing::{debug, error, info};
+
+use crate::aggregate::TelemetryAggregate;
+use crate::can_signal::{Signal, SignalConfig};
+use crate::cancel::{self, Cancel};
+use crate::csv_record::ColumnMismatch;
+use crate::events::{DriverError, DriverErrorCode};
+use crate::phases::SessionSummary;
+use crate::recent_errors::RecentParseError;
+use crate::session_log::SessionLogSummary;
+use crate::stats::DetailedMetrics;
+use crate::stream::TelemetryStream;
+use crate::subscription::TelemetrySubscription;
+use crate::{
+  CsvConfig, DriverInner, DriverState, DriverStatus, FrameFormat, ParseError, PipelineConfig, ReconnectConfig,
+  TcpLineDriverConfig, TelemetryPoint,
+};
+
+/// Signals by the frame carrying them, keyed by `(frameId, extended)`.
+type FrameSignals = BTreeMap<(u32, bool), Vec<Signal>>;
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct CanDriverConfig {
+  /// SocketCAN interface, e.g. `can0` or `vcan0`.
+  interface: String,
+  signals: Vec<SignalConfig>,
+  /// Treat the bus as lost when no mapped frame arrives for this long.
+  #[serde(default)]
+  read_timeout_ms: Option<u64>,
+  reconnect: ReconnectConfig,
+  #[serde(flatten)]
+  pipeline: PipelineConfig,
+}
+
+impl CanDriverConfig {
+  fn line_config(&self) -> TcpLineDriverConfig {
+    TcpLineDriverConfig {
+      reconnect: self.reconnect.clone(),
+      ..TcpLineDriverConfig::for_source(
+        FrameFormat::Jsonl,
+        CsvConfig {
+          has_header: false,
+          columns: Vec::new(),
+          delimiter: ",".to_string(),
+          column_mismatch: ColumnMismatch::default(),
+        },
+        self.pipeline.clone(),
+      )
+    }
+  }
+
+  fn validate(&self) -> std::result::Result<FrameSignals, String> {
+    if self.interface.is_empty() {
+      return Err("interface must not be empty".to_string());
+    }
+    if self.read_timeout_ms == Some(0) {
+      return Err("readTimeoutMs must be positive".to_string());
+    }
+    if self.signals.is_empty() {
+      return Err("signals must map at least one channel".to_string());
+    }
+    let mut frames = FrameSignals::new();
+    for (idx, signal) in self.signals.iter().enumerate() {
+      let max_id = if signal.extended { CAN_EFF_MASK } else { CAN_SFF_MASK };
+      if signal.frame_id > max_id {
+        let kind = id_kind(signal.extended);
+        return Err(format!("signals[{}].frameId {:#x} is out of range for {} ids", idx, signal.frame_id, kind));
+      }
+      if signal.start_bit >= 64 {
+        return Err(format!("signals[{}].startBit must be below 64", idx));
+      }
+      if !(1..=64).contains(&signal.length) {
+        return Err(format!("signals[{}].length must be between 1 and 64", idx));
+      }
+      if !(signal.scale.is_finite() && signal.offset.is_finite()) {
+        return Err(format!("signals[{}]: scale and offset must be finite", idx));
+      }
+      if self.signals[..idx].iter().any(|other| other.channel == signal.channel) {
+        return Err(format!("channel {} is mapped more than once", signal.channel));
+      }
+      let bits = signal.bit_positions().ok_or_else(|| format!("signals[{}] does not fit in an 8-byte frame", idx))?;
+      frames.entry((signal.frame_id, signal.extended)).or_default().push(Signal {
+        channel: signal.channel.clone(),
+        bits,
+        signed: signal.signed,
+        scale: signal.scale,
+        offset: signal.offset,
+      });
+    }
+    Ok(frames)
+  }
+}
+
+fn id_kind(extended: bool) -> &'static str {
+  if extended {
+    "29-bit extended"
+  } else {
+    "11-bit standard"
+  }
+}
+
+/// Reads a Linux SocketCAN interface and feeds the mapped signals through the same sample pipeline as
+/// `TcpLineDriverNative`: every frame carrying a mapped signal becomes one sample with the latest value of each.
+#[napi]
+pub struct CanDriverNative {
+  config: CanDriverConfig,
+  frames: FrameSignals,
+  pub(crate) inner: Arc<DriverInner>,
+}
+
+#[napi]
+impl CanDriverNative {
+  #[napi(constructor)]
+  pub fn new(config_json: String, machine_id: String) -> Result<Self> {
+    let config: CanDriverConfig = serde_json::from_str(&config_json)
+      .map_err(|err| Error::from_reason(format!("invalid config: {}", err)))?;
+    let frames = config.validate().map_err(|err| Error::from_reason(format!("invalid config: {}", err)))?;
+    let inner = DriverInner::new(config.line_config(), machine_id);
+    Ok(Self { config, frames, inner })
+  }
+
+  #[napi(ts_args_type = "timeoutMs?: number, cancel?: CancelToken")]
+  pub async fn connect(&self, timeout_ms: Option<u32>, cancel: Option<Cancel>) -> Result<()> {
+    let config = self.config.clone();
+    let frames = self.frames.clone();
+    let since = self.inner.connected_seq.load(Ordering::Relaxed);
+    self.inner.ensure_source(move |inner| run_can(inner, config, frames));
+    cancel::bounded("connect", self.inner.wait_for_connected(since), timeout_ms, cancel).await
+  }
+
+  #[napi(ts_args_type = "timeoutMs?: number, cancel?: CancelToken")]
+  pub async fn read_telemetry(&self, timeout_ms: Option<u32>, cancel: Option<Cancel>) -> Result<TelemetryPoint> {
+    self.inner.read_telemetry(timeout_ms, cancel).await
+  }
+
+  #[napi(ts_args_type = "timeoutMs: number, cancel?: CancelToken")]
+  pub async fn read_telemetry_with_timeout(
+    &self,
+    timeout_ms: u32,
+    cancel: Option<Cancel>,
+  ) -> Result<Option<TelemetryPoint>> {
+    self.inner.read_telemetry_with_timeout(timeout_ms, cancel).await
+  }
+
+  #[napi]
+  pub async fn peek_telemetry(&self) -> Result<TelemetryPoint> {
+    self.inner.peek_telemetry().await
+  }
+
+  #[napi]
+  pub async fn disconnect(&self) -> Result<()> {
+    self.inner.disconnect().await;
+    Ok(())
+  }
+
+  #[napi]
+  pub fn get_status(&self) -> Result<DriverStatus> {
+    Ok(self.inner.get_status())
+  }
+
+  #[napi]
+  pub fn get_metrics_detailed(&self) -> DetailedMetrics {
+    self.inner.get_metrics_detailed()
+  }
+
+  /// The last 50 parse failures, oldest first, with the offending line cut to 256 characters.
+  #[napi]
+  pub fn get_recent_errors(&self) -> Vec<RecentParseError> {
+    self.inner.get_recent_errors()
+  }
+
+  /// Async-iterator-shaped stream of samples; see `TelemetryStream`.
+  #[napi]
+  pub fn telemetry_stream(&self) -> TelemetryStream {
+    TelemetryStream::new(Arc::clone(&self.inner))
+  }
+
+  /// A handle of its own onto every point published from now on; see `TelemetrySubscription`.
+  #[napi]
+  pub fn subscribe(&self) -> TelemetrySubscription {
+    TelemetrySubscription::new(Arc::clone(&self.inner))
+  }
+
+  /// Takes up to `maxSamples` (default all) queued samples, oldest first; empty unless `queue` is configured.
+  #[napi]
+  pub fn drain_samples(&self, max_samples: Option<u32>) -> Vec<TelemetryPoint> {
+    self.inner.drain_samples(max_samples)
+  }
+
+  /// Per-channel min/max/mean/count over the samples of the last `windowSeconds` (by sample timestamp).
+  #[napi]
+  pub fn read_aggregate(&self, window_seconds: f64) -> Result<TelemetryAggregate> {
+    self.inner.read_aggregate(window_seconds)
+  }
+
+  /// The current roast's phases from CHARGE, with their durations, end temperatures and mean RoR; `null` before
+  /// CHARGE. Needs `roastEvents`.
+  #[napi]
+  pub fn get_session_summary(&self) -> Option<SessionSummary> {
+    self.inner.session_summary()
+  }
+
+  /// Collects every emitted point from now on and writes them to `path` on `stopLogging`. `format` is `csv` or
+  /// `parquet` (default: from the extension, else csv); the header lists the point fields followed by one column per
+  /// extra.
+  #[napi]
+  pub fn start_logging(&self, path: String, format: Option<String>) -> Result<()> {
+    self.inner.start_logging(path, format)
+  }
+
+  /// Writes the log begun by `startLogging` and returns what was written (`null` when not logging).
+  #[napi]
+  pub fn stop_logging(&self) -> Result<Option<SessionLogSummary>> {
+    self.inner.stop_logging()
+  }
+
+  /// Registers a callback for state transitions (CONNECTING, CONNECTED, DISCONNECTED, ...).
+  #[napi(ts_args_type = "callback: (event: StateChangeEvent) => void")]
+  pub fn on_state_change(&self, env: Env, callback: JsFunction) -> Result<()> {
+    self.inner.state_events.subscribe(&env, callback)
+  }
+
+  /// Registers a callback for socket errors and read timeouts.
+  #[napi(ts_args_type = "callback: (error: DriverError) => void")]
+  pub fn on_error(&self, env: Env, callback: JsFunction) -> Result<()> {
+    self.inner.error_events.subscribe(&env, callback)
+  }
+
+  /// Registers a callback for detected roast events (requires `roastEvents` in the config).
+  #[napi(ts_args_type = "callback: (event: RoastEvent) => void")]
+  pub fn on_event(&self, env: Env, callback: JsFunction) -> Result<()> {
+    self.inner.roast_events.subscribe(&env, callback)
+  }
+}
+
+/// Opens the interface again after every socket error or read timeout, paced by `reconnect` like the TCP loop.
+async fn run_can(inner: Arc<DriverInner>, config: CanDriverConfig, frames: FrameSignals) {
+  inner.apply_backoff(&config.reconnect);
+  inner.backoff.lock().reset();
+  let mut attempts: u32 = 0;
+  let mut outage_started: Option<Instant> = None;
+  let mut exhausted = false;
+  loop {
+    if inner.stop_flag.load(Ordering::Relaxed) {
+      break;
+    }
+    inner.set_state(DriverState::CONNECTING);
+    inner.reset_connection_state();
+    inner.reset_roast_events();
+
+    debug!(machine_id = %inner.machine_id, interface = %config.interface, attempt = attempts + 1, "opening");
+    let (connected, err) = read_bus(&inner, &config, &frames).await;
+    if connected {
+      attempts = 0;
+      outage_started = None;
+    }
+    inner.handle_failure(err).await;
+
+    if inner.stop_flag.load(Ordering::Relaxed) || !config.reconnect.enabled {
+      break;
+    }
+    attempts = attempts.saturating_add(1);
+    let outage = outage_started.get_or_insert_with(Instant::now).elapsed();
+    if config.reconnect.exhausted(attempts, outage) {
+      let outage_ms = outage.as_millis() as u64;
+      error!(machine_id = %inner.machine_id, attempts, outage_ms, "reconnect policy exhausted");
+      exhausted = true;
+      break;
+    }
+    {
+      let mut metrics = inner.metrics.lock();
+      metrics.reconnects = metrics.reconnects.saturating_add(1);
+    }
+    let delay = { inner.backoff.lock().next_delay() };
+    debug!(machine_id = %inner.machine_id, delay_ms = delay, attempt = attempts, "reopening after backoff");
+    sleep(Duration::from_millis(delay)).await;
+  }
+
+  inner.set_state(if inner.stop_flag.load(Ordering::Relaxed) {
+    DriverState::STOPPED
+  } else if exhausted {
+    DriverState::FAILED
+  } else {
+    DriverState::DISCONNECTED
+  });
+}
+
+/// Opens the interface with a kernel filter for the mapped frames and reads until it fails. Returns whether the
+/// socket got as far as CONNECTED, and why reading ended.
+async fn read_bus(inner: &Arc<DriverInner>, config: &CanDriverConfig, frames: &FrameSignals) -> (bool, DriverError) {
+  let connect_failed = |err: std::io::Error| {
+    DriverError::new(DriverErrorCode::ConnectFailed, format!("connection failure: {}: {}", config.interface, err))
+  };
+  let socket = match CanSocket::open(&config.interface) {
+    Ok(socket) => socket,
+    Err(err) => return (false, connect_failed(err)),
+  };
+  let filters: Vec<CanFilter> = frames
+    .keys()
+    .map(|(id, extended)| {
+      if *extended {
+        CanFilter::new(id | CAN_EFF_FLAG, CAN_EFF_MASK | CAN_EFF_FLAG | CAN_RTR_FLAG)
+      } else {
+        CanFilter::new(*id, CAN_SFF_MASK | CAN_EFF_FLAG | CAN_RTR_FLAG)
+      }
+    })
+    .collect();
+  if let Err(err) = socket.set_filters(&filters) {
+    return (false, connect_failed(err));
+  }
+
+  inner.backoff.lock().reset();
+  inner.metrics.lock().lastError = None;
+  info!(machine_id = %inner.machine_id, interface = %config.interface, frames = frames.len(), "connected");
+  inner.set_state(DriverState::CONNECTED);
+
+  let mut values = BTreeMap::new();
+  loop {
+    let read = match config.read_timeout_ms {
+      Some(timeout_ms) => match tokio::time::timeout(Duration::from_millis(timeout_ms), socket.read_frame()).await {
+        Ok(read) => read,
+        Err(_) => {
+          let mut metrics = inner.metrics.lock();
+          metrics.staleTimeouts = metrics.staleTimeouts.saturating_add(1);
+          let message = format!("read timeout: no frame for {} ms", timeout_ms);
+          return (true, DriverError::new(DriverErrorCode::Timeout, message));
+        }
+      },
+      None => socket.read_frame().await,
+    };
+    let frame = match read {
+      Ok(CanFrame::Data(frame)) => frame,
+      Ok(_) => continue,
+      Err(err) => return (true, DriverError::new(DriverErrorCode::SocketClosed, format!("socket error: {}", err))),
+    };
+    let Some(signals) = frames.get(&(frame.raw_id(), frame.is_extended())) else {
+      continue;
+    };
+    inner.wait_for_queue_space().await;
+    accept_frame(inner, signals, &mut values, frame.data());
+  }
+}
+
+/// Decodes one frame into the latest values and emits them as one sample; a frame too short for its signals is a
+/// parse error and changes nothing.
+fn accept_frame(inner: &DriverInner, signals: &[Signal], values: &mut BTreeMap<String, f64>, data: &[u8]) {
+  inner.count_line(data.len());
+  let raw = || data.iter().map(|byte| format!("{:02x}", byte)).collect::<String>();
+  let Some(decoded) = signals.iter().map(|signal| signal.decode(data)).collect::<Option<Vec<f64>>>() else {
+    inner.count_parse_error(&ParseError::InvalidFrame("frame too short for its signals"), &raw());
+    return;
+  };
+  for (signal, value) in signals.iter().zip(decoded) {
+    values.insert(signal.channel.clone(), value);
+  }
+  let record = values.iter().map(|(channel, value)| (channel.clone(), serde_json::Value::from(*value))).collect();
+  let parsed = inner.record_to_sample(record, raw);
+  match parsed {
+    Ok(Some(sample)) => inner.accept_sample(sample),
+    Ok(None) => {}
+    Err(err) => inner.count_parse_error(&err, &raw()),
+  }
+}
@@ -0,0 +1,158 @@
+use serde::Deserialize;
+
+/// Bit layout of a signal, as `@1` (little) and `@0` (big) in a DBC file.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub(crate) enum ByteOrder {
+  #[default]
+  Little,
+  Big,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct SignalConfig {
+  /// `btC`, `etC`, `drumRpm`, ..., or any other name for an extra.
+  pub channel: String,
+  pub frame_id: u32,
+  /// `frameId` is a 29-bit extended id.
+  #[serde(default)]
+  pub extended: bool,
+  /// As in DBC: the least significant bit for little-endian signals, the most significant one for big-endian ones.
+  pub start_bit: u32,
+  pub length: u32,
+  #[serde(default)]
+  pub byte_order: ByteOrder,
+  #[serde(default)]
+  pub signed: bool,
+  /// The reading is `raw * scale + offset`.
+  #[serde(default = "default_scale")]
+  pub scale: f64,
+  #[serde(default)]
+  pub offset: f64,
+}
+
+fn default_scale() -> f64 {
+  1.0
+}
+
+impl SignalConfig {
+  /// Payload bit positions from the most to the least significant bit of the signal, where bit `n` is bit `n % 8` of
+  /// byte `n / 8`; `None` if the signal doesn't fit in 8 bytes.
+  pub fn bit_positions(&self) -> Option<Vec<u32>> {
+    let mut bits = Vec::with_capacity(self.length as usize);
+    match self.byte_order {
+      ByteOrder::Little => bits.extend((self.start_bit..self.start_bit.checked_add(self.length)?).rev()),
+      ByteOrder::Big => {
+        // Big-endian signals run from the start bit towards bit 0 of the byte, then on to bit 7 of the next byte.
+        let mut pos = self.start_bit;
+        for _ in 0..self.length {
+          bits.push(pos);
+          pos = if pos.is_multiple_of(8) { pos + 15 } else { pos - 1 };
+        }
+      }
+    }
+    bits.iter().all(|pos| *pos < 64).then_some(bits)
+  }
+}
+
+/// A validated signal.
+#[derive(Debug, Clone)]
+pub(crate) struct Signal {
+  pub channel: String,
+  pub bits: Vec<u32>,
+  pub signed: bool,
+  pub scale: f64,
+  pub offset: f64,
+}
+
+impl Signal {
+  /// The scaled value, or `None` if `data` is too short for the signal.
+  pub fn decode(&self, data: &[u8]) -> Option<f64> {
+    let mut raw: u64 = 0;
+    for pos in &self.bits {
+      let byte = data.get((pos / 8) as usize)?;
+      raw = (raw << 1) | ((byte >> (pos % 8)) & 1) as u64;
+    }
+    let length = self.bits.len() as u32;
+    let value = if self.signed && length < 64 && (raw >> (length - 1)) & 1 == 1 {
+      (raw | (u64::MAX << length)) as i64 as f64
+    } else if self.signed {
+      raw as i64 as f64
+    } else {
+      raw as f64
+    };
+    Some(value * self.scale + self.offset)
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  fn config(layout: serde_json::Value) -> SignalConfig {
+    let mut config = serde_json::json!({ "channel": "drumRpm", "frameId": 0x123 });
+    config.as_object_mut().expect("object").extend(layout.as_object().expect("object").clone());
+    serde_json::from_value(config).expect("signal config")
+  }
+
+  fn signal(layout: serde_json::Value) -> Signal {
+    let config = config(layout);
+    Signal {
+      channel: config.channel.clone(),
+      bits: config.bit_positions().expect("fits the frame"),
+      signed: config.signed,
+      scale: config.scale,
+      offset: config.offset,
+    }
+  }
+
+  #[test]
+  fn reads_intel_signals_from_the_start_bit_up() {
+    let data = [0x34, 0x12, 0xab, 0x0f, 0, 0, 0, 0];
+    assert_eq!(signal(serde_json::json!({ "startBit": 0, "length": 16 })).decode(&data), Some(4660.0));
+    assert_eq!(signal(serde_json::json!({ "startBit": 4, "length": 8 })).decode(&data), Some(35.0));
+    assert_eq!(signal(serde_json::json!({ "startBit": 16, "length": 12 })).decode(&data), Some(4011.0));
+    assert_eq!(signal(serde_json::json!({ "startBit": 27, "length": 1 })).decode(&data), Some(1.0));
+  }
+
+  #[test]
+  fn reads_motorola_signals_from_the_msb_down() {
+    let data = [0x12, 0x34, 0x56, 0, 0, 0, 0, 0];
+    let big = |start: u32, length: u32| {
+      signal(serde_json::json!({ "startBit": start, "length": length, "byteOrder": "big" }))
+    };
+    assert_eq!(big(7, 16).decode(&data), Some(4660.0));
+    // Starting mid-byte: the low nibble of byte 0, then the whole of byte 1.
+    assert_eq!(big(3, 12).decode(&data), Some(564.0));
+    assert_eq!(big(15, 8).decode(&data), Some(52.0));
+    assert_eq!(big(7, 24).decode(&data), Some(1_193_046.0));
+  }
+
+  #[test]
+  fn sign_extends_signed_signals() {
+    let data = [0xfe, 0xff, 0x80, 0, 0, 0, 0, 0];
+    assert_eq!(signal(serde_json::json!({ "startBit": 0, "length": 16, "signed": true })).decode(&data), Some(-2.0));
+    assert_eq!(signal(serde_json::json!({ "startBit": 0, "length": 16 })).decode(&data), Some(65534.0));
+    assert_eq!(signal(serde_json::json!({ "startBit": 16, "length": 8, "signed": true })).decode(&data), Some(-128.0));
+    let big = serde_json::json!({ "startBit": 7, "length": 12, "byteOrder": "big", "signed": true });
+    assert_eq!(signal(big).decode(&data), Some(-17.0));
+    let full = serde_json::json!({ "startBit": 0, "length": 64, "signed": true });
+    assert_eq!(signal(full).decode(&[0xff; 8]), Some(-1.0));
+  }
+
+  #[test]
+  fn scales_and_offsets_the_raw_value() {
+    let layout = serde_json::json!({ "startBit": 8, "length": 16, "signed": true, "scale": 0.1, "offset": -40.0 });
+    let reading = signal(layout).decode(&[0, 0xe8, 0x03, 0, 0, 0, 0, 0]).expect("a reading");
+    assert!((reading - 60.0).abs() < 1e-9, "{reading}");
+  }
+
+  #[test]
+  fn rejects_layouts_past_the_frame_and_short_frames() {
+    assert_eq!(config(serde_json::json!({ "startBit": 60, "length": 8 })).bit_positions(), None);
+    assert_eq!(config(serde_json::json!({ "startBit": 63, "length": 16, "byteOrder": "big" })).bit_positions(), None);
+    assert!(config(serde_json::json!({ "startBit": 56, "length": 8 })).bit_positions().is_some());
+    assert_eq!(signal(serde_json::json!({ "startBit": 16, "length": 16 })).decode(&[1, 2, 3]), None);
+  }
+}
@@ -0,0 +1,106 @@
+use std::future::Future;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+
+use napi::bindgen_prelude::*;
+use napi_derive::napi;
+use parking_lot::Mutex;
+use tokio::sync::Notify;
+
+#[derive(Default)]
+struct CancelState {
+  cancelled: AtomicBool,
+  reason: Mutex<Option<String>>,
+  notify: Notify,
+}
+
+impl CancelState {
+  /// Resolves with the reason once `cancel()` has been called, at once if it already was.
+  async fn cancelled(&self) -> Option<String> {
+    let notified = self.notify.notified();
+    tokio::pin!(notified);
+    notified.as_mut().enable();
+    if !self.cancelled.load(Ordering::Relaxed) {
+      notified.await;
+    }
+    self.reason.lock().clone()
+  }
+}
+
+/// Cancels pending `connect()` and `readTelemetry()` calls it was passed to, without touching the driver: a
+/// cancelled `connect()` leaves the connection loop retrying, a cancelled read leaves the connection up. Like an
+/// `AbortSignal` it stays cancelled, so later calls given it reject at once; the TS driver wraps one per signal.
+#[derive(Default)]
+#[napi]
+pub struct CancelToken {
+  state: Arc<CancelState>,
+}
+
+#[napi]
+impl CancelToken {
+  #[napi(constructor)]
+  pub fn new() -> Self {
+    Self::default()
+  }
+
+  /// Rejects the calls waiting on this token, with `reason` in the message.
+  #[napi]
+  pub fn cancel(&self, reason: Option<String>) {
+    {
+      let mut stored = self.state.reason.lock();
+      if self.state.cancelled.load(Ordering::Relaxed) {
+        return;
+      }
+      *stored = reason;
+      self.state.cancelled.store(true, Ordering::Relaxed);
+    }
+    self.state.notify.notify_waiters();
+  }
+
+  #[napi(getter)]
+  pub fn cancelled(&self) -> bool {
+    self.state.cancelled.load(Ordering::Relaxed)
+  }
+}
+
+/// A `CancelToken` argument of an async method, holding on to its state for the length of the call.
+pub struct Cancel(Arc<CancelState>);
+
+impl FromNapiValue for Cancel {
+  unsafe fn from_napi_value(env: sys::napi_env, napi_val: sys::napi_value) -> Result<Self> {
+    let token = CancelToken::from_napi_ref(env, napi_val)?;
+    Ok(Self(Arc::clone(&token.state)))
+  }
+}
+
+/// Runs `call` (`what` names it in errors) until it settles, `timeout_ms` passes or `cancel` fires. Dropping the call
+/// only stops the caller's wait; whatever it started keeps going.
+pub(crate) async fn bounded<T>(
+  what: &str,
+  call: impl Future<Output = Result<T>>,
+  timeout_ms: Option<u32>,
+  cancel: Option<Cancel>,
+) -> Result<T> {
+  let timeout = async {
+    match timeout_ms {
+      Some(timeout_ms) => tokio::time::sleep(Duration::from_millis(timeout_ms.into())).await,
+      None => std::future::pending().await,
+    }
+  };
+  let cancelled = async {
+    match cancel.as_ref() {
+      Some(Cancel(state)) => state.cancelled().await,
+      None => std::future::pending().await,
+    }
+  };
+  tokio::select! {
+    biased;
+    reason = cancelled => Err(Error::from_reason(match reason {
+      Some(reason) => format!("{} cancelled: {}", what, reason),
+      None => format!("{} cancelled", what),
+    })),
+    result = call => result,
+    _ = timeout => Err(Error::from_reason(format!("{} timed out after {} ms", what, timeout_ms.unwrap_or_default()))),
+  }
+}
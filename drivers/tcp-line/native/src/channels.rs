@@ -0,0 +1,346 @@
+use std::collections::{BTreeMap, HashMap, VecDeque};
+
+use chrono::{DateTime, Utc};
+use schemars::JsonSchema;
+use serde::de::Error as _;
+use serde::{Deserialize, Deserializer};
+use tcp_line_core::channel_key;
+
+use crate::{ExtraEntry, Quality, RawTelemetrySample};
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Deserialize, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub(crate) enum Fill {
+  /// Leave the channel out of points that lack it.
+  #[default]
+  None,
+  /// Repeat the last reading, marked `held`.
+  HoldLast,
+  /// Wait for the next reading and fill in a value on the line between the two, marked `interpolated`.
+  Linear,
+}
+
+/// The point field `powerPct` readings are reported under.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Deserialize, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub(crate) enum PowerField {
+  /// `gasPct`, as points have always carried it. Deprecated: the default moves to `powerPct` in the next major.
+  #[default]
+  GasPct,
+  /// `powerPct`, the name the channel has on input and in config keys.
+  PowerPct,
+  /// Both, while consumers move from `gasPct` to `powerPct`.
+  Both,
+}
+
+impl PowerField {
+  /// The names a point carries the reading under.
+  pub fn names(self) -> &'static [&'static str] {
+    match self {
+      PowerField::GasPct => &["gasPct"],
+      PowerField::PowerPct => &["powerPct"],
+      PowerField::Both => &["gasPct", "powerPct"],
+    }
+  }
+
+  /// `value` as a point's `gasPct` and `powerPct`.
+  pub fn split(self, value: Option<f64>) -> (Option<f64>, Option<f64>) {
+    let gas_pct = value.filter(|_| self != PowerField::PowerPct);
+    let power_pct = value.filter(|_| self != PowerField::GasPct);
+    (gas_pct, power_pct)
+  }
+}
+
+#[derive(Debug, Clone, PartialEq, Deserialize, JsonSchema)]
+#[serde(rename_all = "camelCase", default)]
+pub(crate) struct ChannelConfig {
+  /// Leave the channel out of a point unless it moved by more than `deadband` since it was last emitted.
+  pub emit_on_change_only: bool,
+  /// In the channel's unit, e.g. `0.5` for °C; `0` emits any change. Text extras only compare for equality.
+  pub deadband: f64,
+  /// Readings below `min` or above `max` are limited to it and marked `clamped`.
+  pub min: Option<f64>,
+  pub max: Option<f64>,
+  /// How points without a reading of the channel get a value; numeric channels and extras only.
+  pub fill: Fill,
+  /// On sample timestamps: how long `holdLast` repeats a reading, and the longest gap `linear` bridges.
+  pub max_hold_ms: u64,
+}
+
+impl Default for ChannelConfig {
+  fn default() -> Self {
+    Self { emit_on_change_only: false, deadband: 0.0, min: None, max: None, fill: Fill::None, max_hold_ms: 5000 }
+  }
+}
+
+/// Deserializes a map keyed by channel or extra name, taking an alias (`gasPct`) as its channel's key.
+pub(crate) fn by_channel<'de, D, V>(deserializer: D) -> std::result::Result<BTreeMap<String, V>, D::Error>
+where
+  D: Deserializer<'de>,
+  V: Deserialize<'de>,
+{
+  let mut by_channel = BTreeMap::new();
+  for (key, value) in BTreeMap::<String, V>::deserialize(deserializer)? {
+    let channel = channel_key(&key).to_string();
+    if by_channel.insert(channel, value).is_some() {
+      return Err(D::Error::custom(format!("{} is given twice, under its name and an alias", channel_key(&key))));
+    }
+  }
+  Ok(by_channel)
+}
+
+/// Limits readings to their channel's `min`/`max`, before anything else sees the sample.
+pub(crate) fn clamp(channels: &BTreeMap<String, ChannelConfig>, sample: &mut RawTelemetrySample) {
+  if !channels.values().any(|channel| channel.min.is_some() || channel.max.is_some()) {
+    return;
+  }
+  let mut clamped = Vec::new();
+  let mut limit = |key: &str, value: &mut Option<f64>| {
+    let (Some(current), Some(channel)) = (*value, channels.get(key)) else {
+      return;
+    };
+    let limited = channel.max.map_or(current, |max| current.min(max));
+    let limited = channel.min.map_or(limited, |min| limited.max(min));
+    if limited != current {
+      *value = Some(limited);
+      clamped.push(key.to_string());
+    }
+  };
+  for (key, value) in sample.channels_mut() {
+    limit(key, value);
+  }
+  for extra in sample.extras.iter_mut().flatten() {
+    limit(&extra.key, &mut extra.number_value);
+  }
+  for key in clamped {
+    sample.quality.insert(key, Quality::Clamped);
+  }
+}
+
+/// `quality` of a point: every channel it carries (`powerPct` under the `power_field` names), `fresh` unless the
+/// sample marked it, and the marked extras.
+pub(crate) fn point_quality(sample: &RawTelemetrySample, power_field: PowerField) -> BTreeMap<String, String> {
+  let mut quality = BTreeMap::new();
+  for (key, value) in sample.channels() {
+    if value.is_some() {
+      let mark = sample.quality.get(key).copied().unwrap_or(Quality::Fresh);
+      let names = if key == "powerPct" { power_field.names() } else { &[key][..] };
+      for name in names {
+        quality.insert(name.to_string(), mark.as_str().to_string());
+      }
+    }
+  }
+  for extra in sample.extras.iter().flatten() {
+    if let Some(mark) = sample.quality.get(&extra.key) {
+      quality.insert(extra.key.clone(), mark.as_str().to_string());
+    }
+  }
+  quality
+}
+
+/// The sample key of a point field (`gasPct` and `powerPct` are `powerPct`); extras are named alike.
+pub(crate) fn sample_key(name: &str) -> &str {
+  channel_key(name)
+}
+
+/// The channels and extras `sample` carries, by sample key.
+pub(crate) fn keys(sample: &RawTelemetrySample) -> Vec<String> {
+  let channels = sample.channels().into_iter().filter(|(_, value)| value.is_some());
+  let channels = channels.map(|(key, _)| key.to_string());
+  channels.chain(sample.extras.iter().flatten().map(|extra| extra.key.clone())).collect()
+}
+
+/// The numeric reading of `key`, a channel or an extra.
+pub(crate) fn reading(sample: &RawTelemetrySample, key: &str) -> Option<f64> {
+  let channel = channel_key(key);
+  match sample.channels().into_iter().find(|(key, _)| *key == channel) {
+    Some((_, value)) => value,
+    None => sample.extras.iter().flatten().find(|extra| extra.key == key).and_then(|extra| extra.number_value),
+  }
+}
+
+/// Sets the reading of `key`, marked `quality`, adding the extra if `sample` lacks it.
+pub(crate) fn set_reading(sample: &mut RawTelemetrySample, key: &str, value: f64, quality: Quality) {
+  let slot = match sample.channel_mut(key) {
+    Some(slot) => slot,
+    None => {
+      let extras = sample.extras.get_or_insert_with(Vec::new);
+      match extras.iter_mut().find(|extra| extra.key == key) {
+        Some(extra) => extra.number_value = Some(value),
+        None => extras.push(ExtraEntry {
+          key: key.to_string(),
+          number_value: Some(value),
+          text_value: None,
+          bool_value: None,
+        }),
+      }
+      sample.quality.insert(key.to_string(), quality);
+      return;
+    }
+  };
+  *slot = Some(value);
+  sample.quality.insert(key.to_string(), quality);
+}
+
+#[derive(Debug, Clone, Copy)]
+struct Reading {
+  ts: DateTime<Utc>,
+  value: f64,
+}
+
+/// A sample held back until the next reading of its `linear` channels, or until that can't come in time.
+#[derive(Debug)]
+struct Pending {
+  sample: RawTelemetrySample,
+  awaiting: Vec<String>,
+}
+
+/// Fills the gaps of channels with a `fill` policy from their last reading.
+#[derive(Debug, Default)]
+pub(crate) struct GapFiller {
+  last: HashMap<String, Reading>,
+  pending: VecDeque<Pending>,
+}
+
+impl GapFiller {
+  /// Fills `sample`, returning the samples ready to emit in order: none while `linear` channels wait for a reading,
+  /// several once one arrives.
+  pub fn push(
+    &mut self,
+    channels: &BTreeMap<String, ChannelConfig>,
+    mut sample: RawTelemetrySample,
+  ) -> Vec<RawTelemetrySample> {
+    if !channels.values().any(|channel| channel.fill != Fill::None) {
+      let mut ready = self.flush();
+      ready.push(sample);
+      return ready;
+    }
+    let mut awaiting = Vec::new();
+    for (key, channel) in channels.iter().filter(|(_, channel)| channel.fill != Fill::None) {
+      let previous = self.last.get(key).copied();
+      let within = |previous: &Reading| (sample.ts - previous.ts).num_milliseconds() as u64 <= channel.max_hold_ms;
+      match reading(&sample, key) {
+        Some(value) => {
+          let current = Reading { ts: sample.ts, value };
+          for pending in self.pending.iter_mut().filter(|pending| pending.awaiting.contains(key)) {
+            pending.awaiting.retain(|awaited| awaited != key);
+            let Some(previous) = previous.filter(within) else {
+              continue;
+            };
+            let span = (current.ts - previous.ts).num_milliseconds();
+            if span > 0 {
+              let at = (pending.sample.ts - previous.ts).num_milliseconds() as f64 / span as f64;
+              let value = previous.value + (current.value - previous.value) * at;
+              set_reading(&mut pending.sample, key, value, Quality::Interpolated);
+            }
+          }
+          self.last.insert(key.clone(), current);
+        }
+        None => match (channel.fill, previous.filter(within)) {
+          (Fill::HoldLast, Some(previous)) => set_reading(&mut sample, key, previous.value, Quality::Held),
+          (Fill::Linear, Some(_)) => awaiting.push(key.clone()),
+          // The gap is already too long to bridge, so held-back samples stop waiting for this channel.
+          _ => self.pending.iter_mut().for_each(|pending| pending.awaiting.retain(|awaited| awaited != key)),
+        },
+      }
+    }
+    self.pending.push_back(Pending { sample, awaiting });
+    let mut ready = Vec::new();
+    while self.pending.front().is_some_and(|pending| pending.awaiting.is_empty()) {
+      ready.extend(self.pending.pop_front().map(|pending| pending.sample));
+    }
+    ready
+  }
+
+  /// The held-back samples, as far as they could be filled (e.g. when a replay ends).
+  pub fn flush(&mut self) -> Vec<RawTelemetrySample> {
+    self.pending.drain(..).map(|pending| pending.sample).collect()
+  }
+
+  /// Forgets the last readings and drops the held-back samples (e.g. after a reconnect).
+  pub fn reset(&mut self) {
+    self.last.clear();
+    self.pending.clear();
+  }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Emitted {
+  Number(f64),
+  Text(String),
+  Bool(bool),
+}
+
+/// The values `emitOnChangeOnly` channels were last emitted with.
+#[derive(Debug, Default)]
+pub(crate) struct ChangeFilter {
+  emitted: HashMap<String, Emitted>,
+}
+
+impl ChangeFilter {
+  /// Drops the unchanged readings of `emitOnChangeOnly` channels from `sample`. Returns `None` when that leaves no
+  /// channel value or extra, so the point isn't emitted at all.
+  pub fn apply(
+    &mut self,
+    channels: &BTreeMap<String, ChannelConfig>,
+    mut sample: RawTelemetrySample,
+  ) -> Option<RawTelemetrySample> {
+    if !channels.values().any(|channel| channel.emit_on_change_only) {
+      return Some(sample);
+    }
+    let mut dropped = false;
+    let mut number = |key: &str, value: &mut Option<f64>| {
+      if let Some(current) = *value {
+        if !self.changed(channels, key, Emitted::Number(current)) {
+          *value = None;
+          dropped = true;
+        }
+      }
+    };
+    for (key, value) in sample.channels_mut() {
+      number(key, value);
+    }
+    if let Some(extras) = sample.extras.as_mut() {
+      let before = extras.len();
+      extras.retain(|extra| {
+        let value = match (&extra.number_value, &extra.text_value, extra.bool_value) {
+          (Some(number), _, _) => Emitted::Number(*number),
+          (None, Some(text), _) => Emitted::Text(text.clone()),
+          (None, None, Some(flag)) => Emitted::Bool(flag),
+          (None, None, None) => return true,
+        };
+        self.changed(channels, &extra.key, value)
+      });
+      dropped |= extras.len() < before;
+      if extras.is_empty() {
+        sample.extras = None;
+      }
+    }
+    let empty = !sample.has_channels() && sample.extras.is_none();
+    if dropped && empty {
+      return None;
+    }
+    Some(sample)
+  }
+
+  /// Forgets what was emitted, so the next reading of every channel is emitted again (e.g. after a reconnect).
+  pub fn reset(&mut self) {
+    self.emitted.clear();
+  }
+
+  /// Whether `value` is to be emitted for `key`, remembering it if so.
+  fn changed(&mut self, channels: &BTreeMap<String, ChannelConfig>, key: &str, value: Emitted) -> bool {
+    let Some(channel) = channels.get(key).filter(|channel| channel.emit_on_change_only) else {
+      return true;
+    };
+    let unchanged = match (self.emitted.get(key), &value) {
+      (Some(Emitted::Number(last)), Emitted::Number(current)) => (current - last).abs() <= channel.deadband,
+      (Some(last), current) => last == current,
+      (None, _) => false,
+    };
+    if !unchanged {
+      self.emitted.insert(key.to_string(), value);
+    }
+    !unchanged
+  }
+}
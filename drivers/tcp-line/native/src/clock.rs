@@ -0,0 +1,137 @@
+use std::time::Instant;
+
+use chrono::{DateTime, Duration, Utc};
+use schemars::JsonSchema;
+use serde::Deserialize;
+
+use crate::RawTelemetrySample;
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Deserialize, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub(crate) enum ClockMode {
+  /// Use the device's `ts` as sent.
+  #[default]
+  Device,
+  /// Ignore the device's `ts` and stamp samples with the gateway's receive time.
+  Host,
+  /// Shift the device's `ts` by the measured skew.
+  Corrected,
+}
+
+/// The clock `elapsedSeconds` is counted on.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Deserialize, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub(crate) enum ElapsedClock {
+  /// Sample timestamps, so a device clock step moves it.
+  #[default]
+  Device,
+  /// The gateway's monotonic clock at receipt; points then carry `deviceElapsedSeconds` and `hostElapsedSeconds`.
+  Host,
+}
+
+#[derive(Debug, Clone, PartialEq, Deserialize, JsonSchema)]
+#[serde(rename_all = "camelCase", default)]
+pub(crate) struct ClockConfig {
+  pub mode: ClockMode,
+  /// Samples after each connect the skew is estimated from (their median); later samples reuse that estimate.
+  pub calibration_samples: u32,
+  pub elapsed: ElapsedClock,
+}
+
+impl Default for ClockConfig {
+  fn default() -> Self {
+    Self { mode: ClockMode::Device, calibration_samples: 10, elapsed: ElapsedClock::Device }
+  }
+}
+
+/// Where `elapsedSeconds` counts from: the first sample's timestamp and, when it had one, host receive time.
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct ElapsedBaseline {
+  device: DateTime<Utc>,
+  host: Option<DateTime<Utc>>,
+}
+
+impl ElapsedBaseline {
+  pub fn of(sample: &RawTelemetrySample) -> Self {
+    Self { device: sample.ts, host: sample.host_received_at }
+  }
+
+  /// Seconds from the baseline to `sample` on the device clock and, where both have a receive time, on the host's;
+  /// never negative.
+  pub fn elapsed(&self, sample: &RawTelemetrySample) -> (f64, Option<f64>) {
+    let host = self.host.zip(sample.host_received_at).map(|(from, to)| seconds_between(from, to));
+    (seconds_between(self.device, sample.ts), host)
+  }
+}
+
+/// Host receive clock and the device-to-host skew measured against it.
+pub(crate) struct DeviceClock {
+  anchor_instant: Instant,
+  anchor_utc: DateTime<Utc>,
+  calibration_ms: Vec<f64>,
+  skew_ms: Option<f64>,
+}
+
+impl DeviceClock {
+  pub fn new() -> Self {
+    Self { anchor_instant: Instant::now(), anchor_utc: Utc::now(), calibration_ms: Vec::new(), skew_ms: None }
+  }
+
+  /// Wall-clock time derived from a monotonic clock, so host clock steps (NTP, DST fixes) don't reorder samples.
+  pub fn receive_time(&self) -> DateTime<Utc> {
+    let elapsed = Duration::from_std(self.anchor_instant.elapsed()).unwrap_or_else(|_| Duration::zero());
+    self.anchor_utc + elapsed
+  }
+
+  /// Device minus host time in milliseconds (positive when the device clock is ahead); `None` until measured.
+  pub fn skew_ms(&self) -> Option<f64> {
+    self.skew_ms
+  }
+
+  /// Re-estimates the skew from the next connection's samples; the previous estimate is reported until then.
+  pub fn reset(&mut self) {
+    self.calibration_ms.clear();
+  }
+
+  /// Returns the timestamp to use for a sample received at `received` (a `receive_time`). `device_ts` is `None` when
+  /// the line carried no `ts`.
+  pub fn stamp(
+    &mut self,
+    config: &ClockConfig,
+    device_ts: Option<DateTime<Utc>>,
+    received: DateTime<Utc>,
+  ) -> Option<DateTime<Utc>> {
+    if let Some(device_ts) = device_ts {
+      self.observe(device_ts, received, config.calibration_samples);
+    }
+    match config.mode {
+      ClockMode::Device => device_ts,
+      ClockMode::Host => Some(received),
+      ClockMode::Corrected => match (device_ts, self.skew_ms) {
+        // A device timestamp so far off that the correction overflows falls back to the receive time.
+        (Some(device_ts), Some(skew_ms)) => {
+          let skew = Duration::microseconds((skew_ms * 1000.0).round() as i64);
+          Some(device_ts.checked_sub_signed(skew).unwrap_or(received))
+        }
+        _ => Some(received),
+      },
+    }
+  }
+
+  fn observe(&mut self, device_ts: DateTime<Utc>, received: DateTime<Utc>, samples: u32) {
+    if self.calibration_ms.len() >= samples.max(1) as usize {
+      return;
+    }
+    let offset = device_ts.signed_duration_since(received).num_microseconds().unwrap_or(i64::MAX) as f64 / 1000.0;
+    self.calibration_ms.push(offset);
+    let mut sorted = self.calibration_ms.clone();
+    sorted.sort_by(|a, b| a.total_cmp(b));
+    let mid = sorted.len() / 2;
+    let median = if sorted.len().is_multiple_of(2) { (sorted[mid - 1] + sorted[mid]) / 2.0 } else { sorted[mid] };
+    self.skew_ms = Some(median);
+  }
+}
+
+fn seconds_between(from: DateTime<Utc>, to: DateTime<Utc>) -> f64 {
+  to.signed_duration_since(from).num_milliseconds().max(0) as f64 / 1000.0
+}
@@ -0,0 +1,345 @@
+use std::sync::atomic::{AtomicI64, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+
+use parking_lot::Mutex;
+use schemars::JsonSchema;
+use serde::Deserialize;
+use thiserror::Error;
+use tokio::io::AsyncWriteExt;
+use tokio::net::tcp::OwnedWriteHalf;
+use tokio::sync::oneshot;
+use tokio::task::JoinHandle;
+
+#[derive(Debug, Clone, PartialEq, Deserialize, JsonSchema)]
+#[serde(rename_all = "camelCase", default)]
+pub(crate) struct CommandConfig {
+  pub line_ending: String,
+  /// The device echoes every command back; echoed lines are swallowed instead of parsed as telemetry.
+  pub echo: bool,
+  /// When set, `send_command` waits for a line starting with this prefix (e.g. `OK`).
+  pub ack_prefix: Option<String>,
+  /// A line starting with this prefix (e.g. `ERR`) rejects the pending command.
+  pub nak_prefix: Option<String>,
+  pub ack_timeout_ms: u64,
+  /// Bound on writing an alarm rule's `command`, waiting included; past it the interlock is logged as failed.
+  pub interlock_timeout_ms: u64,
+  pub templates: CommandTemplates,
+}
+
+impl Default for CommandConfig {
+  fn default() -> Self {
+    Self {
+      line_ending: "\n".to_string(),
+      echo: false,
+      ack_prefix: None,
+      nak_prefix: None,
+      ack_timeout_ms: 1000,
+      interlock_timeout_ms: 250,
+      templates: CommandTemplates::default(),
+    }
+  }
+}
+
+#[derive(Debug, Clone, PartialEq, Deserialize, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct HeartbeatConfig {
+  pub interval_ms: u64,
+  /// Written verbatim followed by `commands.lineEnding`; empty sends just the line ending.
+  #[serde(default)]
+  pub payload: String,
+}
+
+/// Wire encodings for the typed setpoint methods; `{value}` is replaced with the requested value, e.g. `OT1;{value}`.
+#[derive(Debug, Clone, Default, PartialEq, Deserialize, JsonSchema)]
+#[serde(rename_all = "camelCase", default)]
+pub(crate) struct CommandTemplates {
+  pub power_pct: Option<String>,
+  pub fan_pct: Option<String>,
+  pub drum_rpm: Option<String>,
+}
+
+#[derive(Debug, Clone, Copy)]
+pub(crate) enum Setpoint {
+  PowerPct,
+  FanPct,
+  DrumRpm,
+}
+
+impl Setpoint {
+  fn key(self) -> &'static str {
+    match self {
+      Setpoint::PowerPct => "powerPct",
+      Setpoint::FanPct => "fanPct",
+      Setpoint::DrumRpm => "drumRpm",
+    }
+  }
+
+  fn validate(self, value: f64) -> std::result::Result<(), CommandError> {
+    let valid = match self {
+      Setpoint::PowerPct | Setpoint::FanPct => (0.0..=100.0).contains(&value),
+      Setpoint::DrumRpm => value.is_finite() && value >= 0.0,
+    };
+    if valid {
+      Ok(())
+    } else {
+      Err(CommandError::InvalidValue(self.key(), value))
+    }
+  }
+}
+
+impl CommandTemplates {
+  /// Renders the command line for a setpoint, validating the range first.
+  pub fn render(&self, setpoint: Setpoint, value: f64) -> std::result::Result<String, CommandError> {
+    setpoint.validate(value)?;
+    let template = match setpoint {
+      Setpoint::PowerPct => self.power_pct.as_deref(),
+      Setpoint::FanPct => self.fan_pct.as_deref(),
+      Setpoint::DrumRpm => self.drum_rpm.as_deref(),
+    }
+    .ok_or(CommandError::NoTemplate(setpoint.key()))?;
+    Ok(template.replace("{value}", &format_value(value)))
+  }
+}
+
+/// Whole numbers go out without a decimal point (`70`, not `70.0`); fractions keep at most two decimals.
+fn format_value(value: f64) -> String {
+  if value.fract() == 0.0 {
+    format!("{}", value as i64)
+  } else {
+    let formatted = format!("{:.2}", value);
+    formatted.trim_end_matches('0').trim_end_matches('.').to_string()
+  }
+}
+
+#[derive(Debug, Error)]
+pub(crate) enum CommandError {
+  #[error("not connected")]
+  NotConnected,
+  #[error("command write failed: {0}")]
+  Write(#[from] std::io::Error),
+  #[error("command rejected: {0}")]
+  Rejected(String),
+  #[error("command ack timed out")]
+  AckTimeout,
+  #[error("no command template configured for {0}")]
+  NoTemplate(&'static str),
+  #[error("{0} value {1} out of range")]
+  InvalidValue(&'static str, f64),
+  #[error("interlock write timed out after {0} ms")]
+  InterlockTimeout(u64),
+}
+
+struct PendingCommand {
+  line: String,
+  echo_seen: bool,
+  reply: oneshot::Sender<std::result::Result<String, CommandError>>,
+}
+
+/// Write side of the device connection plus the bookkeeping needed to match echoes and acks to the in-flight command.
+pub(crate) struct CommandChannel {
+  config: CommandConfig,
+  writer: tokio::sync::Mutex<Option<OwnedWriteHalf>>,
+  /// Held by a command from its write until its ack, so commands go one at a time while only the write itself takes
+  /// `writer`; an interlock can get in while a command waits.
+  turn: tokio::sync::Mutex<()>,
+  pending: Mutex<Option<PendingCommand>>,
+  /// Everything written to the device, for `bytesSent`.
+  sent: AtomicI64,
+}
+
+impl CommandChannel {
+  pub fn new(config: CommandConfig) -> Self {
+    Self {
+      config,
+      writer: tokio::sync::Mutex::new(None),
+      turn: tokio::sync::Mutex::new(()),
+      pending: Mutex::new(None),
+      sent: AtomicI64::new(0),
+    }
+  }
+
+  pub fn bytes_sent(&self) -> i64 {
+    self.sent.load(Ordering::Relaxed)
+  }
+
+  /// Counts bytes written to this channel's connection outside of it, e.g. by the handshake.
+  pub fn count_sent(&self, bytes: usize) {
+    self.sent.fetch_add(bytes as i64, Ordering::Relaxed);
+  }
+
+  pub fn reset_sent(&self) {
+    self.sent.store(0, Ordering::Relaxed);
+  }
+
+  pub fn templates(&self) -> &CommandTemplates {
+    &self.config.templates
+  }
+
+  pub async fn attach(&self, writer: OwnedWriteHalf) {
+    *self.writer.lock().await = Some(writer);
+  }
+
+  pub async fn detach(&self) {
+    self.writer.lock().await.take();
+    if let Some(pending) = self.pending.lock().take() {
+      let _ = pending.reply.send(Err(CommandError::NotConnected));
+    }
+  }
+
+  /// Writes one command line. Commands are serialized: each keeps its turn until the ack (if any) arrives.
+  pub async fn send(&self, line: &str) -> std::result::Result<Option<String>, CommandError> {
+    let line = line.trim_end_matches(['\r', '\n']);
+    let _turn = self.turn.lock().await;
+    let receiver = {
+      let mut writer_guard = self.writer.lock().await;
+      let writer = writer_guard.as_mut().ok_or(CommandError::NotConnected)?;
+
+      let wants_reply = self.config.echo || self.config.ack_prefix.is_some();
+      let receiver = if wants_reply {
+        let (reply, receiver) = oneshot::channel();
+        *self.pending.lock() = Some(PendingCommand { line: line.to_string(), echo_seen: false, reply });
+        Some(receiver)
+      } else {
+        None
+      };
+
+      let payload = self.payload(line);
+      if let Err(err) = write_all(writer, &payload).await {
+        self.pending.lock().take();
+        return Err(err.into());
+      }
+      self.count_sent(payload.len());
+      receiver
+    };
+
+    let Some(receiver) = receiver else {
+      return Ok(None);
+    };
+    match tokio::time::timeout(Duration::from_millis(self.config.ack_timeout_ms), receiver).await {
+      Ok(Ok(result)) => result.map(|reply| if self.config.ack_prefix.is_some() { Some(reply) } else { None }),
+      Ok(Err(_)) => Err(CommandError::NotConnected),
+      Err(_) => {
+        self.pending.lock().take();
+        Err(CommandError::AckTimeout)
+      }
+    }
+  }
+
+  /// Writes a line without waiting for an ack. Returns `Ok(false)` when a command currently has its turn.
+  pub async fn try_write(&self, line: &str) -> std::result::Result<bool, CommandError> {
+    let Ok(_turn) = self.turn.try_lock() else {
+      return Ok(false);
+    };
+    let mut writer_guard = self.writer.lock().await;
+    let writer = writer_guard.as_mut().ok_or(CommandError::NotConnected)?;
+    let payload = self.payload(line);
+    write_all(writer, &payload).await?;
+    self.count_sent(payload.len());
+    Ok(true)
+  }
+
+  /// Writes an interlock line ahead of queued commands and without waiting for an ack, giving up after `timeout_ms`.
+  /// A command waiting for its ack keeps waiting, and may take the device's reply to this line as it.
+  pub async fn interlock(&self, line: &str, timeout_ms: u64) -> std::result::Result<(), CommandError> {
+    let write = async {
+      let mut writer_guard = self.writer.lock().await;
+      let writer = writer_guard.as_mut().ok_or(CommandError::NotConnected)?;
+      let payload = self.payload(line.trim_end_matches(['\r', '\n']));
+      write_all(writer, &payload).await?;
+      self.count_sent(payload.len());
+      Ok(())
+    };
+    tokio::time::timeout(Duration::from_millis(timeout_ms), write)
+      .await
+      .unwrap_or(Err(CommandError::InterlockTimeout(timeout_ms)))
+  }
+
+  fn payload(&self, line: &str) -> Vec<u8> {
+    let mut payload = line.as_bytes().to_vec();
+    payload.extend_from_slice(self.config.line_ending.as_bytes());
+    payload
+  }
+
+  /// Called for every received line; returns true when the line belonged to the pending command and must not be parsed.
+  pub fn intercept(&self, line: &str) -> bool {
+    let mut pending_guard = self.pending.lock();
+    let Some(pending) = pending_guard.as_mut() else {
+      return false;
+    };
+    let trimmed = line.trim();
+
+    if self.config.echo && !pending.echo_seen && trimmed == pending.line.trim() {
+      pending.echo_seen = true;
+      if self.config.ack_prefix.is_none() {
+        let pending = pending_guard.take().expect("pending command");
+        let _ = pending.reply.send(Ok(trimmed.to_string()));
+      }
+      return true;
+    }
+    if self.config.ack_prefix.as_deref().is_some_and(|prefix| trimmed.starts_with(prefix)) {
+      let pending = pending_guard.take().expect("pending command");
+      let _ = pending.reply.send(Ok(trimmed.to_string()));
+      return true;
+    }
+    if self.config.nak_prefix.as_deref().is_some_and(|prefix| trimmed.starts_with(prefix)) {
+      let pending = pending_guard.take().expect("pending command");
+      let _ = pending.reply.send(Err(CommandError::Rejected(trimmed.to_string())));
+      return true;
+    }
+    false
+  }
+}
+
+async fn write_all(writer: &mut OwnedWriteHalf, payload: &[u8]) -> std::io::Result<()> {
+  writer.write_all(payload).await?;
+  writer.flush().await
+}
+
+/// Periodic keepalive writer for one connection; aborted when dropped. A failed write is reported through
+/// `failed()` so the read loop can tear the connection down and reconnect.
+pub(crate) struct Heartbeat {
+  handle: Option<JoinHandle<()>>,
+  failed: Option<oneshot::Receiver<String>>,
+}
+
+impl Heartbeat {
+  pub fn spawn(config: Option<&HeartbeatConfig>, channel: Arc<CommandChannel>) -> Self {
+    let Some(config) = config.filter(|config| config.interval_ms > 0).cloned() else {
+      return Self { handle: None, failed: None };
+    };
+    let (report, failed) = oneshot::channel();
+    let handle = tokio::spawn(async move {
+      let period = Duration::from_millis(config.interval_ms);
+      let mut ticker = tokio::time::interval_at(tokio::time::Instant::now() + period, period);
+      loop {
+        ticker.tick().await;
+        // A busy writer means a command is in flight, which keeps the session alive just as well.
+        if let Err(err) = channel.try_write(&config.payload).await {
+          let _ = report.send(format!("heartbeat failed: {}", err));
+          return;
+        }
+      }
+    });
+    Self { handle: Some(handle), failed: Some(failed) }
+  }
+
+  /// Resolves with the failure reason once a heartbeat write fails; never resolves otherwise.
+  pub async fn failed(&mut self) -> String {
+    if let Some(failed) = self.failed.as_mut() {
+      if let Ok(reason) = failed.await {
+        return reason;
+      }
+      self.failed = None;
+    }
+    std::future::pending().await
+  }
+}
+
+impl Drop for Heartbeat {
+  fn drop(&mut self) {
+    if let Some(handle) = self.handle.take() {
+      handle.abort();
+    }
+  }
+}
@@ -0,0 +1,672 @@
+use std::collections::{BTreeMap, HashSet};
+use std::sync::atomic::Ordering;
+use std::sync::Arc;
+
+use chrono::{DateTime, Utc};
+use napi::bindgen_prelude::*;
+use napi_derive::napi;
+use serde::Deserialize;
+use tokio::sync::{broadcast, mpsc};
+use tokio::task::JoinSet;
+use tracing::{info, warn};
+
+use crate::aggregate::TelemetryAggregate;
+use crate::ble::BleDriverNative;
+#[cfg(target_os = "linux")]
+use crate::can::CanDriverNative;
+use crate::cancel::{self, Cancel};
+use crate::channels;
+use crate::csv_record::ColumnMismatch;
+use crate::events::{DriverError, DriverErrorCode, Subscribers};
+use crate::http_poll::HttpPollDriverNative;
+use crate::opcua::OpcUaDriverNative;
+use crate::phases::SessionSummary;
+use crate::replay::ReplayDriverNative;
+use crate::recent_errors::RecentParseError;
+use crate::session_log::SessionLogSummary;
+use crate::simulator::SimulatedDriverNative;
+use crate::snmp::SnmpDriverNative;
+use crate::stats::DetailedMetrics;
+use crate::stream::TelemetryStream;
+use crate::subscription::TelemetrySubscription;
+use crate::{
+  CsvConfig, DriverInner, DriverMetrics, DriverState, DriverStatus, ExtraEntry, FrameFormat, PipelineConfig, Quality,
+  RawTelemetrySample, ReconnectConfig, TcpLineDriverConfig, TcpLineDriverNative, TelemetryPoint,
+};
+
+#[derive(Debug, Clone, Copy, PartialEq, Deserialize)]
+enum SourceKind {
+  #[serde(rename = "tcp")]
+  Tcp,
+  #[serde(rename = "replay")]
+  Replay,
+  #[serde(rename = "simulated")]
+  Simulated,
+  #[serde(rename = "opcua")]
+  OpcUa,
+  #[serde(rename = "httpPoll")]
+  HttpPoll,
+  #[serde(rename = "ble")]
+  Ble,
+  #[serde(rename = "can")]
+  Can,
+  #[serde(rename = "snmp")]
+  Snmp,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct SourceConfig {
+  name: String,
+  kind: SourceKind,
+  /// The config the source's own driver takes, e.g. a TCP line driver config for `tcp`.
+  connection: serde_json::Value,
+  /// Fields (`btC`, `gasPct` or `powerPct`, ..., or extra keys) this source contributes; all of them when unset.
+  #[serde(default)]
+  channels: Option<Vec<String>>,
+  /// Whether the composite needs this source to count as CONNECTED; an optional source only adds fields while up.
+  #[serde(default = "default_required")]
+  required: bool,
+}
+
+fn default_required() -> bool {
+  true
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+enum EmitOn {
+  /// Every point from any source produces a merged sample.
+  #[default]
+  Any,
+  /// Only points from the first source do; the others fill in their latest values.
+  First,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct CompositeDriverConfig {
+  /// In priority order: when several sources carry a field, the first one's value wins.
+  sources: Vec<SourceConfig>,
+  #[serde(default)]
+  emit_on: EmitOn,
+  /// A source's latest point joins a merge only if it is at most this far from the point that triggered it.
+  #[serde(default = "default_max_skew_ms")]
+  max_skew_ms: u64,
+  #[serde(flatten)]
+  pipeline: PipelineConfig,
+}
+
+fn default_max_skew_ms() -> u64 {
+  2000
+}
+
+impl CompositeDriverConfig {
+  fn line_config(&self) -> TcpLineDriverConfig {
+    TcpLineDriverConfig {
+      // Outages are the sources' business; connect() should wait through them rather than give up on DISCONNECTED.
+      reconnect: ReconnectConfig { enabled: true, ..ReconnectConfig::disabled() },
+      ..TcpLineDriverConfig::for_source(
+        FrameFormat::Jsonl,
+        CsvConfig {
+          has_header: false,
+          columns: Vec::new(),
+          delimiter: ",".to_string(),
+          column_mismatch: ColumnMismatch::default(),
+        },
+        self.pipeline.clone(),
+      )
+    }
+  }
+
+  fn validate(&self) -> std::result::Result<(), String> {
+    if self.sources.is_empty() {
+      return Err("sources must list at least one source".to_string());
+    }
+    let mut names = HashSet::new();
+    for source in self.sources.iter() {
+      if source.name.is_empty() {
+        return Err("sources: name is required".to_string());
+      }
+      if !names.insert(source.name.as_str()) {
+        return Err(format!("sources: duplicate name {}", source.name));
+      }
+      if source.channels.as_ref().is_some_and(Vec::is_empty) {
+        return Err(format!("sources.{}.channels must list at least one channel", source.name));
+      }
+    }
+    if !self.sources.iter().any(|source| source.required) {
+      return Err("sources: at least one source must be required".to_string());
+    }
+    if let Some(setting) = self.pipeline.reading_setting() {
+      return Err(format!("{} is not supported by composites; set it on the sources", setting));
+    }
+    Ok(())
+  }
+}
+
+enum SourceDriver {
+  Tcp(TcpLineDriverNative),
+  Replay(Box<ReplayDriverNative>),
+  Simulated(SimulatedDriverNative),
+  OpcUa(OpcUaDriverNative),
+  HttpPoll(HttpPollDriverNative),
+  Ble(BleDriverNative),
+  #[cfg(target_os = "linux")]
+  Can(CanDriverNative),
+  Snmp(SnmpDriverNative),
+}
+
+impl SourceDriver {
+  fn open(kind: SourceKind, config_json: String, machine_id: String) -> Result<Self> {
+    Ok(match kind {
+      SourceKind::Tcp => Self::Tcp(TcpLineDriverNative::new(config_json, machine_id)?),
+      SourceKind::Replay => Self::Replay(Box::new(ReplayDriverNative::new(config_json, machine_id)?)),
+      SourceKind::Simulated => Self::Simulated(SimulatedDriverNative::new(config_json, machine_id)?),
+      SourceKind::OpcUa => Self::OpcUa(OpcUaDriverNative::new(config_json, machine_id)?),
+      SourceKind::HttpPoll => Self::HttpPoll(HttpPollDriverNative::new(config_json, machine_id)?),
+      SourceKind::Ble => Self::Ble(BleDriverNative::new(config_json, machine_id)?),
+      #[cfg(target_os = "linux")]
+      SourceKind::Can => Self::Can(CanDriverNative::new(config_json, machine_id)?),
+      #[cfg(not(target_os = "linux"))]
+      SourceKind::Can => return Err(Error::from_reason("invalid config: the can driver is only available on Linux")),
+      SourceKind::Snmp => Self::Snmp(SnmpDriverNative::new(config_json, machine_id)?),
+    })
+  }
+
+  fn inner(&self) -> &Arc<DriverInner> {
+    match self {
+      Self::Tcp(driver) => &driver.inner,
+      Self::Replay(driver) => &driver.inner,
+      Self::Simulated(driver) => &driver.inner,
+      Self::OpcUa(driver) => &driver.inner,
+      Self::HttpPoll(driver) => &driver.inner,
+      Self::Ble(driver) => &driver.inner,
+      #[cfg(target_os = "linux")]
+      Self::Can(driver) => &driver.inner,
+      Self::Snmp(driver) => &driver.inner,
+    }
+  }
+
+  async fn connect(&self) -> Result<()> {
+    match self {
+      Self::Tcp(driver) => driver.connect(None, None).await,
+      Self::Replay(driver) => driver.connect(None, None).await,
+      Self::Simulated(driver) => driver.connect(None, None).await,
+      Self::OpcUa(driver) => driver.connect(None, None).await,
+      Self::HttpPoll(driver) => driver.connect(None, None).await,
+      Self::Ble(driver) => driver.connect(None, None).await,
+      #[cfg(target_os = "linux")]
+      Self::Can(driver) => driver.connect(None, None).await,
+      Self::Snmp(driver) => driver.connect(None, None).await,
+    }
+  }
+}
+
+struct Source {
+  name: String,
+  kind: SourceKind,
+  channels: Option<Vec<String>>,
+  required: bool,
+  driver: SourceDriver,
+}
+
+impl Source {
+  fn takes(&self, field: &str) -> bool {
+    let field = channels::sample_key(field);
+    self.channels.as_ref().is_none_or(|listed| listed.iter().any(|channel| channels::sample_key(channel) == field))
+  }
+}
+
+#[derive(Debug, Clone)]
+#[napi(object)]
+pub struct CompositeSourceStatus {
+  pub name: String,
+  pub kind: String,
+  pub required: bool,
+  pub state: DriverState,
+  pub metrics: DriverMetrics,
+}
+
+/// Runs several drivers for one machine and merges their latest points into one sample stream, e.g. BT/ET from a
+/// TCP feed with gas pressure from a poller. Each source keeps its own pipeline (offsets, reconnects); the merged
+/// samples then go through this driver's pacing, dedupe and roast event detection.
+#[napi]
+pub struct CompositeDriverNative {
+  config: CompositeDriverConfig,
+  sources: Arc<Vec<Source>>,
+  inner: Arc<DriverInner>,
+}
+
+#[napi]
+impl CompositeDriverNative {
+  /// Sources run as `<machineId>/<name>`, so their metrics stay apart from the merged stream's.
+  #[napi(constructor)]
+  pub fn new(config_json: String, machine_id: String) -> Result<Self> {
+    let config: CompositeDriverConfig = serde_json::from_str(&config_json)
+      .map_err(|err| Error::from_reason(format!("invalid config: {}", err)))?;
+    config.validate().map_err(|err| Error::from_reason(format!("invalid config: {}", err)))?;
+    let sources = config
+      .sources
+      .iter()
+      .map(|source| {
+        let driver =
+          SourceDriver::open(source.kind, source.connection.to_string(), format!("{}/{}", machine_id, source.name))
+            .map_err(|err| {
+              let reason = err.reason.strip_prefix("invalid config: ").unwrap_or(&err.reason);
+              Error::from_reason(format!("invalid config: sources.{}: {}", source.name, reason))
+            })?;
+        Ok(Source {
+          name: source.name.clone(),
+          kind: source.kind,
+          channels: source.channels.clone(),
+          required: source.required,
+          driver,
+        })
+      })
+      .collect::<Result<Vec<_>>>()?;
+    let inner = DriverInner::new(config.line_config(), machine_id);
+    Ok(Self { config, sources: Arc::new(sources), inner })
+  }
+
+  /// Starts every source; resolves once all required ones are connected, rejects once one of them is down for good.
+  #[napi(ts_args_type = "timeoutMs?: number, cancel?: CancelToken")]
+  pub async fn connect(&self, timeout_ms: Option<u32>, cancel: Option<Cancel>) -> Result<()> {
+    let sources = Arc::clone(&self.sources);
+    let merge = MergeConfig { emit_on: self.config.emit_on, max_skew_ms: self.config.max_skew_ms };
+    let since = self.inner.connected_seq.load(Ordering::Relaxed);
+    self.inner.ensure_source(move |inner| run_composite(inner, sources, merge));
+    cancel::bounded("connect", self.inner.wait_for_connected(since), timeout_ms, cancel).await
+  }
+
+  #[napi(ts_args_type = "timeoutMs?: number, cancel?: CancelToken")]
+  pub async fn read_telemetry(&self, timeout_ms: Option<u32>, cancel: Option<Cancel>) -> Result<TelemetryPoint> {
+    self.inner.read_telemetry(timeout_ms, cancel).await
+  }
+
+  #[napi(ts_args_type = "timeoutMs: number, cancel?: CancelToken")]
+  pub async fn read_telemetry_with_timeout(
+    &self,
+    timeout_ms: u32,
+    cancel: Option<Cancel>,
+  ) -> Result<Option<TelemetryPoint>> {
+    self.inner.read_telemetry_with_timeout(timeout_ms, cancel).await
+  }
+
+  #[napi]
+  pub async fn peek_telemetry(&self) -> Result<TelemetryPoint> {
+    self.inner.peek_telemetry().await
+  }
+
+  #[napi]
+  pub async fn disconnect(&self) -> Result<()> {
+    self.inner.disconnect().await;
+    for source in self.sources.iter() {
+      source.driver.inner().disconnect().await;
+    }
+    Ok(())
+  }
+
+  /// The merged stream's status; its state is CONNECTED while every required source is.
+  #[napi]
+  pub fn get_status(&self) -> Result<DriverStatus> {
+    Ok(self.inner.get_status())
+  }
+
+  /// One entry per source, in config order.
+  #[napi]
+  pub fn get_source_statuses(&self) -> Vec<CompositeSourceStatus> {
+    self
+      .sources
+      .iter()
+      .map(|source| {
+        let status = source.driver.inner().get_status();
+        CompositeSourceStatus {
+          name: source.name.clone(),
+          kind: source.kind.name().to_string(),
+          required: source.required,
+          state: status.state,
+          metrics: status.metrics,
+        }
+      })
+      .collect()
+  }
+
+  #[napi]
+  pub fn get_metrics_detailed(&self) -> DetailedMetrics {
+    self.inner.get_metrics_detailed()
+  }
+
+  /// The last 50 parse failures, oldest first, with the offending line cut to 256 characters.
+  #[napi]
+  pub fn get_recent_errors(&self) -> Vec<RecentParseError> {
+    self.inner.get_recent_errors()
+  }
+
+  /// Async-iterator-shaped stream of samples; see `TelemetryStream`.
+  #[napi]
+  pub fn telemetry_stream(&self) -> TelemetryStream {
+    TelemetryStream::new(Arc::clone(&self.inner))
+  }
+
+  /// A handle of its own onto every point published from now on; see `TelemetrySubscription`.
+  #[napi]
+  pub fn subscribe(&self) -> TelemetrySubscription {
+    TelemetrySubscription::new(Arc::clone(&self.inner))
+  }
+
+  /// Takes up to `maxSamples` (default all) queued samples, oldest first; empty unless `queue` is configured.
+  #[napi]
+  pub fn drain_samples(&self, max_samples: Option<u32>) -> Vec<TelemetryPoint> {
+    self.inner.drain_samples(max_samples)
+  }
+
+  /// Per-channel min/max/mean/count over the samples of the last `windowSeconds` (by sample timestamp).
+  #[napi]
+  pub fn read_aggregate(&self, window_seconds: f64) -> Result<TelemetryAggregate> {
+    self.inner.read_aggregate(window_seconds)
+  }
+
+  /// The current roast's phases from CHARGE, with their durations, end temperatures and mean RoR; `null` before
+  /// CHARGE. Needs `roastEvents`.
+  #[napi]
+  pub fn get_session_summary(&self) -> Option<SessionSummary> {
+    self.inner.session_summary()
+  }
+
+  /// Collects every emitted point from now on and writes them to `path` on `stopLogging`. `format` is `csv` or
+  /// `parquet` (default: from the extension, else csv); the header lists the point fields followed by one column per
+  /// extra.
+  #[napi]
+  pub fn start_logging(&self, path: String, format: Option<String>) -> Result<()> {
+    self.inner.start_logging(path, format)
+  }
+
+  /// Writes the log begun by `startLogging` and returns what was written (`null` when not logging).
+  #[napi]
+  pub fn stop_logging(&self) -> Result<Option<SessionLogSummary>> {
+    self.inner.stop_logging()
+  }
+
+  /// Registers a callback for state transitions (CONNECTING, CONNECTED, DISCONNECTED, ...).
+  #[napi(ts_args_type = "callback: (event: StateChangeEvent) => void")]
+  pub fn on_state_change(&self, env: Env, callback: JsFunction) -> Result<()> {
+    self.inner.state_events.subscribe(&env, callback)
+  }
+
+  /// Registers a callback for errors from any source, and for the composite failing.
+  #[napi(ts_args_type = "callback: (error: DriverError) => void")]
+  pub fn on_error(&self, env: Env, callback: JsFunction) -> Result<()> {
+    let mut all: Vec<&Subscribers<DriverError>> =
+      self.sources.iter().map(|source| &source.driver.inner().error_events).collect();
+    all.push(&self.inner.error_events);
+    Subscribers::subscribe_all(&env, callback, &all)
+  }
+
+  /// Registers a callback for detected roast events (requires `roastEvents` in the config).
+  #[napi(ts_args_type = "callback: (event: RoastEvent) => void")]
+  pub fn on_event(&self, env: Env, callback: JsFunction) -> Result<()> {
+    self.inner.roast_events.subscribe(&env, callback)
+  }
+}
+
+impl SourceKind {
+  fn name(self) -> &'static str {
+    match self {
+      Self::Tcp => "tcp",
+      Self::Replay => "replay",
+      Self::Simulated => "simulated",
+      Self::OpcUa => "opcua",
+      Self::HttpPoll => "httpPoll",
+      Self::Ble => "ble",
+      Self::Can => "can",
+      Self::Snmp => "snmp",
+    }
+  }
+}
+
+#[derive(Debug, Clone, Copy)]
+struct MergeConfig {
+  emit_on: EmitOn,
+  max_skew_ms: u64,
+}
+
+/// Points a source may get ahead of the merge loop by before the watcher waits.
+const EVENT_BUFFER: usize = 256;
+
+enum SourceEvent {
+  Point(usize, Box<TelemetryPoint>),
+  /// The source changed state (or might have); re-derive the composite state.
+  State(usize),
+}
+
+/// Starts the sources and merges their points until a required source is down for good, which fails the composite
+/// and stops the rest. `disconnect()` aborts this task, and with it the watchers.
+async fn run_composite(inner: Arc<DriverInner>, sources: Arc<Vec<Source>>, merge: MergeConfig) {
+  let (sender, mut events) = mpsc::channel(EVENT_BUFFER);
+  let mut tasks = JoinSet::new();
+  // Subscribed before the sources start so their first points aren't missed.
+  for (idx, source) in sources.iter().enumerate() {
+    let child = Arc::clone(source.driver.inner());
+    let points = child.subscribe_points();
+    tasks.spawn(watch_source(idx, child, points, sender.clone()));
+  }
+  drop(sender);
+  for idx in 0..sources.len() {
+    let sources = Arc::clone(&sources);
+    // A source that fails to connect shows in its state, which its watcher reports.
+    tasks.spawn(async move {
+      let _ = sources[idx].driver.connect().await;
+    });
+  }
+
+  let mut latest: Vec<Option<(DateTime<Utc>, TelemetryPoint)>> = vec![None; sources.len()];
+  let mut failure = None;
+  while let Some(event) = events.recv().await {
+    match event {
+      SourceEvent::Point(idx, point) => {
+        let Ok(ts) = DateTime::parse_from_rfc3339(&point.ts) else {
+          continue;
+        };
+        let ts = ts.with_timezone(&Utc);
+        latest[idx] = Some((ts, *point));
+        if merge.emit_on == EmitOn::First && idx != 0 {
+          continue;
+        }
+        if let Some(sample) = merge_latest(&sources, &latest, ts, merge.max_skew_ms) {
+          inner.wait_for_queue_space().await;
+          inner.count_line(0);
+          inner.accept_sample(sample);
+        }
+      }
+      SourceEvent::State(idx) => {
+        // A source that is down has no current values to offer.
+        if *sources[idx].driver.inner().state.lock() != DriverState::CONNECTED {
+          latest[idx] = None;
+        }
+        match composite_state(&sources) {
+          Err(message) => {
+            failure = Some(message);
+            break;
+          }
+          Ok(state) if *inner.state.lock() != state => {
+            if state == DriverState::CONNECTED {
+              inner.metrics.lock().lastError = None;
+              info!(machine_id = %inner.machine_id, "connected");
+            }
+            inner.set_state(state);
+          }
+          Ok(_) => {}
+        }
+      }
+    }
+  }
+
+  drop(tasks);
+  for source in sources.iter() {
+    source.driver.inner().disconnect().await;
+  }
+  if let Some(message) = failure {
+    warn!(machine_id = %inner.machine_id, error = %message, "composite failed");
+    inner.metrics.lock().lastError = Some(message.clone());
+    inner.error_events.emit(DriverError::new(DriverErrorCode::ConnectFailed, message));
+  }
+  inner.set_state(if inner.stop_flag.load(Ordering::Relaxed) { DriverState::STOPPED } else { DriverState::FAILED });
+}
+
+/// Forwards one source's points and state changes to the merge loop until either side goes away.
+async fn watch_source(
+  idx: usize,
+  child: Arc<DriverInner>,
+  mut points: broadcast::Receiver<TelemetryPoint>,
+  events: mpsc::Sender<SourceEvent>,
+) {
+  loop {
+    // Registered before the state is reported so a transition in between isn't missed.
+    let changed = child.notify_state.notified();
+    tokio::pin!(changed);
+    changed.as_mut().enable();
+    if events.send(SourceEvent::State(idx)).await.is_err() {
+      return;
+    }
+    loop {
+      tokio::select! {
+        _ = &mut changed => break,
+        point = points.recv() => match point {
+          Ok(point) => {
+            if events.send(SourceEvent::Point(idx, Box::new(point))).await.is_err() {
+              return;
+            }
+          }
+          Err(broadcast::error::RecvError::Lagged(skipped)) => {
+            warn!(machine_id = %child.machine_id, skipped, "composite fell behind, points skipped");
+          }
+          Err(broadcast::error::RecvError::Closed) => return,
+        },
+      }
+    }
+  }
+}
+
+/// CONNECTED once every required source is, DISCONNECTED while one of them is out, CONNECTING until then; an error
+/// names a required source that has stopped for good (failed, or disconnected without reconnects).
+fn composite_state(sources: &[Source]) -> std::result::Result<DriverState, String> {
+  let mut state = DriverState::CONNECTED;
+  for source in sources.iter().filter(|source| source.required) {
+    let child = source.driver.inner();
+    // Not started yet: its idle state says nothing about this run.
+    if child.handle.lock().is_none() {
+      state = DriverState::CONNECTING;
+      continue;
+    }
+    let last_error = || child.metrics.lock().lastError.clone();
+    match *child.state.lock() {
+      DriverState::CONNECTED | DriverState::PAUSED => {}
+      DriverState::CONNECTING if state == DriverState::CONNECTED => state = DriverState::CONNECTING,
+      DriverState::CONNECTING => {}
+      DriverState::DISCONNECTED if child.config().reconnect.enabled => state = DriverState::DISCONNECTED,
+      DriverState::DISCONNECTED => {
+        return Err(format!("source {}: {}", source.name, last_error().unwrap_or_else(|| "disconnected".to_string())))
+      }
+      DriverState::FAILED => {
+        let message = last_error().unwrap_or_else(|| "reconnect attempts exhausted".to_string());
+        return Err(format!("source {}: {}", source.name, message));
+      }
+      DriverState::STOPPED => return Err(format!("source {}: stopped", source.name)),
+    }
+  }
+  Ok(state)
+}
+
+/// Builds a sample at `ts` from every source's latest point within `max_skew_ms` of it; for each field the first
+/// source (in config order) that has it wins.
+fn merge_latest(
+  sources: &[Source],
+  latest: &[Option<(DateTime<Utc>, TelemetryPoint)>],
+  ts: DateTime<Utc>,
+  max_skew_ms: u64,
+) -> Option<RawTelemetrySample> {
+  let mut sample = RawTelemetrySample {
+    ts,
+    device_ts: false,
+    bt_c: None,
+    et_c: None,
+    power_pct: None,
+    fan_pct: None,
+    drum_rpm: None,
+    ambient_c: None,
+    humidity_pct: None,
+    pressure_hpa: None,
+    exhaust_c: None,
+    inlet_c: None,
+    drum_pressure_pa: None,
+    airflow_pct: None,
+    extras: None,
+    probes: Vec::new(),
+    received_at: ts,
+    host_received_at: None,
+    quality: BTreeMap::new(),
+  };
+  let mut extras: Vec<ExtraEntry> = Vec::new();
+  let mut received_at: Option<DateTime<Utc>> = None;
+  for (source, entry) in sources.iter().zip(latest) {
+    let Some((point_ts, point)) = entry else {
+      continue;
+    };
+    if ts.signed_duration_since(*point_ts).num_milliseconds().unsigned_abs() > max_skew_ms {
+      continue;
+    }
+    let mut taken = Vec::new();
+    for (field, slot, value) in [
+      ("btC", &mut sample.bt_c, point.btC),
+      ("etC", &mut sample.et_c, point.etC),
+      ("gasPct", &mut sample.power_pct, point.power()),
+      ("fanPct", &mut sample.fan_pct, point.fanPct),
+      ("drumRpm", &mut sample.drum_rpm, point.drumRpm),
+      ("ambientC", &mut sample.ambient_c, point.ambientC),
+      ("humidityPct", &mut sample.humidity_pct, point.humidityPct),
+      ("pressureHpa", &mut sample.pressure_hpa, point.pressureHpa),
+      ("exhaustC", &mut sample.exhaust_c, point.exhaustC),
+      ("inletC", &mut sample.inlet_c, point.inletC),
+      ("drumPressurePa", &mut sample.drum_pressure_pa, point.drumPressurePa),
+      ("airflowPct", &mut sample.airflow_pct, point.airflowPct),
+    ] {
+      if slot.is_none() && value.is_some() && source.takes(field) {
+        *slot = value;
+        taken.push(field);
+      }
+    }
+    for extra in point.extras.iter().flatten() {
+      if source.takes(&extra.key) && !extras.iter().any(|taken| taken.key == extra.key) {
+        extras.push(extra.clone());
+        taken.push(&extra.key);
+      }
+    }
+    // The probes behind the channels taken from this source.
+    let probes = point.probes.iter().flatten().filter(|probe| taken.contains(&probe.channel.as_str()));
+    sample.probes.extend(probes.cloned());
+    if taken.is_empty() {
+      continue;
+    }
+    // The merged point is as old as the oldest reading in it, and keeps each reading's mark.
+    let source_received = point.sourceReceivedAt.as_deref().and_then(|at| DateTime::parse_from_rfc3339(at).ok());
+    if let Some(at) = source_received.map(|at| at.with_timezone(&Utc)) {
+      received_at = Some(received_at.map_or(at, |earliest| earliest.min(at)));
+    }
+    for field in taken {
+      // A source with `powerField: "powerPct"` marks the reading under that name.
+      let mark = point.quality.as_ref();
+      let mark = mark.and_then(|quality| quality.get(field).or(quality.get(channels::sample_key(field))));
+      let mark = mark.and_then(|mark| Quality::from_name(mark));
+      if let Some(mark) = mark.filter(|mark| *mark != Quality::Fresh) {
+        sample.quality.insert(channels::sample_key(field).to_string(), mark);
+      }
+    }
+  }
+  sample.received_at = received_at.unwrap_or_else(Utc::now);
+  let has_channels = sample.has_channels();
+  if !extras.is_empty() {
+    sample.extras = Some(extras);
+  }
+  (has_channels || sample.extras.is_some()).then_some(sample)
+}
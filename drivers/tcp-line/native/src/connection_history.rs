@@ -0,0 +1,73 @@
+use std::collections::VecDeque;
+
+use chrono::{SecondsFormat, Utc};
+use napi_derive::napi;
+
+/// Events `getConnectionHistory` keeps.
+const CAPACITY: usize = 100;
+
+#[derive(Debug)]
+#[napi(string_enum)]
+pub enum ConnectionEventKind {
+  /// A connection attempt started; `attempt` counts the tries since `connect()` or since the connection was last up.
+  Connecting,
+  Connected,
+  /// The attempt failed before CONNECTED (connect, proxy, keepalive or handshake).
+  ConnectFailed,
+  /// An established connection ended.
+  Disconnected,
+  /// `disconnect()` was called.
+  Stopped,
+  /// An alarm rule's `command` was written; `reason` names the rule, the line and how long it took.
+  Interlock,
+  /// An alarm rule's `command` couldn't be written within `commands.interlockTimeoutMs`, or at all.
+  InterlockFailed,
+}
+
+/// One entry of `getConnectionHistory`.
+#[derive(Debug, Clone)]
+#[napi(object)]
+pub struct ConnectionEvent {
+  pub ts: String,
+  pub kind: ConnectionEventKind,
+  /// `host:port` dialed, or the device's `ip:port` in listen mode.
+  pub endpoint: Option<String>,
+  /// Why the attempt failed or the connection ended.
+  pub reason: Option<String>,
+  pub attempt: Option<u32>,
+}
+
+impl ConnectionEvent {
+  pub fn new(kind: ConnectionEventKind, endpoint: Option<String>) -> Self {
+    Self { ts: Utc::now().to_rfc3339_opts(SecondsFormat::Millis, true), kind, endpoint, reason: None, attempt: None }
+  }
+
+  pub fn with_reason(mut self, reason: &str) -> Self {
+    self.reason = Some(reason.to_string());
+    self
+  }
+
+  pub fn with_attempt(mut self, attempt: u32) -> Self {
+    self.attempt = Some(attempt);
+    self
+  }
+}
+
+/// The latest connection events, oldest first, so overnight flapping shows without external logging.
+#[derive(Default)]
+pub(crate) struct ConnectionHistory {
+  events: VecDeque<ConnectionEvent>,
+}
+
+impl ConnectionHistory {
+  pub fn push(&mut self, event: ConnectionEvent) {
+    if self.events.len() == CAPACITY {
+      self.events.pop_front();
+    }
+    self.events.push_back(event);
+  }
+
+  pub fn snapshot(&self) -> Vec<ConnectionEvent> {
+    self.events.iter().cloned().collect()
+  }
+}
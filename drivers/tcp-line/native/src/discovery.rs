@@ -0,0 +1,72 @@
+use std::collections::{BTreeMap, HashMap};
+use std::time::Duration;
+
+use mdns_sd::{ServiceDaemon, ServiceEvent};
+use napi::bindgen_prelude::*;
+use napi_derive::napi;
+
+/// Browse time when `discoverDevices` gets no timeout.
+const DEFAULT_TIMEOUT_MS: u32 = 3000;
+
+/// A gateway that answered an mDNS browse.
+#[derive(Debug, Clone)]
+#[napi(object)]
+pub struct DiscoveredDevice {
+  /// Instance name, e.g. `Roaster 1._roaster._tcp.local.`.
+  pub name: String,
+  /// Advertised hostname, e.g. `roaster-1.local.`.
+  pub host: String,
+  /// Resolved IP addresses, IPv4 first.
+  pub addresses: Vec<String>,
+  pub port: u32,
+  /// TXT record key/value pairs.
+  pub txt: HashMap<String, String>,
+}
+
+/// Browses mDNS for `serviceType` (e.g. `_roaster._tcp`; `.local.` is appended when missing) for `timeoutMs` and
+/// returns every instance resolved in that time, sorted by name.
+#[napi]
+pub async fn discover_devices(service_type: String, timeout_ms: Option<u32>) -> Result<Vec<DiscoveredDevice>> {
+  let service_type = qualify(&service_type).map_err(Error::from_reason)?;
+  let daemon = ServiceDaemon::new().map_err(|err| Error::from_reason(format!("mdns unavailable: {}", err)))?;
+  let events = daemon.browse(&service_type).map_err(|err| Error::from_reason(format!("mdns browse failed: {}", err)))?;
+
+  let mut found = BTreeMap::new();
+  let deadline = tokio::time::Instant::now() + Duration::from_millis(timeout_ms.unwrap_or(DEFAULT_TIMEOUT_MS) as u64);
+  while let Ok(Ok(event)) = tokio::time::timeout_at(deadline, events.recv_async()).await {
+    match event {
+      ServiceEvent::ServiceResolved(info) => {
+        let mut addresses: Vec<_> = info.get_addresses().iter().copied().collect();
+        addresses.sort_by_key(|addr| (addr.is_ipv6(), *addr));
+        let device = DiscoveredDevice {
+          name: info.get_fullname().to_string(),
+          host: info.get_hostname().to_string(),
+          addresses: addresses.iter().map(ToString::to_string).collect(),
+          port: info.get_port() as u32,
+          txt: info.get_properties().iter().map(|prop| (prop.key().to_string(), prop.val_str().to_string())).collect(),
+        };
+        found.insert(device.name.clone(), device);
+      }
+      ServiceEvent::ServiceRemoved(_, name) => {
+        found.remove(&name);
+      }
+      _ => {}
+    }
+  }
+  // Stops the daemon's thread; its status reply isn't needed.
+  let _ = daemon.shutdown();
+  Ok(found.into_values().collect())
+}
+
+/// `_name._tcp` or `_name._udp`, fully qualified with the `.local.` domain.
+fn qualify(service_type: &str) -> std::result::Result<String, String> {
+  let trimmed = service_type.trim_end_matches('.');
+  let bare = trimmed.strip_suffix(".local").unwrap_or(trimmed);
+  let valid = bare.split('.').collect::<Vec<_>>();
+  match valid.as_slice() {
+    [name, proto] if name.len() > 1 && name.starts_with('_') && (*proto == "_tcp" || *proto == "_udp") => {
+      Ok(format!("{}.local.", bare))
+    }
+    _ => Err(format!("invalid service type \"{}\": expected e.g. _roaster._tcp", service_type)),
+  }
+}
@@ -0,0 +1,189 @@
+use std::collections::BTreeMap;
+
+use chrono::{DateTime, Utc};
+use schemars::JsonSchema;
+use serde::Deserialize;
+
+use crate::aggregate::ChannelAggregate;
+use crate::{ExtraEntry, Quality, RawTelemetrySample, CHANNELS};
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Deserialize, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub(crate) enum Aggregation {
+  /// The most recent value in the interval.
+  #[default]
+  Last,
+  Mean,
+  Min,
+  Max,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Deserialize, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct DownsampleConfig {
+  /// How each channel's values within an interval are combined.
+  #[serde(default)]
+  pub mode: Aggregation,
+  /// Per-channel overrides of `mode`, keyed by channel (`btC`, `etC`, `powerPct`, `ambientC`, ...) or extra name.
+  #[serde(default, deserialize_with = "crate::channels::by_channel")]
+  pub channels: BTreeMap<String, Aggregation>,
+}
+
+impl DownsampleConfig {
+  fn mode_for(&self, channel: &str) -> Aggregation {
+    self.channels.get(channel).copied().unwrap_or(self.mode)
+  }
+}
+
+/// Running count, sum, min, max and latest value of one channel.
+#[derive(Debug, Clone, Copy, Default)]
+pub(crate) struct Channel {
+  count: u32,
+  last: f64,
+  sum: f64,
+  min: f64,
+  max: f64,
+}
+
+impl Channel {
+  pub fn add(&mut self, value: Option<f64>) {
+    let Some(value) = value else {
+      return;
+    };
+    if self.count == 0 {
+      self.min = value;
+      self.max = value;
+    }
+    self.count += 1;
+    self.last = value;
+    self.sum += value;
+    self.min = self.min.min(value);
+    self.max = self.max.max(value);
+  }
+
+  fn value(&self, mode: Aggregation) -> Option<f64> {
+    (self.count > 0).then(|| match mode {
+      Aggregation::Last => self.last,
+      Aggregation::Mean => self.sum / self.count as f64,
+      Aggregation::Min => self.min,
+      Aggregation::Max => self.max,
+    })
+  }
+
+  pub fn summary(&self) -> Option<ChannelAggregate> {
+    (self.count > 0).then(|| ChannelAggregate {
+      min: self.min,
+      max: self.max,
+      mean: self.sum / self.count as f64,
+      count: self.count,
+    })
+  }
+}
+
+/// Running aggregates of one interval; only the last raw sample is kept, so a fast source costs no extra memory.
+struct Window {
+  started: DateTime<Utc>,
+  last: RawTelemetrySample,
+  /// In `CHANNELS` order.
+  channels: [Channel; CHANNELS.len()],
+  /// In order of first appearance; text and boolean extras keep their latest value.
+  extras: Vec<(String, Channel, Option<String>, Option<bool>)>,
+  /// Marks of any sample in the interval, since they went into the consolidated values.
+  quality: BTreeMap<String, Quality>,
+}
+
+impl Window {
+  fn new(sample: &RawTelemetrySample) -> Self {
+    Self {
+      started: sample.ts,
+      last: sample.clone(),
+      channels: Default::default(),
+      extras: Vec::new(),
+      quality: BTreeMap::new(),
+    }
+  }
+
+  fn add(&mut self, sample: RawTelemetrySample) {
+    for (channel, (_, value)) in self.channels.iter_mut().zip(sample.channels()) {
+      channel.add(value);
+    }
+    for entry in sample.extras.iter().flatten() {
+      let idx = match self.extras.iter().position(|(key, _, _, _)| *key == entry.key) {
+        Some(idx) => idx,
+        None => {
+          self.extras.push((entry.key.clone(), Channel::default(), None, None));
+          self.extras.len() - 1
+        }
+      };
+      let (_, channel, text, flag) = &mut self.extras[idx];
+      channel.add(entry.number_value);
+      if entry.text_value.is_some() {
+        text.clone_from(&entry.text_value);
+      }
+      if entry.bool_value.is_some() {
+        *flag = entry.bool_value;
+      }
+    }
+    self.quality.extend(sample.quality.iter().map(|(key, quality)| (key.clone(), *quality)));
+    self.last = sample;
+  }
+
+  /// One sample for the interval, stamped with its last sample's `ts`.
+  fn consolidate(self, config: &DownsampleConfig) -> RawTelemetrySample {
+    let extras = self
+      .extras
+      .into_iter()
+      .map(|(key, channel, text, flag)| {
+        let number_value = channel.value(config.mode_for(&key));
+        let text_value = if number_value.is_some() { None } else { text };
+        let bool_value = if number_value.is_some() || text_value.is_some() { None } else { flag };
+        ExtraEntry { key, number_value, text_value, bool_value }
+      })
+      .collect::<Vec<_>>();
+    let mut sample =
+      RawTelemetrySample { extras: (!extras.is_empty()).then_some(extras), quality: self.quality, ..self.last };
+    for (channel, (key, value)) in self.channels.iter().zip(sample.channels_mut()) {
+      *value = channel.value(config.mode_for(key));
+    }
+    sample
+  }
+}
+
+/// Folds samples into `emitIntervalMs` windows measured on sample timestamps, so replays downsample the same way at
+/// any speed. A window closes when a sample falls outside it (or the device clock goes backwards).
+#[derive(Default)]
+pub(crate) struct Downsampler {
+  window: Option<Window>,
+}
+
+impl Downsampler {
+  /// The last raw sample of the open window.
+  pub fn last(&self) -> Option<&RawTelemetrySample> {
+    self.window.as_ref().map(|window| &window.last)
+  }
+
+  /// Adds `sample`, returning the consolidated previous window if `sample` opened a new one.
+  pub fn push(
+    &mut self,
+    config: &DownsampleConfig,
+    interval_ms: u64,
+    sample: RawTelemetrySample,
+  ) -> Option<RawTelemetrySample> {
+    let closes = self.window.as_ref().is_some_and(|window| {
+      let delta = sample.ts.signed_duration_since(window.started).num_milliseconds();
+      delta < 0 || delta >= interval_ms as i64
+    });
+    let closed = if closes { self.flush(config) } else { None };
+    self.window.get_or_insert_with(|| Window::new(&sample)).add(sample);
+    closed
+  }
+
+  /// Consolidates the open window, e.g. when a replay ends.
+  pub fn flush(&mut self, config: &DownsampleConfig) -> Option<RawTelemetrySample> {
+    self.window.take().map(|window| window.consolidate(config))
+  }
+
+  pub fn reset(&mut self) {
+    self.window = None;
+  }
+}
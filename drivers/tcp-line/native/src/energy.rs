@@ -0,0 +1,63 @@
+use chrono::{DateTime, Utc};
+use schemars::JsonSchema;
+use serde::Deserialize;
+
+const MS_PER_HOUR: f64 = 3_600_000.0;
+
+#[derive(Debug, Clone, PartialEq, Deserialize, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct EnergyConfig {
+  /// What the burner or heater draws at `powerPct` 100, in kW.
+  pub rated_kw: f64,
+  /// A gap between power readings longer than this, on sample timestamps, isn't counted, so a device that dropped
+  /// out isn't taken to have burned at its last setting all along.
+  #[serde(default = "default_max_gap_ms")]
+  pub max_gap_ms: u64,
+}
+
+fn default_max_gap_ms() -> u64 {
+  10_000
+}
+
+/// The energy used since the session (or, outside one, the connection) started, integrated from `powerPct`.
+#[derive(Debug, Default)]
+pub(crate) struct EnergyMeter {
+  kwh: f64,
+  /// The previous power reading and its timestamp.
+  last: Option<(DateTime<Utc>, f64)>,
+}
+
+impl EnergyMeter {
+  /// Counts the interval since the previous power reading at the mean of the two. Samples without one, and
+  /// readings older than the previous, are skipped.
+  pub fn add(&mut self, config: &EnergyConfig, ts: DateTime<Utc>, power_pct: Option<f64>) {
+    let Some(power) = power_pct.filter(|power| power.is_finite()).map(|power| power.max(0.0)) else {
+      return;
+    };
+    if let Some((since, previous)) = self.last {
+      let gap_ms = (ts - since).num_milliseconds();
+      if gap_ms < 0 {
+        return;
+      }
+      if gap_ms as u64 <= config.max_gap_ms {
+        self.kwh += config.rated_kw * (previous + power) / 200.0 * gap_ms as f64 / MS_PER_HOUR;
+      }
+    }
+    self.last = Some((ts, power));
+  }
+
+  pub fn kwh(&self) -> f64 {
+    self.kwh
+  }
+
+  /// Starts over at 0 kWh.
+  pub fn reset(&mut self) {
+    *self = Self::default();
+  }
+
+  /// Keeps the count but not the previous reading, so the time until the next isn't counted (e.g. after a
+  /// reconnect).
+  pub fn interrupt(&mut self) {
+    self.last = None;
+  }
+}
@@ -0,0 +1,83 @@
+use chrono::{SecondsFormat, Utc};
+use napi::bindgen_prelude::*;
+use napi::threadsafe_function::{ErrorStrategy, ThreadSafeCallContext, ThreadsafeFunction, ThreadsafeFunctionCallMode};
+use napi_derive::napi;
+use parking_lot::Mutex;
+
+use crate::DriverState;
+
+#[derive(Debug, Clone)]
+#[napi(object)]
+pub struct StateChangeEvent {
+  pub previous: DriverState,
+  pub state: DriverState,
+  pub ts: String,
+  pub lastError: Option<String>,
+}
+
+#[derive(Debug)]
+#[napi(string_enum)]
+pub enum DriverErrorCode {
+  ConnectFailed,
+  SocketClosed,
+  ParseError,
+  Timeout,
+}
+
+#[derive(Debug, Clone)]
+#[napi(object)]
+pub struct DriverError {
+  pub code: DriverErrorCode,
+  pub message: String,
+  pub ts: String,
+  /// The offending line, for `ParseError`.
+  pub rawLine: Option<String>,
+}
+
+impl DriverError {
+  pub fn new(code: DriverErrorCode, message: impl Into<String>) -> Self {
+    Self { code, message: message.into(), ts: Utc::now().to_rfc3339_opts(SecondsFormat::Millis, true), rawLine: None }
+  }
+
+  pub fn with_line(mut self, line: &str) -> Self {
+    self.rawLine = Some(line.to_string());
+    self
+  }
+}
+
+/// JS callbacks registered through `on*` methods. Callbacks are invoked on the JS thread without blocking the
+/// driver, and are unref'd so a forgotten subscription never keeps the process alive.
+pub(crate) struct Subscribers<T: ToNapiValue + Send + 'static> {
+  callbacks: Mutex<Vec<ThreadsafeFunction<T, ErrorStrategy::Fatal>>>,
+}
+
+impl<T: ToNapiValue + Send + Clone + 'static> Subscribers<T> {
+  pub fn new() -> Self {
+    Self { callbacks: Mutex::new(Vec::new()) }
+  }
+
+  pub fn subscribe(&self, env: &Env, callback: JsFunction) -> Result<()> {
+    Self::subscribe_all(env, callback, &[self])
+  }
+
+  /// Registers one callback with each of `all`, e.g. a composite driver's own errors and its sources'.
+  pub fn subscribe_all(env: &Env, callback: JsFunction, all: &[&Self]) -> Result<()> {
+    let mut tsfn: ThreadsafeFunction<T, ErrorStrategy::Fatal> =
+      callback.create_threadsafe_function(0, |ctx: ThreadSafeCallContext<T>| Ok(vec![ctx.value]))?;
+    tsfn.unref(env)?;
+    for subscribers in all {
+      subscribers.callbacks.lock().push(tsfn.clone());
+    }
+    Ok(())
+  }
+
+  pub fn emit(&self, event: T) {
+    for callback in self.callbacks.lock().iter() {
+      callback.call(event.clone(), ThreadsafeFunctionCallMode::NonBlocking);
+    }
+  }
+
+  pub fn is_empty(&self) -> bool {
+    self.callbacks.lock().is_empty()
+  }
+}
@@ -0,0 +1,122 @@
+use std::sync::atomic::Ordering;
+use std::time::Duration;
+
+use serde::Deserialize;
+use tokio::time::{sleep, Instant};
+
+use crate::connection_history::{ConnectionEvent, ConnectionEventKind};
+use crate::events::{DriverError, DriverErrorCode};
+use crate::rng::Rng;
+use crate::{DriverInner, DriverState};
+
+const REASON: &str = "injected fault";
+
+/// Faults a simulated or replay driver injects into its own stream, for resilience testing in staging. All off by
+/// default.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub(crate) struct FaultConfig {
+  /// Drops the connection at random, on average this often: each drop comes between half and one and a half times
+  /// this after the previous one.
+  pub disconnect_every_seconds: Option<f64>,
+  /// How long a dropped connection stays down.
+  pub disconnected_for_ms: u64,
+  /// Chance, from 0 to 1, that a line is corrupted before it is parsed: cut short or with a character garbled.
+  pub corrupt_line_probability: f64,
+  /// Holds each sample back a random time up to this long before delivering it.
+  pub max_delay_ms: u64,
+  /// Seeds the fault dice so a run can be repeated; from the clock when unset.
+  pub seed: Option<u64>,
+}
+
+impl Default for FaultConfig {
+  fn default() -> Self {
+    Self {
+      disconnect_every_seconds: None,
+      disconnected_for_ms: 2000,
+      corrupt_line_probability: 0.0,
+      max_delay_ms: 0,
+      seed: None,
+    }
+  }
+}
+
+impl FaultConfig {
+  pub fn validate(&self) -> Result<(), String> {
+    if self.disconnect_every_seconds.is_some_and(|every| !(every.is_finite() && every > 0.0)) {
+      return Err("faults.disconnectEverySeconds must be positive".to_string());
+    }
+    if !(0.0..=1.0).contains(&self.corrupt_line_probability) {
+      return Err("faults.corruptLineProbability must be between 0 and 1".to_string());
+    }
+    Ok(())
+  }
+}
+
+/// Rolls the dice for `FaultConfig`; a driver without `faults` gets one that never fires.
+pub(crate) struct FaultInjector {
+  config: FaultConfig,
+  rng: Rng,
+  next_disconnect: Option<Instant>,
+}
+
+impl FaultInjector {
+  pub fn new(config: Option<FaultConfig>) -> Self {
+    let config = config.unwrap_or_default();
+    let rng = config.seed.map(Rng::new).unwrap_or_else(Rng::from_time);
+    let mut injector = Self { config, rng, next_disconnect: None };
+    injector.next_disconnect = injector.disconnect_after();
+    injector
+  }
+
+  fn disconnect_after(&mut self) -> Option<Instant> {
+    let every = self.config.disconnect_every_seconds?;
+    Some(Instant::now() + Duration::from_secs_f64(every * (0.5 + self.rng.next_f64())))
+  }
+
+  /// Drops the connection when a drop is due, the way a lost socket would, and brings it back after
+  /// `disconnectedForMs`. Returns how long it was down; `None` when no drop was due.
+  pub async fn disconnect(&mut self, inner: &DriverInner) -> Option<Duration> {
+    if self.next_disconnect.is_none_or(|at| Instant::now() < at) {
+      return None;
+    }
+    let started = Instant::now();
+    inner.log_connection(ConnectionEvent::new(ConnectionEventKind::Disconnected, None).with_reason(REASON));
+    inner.handle_failure(DriverError::new(DriverErrorCode::SocketClosed, format!("{}: disconnect", REASON))).await;
+    sleep(Duration::from_millis(self.config.disconnected_for_ms)).await;
+    if !inner.stop_flag.load(Ordering::Relaxed) {
+      {
+        let mut metrics = inner.metrics.lock();
+        metrics.reconnects = metrics.reconnects.saturating_add(1);
+        metrics.lastError = None;
+      }
+      inner.log_connection(ConnectionEvent::new(ConnectionEventKind::Connected, None).with_reason(REASON));
+      inner.set_state(DriverState::CONNECTED);
+    }
+    self.next_disconnect = self.disconnect_after();
+    Some(started.elapsed())
+  }
+
+  /// Whether to corrupt the next line.
+  pub fn corrupts(&mut self) -> bool {
+    self.config.corrupt_line_probability > 0.0 && self.rng.next_f64() < self.config.corrupt_line_probability
+  }
+
+  /// `line` cut short, or with one character replaced by U+FFFD, at a random place.
+  pub fn garble(&mut self, line: &str) -> String {
+    let chars: Vec<char> = line.chars().collect();
+    let at = ((self.rng.next_f64() * chars.len() as f64) as usize).min(chars.len().saturating_sub(1));
+    if self.rng.next_f64() < 0.5 {
+      return chars[..at.max(1).min(chars.len())].iter().collect();
+    }
+    let garbled = chars.iter().enumerate().map(|(idx, ch)| if idx == at { '\u{fffd}' } else { *ch });
+    garbled.collect()
+  }
+
+  /// Waits out the delivery delay of the next sample.
+  pub async fn delay(&mut self) {
+    if self.config.max_delay_ms > 0 {
+      sleep(Duration::from_secs_f64(self.config.max_delay_ms as f64 / 1000.0 * self.rng.next_f64())).await;
+    }
+  }
+}
@@ -0,0 +1,286 @@
+use std::borrow::Cow;
+use std::io::ErrorKind;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use schemars::JsonSchema;
+use serde::Deserialize;
+use tokio::io::{AsyncBufReadExt, AsyncRead, AsyncReadExt, BufReader};
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Deserialize, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub(crate) enum Framing {
+  /// One frame per `\n`-terminated line.
+  #[default]
+  Newline,
+  /// Consistent Overhead Byte Stuffing, frames terminated by a zero byte.
+  Cobs,
+  /// RFC 1055 SLIP, frames terminated by `0xC0`.
+  Slip,
+  /// A 2-byte big-endian length followed by that many bytes.
+  LengthPrefix,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Deserialize, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub(crate) enum Encoding {
+  /// Frames that aren't valid UTF-8 are parse errors.
+  #[default]
+  Utf8,
+  /// Every byte is one character (ISO-8859-1), e.g. `0xB0` for `°`.
+  Latin1,
+  /// UTF-8 with invalid sequences replaced by `U+FFFD`.
+  Lossy,
+}
+
+impl Encoding {
+  /// The frame as text, or `None` if it isn't valid in this encoding.
+  pub fn decode(self, frame: &[u8]) -> Option<Cow<'_, str>> {
+    match self {
+      Encoding::Utf8 => std::str::from_utf8(frame).ok().map(Cow::Borrowed),
+      Encoding::Latin1 => Some(Cow::Owned(frame.iter().map(|byte| *byte as char).collect())),
+      Encoding::Lossy => Some(String::from_utf8_lossy(frame)),
+    }
+  }
+}
+
+pub(crate) enum ReadFrame {
+  Closed,
+  /// A frame was decoded into the buffer; carries the bytes read from the socket.
+  Frame(usize),
+  /// Bytes that don't decode under the framing; counted as a parse error.
+  Invalid(usize, &'static str),
+}
+
+const SLIP_END: u8 = 0xC0;
+const SLIP_ESC: u8 = 0xDB;
+const SLIP_ESC_END: u8 = 0xDC;
+const SLIP_ESC_ESC: u8 = 0xDD;
+
+/// Token bucket for `maxBytesPerSecond`, holding up to a second's worth of bytes.
+struct RateLimit {
+  bytes_per_second: f64,
+  /// Negative once frames have taken more than there was; the reader waits until it is paid back.
+  available: f64,
+  updated: Instant,
+}
+
+impl RateLimit {
+  fn refill(&mut self) {
+    let now = Instant::now();
+    let refilled = now.duration_since(self.updated).as_secs_f64() * self.bytes_per_second;
+    self.available = (self.available + refilled).min(self.bytes_per_second);
+    self.updated = now;
+  }
+}
+
+/// Splits the byte stream into frames per `Framing` and undoes the encoding, so the parser only sees payloads. No
+/// frame is buffered beyond `max_bytes`: the rest of an oversized one is read and discarded.
+pub(crate) struct FrameReader<R> {
+  reader: BufReader<R>,
+  framing: Framing,
+  line_delimiter: Vec<u8>,
+  max_bytes: usize,
+  raw: Vec<u8>,
+  /// Part of a frame has been consumed and the rest is still awaited.
+  mid_frame: Arc<AtomicBool>,
+  limit: Option<RateLimit>,
+}
+
+impl<R: AsyncRead + Unpin> FrameReader<R> {
+  pub fn new(reader: R, framing: Framing, line_delimiter: &str, max_bytes: usize) -> Self {
+    let line_delimiter = line_delimiter.as_bytes().to_vec();
+    Self {
+      reader: BufReader::new(reader),
+      framing,
+      line_delimiter,
+      max_bytes,
+      raw: Vec::new(),
+      mid_frame: Arc::default(),
+      limit: None,
+    }
+  }
+
+  /// Limits reading to `bytes_per_second` on average; see `throttle`.
+  pub fn with_rate_limit(mut self, bytes_per_second: Option<u64>) -> Self {
+    self.limit = bytes_per_second.filter(|rate| *rate > 0).map(|rate| RateLimit {
+      bytes_per_second: rate as f64,
+      available: rate as f64,
+      updated: Instant::now(),
+    });
+    self
+  }
+
+  /// Waits until the frames read so far fit the rate limit. Called between frames, so the socket fills up and TCP
+  /// flow control slows the sender rather than the driver buffering the excess.
+  pub async fn throttle(&mut self) {
+    let Some(limit) = self.limit.as_mut() else {
+      return;
+    };
+    limit.refill();
+    if limit.available < 0.0 {
+      tokio::time::sleep(Duration::from_secs_f64(-limit.available / limit.bytes_per_second)).await;
+    }
+  }
+
+  /// Set while a frame is half received, so a caller racing `read_frame` against a stop can let the frame finish.
+  pub fn mid_frame(&self) -> Arc<AtomicBool> {
+    Arc::clone(&self.mid_frame)
+  }
+
+  /// Reads the next frame's payload into `frame`.
+  pub async fn read_frame(&mut self, frame: &mut Vec<u8>) -> std::io::Result<ReadFrame> {
+    let read = self.read_unlimited(frame).await;
+    if let (Some(limit), Ok(ReadFrame::Frame(bytes) | ReadFrame::Invalid(bytes, _))) = (self.limit.as_mut(), &read) {
+      limit.refill();
+      limit.available -= *bytes as f64;
+    }
+    read
+  }
+
+  async fn read_unlimited(&mut self, frame: &mut Vec<u8>) -> std::io::Result<ReadFrame> {
+    frame.clear();
+    self.mid_frame.store(false, Ordering::Relaxed);
+    match self.framing {
+      Framing::Newline => {
+        let delimiter = std::mem::take(&mut self.line_delimiter);
+        let read = self.read_until(&delimiter, frame).await;
+        self.line_delimiter = delimiter;
+        Ok(match read? {
+          (0, _) => ReadFrame::Closed,
+          (read, true) => ReadFrame::Invalid(read, TOO_LONG),
+          (read, false) => ReadFrame::Frame(read),
+        })
+      }
+      Framing::Cobs => self.read_delimited(0, frame, decode_cobs).await,
+      Framing::Slip => self.read_delimited(SLIP_END, frame, decode_slip).await,
+      Framing::LengthPrefix => {
+        let mut header = [0u8; 2];
+        if let Err(err) = self.reader.read_exact(&mut header).await {
+          return if err.kind() == ErrorKind::UnexpectedEof { Ok(ReadFrame::Closed) } else { Err(err) };
+        }
+        let len = u16::from_be_bytes(header) as usize;
+        self.mid_frame.store(true, Ordering::Relaxed);
+        if len > self.max_bytes {
+          let skipped = tokio::io::copy(&mut (&mut self.reader).take(len as u64), &mut tokio::io::sink()).await?;
+          if skipped < len as u64 {
+            return Ok(ReadFrame::Closed);
+          }
+          return Ok(ReadFrame::Invalid(len + header.len(), TOO_LONG));
+        }
+        frame.resize(len, 0);
+        match self.reader.read_exact(frame).await {
+          Ok(_) => Ok(ReadFrame::Frame(len + header.len())),
+          Err(err) if err.kind() == ErrorKind::UnexpectedEof => Ok(ReadFrame::Closed),
+          Err(err) => Err(err),
+        }
+      }
+    }
+  }
+
+  async fn read_delimited(
+    &mut self,
+    delimiter: u8,
+    frame: &mut Vec<u8>,
+    decode: fn(&[u8], &mut Vec<u8>) -> Result<(), &'static str>,
+  ) -> std::io::Result<ReadFrame> {
+    // Senders often open each frame with a delimiter too; the empty frames between them aren't frames.
+    let mut raw = std::mem::take(&mut self.raw);
+    let mut read = 0;
+    let result = loop {
+      raw.clear();
+      let (chunk, oversized) = match self.read_until(&[delimiter], &mut raw).await {
+        Ok(chunk) => chunk,
+        Err(err) => break Err(err),
+      };
+      read += chunk;
+      if chunk == 0 {
+        break Ok(ReadFrame::Closed);
+      }
+      if oversized {
+        break Ok(ReadFrame::Invalid(read, TOO_LONG));
+      }
+      if !raw.is_empty() {
+        break Ok(match decode(&raw, frame) {
+          Ok(()) => ReadFrame::Frame(read),
+          Err(reason) => ReadFrame::Invalid(read, reason),
+        });
+      }
+    };
+    self.raw = raw;
+    result
+  }
+
+  /// Reads through the next `delimiter` (or to EOF) into `out`, without the delimiter. Returns the bytes consumed and
+  /// whether the frame exceeded `max_bytes`, in which case `out` holds only its first `max_bytes`.
+  async fn read_until(&mut self, delimiter: &[u8], out: &mut Vec<u8>) -> std::io::Result<(usize, bool)> {
+    let keep = self.max_bytes + delimiter.len();
+    let mut window = Vec::with_capacity(delimiter.len() + 1);
+    let mut read = 0;
+    let mut found = false;
+    while !found {
+      let available = self.reader.fill_buf().await?;
+      if available.is_empty() {
+        break;
+      }
+      let mut consumed = available.len();
+      for (idx, byte) in available.iter().enumerate() {
+        if out.len() < keep {
+          out.push(*byte);
+        }
+        // The delimiter can be split across reads, so it is matched on a sliding window rather than within `available`.
+        window.push(*byte);
+        if window.len() > delimiter.len() {
+          window.remove(0);
+        }
+        if window == delimiter {
+          consumed = idx + 1;
+          found = true;
+          break;
+        }
+      }
+      self.reader.consume(consumed);
+      read += consumed;
+      self.mid_frame.store(!found, Ordering::Relaxed);
+    }
+    let len = if found { read - delimiter.len() } else { read };
+    out.truncate(len.min(self.max_bytes));
+    Ok((read, len > self.max_bytes))
+  }
+}
+
+const TOO_LONG: &str = "exceeds maxLineBytes";
+
+fn decode_cobs(encoded: &[u8], out: &mut Vec<u8>) -> Result<(), &'static str> {
+  let mut rest = encoded;
+  while let Some((&code, tail)) = rest.split_first() {
+    let run = (code as usize).saturating_sub(1);
+    if run > tail.len() {
+      return Err("truncated cobs block");
+    }
+    out.extend_from_slice(&tail[..run]);
+    rest = &tail[run..];
+    // A 0xFF block carries no implicit zero, and neither does the last block.
+    if code != 0xFF && !rest.is_empty() {
+      out.push(0);
+    }
+  }
+  Ok(())
+}
+
+fn decode_slip(encoded: &[u8], out: &mut Vec<u8>) -> Result<(), &'static str> {
+  let mut bytes = encoded.iter();
+  while let Some(&byte) = bytes.next() {
+    if byte != SLIP_ESC {
+      out.push(byte);
+      continue;
+    }
+    match bytes.next() {
+      Some(&SLIP_ESC_END) => out.push(SLIP_END),
+      Some(&SLIP_ESC_ESC) => out.push(SLIP_ESC),
+      _ => return Err("invalid slip escape"),
+    }
+  }
+  Ok(())
+}
@@ -0,0 +1,26 @@
+use std::panic::{self, AssertUnwindSafe};
+
+use napi::bindgen_prelude::*;
+use napi_derive::napi;
+
+use tcp_line_core::fuzz::{self, FuzzOutcome};
+
+/// Runs `bytes` as device output (split at `\n`, decoded lossily) through a parser built from a parser or driver
+/// config, for fuzzing from JS. Rejects with the first config issue for an invalid config, and with `parser
+/// panicked: ...` if the parser panics, which is a bug: malformed output must only ever count as errors.
+#[napi]
+pub fn parse_line_for_fuzzing(config_json: String, bytes: Buffer) -> Result<FuzzOutcome> {
+  let parsed = panic::catch_unwind(AssertUnwindSafe(|| fuzz::parse_line_for_fuzzing(&config_json, &bytes)));
+  match parsed {
+    Ok(Ok(outcome)) => Ok(outcome),
+    Ok(Err(issue)) => Err(Error::from_reason(format!("invalid config: {}", issue.describe()))),
+    Err(panic) => {
+      let message = panic
+        .downcast_ref::<String>()
+        .map(String::as_str)
+        .or_else(|| panic.downcast_ref::<&str>().copied())
+        .unwrap_or("unknown panic");
+      Err(Error::from_reason(format!("parser panicked: {}", message)))
+    }
+  }
+}
@@ -0,0 +1,289 @@
+use std::io;
+use std::sync::{Arc, Weak};
+
+use schemars::JsonSchema;
+use serde::Deserialize;
+use tokio::sync::{broadcast, mpsc};
+use tokio::task::JoinHandle;
+use tokio_stream::wrappers::ReceiverStream;
+use tonic::transport::server::TcpIncoming;
+use tonic::{Request, Response, Status};
+use tracing::{info, warn};
+
+use crate::{DriverInner, DriverMetrics, DriverState, DriverStatus, ExtraEntry, ProbeReading, TelemetryPoint};
+
+mod proto {
+  tonic::include_proto!("simcorp.tcpline.v1");
+}
+
+use proto::telemetry_server::{Telemetry, TelemetryServer};
+
+#[derive(Debug, Clone, PartialEq, Deserialize, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct GrpcConfig {
+  #[serde(default = "default_host")]
+  pub host: String,
+  /// `0` binds an ephemeral port; `grpcPort()` returns the actual one.
+  #[serde(default = "default_port")]
+  pub port: u16,
+}
+
+fn default_host() -> String {
+  "127.0.0.1".to_string()
+}
+
+fn default_port() -> u16 {
+  50051
+}
+
+/// Points a `StreamTelemetry` call may fall behind by before it skips ahead.
+const STREAM_BUFFER: usize = 1024;
+
+/// Serves `simcorp.tcpline.v1.Telemetry` (see `proto/telemetry.proto`) for a fixed set of drivers until dropped. The
+/// drivers are held weakly, so the server never keeps one alive.
+pub(crate) struct GrpcServer {
+  port: u16,
+  handle: JoinHandle<()>,
+}
+
+impl GrpcServer {
+  /// Binds `config.host:port` and starts serving; must be called on the tokio runtime.
+  pub fn start(config: &GrpcConfig, drivers: Vec<(String, Weak<DriverInner>)>) -> io::Result<Self> {
+    let listener = std::net::TcpListener::bind((config.host.as_str(), config.port))?;
+    listener.set_nonblocking(true)?;
+    let listener = tokio::net::TcpListener::from_std(listener)?;
+    let port = listener.local_addr()?.port();
+    let service = TelemetryServer::new(TelemetryService { drivers });
+    let handle = tokio::spawn(async move {
+      let server = tonic::transport::Server::builder().add_service(service);
+      if let Err(err) = server.serve_with_incoming(TcpIncoming::from(listener)).await {
+        warn!(port, error = %err, "grpc server stopped");
+      }
+    });
+    info!(host = %config.host, port, "grpc server listening");
+    Ok(Self { port, handle })
+  }
+
+  pub fn port(&self) -> u16 {
+    self.port
+  }
+}
+
+impl Drop for GrpcServer {
+  fn drop(&mut self) {
+    self.handle.abort();
+  }
+}
+
+struct TelemetryService {
+  drivers: Vec<(String, Weak<DriverInner>)>,
+}
+
+impl TelemetryService {
+  /// The live drivers among `machine_ids` (all when empty); an id the server doesn't cover is an error.
+  fn select(&self, machine_ids: &[String]) -> Result<Vec<(String, Arc<DriverInner>)>, Status> {
+    if let Some(unknown) = machine_ids.iter().find(|id| !self.drivers.iter().any(|(known, _)| known == *id)) {
+      return Err(Status::not_found(format!("unknown machine: {}", unknown)));
+    }
+    Ok(
+      self
+        .drivers
+        .iter()
+        .filter(|(id, _)| machine_ids.is_empty() || machine_ids.contains(id))
+        .filter_map(|(id, driver)| driver.upgrade().map(|driver| (id.clone(), driver)))
+        .collect(),
+    )
+  }
+}
+
+#[tonic::async_trait]
+impl Telemetry for TelemetryService {
+  type StreamTelemetryStream = ReceiverStream<Result<proto::TelemetryPoint, Status>>;
+
+  async fn stream_telemetry(
+    &self,
+    request: Request<proto::StreamTelemetryRequest>,
+  ) -> Result<Response<Self::StreamTelemetryStream>, Status> {
+    let drivers = self.select(&request.into_inner().machine_ids)?;
+    let (sender, receiver) = mpsc::channel(STREAM_BUFFER);
+    for (machine_id, driver) in drivers {
+      let points = driver.subscribe_points();
+      tokio::spawn(forward(machine_id, points, sender.clone()));
+    }
+    Ok(Response::new(ReceiverStream::new(receiver)))
+  }
+
+  async fn get_status(
+    &self,
+    request: Request<proto::GetStatusRequest>,
+  ) -> Result<Response<proto::GetStatusResponse>, Status> {
+    let machines = self
+      .select(&request.into_inner().machine_ids)?
+      .into_iter()
+      .map(|(machine_id, driver)| machine_status(machine_id, driver.get_status()))
+      .collect();
+    Ok(Response::new(proto::GetStatusResponse { machines }))
+  }
+}
+
+/// Pumps one driver's points into a client's stream until the client goes away or the driver is dropped.
+async fn forward(
+  machine_id: String,
+  mut points: broadcast::Receiver<TelemetryPoint>,
+  sender: mpsc::Sender<Result<proto::TelemetryPoint, Status>>,
+) {
+  loop {
+    let point = tokio::select! {
+      point = points.recv() => point,
+      _ = sender.closed() => return,
+    };
+    match point {
+      Ok(point) => {
+        if sender.send(Ok(point.into())).await.is_err() {
+          return;
+        }
+      }
+      Err(broadcast::error::RecvError::Lagged(skipped)) => {
+        warn!(machine_id = %machine_id, skipped, "grpc client fell behind, points skipped");
+      }
+      Err(broadcast::error::RecvError::Closed) => return,
+    }
+  }
+}
+
+impl From<TelemetryPoint> for proto::TelemetryPoint {
+  fn from(point: TelemetryPoint) -> Self {
+    let gas_pct = point.power();
+    Self {
+      ts: point.ts,
+      machine_id: point.machineId,
+      elapsed_seconds: point.elapsedSeconds,
+      bt_c: point.btC,
+      et_c: point.etC,
+      gas_pct,
+      fan_pct: point.fanPct,
+      drum_rpm: point.drumRpm,
+      extras: point.extras.unwrap_or_default().into_iter().map(proto::ExtraEntry::from).collect(),
+      session_id: point.sessionId,
+      ambient_c: point.ambientC,
+      humidity_pct: point.humidityPct,
+      pressure_hpa: point.pressureHpa,
+      exhaust_c: point.exhaustC,
+      inlet_c: point.inletC,
+      drum_pressure_pa: point.drumPressurePa,
+      airflow_pct: point.airflowPct,
+      probes: point.probes.unwrap_or_default().into_iter().map(proto::ProbeReading::from).collect(),
+      energy_kwh: point.energyKwh,
+    }
+  }
+}
+
+impl From<ExtraEntry> for proto::ExtraEntry {
+  fn from(entry: ExtraEntry) -> Self {
+    Self {
+      key: entry.key,
+      number_value: entry.number_value,
+      text_value: entry.text_value,
+      bool_value: entry.bool_value,
+    }
+  }
+}
+
+impl From<ProbeReading> for proto::ProbeReading {
+  fn from(reading: ProbeReading) -> Self {
+    Self { name: reading.name, channel: reading.channel, value: reading.value }
+  }
+}
+
+impl From<DriverState> for proto::DriverState {
+  fn from(state: DriverState) -> Self {
+    match state {
+      DriverState::DISCONNECTED => Self::Disconnected,
+      DriverState::CONNECTING => Self::Connecting,
+      DriverState::CONNECTED => Self::Connected,
+      DriverState::PAUSED => Self::Paused,
+      DriverState::STOPPED => Self::Stopped,
+      DriverState::FAILED => Self::Failed,
+    }
+  }
+}
+
+impl From<DriverMetrics> for proto::DriverMetrics {
+  fn from(metrics: DriverMetrics) -> Self {
+    Self {
+      lines_received: metrics.linesReceived,
+      lines_parsed: metrics.linesParsed,
+      parse_errors: metrics.parseErrors,
+      checksum_errors: metrics.checksumErrors,
+      field_errors: metrics.fieldErrors,
+      batch_lines: metrics.batchLines,
+      batch_samples: metrics.batchSamples,
+      sequence_gaps: metrics.sequenceGaps,
+      sequence_missing: metrics.sequenceMissing,
+      sequence_duplicates: metrics.sequenceDuplicates,
+      sequence_reordered: metrics.sequenceReordered,
+      out_of_order_samples: metrics.outOfOrderSamples,
+      telemetry_emitted: metrics.telemetryEmitted,
+      reconnects: metrics.reconnects,
+      commands_sent: metrics.commandsSent,
+      stale_timeouts: metrics.staleTimeouts,
+      dropped_samples: metrics.droppedSamples,
+      influx_points_written: metrics.influxPointsWritten,
+      influx_write_errors: metrics.influxWriteErrors,
+      kafka_messages_delivered: metrics.kafkaMessagesDelivered,
+      kafka_delivery_errors: metrics.kafkaDeliveryErrors,
+      bytes_received: metrics.bytesReceived,
+      bytes_sent: metrics.bytesSent,
+      lines_per_second: metrics.linesPerSecond,
+      last_error: metrics.lastError,
+      last_line_at: metrics.lastLineAt,
+    }
+  }
+}
+
+fn machine_status(machine_id: String, status: DriverStatus) -> proto::MachineStatus {
+  proto::MachineStatus {
+    machine_id,
+    state: proto::DriverState::from(status.state) as i32,
+    metrics: Some(status.metrics.into()),
+    clock_skew_ms: status.clockSkewMs,
+    session: status
+      .session
+      .map(|session| proto::Session { session_id: session.sessionId, started_at: session.startedAt }),
+    active_endpoint: status.activeEndpoint,
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use serde_json::json;
+
+  use super::*;
+
+  #[test]
+  fn maps_the_ambient_channels() {
+    let point = proto::TelemetryPoint::from(TelemetryPoint::test(json!({ "ambientC": 21.5, "humidityPct": 40.0 })));
+    assert_eq!((point.ambient_c, point.humidity_pct, point.pressure_hpa), (Some(21.5), Some(40.0), None));
+  }
+
+  #[test]
+  fn maps_the_registered_channels() {
+    let point = proto::TelemetryPoint::from(TelemetryPoint::test(json!({ "exhaustC": 180.0, "airflowPct": 35.0 })));
+    assert_eq!((point.exhaust_c, point.inlet_c), (Some(180.0), None));
+    assert_eq!((point.drum_pressure_pa, point.airflow_pct), (None, Some(35.0)));
+  }
+
+  #[test]
+  fn maps_the_energy_used() {
+    let point = proto::TelemetryPoint::from(TelemetryPoint::test(json!({ "energyKwh": 0.25 })));
+    assert_eq!(point.energy_kwh, Some(0.25));
+  }
+
+  #[test]
+  fn maps_the_probes() {
+    let probes = json!([{ "name": "bt1", "channel": "btC", "value": 200.5 }]);
+    let point = proto::TelemetryPoint::from(TelemetryPoint::test(json!({ "probes": probes })));
+    let reading = proto::ProbeReading { name: "bt1".to_string(), channel: "btC".to_string(), value: 200.5 };
+    assert_eq!(point.probes, vec![reading]);
+  }
+}
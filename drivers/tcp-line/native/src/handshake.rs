@@ -0,0 +1,103 @@
+use std::time::Duration;
+
+use schemars::JsonSchema;
+use serde::Deserialize;
+use tokio::io::{AsyncRead, AsyncWriteExt};
+use tokio::net::tcp::OwnedWriteHalf;
+
+use crate::events::{DriverError, DriverErrorCode};
+use crate::framing::{Encoding, FrameReader, ReadFrame};
+
+/// One exchange of the login sequence a gateway requires before it streams data, e.g. send `AUTH <token>` and expect
+/// `OK`.
+#[derive(Debug, Clone, PartialEq, Deserialize, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct HandshakeStep {
+  /// Line written to the device, followed by `commands.lineEnding`.
+  #[serde(default)]
+  pub send: Option<String>,
+  /// Prefix of the reply to wait for (e.g. `OK`); lines before it are discarded. Without it the step only sends.
+  #[serde(default)]
+  pub expect: Option<String>,
+  /// How long to wait for `expect`.
+  #[serde(default = "default_timeout_ms")]
+  pub timeout_ms: u64,
+}
+
+fn default_timeout_ms() -> u64 {
+  5000
+}
+
+/// Runs `steps` in order on a fresh connection, returning the bytes written. Handshake lines are neither recorded nor
+/// parsed as telemetry.
+pub(crate) async fn perform<R>(
+  steps: &[HandshakeStep],
+  reader: &mut FrameReader<R>,
+  writer: &mut OwnedWriteHalf,
+  line_ending: &str,
+  encoding: Encoding,
+) -> Result<usize, DriverError>
+where
+  R: AsyncRead + Unpin,
+{
+  let mut sent = 0;
+  for step in steps {
+    if let Some(send) = &step.send {
+      let payload = format!("{}{}", send, line_ending);
+      writer.write_all(payload.as_bytes()).await.map_err(|err| failed(DriverErrorCode::SocketClosed, err))?;
+      sent += payload.len();
+    }
+    if let Some(expect) = &step.expect {
+      expect_line(reader, expect, step.timeout_ms, encoding).await?;
+    }
+  }
+  Ok(sent)
+}
+
+async fn expect_line<R>(
+  reader: &mut FrameReader<R>,
+  expect: &str,
+  timeout_ms: u64,
+  encoding: Encoding,
+) -> Result<(), DriverError>
+where
+  R: AsyncRead + Unpin,
+{
+  let mut frame = Vec::new();
+  let mut last: Option<String> = None;
+  let wait = async {
+    loop {
+      match reader.read_frame(&mut frame).await {
+        Ok(ReadFrame::Frame(_)) => {
+          let Some(text) = encoding.decode(&frame) else {
+            continue;
+          };
+          let line = text.trim();
+          if line.starts_with(expect) {
+            return Ok(());
+          }
+          last = Some(line.to_string());
+        }
+        Ok(ReadFrame::Invalid(..)) => {}
+        Ok(ReadFrame::Closed) => {
+          return Err(failed(DriverErrorCode::SocketClosed, format!("socket closed waiting for {:?}", expect)))
+        }
+        Err(err) => return Err(failed(DriverErrorCode::SocketClosed, err)),
+      }
+    }
+  };
+  match tokio::time::timeout(Duration::from_millis(timeout_ms), wait).await {
+    Ok(result) => result,
+    Err(_) => {
+      let mut message = format!("no line starting with {:?} within {} ms", expect, timeout_ms);
+      if let Some(last) = last {
+        message.push_str(&format!(" (last: {:?})", last));
+      }
+      Err(failed(DriverErrorCode::Timeout, message))
+    }
+  }
+}
+
+fn failed(code: DriverErrorCode, reason: impl std::fmt::Display) -> DriverError {
+  DriverError::new(code, format!("handshake failed: {}", reason))
+}
@@ -0,0 +1,261 @@
+use std::io;
+use std::sync::{Arc, OnceLock};
+use std::time::Duration;
+
+use tokio::io::{AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt};
+use tokio::net::TcpStream;
+use tokio_rustls::rustls::pki_types::ServerName;
+use tokio_rustls::rustls::{self, ClientConfig, RootCertStore};
+use tokio_rustls::TlsConnector;
+use tracing::warn;
+
+/// Responses larger than this are cut off; the sinks and pollers only expect small bodies.
+const MAX_RESPONSE_BYTES: usize = 4 * 1024 * 1024;
+
+/// An `http://` or `https://host[:port]/path?query` URL; `host` may be a bracketed IPv6 literal.
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct HttpUrl {
+  /// `https://`: the request goes over TLS, with the server certificate checked against the system's trusted roots.
+  pub tls: bool,
+  /// Without the brackets of an IPv6 literal.
+  pub host: String,
+  pub port: u16,
+  /// Path plus query, always starting with `/`.
+  pub target: String,
+}
+
+impl HttpUrl {
+  pub fn parse(url: &str) -> Result<Self, String> {
+    let (tls, rest) = match (url.strip_prefix("https://"), url.strip_prefix("http://")) {
+      (Some(rest), _) => (true, rest),
+      (None, Some(rest)) => (false, rest),
+      (None, None) => return Err("must be an http:// or https:// URL".to_string()),
+    };
+    let (authority, target) = match rest.find(['/', '?']) {
+      Some(idx) if rest[idx..].starts_with('/') => (&rest[..idx], rest[idx..].to_string()),
+      Some(idx) => (&rest[..idx], format!("/{}", &rest[idx..])),
+      None => (rest, "/".to_string()),
+    };
+    let (host, port) = match authority.rsplit_once(':') {
+      Some((host, port)) if !port.contains(']') => {
+        (host, port.parse::<u16>().map_err(|_| format!("invalid port: {}", port))?)
+      }
+      _ => (authority, if tls { 443 } else { 80 }),
+    };
+    let host = host.trim_start_matches('[').trim_end_matches(']');
+    if host.is_empty() {
+      return Err("missing host".to_string());
+    }
+    Ok(Self { tls, host: host.to_string(), port, target })
+  }
+
+  /// Whether requests stay on this machine, so a plain `http://` URL doesn't expose what they carry.
+  pub fn is_loopback(&self) -> bool {
+    match self.host.parse::<std::net::IpAddr>() {
+      Ok(ip) => ip.is_loopback(),
+      Err(_) => self.host.eq_ignore_ascii_case("localhost"),
+    }
+  }
+
+  /// `host:port` as the `Host` header wants it, an IPv6 literal in brackets.
+  fn authority(&self) -> String {
+    if self.host.contains(':') {
+      format!("[{}]:{}", self.host, self.port)
+    } else {
+      format!("{}:{}", self.host, self.port)
+    }
+  }
+
+  /// This URL with `path` appended to its path and `query` (percent-encoded) replacing its query.
+  pub fn with_path(&self, path: &str, query: &[(&str, &str)]) -> Self {
+    let base = self.target.split('?').next().unwrap_or_default().trim_end_matches('/');
+    let mut target = format!("{}{}", base, path);
+    for (idx, (key, value)) in query.iter().enumerate() {
+      target.push(if idx == 0 { '?' } else { '&' });
+      target.push_str(&encode_component(key));
+      target.push('=');
+      target.push_str(&encode_component(value));
+    }
+    Self { tls: self.tls, host: self.host.clone(), port: self.port, target }
+  }
+}
+
+fn encode_component(value: &str) -> String {
+  let mut encoded = String::with_capacity(value.len());
+  for byte in value.bytes() {
+    if byte.is_ascii_alphanumeric() || matches!(byte, b'-' | b'_' | b'.' | b'~') {
+      encoded.push(byte as char);
+    } else {
+      encoded.push_str(&format!("%{:02X}", byte));
+    }
+  }
+  encoded
+}
+
+#[derive(Debug)]
+pub(crate) struct HttpResponse {
+  pub status: u16,
+  pub body: Vec<u8>,
+}
+
+impl HttpResponse {
+  pub fn is_success(&self) -> bool {
+    (200..300).contains(&self.status)
+  }
+}
+
+/// Sends one request on a fresh connection (`Connection: close`) and reads the whole response within `timeout`. A
+/// header value with a line break is refused rather than sent, so it can't smuggle in headers of its own.
+pub(crate) async fn send(
+  method: &str,
+  url: &HttpUrl,
+  headers: &[(String, String)],
+  body: &[u8],
+  timeout: Duration,
+) -> io::Result<HttpResponse> {
+  tokio::time::timeout(timeout, exchange(method, url, headers, body))
+    .await
+    .map_err(|_| io::Error::new(io::ErrorKind::TimedOut, format!("no response within {} ms", timeout.as_millis())))?
+}
+
+async fn exchange(method: &str, url: &HttpUrl, headers: &[(String, String)], body: &[u8]) -> io::Result<HttpResponse> {
+  let mut request = format!("{} {} HTTP/1.1\r\nHost: {}\r\nConnection: close\r\n", method, url.target, url.authority());
+  for (name, value) in headers {
+    if name.contains(['\r', '\n', ':']) || value.contains(['\r', '\n']) {
+      return Err(io::Error::new(io::ErrorKind::InvalidInput, format!("header {} must be a single line", name)));
+    }
+    request.push_str(&format!("{}: {}\r\n", name, value));
+  }
+  if !body.is_empty() || method != "GET" {
+    request.push_str(&format!("Content-Length: {}\r\n", body.len()));
+  }
+  request.push_str("\r\n");
+
+  let stream = TcpStream::connect((url.host.as_str(), url.port)).await?;
+  if !url.tls {
+    return round_trip(stream, request.as_bytes(), body).await;
+  }
+  let server_name = ServerName::try_from(url.host.clone())
+    .map_err(|err| io::Error::new(io::ErrorKind::InvalidInput, format!("invalid TLS server name: {}", err)))?;
+  let stream = TlsConnector::from(tls_config()).connect(server_name, stream).await?;
+  round_trip(stream, request.as_bytes(), body).await
+}
+
+async fn round_trip<S: AsyncRead + AsyncWrite + Unpin>(
+  mut stream: S,
+  request: &[u8],
+  body: &[u8],
+) -> io::Result<HttpResponse> {
+  stream.write_all(request).await?;
+  stream.write_all(body).await?;
+  stream.flush().await?;
+
+  let mut raw = Vec::new();
+  let read = (&mut stream).take(MAX_RESPONSE_BYTES as u64).read_to_end(&mut raw).await;
+  // Plenty of servers close without a TLS close_notify once they have sent a `Connection: close` response.
+  match read {
+    Err(err) if err.kind() == io::ErrorKind::UnexpectedEof && !raw.is_empty() => {}
+    read => {
+      read?;
+    }
+  }
+  parse_response(&raw)
+}
+
+/// Client settings shared by every `https://` request: the system's trusted roots (so a private CA added to it works),
+/// TLS 1.2 and 1.3 with ring. Certificates the system store can't load are skipped.
+fn tls_config() -> Arc<ClientConfig> {
+  static CONFIG: OnceLock<Arc<ClientConfig>> = OnceLock::new();
+  let config = CONFIG.get_or_init(|| {
+    let native = rustls_native_certs::load_native_certs();
+    for err in &native.errors {
+      warn!(error = %err, "loading a system root certificate failed");
+    }
+    let mut roots = RootCertStore::empty();
+    roots.add_parsable_certificates(native.certs);
+    let config = ClientConfig::builder_with_provider(Arc::new(rustls::crypto::ring::default_provider()))
+      .with_safe_default_protocol_versions()
+      .expect("ring supports the default protocol versions")
+      .with_root_certificates(roots)
+      .with_no_client_auth();
+    Arc::new(config)
+  });
+  Arc::clone(config)
+}
+
+fn parse_response(raw: &[u8]) -> io::Result<HttpResponse> {
+  let invalid = |reason: &str| io::Error::new(io::ErrorKind::InvalidData, reason.to_string());
+  let split = raw.windows(4).position(|window| window == b"\r\n\r\n").ok_or_else(|| invalid("truncated response"))?;
+  let head = String::from_utf8_lossy(&raw[..split]);
+  let mut lines = head.split("\r\n");
+  let status = lines
+    .next()
+    .and_then(|line| line.split_whitespace().nth(1))
+    .and_then(|status| status.parse::<u16>().ok())
+    .ok_or_else(|| invalid("invalid status line"))?;
+  let mut chunked = false;
+  let mut content_length = None;
+  for line in lines {
+    let Some((name, value)) = line.split_once(':') else {
+      continue;
+    };
+    let value = value.trim();
+    if name.eq_ignore_ascii_case("transfer-encoding") {
+      chunked = value.eq_ignore_ascii_case("chunked");
+    } else if name.eq_ignore_ascii_case("content-length") {
+      content_length = value.parse::<usize>().ok();
+    }
+  }
+  let body = &raw[split + 4..];
+  let body = if chunked {
+    decode_chunked(body).ok_or_else(|| invalid("invalid chunked body"))?
+  } else {
+    body[..content_length.unwrap_or(body.len()).min(body.len())].to_vec()
+  };
+  Ok(HttpResponse { status, body })
+}
+
+fn decode_chunked(mut body: &[u8]) -> Option<Vec<u8>> {
+  let mut decoded = Vec::new();
+  loop {
+    let line_end = body.windows(2).position(|window| window == b"\r\n")?;
+    let size = std::str::from_utf8(&body[..line_end]).ok()?;
+    let size = usize::from_str_radix(size.split(';').next()?.trim(), 16).ok()?;
+    body = &body[line_end + 2..];
+    if size == 0 {
+      return Some(decoded);
+    }
+    decoded.extend_from_slice(body.get(..size)?);
+    body = body.get(size + 2..)?;
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn parses_http_and_https_urls() {
+    let url = HttpUrl::parse("https://eu-central-1-1.aws.cloud2.influxdata.com/api?x=1").expect("url");
+    assert_eq!((url.tls, url.port, url.target.as_str()), (true, 443, "/api?x=1"));
+    let url = HttpUrl::parse("http://influx:8086").expect("url");
+    assert_eq!((url.tls, url.host.as_str(), url.port, url.target.as_str()), (false, "influx", 8086, "/"));
+    assert!(HttpUrl::parse("ftp://influx").is_err());
+  }
+
+  #[test]
+  fn brackets_an_ipv6_host() {
+    let url = HttpUrl::parse("http://[fd00::1]:8086/").expect("url");
+    assert_eq!((url.host.as_str(), url.authority()), ("fd00::1", "[fd00::1]:8086".to_string()));
+    assert!(HttpUrl::parse("http://[::1]/").expect("url").is_loopback());
+    assert!(!url.is_loopback());
+  }
+
+  #[tokio::test]
+  async fn refuses_a_header_value_with_a_line_break() {
+    let url = HttpUrl::parse("http://127.0.0.1:9/").expect("url");
+    let headers = [("Authorization".to_string(), "Token t\r\nX-Injected: 1".to_string())];
+    let err = send("GET", &url, &headers, &[], Duration::from_secs(1)).await.expect_err("sent");
+    assert_eq!(err.kind(), io::ErrorKind::InvalidInput);
+  }
+}
@@ -0,0 +1,436 @@
+use std::collections::BTreeMap;
+use std::sync::atomic::Ordering;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use napi::bindgen_prelude::*;
+use napi_derive::napi;
+use serde::Deserialize;
+use tokio::time::{sleep, MissedTickBehavior};
+use tracing::{debug, error, info};
+
+use crate::aggregate::TelemetryAggregate;
+use crate::cancel::{self, Cancel};
+use crate::csv_record::ColumnMismatch;
+use crate::events::{DriverError, DriverErrorCode};
+use crate::http_client::{self, HttpUrl};
+use crate::phases::SessionSummary;
+use crate::recent_errors::RecentParseError;
+use crate::session_log::SessionLogSummary;
+use crate::stats::DetailedMetrics;
+use crate::stream::TelemetryStream;
+use crate::subscription::TelemetrySubscription;
+use crate::{
+  CsvConfig, DriverInner, DriverState, DriverStatus, FrameFormat, ParseError, PipelineConfig, ReconnectConfig,
+  TcpLineDriverConfig, TelemetryPoint,
+};
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct HttpPollDriverConfig {
+  /// `http://` or `https://host[:port]/path[?query]`, fetched with GET.
+  url: String,
+  /// Sent with every request, e.g. `Authorization`.
+  #[serde(default)]
+  headers: BTreeMap<String, String>,
+  /// Time between the starts of two polls; a slow response delays the next poll rather than overlapping it.
+  #[serde(default = "default_poll_interval_ms")]
+  poll_interval_ms: u64,
+  /// Per request, connecting included.
+  #[serde(default = "default_request_timeout_ms")]
+  request_timeout_ms: u64,
+  /// Channel (`btC`, `etC`, ..., `ts` for the sample timestamp, or any other name for an extra) to a JSONPath-style
+  /// path into the response body: `$.temps.bean`, `$.channels[0].value` or `$['fan %']`.
+  mapping: BTreeMap<String, String>,
+  reconnect: ReconnectConfig,
+  #[serde(flatten)]
+  pipeline: PipelineConfig,
+}
+
+fn default_poll_interval_ms() -> u64 {
+  1000
+}
+
+fn default_request_timeout_ms() -> u64 {
+  5000
+}
+
+impl HttpPollDriverConfig {
+  fn line_config(&self) -> TcpLineDriverConfig {
+    TcpLineDriverConfig {
+      reconnect: self.reconnect.clone(),
+      ..TcpLineDriverConfig::for_source(
+        FrameFormat::Jsonl,
+        CsvConfig {
+          has_header: false,
+          columns: Vec::new(),
+          delimiter: ",".to_string(),
+          column_mismatch: ColumnMismatch::default(),
+        },
+        self.pipeline.clone(),
+      )
+    }
+  }
+
+  fn validate(&self) -> std::result::Result<(HttpUrl, Vec<(String, JsonPath)>), String> {
+    let url = HttpUrl::parse(&self.url).map_err(|err| format!("url {}", err))?;
+    if self.poll_interval_ms == 0 {
+      return Err("pollIntervalMs must be positive".to_string());
+    }
+    if self.request_timeout_ms == 0 {
+      return Err("requestTimeoutMs must be positive".to_string());
+    }
+    if let Some(name) = self.headers.keys().find(|name| !is_header_name(name)) {
+      return Err(format!("headers: invalid header name {:?}", name));
+    }
+    if let Some(name) = self.headers.iter().find(|(_, value)| value.contains(['\r', '\n'])).map(|(name, _)| name) {
+      return Err(format!("headers.{}: value must be a single line", name));
+    }
+    if self.mapping.is_empty() {
+      return Err("mapping must map at least one channel".to_string());
+    }
+    let mapping = self
+      .mapping
+      .iter()
+      .map(|(channel, path)| match JsonPath::parse(path) {
+        Some(path) => Ok((channel.clone(), path)),
+        None => Err(format!("mapping.{}: invalid path {:?}", channel, path)),
+      })
+      .collect::<std::result::Result<Vec<_>, _>>()?;
+    Ok((url, mapping))
+  }
+
+  /// The configured headers, plus `Accept: application/json` unless one was given.
+  fn request_headers(&self) -> Vec<(String, String)> {
+    let mut headers: Vec<(String, String)> =
+      self.headers.iter().map(|(name, value)| (name.clone(), value.clone())).collect();
+    if !headers.iter().any(|(name, _)| name.eq_ignore_ascii_case("accept")) {
+      headers.push(("Accept".to_string(), "application/json".to_string()));
+    }
+    headers
+  }
+}
+
+fn is_header_name(name: &str) -> bool {
+  !name.is_empty() && name.bytes().all(|byte| byte.is_ascii_alphanumeric() || b"!#$%&'*+-.^_`|~".contains(&byte))
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum PathSegment {
+  Key(String),
+  Index(usize),
+}
+
+/// A field selector in the JSONPath subset that plain lookups need: an optional leading `$`, then `.key`, `['key']`
+/// (or `["key"]`) and `[index]` steps. A path without `$` starts with a bare key, as in `temps.bean`.
+#[derive(Debug, Clone, PartialEq)]
+struct JsonPath(Vec<PathSegment>);
+
+impl JsonPath {
+  fn parse(path: &str) -> Option<Self> {
+    let mut rest = match path.strip_prefix('$') {
+      Some(rest) => rest.to_string(),
+      None if path.is_empty() || path.starts_with(['.', '[']) => return None,
+      None => format!(".{}", path),
+    };
+    let mut segments = Vec::new();
+    while !rest.is_empty() {
+      let remaining = if let Some(after) = rest.strip_prefix('.') {
+        let end = after.find(['.', '[']).unwrap_or(after.len());
+        if end == 0 {
+          return None;
+        }
+        segments.push(PathSegment::Key(after[..end].to_string()));
+        after[end..].to_string()
+      } else if let Some(after) = rest.strip_prefix('[') {
+        match after.chars().next() {
+          Some(quote @ ('\'' | '"')) => {
+            let close = after[1..].find(quote)? + 1;
+            segments.push(PathSegment::Key(after[1..close].to_string()));
+            after[close + 1..].strip_prefix(']')?.to_string()
+          }
+          _ => {
+            let close = after.find(']')?;
+            segments.push(PathSegment::Index(after[..close].trim().parse().ok()?));
+            after[close + 1..].to_string()
+          }
+        }
+      } else {
+        return None;
+      };
+      rest = remaining;
+    }
+    Some(Self(segments))
+  }
+
+  /// The selected value, or `None` if any step is missing.
+  fn select<'a>(&self, document: &'a serde_json::Value) -> Option<&'a serde_json::Value> {
+    self.0.iter().try_fold(document, |value, segment| match segment {
+      PathSegment::Key(key) => value.get(key),
+      PathSegment::Index(idx) => value.get(idx),
+    })
+  }
+}
+
+/// Polls a JSON status endpoint and feeds the mapped fields through the same sample pipeline as
+/// `TcpLineDriverNative`: every successful poll becomes one sample.
+#[napi]
+pub struct HttpPollDriverNative {
+  config: HttpPollDriverConfig,
+  url: HttpUrl,
+  mapping: Vec<(String, JsonPath)>,
+  pub(crate) inner: Arc<DriverInner>,
+}
+
+#[napi]
+impl HttpPollDriverNative {
+  #[napi(constructor)]
+  pub fn new(config_json: String, machine_id: String) -> Result<Self> {
+    let config: HttpPollDriverConfig = serde_json::from_str(&config_json)
+      .map_err(|err| Error::from_reason(format!("invalid config: {}", err)))?;
+    let (url, mapping) = config.validate().map_err(|err| Error::from_reason(format!("invalid config: {}", err)))?;
+    let inner = DriverInner::new(config.line_config(), machine_id);
+    Ok(Self { config, url, mapping, inner })
+  }
+
+  #[napi(ts_args_type = "timeoutMs?: number, cancel?: CancelToken")]
+  pub async fn connect(&self, timeout_ms: Option<u32>, cancel: Option<Cancel>) -> Result<()> {
+    let poller = Poller {
+      headers: self.config.request_headers(),
+      config: self.config.clone(),
+      url: self.url.clone(),
+      mapping: self.mapping.clone(),
+    };
+    let since = self.inner.connected_seq.load(Ordering::Relaxed);
+    self.inner.ensure_source(move |inner| run_polling(inner, poller));
+    cancel::bounded("connect", self.inner.wait_for_connected(since), timeout_ms, cancel).await
+  }
+
+  #[napi(ts_args_type = "timeoutMs?: number, cancel?: CancelToken")]
+  pub async fn read_telemetry(&self, timeout_ms: Option<u32>, cancel: Option<Cancel>) -> Result<TelemetryPoint> {
+    self.inner.read_telemetry(timeout_ms, cancel).await
+  }
+
+  #[napi(ts_args_type = "timeoutMs: number, cancel?: CancelToken")]
+  pub async fn read_telemetry_with_timeout(
+    &self,
+    timeout_ms: u32,
+    cancel: Option<Cancel>,
+  ) -> Result<Option<TelemetryPoint>> {
+    self.inner.read_telemetry_with_timeout(timeout_ms, cancel).await
+  }
+
+  #[napi]
+  pub async fn peek_telemetry(&self) -> Result<TelemetryPoint> {
+    self.inner.peek_telemetry().await
+  }
+
+  #[napi]
+  pub async fn disconnect(&self) -> Result<()> {
+    self.inner.disconnect().await;
+    Ok(())
+  }
+
+  #[napi]
+  pub fn get_status(&self) -> Result<DriverStatus> {
+    Ok(self.inner.get_status())
+  }
+
+  #[napi]
+  pub fn get_metrics_detailed(&self) -> DetailedMetrics {
+    self.inner.get_metrics_detailed()
+  }
+
+  /// The last 50 parse failures, oldest first, with the offending line cut to 256 characters.
+  #[napi]
+  pub fn get_recent_errors(&self) -> Vec<RecentParseError> {
+    self.inner.get_recent_errors()
+  }
+
+  /// Async-iterator-shaped stream of samples; see `TelemetryStream`.
+  #[napi]
+  pub fn telemetry_stream(&self) -> TelemetryStream {
+    TelemetryStream::new(Arc::clone(&self.inner))
+  }
+
+  /// A handle of its own onto every point published from now on; see `TelemetrySubscription`.
+  #[napi]
+  pub fn subscribe(&self) -> TelemetrySubscription {
+    TelemetrySubscription::new(Arc::clone(&self.inner))
+  }
+
+  /// Takes up to `maxSamples` (default all) queued samples, oldest first; empty unless `queue` is configured.
+  #[napi]
+  pub fn drain_samples(&self, max_samples: Option<u32>) -> Vec<TelemetryPoint> {
+    self.inner.drain_samples(max_samples)
+  }
+
+  /// Per-channel min/max/mean/count over the samples of the last `windowSeconds` (by sample timestamp).
+  #[napi]
+  pub fn read_aggregate(&self, window_seconds: f64) -> Result<TelemetryAggregate> {
+    self.inner.read_aggregate(window_seconds)
+  }
+
+  /// The current roast's phases from CHARGE, with their durations, end temperatures and mean RoR; `null` before
+  /// CHARGE. Needs `roastEvents`.
+  #[napi]
+  pub fn get_session_summary(&self) -> Option<SessionSummary> {
+    self.inner.session_summary()
+  }
+
+  /// Collects every emitted point from now on and writes them to `path` on `stopLogging`. `format` is `csv` or
+  /// `parquet` (default: from the extension, else csv); the header lists the point fields followed by one column per
+  /// extra.
+  #[napi]
+  pub fn start_logging(&self, path: String, format: Option<String>) -> Result<()> {
+    self.inner.start_logging(path, format)
+  }
+
+  /// Writes the log begun by `startLogging` and returns what was written (`null` when not logging).
+  #[napi]
+  pub fn stop_logging(&self) -> Result<Option<SessionLogSummary>> {
+    self.inner.stop_logging()
+  }
+
+  /// Registers a callback for state transitions (CONNECTING, CONNECTED, DISCONNECTED, ...).
+  #[napi(ts_args_type = "callback: (event: StateChangeEvent) => void")]
+  pub fn on_state_change(&self, env: Env, callback: JsFunction) -> Result<()> {
+    self.inner.state_events.subscribe(&env, callback)
+  }
+
+  /// Registers a callback for failed polls.
+  #[napi(ts_args_type = "callback: (error: DriverError) => void")]
+  pub fn on_error(&self, env: Env, callback: JsFunction) -> Result<()> {
+    self.inner.error_events.subscribe(&env, callback)
+  }
+
+  /// Registers a callback for detected roast events (requires `roastEvents` in the config).
+  #[napi(ts_args_type = "callback: (event: RoastEvent) => void")]
+  pub fn on_event(&self, env: Env, callback: JsFunction) -> Result<()> {
+    self.inner.roast_events.subscribe(&env, callback)
+  }
+}
+
+struct Poller {
+  config: HttpPollDriverConfig,
+  url: HttpUrl,
+  headers: Vec<(String, String)>,
+  mapping: Vec<(String, JsonPath)>,
+}
+
+/// Polls until a request fails, then backs off per `reconnect` like the TCP loop and starts over; the driver is
+/// CONNECTED from the first good response of each round.
+async fn run_polling(inner: Arc<DriverInner>, poller: Poller) {
+  let reconnect = &poller.config.reconnect;
+  inner.apply_backoff(reconnect);
+  inner.backoff.lock().reset();
+  let mut attempts: u32 = 0;
+  let mut outage_started: Option<Instant> = None;
+  let mut exhausted = false;
+  loop {
+    if inner.stop_flag.load(Ordering::Relaxed) {
+      break;
+    }
+    inner.set_state(DriverState::CONNECTING);
+    inner.reset_connection_state();
+    inner.reset_roast_events();
+
+    debug!(machine_id = %inner.machine_id, url = %poller.config.url, attempt = attempts + 1, "polling");
+    let (connected, err) = poll_until_failure(&inner, &poller).await;
+    if connected {
+      attempts = 0;
+      outage_started = None;
+    }
+    inner.handle_failure(err).await;
+
+    if inner.stop_flag.load(Ordering::Relaxed) || !reconnect.enabled {
+      break;
+    }
+    attempts = attempts.saturating_add(1);
+    let outage = outage_started.get_or_insert_with(Instant::now).elapsed();
+    if reconnect.exhausted(attempts, outage) {
+      let outage_ms = outage.as_millis() as u64;
+      error!(machine_id = %inner.machine_id, attempts, outage_ms, "reconnect policy exhausted");
+      exhausted = true;
+      break;
+    }
+    {
+      let mut metrics = inner.metrics.lock();
+      metrics.reconnects = metrics.reconnects.saturating_add(1);
+    }
+    let delay = { inner.backoff.lock().next_delay() };
+    debug!(machine_id = %inner.machine_id, delay_ms = delay, attempt = attempts, "repolling after backoff");
+    sleep(Duration::from_millis(delay)).await;
+  }
+
+  inner.set_state(if inner.stop_flag.load(Ordering::Relaxed) {
+    DriverState::STOPPED
+  } else if exhausted {
+    DriverState::FAILED
+  } else {
+    DriverState::DISCONNECTED
+  });
+}
+
+/// Returns whether any poll succeeded, and the failure that ended the round.
+async fn poll_until_failure(inner: &Arc<DriverInner>, poller: &Poller) -> (bool, DriverError) {
+  let config = &poller.config;
+  let timeout = Duration::from_millis(config.request_timeout_ms);
+  let mut ticks = tokio::time::interval(Duration::from_millis(config.poll_interval_ms));
+  ticks.set_missed_tick_behavior(MissedTickBehavior::Delay);
+  let mut connected = false;
+  loop {
+    ticks.tick().await;
+    let body = match http_client::send("GET", &poller.url, &poller.headers, &[], timeout).await {
+      Ok(response) if response.is_success() => response.body,
+      Ok(response) => {
+        let message = format!("HTTP {}: {}", response.status, String::from_utf8_lossy(&response.body).trim());
+        return (connected, poll_failed(connected, DriverErrorCode::ConnectFailed, message));
+      }
+      Err(err) if err.kind() == std::io::ErrorKind::TimedOut => {
+        return (connected, poll_failed(connected, DriverErrorCode::Timeout, err.to_string()));
+      }
+      Err(err) => return (connected, poll_failed(connected, DriverErrorCode::ConnectFailed, err.to_string())),
+    };
+    if !connected {
+      connected = true;
+      inner.backoff.lock().reset();
+      inner.metrics.lock().lastError = None;
+      info!(machine_id = %inner.machine_id, url = %config.url, "connected");
+      inner.set_state(DriverState::CONNECTED);
+    }
+    inner.wait_for_queue_space().await;
+    accept_response(inner, &poller.mapping, &body);
+  }
+}
+
+fn poll_failed(connected: bool, code: DriverErrorCode, message: String) -> DriverError {
+  let prefix = if connected { "poll failed" } else { "connection failure" };
+  DriverError::new(code, format!("{}: {}", prefix, message))
+}
+
+/// Picks the mapped fields out of one response and emits them as a sample; a body that isn't JSON is a parse error.
+fn accept_response(inner: &DriverInner, mapping: &[(String, JsonPath)], body: &[u8]) {
+  inner.count_line(body.len());
+  let raw = String::from_utf8_lossy(body);
+  let Ok(document) = serde_json::from_slice::<serde_json::Value>(body) else {
+    inner.count_parse_error(&ParseError::InvalidJson, raw.trim());
+    return;
+  };
+  // Objects and arrays have no reading to offer; like missing fields they leave the channel out.
+  let record = mapping
+    .iter()
+    .filter_map(|(channel, path)| {
+      path
+        .select(&document)
+        .filter(|value| !value.is_object() && !value.is_array())
+        .map(|value| (channel.clone(), value.clone()))
+    })
+    .collect();
+  let parsed = inner.record_to_sample(record, || raw.trim().to_string());
+  match parsed {
+    Ok(Some(sample)) => inner.accept_sample(sample),
+    Ok(None) => {}
+    Err(err) => inner.count_parse_error(&err, raw.trim()),
+  }
+}
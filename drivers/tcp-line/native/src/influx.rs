@@ -0,0 +1,260 @@
+use std::fmt::Write as _;
+use std::sync::atomic::{AtomicI64, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+
+use chrono::DateTime;
+use schemars::JsonSchema;
+use serde::Deserialize;
+use tokio::sync::mpsc;
+use tokio::task::JoinHandle;
+use tracing::warn;
+
+use crate::http_client::{self, HttpUrl};
+use crate::TelemetryPoint;
+
+#[derive(Debug, Clone, PartialEq, Deserialize, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct InfluxConfig {
+  /// Base URL of the InfluxDB v2 server, e.g. `http://influx:8086`; `https://` for TLS, which a token needs unless
+  /// the server is on this machine.
+  pub url: String,
+  pub org: String,
+  pub bucket: String,
+  /// API token, sent as `Authorization: Token <token>`.
+  #[serde(default)]
+  pub token: Option<String>,
+  #[serde(default = "default_measurement")]
+  pub measurement: String,
+  /// Points per write request.
+  #[serde(default = "default_batch_size")]
+  pub batch_size: u32,
+  /// A partial batch is written after this long.
+  #[serde(default = "default_flush_interval_ms")]
+  pub flush_interval_ms: u64,
+  /// Retries of a failed write (connection error, 5xx or 429) before the batch is dropped.
+  #[serde(default = "default_max_retries")]
+  pub max_retries: u32,
+  /// First retry delay, doubled for every further attempt.
+  #[serde(default = "default_retry_backoff_ms")]
+  pub retry_backoff_ms: u64,
+  /// Points held while writes are slow or retrying; further points are dropped and counted in `droppedSamples`.
+  #[serde(default = "default_max_buffered_points")]
+  pub max_buffered_points: u32,
+}
+
+fn default_measurement() -> String {
+  "roaster_telemetry".to_string()
+}
+
+fn default_batch_size() -> u32 {
+  500
+}
+
+fn default_flush_interval_ms() -> u64 {
+  1000
+}
+
+fn default_max_retries() -> u32 {
+  3
+}
+
+fn default_retry_backoff_ms() -> u64 {
+  500
+}
+
+fn default_max_buffered_points() -> u32 {
+  10_000
+}
+
+const WRITE_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Delivery counters shared between a driver and its sink tasks; they outlive a sink replaced by `updateConfig`.
+#[derive(Default)]
+pub(crate) struct SinkStats {
+  /// Points (Influx) or messages (Kafka) acknowledged.
+  pub written: AtomicI64,
+  /// What was dropped, in each sink's unit: Influx counts failed write requests, each a batch of points, while Kafka
+  /// counts the messages of the batches it gave up on.
+  pub errors: AtomicI64,
+}
+
+/// Background writer for one driver: points are queued without blocking the read loop and written in batches.
+pub(crate) struct InfluxSink {
+  sender: mpsc::Sender<TelemetryPoint>,
+  handle: JoinHandle<()>,
+}
+
+impl InfluxSink {
+  pub fn spawn(config: InfluxConfig, stats: Arc<SinkStats>) -> Self {
+    let (sender, receiver) = mpsc::channel(config.max_buffered_points.max(1) as usize);
+    let handle = tokio::spawn(run(config, receiver, stats));
+    Self { sender, handle }
+  }
+
+  /// Queues `point`; returns `false` if the buffer is full.
+  pub fn send(&self, point: TelemetryPoint) -> bool {
+    self.sender.try_send(point).is_ok()
+  }
+
+  /// Stops taking points and waits until the buffered ones are written.
+  pub async fn close(self) {
+    drop(self.sender);
+    let _ = self.handle.await;
+  }
+}
+
+/// Runs until the sink is dropped, then writes what is still buffered.
+async fn run(config: InfluxConfig, mut receiver: mpsc::Receiver<TelemetryPoint>, stats: Arc<SinkStats>) {
+  let url = match HttpUrl::parse(&config.url) {
+    Ok(url) => url.with_path(
+      "/api/v2/write",
+      &[("org", config.org.as_str()), ("bucket", config.bucket.as_str()), ("precision", "ms")],
+    ),
+    Err(err) => {
+      warn!(url = %config.url, error = %err, "influx sink disabled");
+      return;
+    }
+  };
+  let mut headers = vec![("Content-Type".to_string(), "text/plain; charset=utf-8".to_string())];
+  if let Some(token) = &config.token {
+    headers.push(("Authorization".to_string(), format!("Token {}", token)));
+  }
+  let period = Duration::from_millis(config.flush_interval_ms.max(1));
+  let mut flush = tokio::time::interval_at(tokio::time::Instant::now() + period, period);
+  let mut batch = String::new();
+  let mut count = 0usize;
+  loop {
+    let closed = tokio::select! {
+      point = receiver.recv() => match point {
+        Some(point) => {
+          if write_line(&mut batch, &config.measurement, &point) {
+            count += 1;
+          }
+          if count < config.batch_size as usize {
+            continue;
+          }
+          false
+        }
+        None => true,
+      },
+      _ = flush.tick() => false,
+    };
+    if count > 0 {
+      write_batch(&config, &url, &headers, std::mem::take(&mut batch), count, &stats).await;
+      count = 0;
+    }
+    if closed {
+      return;
+    }
+  }
+}
+
+async fn write_batch(
+  config: &InfluxConfig,
+  url: &HttpUrl,
+  headers: &[(String, String)],
+  body: String,
+  count: usize,
+  stats: &SinkStats,
+) {
+  let mut attempt = 0;
+  loop {
+    let error = match http_client::send("POST", url, headers, body.as_bytes(), WRITE_TIMEOUT).await {
+      Ok(response) if response.is_success() => {
+        stats.written.fetch_add(count as i64, Ordering::Relaxed);
+        return;
+      }
+      Ok(response) => {
+        let message = format!("HTTP {}: {}", response.status, String::from_utf8_lossy(&response.body).trim());
+        // Anything but overload is the request's fault (bad token, unknown bucket); retrying won't help.
+        if response.status < 500 && response.status != 429 {
+          stats.errors.fetch_add(1, Ordering::Relaxed);
+          warn!(url = %config.url, points = count, error = %message, "influx write rejected");
+          return;
+        }
+        message
+      }
+      Err(err) => err.to_string(),
+    };
+    if attempt >= config.max_retries {
+      stats.errors.fetch_add(1, Ordering::Relaxed);
+      warn!(url = %config.url, points = count, error = %error, "influx write failed, dropping batch");
+      return;
+    }
+    tokio::time::sleep(Duration::from_millis(config.retry_backoff_ms.saturating_mul(1 << attempt.min(16)))).await;
+    attempt += 1;
+  }
+}
+
+/// Appends `point` as one line of line protocol; returns `false` if its timestamp can't be read.
+fn write_line(out: &mut String, measurement: &str, point: &TelemetryPoint) -> bool {
+  let Ok(ts) = DateTime::parse_from_rfc3339(&point.ts) else {
+    return false;
+  };
+  out.push_str(&escape(measurement, &[',', ' ']));
+  let _ = write!(out, ",machine_id={}", escape(&point.machineId, &[',', '=', ' ']));
+  if let Some(session_id) = &point.sessionId {
+    let _ = write!(out, ",session_id={}", escape(session_id, &[',', '=', ' ']));
+  }
+  let _ = write!(out, " elapsedSeconds={}", point.elapsedSeconds);
+  let channels = [
+    ("btC", point.btC),
+    ("etC", point.etC),
+    ("gasPct", point.gasPct),
+    ("powerPct", point.powerPct),
+    ("fanPct", point.fanPct),
+    ("drumRpm", point.drumRpm),
+    ("ambientC", point.ambientC),
+    ("humidityPct", point.humidityPct),
+    ("pressureHpa", point.pressureHpa),
+    ("exhaustC", point.exhaustC),
+    ("inletC", point.inletC),
+    ("drumPressurePa", point.drumPressurePa),
+    ("airflowPct", point.airflowPct),
+    ("energyKwh", point.energyKwh),
+  ];
+  for (name, value) in channels {
+    if let Some(value) = value.filter(|value| value.is_finite()) {
+      let _ = write!(out, ",{}={}", name, value);
+    }
+  }
+  // A probe named after its channel is already there as the channel.
+  for probe in point.probes.iter().flatten().filter(|probe| probe.name != probe.channel && probe.value.is_finite()) {
+    let _ = write!(out, ",{}={}", escape(&probe.name, &[',', '=', ' ']), probe.value);
+  }
+  for extra in point.extras.iter().flatten() {
+    let key = escape(&extra.key, &[',', '=', ' ']);
+    match (extra.number_value.filter(|value| value.is_finite()), &extra.text_value) {
+      (Some(value), _) => {
+        let _ = write!(out, ",{}={}", key, value);
+      }
+      (None, Some(text)) => {
+        let _ = write!(out, ",{}=\"{}\"", key, escape(text, &['"', '\\']));
+      }
+      (None, None) => {
+        if let Some(flag) = extra.bool_value {
+          let _ = write!(out, ",{}={}", key, flag);
+        }
+      }
+    }
+  }
+  let _ = writeln!(out, " {}", ts.timestamp_millis());
+  true
+}
+
+fn escape(value: &str, special: &[char]) -> String {
+  let mut escaped = String::with_capacity(value.len());
+  for ch in value.chars() {
+    // Line protocol has no way to carry a newline; keep the line intact.
+    if ch == '\n' {
+      escaped.push_str("\\n");
+      continue;
+    }
+    if special.contains(&ch) {
+      escaped.push('\\');
+    }
+    escaped.push(ch);
+  }
+  escaped
+}
@@ -0,0 +1,485 @@
+use std::collections::BTreeMap;
+use std::sync::atomic::Ordering;
+use std::sync::Arc;
+use std::time::Duration;
+
+use chrono::{DateTime, Utc};
+use rskafka::client::partition::{Compression, PartitionClient, UnknownTopicHandling};
+use rskafka::client::ClientBuilder;
+use rskafka::record::Record;
+use rskafka::BackoffConfig;
+use schemars::JsonSchema;
+use serde::Deserialize;
+use tokio::sync::mpsc;
+use tokio::task::JoinHandle;
+use tokio::time::Instant;
+use tracing::{debug, warn};
+
+use crate::influx::SinkStats;
+use crate::TelemetryPoint;
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Deserialize, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub(crate) enum KafkaFormat {
+  /// The point as a JSON object, as returned by `readTelemetry`.
+  #[default]
+  Json,
+  /// Avro single-object encoding of [`AVRO_SCHEMA`]: `C3 01`, the schema's 8-byte fingerprint, then the datum.
+  Avro,
+}
+
+#[derive(Debug, Clone, PartialEq, Deserialize, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct KafkaConfig {
+  /// Bootstrap brokers as `host:port`.
+  pub brokers: Vec<String>,
+  /// Must already exist; the partition is picked from the key like the Java client's default partitioner.
+  pub topic: String,
+  #[serde(default)]
+  pub format: KafkaFormat,
+  #[serde(default = "default_client_id")]
+  pub client_id: String,
+  /// Messages per produce request.
+  #[serde(default = "default_batch_size")]
+  pub batch_size: u32,
+  /// A partial batch is produced after this long.
+  #[serde(default = "default_linger_ms")]
+  pub linger_ms: u64,
+  /// How long a batch is retried (reconnecting as needed) before it is dropped.
+  #[serde(default = "default_delivery_timeout_ms")]
+  pub delivery_timeout_ms: u64,
+  /// Points held while the brokers are slow or unreachable; further points are dropped and counted in
+  /// `droppedSamples`.
+  #[serde(default = "default_max_buffered_points")]
+  pub max_buffered_points: u32,
+}
+
+fn default_client_id() -> String {
+  "tcp-line-driver".to_string()
+}
+
+fn default_batch_size() -> u32 {
+  100
+}
+
+fn default_linger_ms() -> u64 {
+  100
+}
+
+fn default_delivery_timeout_ms() -> u64 {
+  30_000
+}
+
+fn default_max_buffered_points() -> u32 {
+  10_000
+}
+
+/// Wait before the first retry of a batch; it doubles up to [`MAX_RETRY_BACKOFF`].
+const RETRY_BACKOFF: Duration = Duration::from_millis(100);
+const MAX_RETRY_BACKOFF: Duration = Duration::from_secs(5);
+
+/// Background producer for one driver; every message is keyed by the driver's machine id.
+pub(crate) struct KafkaSink {
+  sender: mpsc::Sender<TelemetryPoint>,
+  handle: JoinHandle<()>,
+}
+
+impl KafkaSink {
+  pub fn spawn(config: KafkaConfig, machine_id: String, stats: Arc<SinkStats>) -> Self {
+    let (sender, receiver) = mpsc::channel(config.max_buffered_points.max(1) as usize);
+    let handle = tokio::spawn(run(config, machine_id, receiver, stats));
+    Self { sender, handle }
+  }
+
+  /// Queues `point`; returns `false` if the buffer is full.
+  pub fn send(&self, point: TelemetryPoint) -> bool {
+    self.sender.try_send(point).is_ok()
+  }
+
+  /// Stops taking points and waits until the buffered ones are produced.
+  pub async fn close(self) {
+    drop(self.sender);
+    let _ = self.handle.await;
+  }
+}
+
+/// Runs until the sink is dropped, then produces what is still buffered.
+async fn run(
+  config: KafkaConfig,
+  machine_id: String,
+  mut receiver: mpsc::Receiver<TelemetryPoint>,
+  stats: Arc<SinkStats>,
+) {
+  let period = Duration::from_millis(config.linger_ms.max(1));
+  let mut linger = tokio::time::interval_at(Instant::now() + period, period);
+  let mut producer: Option<PartitionClient> = None;
+  let mut batch = Vec::new();
+  loop {
+    let closed = tokio::select! {
+      point = receiver.recv() => match point {
+        Some(point) => {
+          batch.push(record(&config, &machine_id, &point));
+          if batch.len() < config.batch_size as usize {
+            continue;
+          }
+          false
+        }
+        None => true,
+      },
+      _ = linger.tick() => false,
+    };
+    if !batch.is_empty() {
+      deliver(&config, &machine_id, &mut producer, std::mem::take(&mut batch), &stats).await;
+    }
+    if closed {
+      return;
+    }
+  }
+}
+
+/// Produces `records`, reconnecting and backing off between tries, until they are acknowledged or `delivery_timeout_ms`
+/// has passed since the first try; then they are dropped and counted as errors. Points queue up in the meantime.
+async fn deliver(
+  config: &KafkaConfig,
+  machine_id: &str,
+  producer: &mut Option<PartitionClient>,
+  records: Vec<Record>,
+  stats: &SinkStats,
+) {
+  let count = records.len() as i64;
+  let deadline = Instant::now() + Duration::from_millis(config.delivery_timeout_ms);
+  let mut backoff = RETRY_BACKOFF;
+  loop {
+    let error = match produce(config, machine_id, producer, records.clone(), deadline).await {
+      Ok(()) => {
+        stats.written.fetch_add(count, Ordering::Relaxed);
+        return;
+      }
+      Err(err) => err,
+    };
+    // Reconnect for the next try; the brokers or the partition leader may have moved.
+    *producer = None;
+    let now = Instant::now();
+    if now >= deadline {
+      stats.errors.fetch_add(count, Ordering::Relaxed);
+      warn!(topic = %config.topic, messages = count, error = %error, "kafka produce failed, dropping batch");
+      return;
+    }
+    debug!(topic = %config.topic, messages = count, error = %error, "kafka produce failed, retrying");
+    tokio::time::sleep(backoff.min(deadline - now)).await;
+    backoff = (backoff * 2).min(MAX_RETRY_BACKOFF);
+  }
+}
+
+async fn produce(
+  config: &KafkaConfig,
+  machine_id: &str,
+  producer: &mut Option<PartitionClient>,
+  records: Vec<Record>,
+  deadline: Instant,
+) -> Result<(), String> {
+  tokio::time::timeout_at(deadline, async {
+    if producer.is_none() {
+      *producer = Some(connect(config, machine_id).await?);
+    }
+    let Some(client) = producer.as_ref() else {
+      return Ok(());
+    };
+    client.produce(records, Compression::NoCompression).await.map(|_| ()).map_err(|err| err.to_string())
+  })
+  .await
+  .map_err(|_| format!("not delivered within {} ms", config.delivery_timeout_ms))?
+}
+
+async fn connect(config: &KafkaConfig, machine_id: &str) -> Result<PartitionClient, String> {
+  let backoff = BackoffConfig {
+    max_backoff: Duration::from_secs(5),
+    deadline: Some(Duration::from_millis(config.delivery_timeout_ms)),
+    ..BackoffConfig::default()
+  };
+  let client = ClientBuilder::new(config.brokers.clone())
+    .client_id(config.client_id.as_str())
+    .backoff_config(backoff)
+    .build()
+    .await
+    .map_err(|err| err.to_string())?;
+  let topics = client.list_topics().await.map_err(|err| err.to_string())?;
+  let partitions = topics
+    .into_iter()
+    .find(|topic| topic.name == config.topic)
+    .map(|topic| topic.partitions)
+    .filter(|partitions| !partitions.is_empty())
+    .ok_or_else(|| format!("topic {} does not exist", config.topic))?;
+  let idx = (murmur2(machine_id.as_bytes()) & 0x7fff_ffff) as usize % partitions.len();
+  let partition = partitions.into_iter().nth(idx).unwrap_or_default();
+  client
+    .partition_client(config.topic.as_str(), partition, UnknownTopicHandling::Error)
+    .await
+    .map_err(|err| err.to_string())
+}
+
+fn record(config: &KafkaConfig, machine_id: &str, point: &TelemetryPoint) -> Record {
+  let value = match config.format {
+    KafkaFormat::Json => serde_json::to_vec(point).unwrap_or_default(),
+    KafkaFormat::Avro => encode_avro(point),
+  };
+  Record {
+    key: Some(machine_id.as_bytes().to_vec()),
+    value: Some(value),
+    headers: BTreeMap::new(),
+    timestamp: DateTime::parse_from_rfc3339(&point.ts).map_or_else(|_| Utc::now(), |ts| ts.with_timezone(&Utc)),
+  }
+}
+
+/// Kafka's murmur2 variant, so a machine lands on the same partition as with the Java client's default partitioner.
+fn murmur2(data: &[u8]) -> u32 {
+  const M: u32 = 0x5bd1_e995;
+  let mut h = 0x9747_b28c ^ data.len() as u32;
+  let chunks = data.chunks_exact(4);
+  let tail = chunks.remainder();
+  for chunk in chunks {
+    let mut k = u32::from_le_bytes([chunk[0], chunk[1], chunk[2], chunk[3]]);
+    k = k.wrapping_mul(M);
+    k ^= k >> 24;
+    k = k.wrapping_mul(M);
+    h = h.wrapping_mul(M) ^ k;
+  }
+  if tail.len() >= 3 {
+    h ^= (tail[2] as u32) << 16;
+  }
+  if tail.len() >= 2 {
+    h ^= (tail[1] as u32) << 8;
+  }
+  if !tail.is_empty() {
+    h ^= tail[0] as u32;
+    h = h.wrapping_mul(M);
+  }
+  h ^= h >> 13;
+  h = h.wrapping_mul(M);
+  h ^ (h >> 15)
+}
+
+/// Schema of the `avro` payload, in Parsing Canonical Form (which is what the fingerprint is taken over).
+pub(crate) const AVRO_SCHEMA: &str = concat!(
+  r#"{"name":"simcorp.roaster.TelemetryPoint","type":"record","fields":["#,
+  r#"{"name":"ts","type":"string"},{"name":"machineId","type":"string"},{"name":"elapsedSeconds","type":"double"},"#,
+  r#"{"name":"btC","type":["null","double"]},{"name":"etC","type":["null","double"]},"#,
+  r#"{"name":"gasPct","type":["null","double"]},{"name":"fanPct","type":["null","double"]},"#,
+  r#"{"name":"drumRpm","type":["null","double"]},"#,
+  r#"{"name":"extras","type":["null",{"type":"array","items":{"name":"simcorp.roaster.ExtraEntry","type":"record","#,
+  r#""fields":[{"name":"key","type":"string"},{"name":"numberValue","type":["null","double"]},"#,
+  r#"{"name":"textValue","type":["null","string"]},{"name":"boolValue","type":["null","boolean"]}]}}]},"#,
+  r#"{"name":"sessionId","type":["null","string"]},{"name":"ambientC","type":["null","double"]},"#,
+  r#"{"name":"humidityPct","type":["null","double"]},{"name":"pressureHpa","type":["null","double"]},"#,
+  r#"{"name":"exhaustC","type":["null","double"]},{"name":"inletC","type":["null","double"]},"#,
+  r#"{"name":"drumPressurePa","type":["null","double"]},{"name":"airflowPct","type":["null","double"]},"#,
+  r#"{"name":"probes","type":["null",{"type":"array","items":{"name":"simcorp.roaster.ProbeReading","type":"record","#,
+  r#""fields":[{"name":"name","type":"string"},{"name":"channel","type":"string"},"#,
+  r#"{"name":"value","type":"double"}]}}]},{"name":"energyKwh","type":["null","double"]}]}"#
+);
+
+fn encode_avro(point: &TelemetryPoint) -> Vec<u8> {
+  let mut out = vec![0xc3, 0x01];
+  out.extend_from_slice(&avro_fingerprint(AVRO_SCHEMA.as_bytes()).to_le_bytes());
+  avro_string(&mut out, &point.ts);
+  avro_string(&mut out, &point.machineId);
+  out.extend_from_slice(&point.elapsedSeconds.to_le_bytes());
+  for value in [point.btC, point.etC, point.power(), point.fanPct, point.drumRpm] {
+    avro_optional_double(&mut out, value);
+  }
+  match point.extras.as_deref() {
+    Some(extras) => {
+      avro_long(&mut out, 1);
+      if !extras.is_empty() {
+        avro_long(&mut out, extras.len() as i64);
+        for extra in extras {
+          avro_string(&mut out, &extra.key);
+          avro_optional_double(&mut out, extra.number_value);
+          match &extra.text_value {
+            Some(text) => {
+              avro_long(&mut out, 1);
+              avro_string(&mut out, text);
+            }
+            None => avro_long(&mut out, 0),
+          }
+          match extra.bool_value {
+            Some(flag) => {
+              avro_long(&mut out, 1);
+              out.push(flag as u8);
+            }
+            None => avro_long(&mut out, 0),
+          }
+        }
+      }
+      avro_long(&mut out, 0);
+    }
+    None => avro_long(&mut out, 0),
+  }
+  match &point.sessionId {
+    Some(session_id) => {
+      avro_long(&mut out, 1);
+      avro_string(&mut out, session_id);
+    }
+    None => avro_long(&mut out, 0),
+  }
+  let channels = [
+    point.ambientC,
+    point.humidityPct,
+    point.pressureHpa,
+    point.exhaustC,
+    point.inletC,
+    point.drumPressurePa,
+    point.airflowPct,
+  ];
+  for value in channels {
+    avro_optional_double(&mut out, value);
+  }
+  match point.probes.as_deref() {
+    Some(probes) => {
+      avro_long(&mut out, 1);
+      if !probes.is_empty() {
+        avro_long(&mut out, probes.len() as i64);
+        for probe in probes {
+          avro_string(&mut out, &probe.name);
+          avro_string(&mut out, &probe.channel);
+          out.extend_from_slice(&probe.value.to_le_bytes());
+        }
+      }
+      avro_long(&mut out, 0);
+    }
+    None => avro_long(&mut out, 0),
+  }
+  avro_optional_double(&mut out, point.energyKwh);
+  out
+}
+
+/// Zig-zag varint.
+fn avro_long(out: &mut Vec<u8>, value: i64) {
+  let mut n = ((value << 1) ^ (value >> 63)) as u64;
+  while n >= 0x80 {
+    out.push((n as u8 & 0x7f) | 0x80);
+    n >>= 7;
+  }
+  out.push(n as u8);
+}
+
+fn avro_string(out: &mut Vec<u8>, value: &str) {
+  avro_long(out, value.len() as i64);
+  out.extend_from_slice(value.as_bytes());
+}
+
+fn avro_optional_double(out: &mut Vec<u8>, value: Option<f64>) {
+  match value {
+    Some(value) => {
+      avro_long(out, 1);
+      out.extend_from_slice(&value.to_le_bytes());
+    }
+    None => avro_long(out, 0),
+  }
+}
+
+/// CRC-64-AVRO (Rabin) fingerprint, as used by single-object encoding.
+fn avro_fingerprint(data: &[u8]) -> u64 {
+  const EMPTY: u64 = 0xc15d_213a_a4d7_a795;
+  let mut fp = EMPTY;
+  for byte in data {
+    let mut entry = (fp ^ *byte as u64) & 0xff;
+    for _ in 0..8 {
+      entry = (entry >> 1) ^ (EMPTY & (entry & 1).wrapping_neg());
+    }
+    fp = (fp >> 8) ^ entry;
+  }
+  fp
+}
+
+#[cfg(test)]
+mod tests {
+  use apache_avro::types::Value as AvroValue;
+  use apache_avro::Schema;
+  use serde_json::json;
+
+  use super::*;
+
+  /// Checks the single-object header and decodes the datum with `AVRO_SCHEMA`, returning its fields by name.
+  fn decode(payload: &[u8]) -> BTreeMap<String, AvroValue> {
+    let schema = Schema::parse_str(AVRO_SCHEMA).expect("schema");
+    assert_eq!(schema.canonical_form(), AVRO_SCHEMA);
+    assert_eq!(payload[..2], [0xc3, 0x01]);
+    assert_eq!(payload[2..10], avro_fingerprint(AVRO_SCHEMA.as_bytes()).to_le_bytes());
+    let mut datum = &payload[10..];
+    let AvroValue::Record(fields) = apache_avro::from_avro_datum(&schema, &mut datum, None).expect("datum") else {
+      panic!("not a record");
+    };
+    assert!(datum.is_empty(), "trailing bytes");
+    fields.into_iter().collect()
+  }
+
+  #[tokio::test]
+  async fn retries_a_batch_until_the_delivery_timeout() {
+    // A "broker" that hangs up on every connection.
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.expect("bind");
+    let address = listener.local_addr().expect("address");
+    tokio::spawn(async move {
+      while let Ok((socket, _)) = listener.accept().await {
+        drop(socket);
+      }
+    });
+    let config: KafkaConfig =
+      serde_json::from_value(json!({ "brokers": [address.to_string()], "topic": "t", "deliveryTimeoutMs": 300 }))
+        .expect("config");
+    let records = vec![record(&config, "m1", &TelemetryPoint::test(json!({}))); 3];
+    let stats = SinkStats::default();
+    let started = Instant::now();
+    deliver(&config, "m1", &mut None, records, &stats).await;
+    assert!(started.elapsed() >= Duration::from_millis(300), "dropped after {:?}", started.elapsed());
+    assert_eq!((stats.written.load(Ordering::Relaxed), stats.errors.load(Ordering::Relaxed)), (0, 3));
+  }
+
+  fn double(value: f64) -> AvroValue {
+    AvroValue::Union(1, Box::new(AvroValue::Double(value)))
+  }
+
+  #[test]
+  fn encodes_the_ambient_channels() {
+    let fields = decode(&encode_avro(&TelemetryPoint::test(json!({ "ambientC": 21.5, "pressureHpa": 1013.2 }))));
+    assert_eq!(fields["ambientC"], double(21.5));
+    assert_eq!(fields["humidityPct"], AvroValue::Union(0, Box::new(AvroValue::Null)));
+    assert_eq!(fields["pressureHpa"], double(1013.2));
+  }
+
+  #[test]
+  fn encodes_the_registered_channels() {
+    let point = TelemetryPoint::test(json!({ "exhaustC": 180.0, "inletC": 250.5, "drumPressurePa": -12.0 }));
+    let fields = decode(&encode_avro(&point));
+    assert_eq!(fields["exhaustC"], double(180.0));
+    assert_eq!(fields["inletC"], double(250.5));
+    assert_eq!(fields["drumPressurePa"], double(-12.0));
+    assert_eq!(fields["airflowPct"], AvroValue::Union(0, Box::new(AvroValue::Null)));
+  }
+
+  #[test]
+  fn encodes_the_energy_used() {
+    let fields = decode(&encode_avro(&TelemetryPoint::test(json!({ "energyKwh": 0.25 }))));
+    assert_eq!(fields["energyKwh"], double(0.25));
+  }
+
+  #[test]
+  fn encodes_the_probes() {
+    let probes = json!([
+      { "name": "bt1", "channel": "btC", "value": 200.5 },
+      { "name": "bt2", "channel": "btC", "value": 199.0 },
+    ]);
+    let fields = decode(&encode_avro(&TelemetryPoint::test(json!({ "probes": probes }))));
+    let reading = |name: &str, value: f64| {
+      AvroValue::Record(vec![
+        ("name".to_string(), AvroValue::String(name.to_string())),
+        ("channel".to_string(), AvroValue::String("btC".to_string())),
+        ("value".to_string(), AvroValue::Double(value)),
+      ])
+    };
+    let expected = AvroValue::Array(vec![reading("bt1", 200.5), reading("bt2", 199.0)]);
+    assert_eq!(fields["probes"], AvroValue::Union(1, Box::new(expected)));
+    let fields = decode(&encode_avro(&TelemetryPoint::test(json!({}))));
+    assert_eq!(fields["probes"], AvroValue::Union(0, Box::new(AvroValue::Null)));
+  }
+}
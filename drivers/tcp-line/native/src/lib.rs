@@ -1,340 +1,1026 @@
-use std::sync::atomic::{AtomicBool, Ordering};
+#![allow(non_snake_case)]
+
+mod aggregate;
+mod alarms;
+mod benchmark;
+mod ble;
+mod bundle;
+#[cfg(target_os = "linux")]
+mod can;
+// The CAN signal layout builds everywhere, so its decoding is tested on every platform.
+#[cfg_attr(not(target_os = "linux"), allow(dead_code))]
+mod can_signal;
+mod cancel;
+mod channels;
+mod clock;
+mod commands;
+mod composite;
+mod connection_history;
+mod discovery;
+mod downsample;
+mod energy;
+mod events;
+mod faults;
+mod framing;
+mod fuzz;
+mod grpc;
+mod handshake;
+mod http_client;
+mod http_poll;
+mod influx;
+mod kafka;
+mod listen;
+mod logging;
+mod manager;
+mod metrics_server;
+mod opcua;
+mod ordering;
+mod phases;
+mod probe;
+mod proxy;
+mod queue;
+mod raw_tap;
+mod recent_errors;
+mod recorder;
+mod registry;
+mod replay;
+mod resample;
+mod roast_events;
+mod roast_import;
+mod runtime;
+mod self_test;
+mod sequence;
+mod session;
+mod session_log;
+mod simulator;
+mod snmp;
+mod spool;
+mod stats;
+mod stream;
+mod subscription;
+mod test_server;
+mod validation;
+
+// napi only registers free functions outside `cfg(test)`; re-exporting keeps them reachable in test builds.
+pub use crate::benchmark::benchmark_parser;
+pub use crate::cancel::CancelToken;
+pub use crate::discovery::discover_devices;
+pub use crate::fuzz::parse_line_for_fuzzing;
+pub use crate::logging::set_log_callback;
+pub use crate::probe::probe_endpoint;
+pub use crate::registry::list_active_drivers;
+pub use crate::roast_import::import_roast_log;
+pub use crate::runtime::init_runtime;
+pub use crate::self_test::self_test;
+pub use crate::validation::{config_schema, validate_config};
+
+use std::collections::BTreeMap;
+use std::future::Future;
+use std::sync::atomic::{AtomicBool, AtomicU64, AtomicUsize, Ordering};
 use std::sync::Arc;
-use std::time::Duration;
+use std::time::{Duration, Instant};
 
 use chrono::{DateTime, SecondsFormat, Utc};
 use napi::bindgen_prelude::*;
 use napi_derive::napi;
-use parking_lot::Mutex;
-use serde::Deserialize;
-use thiserror::Error;
-use tokio::io::{AsyncBufReadExt, BufReader};
+use parking_lot::{Mutex, RwLock};
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use tokio::io::AsyncRead;
 use tokio::net::TcpStream;
 use tokio::task::JoinHandle;
 use tokio::time::sleep;
-
-const RESERVED_KEYS: &[&str] = &["ts", "btC", "etC", "powerPct", "fanPct", "drumRpm"];
-
-#[derive(Debug, Clone, Deserialize)]
+use tracing::{debug, error, info, warn};
+
+use tcp_line_core::{assembly, checksum, csv_record, detect, rng, timestamp, xml};
+use tcp_line_core::{
+  parse_timestamp, Backoff, ChannelRegistry, CsvConfig, DriverMetrics, DriverState, EnvironmentConfig, ExtraEntry,
+  ExtrasConfig, FieldError, FrameFormat, JsonConfig, Offsets, ParseError, ParserConfig, ProbeReading, ProbesConfig,
+  Quality, RawTelemetrySample, ReconnectConfig, RegexConfig, Strictness, TcpLineParser, CHANNELS,
+};
+
+use crate::aggregate::{SampleHistory, TelemetryAggregate};
+use crate::alarms::{AlarmEvent, AlarmRule, AlarmRules, StaleWatch, StaleWatchdog, Staleness};
+use crate::assembly::AssemblyConfig;
+use crate::bundle::{CaptureConfig, SessionBundleSummary, SessionCapture};
+use crate::cancel::Cancel;
+use crate::channels::{ChangeFilter, ChannelConfig, GapFiller, PowerField};
+use crate::checksum::Checksum;
+use crate::clock::{ClockConfig, DeviceClock, ElapsedBaseline, ElapsedClock};
+use crate::commands::{CommandChannel, CommandConfig, Heartbeat, HeartbeatConfig, Setpoint};
+use crate::connection_history::{ConnectionEvent, ConnectionEventKind, ConnectionHistory};
+use crate::detect::{DetectedFormat, FormatDetection};
+use crate::downsample::{DownsampleConfig, Downsampler};
+use crate::energy::{EnergyConfig, EnergyMeter};
+use crate::events::{DriverError, DriverErrorCode, StateChangeEvent, Subscribers};
+use crate::framing::{Encoding, FrameReader, Framing, ReadFrame};
+use crate::grpc::{GrpcConfig, GrpcServer};
+use crate::handshake::HandshakeStep;
+use crate::influx::{InfluxConfig, InfluxSink, SinkStats};
+use crate::kafka::{KafkaConfig, KafkaSink};
+use crate::listen::{ConnectionMode, Peer, PeerStatus};
+use crate::ordering::{OrderingConfig, SampleOrder};
+use crate::phases::{PhaseProgress, PhaseTracker, SessionSummary};
+use crate::proxy::ProxyConfig;
+use crate::queue::{QueueConfig, QueuePolicy, SampleQueue};
+use crate::raw_tap::{Outcome, RawLineTap};
+use crate::recent_errors::{RecentErrors, RecentParseError};
+use crate::recorder::{LineRecorder, RecordConfig};
+use crate::resample::{GridTicker, ResampleConfig, Resampler};
+use crate::roast_events::{RoastEvent, RoastEventConfig, RoastEventDetector};
+use crate::sequence::{SequenceConfig, SequenceGapEvent, SequenceTracker, Step};
+use crate::session::SessionInfo;
+use crate::session_log::{LogFormat, SessionLog, SessionLogSummary};
+use crate::spool::{SampleSpool, SpoolConfig};
+use crate::stats::{DetailedMetrics, DriverStats, SampleRate};
+use crate::stream::TelemetryStream;
+use crate::subscription::TelemetrySubscription;
+use crate::timestamp::TimestampConfig;
+use crate::xml::XmlConfig;
+
+/// Points a gRPC stream or `subscribe()` handle may lag behind before it skips ahead.
+const POINT_TAP_CAPACITY: usize = 1024;
+
+#[derive(Debug, Clone, PartialEq, Deserialize, JsonSchema)]
 #[serde(rename_all = "camelCase")]
 struct TcpLineDriverConfig {
+  /// Whether the driver dials the device or the device dials the driver; in `listen` mode `host:port` is bound.
+  #[serde(default)]
+  mode: ConnectionMode,
   host: String,
   port: u16,
+  /// Devices served at once in `listen` mode; further connections are closed on accept.
+  #[serde(default = "default_max_connections")]
+  max_connections: u32,
+  /// Backups for `host:port`, tried in turn when the current endpoint can't be reached.
+  #[serde(default)]
+  endpoints: Vec<Endpoint>,
+  /// Which endpoint the reconnect loop moves to; only applies with `reconnect.enabled`.
+  #[serde(default)]
+  failover: FailoverPolicy,
+  /// What `connect()` does when another driver in the process is already connected to `host:port`.
+  #[serde(default)]
+  on_duplicate: DuplicatePolicy,
+  /// How frames are cut from the byte stream; `format` then parses each frame.
+  #[serde(default)]
+  framing: Framing,
+  /// Terminator of `newline` frames, e.g. `\r` or `;` for devices that never send `\n`.
+  #[serde(default = "default_line_delimiter")]
+  line_delimiter: String,
+  /// Longest frame accepted; longer ones are discarded as parse errors instead of growing the buffer.
+  #[serde(default = "default_max_line_bytes")]
+  max_line_bytes: u32,
+  /// Inbound limit per connection, in bytes per second; reads wait while a device sends faster, e.g. when it floods
+  /// the line with debug output.
+  #[serde(default)]
+  max_bytes_per_second: Option<u64>,
+  /// How frame bytes become text.
+  #[serde(default)]
+  encoding: Encoding,
   format: FrameFormat,
   csv: CsvConfig,
+  /// Required when `format` is `regex`.
+  #[serde(default)]
+  regex: Option<RegexConfig>,
+  /// Required when `format` is `xml`.
+  #[serde(default)]
+  xml: Option<XmlConfig>,
+  /// How `jsonl` lines with nested objects are read.
+  #[serde(default)]
+  json: JsonConfig,
+  /// Trailer each line must carry; it is verified and stripped before parsing.
+  #[serde(default)]
+  checksum: Checksum,
+  /// Joins several lines into one record before parsing, for devices that split a reading across lines.
+  #[serde(default)]
+  assembly: Option<AssemblyConfig>,
+  /// Sample pipeline settings shared with every other driver.
+  #[serde(flatten)]
+  pipeline: PipelineConfig,
+  /// Emits points on a fixed wall-clock grid instead; `drainSamples` still gets every sample. TCP driver only.
+  #[serde(default)]
+  resample: Option<ResampleConfig>,
+  /// What `readTelemetry()` does while no sample has arrived yet.
+  #[serde(default)]
+  read_wait: ReadWaitConfig,
+  /// Whether `readTelemetry()` returns each sample once or the latest one every time.
+  #[serde(default)]
+  read_mode: ReadMode,
+  reconnect: ReconnectConfig,
+  #[serde(default)]
+  record: Option<RecordConfig>,
+  /// Keeps the raw lines, emitted points and a metrics timeline in memory for `exportSession`. TCP driver only.
+  #[serde(default)]
+  capture: Option<CaptureConfig>,
+  /// Appends every emitted point to disk for `drainPersisted`, so samples survive a process restart.
+  #[serde(default)]
+  spool: Option<SpoolConfig>,
+  /// Writes every emitted point to InfluxDB from a background task.
+  #[serde(default)]
+  influx: Option<InfluxConfig>,
+  /// Produces every emitted point to a Kafka topic from a background task, keyed by machine id.
+  #[serde(default)]
+  kafka: Option<KafkaConfig>,
+  /// Serves this driver's telemetry and status over gRPC, starting with the first `connect()`.
+  #[serde(default)]
+  grpc: Option<GrpcConfig>,
+  #[serde(default)]
+  commands: CommandConfig,
+  #[serde(default)]
+  keepalive: KeepaliveConfig,
+  /// Treat this long without a complete line as a dead connection and reconnect. `None` waits forever.
+  #[serde(default)]
+  read_timeout_ms: Option<u64>,
+  /// Raises the `staleData` alarm when a connected device goes this long without a valid sample, e.g. a probe that
+  /// lost its reading while the line keeps up heartbeats. TCP driver only.
+  #[serde(default)]
+  stale_data_alarm_ms: Option<u64>,
+  /// Threshold rules checked against every accepted sample, raising and clearing through `onAlarm`. TCP driver only.
+  #[serde(default)]
+  alarms: Vec<AlarmRule>,
+  /// Integrates `powerPct` over time into `energyKwh` on points, counted per session. TCP driver only.
+  #[serde(default)]
+  energy: Option<EnergyConfig>,
+  /// Bound on each TCP connect attempt; `0` leaves it to the OS (which can take minutes against a dead host).
+  #[serde(default = "default_connect_timeout_ms")]
+  connect_timeout_ms: u64,
+  /// How long `disconnect()` gives the loop to finish the line it is reading and the sinks to write out what they
+  /// buffered before aborting; `0` aborts the loop at once and doesn't wait for the sinks.
+  #[serde(default = "default_shutdown_timeout_ms")]
+  shutdown_timeout_ms: u64,
+  /// Reaches every endpoint through this SOCKS5 or HTTP CONNECT proxy; `connectTimeoutMs` covers the handshake.
+  #[serde(default)]
+  proxy: Option<ProxyConfig>,
+  /// Send/expect steps run after connecting, before the driver counts as CONNECTED, e.g. a gateway login.
+  #[serde(default)]
+  handshake: Vec<HandshakeStep>,
+  #[serde(default)]
+  heartbeat: Option<HeartbeatConfig>,
+  /// How `ts` is encoded; lines without one are stamped with the receive time.
+  #[serde(default)]
+  timestamp: TimestampConfig,
+  /// Where sample timestamps come from when the device clock can't be trusted, and the clock `elapsedSeconds` is
+  /// counted on.
+  #[serde(default)]
+  clock: ClockConfig,
+  /// Follows the device's frame counter, counting gaps, duplicates and late frames; gaps are reported through
+  /// `onGap`. TCP driver only.
+  #[serde(default)]
+  sequence: Option<SequenceConfig>,
+  /// What happens to samples timestamped before the latest one; each is counted in `outOfOrderSamples`. TCP driver
+  /// only.
+  #[serde(default)]
+  ordering: OrderingConfig,
+  /// Runs the connection loop and its background tasks on the runtime `initRuntime` registered under this name.
+  #[serde(default)]
+  runtime: Option<String>,
+}
+
+/// How samples become points, whatever the source: every driver config flattens this in, so a pipeline option added
+/// here reaches them all. The reading settings (`offsets` to `strictness`) only apply to sources that parse readings,
+/// which the simulator and composites don't; they reject them.
+#[derive(Debug, Clone, PartialEq, Deserialize, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+struct PipelineConfig {
   emit_interval_ms: u64,
+  /// Consolidates the samples of each `emitIntervalMs` into one point instead of passing every sample through.
+  #[serde(default)]
+  downsample: Option<DownsampleConfig>,
   dedupe_within_ms: u64,
+  /// What counts as a duplicate within `dedupeWithinMs`.
+  #[serde(default)]
+  dedupe_strategy: DedupeStrategy,
+  /// Per-channel emission settings, keyed by channel (`btC`, `etC`, `powerPct`, `ambientC`, ...) or extra name.
+  #[serde(default, deserialize_with = "crate::channels::by_channel")]
+  channels: BTreeMap<String, ChannelConfig>,
+  /// The point field `powerPct` readings go out under; `gasPct` by default.
+  #[serde(default)]
+  power_field: PowerField,
+  #[serde(default)]
   offsets: Offsets,
-  reconnect: ReconnectConfig,
+  /// Several calibrated probes per channel (`bt1`/`bt2`), reported in `probes` and combined into `btC`/`etC`.
+  #[serde(default)]
+  probes: ProbesConfig,
+  /// Units the device sends the ambient channels (`ambientC`, `humidityPct`, `pressureHpa`) in.
+  #[serde(default)]
+  environment: EnvironmentConfig,
+  /// Device keys promoted to registered channels (`exhaustC`, `inletC`, `drumPressurePa`, `airflowPct`) on points.
+  #[serde(default)]
+  channel_registry: ChannelRegistry,
+  /// Which non-channel keys become extras, and under what name.
+  #[serde(default)]
+  extras: ExtrasConfig,
+  /// What becomes of a reading whose channel field isn't a number: dropped from the sample (`lenient`), dropped and
+  /// counted in `fieldErrors` (`warn`), or the reading rejected (`strict`).
+  #[serde(default)]
+  strictness: Strictness,
+  /// Enables CHARGE / TURNING_POINT / DRY_END / FC detection, reported through `onEvent`.
+  #[serde(default)]
+  roast_events: Option<RoastEventConfig>,
+  /// Buffers every sample for `drainSamples`, for consumers that can't afford to miss one between polls.
+  #[serde(default)]
+  queue: Option<QueueConfig>,
 }
 
-#[derive(Debug, Clone, Deserialize)]
-#[serde(rename_all = "camelCase")]
-enum FrameFormat {
-  #[serde(rename = "jsonl")]
-  Jsonl,
-  #[serde(rename = "csv")]
-  Csv,
+impl PipelineConfig {
+  fn new(emit_interval_ms: u64, dedupe_within_ms: u64) -> Self {
+    Self {
+      emit_interval_ms,
+      downsample: None,
+      dedupe_within_ms,
+      dedupe_strategy: DedupeStrategy::default(),
+      channels: BTreeMap::new(),
+      power_field: PowerField::default(),
+      offsets: Offsets::default(),
+      probes: ProbesConfig::default(),
+      environment: EnvironmentConfig::default(),
+      channel_registry: ChannelRegistry::new(),
+      extras: ExtrasConfig::default(),
+      strictness: Strictness::default(),
+      roast_events: None,
+      queue: None,
+    }
+  }
+
+  /// The first reading setting changed from its default, for the sources that don't parse readings to reject.
+  fn reading_setting(&self) -> Option<&'static str> {
+    let settings = [
+      ("offsets", self.offsets != Offsets::default()),
+      ("probes", self.probes != ProbesConfig::default()),
+      ("environment", self.environment != EnvironmentConfig::default()),
+      ("channelRegistry", self.channel_registry != ChannelRegistry::new()),
+      ("extras", self.extras != ExtrasConfig::default()),
+      ("strictness", self.strictness != Strictness::default()),
+    ];
+    settings.into_iter().find(|(_, set)| *set).map(|(name, _)| name)
+  }
 }
 
-#[derive(Debug, Clone, Deserialize)]
-#[serde(rename_all = "camelCase")]
-struct CsvConfig {
-  has_header: bool,
-  columns: Vec<String>,
-  delimiter: String,
+fn default_max_connections() -> u32 {
+  1
 }
 
-#[derive(Debug, Clone, Deserialize)]
-#[serde(rename_all = "camelCase")]
-struct Offsets {
-  bt_c: f64,
-  et_c: f64,
+fn default_connect_timeout_ms() -> u64 {
+  5000
 }
 
-#[derive(Debug, Clone, Deserialize)]
-#[serde(rename_all = "camelCase")]
-struct ReconnectConfig {
-  enabled: bool,
-  min_backoff_ms: u64,
-  max_backoff_ms: u64,
+fn default_shutdown_timeout_ms() -> u64 {
+  2000
 }
 
-#[derive(Debug, Clone)]
-struct Backoff {
-  current: u64,
-  min: u64,
-  max: u64,
+fn default_line_delimiter() -> String {
+  "\n".to_string()
+}
+
+fn default_max_line_bytes() -> u32 {
+  64 * 1024
 }
 
-impl Backoff {
-  fn new(min: u64, max: u64) -> Self {
-    Self { current: min, min, max }
+impl TcpLineDriverConfig {
+  /// Settings for sources that don't read from a socket (replay, simulator, ...): their `pipeline`, with `format` and
+  /// `csv` for the parser.
+  fn for_source(format: FrameFormat, csv: CsvConfig, pipeline: PipelineConfig) -> Self {
+    Self {
+      mode: ConnectionMode::Connect,
+      host: String::new(),
+      port: 0,
+      max_connections: default_max_connections(),
+      endpoints: Vec::new(),
+      failover: FailoverPolicy::default(),
+      on_duplicate: DuplicatePolicy::default(),
+      framing: Framing::Newline,
+      line_delimiter: default_line_delimiter(),
+      max_line_bytes: default_max_line_bytes(),
+      max_bytes_per_second: None,
+      encoding: Encoding::Utf8,
+      format,
+      csv,
+      regex: None,
+      xml: None,
+      json: JsonConfig::default(),
+      checksum: Checksum::None,
+      assembly: None,
+      pipeline,
+      read_wait: ReadWaitConfig::default(),
+      read_mode: ReadMode::default(),
+      resample: None,
+      reconnect: ReconnectConfig::disabled(),
+      record: None,
+      capture: None,
+      spool: None,
+      influx: None,
+      kafka: None,
+      grpc: None,
+      commands: CommandConfig::default(),
+      keepalive: KeepaliveConfig::default(),
+      read_timeout_ms: None,
+      stale_data_alarm_ms: None,
+      alarms: Vec::new(),
+      energy: None,
+      connect_timeout_ms: 0,
+      shutdown_timeout_ms: 0,
+      proxy: None,
+      handshake: Vec::new(),
+      heartbeat: None,
+      timestamp: TimestampConfig::default(),
+      clock: ClockConfig::default(),
+      sequence: None,
+      ordering: OrderingConfig::default(),
+      runtime: None,
+    }
+  }
+
+  /// The parsing settings, for a `TcpLineParser`.
+  fn parser(&self) -> ParserConfig {
+    ParserConfig {
+      format: self.format.clone(),
+      csv: self.csv.clone(),
+      regex: self.regex.clone(),
+      xml: self.xml.clone(),
+      json: self.json.clone(),
+      checksum: self.checksum,
+      assembly: self.assembly.clone(),
+      offsets: self.pipeline.offsets.clone(),
+      probes: self.pipeline.probes.clone(),
+      environment: self.pipeline.environment,
+      channel_registry: self.pipeline.channel_registry.clone(),
+      extras: self.pipeline.extras.clone(),
+      timestamp: self.timestamp.clone(),
+      strictness: self.pipeline.strictness,
+    }
   }
 
-  fn next(&mut self) -> u64 {
-    let value = self.current;
-    self.current = self.current.saturating_mul(2).clamp(self.min, self.max);
-    value
+  /// `host:port` of a connect-mode TCP driver, the key two drivers reading one device share.
+  fn endpoint_key(&self) -> Option<String> {
+    let connects = self.mode == ConnectionMode::Connect && !self.host.is_empty();
+    connects.then(|| format!("{}:{}", self.host.to_ascii_lowercase(), self.port))
   }
 
-  fn reset(&mut self) {
-    self.current = self.min;
+  /// `host:port` followed by the backup `endpoints`.
+  fn all_endpoints(&self) -> Vec<Endpoint> {
+    let primary = Endpoint { host: self.host.clone(), port: self.port };
+    std::iter::once(primary).chain(self.endpoints.iter().cloned()).collect()
   }
 }
 
-#[derive(Debug, Clone)]
-struct RawTelemetrySample {
-  ts: DateTime<Utc>,
-  bt_c: Option<f64>,
-  et_c: Option<f64>,
-  power_pct: Option<f64>,
-  fan_pct: Option<f64>,
-  drum_rpm: Option<f64>,
-  extras: Option<Vec<ExtraEntry>>,
+#[derive(Debug, Clone, PartialEq, Deserialize, JsonSchema)]
+struct Endpoint {
+  host: String,
+  port: u16,
 }
 
-#[derive(Debug, Clone, Copy)]
-#[napi(string_enum)]
-enum DriverState {
-  DISCONNECTED,
-  CONNECTING,
-  CONNECTED,
-  STOPPED,
+impl std::fmt::Display for Endpoint {
+  fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+    write!(f, "{}:{}", self.host, self.port)
+  }
 }
 
-#[derive(Debug, Clone, Default)]
-#[napi(object)]
-struct DriverMetrics {
-  pub linesReceived: u64,
-  pub linesParsed: u64,
-  pub parseErrors: u64,
-  pub telemetryEmitted: u64,
-  pub reconnects: u64,
-  pub lastError: Option<String>,
-  pub lastLineAt: Option<String>,
+#[derive(Debug, Clone, Copy, Default, PartialEq, Deserialize, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+enum DuplicatePolicy {
+  /// Connect anyway, logging a warning.
+  #[default]
+  Allow,
+  /// Fail `connect()` with a `duplicate connection` error.
+  Error,
+  /// A driver constructed while another for `host:port` exists becomes a second handle on it, sharing its connection
+  /// and samples instead of opening its own; the constructor fails if its machine id or parsing settings differ.
+  Share,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Deserialize, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+enum ReadWaitMode {
+  /// Reject with `no telemetry yet` once `timeoutMs` has passed.
+  #[default]
+  Fail,
+  /// Wait for as long as it takes, until the sample arrives or the driver stops.
+  WaitForever,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Deserialize, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+enum ReadMode {
+  /// Return the latest sample, again on every call until a newer one arrives.
+  #[default]
+  Latest,
+  /// Return each sample once: a call after the latest was returned waits for the next, per `readWait`.
+  Consume,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Deserialize, JsonSchema)]
+#[serde(rename_all = "camelCase", default)]
+struct ReadWaitConfig {
+  mode: ReadWaitMode,
+  /// Defaults to twice the emit (or resample) interval, at least 500 ms.
+  timeout_ms: Option<u64>,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Deserialize, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+enum FailoverPolicy {
+  /// Return to `host:port` after every drop, falling back down the list only while it is unreachable.
+  #[default]
+  Ordered,
+  /// Move on to the next endpoint after every drop or failed attempt, spreading reconnects across the pair.
+  RoundRobin,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Deserialize, JsonSchema)]
+#[serde(rename_all = "camelCase", default)]
+struct KeepaliveConfig {
+  enabled: bool,
+  /// Idle time before the first probe; the OS default applies when unset.
+  idle_ms: Option<u64>,
+  interval_ms: Option<u64>,
+  retries: Option<u32>,
+}
+
+impl KeepaliveConfig {
+  fn apply(&self, stream: &TcpStream) -> std::io::Result<()> {
+    if !self.enabled {
+      return Ok(());
+    }
+    let mut keepalive = socket2::TcpKeepalive::new();
+    if let Some(idle_ms) = self.idle_ms {
+      keepalive = keepalive.with_time(Duration::from_millis(idle_ms));
+    }
+    #[cfg(any(target_os = "linux", target_os = "macos", windows))]
+    if let Some(interval_ms) = self.interval_ms {
+      keepalive = keepalive.with_interval(Duration::from_millis(interval_ms));
+    }
+    #[cfg(any(target_os = "linux", target_os = "macos"))]
+    if let Some(retries) = self.retries {
+      keepalive = keepalive.with_retries(retries);
+    }
+    socket2::SockRef::from(stream).set_tcp_keepalive(&keepalive)
+  }
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Deserialize, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+enum DedupeStrategy {
+  /// Any sample within `dedupeWithinMs` of the previous one.
+  #[default]
+  Timestamp,
+  /// Only samples within `dedupeWithinMs` whose channel and extra values all match the previous sample, for devices
+  /// that timestamp coarsely and would otherwise lose real changes.
+  IdenticalValues,
+  /// Keep every sample.
+  Off,
 }
 
 #[derive(Debug, Clone)]
 #[napi(object)]
-struct DriverStatus {
+pub struct DriverStatus {
   pub state: DriverState,
   pub metrics: DriverMetrics,
+  /// Device clock minus gateway clock in milliseconds, estimated from the first samples after connecting.
+  pub clockSkewMs: Option<f64>,
+  /// The session started with `startSession`, if any.
+  pub session: Option<SessionInfo>,
+  /// `host:port` of the endpoint connected to, or being tried while reconnecting.
+  pub activeEndpoint: Option<String>,
+  /// Devices connected in `listen` mode; always empty in `connect` mode.
+  pub peers: Vec<PeerStatus>,
+  /// What `format: "autodetect"` settled on (in listen mode, for the first device); `null` until it has decided.
+  pub detectedFormat: Option<FormatDetection>,
+  /// Estimated sample rate, jitter and longest gap over the last 60 seconds of accepted samples.
+  pub sampleRate: Option<SampleRate>,
+  /// Set while the `staleDataAlarmMs` alarm is raised.
+  pub staleDataAlarm: bool,
+  /// The raising event of each alarm currently raised, `staleData` included, oldest first.
+  pub activeAlarms: Vec<AlarmEvent>,
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 #[napi(object)]
-struct TelemetryPoint {
+pub struct TelemetryPoint {
   pub ts: String,
   pub machineId: String,
+  /// Seconds since the first sample of the connection or session, on the clock `clock.elapsed` picks.
   pub elapsedSeconds: f64,
+  /// With `clock.elapsed: "host"`: the elapsed time on sample timestamps and on the gateway's monotonic clock at
+  /// receipt; they part when the device clock steps.
+  pub deviceElapsedSeconds: Option<f64>,
+  pub hostElapsedSeconds: Option<f64>,
   pub btC: Option<f64>,
   pub etC: Option<f64>,
+  /// The `powerPct` reading, under the name(s) `powerField` picks.
   pub gasPct: Option<f64>,
+  pub powerPct: Option<f64>,
   pub fanPct: Option<f64>,
   pub drumRpm: Option<f64>,
+  /// Ambient readings of a roastery weather station, in °C, % and hPa.
+  pub ambientC: Option<f64>,
+  pub humidityPct: Option<f64>,
+  pub pressureHpa: Option<f64>,
+  /// Registered channels, set when `channelRegistry` promotes them.
+  pub exhaustC: Option<f64>,
+  pub inletC: Option<f64>,
+  pub drumPressurePa: Option<f64>,
+  pub airflowPct: Option<f64>,
+  /// With `energy`: the energy used so far in the session, in kWh, as of the latest accepted sample.
+  pub energyKwh: Option<f64>,
   pub extras: Option<Vec<ExtraEntry>>,
+  /// Calibrated readings of the configured `probes`, on top of the `btC`/`etC` made from them.
+  pub probes: Option<Vec<ProbeReading>>,
+  /// With `roastEvents`: the phase shares since CHARGE, unset before it.
+  pub phases: Option<PhaseProgress>,
+  /// Set while a session is active; see `start_session`.
+  pub sessionId: Option<String>,
+  /// Gateway time the line behind this point arrived.
+  pub sourceReceivedAt: Option<String>,
+  /// How long before the point was handed out that was, in milliseconds.
+  pub ageMs: Option<f64>,
+  /// `fresh`, `held`, `interpolated` or `clamped` for every channel the point carries, and for extras that aren't
+  /// fresh.
+  pub quality: Option<BTreeMap<String, String>>,
 }
 
-#[derive(Debug, Clone)]
-#[napi(object)]
-struct ExtraEntry {
-  pub key: String,
-  pub number_value: Option<f64>,
-  pub text_value: Option<String>,
+impl TelemetryPoint {
+  /// The `powerPct` reading, whichever name it went out under.
+  pub(crate) fn power(&self) -> Option<f64> {
+    self.powerPct.or(self.gasPct)
+  }
 }
 
-struct TcpLineParser {
-  config: TcpLineDriverConfig,
-  csv_header_parsed: bool,
-  csv_columns: Vec<String>,
+#[cfg(test)]
+impl TelemetryPoint {
+  /// A point of machine `m1` at 06:00 UTC with `fields` on top, for tests of what the outputs make of it.
+  pub(crate) fn test(fields: serde_json::Value) -> Self {
+    let mut point = serde_json::json!({ "ts": "2025-01-01T06:00:00.000Z", "machineId": "m1", "elapsedSeconds": 1.5 });
+    point.as_object_mut().expect("object").extend(fields.as_object().expect("object").clone());
+    serde_json::from_value(point).expect("point")
+  }
 }
 
-impl TcpLineParser {
-  fn new(config: TcpLineDriverConfig) -> Self {
-    Self { csv_columns: config.csv.columns.clone(), csv_header_parsed: false, config }
-  }
+struct DriverInner {
+  /// Swapped wholesale by `update_config`; readers take a snapshot with `config()`.
+  config: RwLock<Arc<TcpLineDriverConfig>>,
+  machine_id: String,
+  parser: Mutex<TcpLineParser>,
+  state: Mutex<DriverState>,
+  metrics: Mutex<DriverMetrics>,
+  latest_sample: Mutex<Option<RawTelemetrySample>>,
+  /// Bumped for every accepted sample so streams can tell a new sample from the one they already yielded.
+  sample_seq: AtomicU64,
+  /// `sample_seq` of the sample `readTelemetry` last returned under `readMode: "consume"`.
+  read_seq: AtomicU64,
+  /// Bumped on every transition to CONNECTED, so `connect()` can tell a short-lived source (e.g. a fast replay) that
+  /// already finished from one that never connected.
+  connected_seq: AtomicU64,
+  /// Bumped whenever `connect()` spawns a new connection loop or source task.
+  run_seq: AtomicU64,
+  /// Endpoint of the current or latest connection attempt, for `DriverStatus`.
+  active_endpoint: Mutex<Option<Endpoint>>,
+  /// Bound port while a listen-mode driver is accepting devices.
+  listen_port: Mutex<Option<u16>>,
+  peers: Mutex<Vec<Arc<Peer>>>,
+  start_ts: Mutex<Option<ElapsedBaseline>>,
+  stop_flag: AtomicBool,
+  /// Set by `pause()`: a connection reports PAUSED instead of CONNECTED and parsed samples are dropped.
+  paused: AtomicBool,
+  notify_sample: tokio::sync::Notify,
+  notify_state: tokio::sync::Notify,
+  backoff: Mutex<Backoff>,
+  handle: Mutex<Option<JoinHandle<()>>>,
+  recorder: Mutex<Option<LineRecorder>>,
+  capture: Mutex<SessionCapture>,
+  /// The config as the TCP driver was given it, for `exportSession`.
+  config_json: Mutex<Option<serde_json::Value>>,
+  spool: Mutex<Option<SampleSpool>>,
+  /// Started with the first point after `influx` is configured.
+  influx: Mutex<Option<InfluxSink>>,
+  influx_stats: Arc<SinkStats>,
+  /// Started with the first point after `kafka` is configured.
+  kafka: Mutex<Option<KafkaSink>>,
+  kafka_stats: Arc<SinkStats>,
+  /// Running once `connect()` has started it for `grpc`.
+  grpc: Mutex<Option<GrpcServer>>,
+  /// Every published point, for gRPC streams and `subscribe()` handles; only built while someone subscribes.
+  point_tap: tokio::sync::broadcast::Sender<TelemetryPoint>,
+  commands: Arc<CommandChannel>,
+  state_events: Subscribers<StateChangeEvent>,
+  error_events: Subscribers<DriverError>,
+  /// `onRawLine` subscribers; nothing is built for them until one subscribes.
+  raw_lines: RawLineTap,
+  recent_errors: Mutex<RecentErrors>,
+  connection_history: Arc<Mutex<ConnectionHistory>>,
+  stats: Mutex<DriverStats>,
+  clock: Mutex<DeviceClock>,
+  /// While set, `start_ts` survives reconnects so a roast interrupted by a dropped socket keeps its elapsed time.
+  session: Mutex<Option<SessionInfo>>,
+  /// Set between `startLogging` and `stopLogging`.
+  session_log: Mutex<Option<SessionLog>>,
+  roast_detector: Mutex<Option<RoastEventDetector>>,
+  roast_events: Subscribers<RoastEvent>,
+  /// The frame counter of the connection in connect mode; listen-mode peers keep their own.
+  sequence: Mutex<SequenceTracker>,
+  gap_events: Subscribers<SequenceGapEvent>,
+  /// Samples `ordering: reorder` is holding back.
+  order: Mutex<SampleOrder>,
+  stale: Mutex<StaleWatch>,
+  alarm_rules: Mutex<AlarmRules>,
+  active_alarms: Mutex<Vec<AlarmEvent>>,
+  alarm_events: Subscribers<AlarmEvent>,
+  energy: Mutex<EnergyMeter>,
+  phases: Mutex<PhaseTracker>,
+  downsampler: Mutex<Downsampler>,
+  gap_filler: Mutex<GapFiller>,
+  resampler: Mutex<Resampler>,
+  change_filter: Mutex<ChangeFilter>,
+  history: Mutex<SampleHistory>,
+  queue: Mutex<SampleQueue>,
+  /// Signalled when `drainSamples` makes room or the queue policy may have changed.
+  notify_queue: tokio::sync::Notify,
+  /// `TcpLineDriverNative` handles connected through this driver; more than one under `onDuplicate: "share"`.
+  attached: AtomicUsize,
+}
 
-  fn reset(&mut self) {
-    self.csv_header_parsed = false;
-    self.csv_columns = self.config.csv.columns.clone();
+impl DriverInner {
+  fn new(config: TcpLineDriverConfig, machine_id: String) -> Arc<Self> {
+    let parser = TcpLineParser::new(config.parser());
+    let commands = Arc::new(CommandChannel::new(config.commands.clone()));
+    let roast_detector = config.pipeline.roast_events.clone().map(RoastEventDetector::new);
+    let alarm_rules = AlarmRules::new(&config.alarms);
+    let inner = Arc::new(Self {
+      config: RwLock::new(Arc::new(config)),
+      machine_id,
+      parser: Mutex::new(parser),
+      state: Mutex::new(DriverState::DISCONNECTED),
+      metrics: Mutex::new(DriverMetrics::default()),
+      latest_sample: Mutex::new(None),
+      sample_seq: AtomicU64::new(0),
+      read_seq: AtomicU64::new(0),
+      connected_seq: AtomicU64::new(0),
+      run_seq: AtomicU64::new(0),
+      active_endpoint: Mutex::new(None),
+      listen_port: Mutex::new(None),
+      peers: Mutex::new(Vec::new()),
+      start_ts: Mutex::new(None),
+      stop_flag: AtomicBool::new(false),
+      paused: AtomicBool::new(false),
+      notify_sample: tokio::sync::Notify::new(),
+      notify_state: tokio::sync::Notify::new(),
+      backoff: Mutex::new(Backoff::new(0, 0)),
+      handle: Mutex::new(None),
+      recorder: Mutex::new(None),
+      capture: Mutex::new(SessionCapture::new(None)),
+      config_json: Mutex::new(None),
+      spool: Mutex::new(None),
+      influx: Mutex::new(None),
+      influx_stats: Arc::new(SinkStats::default()),
+      kafka: Mutex::new(None),
+      kafka_stats: Arc::new(SinkStats::default()),
+      grpc: Mutex::new(None),
+      point_tap: tokio::sync::broadcast::channel(POINT_TAP_CAPACITY).0,
+      commands,
+      state_events: Subscribers::new(),
+      error_events: Subscribers::new(),
+      raw_lines: RawLineTap::new(),
+      recent_errors: Mutex::new(RecentErrors::default()),
+      connection_history: Arc::new(Mutex::new(ConnectionHistory::default())),
+      stats: Mutex::new(DriverStats::new()),
+      clock: Mutex::new(DeviceClock::new()),
+      session: Mutex::new(None),
+      session_log: Mutex::new(None),
+      roast_detector: Mutex::new(roast_detector),
+      roast_events: Subscribers::new(),
+      sequence: Mutex::new(SequenceTracker::default()),
+      gap_events: Subscribers::new(),
+      order: Mutex::new(SampleOrder::default()),
+      stale: Mutex::new(StaleWatch::default()),
+      alarm_rules: Mutex::new(alarm_rules),
+      active_alarms: Mutex::new(Vec::new()),
+      alarm_events: Subscribers::new(),
+      energy: Mutex::new(EnergyMeter::default()),
+      phases: Mutex::new(PhaseTracker::default()),
+      downsampler: Mutex::new(Downsampler::default()),
+      gap_filler: Mutex::new(GapFiller::default()),
+      resampler: Mutex::new(Resampler::default()),
+      change_filter: Mutex::new(ChangeFilter::default()),
+      history: Mutex::new(SampleHistory::default()),
+      queue: Mutex::new(SampleQueue::default()),
+      notify_queue: tokio::sync::Notify::new(),
+      attached: AtomicUsize::new(0),
+    });
+    registry::register(&inner);
+    inner
   }
 
-  fn parse_line(&mut self, line: &str) -> Result<Option<RawTelemetrySample>, ParseError> {
-    let trimmed = line.trim();
-    if trimmed.is_empty() {
-      return Ok(None);
+  /// `new` plus the side effects a TCP driver config asks for at construction (`record`, `spool`).
+  fn open(config: TcpLineDriverConfig, machine_id: String) -> Result<Arc<Self>> {
+    runtime::check(config.runtime.as_deref())?;
+    let inner = Self::new(config, machine_id);
+    if let Some(record) = inner.config().record.clone() {
+      inner
+        .start_recording(record)
+        .map_err(|err| Error::from_reason(format!("failed to start recording: {}", err)))?;
     }
-    match self.config.format {
-      FrameFormat::Jsonl => self.parse_json_line(trimmed),
-      FrameFormat::Csv => self.parse_csv_line(trimmed),
+    if let Some(spool) = inner.config().spool.clone() {
+      *inner.spool.lock() =
+        Some(SampleSpool::open(spool).map_err(|err| Error::from_reason(format!("failed to open spool: {}", err)))?);
     }
+    Ok(inner)
   }
 
-  fn parse_json_line(&mut self, line: &str) -> Result<Option<RawTelemetrySample>, ParseError> {
-    let value: serde_json::Value = serde_json::from_str(line).map_err(|_| ParseError::InvalidJson)?;
-    let map = value
-      .as_object()
-      .cloned()
-      .ok_or(ParseError::InvalidJson)?
-      .into_iter()
-      .collect::<Vec<_>>();
-    self.to_sample(map)
+  fn config(&self) -> Arc<TcpLineDriverConfig> {
+    Arc::clone(&self.config.read())
   }
 
-  fn parse_csv_line(&mut self, line: &str) -> Result<Option<RawTelemetrySample>, ParseError> {
-    let parts = line.split(&self.config.csv.delimiter).map(|p| p.trim().to_owned()).collect::<Vec<_>>();
-    if self.config.csv.has_header && !self.csv_header_parsed {
-      self.csv_columns = parts;
-      self.csv_header_parsed = true;
-      return Ok(None);
-    }
-
-    let columns = if !self.csv_columns.is_empty() {
-      self.csv_columns.clone()
-    } else {
-      vec![
-        "ts".to_string(),
-        "btC".to_string(),
-        "etC".to_string(),
-        "powerPct".to_string(),
-        "fanPct".to_string(),
-        "drumRpm".to_string(),
-      ]
-    };
-
-    let mut map = Vec::new();
-    for (idx, value) in parts.into_iter().enumerate() {
-      if let Some(key) = columns.get(idx) {
-        map.push((key.clone(), serde_json::Value::String(value)));
-      }
-    }
-
-    self.to_sample(map)
+  fn apply_backoff(&self, reconnect: &ReconnectConfig) {
+    self.backoff.lock().apply(reconnect);
   }
 
-  fn to_sample(&self, record: Vec<(String, serde_json::Value)>) -> Result<Option<RawTelemetrySample>, ParseError> {
-    let mut ts_value: Option<DateTime<Utc>> = None;
-    for (key, value) in record.iter() {
-      if key == "ts" {
-        if let Some(ts) = value.as_str() {
-          ts_value = Some(parse_timestamp(ts)?);
+  /// Applies a new config to the live driver. Parser, offsets, dedupe, pacing, watchdog and reconnect settings take
+  /// effect on the next line; transport changes (host, port, framing, keepalive, connect timeout, heartbeat) restart a
+  /// running connection loop. Returns whether it was restarted.
+  async fn update_config(self: &Arc<Self>, config: TcpLineDriverConfig) -> Result<bool> {
+    let previous = self.config();
+    if config.commands != previous.commands {
+      return Err(Error::from_reason("invalid config: commands cannot be changed on a live driver"));
+    }
+    runtime::check(config.runtime.as_deref())?;
+    if config.record != previous.record {
+      let result = match config.record.clone() {
+        Some(record) => self.start_recording(record),
+        None => self.stop_recording(),
+      };
+      result.map_err(|err| Error::from_reason(format!("failed to update recording: {}", err)))?;
+    }
+    if config.spool != previous.spool {
+      let spool = match config.spool.clone() {
+        Some(spool) => {
+          Some(SampleSpool::open(spool).map_err(|err| Error::from_reason(format!("failed to open spool: {}", err)))?)
         }
+        None => None,
+      };
+      *self.spool.lock() = spool;
+    }
+    if config.influx != previous.influx {
+      // The old sink writes out what it buffered; the next point starts one for the new settings.
+      *self.influx.lock() = None;
+    }
+    if config.kafka != previous.kafka {
+      *self.kafka.lock() = None;
+    }
+    let grpc_changed = config.grpc != previous.grpc;
+    let reconnect = config.mode != previous.mode
+      || config.host != previous.host
+      || config.framing != previous.framing
+      || config.line_delimiter != previous.line_delimiter
+      || config.max_line_bytes != previous.max_line_bytes
+      || config.max_bytes_per_second != previous.max_bytes_per_second
+      || config.port != previous.port
+      || config.endpoints != previous.endpoints
+      || config.max_connections != previous.max_connections
+      || config.keepalive != previous.keepalive
+      || config.connect_timeout_ms != previous.connect_timeout_ms
+      || config.proxy != previous.proxy
+      || config.handshake != previous.handshake
+      || config.heartbeat != previous.heartbeat
+      || config.runtime != previous.runtime;
+
+    self.parser.lock().update_config(config.parser());
+    self.apply_backoff(&config.reconnect);
+    {
+      let mut detector = self.roast_detector.lock();
+      if detector.as_ref().map(RoastEventDetector::config) != config.pipeline.roast_events.as_ref() {
+        *detector = config.pipeline.roast_events.clone().map(RoastEventDetector::new);
       }
     }
+    let cleared = self.alarm_rules.lock().update(&config.alarms);
+    for event in cleared {
+      self.emit_alarm(event);
+    }
+    *self.config.write() = Arc::new(config);
+    // A running server moves to the new address right away; otherwise the next connect() starts it.
+    if grpc_changed && self.grpc.lock().take().is_some() {
+      self.ensure_grpc()?;
+    }
+    // A loop blocked on a full queue re-checks against the new capacity and policy.
+    self.notify_queue.notify_waiters();
+    info!(machine_id = %self.machine_id, reconnect, "config updated");
+
+    let running = self.handle.lock().as_ref().is_some_and(|handle| !handle.is_finished());
+    if !reconnect || !running {
+      return Ok(false);
+    }
+    let handle = self.handle.lock().take();
+    if let Some(handle) = handle {
+      handle.abort();
+      // Wait out the cancellation so the old loop can't touch the command channel after the new one attaches.
+      let _ = handle.await;
+    }
+    self.commands.detach().await;
+    self.ensure_loop();
+    Ok(true)
+  }
 
-    let ts = ts_value.unwrap_or_else(Utc::now);
+  /// Starts the connection loop and waits for the first connect, like `TcpLineDriverNative::connect`.
+  async fn connect(self: &Arc<Self>) -> Result<()> {
+    let since = self.connected_seq.load(Ordering::Relaxed);
+    self.check_duplicate()?;
+    self.ensure_grpc()?;
+    self.ensure_loop();
+    self.wait_for_connected(since).await
+  }
 
-    let mut extras = Vec::<ExtraEntry>::new();
-    let mut sample = RawTelemetrySample {
-      ts,
-      bt_c: None,
-      et_c: None,
-      power_pct: None,
-      fan_pct: None,
-      drum_rpm: None,
-      extras: None,
+  /// Applies `onDuplicate` when another driver's loop is already running against this one's `host:port`.
+  fn check_duplicate(&self) -> Result<()> {
+    let config = self.config();
+    let Some(endpoint) = config.endpoint_key() else {
+      return Ok(());
+    };
+    let Some(other) = registry::connected_to(&endpoint, self) else {
+      return Ok(());
     };
+    if config.on_duplicate == DuplicatePolicy::Error {
+      return Err(Error::from_reason(format!(
+        "duplicate connection: {} is already connected by machine {}",
+        endpoint, other.machine_id
+      )));
+    }
+    warn!(machine_id = %self.machine_id, endpoint, other = %other.machine_id, "device already connected elsewhere");
+    Ok(())
+  }
 
-    for (key, value) in record.into_iter() {
-      match key.as_str() {
-        "btC" => sample.bt_c = parse_number(&value).map(|v| v + self.config.offsets.bt_c),
-        "etC" => sample.et_c = parse_number(&value).map(|v| v + self.config.offsets.et_c),
-        "powerPct" => sample.power_pct = parse_number(&value),
-        "fanPct" => sample.fan_pct = parse_number(&value),
-        "drumRpm" => sample.drum_rpm = parse_number(&value),
-        "ts" => {}
-        _ => {
-          if RESERVED_KEYS.contains(&key.as_str()) {
-            continue;
-          }
-          if let Some(num) = parse_number(&value) {
-            extras.push(ExtraEntry { key, number_value: Some(num), text_value: None });
-          } else if let Some(text) = value.as_str() {
-            let trimmed = text.trim();
-            if !trimmed.is_empty() {
-              extras.push(ExtraEntry { key, number_value: None, text_value: Some(trimmed.to_string()) });
-            }
-          }
-        }
-      }
+  /// Refuses a second handle (`onDuplicate: "share"`) that would read the device as another machine or parse its
+  /// lines differently; any other setting of its own is ignored, with a warning.
+  fn check_share(&self, config: &TcpLineDriverConfig, machine_id: &str) -> Result<()> {
+    let current = self.config();
+    let endpoint = current.endpoint_key().unwrap_or_default();
+    if machine_id != self.machine_id {
+      return Err(Error::from_reason(format!(
+        "cannot share {}: it is read as machine {}, not {}",
+        endpoint, self.machine_id, machine_id
+      )));
+    }
+    if config.parser() != current.parser() {
+      return Err(Error::from_reason(format!(
+        "cannot share {}: the parsing settings differ from those machine {} reads it with",
+        endpoint, self.machine_id
+      )));
     }
+    if *config != *current {
+      warn!(machine_id = %self.machine_id, endpoint, "sharing the connection; the new handle's settings are ignored");
+    }
+    Ok(())
+  }
 
-    let has_channels =
-      sample.bt_c.is_some() || sample.et_c.is_some() || sample.power_pct.is_some() || sample.fan_pct.is_some() || sample.drum_rpm.is_some();
+  fn endpoint_key(&self) -> Option<String> {
+    self.config().endpoint_key()
+  }
 
-    if !extras.is_empty() {
-      sample.extras = Some(extras);
-    }
+  /// Whether a connection loop or source task is running, i.e. `connect()` was called and the driver hasn't stopped
+  /// or given up.
+  fn is_running(&self) -> bool {
+    self.handle.lock().as_ref().is_some_and(|handle| !handle.is_finished())
+  }
 
-    if !has_channels && sample.extras.is_none() {
-      return Ok(None);
+  /// Marks a handle connected, once per `attached` flag.
+  fn attach(&self, attached: &AtomicBool) {
+    if !attached.swap(true, Ordering::Relaxed) {
+      self.attached.fetch_add(1, Ordering::Relaxed);
     }
-
-    Ok(Some(sample))
   }
-}
 
-fn parse_number(value: &serde_json::Value) -> Option<f64> {
-  match value {
-    serde_json::Value::Number(n) => n.as_f64(),
-    serde_json::Value::String(s) => {
-      if s.is_empty() {
-        None
-      } else {
-        s.parse::<f64>().ok()
-      }
+  /// Lets go of a handle's hold on the connection: `false` while another handle sharing it is still connected, so
+  /// only the last `disconnect()` stops it.
+  fn release(&self, attached: &AtomicBool) -> bool {
+    if attached.swap(false, Ordering::Relaxed) {
+      self.attached.fetch_sub(1, Ordering::Relaxed) == 1
+    } else {
+      self.attached.load(Ordering::Relaxed) == 0
     }
-    _ => None,
   }
-}
 
-fn parse_timestamp(value: &str) -> Result<DateTime<Utc>, ParseError> {
-  DateTime::parse_from_rfc3339(value)
-    .map(|dt| dt.with_timezone(&Utc))
-    .map_err(|_| ParseError::InvalidTimestamp)
-}
+  /// Starts the `grpc` server for this driver unless it is already running.
+  fn ensure_grpc(self: &Arc<Self>) -> Result<()> {
+    let Some(grpc) = self.config().grpc.clone() else {
+      return Ok(());
+    };
+    let mut server = self.grpc.lock();
+    if server.is_none() {
+      let drivers = vec![(self.machine_id.clone(), Arc::downgrade(self))];
+      let started = GrpcServer::start(&grpc, drivers)
+        .map_err(|err| Error::from_reason(format!("grpc bind {}:{} failed: {}", grpc.host, grpc.port, err)))?;
+      *server = Some(started);
+    }
+    Ok(())
+  }
 
-#[derive(Debug, Error)]
-enum ParseError {
-  #[error("invalid json")]
-  InvalidJson,
-  #[error("invalid timestamp")]
-  InvalidTimestamp,
-}
+  fn grpc_port(&self) -> Option<u32> {
+    self.grpc.lock().as_ref().map(|server| server.port() as u32)
+  }
 
-struct DriverInner {
-  config: TcpLineDriverConfig,
-  machine_id: String,
-  parser: Mutex<TcpLineParser>,
-  state: Mutex<DriverState>,
-  metrics: Mutex<DriverMetrics>,
-  latest_sample: Mutex<Option<RawTelemetrySample>>,
-  start_ts: Mutex<Option<DateTime<Utc>>>,
-  stop_flag: AtomicBool,
-  notify_sample: tokio::sync::Notify,
-  notify_state: tokio::sync::Notify,
-  backoff: Mutex<Backoff>,
-  handle: Mutex<Option<JoinHandle<()>>>,
-}
+  fn listen_port(&self) -> Option<u32> {
+    self.listen_port.lock().map(u32::from)
+  }
 
-impl DriverInner {
-  fn new(config: TcpLineDriverConfig, machine_id: String) -> Arc<Self> {
-    let parser = TcpLineParser::new(config.clone());
-    Arc::new(Self {
-      config,
-      machine_id,
-      parser: Mutex::new(parser),
-      state: Mutex::new(DriverState::DISCONNECTED),
-      metrics: Mutex::new(DriverMetrics::default()),
-      latest_sample: Mutex::new(None),
-      start_ts: Mutex::new(None),
-      stop_flag: AtomicBool::new(false),
-      notify_sample: tokio::sync::Notify::new(),
-      notify_state: tokio::sync::Notify::new(),
-      backoff: Mutex::new(Backoff::new(0, 0)),
-      handle: Mutex::new(None),
-    })
+  fn subscribe_points(&self) -> tokio::sync::broadcast::Receiver<TelemetryPoint> {
+    self.point_tap.subscribe()
   }
 
   fn ensure_loop(self: &Arc<Self>) {
@@ -345,16 +1031,55 @@ impl DriverInner {
       }
     }
     self.stop_flag.store(false, Ordering::Relaxed);
-    let mut backoff = self.backoff.lock();
-    backoff.min = self.config.reconnect.min_backoff_ms;
-    backoff.max = self.config.reconnect.max_backoff_ms;
-    backoff.reset();
-    drop(backoff);
+    self.apply_backoff(&self.config().reconnect);
+    self.backoff.lock().reset();
+    // Without reconnects, connect() treats DISCONNECTED as the attempt's outcome, so don't let it see the idle state.
+    self.run_seq.fetch_add(1, Ordering::Relaxed);
+    self.set_state(DriverState::CONNECTING);
     let runner = Arc::clone(self);
-    *handle_guard = Some(tokio::spawn(async move { runner.run_loop().await }));
+    let config = self.config();
+    *handle_guard = Some(match config.mode {
+      ConnectionMode::Connect => runtime::spawn(config.runtime.as_deref(), async move {
+        let _grid = GridTicker::spawn(Arc::clone(&runner));
+        let _stale = StaleWatchdog::spawn(Arc::clone(&runner));
+        runner.run_loop().await
+      }),
+      ConnectionMode::Listen => runtime::spawn(config.runtime.as_deref(), async move {
+        let _grid = GridTicker::spawn(Arc::clone(&runner));
+        let _stale = StaleWatchdog::spawn(Arc::clone(&runner));
+        runner.run_listener().await
+      }),
+    });
+  }
+
+  /// Spawns a non-TCP sample source (replay, simulator) unless one is already running.
+  fn ensure_source<F, Fut>(self: &Arc<Self>, source: F)
+  where
+    F: FnOnce(Arc<Self>) -> Fut,
+    Fut: Future<Output = ()> + Send + 'static,
+  {
+    let mut handle_guard = self.handle.lock();
+    if let Some(handle) = handle_guard.as_ref() {
+      if !handle.is_finished() {
+        return;
+      }
+    }
+    self.stop_flag.store(false, Ordering::Relaxed);
+    // Set before spawning so connect() doesn't observe the idle DISCONNECTED state as a failure.
+    self.run_seq.fetch_add(1, Ordering::Relaxed);
+    self.set_state(DriverState::CONNECTING);
+    *handle_guard = Some(runtime::spawn(self.config().runtime.as_deref(), source(Arc::clone(self))));
   }
 
   async fn run_loop(self: Arc<Self>) {
+    let mut attempts: u32 = 0;
+    let mut outage_started: Option<Instant> = None;
+    let mut exhausted = false;
+    let mut next_endpoint: usize = 0;
+    // Endpoints that failed since the last backoff; the loop only sleeps once each has had a turn.
+    let mut failed_since_backoff: usize = 0;
+    // Connection attempts since the connection was last up, for the history.
+    let mut tries: u32 = 0;
     loop {
       if self.stop_flag.load(Ordering::Relaxed) {
         break;
@@ -363,20 +1088,49 @@ impl DriverInner {
       self.set_state(DriverState::CONNECTING);
       self.reset_connection_state();
 
-      match TcpStream::connect((self.config.host.as_str(), self.config.port)).await {
+      let endpoints = self.config().all_endpoints();
+      let index = next_endpoint % endpoints.len();
+      let endpoint = endpoints[index].clone();
+      *self.active_endpoint.lock() = Some(endpoint.clone());
+      let attempt = attempts + 1;
+      debug!(machine_id = %self.machine_id, host = %endpoint.host, port = endpoint.port, attempt, "connecting");
+      tries = tries.saturating_add(1);
+      self.log_connection(
+        ConnectionEvent::new(ConnectionEventKind::Connecting, Some(endpoint.to_string())).with_attempt(tries),
+      );
+      let connected = match self.connect_stream(&endpoint).await {
         Ok(stream) => {
-          self.handle_connected(stream).await;
+          // A connect whose keepalive setup or handshake fails is still a failed attempt.
+          let connected = self.handle_connected(stream, &endpoint).await;
+          if connected {
+            attempts = 0;
+            tries = 0;
+            outage_started = None;
+          }
+          connected
         }
         Err(err) => {
-          self.handle_failure(format!("connection failure: {}", err)).await;
+          let event = ConnectionEvent::new(ConnectionEventKind::ConnectFailed, Some(endpoint.to_string()));
+          self.log_connection(event.with_reason(&err.message));
+          self.handle_failure(err).await;
+          false
         }
-      }
+      };
 
       if self.stop_flag.load(Ordering::Relaxed) {
         break;
       }
 
-      if !self.config.reconnect.enabled {
+      if !self.config().reconnect.enabled {
+        break;
+      }
+
+      attempts = attempts.saturating_add(1);
+      let outage = outage_started.get_or_insert_with(Instant::now).elapsed();
+      if self.config().reconnect.exhausted(attempts, outage) {
+        let outage_ms = outage.as_millis() as u64;
+        error!(machine_id = %self.machine_id, attempts, outage_ms, "reconnect policy exhausted");
+        exhausted = true;
         break;
       }
 
@@ -385,19 +1139,87 @@ impl DriverInner {
         metrics.reconnects = metrics.reconnects.saturating_add(1);
       }
 
-      let delay = { self.backoff.lock().next() };
-      sleep(Duration::from_millis(delay)).await;
+      next_endpoint = match (connected, self.config().failover) {
+        (true, FailoverPolicy::Ordered) => 0,
+        _ => index + 1,
+      };
+      if !connected {
+        failed_since_backoff += 1;
+        if failed_since_backoff < endpoints.len() {
+          debug!(machine_id = %self.machine_id, failed = %endpoint, "failing over to next endpoint");
+          continue;
+        }
+      }
+      failed_since_backoff = 0;
+
+      let delay = { self.backoff.lock().next_delay() };
+      debug!(machine_id = %self.machine_id, delay_ms = delay, attempt = attempts, "reconnecting after backoff");
+      tokio::select! {
+        () = sleep(Duration::from_millis(delay)) => {}
+        () = self.wait_for_stop() => break,
+      }
     }
 
     let final_state = if self.stop_flag.load(Ordering::Relaxed) {
       DriverState::STOPPED
+    } else if exhausted {
+      DriverState::FAILED
     } else {
       DriverState::DISCONNECTED
     };
     self.set_state(final_state);
   }
 
-  async fn handle_connected(&self, stream: TcpStream) {
+  async fn connect_stream(&self, endpoint: &Endpoint) -> std::result::Result<TcpStream, DriverError> {
+    let config = self.config();
+    let connect = async {
+      match &config.proxy {
+        Some(proxy) => proxy.connect(&endpoint.host, endpoint.port).await,
+        None => TcpStream::connect((endpoint.host.as_str(), endpoint.port)).await,
+      }
+    };
+    let result = if config.connect_timeout_ms == 0 {
+      connect.await
+    } else {
+      match tokio::time::timeout(Duration::from_millis(config.connect_timeout_ms), connect).await {
+        Ok(result) => result,
+        Err(_) => {
+          return Err(DriverError::new(
+            DriverErrorCode::Timeout,
+            format!("connection failure: timed out after {} ms", config.connect_timeout_ms),
+          ))
+        }
+      }
+    };
+    result.map_err(|err| DriverError::new(DriverErrorCode::ConnectFailed, format!("connection failure: {}", err)))
+  }
+
+  /// Sets up the connection and reads from it until it ends; returns whether it reached CONNECTED.
+  async fn handle_connected(self: &Arc<Self>, stream: TcpStream, endpoint: &Endpoint) -> bool {
+    let failed = |err: &DriverError| {
+      ConnectionEvent::new(ConnectionEventKind::ConnectFailed, Some(endpoint.to_string())).with_reason(&err.message)
+    };
+    if let Err(err) = self.config().keepalive.apply(&stream) {
+      let err = DriverError::new(DriverErrorCode::ConnectFailed, format!("keepalive setup failed: {}", err));
+      self.log_connection(failed(&err));
+      self.handle_failure(err).await;
+      return false;
+    }
+    let config = self.config();
+    let (read_half, mut write_half) = stream.into_split();
+    let mut reader = FrameReader::new(read_half, config.framing, &config.line_delimiter, config.max_line_bytes as usize)
+      .with_rate_limit(config.max_bytes_per_second);
+    let handshake =
+      handshake::perform(&config.handshake, &mut reader, &mut write_half, &config.commands.line_ending, config.encoding)
+        .await;
+    match handshake {
+      Ok(sent) => self.commands.count_sent(sent),
+      Err(err) => {
+        self.log_connection(failed(&err));
+        self.handle_failure(err).await;
+        return false;
+      }
+    }
     {
       let mut backoff = self.backoff.lock();
       backoff.reset();
@@ -406,85 +1228,795 @@ impl DriverInner {
       let mut metrics = self.metrics.lock();
       metrics.lastError = None;
     }
+    self.commands.attach(write_half).await;
+    info!(machine_id = %self.machine_id, host = %endpoint.host, port = endpoint.port, "connected");
+    self.log_connection(ConnectionEvent::new(ConnectionEventKind::Connected, Some(endpoint.to_string())));
     self.set_state(DriverState::CONNECTED);
-    let mut reader = BufReader::new(stream);
-    let mut buf = String::new();
+    let mut heartbeat = Heartbeat::spawn(config.heartbeat.as_ref(), Arc::clone(&self.commands));
+    if let Some(err) = self.read_frames(&mut reader, &mut heartbeat, None).await {
+      self.log_connection(
+        ConnectionEvent::new(ConnectionEventKind::Disconnected, Some(endpoint.to_string())).with_reason(&err.message),
+      );
+      self.handle_failure(err).await;
+    }
+    drop(heartbeat);
+    self.commands.detach().await;
+    true
+  }
+
+  /// Parses frames from `reader` until the driver stops (`None`) or the connection ends, returning why. A listen-mode
+  /// `peer` parses with its own parser and counts its lines in its own status as well.
+  async fn read_frames<R>(
+    &self,
+    reader: &mut FrameReader<R>,
+    heartbeat: &mut Heartbeat,
+    peer: Option<&Peer>,
+  ) -> Option<DriverError>
+  where
+    R: AsyncRead + Unpin,
+  {
+    let mut frame = Vec::new();
 
     loop {
       if self.stop_flag.load(Ordering::Relaxed) {
-        break;
+        return None;
       }
 
-      buf.clear();
-      let read = reader.read_line(&mut buf).await;
+      tokio::select! {
+        () = self.wait_for_queue_space() => {}
+        () = self.wait_for_stop() => return None,
+      }
+      tokio::select! {
+        () = reader.throttle() => {}
+        () = self.wait_for_stop() => return None,
+      }
+      let mid_frame = reader.mid_frame();
+      let read = {
+        let read = self.read_next_frame(reader, &mut frame);
+        tokio::pin!(read);
+        tokio::select! {
+          read = &mut read => read,
+          reason = heartbeat.failed() => Err(DriverError::new(DriverErrorCode::SocketClosed, reason)),
+          () = self.wait_for_stop() => {
+            if !mid_frame.load(Ordering::Relaxed) {
+              return None;
+            }
+            // Let the half-received line finish; `disconnect()` aborts the loop once `shutdownTimeoutMs` is up.
+            read.await
+          }
+        }
+      };
       match read {
-        Ok(0) => {
-          self.handle_failure("socket closed".to_string()).await;
-          break;
+        Ok(ReadFrame::Closed) => return Some(DriverError::new(DriverErrorCode::SocketClosed, "socket closed")),
+        Ok(ReadFrame::Invalid(bytes, reason)) => {
+          self.count_line(bytes);
+          let err = ParseError::InvalidFrame(reason);
+          let raw = String::from_utf8_lossy(&frame);
+          self.count_parse_error(&err, &raw);
+          self.tap_line(&raw, Outcome::Error(&err), peer);
+          if let Some(peer) = peer {
+            peer.count_line(bytes, Err(&err));
+          }
         }
-        Ok(_) => {
-          {
-            let mut metrics = self.metrics.lock();
-            metrics.linesReceived = metrics.linesReceived.saturating_add(1);
+        Ok(ReadFrame::Frame(bytes)) => {
+          self.count_line(bytes);
+          self.record_frame(&frame);
+          let Some(text) = self.config().encoding.decode(&frame) else {
+            let raw = String::from_utf8_lossy(&frame);
+            self.count_parse_error(&ParseError::InvalidUtf8, &raw);
+            self.tap_line(&raw, Outcome::Error(&ParseError::InvalidUtf8), peer);
+            if let Some(peer) = peer {
+              peer.count_line(bytes, Err(&ParseError::InvalidUtf8));
+            }
+            continue;
+          };
+          let line = text.trim_end_matches(['\n', '\r']);
+          if self.commands.intercept(line) {
+            self.tap_line(&text, Outcome::Reply, peer);
+            continue;
+          }
+          let (parser, sequence) = match peer {
+            Some(peer) => (peer.parser(&self.config()), peer.sequence()),
+            None => (&self.parser, &self.sequence),
+          };
+          let processed = self.process_line(parser, sequence, line.trim_end(), peer);
+          if let Err(err) = &processed {
+            self.count_parse_error(err, line);
           }
-          if let Err(err) = self.process_line(buf.trim_end_matches(['\n', '\r']).trim_end()) {
-            let mut metrics = self.metrics.lock();
-            metrics.parseErrors = metrics.parseErrors.saturating_add(1);
-            metrics.lastError = Some(err.to_string());
+          self.tap_line(&text, processed.as_ref().copied().into(), peer);
+          if let Some(peer) = peer {
+            peer.count_line(bytes, processed.as_ref().copied());
           }
         }
+        Err(err) => return Some(err),
+      }
+    }
+  }
+
+  /// Reads one frame, applying the `readTimeoutMs` watchdog. Errors are passed on to `handle_failure`.
+  async fn read_next_frame<R>(
+    &self,
+    reader: &mut FrameReader<R>,
+    frame: &mut Vec<u8>,
+  ) -> std::result::Result<ReadFrame, DriverError>
+  where
+    R: AsyncRead + Unpin,
+  {
+    let read = match self.config().read_timeout_ms {
+      Some(timeout_ms) => match tokio::time::timeout(Duration::from_millis(timeout_ms), reader.read_frame(frame))
+        .await
+      {
+        Ok(read) => read,
+        Err(_) => {
+          let mut metrics = self.metrics.lock();
+          metrics.staleTimeouts = metrics.staleTimeouts.saturating_add(1);
+          let message = format!("read timeout: no line for {} ms", timeout_ms);
+          return Err(DriverError::new(DriverErrorCode::Timeout, message));
+        }
+      },
+      None => reader.read_frame(frame).await,
+    };
+    read.map_err(|err| DriverError::new(DriverErrorCode::SocketClosed, format!("socket error: {}", err)))
+  }
+
+  async fn send_setpoint(&self, setpoint: Setpoint, value: f64) -> Result<Option<String>> {
+    let line = self.commands.templates().render(setpoint, value).map_err(|err| Error::from_reason(err.to_string()))?;
+    self.send_command(&line).await
+  }
+
+  async fn send_command(&self, line: &str) -> Result<Option<String>> {
+    let reply = self.commands.send(line).await.map_err(|err| {
+      let message = err.to_string();
+      warn!(machine_id = %self.machine_id, line, error = %message, "command failed");
+      self.metrics.lock().lastError = Some(message.clone());
+      Error::from_reason(message)
+    })?;
+    debug!(machine_id = %self.machine_id, line, reply = ?reply, "command sent");
+    let mut metrics = self.metrics.lock();
+    metrics.commandsSent = metrics.commandsSent.saturating_add(1);
+    Ok(reply)
+  }
+
+  /// Writes `command` for the raised `alarm` on a task of its own, so the loop doesn't wait on the device, and logs the
+  /// outcome in the connection history.
+  fn trigger_interlock(&self, config: &TcpLineDriverConfig, alarm: &str, command: &str) {
+    let commands = Arc::clone(&self.commands);
+    let history = Arc::clone(&self.connection_history);
+    let endpoint = self.active_endpoint.lock().as_ref().map(Endpoint::to_string);
+    let machine_id = self.machine_id.clone();
+    let alarm = alarm.to_string();
+    let command = command.to_string();
+    let timeout_ms = config.commands.interlock_timeout_ms;
+    runtime::spawn(config.runtime.as_deref(), async move {
+      let started = Instant::now();
+      let event = match commands.interlock(&command, timeout_ms).await {
+        Ok(()) => {
+          let elapsed_ms = started.elapsed().as_millis();
+          info!(machine_id = %machine_id, alarm, line = command, elapsed_ms, "interlock sent");
+          ConnectionEvent::new(ConnectionEventKind::Interlock, endpoint)
+            .with_reason(&format!("{}: sent {:?} in {} ms", alarm, command, elapsed_ms))
+        }
         Err(err) => {
-          self.handle_failure(format!("socket error: {}", err)).await;
-          break;
+          warn!(machine_id = %machine_id, alarm, line = command, error = %err, "interlock failed");
+          ConnectionEvent::new(ConnectionEventKind::InterlockFailed, endpoint)
+            .with_reason(&format!("{}: {:?} not sent: {}", alarm, command, err))
         }
-      }
+      };
+      history.lock().push(event);
+    });
+  }
+
+  fn log_connection(&self, event: ConnectionEvent) {
+    self.connection_history.lock().push(event);
+  }
+
+  fn tap_line(&self, line: &str, outcome: Outcome<'_>, peer: Option<&Peer>) {
+    if self.raw_lines.is_active() {
+      self.raw_lines.emit(line, outcome, peer.map(Peer::address));
     }
   }
 
-  fn process_line(&self, line: &str) -> Result<(), ParseError> {
-    let mut parser = self.parser.lock();
-    if let Some(sample) = parser.parse_line(line)? {
-      self.accept_sample(sample);
+  /// Records the frame as received, before decoding, for `record` and `capture`.
+  fn record_frame(&self, frame: &[u8]) {
+    self.capture_frame(frame);
+    let mut recorder_guard = self.recorder.lock();
+    let Some(recorder) = recorder_guard.as_mut() else {
+      return;
+    };
+    if let Err(err) = recorder.write_frame(Utc::now(), frame) {
+      let message = format!("recording to {} failed: {}", recorder.path(), err);
+      warn!(machine_id = %self.machine_id, error = %message, "recording stopped");
+      *recorder_guard = None;
+      self.metrics.lock().lastError = Some(message);
     }
-    Ok(())
   }
 
-  fn accept_sample(&self, sample: RawTelemetrySample) {
-    let mut latest_guard = self.latest_sample.lock();
-    if let Some(latest) = latest_guard.as_ref() {
-      let delta = sample.ts.signed_duration_since(latest.ts).num_milliseconds();
-      if self.config.dedupe_within_ms > 0 && delta < self.config.dedupe_within_ms as i64 {
-        return;
-      }
+  fn capture_frame(&self, frame: &[u8]) {
+    let config = self.config();
+    let Some(capture) = config.capture.as_ref() else {
+      return;
+    };
+    let due = {
+      let mut session = self.capture.lock();
+      session.frame(capture, Utc::now(), frame);
+      session.metrics_due(capture)
+    };
+    if due {
+      let metrics = self.get_status().metrics;
+      self.capture.lock().snapshot(capture, metrics);
     }
+  }
 
-    *latest_guard = Some(sample.clone());
-    drop(latest_guard);
+  /// Writes what `capture` kept to the archive `path`; see `SessionCapture::export`.
+  fn export_session(&self, path: &str) -> Result<SessionBundleSummary> {
+    let config = self.config();
+    let Some(capture) = config.capture.as_ref() else {
+      return Err(Error::from_reason("exportSession needs capture in the config"));
+    };
+    let metrics = self.get_status().metrics;
+    let mut session = self.capture.lock();
+    session.snapshot(capture, metrics);
+    let config_json = self.config_json.lock().clone();
+    session
+      .export(path, &self.machine_id, config_json.as_ref())
+      .map_err(|err| Error::from_reason(format!("writing {} failed: {}", path, err)))
+  }
 
-    {
-      let mut start_ts = self.start_ts.lock();
-      if start_ts.is_none() {
-        *start_ts = Some(sample.ts);
-      }
+  fn start_recording(&self, config: RecordConfig) -> std::io::Result<()> {
+    let recorder = LineRecorder::open(config)?;
+    if let Some(mut previous) = self.recorder.lock().replace(recorder) {
+      previous.flush()?;
     }
+    Ok(())
+  }
 
-    {
-      let mut metrics = self.metrics.lock();
-      metrics.linesParsed = metrics.linesParsed.saturating_add(1);
-      metrics.lastLineAt = Some(sample.ts.to_rfc3339_opts(SecondsFormat::Millis, true));
+  fn stop_recording(&self) -> std::io::Result<()> {
+    match self.recorder.lock().take() {
+      Some(mut recorder) => recorder.flush(),
+      None => Ok(()),
     }
-
-    self.notify_sample.notify_waiters();
   }
 
-  async fn handle_failure(&self, msg: String) {
-    {
-      let mut metrics = self.metrics.lock();
-      metrics.lastError = Some(msg.clone());
+  /// Parses `line` with `parser` and accepts its samples, if the line completed any.
+  fn process_line(
+    &self,
+    parser: &Mutex<TcpLineParser>,
+    sequence: &Mutex<SequenceTracker>,
+    line: &str,
+    peer: Option<&Peer>,
+  ) -> std::result::Result<bool, ParseError> {
+    let samples = self.parse_with(parser, line)?;
+    let parsed = !samples.is_empty();
+    for mut sample in samples {
+      let device_ts = sample.device_ts.then_some(sample.ts);
+      let mut clock = self.clock.lock();
+      let received = clock.receive_time();
+      if let Some(ts) = clock.stamp(&self.config().clock, device_ts, received) {
+        sample.ts = ts;
+      }
+      drop(clock);
+      sample.host_received_at = Some(received);
+      self.track_sequence(sequence, &sample, peer);
+      self.accept_sample(sample);
+    }
+    Ok(parsed)
+  }
+
+  /// Checks the `sequence` counter of `sample` against the frames before it, counting gaps, duplicates and late
+  /// frames. Frames are followed while paused too, so resuming doesn't look like a gap.
+  fn track_sequence(&self, sequence: &Mutex<SequenceTracker>, sample: &RawTelemetrySample, peer: Option<&Peer>) {
+    let config = self.config();
+    let Some(config) = config.sequence.as_ref() else {
+      return;
+    };
+    let Some(seq) = channels::reading(sample, &config.field).filter(|seq| *seq >= 0.0 && seq.fract() == 0.0) else {
+      return;
+    };
+    let step = sequence.lock().observe(config, seq as u64);
+    let mut metrics = self.metrics.lock();
+    match step {
+      Step::InOrder => {}
+      Step::Gap { expected } => {
+        let missing = seq as u64 - expected;
+        metrics.sequenceGaps = metrics.sequenceGaps.saturating_add(1);
+        metrics.sequenceMissing = metrics.sequenceMissing.saturating_add(missing as i64);
+        drop(metrics);
+        debug!(machine_id = %self.machine_id, expected, received = seq, "sequence gap");
+        self.gap_events.emit(SequenceGapEvent {
+          ts: sample.ts.to_rfc3339_opts(SecondsFormat::Millis, true),
+          expected: expected as f64,
+          received: seq,
+          missing: missing as f64,
+          peer: peer.map(Peer::address),
+        });
+      }
+      Step::Duplicate => metrics.sequenceDuplicates = metrics.sequenceDuplicates.saturating_add(1),
+      Step::Reordered => metrics.sequenceReordered = metrics.sequenceReordered.saturating_add(1),
+      Step::Restarted => info!(machine_id = %self.machine_id, received = seq, "sequence counter restarted"),
+    }
+  }
+
+  fn parse_line(&self, line: &str) -> std::result::Result<Vec<RawTelemetrySample>, ParseError> {
+    self.parse_with(&self.parser, line)
+  }
+
+  /// Runs `parser`, feeding the parse latency histogram. The samples of a line: none, one, or one per object of a
+  /// JSON array.
+  fn parse_with(
+    &self,
+    parser: &Mutex<TcpLineParser>,
+    line: &str,
+  ) -> std::result::Result<Vec<RawTelemetrySample>, ParseError> {
+    let started = Instant::now();
+    let mut parser = parser.lock();
+    let parsed = parser.parse_line(line);
+    self.stats.lock().record_parse(started.elapsed());
+    self.count_field_errors(parser.field_errors(), line);
+    let first = parsed?;
+    let Some(batch) = parser.take_batch() else {
+      return Ok(first.into_iter().collect());
+    };
+    let samples: Vec<_> = first.into_iter().chain(batch).collect();
+    let mut metrics = self.metrics.lock();
+    metrics.batchLines = metrics.batchLines.saturating_add(1);
+    metrics.batchSamples = metrics.batchSamples.saturating_add(samples.len() as i64);
+    Ok(samples)
+  }
+
+  /// Builds a sample from a record of a field-based source (SNMP, OPC UA, BLE, ...); `raw` renders the record for
+  /// `getRecentErrors`.
+  fn record_to_sample(
+    &self,
+    record: Vec<(String, serde_json::Value)>,
+    raw: impl Fn() -> String,
+  ) -> std::result::Result<Option<RawTelemetrySample>, ParseError> {
+    let mut parser = self.parser.lock();
+    let parsed = parser.to_sample(record);
+    if !parser.field_errors().is_empty() {
+      self.count_field_errors(parser.field_errors(), &raw());
+    }
+    parsed
+  }
+
+  /// Reports the fields `strictness: warn` left out of the sample of `line`.
+  fn count_field_errors(&self, field_errors: &[FieldError], line: &str) {
+    if field_errors.is_empty() {
+      return;
+    }
+    warn!(machine_id = %self.machine_id, fields = field_errors.len(), line, "malformed fields");
+    let mut metrics = self.metrics.lock();
+    metrics.fieldErrors = metrics.fieldErrors.saturating_add(field_errors.len() as i64);
+    drop(metrics);
+    let mut recent_errors = self.recent_errors.lock();
+    for field_error in field_errors {
+      recent_errors.push("invalidField", format!("invalid field {field_error}"), line);
+    }
+  }
+
+  fn count_line(&self, bytes: usize) {
+    {
+      let mut metrics = self.metrics.lock();
+      metrics.linesReceived = metrics.linesReceived.saturating_add(1);
+      metrics.bytesReceived = metrics.bytesReceived.saturating_add(bytes as i64);
+    }
+    self.stats.lock().record_line();
+  }
+
+  fn count_parse_error(&self, err: &ParseError, line: &str) {
+    warn!(machine_id = %self.machine_id, error = %err, line, "parse error");
+    {
+      let mut metrics = self.metrics.lock();
+      metrics.parseErrors = metrics.parseErrors.saturating_add(1);
+      if matches!(err, ParseError::MissingChecksum | ParseError::ChecksumMismatch) {
+        metrics.checksumErrors = metrics.checksumErrors.saturating_add(1);
+      }
+      metrics.lastError = Some(err.to_string());
+    }
+    self.recent_errors.lock().push(err.kind(), err.to_string(), line);
+    if !self.error_events.is_empty() {
+      self.error_events.emit(DriverError::new(DriverErrorCode::ParseError, err.to_string()).with_line(line));
+    }
+  }
+
+  fn accept_sample(&self, sample: RawTelemetrySample) {
+    if self.paused.load(Ordering::Relaxed) {
+      return;
+    }
+    if self.stale.lock().touch(Instant::now()) {
+      info!(machine_id = %self.machine_id, "samples resumed");
+      self.emit_alarm(AlarmEvent::new("staleData", false, "samples resumed".to_string()));
+    }
+    let (ready, late) = self.order.lock().push(&self.config().ordering, sample);
+    if late {
+      let mut metrics = self.metrics.lock();
+      metrics.outOfOrderSamples = metrics.outOfOrderSamples.saturating_add(1);
+    }
+    for sample in ready {
+      self.accept_in_order(sample);
+    }
+  }
+
+  /// Takes a sample `ordering` let through.
+  fn accept_in_order(&self, mut sample: RawTelemetrySample) {
+    let config = self.config();
+    channels::clamp(&config.pipeline.channels, &mut sample);
+    if !config.alarms.is_empty() {
+      let events = self.alarm_rules.lock().observe(&sample);
+      for event in events {
+        let rule = config.alarms.iter().find(|rule| rule.name == event.alarm);
+        if let Some(command) = rule.and_then(|rule| rule.command.as_ref()).filter(|_| event.active) {
+          self.trigger_interlock(&config, &event.alarm, command);
+        }
+        if event.active {
+          warn!(machine_id = %self.machine_id, alarm = %event.alarm, message = %event.message, "alarm raised");
+        } else {
+          info!(machine_id = %self.machine_id, alarm = %event.alarm, message = %event.message, "alarm cleared");
+        }
+        self.emit_alarm(event);
+      }
+    }
+    let downsample = config.pipeline.downsample.as_ref().filter(|_| config.pipeline.emit_interval_ms > 0);
+    let emit = {
+      let latest = self.latest_sample.lock();
+      let mut downsampler = self.downsampler.lock();
+      let resampler = self.resampler.lock();
+      if downsample.is_none() {
+        downsampler.reset();
+      }
+      // While downsampling or resampling, duplicates are judged against the last raw sample rather than the last
+      // emitted point.
+      if let Some(previous) = downsampler.last().or(resampler.last()).or(latest.as_ref()) {
+        if self.is_duplicate(&sample, previous) {
+          return;
+        }
+      }
+      match downsample {
+        Some(downsample) => downsampler.push(downsample, config.pipeline.emit_interval_ms, sample.clone()),
+        None => Some(sample.clone()),
+      }
+    };
+    let ready = emit.map(|emit| self.gap_filler.lock().push(&config.pipeline.channels, emit)).unwrap_or_default();
+    self.stats.lock().record_sample();
+    if let Some(energy) = &config.energy {
+      self.energy.lock().add(energy, sample.ts, sample.power_pct);
     }
+
+    {
+      let mut start_ts = self.start_ts.lock();
+      if start_ts.is_none() {
+        *start_ts = Some(ElapsedBaseline::of(&sample));
+      }
+    }
+
+    {
+      let mut metrics = self.metrics.lock();
+      metrics.linesParsed = metrics.linesParsed.saturating_add(1);
+      metrics.lastLineAt = Some(sample.ts.to_rfc3339_opts(SecondsFormat::Millis, true));
+    }
+
+    self.detect_roast_event(&sample);
+    self.history.lock().record(sample);
+    self.publish_filtered(&config, ready);
+  }
+
+  /// Publishes the filled samples that `emitOnChangeOnly` lets through.
+  fn publish_filtered(&self, config: &TcpLineDriverConfig, samples: Vec<RawTelemetrySample>) {
+    for sample in samples {
+      let emit = self.change_filter.lock().apply(&config.pipeline.channels, sample);
+      if let Some(sample) = emit {
+        self.publish(sample);
+      }
+    }
+  }
+
+  /// Makes `sample` the latest point, for `readTelemetry`, streams, the queue, the spool, sinks and the session log.
+  /// With `resample` only the queue gets it, and the grid points go everywhere else.
+  fn publish(&self, sample: RawTelemetrySample) {
+    let config = self.config();
+    if config.resample.is_none() {
+      self.emit(sample, true);
+      return;
+    }
+    if config.pipeline.queue.is_some() {
+      let point = self.build_point(sample.clone());
+      self.enqueue(point);
+    }
+    self.resampler.lock().record(sample);
+  }
+
+  /// Publishes the `resample` grid point for `tick`, once the connection has delivered a sample.
+  fn emit_grid(&self, tick: DateTime<Utc>) {
+    let config = self.config();
+    let Some(resample) = config.resample.as_ref() else {
+      return;
+    };
+    let point = self.resampler.lock().at(resample, tick);
+    if let Some(sample) = point {
+      self.emit(sample, false);
+    }
+  }
+
+  fn emit(&self, sample: RawTelemetrySample, enqueue: bool) {
+    let config = self.config();
+    let sinks = (enqueue && config.pipeline.queue.is_some()) || config.influx.is_some() || config.kafka.is_some();
+    let tapped = self.point_tap.receiver_count() > 0;
+    // Without a taker for the point, the sample moves into `latest_sample` uncopied.
+    let logged = self.session_log.lock().is_some() || config.capture.is_some();
+    let taken = sinks || tapped || self.spool.lock().is_some() || logged;
+    let point = taken.then(|| self.build_point(sample.clone()));
+    {
+      // Bumped under the lock so a reader taking the sample also gets its number.
+      let mut latest = self.latest_sample.lock();
+      *latest = Some(sample);
+      self.sample_seq.fetch_add(1, Ordering::Relaxed);
+    }
+    self.notify_sample.notify_waiters();
+    let Some(point) = point else {
+      return;
+    };
+    if tapped {
+      let _ = self.point_tap.send(point.clone());
+    }
+    if let Some(log) = self.session_log.lock().as_mut() {
+      log.record(&point);
+    }
+    if let Some(capture) = config.capture.as_ref() {
+      self.capture.lock().point(capture, &point);
+    }
+    self.persist(&point);
+    self.forward(&config, &point);
+    if enqueue {
+      self.enqueue(point);
+    }
+  }
+
+  fn forward(&self, config: &TcpLineDriverConfig, point: &TelemetryPoint) {
+    let mut dropped = 0;
+    if let Some(influx) = config.influx.as_ref() {
+      let mut sink_guard = self.influx.lock();
+      let sink = sink_guard.get_or_insert_with(|| InfluxSink::spawn(influx.clone(), Arc::clone(&self.influx_stats)));
+      if !sink.send(point.clone()) {
+        dropped += 1;
+      }
+    }
+    if let Some(kafka) = config.kafka.as_ref() {
+      let mut sink_guard = self.kafka.lock();
+      let sink = sink_guard.get_or_insert_with(|| {
+        KafkaSink::spawn(kafka.clone(), self.machine_id.clone(), Arc::clone(&self.kafka_stats))
+      });
+      if !sink.send(point.clone()) {
+        dropped += 1;
+      }
+    }
+    if dropped > 0 {
+      let mut metrics = self.metrics.lock();
+      metrics.droppedSamples = metrics.droppedSamples.saturating_add(dropped);
+    }
+  }
+
+  /// Closes the sinks and waits until `deadline` for them to write out their buffers, and syncs the spool to disk.
+  /// Without a deadline they write out in the background instead. The next point starts new sinks.
+  async fn flush_sinks(&self, deadline: Option<tokio::time::Instant>) {
+    let influx = self.influx.lock().take();
+    if let Some(influx) = influx {
+      match deadline {
+        Some(deadline) => {
+          if tokio::time::timeout_at(deadline, influx.close()).await.is_err() {
+            warn!(machine_id = %self.machine_id, "influx sink still writing at shutdownTimeoutMs");
+          }
+        }
+        None => drop(tokio::spawn(influx.close())),
+      }
+    }
+    let kafka = self.kafka.lock().take();
+    if let Some(kafka) = kafka {
+      match deadline {
+        Some(deadline) => {
+          if tokio::time::timeout_at(deadline, kafka.close()).await.is_err() {
+            warn!(machine_id = %self.machine_id, "kafka sink still producing at shutdownTimeoutMs");
+          }
+        }
+        None => drop(tokio::spawn(kafka.close())),
+      }
+    }
+    if let Some(spool) = self.spool.lock().as_ref() {
+      if let Err(err) = spool.sync() {
+        warn!(machine_id = %self.machine_id, path = spool.path(), error = %err, "syncing spool failed");
+      }
+    }
+  }
+
+  fn persist(&self, point: &TelemetryPoint) {
+    let mut spool_guard = self.spool.lock();
+    let Some(spool) = spool_guard.as_mut() else {
+      return;
+    };
+    match spool.append(point) {
+      Ok(true) => {}
+      Ok(false) => {
+        let mut metrics = self.metrics.lock();
+        metrics.droppedSamples = metrics.droppedSamples.saturating_add(1);
+      }
+      Err(err) => {
+        let message = format!("spooling to {} failed: {}", spool.path(), err);
+        warn!(machine_id = %self.machine_id, error = %message, "spooling stopped");
+        *spool_guard = None;
+        self.metrics.lock().lastError = Some(message);
+      }
+    }
+  }
+
+  fn drain_persisted(&self, max_samples: Option<u32>) -> Result<Vec<TelemetryPoint>> {
+    let mut spool_guard = self.spool.lock();
+    let Some(spool) = spool_guard.as_mut() else {
+      return Err(Error::from_reason("no spool configured"));
+    };
+    spool
+      .drain(max_samples.map(|max| max as usize))
+      .map_err(|err| Error::from_reason(format!("draining {} failed: {}", spool.path(), err)))
+  }
+
+  fn start_logging(&self, path: String, format: Option<String>) -> Result<()> {
+    let format = LogFormat::resolve(format.as_deref(), &path).map_err(Error::from_reason)?;
+    let mut log_guard = self.session_log.lock();
+    if let Some(log) = log_guard.as_ref() {
+      return Err(Error::from_reason(format!("already logging to {}; call stopLogging first", log.path())));
+    }
+    let probes = self.config().pipeline.probes.sensors.iter().map(|probe| probe.key.clone()).collect();
+    let log = SessionLog::create(path.clone(), format, probes)
+      .map_err(|err| Error::from_reason(format!("failed to create {}: {}", path, err)))?;
+    *log_guard = Some(log);
+    Ok(())
+  }
+
+  fn stop_logging(&self) -> Result<Option<SessionLogSummary>> {
+    let Some(log) = self.session_log.lock().take() else {
+      return Ok(None);
+    };
+    let path = log.path().to_string();
+    log.finish().map(Some).map_err(|err| Error::from_reason(format!("writing {} failed: {}", path, err)))
+  }
+
+  /// Emits the partial interval and the samples waiting on a `linear` fill that a finished replay or simulation leaves
+  /// behind.
+  fn flush_downsample(&self) {
+    let config = self.config();
+    let flushed = config.pipeline.downsample.as_ref().and_then(|downsample| self.downsampler.lock().flush(downsample));
+    let mut ready =
+      flushed.map(|flushed| self.gap_filler.lock().push(&config.pipeline.channels, flushed)).unwrap_or_default();
+    ready.extend(self.gap_filler.lock().flush());
+    self.publish_filtered(&config, ready);
+  }
+
+  fn enqueue(&self, point: TelemetryPoint) {
+    let config = self.config();
+    let Some(queue) = config.pipeline.queue.as_ref() else {
+      return;
+    };
+    if self.queue.lock().push(queue, point) {
+      let mut metrics = self.metrics.lock();
+      metrics.droppedSamples = metrics.droppedSamples.saturating_add(1);
+    }
+  }
+
+  /// Holds the source while a `block` queue is full; returns at once under the other policies.
+  async fn wait_for_queue_space(&self) {
+    loop {
+      let notified = self.notify_queue.notified();
+      tokio::pin!(notified);
+      notified.as_mut().enable();
+      let config = self.config();
+      let Some(queue) = config.pipeline.queue.as_ref().filter(|queue| queue.policy == QueuePolicy::Block) else {
+        return;
+      };
+      if !self.queue.lock().is_full(queue) {
+        return;
+      }
+      notified.await;
+    }
+  }
+
+  /// Resolves once `disconnect()` has asked the loop to stop.
+  async fn wait_for_stop(&self) {
+    loop {
+      let notified = self.notify_state.notified();
+      tokio::pin!(notified);
+      notified.as_mut().enable();
+      if self.stop_flag.load(Ordering::Relaxed) {
+        return;
+      }
+      notified.await;
+    }
+  }
+
+  fn drain_samples(&self, max_samples: Option<u32>) -> Vec<TelemetryPoint> {
+    let points = self.queue.lock().drain(max_samples.map(|max| max as usize));
+    self.count_emitted(points.len());
+    self.notify_queue.notify_waiters();
+    points
+  }
+
+  fn count_emitted(&self, count: usize) {
+    if count > 0 {
+      let mut metrics = self.metrics.lock();
+      metrics.telemetryEmitted = metrics.telemetryEmitted.saturating_add(count as i64);
+    }
+  }
+
+  fn read_aggregate(&self, window_seconds: f64) -> Result<TelemetryAggregate> {
+    if !(window_seconds.is_finite() && window_seconds > 0.0) {
+      return Err(Error::from_reason("windowSeconds must be positive"));
+    }
+    Ok(self.history.lock().aggregate(&self.machine_id, window_seconds))
+  }
+
+  fn is_duplicate(&self, sample: &RawTelemetrySample, latest: &RawTelemetrySample) -> bool {
+    let config = self.config();
+    let delta = sample.ts.signed_duration_since(latest.ts).num_milliseconds();
+    let within = config.pipeline.dedupe_within_ms > 0 && delta < config.pipeline.dedupe_within_ms as i64;
+    match config.pipeline.dedupe_strategy {
+      DedupeStrategy::Timestamp => within,
+      DedupeStrategy::IdenticalValues => within && sample.same_values(latest),
+      DedupeStrategy::Off => false,
+    }
+  }
+
+  fn detect_roast_event(&self, sample: &RawTelemetrySample) {
+    let Some(bt_c) = sample.bt_c else {
+      return;
+    };
+    let detected = {
+      let mut detector = self.roast_detector.lock();
+      let Some(detector) = detector.as_mut() else {
+        return;
+      };
+      detector.observe(sample.ts, bt_c)
+    };
+    self.phases.lock().observe(sample.ts, bt_c, detected.as_ref());
+    let Some(detected) = detected else {
+      return;
+    };
+    info!(machine_id = %self.machine_id, event = ?detected.event_type, bt_c = detected.bt_c, "roast event");
+    self.roast_events.emit(RoastEvent {
+      eventType: detected.event_type,
+      ts: detected.ts.to_rfc3339_opts(SecondsFormat::Millis, true),
+      machineId: self.machine_id.clone(),
+      btC: detected.bt_c,
+      rorCPerMin: detected.ror_c_per_min,
+      sessionId: self.session.lock().as_ref().map(|session| session.sessionId.clone()),
+    });
+  }
+
+  /// Starts event detection over for a new roast (a new session, or a replay/simulation loop).
+  fn reset_roast_events(&self) {
+    if let Some(detector) = self.roast_detector.lock().as_mut() {
+      detector.reset();
+    }
+    self.phases.lock().reset();
+  }
+
+  fn session_summary(&self) -> Option<SessionSummary> {
+    let session_id = self.session.lock().as_ref().map(|session| session.sessionId.clone());
+    let energy_kwh = self.config().energy.as_ref().map(|_| self.energy.lock().kwh());
+    self.phases.lock().summary(&self.machine_id, session_id, energy_kwh)
+  }
+
+  async fn handle_failure(&self, err: DriverError) {
+    warn!(machine_id = %self.machine_id, code = ?err.code, error = %err.message, "driver failure");
+    self.metrics.lock().lastError = Some(err.message.clone());
+    self.error_events.emit(err);
+    // What `reorder` still holds goes out before the connection state is cleared.
+    let held = self.order.lock().flush();
+    held.into_iter().for_each(|sample| self.accept_in_order(sample));
+    self.order.lock().reset();
     self.parser.lock().reset();
-    *self.start_ts.lock() = None;
+    self.sequence.lock().reset();
+    self.reset_elapsed();
     *self.latest_sample.lock() = None;
+    self.downsampler.lock().reset();
+    self.gap_filler.lock().reset();
+    self.resampler.lock().reset();
+    self.change_filter.lock().reset();
     self.notify_sample.notify_waiters();
     self.set_state(if self.stop_flag.load(Ordering::Relaxed) {
       DriverState::STOPPED
@@ -493,131 +2025,532 @@ impl DriverInner {
     });
   }
 
+  /// Outside a session the elapsed baseline and the energy count restart with every connection; in one, the energy
+  /// count only leaves out the time the connection was down.
+  fn reset_elapsed(&self) {
+    if self.session.lock().is_none() {
+      *self.start_ts.lock() = None;
+      self.energy.lock().reset();
+    } else {
+      self.energy.lock().interrupt();
+    }
+  }
+
+  /// Starts a new session: the next sample becomes `elapsedSeconds` 0 and points carry its id until `end_session`.
+  fn start_session(&self, session_id: Option<String>) -> SessionInfo {
+    let session = SessionInfo::start(&self.machine_id, session_id);
+    *self.session.lock() = Some(session.clone());
+    *self.capture.lock() = SessionCapture::new(Some(session.sessionId.clone()));
+    *self.start_ts.lock() = None;
+    self.energy.lock().reset();
+    self.reset_roast_events();
+    info!(machine_id = %self.machine_id, session_id = %session.sessionId, "session started");
+    session
+  }
+
+  fn end_session(&self) -> Option<SessionInfo> {
+    let session = self.session.lock().take();
+    *self.start_ts.lock() = None;
+    self.energy.lock().reset();
+    self.reset_roast_events();
+    if let Some(session) = session.as_ref() {
+      info!(machine_id = %self.machine_id, session_id = %session.sessionId, "session ended");
+    }
+    session
+  }
+
   fn reset_connection_state(&self) {
     self.parser.lock().reset();
+    self.sequence.lock().reset();
+    self.order.lock().reset();
     *self.latest_sample.lock() = None;
-    *self.start_ts.lock() = None;
+    self.downsampler.lock().reset();
+    self.gap_filler.lock().reset();
+    self.resampler.lock().reset();
+    self.change_filter.lock().reset();
+    self.history.lock().reset();
+    self.reset_elapsed();
+    self.stats.lock().reset_sample_clock();
+    self.clock.lock().reset();
   }
 
-  async fn wait_for_connected(&self) -> Result<()> {
+  async fn wait_for_connected(&self, since: u64) -> Result<()> {
     loop {
+      // Register for the next transition before reading the state so a change in between isn't missed.
+      let notified = self.notify_state.notified();
+      tokio::pin!(notified);
+      notified.as_mut().enable();
+      if self.connected_seq.load(Ordering::Relaxed) > since {
+        return Ok(());
+      }
       let state = *self.state.lock();
       match state {
-        DriverState::CONNECTED => return Ok(()),
+        DriverState::CONNECTED | DriverState::PAUSED => return Ok(()),
         DriverState::STOPPED => return Err(Error::from_reason("driver stopped")),
-        DriverState::DISCONNECTED if !self.config.reconnect.enabled => {
+        DriverState::FAILED => {
+          let last_error = self.metrics.lock().lastError.clone();
+          let message = last_error.unwrap_or_else(|| "reconnect attempts exhausted".to_string());
+          return Err(Error::from_reason(message));
+        }
+        DriverState::DISCONNECTED if !self.config().reconnect.enabled => {
           let message = self.metrics.lock().lastError.clone().unwrap_or_else(|| "disconnected".to_string());
           return Err(Error::from_reason(message));
         }
         _ => {}
       }
-      self.notify_state.notified().await;
+      notified.await;
     }
   }
 
   fn set_state(&self, state: DriverState) {
-    let mut guard = self.state.lock();
-    *guard = state;
+    let state = match state {
+      DriverState::CONNECTED if self.paused.load(Ordering::Relaxed) => DriverState::PAUSED,
+      state => state,
+    };
+    let previous = std::mem::replace(&mut *self.state.lock(), state);
+    // Pausing and resuming an open connection isn't a new one.
+    let connected = match state {
+      DriverState::CONNECTED => previous != DriverState::PAUSED,
+      DriverState::PAUSED => !matches!(previous, DriverState::CONNECTED | DriverState::PAUSED),
+      _ => false,
+    };
+    if connected {
+      self.connected_seq.fetch_add(1, Ordering::Relaxed);
+    }
+    if state == DriverState::CONNECTED && previous != DriverState::CONNECTED {
+      self.stale.lock().start(Instant::now());
+    }
+    if state == DriverState::STOPPED {
+      if self.stale.lock().reset() {
+        self.emit_alarm(AlarmEvent::new("staleData", false, "driver stopped".to_string()));
+      }
+      let cleared = self.alarm_rules.lock().clear("driver stopped");
+      for event in cleared {
+        self.emit_alarm(event);
+      }
+    }
     self.notify_state.notify_waiters();
+    if previous != state {
+      self.stats.lock().record_transition(previous);
+    }
+    if previous != state && !self.state_events.is_empty() {
+      self.state_events.emit(StateChangeEvent {
+        previous,
+        state,
+        ts: Utc::now().to_rfc3339_opts(SecondsFormat::Millis, true),
+        lastError: self.metrics.lock().lastError.clone(),
+      });
+    }
   }
 
-  async fn wait_for_sample(&self) -> Result<()> {
-    let timeout_ms = (self.config.emit_interval_ms * 2).max(500);
+  /// Raises `staleData` once the connection has been up `window` without a valid sample; returns when to look again,
+  /// if the window is running.
+  fn check_stale(&self, window: Duration) -> Option<Instant> {
+    if *self.state.lock() != DriverState::CONNECTED {
+      return None;
+    }
+    match self.stale.lock().check(window, Instant::now()) {
+      Staleness::Idle => return None,
+      Staleness::Fresh { due } => return Some(due),
+      Staleness::Stale => {}
+    }
+    let window_ms = window.as_millis() as u64;
+    warn!(machine_id = %self.machine_id, window_ms, "no valid sample within staleDataAlarmMs");
+    self.emit_alarm(AlarmEvent::new("staleData", true, format!("no valid sample for {} ms", window_ms)));
+    None
+  }
+
+  /// Reports a raised or cleared alarm, keeping `activeAlarms` in step.
+  fn emit_alarm(&self, event: AlarmEvent) {
+    {
+      let mut active = self.active_alarms.lock();
+      active.retain(|raised| raised.alarm != event.alarm);
+      if event.active {
+        active.push(event.clone());
+      }
+    }
+    self.alarm_events.emit(event);
+  }
+
+  /// Waits for a sample, one numbered past `after` if given; gives up with "no telemetry yet" after `limit`, or with
+  /// `None` only once the driver stops.
+  async fn wait_for_sample(&self, limit: Option<Duration>, after: Option<u64>) -> Result<()> {
     loop {
       if self.stop_flag.load(Ordering::Relaxed) {
         return Err(Error::from_reason("driver stopped"));
       }
-      if self.latest_sample.lock().is_some() {
-        return Ok(());
+      // While paused, wait for `resume()` (or a stop) without timing out.
+      let resumed = self.notify_state.notified();
+      if self.paused.load(Ordering::Relaxed) {
+        resumed.await;
+        continue;
       }
       let notified = self.notify_sample.notified();
-      match tokio::time::timeout(Duration::from_millis(timeout_ms), notified).await {
+      let sampled = self.latest_sample.lock().is_some();
+      if sampled && after.is_none_or(|after| self.sample_seq.load(Ordering::Relaxed) > after) {
+        return Ok(());
+      }
+      let Some(limit) = limit else {
+        notified.await;
+        continue;
+      };
+      match tokio::time::timeout(limit, notified).await {
         Ok(_) => continue,
+        Err(_) if sampled => return Err(Error::from_reason("no new telemetry")),
         Err(_) => return Err(Error::from_reason("no telemetry yet")),
       }
     }
   }
 
-  async fn read_telemetry(&self) -> Result<TelemetryPoint> {
-    self.wait_for_sample().await?;
-    let sample = {
-      self.latest_sample
-        .lock()
-        .clone()
-        .ok_or_else(|| Error::from_reason("no telemetry yet"))?
-    };
+  /// How long `readTelemetry()` waits for the first sample per `readWait`; `None` to wait until the driver stops.
+  fn read_limit(&self) -> Option<Duration> {
+    let config = self.config();
+    if config.read_wait.mode == ReadWaitMode::WaitForever {
+      return None;
+    }
+    let resample_ms = config.resample.as_ref().map_or(0, |resample| resample.interval_ms);
+    let interval_ms = resample_ms.max(config.pipeline.emit_interval_ms);
+    Some(Duration::from_millis(config.read_wait.timeout_ms.unwrap_or((interval_ms * 2).max(500))))
+  }
 
-    let elapsed_seconds = {
-      let mut start_ts = self.start_ts.lock();
-      let base = start_ts.get_or_insert(sample.ts);
-      let delta_ms = sample
-        .ts
-        .signed_duration_since(*base)
-        .num_milliseconds()
-        .max(0) as f64;
-      delta_ms / 1000.0
+  /// The latest sample, taking it under `readMode: "consume"` (then waiting for one not taken yet) unless `peek`.
+  /// Only taken samples count in `telemetryEmitted`.
+  async fn read_latest(&self, limit: Option<Duration>, peek: bool) -> Result<TelemetryPoint> {
+    let consume = !peek && self.config().read_mode == ReadMode::Consume;
+    loop {
+      let after = consume.then(|| self.read_seq.load(Ordering::Relaxed));
+      self.wait_for_sample(limit, after).await?;
+      let (sample, seq) = {
+        let latest = self.latest_sample.lock();
+        (latest.clone().ok_or_else(|| Error::from_reason("no telemetry yet"))?, self.sample_seq.load(Ordering::Relaxed))
+      };
+      // A concurrent read took this sample first; wait for the next.
+      if let Some(after) = after {
+        if self.read_seq.compare_exchange(after, seq, Ordering::Relaxed, Ordering::Relaxed).is_err() {
+          continue;
+        }
+      }
+      return Ok(if peek { self.build_point(sample) } else { self.telemetry_point(sample) });
+    }
+  }
+
+  /// The latest sample, waiting for the first one per `readWait`, or for `timeout_ms` instead when given.
+  async fn read_telemetry(&self, timeout_ms: Option<u32>, cancel: Option<Cancel>) -> Result<TelemetryPoint> {
+    let limit = if timeout_ms.is_some() { None } else { self.read_limit() };
+    cancel::bounded("readTelemetry", self.read_latest(limit, false), timeout_ms, cancel).await
+  }
+
+  /// The latest sample per `readWait`, without taking it under `readMode: "consume"` or counting it.
+  async fn peek_telemetry(&self) -> Result<TelemetryPoint> {
+    self.read_latest(self.read_limit(), true).await
+  }
+
+  /// Like `read_telemetry`, but resolves with `None` when no sample arrives within `timeout_ms`.
+  async fn read_telemetry_with_timeout(
+    &self,
+    timeout_ms: u32,
+    cancel: Option<Cancel>,
+  ) -> Result<Option<TelemetryPoint>> {
+    let read = async {
+      match tokio::time::timeout(Duration::from_millis(timeout_ms.into()), self.read_latest(None, false)).await {
+        Ok(point) => point.map(Some),
+        Err(_) => Ok(None),
+      }
     };
+    cancel::bounded("readTelemetry", read, None, cancel).await
+  }
 
+  fn telemetry_point(&self, sample: RawTelemetrySample) -> TelemetryPoint {
     {
       let mut metrics = self.metrics.lock();
       metrics.telemetryEmitted = metrics.telemetryEmitted.saturating_add(1);
     }
+    self.build_point(sample)
+  }
 
-    Ok(TelemetryPoint {
+  fn build_point(&self, sample: RawTelemetrySample) -> TelemetryPoint {
+    let baseline = *self.start_ts.lock().get_or_insert_with(|| ElapsedBaseline::of(&sample));
+    let (device_elapsed, host_elapsed) = baseline.elapsed(&sample);
+
+    let age_ms = Utc::now().signed_duration_since(sample.received_at).num_milliseconds().max(0) as f64;
+    let config = self.config();
+    let host_clock = config.clock.elapsed == ElapsedClock::Host;
+    let power_field = config.pipeline.power_field;
+    let quality = channels::point_quality(&sample, power_field);
+    let (gas_pct, power_pct) = power_field.split(sample.power_pct);
+    TelemetryPoint {
       ts: sample.ts.to_rfc3339_opts(SecondsFormat::Millis, true),
       machineId: self.machine_id.clone(),
-      elapsedSeconds: elapsed_seconds,
+      elapsedSeconds: host_elapsed.filter(|_| host_clock).unwrap_or(device_elapsed),
+      deviceElapsedSeconds: host_clock.then_some(device_elapsed),
+      hostElapsedSeconds: host_elapsed.filter(|_| host_clock),
       btC: sample.bt_c,
       etC: sample.et_c,
-      gasPct: sample.power_pct,
+      gasPct: gas_pct,
+      powerPct: power_pct,
       fanPct: sample.fan_pct,
       drumRpm: sample.drum_rpm,
+      ambientC: sample.ambient_c,
+      humidityPct: sample.humidity_pct,
+      pressureHpa: sample.pressure_hpa,
+      exhaustC: sample.exhaust_c,
+      inletC: sample.inlet_c,
+      drumPressurePa: sample.drum_pressure_pa,
+      airflowPct: sample.airflow_pct,
+      energyKwh: config.energy.as_ref().map(|_| self.energy.lock().kwh()),
       extras: sample.extras,
-    })
+      probes: (!sample.probes.is_empty()).then_some(sample.probes),
+      phases: self.phases.lock().progress(sample.ts),
+      sessionId: self.session.lock().as_ref().map(|session| session.sessionId.clone()),
+      sourceReceivedAt: Some(sample.received_at.to_rfc3339_opts(SecondsFormat::Millis, true)),
+      ageMs: Some(age_ms),
+      quality: Some(quality),
+    }
+  }
+
+  fn detected_format(&self) -> Option<DetectedFormat> {
+    if let Some(detected) = self.parser.lock().detected().cloned() {
+      return Some(detected);
+    }
+    self.peers.lock().iter().find_map(|peer| peer.detected_format())
   }
 
   fn get_status(&self) -> DriverStatus {
-    DriverStatus { state: *self.state.lock(), metrics: self.metrics.lock().clone() }
+    let mut metrics = self.metrics.lock().clone();
+    let (lines_per_second, sample_rate) = {
+      let mut stats = self.stats.lock();
+      (stats.lines_per_second(), stats.sample_rate())
+    };
+    metrics.linesPerSecond = lines_per_second;
+    metrics.influxPointsWritten = self.influx_stats.written.load(Ordering::Relaxed);
+    metrics.influxWriteErrors = self.influx_stats.errors.load(Ordering::Relaxed);
+    metrics.kafkaMessagesDelivered = self.kafka_stats.written.load(Ordering::Relaxed);
+    metrics.kafkaDeliveryErrors = self.kafka_stats.errors.load(Ordering::Relaxed);
+    metrics.bytesSent = self.commands.bytes_sent();
+    // Before the literal below: its `peers` guard lives until the end of the statement.
+    let detected_format = self.detected_format().as_ref().map(DetectedFormat::status);
+    DriverStatus {
+      state: *self.state.lock(),
+      metrics,
+      clockSkewMs: self.clock.lock().skew_ms(),
+      session: self.session.lock().clone(),
+      activeEndpoint: self.active_endpoint.lock().as_ref().map(Endpoint::to_string),
+      peers: self.peers.lock().iter().map(|peer| peer.status()).collect(),
+      detectedFormat: detected_format,
+      sampleRate: sample_rate,
+      staleDataAlarm: self.stale.lock().raised(),
+      activeAlarms: self.active_alarms.lock().clone(),
+    }
+  }
+
+  fn get_metrics_detailed(&self) -> DetailedMetrics {
+    let status = self.get_status();
+    self.stats.lock().snapshot(status.state, status.metrics)
+  }
+
+  fn get_recent_errors(&self) -> Vec<RecentParseError> {
+    self.recent_errors.lock().snapshot()
+  }
+
+  /// Holds back samples while keeping the connection (and commands) up; see `DriverState::PAUSED`.
+  fn pause(&self) {
+    self.paused.store(true, Ordering::Relaxed);
+    *self.latest_sample.lock() = None;
+    self.downsampler.lock().reset();
+    self.gap_filler.lock().reset();
+    self.resampler.lock().reset();
+    self.change_filter.lock().reset();
+    if *self.state.lock() == DriverState::CONNECTED {
+      self.set_state(DriverState::PAUSED);
+    }
+  }
+
+  fn resume(&self) {
+    self.paused.store(false, Ordering::Relaxed);
+    if *self.state.lock() == DriverState::PAUSED {
+      self.set_state(DriverState::CONNECTED);
+    }
+    // Readers parked in `wait_for_sample` re-check the flag.
+    self.notify_state.notify_waiters();
   }
 
+  /// Clears what a driver accumulates (counters, parser, dedupe and downsampling state, backoff, the elapsed
+  /// baseline, the capture) and, unless it was never connected or is disconnected, drops the connection and starts a
+  /// fresh one.
+  async fn reset(self: &Arc<Self>) {
+    let handle = self.handle.lock().take();
+    let restart = handle.is_some();
+    if let Some(handle) = handle {
+      handle.abort();
+      let _ = handle.await;
+      if matches!(*self.state.lock(), DriverState::CONNECTED | DriverState::PAUSED) {
+        let endpoint = self.active_endpoint.lock().as_ref().map(Endpoint::to_string);
+        self.log_connection(ConnectionEvent::new(ConnectionEventKind::Disconnected, endpoint).with_reason("reset"));
+      }
+    }
+    self.commands.detach().await;
+    self.peers.lock().clear();
+    *self.listen_port.lock() = None;
+
+    // A fresh parser also forgets a detected format and a learned CSV header.
+    *self.parser.lock() = TcpLineParser::new(self.config().parser());
+    self.reset_connection_state();
+    self.reset_roast_events();
+    *self.start_ts.lock() = None;
+    self.energy.lock().reset();
+    *self.metrics.lock() = DriverMetrics::default();
+    *self.stats.lock() = DriverStats::new();
+    let session_id = self.session.lock().as_ref().map(|session| session.sessionId.clone());
+    *self.capture.lock() = SessionCapture::new(session_id);
+    self.commands.reset_sent();
+    for stats in [&self.influx_stats, &self.kafka_stats] {
+      stats.written.store(0, Ordering::Relaxed);
+      stats.errors.store(0, Ordering::Relaxed);
+    }
+    self.backoff.lock().reset();
+    info!(machine_id = %self.machine_id, restart, "driver reset");
+    if restart {
+      self.ensure_loop();
+    }
+  }
+
+  /// Asks the loop to stop after the line it is reading, writes out what the sinks and the downsampler hold, and
+  /// aborts whatever is still running after `shutdownTimeoutMs`. With `0` the loop is aborted at once and the sinks
+  /// are left to write out in the background.
   async fn disconnect(&self) {
+    self.paused.store(false, Ordering::Relaxed);
     self.stop_flag.store(true, Ordering::Relaxed);
     self.set_state(DriverState::STOPPED);
     self.notify_sample.notify_waiters();
-    if let Some(handle) = self.handle.lock().take() {
-      handle.abort();
+    let timeout_ms = self.config().shutdown_timeout_ms;
+    let deadline = tokio::time::Instant::now() + Duration::from_millis(timeout_ms);
+    let handle = self.handle.lock().take();
+    if let Some(mut handle) = handle {
+      let stopped = timeout_ms > 0 && tokio::time::timeout_at(deadline, &mut handle).await.is_ok();
+      if !stopped {
+        if timeout_ms > 0 {
+          warn!(machine_id = %self.machine_id, timeout_ms, "loop still running at shutdownTimeoutMs; aborting");
+        }
+        handle.abort();
+      }
+      self.log_connection(ConnectionEvent::new(ConnectionEventKind::Stopped, None));
     }
+    self.flush_downsample();
+    self.flush_sinks(Some(deadline).filter(|_| timeout_ms > 0)).await;
+    // Aborting a listener drops its connections without letting them deregister.
+    self.peers.lock().clear();
+    *self.listen_port.lock() = None;
+    self.commands.detach().await;
   }
 }
 
 #[napi]
 pub struct TcpLineDriverNative {
   inner: Arc<DriverInner>,
+  /// Set by `connect()` until `disconnect()`.
+  attached: AtomicBool,
 }
 
 #[napi]
 impl TcpLineDriverNative {
+  /// With `onDuplicate: "share"` and another driver for the same `host:port` alive, the new one is a handle on that
+  /// driver, which must have the same `machine_id` and parsing settings; the rest of its own config is ignored.
   #[napi(constructor)]
   pub fn new(config_json: String, machine_id: String) -> Result<Self> {
-    let config: TcpLineDriverConfig = serde_json::from_str(&config_json)
-      .map_err(|err| Error::from_reason(format!("invalid config: {}", err)))?;
-    Ok(Self { inner: DriverInner::new(config, machine_id) })
+    let config = validation::parse_config(&config_json)?;
+    let shared = match config.endpoint_key() {
+      Some(endpoint) if config.on_duplicate == DuplicatePolicy::Share => registry::find(&endpoint),
+      _ => None,
+    };
+    let inner = match shared {
+      Some(inner) => {
+        inner.check_share(&config, &machine_id)?;
+        inner
+      }
+      None => {
+        let inner = DriverInner::open(config, machine_id)?;
+        *inner.config_json.lock() = serde_json::from_str(&config_json).ok();
+        inner
+      }
+    };
+    Ok(Self { inner, attached: AtomicBool::new(false) })
+  }
+
+  /// Resolves once connected. `timeoutMs` and `cancel` only bound the wait: the loop keeps connecting (per the
+  /// reconnect policy) until `disconnect()`.
+  #[napi(ts_args_type = "timeoutMs?: number, cancel?: CancelToken")]
+  pub async fn connect(&self, timeout_ms: Option<u32>, cancel: Option<Cancel>) -> Result<()> {
+    self.inner.attach(&self.attached);
+    cancel::bounded("connect", self.inner.connect(), timeout_ms, cancel).await
+  }
+
+  /// Stops emitting samples without closing the connection, e.g. during a cleaning cycle: the state becomes PAUSED,
+  /// lines are still read and counted, and `readTelemetry` waits until `resume()`.
+  #[napi]
+  pub fn pause(&self) {
+    self.inner.pause();
+  }
+
+  /// Emits samples again, starting with the next line parsed.
+  #[napi]
+  pub fn resume(&self) {
+    self.inner.resume();
+  }
+
+  /// Starts over without constructing a new driver: metrics, parser, dedupe state, backoff and the elapsed baseline
+  /// are cleared, and a started driver reconnects at once. Resolves as soon as the new attempt has been started.
+  #[napi]
+  pub async fn reset(&self) {
+    self.inner.reset().await;
+  }
+
+  /// Port of the `grpc` server once `connect()` has started it, else `null`.
+  #[napi]
+  pub fn grpc_port(&self) -> Option<u32> {
+    self.inner.grpc_port()
   }
 
+  /// The last 100 connection events (attempts, connects, failures, drops, stops), oldest first.
   #[napi]
-  pub async fn connect(&self) -> Result<()> {
-    self.inner.ensure_loop();
-    self.inner.wait_for_connected().await
+  pub fn get_connection_history(&self) -> Vec<ConnectionEvent> {
+    self.inner.connection_history.lock().snapshot()
   }
 
+  /// Port bound in `listen` mode once `connect()` has started listening, else `null`.
   #[napi]
-  pub async fn read_telemetry(&self) -> Result<TelemetryPoint> {
-    self.inner.read_telemetry().await
+  pub fn listen_port(&self) -> Option<u32> {
+    self.inner.listen_port()
+  }
+
+  #[napi(ts_args_type = "timeoutMs?: number, cancel?: CancelToken")]
+  pub async fn read_telemetry(&self, timeout_ms: Option<u32>, cancel: Option<Cancel>) -> Result<TelemetryPoint> {
+    self.inner.read_telemetry(timeout_ms, cancel).await
+  }
+
+  /// The latest sample, or `null` when none arrives within `timeoutMs`; unlike `readTelemetry` it never times out
+  /// with an error.
+  #[napi(ts_args_type = "timeoutMs: number, cancel?: CancelToken")]
+  pub async fn read_telemetry_with_timeout(
+    &self,
+    timeout_ms: u32,
+    cancel: Option<Cancel>,
+  ) -> Result<Option<TelemetryPoint>> {
+    self.inner.read_telemetry_with_timeout(timeout_ms, cancel).await
   }
 
+  /// The latest sample, as `readTelemetry` would return it under `readMode: "latest"`, without taking it or counting
+  /// it in `telemetryEmitted`.
+  #[napi]
+  pub async fn peek_telemetry(&self) -> Result<TelemetryPoint> {
+    self.inner.peek_telemetry().await
+  }
+
+  /// Stops the connection, unless another handle sharing it (`onDuplicate: "share"`) is still connected.
   #[napi]
   pub async fn disconnect(&self) -> Result<()> {
-    self.inner.disconnect().await;
+    if self.inner.release(&self.attached) {
+      self.inner.disconnect().await;
+    }
     Ok(())
   }
 
@@ -625,5 +2558,192 @@ impl TcpLineDriverNative {
   pub fn get_status(&self) -> Result<DriverStatus> {
     Ok(self.inner.get_status())
   }
+
+  /// Rates, parse latency histogram, inter-sample interval percentiles and time spent per state.
+  #[napi]
+  pub fn get_metrics_detailed(&self) -> DetailedMetrics {
+    self.inner.get_metrics_detailed()
+  }
+
+  /// The last 50 parse failures, oldest first, with the offending line cut to 256 characters.
+  #[napi]
+  pub fn get_recent_errors(&self) -> Vec<RecentParseError> {
+    self.inner.get_recent_errors()
+  }
+
+  /// Async-iterator-shaped stream of samples; see `TelemetryStream`.
+  #[napi]
+  pub fn telemetry_stream(&self) -> TelemetryStream {
+    TelemetryStream::new(Arc::clone(&self.inner))
+  }
+
+  /// A handle of its own onto every point published from now on; see `TelemetrySubscription`.
+  #[napi]
+  pub fn subscribe(&self) -> TelemetrySubscription {
+    TelemetrySubscription::new(Arc::clone(&self.inner))
+  }
+
+  /// Takes up to `maxSamples` (default all) queued samples, oldest first; empty unless `queue` is configured.
+  #[napi]
+  pub fn drain_samples(&self, max_samples: Option<u32>) -> Vec<TelemetryPoint> {
+    self.inner.drain_samples(max_samples)
+  }
+
+  /// Takes up to `maxSamples` (default all) spooled points, oldest first, and acknowledges them; requires `spool`.
+  /// Delivery is at least once: the acknowledgement is synced to disk before this returns, and a crash before that
+  /// returns the same points again after the restart, so consumers should tolerate duplicates (same `ts`). Points are
+  /// the caller's once this returns. Appended points are synced on `disconnect()`, so a power loss (not a process
+  /// crash) can lose the ones spooled since.
+  #[napi]
+  pub fn drain_persisted(&self, max_samples: Option<u32>) -> Result<Vec<TelemetryPoint>> {
+    self.inner.drain_persisted(max_samples)
+  }
+
+  /// Per-channel min/max/mean/count over the samples of the last `windowSeconds` (by sample timestamp).
+  #[napi]
+  pub fn read_aggregate(&self, window_seconds: f64) -> Result<TelemetryAggregate> {
+    self.inner.read_aggregate(window_seconds)
+  }
+
+  /// The current roast's phases from CHARGE, with their durations, end temperatures and mean RoR; `null` before
+  /// CHARGE. Needs `roastEvents`.
+  #[napi]
+  pub fn get_session_summary(&self) -> Option<SessionSummary> {
+    self.inner.session_summary()
+  }
+
+  /// Collects every emitted point from now on and writes them to `path` on `stopLogging`. `format` is `csv` or
+  /// `parquet` (default: from the extension, else csv); the header lists the point fields followed by one column per
+  /// extra.
+  #[napi]
+  pub fn start_logging(&self, path: String, format: Option<String>) -> Result<()> {
+    self.inner.start_logging(path, format)
+  }
+
+  /// Writes the log begun by `startLogging` and returns what was written (`null` when not logging).
+  #[napi]
+  pub fn stop_logging(&self) -> Result<Option<SessionLogSummary>> {
+    self.inner.stop_logging()
+  }
+
+  /// Writes what `capture` kept since the driver started or the latest `startSession` to the tar archive `path`: the
+  /// raw lines, the config with secrets redacted, a replay config for the lines, the emitted points and the metrics
+  /// timeline. Needs `capture`.
+  #[napi]
+  pub fn export_session(&self, path: String) -> Result<SessionBundleSummary> {
+    self.inner.export_session(&path)
+  }
+
+  /// Writes one command line (e.g. `SET FAN 70`) to the device. Resolves with the ack line when
+  /// `commands.ackPrefix` is configured, otherwise with `null` once the line is written.
+  #[napi]
+  pub async fn send_command(&self, line: String) -> Result<Option<String>> {
+    self.inner.send_command(&line).await
+  }
+
+  /// Sends the `commands.templates.powerPct` command with `{value}` filled in (0–100).
+  #[napi]
+  pub async fn set_power_pct(&self, value: f64) -> Result<Option<String>> {
+    self.inner.send_setpoint(Setpoint::PowerPct, value).await
+  }
+
+  /// Sends the `commands.templates.fanPct` command with `{value}` filled in (0–100).
+  #[napi]
+  pub async fn set_fan_pct(&self, value: f64) -> Result<Option<String>> {
+    self.inner.send_setpoint(Setpoint::FanPct, value).await
+  }
+
+  /// Sends the `commands.templates.drumRpm` command with `{value}` filled in (non-negative).
+  #[napi]
+  pub async fn set_drum_rpm(&self, value: f64) -> Result<Option<String>> {
+    self.inner.send_setpoint(Setpoint::DrumRpm, value).await
+  }
+
+  /// Starts appending raw lines to an NDJSON capture. `path` overrides `record.path` from the config;
+  /// rotation limits always come from the config (or their defaults).
+  #[napi]
+  pub fn start_recording(&self, path: Option<String>) -> Result<()> {
+    let record = match (self.inner.config().record.clone(), path) {
+      (Some(record), None) => record,
+      (Some(record), Some(path)) => RecordConfig { path, ..record },
+      (None, Some(path)) => RecordConfig::with_path(path),
+      (None, None) => return Err(Error::from_reason("no recording path configured")),
+    };
+    self
+      .inner
+      .start_recording(record)
+      .map_err(|err| Error::from_reason(format!("failed to start recording: {}", err)))
+  }
+
+  #[napi]
+  pub fn stop_recording(&self) -> Result<()> {
+    self
+      .inner
+      .stop_recording()
+      .map_err(|err| Error::from_reason(format!("failed to stop recording: {}", err)))
+  }
+
+  /// Resets the `elapsedSeconds` baseline and `energyKwh` for back-to-back roasts and tags points with `sessionId`
+  /// (generated from the machine id and start time when omitted). A running session is replaced.
+  #[napi]
+  pub fn start_session(&self, session_id: Option<String>) -> SessionInfo {
+    self.inner.start_session(session_id)
+  }
+
+  /// Ends the current session and returns it (`null` when none was active).
+  #[napi]
+  pub fn end_session(&self) -> Option<SessionInfo> {
+    self.inner.end_session()
+  }
+
+  /// Applies a new config without dropping the connection unless host, port or other transport settings changed.
+  /// Resolves with `true` when the connection was restarted. `commands` can't be changed on a live driver.
+  #[napi]
+  pub async fn update_config(&self, config_json: String) -> Result<bool> {
+    let config = validation::parse_config(&config_json)?;
+    let restarted = self.inner.update_config(config).await?;
+    *self.inner.config_json.lock() = serde_json::from_str(&config_json).ok();
+    Ok(restarted)
+  }
+
+  /// Registers a callback invoked with `{ previous, state, ts, lastError }` on every state transition.
+  #[napi(ts_args_type = "callback: (event: StateChangeEvent) => void")]
+  pub fn on_state_change(&self, env: Env, callback: JsFunction) -> Result<()> {
+    self.inner.state_events.subscribe(&env, callback)
+  }
+
+  /// Registers a callback invoked with `{ code, message, ts, rawLine? }` for connection failures, socket closes,
+  /// timeouts and parse errors.
+  #[napi(ts_args_type = "callback: (error: DriverError) => void")]
+  pub fn on_error(&self, env: Env, callback: JsFunction) -> Result<()> {
+    self.inner.error_events.subscribe(&env, callback)
+  }
+
+  /// Registers a callback for detected roast events (requires `roastEvents` in the config).
+  #[napi(ts_args_type = "callback: (event: RoastEvent) => void")]
+  pub fn on_event(&self, env: Env, callback: JsFunction) -> Result<()> {
+    self.inner.roast_events.subscribe(&env, callback)
+  }
+
+  /// Registers a callback invoked with `{ ts, expected, received, missing, peer? }` whenever the `sequence` counter
+  /// skips frames.
+  #[napi(ts_args_type = "callback: (event: SequenceGapEvent) => void")]
+  pub fn on_gap(&self, env: Env, callback: JsFunction) -> Result<()> {
+    self.inner.gap_events.subscribe(&env, callback)
+  }
+
+  /// Registers a callback invoked with `{ ts, alarm, active, message, channel, value, threshold }` when an alarm
+  /// (`staleData` or an `alarms` rule) is raised or clears.
+  #[napi(ts_args_type = "callback: (event: AlarmEvent) => void")]
+  pub fn on_alarm(&self, env: Env, callback: JsFunction) -> Result<()> {
+    self.inner.alarm_events.subscribe(&env, callback)
+  }
+
+  /// Registers a callback invoked with `{ ts, line, outcome, error?, peer?, dropped }` for every frame received, at
+  /// most `maxPerSecond` (20) a second across all subscribers; `dropped` counts the lines left out in between.
+  #[napi(ts_args_type = "callback: (event: RawLineEvent) => void, maxPerSecond?: number")]
+  pub fn on_raw_line(&self, env: Env, callback: JsFunction, max_per_second: Option<u32>) -> Result<()> {
+    self.inner.raw_lines.subscribe(&env, callback, max_per_second)
+  }
 }
 
@@ -0,0 +1,225 @@
+use std::net::SocketAddr;
+use std::sync::atomic::Ordering;
+use std::sync::Arc;
+use std::time::Duration;
+
+use chrono::{SecondsFormat, Utc};
+use napi_derive::napi;
+use parking_lot::Mutex;
+use schemars::JsonSchema;
+use serde::Deserialize;
+use tokio::net::{TcpListener, TcpStream};
+use tokio::task::JoinSet;
+use tokio::time::sleep;
+use tracing::{info, warn};
+
+use crate::commands::Heartbeat;
+use crate::connection_history::{ConnectionEvent, ConnectionEventKind};
+use crate::detect::DetectedFormat;
+use crate::events::{DriverError, DriverErrorCode};
+use crate::framing::FrameReader;
+use crate::sequence::SequenceTracker;
+use crate::{DriverInner, DriverState, ParseError, TcpLineDriverConfig, TcpLineParser};
+
+/// Pause after a failed `accept` (e.g. out of file descriptors) before trying again.
+const ACCEPT_RETRY_MS: u64 = 100;
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Deserialize, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub(crate) enum ConnectionMode {
+  /// Dial `host:port` (and any `endpoints`).
+  #[default]
+  Connect,
+  /// Bind `host:port` and wait for devices to dial in, for roasters that connect out to a configured address.
+  Listen,
+}
+
+/// One device connected to a listen-mode driver, as reported by `getStatus().peers`.
+#[derive(Debug, Clone)]
+#[napi(object)]
+pub struct PeerStatus {
+  /// Remote `ip:port` of the device.
+  pub address: String,
+  pub connectedAt: String,
+  pub linesReceived: i64,
+  pub linesParsed: i64,
+  pub parseErrors: i64,
+  pub bytesReceived: i64,
+  pub lastLineAt: Option<String>,
+}
+
+/// A listen-mode connection. It has its own parser so a CSV header or a partly assembled record of one device never
+/// leaks into another's.
+pub(crate) struct Peer {
+  status: Mutex<PeerStatus>,
+  parser: Mutex<TcpLineParser>,
+  sequence: Mutex<SequenceTracker>,
+  /// The config `parser` was built from; a newer one from `updateConfig` is applied before the next line.
+  config: Mutex<Arc<TcpLineDriverConfig>>,
+}
+
+impl Peer {
+  fn new(address: SocketAddr, config: Arc<TcpLineDriverConfig>) -> Self {
+    Self {
+      status: Mutex::new(PeerStatus {
+        address: address.to_string(),
+        connectedAt: Utc::now().to_rfc3339_opts(SecondsFormat::Millis, true),
+        linesReceived: 0,
+        linesParsed: 0,
+        parseErrors: 0,
+        bytesReceived: 0,
+        lastLineAt: None,
+      }),
+      parser: Mutex::new(TcpLineParser::new(config.parser())),
+      sequence: Mutex::new(SequenceTracker::default()),
+      config: Mutex::new(config),
+    }
+  }
+
+  pub(crate) fn address(&self) -> String {
+    self.status.lock().address.clone()
+  }
+
+  pub(crate) fn status(&self) -> PeerStatus {
+    self.status.lock().clone()
+  }
+
+  pub(crate) fn detected_format(&self) -> Option<DetectedFormat> {
+    self.parser.lock().detected().cloned()
+  }
+
+  pub(crate) fn parser(&self, latest: &Arc<TcpLineDriverConfig>) -> &Mutex<TcpLineParser> {
+    let mut config = self.config.lock();
+    if !Arc::ptr_eq(&config, latest) {
+      self.parser.lock().update_config(latest.parser());
+      *config = Arc::clone(latest);
+    }
+    &self.parser
+  }
+
+  pub(crate) fn sequence(&self) -> &Mutex<SequenceTracker> {
+    &self.sequence
+  }
+
+  /// Counts one frame and its outcome: a sample (`Ok(true)`), a line that only fed the parser, or a parse error.
+  pub(crate) fn count_line(&self, bytes: usize, outcome: std::result::Result<bool, &ParseError>) {
+    let mut status = self.status.lock();
+    status.linesReceived = status.linesReceived.saturating_add(1);
+    status.bytesReceived = status.bytesReceived.saturating_add(bytes as i64);
+    status.lastLineAt = Some(Utc::now().to_rfc3339_opts(SecondsFormat::Millis, true));
+    match outcome {
+      Ok(true) => status.linesParsed = status.linesParsed.saturating_add(1),
+      Ok(false) => {}
+      Err(_) => status.parseErrors = status.parseErrors.saturating_add(1),
+    }
+  }
+}
+
+impl DriverInner {
+  /// Listen-mode counterpart of `run_loop`: accepts up to `maxConnections` devices and runs each through the parse
+  /// pipeline. The driver is CONNECTED while at least one device is, and CONNECTING while it waits for one.
+  pub(crate) async fn run_listener(self: Arc<Self>) {
+    let config = self.config();
+    let listener = match TcpListener::bind((config.host.as_str(), config.port)).await {
+      Ok(listener) => listener,
+      Err(err) => {
+        let message = format!("listen failure: {}:{}: {}", config.host, config.port, err);
+        let endpoint = format!("{}:{}", config.host, config.port);
+        self.log_connection(
+          ConnectionEvent::new(ConnectionEventKind::ConnectFailed, Some(endpoint)).with_reason(&message),
+        );
+        self.handle_failure(DriverError::new(DriverErrorCode::ConnectFailed, message)).await;
+        self.set_state(DriverState::FAILED);
+        return;
+      }
+    };
+    let port = listener.local_addr().map(|addr| addr.port()).unwrap_or(config.port);
+    *self.listen_port.lock() = Some(port);
+    info!(machine_id = %self.machine_id, host = %config.host, port, "listening");
+
+    let mut peers = JoinSet::new();
+    loop {
+      tokio::select! {
+        accepted = listener.accept() => match accepted {
+          Ok((stream, address)) => {
+            let max_connections = self.config().max_connections as usize;
+            if peers.len() >= max_connections {
+              warn!(machine_id = %self.machine_id, peer = %address, max_connections, "refusing device connection");
+              continue;
+            }
+            if peers.is_empty() {
+              self.reset_connection_state();
+            }
+            peers.spawn(Arc::clone(&self).serve_peer(stream, address));
+          }
+          Err(err) => {
+            warn!(machine_id = %self.machine_id, error = %err, "accept failed");
+            sleep(Duration::from_millis(ACCEPT_RETRY_MS)).await;
+          }
+        },
+        Some(_) = peers.join_next(), if !peers.is_empty() => {
+          if peers.is_empty() && !self.stop_flag.load(Ordering::Relaxed) {
+            self.set_state(DriverState::CONNECTING);
+          }
+        }
+        () = self.wait_for_stop() => break,
+      }
+    }
+    // Each device finishes the line it is reading; `disconnect()` aborts the rest at `shutdownTimeoutMs`.
+    while peers.join_next().await.is_some() {}
+  }
+
+  async fn serve_peer(self: Arc<Self>, stream: TcpStream, address: SocketAddr) {
+    if let Err(err) = self.config().keepalive.apply(&stream) {
+      warn!(machine_id = %self.machine_id, peer = %address, error = %err, "keepalive setup failed");
+    }
+    let peer = Arc::new(Peer::new(address, self.config()));
+    let (read_half, write_half) = stream.into_split();
+    // Commands and heartbeats need a single device to talk to.
+    let exclusive = self.config().max_connections == 1;
+    if exclusive {
+      self.commands.attach(write_half).await;
+    }
+    let config = self.config();
+    let mut heartbeat = Heartbeat::spawn(config.heartbeat.as_ref().filter(|_| exclusive), Arc::clone(&self.commands));
+    let connected = {
+      let mut peers = self.peers.lock();
+      peers.push(Arc::clone(&peer));
+      peers.len()
+    };
+    info!(machine_id = %self.machine_id, peer = %address, peers = connected, "device connected");
+    self.log_connection(ConnectionEvent::new(ConnectionEventKind::Connected, Some(address.to_string())));
+    if connected == 1 {
+      self.metrics.lock().lastError = None;
+      self.set_state(DriverState::CONNECTED);
+    }
+
+    let mut reader = FrameReader::new(read_half, config.framing, &config.line_delimiter, config.max_line_bytes as usize)
+      .with_rate_limit(config.max_bytes_per_second);
+    let ended = self.read_frames(&mut reader, &mut heartbeat, Some(&peer)).await;
+    drop(heartbeat);
+    if exclusive {
+      self.commands.detach().await;
+    }
+    let remaining = {
+      let mut peers = self.peers.lock();
+      peers.retain(|other| !Arc::ptr_eq(other, &peer));
+      peers.len()
+    };
+    let Some(err) = ended else {
+      return;
+    };
+    self.log_connection(
+      ConnectionEvent::new(ConnectionEventKind::Disconnected, Some(address.to_string())).with_reason(&err.message),
+    );
+    let err = DriverError::new(err.code, format!("peer {}: {}", address, err.message));
+    if remaining == 0 {
+      // The last device left: report it like a dropped connection, then wait for the next one.
+      self.handle_failure(err).await;
+    } else {
+      warn!(machine_id = %self.machine_id, peer = %address, error = %err.message, "device disconnected");
+      self.metrics.lock().lastError = Some(err.message.clone());
+      self.error_events.emit(err);
+    }
+  }
+}
@@ -0,0 +1,133 @@
+use std::collections::HashMap;
+use std::fmt;
+use std::sync::atomic::{AtomicU8, Ordering};
+use std::sync::Once;
+
+use chrono::{SecondsFormat, Utc};
+use napi::bindgen_prelude::*;
+use napi::threadsafe_function::{ErrorStrategy, ThreadSafeCallContext, ThreadsafeFunction, ThreadsafeFunctionCallMode};
+use napi_derive::napi;
+use parking_lot::Mutex;
+use tracing::field::{Field, Visit};
+use tracing::{Event, Level, Metadata, Subscriber};
+use tracing_subscriber::layer::{Context, SubscriberExt};
+use tracing_subscriber::Layer;
+
+#[derive(Debug, Clone)]
+#[napi(object)]
+pub struct LogRecord {
+  pub level: String,
+  pub target: String,
+  pub message: String,
+  pub ts: String,
+  /// Structured fields attached to the event, e.g. `machine_id`, `attempt`, `delay_ms`, `line`.
+  pub fields: HashMap<String, String>,
+}
+
+/// `0` means off; otherwise the most verbose level forwarded (1 = error … 5 = trace).
+static MAX_LEVEL: AtomicU8 = AtomicU8::new(0);
+static CALLBACK: Mutex<Option<ThreadsafeFunction<LogRecord, ErrorStrategy::Fatal>>> = parking_lot::const_mutex(None);
+static INSTALL: Once = Once::new();
+
+fn level_rank(level: &Level) -> u8 {
+  match *level {
+    Level::ERROR => 1,
+    Level::WARN => 2,
+    Level::INFO => 3,
+    Level::DEBUG => 4,
+    Level::TRACE => 5,
+  }
+}
+
+fn parse_level(level: &str) -> Result<u8> {
+  match level.to_ascii_lowercase().as_str() {
+    "off" => Ok(0),
+    "error" => Ok(1),
+    "warn" => Ok(2),
+    "info" => Ok(3),
+    "debug" => Ok(4),
+    "trace" => Ok(5),
+    other => Err(Error::from_reason(format!("unknown log level: {}", other))),
+  }
+}
+
+/// Forwards driver log events (connect attempts, backoff delays, parse errors with the offending line, …) at
+/// `level` and above to `callback`. Replaces any previous callback; omit `callback` or pass `"off"` to stop.
+#[napi(
+  ts_args_type = "level: 'error' | 'warn' | 'info' | 'debug' | 'trace' | 'off', callback?: (record: LogRecord) => void"
+)]
+pub fn set_log_callback(env: Env, level: String, callback: Option<JsFunction>) -> Result<()> {
+  let rank = parse_level(&level)?;
+  let tsfn = match callback {
+    Some(callback) if rank > 0 => {
+      let mut tsfn: ThreadsafeFunction<LogRecord, ErrorStrategy::Fatal> =
+        callback.create_threadsafe_function(0, |ctx: ThreadSafeCallContext<LogRecord>| Ok(vec![ctx.value]))?;
+      tsfn.unref(&env)?;
+      Some(tsfn)
+    }
+    _ => None,
+  };
+
+  INSTALL.call_once(|| {
+    // A host that already installed a global subscriber keeps it; the bridge then stays silent.
+    let _ = tracing::subscriber::set_global_default(tracing_subscriber::registry().with(JsLogLayer));
+  });
+  MAX_LEVEL.store(if tsfn.is_some() { rank } else { 0 }, Ordering::Relaxed);
+  *CALLBACK.lock() = tsfn;
+  tracing::callsite::rebuild_interest_cache();
+  Ok(())
+}
+
+struct JsLogLayer;
+
+impl<S: Subscriber> Layer<S> for JsLogLayer {
+  fn enabled(&self, metadata: &Metadata<'_>, _ctx: Context<'_, S>) -> bool {
+    level_rank(metadata.level()) <= MAX_LEVEL.load(Ordering::Relaxed)
+  }
+
+  fn on_event(&self, event: &Event<'_>, _ctx: Context<'_, S>) {
+    let callback = CALLBACK.lock();
+    let Some(callback) = callback.as_ref() else {
+      return;
+    };
+    let mut visitor = FieldVisitor::default();
+    event.record(&mut visitor);
+    let metadata = event.metadata();
+    callback.call(
+      LogRecord {
+        level: metadata.level().as_str().to_ascii_lowercase(),
+        target: metadata.target().to_string(),
+        message: visitor.message,
+        ts: Utc::now().to_rfc3339_opts(SecondsFormat::Millis, true),
+        fields: visitor.fields,
+      },
+      ThreadsafeFunctionCallMode::NonBlocking,
+    );
+  }
+}
+
+#[derive(Default)]
+struct FieldVisitor {
+  message: String,
+  fields: HashMap<String, String>,
+}
+
+impl Visit for FieldVisitor {
+  fn record_str(&mut self, field: &Field, value: &str) {
+    self.insert(field, value.to_string());
+  }
+
+  fn record_debug(&mut self, field: &Field, value: &dyn fmt::Debug) {
+    self.insert(field, format!("{:?}", value));
+  }
+}
+
+impl FieldVisitor {
+  fn insert(&mut self, field: &Field, value: String) {
+    if field.name() == "message" {
+      self.message = value;
+    } else {
+      self.fields.insert(field.name().to_string(), value);
+    }
+  }
+}
@@ -0,0 +1,236 @@
+use std::collections::{HashMap, HashSet};
+use std::sync::atomic::Ordering;
+use std::sync::Arc;
+
+use napi::bindgen_prelude::*;
+use napi_derive::napi;
+use parking_lot::Mutex;
+use serde::Deserialize;
+use tokio::task::JoinHandle;
+
+use crate::events::Subscribers;
+use crate::grpc::{GrpcConfig, GrpcServer};
+use crate::session::SessionInfo;
+use crate::stream::TelemetryStream;
+use crate::validation;
+use crate::{DriverInner, DriverMetrics, DriverState, DriverStatus, TcpLineDriverConfig, TelemetryPoint};
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct MachineConfig {
+  machine_id: String,
+  connection: TcpLineDriverConfig,
+}
+
+/// The object form of the manager config; a bare array is shorthand for `{ machines }`.
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct ManagerConfig {
+  machines: Vec<MachineConfig>,
+  /// One gRPC server covering every machine, started with the first `connect` or `connectAll`.
+  #[serde(default)]
+  grpc: Option<GrpcConfig>,
+}
+
+#[derive(Debug, Clone)]
+#[napi(object)]
+pub struct MachineStatus {
+  pub machineId: String,
+  pub state: DriverState,
+  pub metrics: DriverMetrics,
+}
+
+/// Many TCP line drivers behind one object. Every machine runs as tasks on the addon's shared tokio runtime, so a
+/// 40-roaster floor costs 40 tasks rather than 40 driver objects with their own bookkeeping; `onTelemetry` merges
+/// all machines into one callback tagged with `machineId`.
+#[napi]
+pub struct DriverManagerNative {
+  machines: Vec<(String, Arc<DriverInner>)>,
+  telemetry_events: Arc<Subscribers<TelemetryPoint>>,
+  forwarders: Mutex<HashMap<String, JoinHandle<()>>>,
+  grpc_config: Option<GrpcConfig>,
+  grpc: Mutex<Option<GrpcServer>>,
+}
+
+impl DriverManagerNative {
+  fn machine(&self, machine_id: &str) -> Result<&Arc<DriverInner>> {
+    self
+      .machines
+      .iter()
+      .find(|(id, _)| id == machine_id)
+      .map(|(_, inner)| inner)
+      .ok_or_else(|| Error::from_reason(format!("unknown machine: {}", machine_id)))
+  }
+
+  fn ensure_grpc(&self) -> Result<()> {
+    let Some(config) = self.grpc_config.as_ref() else {
+      return Ok(());
+    };
+    let mut server = self.grpc.lock();
+    if server.is_none() {
+      let drivers = self.machines.iter().map(|(id, inner)| (id.clone(), Arc::downgrade(inner))).collect();
+      let started = GrpcServer::start(config, drivers)
+        .map_err(|err| Error::from_reason(format!("grpc bind {}:{} failed: {}", config.host, config.port, err)))?;
+      *server = Some(started);
+    }
+    Ok(())
+  }
+
+  /// Pumps one machine's paced sample stream into the shared callback; restarted on every `connect`.
+  fn forward(&self, machine_id: &str, inner: &Arc<DriverInner>) {
+    if self.telemetry_events.is_empty() {
+      return;
+    }
+    let mut forwarders = self.forwarders.lock();
+    if forwarders.get(machine_id).is_some_and(|handle| !handle.is_finished()) {
+      return;
+    }
+    let stream = TelemetryStream::new(Arc::clone(inner));
+    let events = Arc::clone(&self.telemetry_events);
+    let handle = tokio::spawn(async move {
+      while let Ok(result) = stream.next().await {
+        let Some(point) = result.value else {
+          break;
+        };
+        events.emit(point);
+      }
+    });
+    forwarders.insert(machine_id.to_string(), handle);
+  }
+}
+
+#[napi]
+impl DriverManagerNative {
+  /// `configs_json` is an array of `{ machineId, connection }`, `connection` being a regular TCP driver config, or
+  /// `{ machines: [...], grpc? }`.
+  #[napi(constructor)]
+  pub fn new(configs_json: String) -> Result<Self> {
+    let invalid = |err: serde_json::Error| Error::from_reason(format!("invalid config: {}", err));
+    let value: serde_json::Value = serde_json::from_str(&configs_json).map_err(invalid)?;
+    let ManagerConfig { machines: configs, grpc } = if value.is_array() {
+      ManagerConfig { machines: serde_json::from_value(value).map_err(invalid)?, grpc: None }
+    } else {
+      serde_json::from_value(value).map_err(invalid)?
+    };
+    let mut seen = HashSet::new();
+    let mut machines = Vec::with_capacity(configs.len());
+    for config in configs {
+      if !seen.insert(config.machine_id.clone()) {
+        return Err(Error::from_reason(format!("invalid config: duplicate machineId {}", config.machine_id)));
+      }
+      validation::check(&config.connection)
+        .map_err(|err| Error::from_reason(format!("{} (machine {})", err.reason, config.machine_id)))?;
+      let inner = DriverInner::open(config.connection, config.machine_id.clone())?;
+      machines.push((config.machine_id, inner));
+    }
+    Ok(Self {
+      machines,
+      telemetry_events: Arc::new(Subscribers::new()),
+      forwarders: Mutex::new(HashMap::new()),
+      grpc_config: grpc,
+      grpc: Mutex::new(None),
+    })
+  }
+
+  #[napi]
+  pub fn machine_ids(&self) -> Vec<String> {
+    self.machines.iter().map(|(id, _)| id.clone()).collect()
+  }
+
+  /// Port of the manager-wide `grpc` server once a connect has started it, else `null`.
+  #[napi]
+  pub fn grpc_port(&self) -> Option<u32> {
+    self.grpc.lock().as_ref().map(|server| server.port() as u32)
+  }
+
+  #[napi]
+  pub async fn connect(&self, machine_id: String) -> Result<()> {
+    let inner = self.machine(&machine_id)?;
+    self.ensure_grpc()?;
+    self.forward(&machine_id, inner);
+    inner.connect().await
+  }
+
+  /// Starts every machine. Resolves once all have connected at least once; rejects with the failed machines
+  /// otherwise (the others stay connected and the failed ones keep retrying per their reconnect policy).
+  #[napi]
+  pub async fn connect_all(&self) -> Result<()> {
+    self.ensure_grpc()?;
+    let mut started = Vec::with_capacity(self.machines.len());
+    for (id, inner) in self.machines.iter() {
+      started.push(inner.connected_seq.load(Ordering::Relaxed));
+      self.forward(id, inner);
+      inner.ensure_grpc()?;
+      inner.ensure_loop();
+    }
+    let mut failures = Vec::new();
+    for ((id, inner), since) in self.machines.iter().zip(started) {
+      if let Err(err) = inner.wait_for_connected(since).await {
+        failures.push(format!("{}: {}", id, err.reason));
+      }
+    }
+    if failures.is_empty() {
+      Ok(())
+    } else {
+      Err(Error::from_reason(format!("connect failed for {}", failures.join("; "))))
+    }
+  }
+
+  #[napi]
+  pub async fn disconnect(&self, machine_id: String) -> Result<()> {
+    self.machine(&machine_id)?.disconnect().await;
+    Ok(())
+  }
+
+  #[napi]
+  pub async fn disconnect_all(&self) -> Result<()> {
+    for (_, inner) in self.machines.iter() {
+      inner.disconnect().await;
+    }
+    Ok(())
+  }
+
+  #[napi]
+  pub async fn read_telemetry(&self, machine_id: String) -> Result<TelemetryPoint> {
+    self.machine(&machine_id)?.read_telemetry(None, None).await
+  }
+
+  #[napi]
+  pub fn start_session(&self, machine_id: String, session_id: Option<String>) -> Result<SessionInfo> {
+    Ok(self.machine(&machine_id)?.start_session(session_id))
+  }
+
+  #[napi]
+  pub fn end_session(&self, machine_id: String) -> Result<Option<SessionInfo>> {
+    Ok(self.machine(&machine_id)?.end_session())
+  }
+
+  #[napi]
+  pub fn get_status(&self, machine_id: String) -> Result<DriverStatus> {
+    Ok(self.machine(&machine_id)?.get_status())
+  }
+
+  #[napi]
+  pub fn get_statuses(&self) -> Vec<MachineStatus> {
+    self
+      .machines
+      .iter()
+      .map(|(id, inner)| {
+        let status = inner.get_status();
+        MachineStatus { machineId: id.clone(), state: status.state, metrics: status.metrics }
+      })
+      .collect()
+  }
+
+  /// Registers a callback receiving every machine's telemetry (paced per machine by its `emitIntervalMs`).
+  #[napi(ts_args_type = "callback: (point: TelemetryPoint) => void")]
+  pub fn on_telemetry(&self, env: Env, callback: JsFunction) -> Result<()> {
+    self.telemetry_events.subscribe(&env, callback)?;
+    for (id, inner) in self.machines.iter() {
+      if matches!(*inner.state.lock(), DriverState::CONNECTING | DriverState::CONNECTED | DriverState::PAUSED) {
+        self.forward(id, inner);
+      }
+    }
+    Ok(())
+  }
+}
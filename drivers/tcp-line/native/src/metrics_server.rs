@@ -0,0 +1,216 @@
+use std::fmt::Write as _;
+use std::sync::Arc;
+
+use napi::bindgen_prelude::*;
+use napi_derive::napi;
+use parking_lot::Mutex;
+use serde::Deserialize;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::{TcpListener, TcpStream};
+use tokio::task::JoinHandle;
+
+use crate::registry::live_drivers;
+use crate::{parse_timestamp, DriverInner, DriverMetrics, DriverState};
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+struct MetricsServerConfig {
+  host: String,
+  /// `0` binds an ephemeral port; `start()` returns the actual one.
+  port: u16,
+  path: String,
+}
+
+impl Default for MetricsServerConfig {
+  fn default() -> Self {
+    Self { host: "127.0.0.1".to_string(), port: 9464, path: "/metrics".to_string() }
+  }
+}
+
+/// Prometheus scrape endpoint for every line driver in the process, labelled by `machine_id`. One instance serves
+/// all drivers, so a gateway running dozens of machines exposes a single port.
+#[napi]
+pub struct MetricsServer {
+  config: MetricsServerConfig,
+  port: Mutex<Option<u16>>,
+  accept_handle: Mutex<Option<JoinHandle<()>>>,
+}
+
+#[napi]
+impl MetricsServer {
+  #[napi(constructor)]
+  pub fn new(config_json: String) -> Result<Self> {
+    let config: MetricsServerConfig = serde_json::from_str(&config_json)
+      .map_err(|err| Error::from_reason(format!("invalid config: {}", err)))?;
+    Ok(Self { config, port: Mutex::new(None), accept_handle: Mutex::new(None) })
+  }
+
+  /// Binds and starts serving, returning the bound port.
+  #[napi]
+  pub async fn start(&self) -> Result<u32> {
+    if let Some(port) = *self.port.lock() {
+      return Ok(port as u32);
+    }
+    let listener = TcpListener::bind((self.config.host.as_str(), self.config.port))
+      .await
+      .map_err(|err| Error::from_reason(format!("bind failed: {}", err)))?;
+    let port = listener.local_addr().map_err(|err| Error::from_reason(err.to_string()))?.port();
+
+    let path = self.config.path.clone();
+    let handle = tokio::spawn(async move {
+      while let Ok((stream, _)) = listener.accept().await {
+        tokio::spawn(serve_scrape(stream, path.clone()));
+      }
+    });
+
+    *self.accept_handle.lock() = Some(handle);
+    *self.port.lock() = Some(port);
+    Ok(port as u32)
+  }
+
+  #[napi]
+  pub async fn stop(&self) -> Result<()> {
+    if let Some(handle) = self.accept_handle.lock().take() {
+      handle.abort();
+    }
+    *self.port.lock() = None;
+    Ok(())
+  }
+
+  /// The exposition text a scrape would return right now.
+  #[napi]
+  pub fn render(&self) -> String {
+    render_metrics(&live_drivers())
+  }
+}
+
+async fn serve_scrape(mut stream: TcpStream, path: String) {
+  let mut request = Vec::new();
+  let mut buf = [0u8; 1024];
+  while !request.windows(4).any(|window| window == b"\r\n\r\n") {
+    match stream.read(&mut buf).await {
+      Ok(0) | Err(_) => return,
+      Ok(read) => request.extend_from_slice(&buf[..read]),
+    }
+    if request.len() > 16 * 1024 {
+      return;
+    }
+  }
+
+  let request = String::from_utf8_lossy(&request);
+  let mut parts = request.lines().next().unwrap_or_default().split_whitespace();
+  let method = parts.next().unwrap_or_default();
+  let target = parts.next().unwrap_or_default();
+  let target = target.split('?').next().unwrap_or_default();
+
+  let (status, content_type, body) = if method != "GET" {
+    ("405 Method Not Allowed", "text/plain", "method not allowed\n".to_string())
+  } else if target != path {
+    ("404 Not Found", "text/plain", "not found\n".to_string())
+  } else {
+    ("200 OK", "text/plain; version=0.0.4; charset=utf-8", render_metrics(&live_drivers()))
+  };
+  let response = format!(
+    "HTTP/1.1 {}\r\nContent-Type: {}\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+    status,
+    content_type,
+    body.len(),
+    body
+  );
+  let _ = stream.write_all(response.as_bytes()).await;
+  let _ = stream.shutdown().await;
+}
+
+type CounterFn = fn(&DriverMetrics) -> i64;
+
+const STATES: &[(DriverState, &str)] = &[
+  (DriverState::DISCONNECTED, "DISCONNECTED"),
+  (DriverState::CONNECTING, "CONNECTING"),
+  (DriverState::CONNECTED, "CONNECTED"),
+  (DriverState::PAUSED, "PAUSED"),
+  (DriverState::STOPPED, "STOPPED"),
+  (DriverState::FAILED, "FAILED"),
+];
+
+fn render_metrics(drivers: &[Arc<DriverInner>]) -> String {
+  let statuses: Vec<_> = drivers.iter().map(|driver| (escape_label(&driver.machine_id), driver.get_status())).collect();
+  let mut out = String::new();
+
+  let counters: &[(&str, &str, CounterFn)] = &[
+    ("tcp_line_lines_received_total", "Lines read from the device.", |m| m.linesReceived),
+    ("tcp_line_lines_parsed_total", "Lines parsed into samples.", |m| m.linesParsed),
+    ("tcp_line_parse_errors_total", "Lines that failed to parse.", |m| m.parseErrors),
+    ("tcp_line_checksum_errors_total", "Lines with a missing or wrong checksum.", |m| m.checksumErrors),
+    ("tcp_line_field_errors_total", "Channel fields left out of samples for not being numbers.", |m| m.fieldErrors),
+    ("tcp_line_batch_lines_total", "Lines holding a JSON array of readings.", |m| m.batchLines),
+    ("tcp_line_batch_samples_total", "Samples completed by JSON array lines.", |m| m.batchSamples),
+    ("tcp_line_sequence_gaps_total", "Jumps of the frame counter past the next value.", |m| m.sequenceGaps),
+    ("tcp_line_sequence_missing_total", "Frames skipped by frame counter jumps.", |m| m.sequenceMissing),
+    ("tcp_line_sequence_duplicates_total", "Frames repeating the previous frame counter.", |m| m.sequenceDuplicates),
+    ("tcp_line_sequence_reordered_total", "Frames arriving after a later one.", |m| m.sequenceReordered),
+    ("tcp_line_out_of_order_samples_total", "Samples timestamped before the latest one.", |m| m.outOfOrderSamples),
+    ("tcp_line_telemetry_emitted_total", "Telemetry points returned to readTelemetry.", |m| m.telemetryEmitted),
+    ("tcp_line_reconnects_total", "Reconnect attempts.", |m| m.reconnects),
+    ("tcp_line_commands_sent_total", "Command lines written to the device.", |m| m.commandsSent),
+    ("tcp_line_stale_timeouts_total", "Connections dropped by the readTimeoutMs watchdog.", |m| m.staleTimeouts),
+    (
+      "tcp_line_dropped_samples_total",
+      "Samples discarded because a queue, spool or sink buffer was full.",
+      |m| m.droppedSamples,
+    ),
+    ("tcp_line_influx_points_written_total", "Points acknowledged by InfluxDB.", |m| m.influxPointsWritten),
+    ("tcp_line_influx_write_errors_total", "Influx writes rejected or out of retries.", |m| m.influxWriteErrors),
+    (
+      "tcp_line_kafka_messages_delivered_total",
+      "Messages acknowledged by the Kafka brokers.",
+      |m| m.kafkaMessagesDelivered,
+    ),
+    (
+      "tcp_line_kafka_delivery_errors_total",
+      "Kafka messages dropped after failed delivery.",
+      |m| m.kafkaDeliveryErrors,
+    ),
+    ("tcp_line_bytes_received_total", "Bytes read from the device, including line endings.", |m| m.bytesReceived),
+    ("tcp_line_bytes_sent_total", "Bytes written to the device.", |m| m.bytesSent),
+  ];
+  for (name, help, value) in counters {
+    let _ = writeln!(out, "# HELP {} {}\n# TYPE {} counter", name, help, name);
+    for (machine_id, status) in statuses.iter() {
+      let _ = writeln!(out, "{}{{machine_id=\"{}\"}} {}", name, machine_id, value(&status.metrics));
+    }
+  }
+
+  let _ = writeln!(out, "# HELP tcp_line_state Current driver state (1 for the active state).");
+  let _ = writeln!(out, "# TYPE tcp_line_state gauge");
+  for (machine_id, status) in statuses.iter() {
+    for (state, label) in STATES {
+      let active = if *state == status.state { 1 } else { 0 };
+      let _ = writeln!(out, "tcp_line_state{{machine_id=\"{}\",state=\"{}\"}} {}", machine_id, label, active);
+    }
+  }
+
+  let _ = writeln!(out, "# HELP tcp_line_last_line_timestamp_seconds Sample time of the last parsed line.");
+  let _ = writeln!(out, "# TYPE tcp_line_last_line_timestamp_seconds gauge");
+  for (machine_id, status) in statuses.iter() {
+    let Some(at) = status.metrics.lastLineAt.as_deref().and_then(|at| parse_timestamp(at).ok()) else {
+      continue;
+    };
+    let seconds = at.timestamp_millis() as f64 / 1000.0;
+    let _ = writeln!(out, "tcp_line_last_line_timestamp_seconds{{machine_id=\"{}\"}} {}", machine_id, seconds);
+  }
+
+  let _ = writeln!(
+    out,
+    "# HELP tcp_line_clock_skew_seconds Device clock minus gateway clock.\n# TYPE tcp_line_clock_skew_seconds gauge"
+  );
+  for (machine_id, status) in statuses.iter() {
+    if let Some(skew_ms) = status.clockSkewMs {
+      let _ = writeln!(out, "tcp_line_clock_skew_seconds{{machine_id=\"{}\"}} {}", machine_id, skew_ms / 1000.0);
+    }
+  }
+  out
+}
+
+fn escape_label(value: &str) -> String {
+  value.replace('\\', "\\\\").replace('"', "\\\"").replace('\n', "\\n")
+}
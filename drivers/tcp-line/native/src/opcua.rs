@@ -0,0 +1,728 @@
+use std::collections::BTreeMap;
+use std::path::PathBuf;
+use std::str::FromStr;
+use std::sync::atomic::Ordering;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use chrono::SecondsFormat;
+use napi::bindgen_prelude::*;
+use napi_derive::napi;
+use ::opcua::client::{
+  Client, ClientBuilder, IdentityToken, MonitoredItemMap, OnSubscriptionNotificationCore, Password, Session,
+  SessionPollResult,
+};
+use ::opcua::types::{
+  DataChangeNotification, DataValue, EndpointDescription, MessageSecurityMode,
+  MonitoredItemCreateRequest, MonitoringMode, MonitoringParameters, NodeId, NotificationMessage, ReadValueId,
+  TimestampsToReturn, Variant,
+};
+use serde::Deserialize;
+use tokio::sync::mpsc;
+use tokio::task::JoinHandle;
+use tokio::time::sleep;
+use tokio_stream::StreamExt;
+use tracing::{debug, error, info, warn};
+
+use crate::aggregate::TelemetryAggregate;
+use crate::cancel::{self, Cancel};
+use crate::csv_record::ColumnMismatch;
+use crate::events::{DriverError, DriverErrorCode};
+use crate::phases::SessionSummary;
+use crate::recent_errors::RecentParseError;
+use crate::session_log::SessionLogSummary;
+use crate::stats::DetailedMetrics;
+use crate::stream::TelemetryStream;
+use crate::subscription::TelemetrySubscription;
+use crate::{
+  CsvConfig, DriverInner, DriverState, DriverStatus, FrameFormat, PipelineConfig, ReconnectConfig, TcpLineDriverConfig,
+  TelemetryPoint,
+};
+
+/// The OPC UA security policies, by their short names from the spec.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Deserialize)]
+enum SecurityPolicy {
+  #[default]
+  None,
+  Basic128Rsa15,
+  Basic256,
+  Basic256Sha256,
+  Aes128Sha256RsaOaep,
+  Aes256Sha256RsaPss,
+}
+
+impl SecurityPolicy {
+  fn name(self) -> &'static str {
+    match self {
+      Self::None => "None",
+      Self::Basic128Rsa15 => "Basic128Rsa15",
+      Self::Basic256 => "Basic256",
+      Self::Basic256Sha256 => "Basic256Sha256",
+      Self::Aes128Sha256RsaOaep => "Aes128Sha256RsaOaep",
+      Self::Aes256Sha256RsaPss => "Aes256Sha256RsaPss",
+    }
+  }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Deserialize)]
+enum SecurityMode {
+  None,
+  Sign,
+  SignAndEncrypt,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct OpcUaDriverConfig {
+  /// `opc.tcp://host:port[/path]`; the server's endpoints are discovered there and the one matching the security
+  /// settings is used.
+  endpoint_url: String,
+  #[serde(default)]
+  security_policy: SecurityPolicy,
+  /// Defaults to `None` without a security policy and `SignAndEncrypt` with one.
+  #[serde(default)]
+  security_mode: Option<SecurityMode>,
+  /// User name authentication; anonymous when unset.
+  #[serde(default)]
+  username: Option<String>,
+  #[serde(default)]
+  password: Option<String>,
+  /// Channel (`btC`, `etC`, `powerPct`, `fanPct`, `drumRpm`, or any other name for an extra) to node id, e.g.
+  /// `ns=2;s=Roaster1.BeanTemp`.
+  nodes: BTreeMap<String, String>,
+  /// Subscription publishing interval; the nodes are sampled at the same rate.
+  #[serde(default = "default_publishing_interval_ms")]
+  publishing_interval_ms: u64,
+  /// Holds the client certificate (created on first use) and the trusted/rejected server certificates; defaults to a
+  /// directory under the system temp dir.
+  #[serde(default)]
+  pki_dir: Option<String>,
+  /// Trust a secured server's certificate on first connect instead of rejecting it until it is moved to `trusted/`.
+  #[serde(default)]
+  trust_server_certs: bool,
+  /// Endpoint discovery, session activation and subscription setup together.
+  #[serde(default = "default_connect_timeout_ms")]
+  connect_timeout_ms: u64,
+  reconnect: ReconnectConfig,
+  #[serde(flatten)]
+  pipeline: PipelineConfig,
+}
+
+fn default_publishing_interval_ms() -> u64 {
+  1000
+}
+
+fn default_connect_timeout_ms() -> u64 {
+  10_000
+}
+
+impl OpcUaDriverConfig {
+  fn line_config(&self) -> TcpLineDriverConfig {
+    TcpLineDriverConfig {
+      reconnect: self.reconnect.clone(),
+      ..TcpLineDriverConfig::for_source(
+        FrameFormat::Jsonl,
+        CsvConfig {
+          has_header: false,
+          columns: Vec::new(),
+          delimiter: ",".to_string(),
+          column_mismatch: ColumnMismatch::default(),
+        },
+        self.pipeline.clone(),
+      )
+    }
+  }
+
+  fn security_mode(&self) -> SecurityMode {
+    match (self.security_mode, self.security_policy) {
+      (Some(mode), _) => mode,
+      (None, SecurityPolicy::None) => SecurityMode::None,
+      (None, _) => SecurityMode::SignAndEncrypt,
+    }
+  }
+
+  /// The monitored nodes in channel order.
+  fn validate(&self) -> std::result::Result<Vec<(String, NodeId)>, String> {
+    if !self.endpoint_url.starts_with("opc.tcp://") {
+      return Err("endpointUrl must be an opc.tcp:// URL".to_string());
+    }
+    if (self.security_policy == SecurityPolicy::None) != (self.security_mode() == SecurityMode::None) {
+      return Err("securityMode None goes with securityPolicy None and only with it".to_string());
+    }
+    if self.password.is_some() && self.username.is_none() {
+      return Err("password requires username".to_string());
+    }
+    if self.publishing_interval_ms == 0 {
+      return Err("publishingIntervalMs must be positive".to_string());
+    }
+    if self.nodes.is_empty() {
+      return Err("nodes must map at least one channel".to_string());
+    }
+    self
+      .nodes
+      .iter()
+      .map(|(channel, node)| match NodeId::from_str(node) {
+        Ok(node_id) => Ok((channel.clone(), node_id)),
+        Err(_) => Err(format!("nodes.{}: invalid node id {:?}", channel, node)),
+      })
+      .collect()
+  }
+
+  fn identity(&self) -> IdentityToken {
+    match &self.username {
+      Some(username) => {
+        IdentityToken::UserName(username.clone(), Password::new(self.password.clone().unwrap_or_default()))
+      }
+      None => IdentityToken::Anonymous,
+    }
+  }
+}
+
+/// Subscribes to OPC UA nodes and feeds their values through the same sample pipeline as `TcpLineDriverNative`: every
+/// publish from the server becomes one sample carrying the latest value of each mapped node, stamped with the newest
+/// source timestamp among the changes.
+#[napi]
+pub struct OpcUaDriverNative {
+  config: OpcUaDriverConfig,
+  nodes: Vec<(String, NodeId)>,
+  pub(crate) inner: Arc<DriverInner>,
+}
+
+#[napi]
+impl OpcUaDriverNative {
+  #[napi(constructor)]
+  pub fn new(config_json: String, machine_id: String) -> Result<Self> {
+    let config: OpcUaDriverConfig = serde_json::from_str(&config_json)
+      .map_err(|err| Error::from_reason(format!("invalid config: {}", err)))?;
+    let nodes = config.validate().map_err(|err| Error::from_reason(format!("invalid config: {}", err)))?;
+    let inner = DriverInner::new(config.line_config(), machine_id);
+    Ok(Self { config, nodes, inner })
+  }
+
+  #[napi(ts_args_type = "timeoutMs?: number, cancel?: CancelToken")]
+  pub async fn connect(&self, timeout_ms: Option<u32>, cancel: Option<Cancel>) -> Result<()> {
+    let config = self.config.clone();
+    let nodes = self.nodes.clone();
+    let since = self.inner.connected_seq.load(Ordering::Relaxed);
+    self.inner.ensure_source(move |inner| run_opcua(inner, config, nodes));
+    cancel::bounded("connect", self.inner.wait_for_connected(since), timeout_ms, cancel).await
+  }
+
+  #[napi(ts_args_type = "timeoutMs?: number, cancel?: CancelToken")]
+  pub async fn read_telemetry(&self, timeout_ms: Option<u32>, cancel: Option<Cancel>) -> Result<TelemetryPoint> {
+    self.inner.read_telemetry(timeout_ms, cancel).await
+  }
+
+  #[napi(ts_args_type = "timeoutMs: number, cancel?: CancelToken")]
+  pub async fn read_telemetry_with_timeout(
+    &self,
+    timeout_ms: u32,
+    cancel: Option<Cancel>,
+  ) -> Result<Option<TelemetryPoint>> {
+    self.inner.read_telemetry_with_timeout(timeout_ms, cancel).await
+  }
+
+  #[napi]
+  pub async fn peek_telemetry(&self) -> Result<TelemetryPoint> {
+    self.inner.peek_telemetry().await
+  }
+
+  #[napi]
+  pub async fn disconnect(&self) -> Result<()> {
+    self.inner.disconnect().await;
+    Ok(())
+  }
+
+  #[napi]
+  pub fn get_status(&self) -> Result<DriverStatus> {
+    Ok(self.inner.get_status())
+  }
+
+  #[napi]
+  pub fn get_metrics_detailed(&self) -> DetailedMetrics {
+    self.inner.get_metrics_detailed()
+  }
+
+  /// The last 50 parse failures, oldest first, with the offending line cut to 256 characters.
+  #[napi]
+  pub fn get_recent_errors(&self) -> Vec<RecentParseError> {
+    self.inner.get_recent_errors()
+  }
+
+  /// Async-iterator-shaped stream of samples; see `TelemetryStream`.
+  #[napi]
+  pub fn telemetry_stream(&self) -> TelemetryStream {
+    TelemetryStream::new(Arc::clone(&self.inner))
+  }
+
+  /// A handle of its own onto every point published from now on; see `TelemetrySubscription`.
+  #[napi]
+  pub fn subscribe(&self) -> TelemetrySubscription {
+    TelemetrySubscription::new(Arc::clone(&self.inner))
+  }
+
+  /// Takes up to `maxSamples` (default all) queued samples, oldest first; empty unless `queue` is configured.
+  #[napi]
+  pub fn drain_samples(&self, max_samples: Option<u32>) -> Vec<TelemetryPoint> {
+    self.inner.drain_samples(max_samples)
+  }
+
+  /// Per-channel min/max/mean/count over the samples of the last `windowSeconds` (by sample timestamp).
+  #[napi]
+  pub fn read_aggregate(&self, window_seconds: f64) -> Result<TelemetryAggregate> {
+    self.inner.read_aggregate(window_seconds)
+  }
+
+  /// The current roast's phases from CHARGE, with their durations, end temperatures and mean RoR; `null` before
+  /// CHARGE. Needs `roastEvents`.
+  #[napi]
+  pub fn get_session_summary(&self) -> Option<SessionSummary> {
+    self.inner.session_summary()
+  }
+
+  /// Collects every emitted point from now on and writes them to `path` on `stopLogging`. `format` is `csv` or
+  /// `parquet` (default: from the extension, else csv); the header lists the point fields followed by one column per
+  /// extra.
+  #[napi]
+  pub fn start_logging(&self, path: String, format: Option<String>) -> Result<()> {
+    self.inner.start_logging(path, format)
+  }
+
+  /// Writes the log begun by `startLogging` and returns what was written (`null` when not logging).
+  #[napi]
+  pub fn stop_logging(&self) -> Result<Option<SessionLogSummary>> {
+    self.inner.stop_logging()
+  }
+
+  /// Registers a callback for state transitions (CONNECTING, CONNECTED, DISCONNECTED, ...).
+  #[napi(ts_args_type = "callback: (event: StateChangeEvent) => void")]
+  pub fn on_state_change(&self, env: Env, callback: JsFunction) -> Result<()> {
+    self.inner.state_events.subscribe(&env, callback)
+  }
+
+  /// Registers a callback for connection failures and lost sessions.
+  #[napi(ts_args_type = "callback: (error: DriverError) => void")]
+  pub fn on_error(&self, env: Env, callback: JsFunction) -> Result<()> {
+    self.inner.error_events.subscribe(&env, callback)
+  }
+
+  /// Registers a callback for detected roast events (requires `roastEvents` in the config).
+  #[napi(ts_args_type = "callback: (event: RoastEvent) => void")]
+  pub fn on_event(&self, env: Env, callback: JsFunction) -> Result<()> {
+    self.inner.roast_events.subscribe(&env, callback)
+  }
+}
+
+/// Connects and resubscribes with a fresh session after every loss, paced by `reconnect` like the TCP loop.
+async fn run_opcua(inner: Arc<DriverInner>, config: OpcUaDriverConfig, nodes: Vec<(String, NodeId)>) {
+  inner.apply_backoff(&config.reconnect);
+  inner.backoff.lock().reset();
+  let mut client = match build_client(&config) {
+    Ok(client) => client,
+    Err(err) => {
+      let message = format!("opc ua client setup failed: {}", err);
+      inner.handle_failure(DriverError::new(DriverErrorCode::ConnectFailed, message)).await;
+      inner.set_state(DriverState::FAILED);
+      return;
+    }
+  };
+  let mut attempts: u32 = 0;
+  let mut outage_started: Option<Instant> = None;
+  let mut exhausted = false;
+  loop {
+    if inner.stop_flag.load(Ordering::Relaxed) {
+      break;
+    }
+    inner.set_state(DriverState::CONNECTING);
+    inner.reset_connection_state();
+    inner.reset_roast_events();
+
+    debug!(machine_id = %inner.machine_id, endpoint = %config.endpoint_url, attempt = attempts + 1, "connecting");
+    let (connected, err) = run_session(&inner, &config, &nodes, &mut client).await;
+    if connected {
+      attempts = 0;
+      outage_started = None;
+    }
+    inner.handle_failure(err).await;
+
+    if inner.stop_flag.load(Ordering::Relaxed) || !config.reconnect.enabled {
+      break;
+    }
+    attempts = attempts.saturating_add(1);
+    let outage = outage_started.get_or_insert_with(Instant::now).elapsed();
+    if config.reconnect.exhausted(attempts, outage) {
+      let outage_ms = outage.as_millis() as u64;
+      error!(machine_id = %inner.machine_id, attempts, outage_ms, "reconnect policy exhausted");
+      exhausted = true;
+      break;
+    }
+    {
+      let mut metrics = inner.metrics.lock();
+      metrics.reconnects = metrics.reconnects.saturating_add(1);
+    }
+    let delay = { inner.backoff.lock().next_delay() };
+    debug!(machine_id = %inner.machine_id, delay_ms = delay, attempt = attempts, "reconnecting after backoff");
+    sleep(Duration::from_millis(delay)).await;
+  }
+
+  inner.set_state(if inner.stop_flag.load(Ordering::Relaxed) {
+    DriverState::STOPPED
+  } else if exhausted {
+    DriverState::FAILED
+  } else {
+    DriverState::DISCONNECTED
+  });
+}
+
+fn build_client(config: &OpcUaDriverConfig) -> std::result::Result<Client, String> {
+  let pki_dir =
+    config.pki_dir.as_ref().map(PathBuf::from).unwrap_or_else(|| std::env::temp_dir().join("tcp-line-opcua-pki"));
+  ClientBuilder::new()
+    .application_name("tcp-line-driver")
+    .application_uri("urn:sim-corp:tcp-line-driver")
+    .product_uri("urn:sim-corp:tcp-line-driver")
+    .pki_dir(pki_dir)
+    .create_sample_keypair(true)
+    .trust_server_certs(config.trust_server_certs)
+    // Reconnects are ours (see run_opcua); the session's own retries would hide a lost connection.
+    .session_retry_limit(0)
+    .client()
+    .map_err(|errors| errors.join("; "))
+}
+
+const DISCONNECT_TIMEOUT: Duration = Duration::from_secs(2);
+
+enum SessionEvent {
+  Connected,
+  Lost(String),
+}
+
+/// Drives a session's event loop; aborted with the session it belongs to.
+struct EventLoopTask(JoinHandle<()>);
+
+impl Drop for EventLoopTask {
+  fn drop(&mut self) {
+    self.0.abort();
+  }
+}
+
+/// One session from discovery until it is lost. Returns whether it got as far as CONNECTED, and why it ended.
+async fn run_session(
+  inner: &Arc<DriverInner>,
+  config: &OpcUaDriverConfig,
+  nodes: &[(String, NodeId)],
+  client: &mut Client,
+) -> (bool, DriverError) {
+  let mode = match config.security_mode() {
+    SecurityMode::None => MessageSecurityMode::None,
+    SecurityMode::Sign => MessageSecurityMode::Sign,
+    SecurityMode::SignAndEncrypt => MessageSecurityMode::SignAndEncrypt,
+  };
+  let endpoint: EndpointDescription = (config.endpoint_url.as_str(), config.security_policy.name(), mode).into();
+  let timeout = Duration::from_millis(config.connect_timeout_ms);
+  let (session, event_loop) =
+    match tokio::time::timeout(timeout, client.connect_to_matching_endpoint(endpoint, config.identity())).await {
+      Ok(Ok(session)) => session,
+      Ok(Err(err)) => return (false, connect_failed(err.to_string())),
+      Err(_) => return (false, timed_out(config)),
+    };
+
+  let (event_sender, mut events) = mpsc::unbounded_channel();
+  let event_loop = EventLoopTask(tokio::spawn(async move {
+    let stream = event_loop.enter();
+    tokio::pin!(stream);
+    let reason = loop {
+      match stream.next().await {
+        Some(Ok(SessionPollResult::Reconnected(_))) => {
+          let _ = event_sender.send(SessionEvent::Connected);
+        }
+        Some(Ok(SessionPollResult::ConnectionLost(status))) => break status.to_string(),
+        Some(Ok(_)) => {}
+        Some(Err(status)) => break status.to_string(),
+        None => break "session closed".to_string(),
+      }
+    };
+    let _ = event_sender.send(SessionEvent::Lost(reason));
+  }));
+
+  let outcome = serve_session(inner, config, nodes, &session, &mut events).await;
+  // Close the session on the server as well while the connection is still up, or every failed attempt holds a
+  // session there until it times out.
+  if !event_loop.0.is_finished() {
+    let _ = tokio::time::timeout(DISCONNECT_TIMEOUT, session.disconnect()).await;
+  }
+  outcome
+}
+
+/// Waits for the session to activate, subscribes, then feeds samples until the session is lost.
+async fn serve_session(
+  inner: &Arc<DriverInner>,
+  config: &OpcUaDriverConfig,
+  nodes: &[(String, NodeId)],
+  session: &Session,
+  events: &mut mpsc::UnboundedReceiver<SessionEvent>,
+) -> (bool, DriverError) {
+  let (change_sender, mut changes) = mpsc::unbounded_channel();
+  let setup = async {
+    match events.recv().await {
+      Some(SessionEvent::Connected) => {}
+      Some(SessionEvent::Lost(reason)) => return Err(reason),
+      None => return Err("session closed".to_string()),
+    }
+    inner.metrics.lock().lastError = None;
+    subscribe(inner, session, config, nodes, change_sender).await
+  };
+  match tokio::time::timeout(Duration::from_millis(config.connect_timeout_ms), setup).await {
+    Ok(Ok(())) => {}
+    Ok(Err(message)) => return (false, connect_failed(message)),
+    Err(_) => return (false, timed_out(config)),
+  }
+
+  inner.backoff.lock().reset();
+  info!(machine_id = %inner.machine_id, endpoint = %config.endpoint_url, nodes = nodes.len(), "connected");
+  inner.set_state(DriverState::CONNECTED);
+
+  let mut values = BTreeMap::new();
+  let reason = loop {
+    tokio::select! {
+      batch = changes.recv() => {
+        let Some(batch) = batch else {
+          break "subscription closed".to_string();
+        };
+        inner.wait_for_queue_space().await;
+        accept_changes(inner, &mut values, batch);
+      }
+      event = events.recv() => match event {
+        Some(SessionEvent::Connected) => {}
+        Some(SessionEvent::Lost(reason)) => break reason,
+        None => break "session closed".to_string(),
+      },
+    }
+  };
+  (true, DriverError::new(DriverErrorCode::SocketClosed, format!("connection lost: {}", reason)))
+}
+
+fn connect_failed(message: String) -> DriverError {
+  DriverError::new(DriverErrorCode::ConnectFailed, format!("connection failure: {}", message))
+}
+
+fn timed_out(config: &OpcUaDriverConfig) -> DriverError {
+  let message = format!("connection failure: timed out after {} ms", config.connect_timeout_ms);
+  DriverError::new(DriverErrorCode::Timeout, message)
+}
+
+/// Creates the subscription and one monitored item per mapped node. Nodes the server rejects are logged and skipped;
+/// it is an error only when none can be monitored.
+async fn subscribe(
+  inner: &DriverInner,
+  session: &Session,
+  config: &OpcUaDriverConfig,
+  nodes: &[(String, NodeId)],
+  changes: mpsc::UnboundedSender<Vec<(String, DataValue)>>,
+) -> std::result::Result<(), String> {
+  let interval = Duration::from_millis(config.publishing_interval_ms);
+  let callback = Notifications { nodes: nodes.to_vec(), changes };
+  let subscription_id = session
+    .create_subscription(interval, 60, 10, 0, 0, true, callback)
+    .await
+    .map_err(|err| format!("create subscription failed: {}", err))?;
+  let items = nodes
+    .iter()
+    .map(|(_, node_id)| {
+      let parameters = MonitoringParameters {
+        sampling_interval: config.publishing_interval_ms as f64,
+        queue_size: 1,
+        discard_oldest: true,
+        ..MonitoringParameters::default()
+      };
+      MonitoredItemCreateRequest::new(ReadValueId::from(node_id.clone()), MonitoringMode::Reporting, parameters)
+    })
+    .collect();
+  let created = session
+    .create_monitored_items(subscription_id, TimestampsToReturn::Both, items)
+    .await
+    .map_err(|err| format!("create monitored items failed: {}", err))?;
+  let mut monitored = 0;
+  for ((channel, node_id), item) in nodes.iter().zip(&created) {
+    let status = item.result.status_code;
+    if status.is_bad() {
+      warn!(machine_id = %inner.machine_id, %channel, node = %node_id, %status, "node not monitored");
+      inner.metrics.lock().lastError = Some(format!("nodes.{}: {}", channel, status));
+    } else {
+      monitored += 1;
+    }
+  }
+  if monitored == 0 {
+    return Err("none of the nodes could be monitored".to_string());
+  }
+  Ok(())
+}
+
+/// Hands each publish (one `NotificationMessage`) to the session task as a single batch of channel values.
+struct Notifications {
+  nodes: Vec<(String, NodeId)>,
+  changes: mpsc::UnboundedSender<Vec<(String, DataValue)>>,
+}
+
+impl OnSubscriptionNotificationCore for Notifications {
+  fn on_subscription_notification(&mut self, notification: NotificationMessage, monitored_items: MonitoredItemMap<'_>) {
+    let mut batch = Vec::new();
+    for data in notification.notification_data.into_iter().flatten() {
+      let Some(changes) = data.into_inner_as::<DataChangeNotification>() else {
+        continue;
+      };
+      for change in changes.monitored_items.into_iter().flatten() {
+        let Some(item) = monitored_items.get(change.client_handle) else {
+          continue;
+        };
+        let node_id = &item.item_to_monitor().node_id;
+        for (channel, _) in self.nodes.iter().filter(|(_, node)| node == node_id) {
+          batch.push((channel.clone(), change.value.clone()));
+        }
+      }
+    }
+    if !batch.is_empty() {
+      let _ = self.changes.send(batch);
+    }
+  }
+}
+
+/// Folds `batch` into the latest values and emits them as one sample.
+fn accept_changes(
+  inner: &DriverInner,
+  values: &mut BTreeMap<String, serde_json::Value>,
+  batch: Vec<(String, DataValue)>,
+) {
+  inner.count_line(0);
+  let record = change_record(values, batch);
+  let raw = || serde_json::to_string(&*values).unwrap_or_default();
+  let parsed = inner.record_to_sample(record, raw);
+  match parsed {
+    Ok(Some(sample)) => inner.accept_sample(sample),
+    Ok(None) => {}
+    Err(err) => inner.count_parse_error(&err, &raw()),
+  }
+}
+
+/// Folds `batch` into `values` and returns the record a sample is built from: the latest value of every channel, with
+/// the newest source (else server) timestamp of the batch as `ts`.
+fn change_record(
+  values: &mut BTreeMap<String, serde_json::Value>,
+  batch: Vec<(String, DataValue)>,
+) -> Vec<(String, serde_json::Value)> {
+  let mut ts = None;
+  for (channel, value) in batch {
+    ts = ts.max(value.source_timestamp.or(value.server_timestamp).map(|ts| ts.as_chrono()));
+    // A bad status means the node has no usable value right now.
+    let good = value.status.is_none_or(|status| !status.is_bad());
+    match value.value.as_ref().filter(|_| good).map(variant_value) {
+      Some(value) if !value.is_null() => values.insert(channel, value),
+      _ => values.remove(&channel),
+    };
+  }
+  let mut record: Vec<(String, serde_json::Value)> =
+    values.iter().map(|(key, value)| (key.clone(), value.clone())).collect();
+  if let Some(ts) = ts {
+    record.push(("ts".to_string(), serde_json::Value::String(ts.to_rfc3339_opts(SecondsFormat::Millis, true))));
+  }
+  record
+}
+
+/// Numbers (and booleans, as 0/1) become readings; strings become text extras; anything else is dropped.
+fn variant_value(variant: &Variant) -> serde_json::Value {
+  match variant {
+    Variant::Boolean(value) => serde_json::Value::from(u8::from(*value)),
+    Variant::String(text) if !text.is_null() => serde_json::Value::String(text.as_ref().to_string()),
+    other => other.as_f64().map(serde_json::Value::from).unwrap_or(serde_json::Value::Null),
+  }
+}
+
+
+#[cfg(test)]
+mod tests {
+  use ::opcua::types::{DateTime, StatusCode, UAString};
+  use tcp_line_core::{RawTelemetrySample, TcpLineParser};
+
+  use super::*;
+
+  fn config(nodes: serde_json::Value) -> OpcUaDriverConfig {
+    serde_json::from_value(serde_json::json!({
+      "endpointUrl": "opc.tcp://127.0.0.1:4840",
+      "nodes": nodes,
+      "reconnect": { "enabled": false, "minBackoffMs": 10, "maxBackoffMs": 20 },
+      "emitIntervalMs": 0,
+      "dedupeWithinMs": 0,
+      "offsets": { "btC": 0, "etC": 0 },
+    }))
+    .expect("config")
+  }
+
+  fn change(channel: &str, variant: Variant, at: Option<&str>) -> (String, DataValue) {
+    let at = at.map(|at| DateTime::from(chrono::DateTime::parse_from_rfc3339(at).expect("timestamp").to_utc()));
+    (channel.to_string(), DataValue { value: Some(variant), source_timestamp: at, ..DataValue::default() })
+  }
+
+  /// Runs each batch through `change_record` and the driver's parser, as `accept_changes` does.
+  fn samples(config: &OpcUaDriverConfig, batches: Vec<Vec<(String, DataValue)>>) -> Vec<RawTelemetrySample> {
+    let mut parser = TcpLineParser::new(config.line_config().parser());
+    let mut values = BTreeMap::new();
+    batches
+      .into_iter()
+      .map(|batch| parser.to_sample(change_record(&mut values, batch)).expect("parses").expect("a sample"))
+      .collect()
+  }
+
+  #[test]
+  fn parses_a_node_id_per_channel() {
+    let nodes = config(serde_json::json!({ "btC": "ns=2;s=Roaster1.BeanTemp", "drumRpm": "ns=3;i=1001" })).validate();
+    let expected = vec![
+      ("btC".to_string(), NodeId::new(2, "Roaster1.BeanTemp")),
+      ("drumRpm".to_string(), NodeId::new(3, 1001u32)),
+    ];
+    assert_eq!(nodes, Ok(expected));
+    let invalid = config(serde_json::json!({ "btC": "BeanTemp" })).validate();
+    assert_eq!(invalid, Err(r#"nodes.btC: invalid node id "BeanTemp""#.to_string()));
+  }
+
+  #[test]
+  fn maps_node_values_onto_channels_and_extras() {
+    let config = config(serde_json::json!({ "btC": "ns=2;s=BT", "etC": "ns=2;s=ET", "burner": "ns=2;s=Burner" }));
+    let batch = vec![
+      change("btC", Variant::Double(201.5), Some("2025-01-01T00:00:01.250Z")),
+      change("etC", Variant::Float(230.25), Some("2025-01-01T00:00:01.000Z")),
+      change("burner", Variant::Boolean(true), None),
+    ];
+    let sample = samples(&config, vec![batch]).remove(0);
+    assert_eq!((sample.bt_c, sample.et_c), (Some(201.5), Some(230.25)));
+    // The newest source timestamp of the batch stamps the sample.
+    assert_eq!(sample.ts.to_rfc3339_opts(SecondsFormat::Millis, true), "2025-01-01T00:00:01.250Z");
+    let extras = sample.extras.expect("extras");
+    let numbers: Vec<_> = extras.iter().map(|extra| (extra.key.as_str(), extra.number_value)).collect();
+    assert_eq!(numbers, [("burner", Some(1.0))]);
+  }
+
+  #[test]
+  fn keeps_the_latest_value_and_drops_a_bad_one() {
+    let config = config(serde_json::json!({ "btC": "ns=2;s=BT", "etC": "ns=2;s=ET", "phase": "ns=2;s=Phase" }));
+    let (_, reading) = change("etC", Variant::Double(0.0), None);
+    let bad = ("etC".to_string(), DataValue { status: Some(StatusCode::BadSensorFailure), ..reading });
+    let batches = vec![
+      vec![change("btC", Variant::Double(190.0), None), change("etC", Variant::Double(220.0), None)],
+      vec![bad, change("phase", Variant::String(UAString::from("drying")), None)],
+    ];
+    let sample = samples(&config, batches).remove(1);
+    assert_eq!((sample.bt_c, sample.et_c), (Some(190.0), None));
+    let extras = sample.extras.expect("extras");
+    let texts: Vec<_> = extras.iter().map(|extra| (extra.key.as_str(), extra.text_value.as_deref())).collect();
+    assert_eq!(texts, [("phase", Some("drying"))]);
+  }
+
+  #[test]
+  fn reads_variants_as_numbers_or_text() {
+    assert_eq!(variant_value(&Variant::Int16(-40)), serde_json::json!(-40.0));
+    assert_eq!(variant_value(&Variant::Boolean(false)), serde_json::json!(0));
+    assert_eq!(variant_value(&Variant::String(UAString::from("ok"))), serde_json::json!("ok"));
+    assert_eq!(variant_value(&Variant::String(UAString::null())), serde_json::Value::Null);
+    assert_eq!(variant_value(&Variant::Empty), serde_json::Value::Null);
+  }
+}
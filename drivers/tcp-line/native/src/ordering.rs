@@ -0,0 +1,90 @@
+use chrono::{DateTime, Duration, Utc};
+use schemars::JsonSchema;
+use serde::Deserialize;
+
+use crate::RawTelemetrySample;
+
+/// What happens to a sample timestamped before the latest one.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Deserialize, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub(crate) enum OrderingPolicy {
+  /// Pass it on as it came.
+  #[default]
+  Accept,
+  /// Discard it.
+  DropOlder,
+  /// Hold samples back `windowMs` on their timestamps and let them out in timestamp order; one later than that is
+  /// discarded.
+  Reorder,
+}
+
+#[derive(Debug, Clone, PartialEq, Deserialize, JsonSchema)]
+#[serde(rename_all = "camelCase", default)]
+pub(crate) struct OrderingConfig {
+  pub policy: OrderingPolicy,
+  pub window_ms: u64,
+}
+
+impl Default for OrderingConfig {
+  fn default() -> Self {
+    Self { policy: OrderingPolicy::Accept, window_ms: 500 }
+  }
+}
+
+/// The samples held back by `reorder`, and the latest timestamp seen, for one connection.
+#[derive(Debug, Default)]
+pub(crate) struct SampleOrder {
+  /// Sorted by timestamp; equal timestamps keep their arrival order.
+  held: Vec<RawTelemetrySample>,
+  latest: Option<DateTime<Utc>>,
+  /// The timestamp of the last sample let out under `reorder`.
+  released: Option<DateTime<Utc>>,
+}
+
+impl SampleOrder {
+  /// Takes a sample as it arrives. Returns the samples to accept now, oldest first, and whether `sample` was out of
+  /// order.
+  pub fn push(&mut self, config: &OrderingConfig, sample: RawTelemetrySample) -> (Vec<RawTelemetrySample>, bool) {
+    let late = self.latest.is_some_and(|latest| sample.ts < latest);
+    if !late {
+      self.latest = Some(sample.ts);
+    }
+    let ready = match config.policy {
+      OrderingPolicy::Accept => vec![sample],
+      OrderingPolicy::DropOlder if late => Vec::new(),
+      OrderingPolicy::DropOlder => vec![sample],
+      OrderingPolicy::Reorder => self.reorder(config, sample),
+    };
+    (ready, late)
+  }
+
+  fn reorder(&mut self, config: &OrderingConfig, sample: RawTelemetrySample) -> Vec<RawTelemetrySample> {
+    if self.released.is_some_and(|released| sample.ts < released) {
+      return Vec::new();
+    }
+    let at = self.held.partition_point(|held| held.ts <= sample.ts);
+    self.held.insert(at, sample);
+    let Some(latest) = self.latest else {
+      return Vec::new();
+    };
+    let due = latest - Duration::milliseconds(config.window_ms as i64);
+    let ready: Vec<RawTelemetrySample> = self.held.drain(..self.held.partition_point(|held| held.ts <= due)).collect();
+    if let Some(last) = ready.last() {
+      self.released = Some(last.ts);
+    }
+    ready
+  }
+
+  /// Lets out every held sample, oldest first, e.g. when the connection drops.
+  pub fn flush(&mut self) -> Vec<RawTelemetrySample> {
+    let ready = std::mem::take(&mut self.held);
+    if let Some(last) = ready.last() {
+      self.released = Some(last.ts);
+    }
+    ready
+  }
+
+  pub fn reset(&mut self) {
+    *self = Self::default();
+  }
+}
@@ -0,0 +1,161 @@
+use chrono::{DateTime, SecondsFormat, Utc};
+use napi_derive::napi;
+use serde::{Deserialize, Serialize};
+
+use crate::roast_events::{DetectedEvent, RoastEventType};
+
+/// The roast phases, each starting at the event before it: CHARGE, DRY_END and FC.
+const PHASES: [&str; 3] = ["drying", "maillard", "development"];
+
+/// Where the roast stands, as a share of the time since CHARGE.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[napi(object)]
+pub struct PhaseProgress {
+  /// `drying`, `maillard` or `development`.
+  pub current: String,
+  pub dryingPct: f64,
+  pub maillardPct: f64,
+  /// The development time ratio, in percent.
+  pub developmentPct: f64,
+}
+
+#[derive(Debug, Clone)]
+#[napi(object)]
+pub struct PhaseSummary {
+  pub phase: String,
+  pub startedAt: String,
+  /// When the next phase started; unset for the running phase, which is counted to the latest sample.
+  pub endedAt: Option<String>,
+  pub durationSeconds: f64,
+  /// Share of the time since CHARGE.
+  pub pct: f64,
+  pub startBtC: f64,
+  pub endBtC: f64,
+  /// BT's mean rate of rise over the phase, in °C/min; unset for a phase that hasn't lasted yet.
+  pub meanRorCPerMin: Option<f64>,
+}
+
+/// The current roast from CHARGE, for `getSessionSummary()`.
+#[derive(Debug, Clone)]
+#[napi(object)]
+pub struct SessionSummary {
+  pub machineId: String,
+  pub sessionId: Option<String>,
+  pub chargedAt: String,
+  /// From CHARGE to the latest sample.
+  pub totalSeconds: f64,
+  /// The phases reached so far, in order.
+  pub phases: Vec<PhaseSummary>,
+  /// With `energy`: the energy used so far in the session, in kWh.
+  pub energyKwh: Option<f64>,
+}
+
+/// The phase boundaries of one roast, taken from the detected events, and the latest BT reading. `reset` starts over.
+#[derive(Debug, Default)]
+pub(crate) struct PhaseTracker {
+  /// When each phase reached started, with BT then.
+  starts: Vec<(DateTime<Utc>, f64)>,
+  latest: Option<(DateTime<Utc>, f64)>,
+}
+
+impl PhaseTracker {
+  /// Takes a BT reading, with the event it led to, if any.
+  pub fn observe(&mut self, ts: DateTime<Utc>, bt_c: f64, detected: Option<&DetectedEvent>) {
+    if let Some(event) = detected {
+      let phase = match event.event_type {
+        RoastEventType::Charge => 0,
+        RoastEventType::DryEnd => 1,
+        RoastEventType::FirstCrack => 2,
+        RoastEventType::TurningPoint => usize::MAX,
+      };
+      if phase == 0 {
+        self.reset();
+      }
+      if phase == self.starts.len() {
+        self.starts.push((event.ts, event.bt_c));
+      }
+    }
+    if !self.starts.is_empty() && self.latest.is_none_or(|(at, _)| ts > at) {
+      self.latest = Some((ts, bt_c));
+    }
+  }
+
+  pub fn reset(&mut self) {
+    self.starts.clear();
+    self.latest = None;
+  }
+
+  /// The phase shares as of `ts`; `None` before CHARGE.
+  pub fn progress(&self, ts: DateTime<Utc>) -> Option<PhaseProgress> {
+    let durations = self.durations(ts)?;
+    let share = share_of(durations.iter().sum());
+    let pct = |idx: usize| durations.get(idx).copied().map_or(0.0, &share);
+    Some(PhaseProgress {
+      current: PHASES[durations.len() - 1].to_string(),
+      dryingPct: pct(0),
+      maillardPct: pct(1),
+      developmentPct: pct(2),
+    })
+  }
+
+  /// The phases so far, up to the latest sample; `None` before CHARGE.
+  pub fn summary(
+    &self,
+    machine_id: &str,
+    session_id: Option<String>,
+    energy_kwh: Option<f64>,
+  ) -> Option<SessionSummary> {
+    let (latest_ts, latest_c) = self.latest?;
+    let durations = self.durations(latest_ts)?;
+    let total = durations.iter().sum::<f64>();
+    let share = share_of(total);
+    let format = |ts: DateTime<Utc>| ts.to_rfc3339_opts(SecondsFormat::Millis, true);
+    let phases = self
+      .starts
+      .iter()
+      .zip(durations)
+      .enumerate()
+      .map(|(idx, (&(started, start_c), duration))| {
+        let end = self.starts.get(idx + 1).copied();
+        let end_c = end.map_or(latest_c, |(_, end_c)| end_c);
+        PhaseSummary {
+          phase: PHASES[idx].to_string(),
+          startedAt: format(started),
+          endedAt: end.map(|(ended, _)| format(ended)),
+          durationSeconds: duration,
+          pct: share(duration),
+          startBtC: start_c,
+          endBtC: end_c,
+          meanRorCPerMin: (duration > 0.0).then(|| (end_c - start_c) / duration * 60.0),
+        }
+      })
+      .collect();
+    Some(SessionSummary {
+      machineId: machine_id.to_string(),
+      sessionId: session_id,
+      chargedAt: format(self.starts[0].0),
+      totalSeconds: total,
+      phases,
+      energyKwh: energy_kwh,
+    })
+  }
+
+  /// How long each phase reached has lasted as of `ts`, in seconds.
+  fn durations(&self, ts: DateTime<Utc>) -> Option<Vec<f64>> {
+    if self.starts.is_empty() {
+      return None;
+    }
+    let ends = self.starts.iter().skip(1).map(|(at, _)| *at).chain(std::iter::once(ts));
+    let durations = self.starts.iter().zip(ends).map(|((start, _), end)| seconds_between(*start, end).max(0.0));
+    Some(durations.collect())
+  }
+}
+
+/// A duration as a percentage of `total`.
+fn share_of(total: f64) -> impl Fn(f64) -> f64 {
+  move |duration| if total > 0.0 { duration / total * 100.0 } else { 0.0 }
+}
+
+fn seconds_between(from: DateTime<Utc>, to: DateTime<Utc>) -> f64 {
+  to.signed_duration_since(from).num_milliseconds() as f64 / 1000.0
+}
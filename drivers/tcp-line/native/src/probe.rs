@@ -0,0 +1,172 @@
+use std::time::Duration;
+
+use napi::bindgen_prelude::*;
+use napi_derive::napi;
+use tokio::net::TcpStream;
+use tokio::time::Instant;
+
+use crate::csv_record::{split_record, ColumnMismatch};
+use crate::detect::{self, DetectedFormat};
+use crate::framing::{Encoding, FrameReader, Framing, ReadFrame};
+use crate::{CsvConfig, ExtraEntry, FrameFormat, PipelineConfig, TcpLineDriverConfig, TcpLineParser};
+
+/// Lines read before the probe stops listening.
+const PROBE_LINES: usize = 5;
+const DEFAULT_TIMEOUT_MS: u32 = 5000;
+/// Positional columns of CSV without a header, as the driver reads them.
+const DEFAULT_CSV_COLUMNS: &[&str] = &["ts", "btC", "etC", "powerPct", "fanPct", "drumRpm"];
+
+/// What `probeEndpoint` read from a device.
+#[derive(Debug, Clone)]
+#[napi(object)]
+pub struct ProbeResult {
+  /// Format the lines were parsed as: the one asked for, or the detected one. `null` when nothing fit.
+  pub format: Option<String>,
+  /// CSV delimiter, detected from the lines.
+  pub delimiter: Option<String>,
+  /// The first CSV line is a header.
+  pub hasHeader: Option<bool>,
+  /// Field names: the JSON keys seen, the CSV header, or the positional columns CSV without a header is read as.
+  pub columns: Vec<String>,
+  /// Raw lines received, without their line endings.
+  pub lines: Vec<String>,
+  /// The last line that parsed into a sample.
+  pub sample: Option<ProbeSample>,
+  pub parseErrors: u32,
+  /// Why the first failing line didn't parse.
+  pub firstError: Option<String>,
+}
+
+#[derive(Debug, Clone)]
+#[napi(object)]
+pub struct ProbeSample {
+  pub ts: String,
+  pub btC: Option<f64>,
+  pub etC: Option<f64>,
+  pub gasPct: Option<f64>,
+  pub fanPct: Option<f64>,
+  pub drumRpm: Option<f64>,
+  pub extras: Option<Vec<ExtraEntry>>,
+}
+
+/// Connects to `host:port`, reads up to five newline-terminated lines within `timeoutMs` (5000), and parses them as
+/// `format` (`jsonl`, `csv` or `keyValue`), or as the format detected from the lines when it is omitted. For a "test
+/// connection" button: rejects when the connection fails or no line arrives in time.
+#[napi]
+pub async fn probe_endpoint(
+  host: String,
+  port: u32,
+  format: Option<String>,
+  timeout_ms: Option<u32>,
+) -> Result<ProbeResult> {
+  let port = u16::try_from(port)
+    .ok()
+    .filter(|port| *port > 0)
+    .ok_or_else(|| Error::from_reason("port must be between 1 and 65535"))?;
+  let requested = match format.as_deref() {
+    None | Some("autodetect") => None,
+    Some("jsonl") => Some(FrameFormat::Jsonl),
+    Some("csv") => Some(FrameFormat::Csv),
+    Some("keyValue") => Some(FrameFormat::KeyValue),
+    Some(other) => {
+      return Err(Error::from_reason(format!("unsupported probe format \"{}\": use jsonl, csv or keyValue", other)))
+    }
+  };
+  let timeout_ms = timeout_ms.unwrap_or(DEFAULT_TIMEOUT_MS) as u64;
+  let deadline = Instant::now() + Duration::from_millis(timeout_ms);
+
+  let stream = match tokio::time::timeout_at(deadline, TcpStream::connect((host.as_str(), port))).await {
+    Ok(Ok(stream)) => stream,
+    Ok(Err(err)) => return Err(Error::from_reason(format!("connection failure: {}:{}: {}", host, port, err))),
+    Err(_) => return Err(Error::from_reason(format!("connection failure: timed out after {} ms", timeout_ms))),
+  };
+  let mut reader = FrameReader::new(stream, Framing::Newline, "\n", 64 * 1024);
+  let mut frame = Vec::new();
+  let mut lines = Vec::new();
+  while lines.len() < PROBE_LINES {
+    match tokio::time::timeout_at(deadline, reader.read_frame(&mut frame)).await {
+      Ok(Ok(ReadFrame::Frame(_))) => {
+        let text = Encoding::Lossy.decode(&frame).unwrap_or_default();
+        lines.push(text.trim_end_matches(['\n', '\r']).to_string());
+      }
+      Ok(Ok(ReadFrame::Invalid(..))) => {}
+      Ok(Ok(ReadFrame::Closed)) | Ok(Err(_)) | Err(_) => break,
+    }
+  }
+  if lines.is_empty() {
+    return Err(Error::from_reason(format!("no lines received from {}:{} within {} ms", host, port, timeout_ms)));
+  }
+  Ok(parse_lines(requested, lines))
+}
+
+fn parse_lines(requested: Option<FrameFormat>, lines: Vec<String>) -> ProbeResult {
+  let detected = detect::detect(&lines);
+  let format = match (requested, detected) {
+    (Some(FrameFormat::Jsonl), _) => Some(DetectedFormat::Jsonl),
+    (Some(FrameFormat::KeyValue), _) => Some(DetectedFormat::KeyValue),
+    (Some(_), Some(csv @ DetectedFormat::Csv { .. })) => Some(csv),
+    (Some(_), _) => Some(DetectedFormat::Csv { delimiter: ",".to_string(), has_header: false }),
+    (None, detected) => detected,
+  };
+  let mut result = ProbeResult {
+    format: format.as_ref().map(|format| format.name().to_string()),
+    delimiter: None,
+    hasHeader: None,
+    columns: Vec::new(),
+    lines,
+    sample: None,
+    parseErrors: 0,
+    firstError: None,
+  };
+  let Some(format) = format else {
+    return result;
+  };
+
+  result.columns = detect::columns(&format, &result.lines);
+  let (frame_format, csv) = match &format {
+    DetectedFormat::Jsonl => (FrameFormat::Jsonl, probe_csv(",", false)),
+    DetectedFormat::KeyValue => (FrameFormat::KeyValue, probe_csv(",", false)),
+    DetectedFormat::Csv { delimiter, has_header } => {
+      result.delimiter = Some(delimiter.clone());
+      result.hasHeader = Some(*has_header);
+      if !has_header {
+        let first = result.lines.iter().find(|line| !line.trim().is_empty());
+        let width = first.and_then(|line| split_record(line, delimiter).ok()).map_or(0, |row| row.len());
+        result.columns = DEFAULT_CSV_COLUMNS.iter().take(width).map(|column| column.to_string()).collect();
+      }
+      (FrameFormat::Csv, probe_csv(delimiter, *has_header))
+    }
+  };
+  let config = TcpLineDriverConfig::for_source(frame_format, csv, PipelineConfig::new(0, 0));
+  let mut parser = TcpLineParser::new(config.parser());
+  for line in &result.lines {
+    match parser.parse_line(line) {
+      Ok(Some(sample)) => {
+        result.sample = Some(ProbeSample {
+          ts: sample.ts.to_rfc3339_opts(chrono::SecondsFormat::Millis, true),
+          btC: sample.bt_c,
+          etC: sample.et_c,
+          gasPct: sample.power_pct,
+          fanPct: sample.fan_pct,
+          drumRpm: sample.drum_rpm,
+          extras: sample.extras,
+        })
+      }
+      Ok(None) => {}
+      Err(err) => {
+        result.parseErrors += 1;
+        result.firstError.get_or_insert_with(|| err.to_string());
+      }
+    }
+  }
+  result
+}
+
+fn probe_csv(delimiter: &str, has_header: bool) -> CsvConfig {
+  CsvConfig {
+    has_header,
+    columns: Vec::new(),
+    delimiter: delimiter.to_string(),
+    column_mismatch: ColumnMismatch::Lenient,
+  }
+}
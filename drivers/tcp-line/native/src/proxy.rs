@@ -0,0 +1,186 @@
+use std::io;
+use std::net::IpAddr;
+
+use schemars::JsonSchema;
+use serde::Deserialize;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::TcpStream;
+
+/// Longest CONNECT response head accepted before giving up on the proxy.
+const MAX_RESPONSE_HEAD_BYTES: usize = 8 * 1024;
+
+#[derive(Debug, Clone, PartialEq, Deserialize, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct ProxyConfig {
+  #[serde(rename = "type")]
+  pub kind: ProxyKind,
+  pub host: String,
+  pub port: u16,
+  /// Sent as SOCKS5 username/password auth or as an HTTP `Proxy-Authorization: Basic` header.
+  #[serde(default)]
+  pub username: Option<String>,
+  #[serde(default)]
+  pub password: Option<String>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Deserialize, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub(crate) enum ProxyKind {
+  /// SOCKS5 (RFC 1928); the proxy resolves hostnames.
+  Socks5,
+  /// HTTP `CONNECT` tunnel.
+  Http,
+}
+
+impl ProxyConfig {
+  /// Connects to the proxy and has it open a tunnel to `host:port`; the returned stream then carries the device's
+  /// bytes as if connected directly.
+  pub async fn connect(&self, host: &str, port: u16) -> io::Result<TcpStream> {
+    let mut stream = TcpStream::connect((self.host.as_str(), self.port))
+      .await
+      .map_err(|err| io::Error::new(err.kind(), format!("proxy {}:{}: {}", self.host, self.port, err)))?;
+    match self.kind {
+      ProxyKind::Socks5 => self.socks5_handshake(&mut stream, host, port).await?,
+      ProxyKind::Http => self.http_connect(&mut stream, host, port).await?,
+    }
+    Ok(stream)
+  }
+
+  async fn socks5_handshake(&self, stream: &mut TcpStream, host: &str, port: u16) -> io::Result<()> {
+    let methods: &[u8] = if self.username.is_some() { &[0x00, 0x02] } else { &[0x00] };
+    let mut greeting = vec![0x05, methods.len() as u8];
+    greeting.extend_from_slice(methods);
+    stream.write_all(&greeting).await?;
+    let mut choice = [0u8; 2];
+    stream.read_exact(&mut choice).await?;
+    if choice[0] != 0x05 {
+      return Err(proxy_error("not a SOCKS5 proxy"));
+    }
+    match choice[1] {
+      0x00 => {}
+      0x02 => {
+        let Some(username) = self.username.as_deref() else {
+          return Err(proxy_error("SOCKS5 proxy requires a username"));
+        };
+        let password = self.password.as_deref().unwrap_or_default();
+        let mut auth = vec![0x01, username.len() as u8];
+        auth.extend_from_slice(username.as_bytes());
+        auth.push(password.len() as u8);
+        auth.extend_from_slice(password.as_bytes());
+        stream.write_all(&auth).await?;
+        let mut status = [0u8; 2];
+        stream.read_exact(&mut status).await?;
+        if status[1] != 0x00 {
+          return Err(proxy_error("SOCKS5 proxy rejected the credentials"));
+        }
+      }
+      _ => return Err(proxy_error("SOCKS5 proxy accepts none of the offered auth methods")),
+    }
+
+    let mut request = vec![0x05, 0x01, 0x00];
+    match host.trim_start_matches('[').trim_end_matches(']').parse::<IpAddr>() {
+      Ok(IpAddr::V4(addr)) => {
+        request.push(0x01);
+        request.extend_from_slice(&addr.octets());
+      }
+      Ok(IpAddr::V6(addr)) => {
+        request.push(0x04);
+        request.extend_from_slice(&addr.octets());
+      }
+      Err(_) if host.len() > 255 => return Err(proxy_error("SOCKS5 hostnames are limited to 255 bytes")),
+      Err(_) => {
+        request.push(0x03);
+        request.push(host.len() as u8);
+        request.extend_from_slice(host.as_bytes());
+      }
+    }
+    request.extend_from_slice(&port.to_be_bytes());
+    stream.write_all(&request).await?;
+
+    let mut reply = [0u8; 4];
+    stream.read_exact(&mut reply).await?;
+    if reply[1] != 0x00 {
+      return Err(proxy_error(&format!("SOCKS5 proxy refused {}:{}: {}", host, port, socks5_reply(reply[1]))));
+    }
+    // The bound address is of no use here, but must be consumed before the device's bytes start.
+    let address_len = match reply[3] {
+      0x01 => 4,
+      0x04 => 16,
+      0x03 => stream.read_u8().await? as usize,
+      other => return Err(proxy_error(&format!("SOCKS5 proxy sent unknown address type {}", other))),
+    };
+    let mut bound = vec![0u8; address_len + 2];
+    stream.read_exact(&mut bound).await?;
+    Ok(())
+  }
+
+  async fn http_connect(&self, stream: &mut TcpStream, host: &str, port: u16) -> io::Result<()> {
+    let authority = if host.contains(':') && !host.starts_with('[') {
+      format!("[{}]:{}", host, port)
+    } else {
+      format!("{}:{}", host, port)
+    };
+    let mut request = format!("CONNECT {} HTTP/1.1\r\nHost: {}\r\n", authority, authority);
+    if let Some(username) = &self.username {
+      let credentials = format!("{}:{}", username, self.password.as_deref().unwrap_or_default());
+      request.push_str(&format!("Proxy-Authorization: Basic {}\r\n", base64(credentials.as_bytes())));
+    }
+    request.push_str("\r\n");
+    stream.write_all(request.as_bytes()).await?;
+
+    // Read byte by byte so nothing the device sends right after the response head is swallowed.
+    let mut head = Vec::new();
+    while !head.ends_with(b"\r\n\r\n") {
+      if head.len() >= MAX_RESPONSE_HEAD_BYTES {
+        return Err(proxy_error("HTTP proxy response head too large"));
+      }
+      head.push(stream.read_u8().await?);
+    }
+    let head = String::from_utf8_lossy(&head);
+    let status_line = head.split("\r\n").next().unwrap_or_default();
+    let status = status_line.split_whitespace().nth(1).and_then(|status| status.parse::<u16>().ok());
+    match status {
+      Some(status) if (200..300).contains(&status) => Ok(()),
+      Some(_) => {
+        let reason = status_line.split_once(' ').map(|(_, rest)| rest).unwrap_or(status_line);
+        Err(proxy_error(&format!("HTTP proxy refused CONNECT {}: {}", authority, reason)))
+      }
+      None => Err(proxy_error("invalid HTTP proxy response")),
+    }
+  }
+}
+
+fn proxy_error(message: &str) -> io::Error {
+  io::Error::other(message.to_string())
+}
+
+fn socks5_reply(code: u8) -> &'static str {
+  match code {
+    0x01 => "general failure",
+    0x02 => "not allowed by ruleset",
+    0x03 => "network unreachable",
+    0x04 => "host unreachable",
+    0x05 => "connection refused",
+    0x06 => "TTL expired",
+    0x07 => "command not supported",
+    0x08 => "address type not supported",
+    _ => "unknown error",
+  }
+}
+
+fn base64(bytes: &[u8]) -> String {
+  const ALPHABET: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+  let mut encoded = String::with_capacity(bytes.len().div_ceil(3) * 4);
+  for chunk in bytes.chunks(3) {
+    let byte = |idx: usize| *chunk.get(idx).unwrap_or(&0) as u32;
+    let triple = byte(0) << 16 | byte(1) << 8 | byte(2);
+    for idx in 0..4 {
+      if idx <= chunk.len() {
+        encoded.push(ALPHABET[(triple >> (18 - 6 * idx) & 0x3f) as usize] as char);
+      } else {
+        encoded.push('=');
+      }
+    }
+  }
+  encoded
+}
@@ -0,0 +1,63 @@
+use std::collections::VecDeque;
+
+use schemars::JsonSchema;
+use serde::Deserialize;
+
+use crate::TelemetryPoint;
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Deserialize, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub(crate) enum QueuePolicy {
+  /// Make room by discarding the oldest queued sample.
+  #[default]
+  DropOldest,
+  /// Discard the incoming sample.
+  DropNewest,
+  /// Stop reading from the source until `drainSamples` makes room, pushing back on the device through TCP flow
+  /// control. Nothing is dropped.
+  Block,
+}
+
+#[derive(Debug, Clone, PartialEq, Deserialize, JsonSchema)]
+#[serde(rename_all = "camelCase", default)]
+pub(crate) struct QueueConfig {
+  pub capacity: u32,
+  pub policy: QueuePolicy,
+}
+
+impl Default for QueueConfig {
+  fn default() -> Self {
+    Self { capacity: 1024, policy: QueuePolicy::DropOldest }
+  }
+}
+
+/// Every accepted sample, in order, until `drainSamples` takes it; `readTelemetry` keeps seeing only the latest.
+#[derive(Default)]
+pub(crate) struct SampleQueue {
+  points: VecDeque<TelemetryPoint>,
+}
+
+impl SampleQueue {
+  /// Queues `point` per the policy; returns whether a sample was dropped.
+  pub fn push(&mut self, config: &QueueConfig, point: TelemetryPoint) -> bool {
+    // `Block` waits for room before reading, so it only overfills after the capacity is lowered; keep everything.
+    if self.points.len() < config.capacity as usize || config.policy == QueuePolicy::Block {
+      self.points.push_back(point);
+      return false;
+    }
+    if config.policy == QueuePolicy::DropOldest {
+      self.points.pop_front();
+      self.points.push_back(point);
+    }
+    true
+  }
+
+  pub fn is_full(&self, config: &QueueConfig) -> bool {
+    self.points.len() >= config.capacity as usize
+  }
+
+  pub fn drain(&mut self, max: Option<usize>) -> Vec<TelemetryPoint> {
+    let count = max.unwrap_or(self.points.len()).min(self.points.len());
+    self.points.drain(..count).collect()
+  }
+}
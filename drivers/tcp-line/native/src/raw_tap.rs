@@ -0,0 +1,120 @@
+use std::time::{Duration, Instant};
+
+use chrono::{SecondsFormat, Utc};
+use napi::bindgen_prelude::*;
+use napi::JsFunction;
+use napi_derive::napi;
+use parking_lot::Mutex;
+
+use crate::events::Subscribers;
+use crate::ParseError;
+
+/// Lines per second forwarded when `onRawLine` gets no limit.
+const DEFAULT_MAX_PER_SECOND: u32 = 20;
+
+/// A line as received from the device, for `onRawLine`.
+#[derive(Debug, Clone)]
+#[napi(object)]
+pub struct RawLineEvent {
+  pub ts: String,
+  /// The decoded frame without its delimiter; bytes that didn't decode are shown as `U+FFFD`.
+  pub line: String,
+  /// `parsed` (it completed a sample), `skipped` (it parsed without one, e.g. a CSV header or part of a record),
+  /// `error`, or `reply` (a command reply).
+  pub outcome: String,
+  /// Why the line didn't parse, for `error`.
+  pub error: Option<String>,
+  /// Remote `ip:port` of the device in listen mode.
+  pub peer: Option<String>,
+  /// Lines left out by the rate limit since the previous event.
+  pub dropped: u32,
+}
+
+pub(crate) enum Outcome<'a> {
+  Parsed,
+  Skipped,
+  Error(&'a ParseError),
+  Reply,
+}
+
+impl<'a> From<std::result::Result<bool, &'a ParseError>> for Outcome<'a> {
+  fn from(processed: std::result::Result<bool, &'a ParseError>) -> Self {
+    match processed {
+      Ok(true) => Outcome::Parsed,
+      Ok(false) => Outcome::Skipped,
+      Err(err) => Outcome::Error(err),
+    }
+  }
+}
+
+/// The `onRawLine` subscribers, fed at most `maxPerSecond` lines a second in total.
+pub(crate) struct RawLineTap {
+  subscribers: Subscribers<RawLineEvent>,
+  limit: Mutex<RateLimit>,
+}
+
+struct RateLimit {
+  max_per_second: u32,
+  window_start: Instant,
+  sent: u32,
+  dropped: u32,
+}
+
+impl RawLineTap {
+  pub fn new() -> Self {
+    Self {
+      subscribers: Subscribers::new(),
+      limit: Mutex::new(RateLimit {
+        max_per_second: DEFAULT_MAX_PER_SECOND,
+        window_start: Instant::now(),
+        sent: 0,
+        dropped: 0,
+      }),
+    }
+  }
+
+  /// Adds a subscriber; `max_per_second` replaces the limit shared by all of them.
+  pub fn subscribe(&self, env: &Env, callback: JsFunction, max_per_second: Option<u32>) -> Result<()> {
+    let max_per_second = max_per_second.unwrap_or(DEFAULT_MAX_PER_SECOND);
+    if max_per_second == 0 {
+      return Err(Error::from_reason("maxPerSecond must be positive"));
+    }
+    self.limit.lock().max_per_second = max_per_second;
+    self.subscribers.subscribe(env, callback)
+  }
+
+  /// Without subscribers the read loop skips building events altogether.
+  pub fn is_active(&self) -> bool {
+    !self.subscribers.is_empty()
+  }
+
+  pub fn emit(&self, line: &str, outcome: Outcome<'_>, peer: Option<String>) {
+    let dropped = {
+      let mut limit = self.limit.lock();
+      if limit.window_start.elapsed() >= Duration::from_secs(1) {
+        limit.window_start = Instant::now();
+        limit.sent = 0;
+      }
+      if limit.sent >= limit.max_per_second {
+        limit.dropped = limit.dropped.saturating_add(1);
+        return;
+      }
+      limit.sent += 1;
+      std::mem::take(&mut limit.dropped)
+    };
+    let (outcome, error) = match outcome {
+      Outcome::Parsed => ("parsed", None),
+      Outcome::Skipped => ("skipped", None),
+      Outcome::Error(err) => ("error", Some(err.to_string())),
+      Outcome::Reply => ("reply", None),
+    };
+    self.subscribers.emit(RawLineEvent {
+      ts: Utc::now().to_rfc3339_opts(SecondsFormat::Millis, true),
+      line: line.to_string(),
+      outcome: outcome.to_string(),
+      error,
+      peer,
+      dropped,
+    });
+  }
+}
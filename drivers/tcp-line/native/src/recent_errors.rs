@@ -0,0 +1,49 @@
+use std::collections::VecDeque;
+
+use chrono::{SecondsFormat, Utc};
+use napi_derive::napi;
+
+/// Parse failures `getRecentErrors` keeps.
+const CAPACITY: usize = 50;
+/// Characters of the offending line kept per failure.
+const MAX_LINE_CHARS: usize = 256;
+
+/// One parse failure, as returned by `getRecentErrors`.
+#[derive(Debug, Clone)]
+#[napi(object)]
+pub struct RecentParseError {
+  pub ts: String,
+  /// Category, e.g. `invalidJson`, `checksumMismatch` or `columnMismatch`.
+  pub kind: String,
+  pub message: String,
+  /// The offending line, cut to 256 characters.
+  pub line: String,
+  /// `line` was cut.
+  pub truncated: bool,
+}
+
+/// The latest parse failures, oldest first; unlike `metrics.lastError` earlier ones survive the next failure.
+#[derive(Default)]
+pub(crate) struct RecentErrors {
+  entries: VecDeque<RecentParseError>,
+}
+
+impl RecentErrors {
+  pub fn push(&mut self, kind: &str, message: String, line: &str) {
+    if self.entries.len() == CAPACITY {
+      self.entries.pop_front();
+    }
+    let cut = line.char_indices().nth(MAX_LINE_CHARS).map(|(idx, _)| idx);
+    self.entries.push_back(RecentParseError {
+      ts: Utc::now().to_rfc3339_opts(SecondsFormat::Millis, true),
+      kind: kind.to_string(),
+      message,
+      line: line[..cut.unwrap_or(line.len())].to_string(),
+      truncated: cut.is_some(),
+    });
+  }
+
+  pub fn snapshot(&self) -> Vec<RecentParseError> {
+    self.entries.iter().cloned().collect()
+  }
+}
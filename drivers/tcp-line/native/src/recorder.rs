@@ -0,0 +1,137 @@
+use std::borrow::Cow;
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, LineWriter, Write};
+use std::path::PathBuf;
+
+use base64::prelude::{Engine, BASE64_STANDARD};
+use chrono::{DateTime, SecondsFormat, Utc};
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Clone, PartialEq, Deserialize, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct RecordConfig {
+  pub path: String,
+  #[serde(default = "default_max_bytes")]
+  pub max_bytes: u64,
+  #[serde(default = "default_max_files")]
+  pub max_files: u32,
+}
+
+impl RecordConfig {
+  pub fn with_path(path: String) -> Self {
+    Self { path, max_bytes: default_max_bytes(), max_files: default_max_files() }
+  }
+}
+
+fn default_max_bytes() -> u64 {
+  10 * 1024 * 1024
+}
+
+fn default_max_files() -> u32 {
+  5
+}
+
+/// One captured frame as written to the NDJSON recording.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct RecordedLine {
+  pub received_at: String,
+  /// The frame as received, up to the delimiter (a `\r` before `\n` is kept). Lossy when the frame isn't UTF-8, with
+  /// the exact bytes in `base64`.
+  pub line: String,
+  #[serde(default, skip_serializing_if = "Option::is_none")]
+  pub base64: Option<String>,
+}
+
+impl RecordedLine {
+  pub fn new(received_at: DateTime<Utc>, frame: &[u8]) -> Self {
+    let (line, base64) = match std::str::from_utf8(frame) {
+      Ok(line) => (line.to_string(), None),
+      Err(_) => (String::from_utf8_lossy(frame).into_owned(), Some(BASE64_STANDARD.encode(frame))),
+    };
+    Self { received_at: received_at.to_rfc3339_opts(SecondsFormat::Millis, true), line, base64 }
+  }
+
+  /// The bytes that were received: `base64` when set (and valid), else `line`.
+  pub fn frame(&self) -> Cow<'_, [u8]> {
+    match self.base64.as_deref().and_then(|encoded| BASE64_STANDARD.decode(encoded).ok()) {
+      Some(bytes) => Cow::Owned(bytes),
+      None => Cow::Borrowed(self.line.as_bytes()),
+    }
+  }
+}
+
+/// Appends raw frames to `path`, rotating to `path.1` .. `path.{max_files - 1}` once `max_bytes` is reached.
+pub(crate) struct LineRecorder {
+  config: RecordConfig,
+  writer: LineWriter<File>,
+  written: u64,
+}
+
+impl LineRecorder {
+  pub fn open(config: RecordConfig) -> io::Result<Self> {
+    let (file, written) = open_append(&config.path)?;
+    Ok(Self { writer: LineWriter::new(file), written, config })
+  }
+
+  pub fn path(&self) -> &str {
+    &self.config.path
+  }
+
+  pub fn write_frame(&mut self, received_at: DateTime<Utc>, frame: &[u8]) -> io::Result<()> {
+    let entry = RecordedLine::new(received_at, frame);
+    let mut encoded = serde_json::to_vec(&entry).map_err(io::Error::other)?;
+    encoded.push(b'\n');
+
+    if self.config.max_bytes > 0 && self.written > 0 && self.written + encoded.len() as u64 > self.config.max_bytes {
+      self.rotate()?;
+    }
+
+    self.writer.write_all(&encoded)?;
+    self.written += encoded.len() as u64;
+    Ok(())
+  }
+
+  pub fn flush(&mut self) -> io::Result<()> {
+    self.writer.flush()
+  }
+
+  fn rotate(&mut self) -> io::Result<()> {
+    self.writer.flush()?;
+    let keep = self.config.max_files.max(1);
+    let base = PathBuf::from(&self.config.path);
+    let rotated = |idx: u32| PathBuf::from(format!("{}.{}", self.config.path, idx));
+
+    // The oldest file falls off the end; everything else shifts up by one.
+    let _ = fs::remove_file(rotated(keep - 1));
+    for idx in (1..keep.saturating_sub(1)).rev() {
+      let from = rotated(idx);
+      if from.exists() {
+        fs::rename(&from, rotated(idx + 1))?;
+      }
+    }
+    if keep > 1 {
+      fs::rename(&base, rotated(1))?;
+    } else {
+      fs::remove_file(&base)?;
+    }
+
+    let (file, written) = open_append(&self.config.path)?;
+    self.writer = LineWriter::new(file);
+    self.written = written;
+    Ok(())
+  }
+}
+
+fn open_append(path: &str) -> io::Result<(File, u64)> {
+  let path = PathBuf::from(path);
+  if let Some(parent) = path.parent() {
+    if !parent.as_os_str().is_empty() {
+      fs::create_dir_all(parent)?;
+    }
+  }
+  let file = OpenOptions::new().create(true).append(true).open(&path)?;
+  let written = file.metadata()?.len();
+  Ok((file, written))
+}
@@ -0,0 +1,61 @@
+use std::sync::{Arc, Weak};
+
+use napi_derive::napi;
+use parking_lot::Mutex;
+
+use crate::{DriverInner, DriverState};
+
+/// Every driver created in this process: the metrics endpoint exports whichever are still alive, and connect-mode
+/// TCP drivers are looked up by endpoint to catch two of them reading one device.
+static DRIVERS: Mutex<Vec<Weak<DriverInner>>> = parking_lot::const_mutex(Vec::new());
+
+pub(crate) fn register(inner: &Arc<DriverInner>) {
+  let mut drivers = DRIVERS.lock();
+  drivers.retain(|driver| driver.strong_count() > 0);
+  drivers.push(Arc::downgrade(inner));
+}
+
+pub(crate) fn live_drivers() -> Vec<Arc<DriverInner>> {
+  let mut drivers = DRIVERS.lock();
+  drivers.retain(|driver| driver.strong_count() > 0);
+  drivers.iter().filter_map(Weak::upgrade).collect()
+}
+
+/// A live driver connecting to `endpoint` (`host:port`), a running one first, for `onDuplicate: "share"`.
+pub(crate) fn find(endpoint: &str) -> Option<Arc<DriverInner>> {
+  let mut drivers = live_drivers();
+  drivers.retain(|driver| driver.endpoint_key().as_deref() == Some(endpoint));
+  let running = drivers.iter().find(|driver| driver.is_running()).cloned();
+  running.or_else(|| drivers.into_iter().next())
+}
+
+/// A driver other than `inner` whose connection loop is running against `endpoint`.
+pub(crate) fn connected_to(endpoint: &str, inner: &DriverInner) -> Option<Arc<DriverInner>> {
+  live_drivers().into_iter().find(|driver| {
+    let other = !std::ptr::eq(Arc::as_ptr(driver), inner);
+    other && driver.is_running() && driver.endpoint_key().as_deref() == Some(endpoint)
+  })
+}
+
+#[derive(Debug, Clone)]
+#[napi(object)]
+pub struct ActiveDriver {
+  pub machineId: String,
+  /// `host:port` of a connect-mode TCP driver; absent for listen mode and the other sources.
+  pub endpoint: Option<String>,
+  pub state: DriverState,
+}
+
+/// The drivers of this process whose connection loop or source is running, e.g. to spot two pointed at one device.
+#[napi]
+pub fn list_active_drivers() -> Vec<ActiveDriver> {
+  live_drivers()
+    .into_iter()
+    .filter(|driver| driver.is_running())
+    .map(|driver| ActiveDriver {
+      machineId: driver.machine_id.clone(),
+      endpoint: driver.endpoint_key(),
+      state: *driver.state.lock(),
+    })
+    .collect()
+}
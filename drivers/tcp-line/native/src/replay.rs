@@ -0,0 +1,310 @@
+use std::borrow::Cow;
+use std::sync::atomic::Ordering;
+use std::sync::Arc;
+use std::time::Duration;
+
+use chrono::{DateTime, Utc};
+use napi::bindgen_prelude::*;
+use napi_derive::napi;
+use serde::Deserialize;
+use tokio::fs::File;
+use tokio::io::{AsyncBufReadExt, BufReader};
+use tokio::time::sleep;
+
+use crate::aggregate::TelemetryAggregate;
+use crate::assembly::AssemblyConfig;
+use crate::cancel::{self, Cancel};
+use crate::checksum::Checksum;
+use crate::events::{DriverError, DriverErrorCode};
+use crate::faults::{FaultConfig, FaultInjector};
+use crate::framing::Encoding;
+use crate::phases::SessionSummary;
+use crate::recorder::RecordedLine;
+use crate::recent_errors::RecentParseError;
+use crate::session_log::SessionLogSummary;
+use crate::stats::DetailedMetrics;
+use crate::stream::TelemetryStream;
+use crate::subscription::TelemetrySubscription;
+use crate::timestamp::TimestampConfig;
+use crate::xml::XmlConfig;
+use crate::{
+  parse_timestamp, validation, CsvConfig, DriverInner, DriverState, DriverStatus, FrameFormat, JsonConfig, ParseError,
+  PipelineConfig, RegexConfig, TcpLineDriverConfig, TelemetryPoint,
+};
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct ReplayDriverConfig {
+  path: String,
+  #[serde(default = "default_speed")]
+  speed: f64,
+  #[serde(default, rename = "loop")]
+  loop_replay: bool,
+  format: FrameFormat,
+  csv: CsvConfig,
+  #[serde(flatten)]
+  pipeline: PipelineConfig,
+  #[serde(default)]
+  regex: Option<RegexConfig>,
+  #[serde(default)]
+  xml: Option<XmlConfig>,
+  #[serde(default)]
+  json: JsonConfig,
+  #[serde(default)]
+  checksum: Checksum,
+  #[serde(default)]
+  encoding: Encoding,
+  #[serde(default)]
+  assembly: Option<AssemblyConfig>,
+  #[serde(default)]
+  timestamp: TimestampConfig,
+  #[serde(default)]
+  faults: Option<FaultConfig>,
+}
+
+fn default_speed() -> f64 {
+  1.0
+}
+
+impl ReplayDriverConfig {
+  fn line_config(&self) -> TcpLineDriverConfig {
+    TcpLineDriverConfig {
+      timestamp: self.timestamp.clone(),
+      regex: self.regex.clone(),
+      xml: self.xml.clone(),
+      json: self.json.clone(),
+      checksum: self.checksum,
+      encoding: self.encoding,
+      assembly: self.assembly.clone(),
+      ..TcpLineDriverConfig::for_source(
+        self.format.clone(),
+        self.csv.clone(),
+        self.pipeline.clone(),
+      )
+    }
+  }
+}
+
+/// Replays a raw-line capture (see `record`) or a plain JSONL/CSV log through the same parser and sample
+/// pipeline as `TcpLineDriverNative`. Captures are paced by `receivedAt`, plain logs by the parsed `ts`;
+/// `speed` divides the original gaps and `0` replays as fast as possible.
+#[napi]
+pub struct ReplayDriverNative {
+  config: ReplayDriverConfig,
+  pub(crate) inner: Arc<DriverInner>,
+}
+
+#[napi]
+impl ReplayDriverNative {
+  #[napi(constructor)]
+  pub fn new(config_json: String, machine_id: String) -> Result<Self> {
+    let config: ReplayDriverConfig = serde_json::from_str(&config_json)
+      .map_err(|err| Error::from_reason(format!("invalid config: {}", err)))?;
+    if !config.speed.is_finite() || config.speed < 0.0 {
+      return Err(Error::from_reason("invalid config: speed must be a non-negative number"));
+    }
+    if let Some(faults) = &config.faults {
+      faults.validate().map_err(|err| Error::from_reason(format!("invalid config: {}", err)))?;
+    }
+    let line_config = config.line_config();
+    validation::check_parsing(&line_config)?;
+    let inner = DriverInner::new(line_config, machine_id);
+    Ok(Self { config, inner })
+  }
+
+  #[napi(ts_args_type = "timeoutMs?: number, cancel?: CancelToken")]
+  pub async fn connect(&self, timeout_ms: Option<u32>, cancel: Option<Cancel>) -> Result<()> {
+    let config = self.config.clone();
+    let since = self.inner.connected_seq.load(Ordering::Relaxed);
+    self.inner.ensure_source(move |inner| run_replay(inner, config));
+    cancel::bounded("connect", self.inner.wait_for_connected(since), timeout_ms, cancel).await
+  }
+
+  #[napi(ts_args_type = "timeoutMs?: number, cancel?: CancelToken")]
+  pub async fn read_telemetry(&self, timeout_ms: Option<u32>, cancel: Option<Cancel>) -> Result<TelemetryPoint> {
+    self.inner.read_telemetry(timeout_ms, cancel).await
+  }
+
+  #[napi(ts_args_type = "timeoutMs: number, cancel?: CancelToken")]
+  pub async fn read_telemetry_with_timeout(
+    &self,
+    timeout_ms: u32,
+    cancel: Option<Cancel>,
+  ) -> Result<Option<TelemetryPoint>> {
+    self.inner.read_telemetry_with_timeout(timeout_ms, cancel).await
+  }
+
+  #[napi]
+  pub async fn peek_telemetry(&self) -> Result<TelemetryPoint> {
+    self.inner.peek_telemetry().await
+  }
+
+  #[napi]
+  pub async fn disconnect(&self) -> Result<()> {
+    self.inner.disconnect().await;
+    Ok(())
+  }
+
+  #[napi]
+  pub fn get_status(&self) -> Result<DriverStatus> {
+    Ok(self.inner.get_status())
+  }
+
+  #[napi]
+  pub fn get_metrics_detailed(&self) -> DetailedMetrics {
+    self.inner.get_metrics_detailed()
+  }
+
+  /// The last 50 parse failures, oldest first, with the offending line cut to 256 characters.
+  #[napi]
+  pub fn get_recent_errors(&self) -> Vec<RecentParseError> {
+    self.inner.get_recent_errors()
+  }
+
+  /// Async-iterator-shaped stream of samples; see `TelemetryStream`.
+  #[napi]
+  pub fn telemetry_stream(&self) -> TelemetryStream {
+    TelemetryStream::new(Arc::clone(&self.inner))
+  }
+
+  /// A handle of its own onto every point published from now on; see `TelemetrySubscription`.
+  #[napi]
+  pub fn subscribe(&self) -> TelemetrySubscription {
+    TelemetrySubscription::new(Arc::clone(&self.inner))
+  }
+
+  /// Takes up to `maxSamples` (default all) queued samples, oldest first; empty unless `queue` is configured.
+  #[napi]
+  pub fn drain_samples(&self, max_samples: Option<u32>) -> Vec<TelemetryPoint> {
+    self.inner.drain_samples(max_samples)
+  }
+
+  /// Per-channel min/max/mean/count over the samples of the last `windowSeconds` (by sample timestamp).
+  #[napi]
+  pub fn read_aggregate(&self, window_seconds: f64) -> Result<TelemetryAggregate> {
+    self.inner.read_aggregate(window_seconds)
+  }
+
+  /// The current roast's phases from CHARGE, with their durations, end temperatures and mean RoR; `null` before
+  /// CHARGE. Needs `roastEvents`.
+  #[napi]
+  pub fn get_session_summary(&self) -> Option<SessionSummary> {
+    self.inner.session_summary()
+  }
+
+  /// Collects every emitted point from now on and writes them to `path` on `stopLogging`. `format` is `csv` or
+  /// `parquet` (default: from the extension, else csv); the header lists the point fields followed by one column per
+  /// extra.
+  #[napi]
+  pub fn start_logging(&self, path: String, format: Option<String>) -> Result<()> {
+    self.inner.start_logging(path, format)
+  }
+
+  /// Writes the log begun by `startLogging` and returns what was written (`null` when not logging).
+  #[napi]
+  pub fn stop_logging(&self) -> Result<Option<SessionLogSummary>> {
+    self.inner.stop_logging()
+  }
+
+  /// Registers a callback for detected roast events (requires `roastEvents` in the config).
+  #[napi(ts_args_type = "callback: (event: RoastEvent) => void")]
+  pub fn on_event(&self, env: Env, callback: JsFunction) -> Result<()> {
+    self.inner.roast_events.subscribe(&env, callback)
+  }
+}
+
+async fn run_replay(inner: Arc<DriverInner>, config: ReplayDriverConfig) {
+  let mut faults = FaultInjector::new(config.faults.clone());
+  loop {
+    inner.reset_connection_state();
+    inner.reset_roast_events();
+    let file = match File::open(&config.path).await {
+      Ok(file) => file,
+      Err(err) => {
+        let message = format!("replay open failed: {}", err);
+        inner.handle_failure(DriverError::new(DriverErrorCode::ConnectFailed, message)).await;
+        return;
+      }
+    };
+    inner.metrics.lock().lastError = None;
+    inner.set_state(DriverState::CONNECTED);
+
+    if let Err(err) = replay_file(&inner, &config, file, &mut faults).await {
+      let message = format!("replay read failed: {}", err);
+      inner.handle_failure(DriverError::new(DriverErrorCode::SocketClosed, message)).await;
+      return;
+    }
+    inner.flush_downsample();
+
+    if !config.loop_replay || inner.stop_flag.load(Ordering::Relaxed) {
+      break;
+    }
+  }
+  inner.metrics.lock().lastError = Some("replay finished".to_string());
+  inner.set_state(DriverState::DISCONNECTED);
+}
+
+async fn replay_file(
+  inner: &DriverInner,
+  config: &ReplayDriverConfig,
+  file: File,
+  faults: &mut FaultInjector,
+) -> std::io::Result<()> {
+  let mut reader = BufReader::new(file);
+  let mut buf = Vec::new();
+  let mut previous_at: Option<DateTime<Utc>> = None;
+
+  loop {
+    inner.wait_for_queue_space().await;
+    buf.clear();
+    if reader.read_until(b'\n', &mut buf).await? == 0 || inner.stop_flag.load(Ordering::Relaxed) {
+      break;
+    }
+    // The replay resumes where it left off once the connection is back, without pacing the outage.
+    if faults.disconnect(inner).await.is_some() {
+      if inner.stop_flag.load(Ordering::Relaxed) {
+        break;
+      }
+      previous_at = None;
+    }
+    // Captures written by `record` carry the original frame and receive time; everything else is the line itself.
+    let recorded = serde_json::from_slice::<RecordedLine>(&buf).ok();
+    let (frame, received_at) = match &recorded {
+      Some(recorded) => (recorded.frame(), parse_timestamp(&recorded.received_at).ok()),
+      None => (Cow::Borrowed(buf.as_slice()), None),
+    };
+    inner.count_line(frame.len() + 1);
+    let Some(text) = config.encoding.decode(&frame) else {
+      inner.count_parse_error(&ParseError::InvalidUtf8, &String::from_utf8_lossy(&frame));
+      continue;
+    };
+    let line = text.trim_end_matches(['\n', '\r']).to_string();
+    let line = if faults.corrupts() { faults.garble(&line) } else { line };
+
+    let parsed = inner.parse_line(line.trim_end());
+    let samples = match parsed {
+      Ok(samples) => samples,
+      Err(err) => {
+        inner.count_parse_error(&err, &line);
+        continue;
+      }
+    };
+
+    let at = received_at.or(samples.first().map(|sample| sample.ts));
+    if let (Some(at), Some(previous)) = (at, previous_at) {
+      let gap_ms = at.signed_duration_since(previous).num_milliseconds();
+      if config.speed > 0.0 && gap_ms > 0 {
+        sleep(Duration::from_millis((gap_ms as f64 / config.speed) as u64)).await;
+      }
+    }
+    if at.is_some() {
+      previous_at = at;
+    }
+
+    faults.delay().await;
+    for sample in samples {
+      inner.accept_sample(sample);
+    }
+  }
+  Ok(())
+}
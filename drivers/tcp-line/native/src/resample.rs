@@ -0,0 +1,149 @@
+use std::collections::VecDeque;
+use std::sync::Arc;
+use std::time::Duration;
+
+use chrono::{DateTime, TimeDelta, Utc};
+use schemars::JsonSchema;
+use serde::Deserialize;
+use tokio::task::JoinHandle;
+
+use crate::channels;
+use crate::{DriverInner, Quality, RawTelemetrySample};
+
+/// How often an idle ticker checks whether `resample` was turned on by a config update.
+const IDLE_POLL: Duration = Duration::from_secs(1);
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Deserialize, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub(crate) enum ResampleMode {
+  /// The latest sample at each grid time.
+  #[default]
+  Hold,
+  /// The line between the samples around each grid time, emitted one interval late so the later one has arrived.
+  Linear,
+}
+
+#[derive(Debug, Clone, PartialEq, Deserialize, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct ResampleConfig {
+  /// Grid spacing on the gateway clock; points land on its multiples, e.g. every whole second for `1000`.
+  pub interval_ms: u64,
+  #[serde(default)]
+  pub mode: ResampleMode,
+}
+
+/// The recent emitted samples that grid points are made from, by the time they were received.
+#[derive(Debug, Default)]
+pub(crate) struct Resampler {
+  samples: VecDeque<RawTelemetrySample>,
+}
+
+impl Resampler {
+  pub fn record(&mut self, sample: RawTelemetrySample) {
+    self.samples.push_back(sample);
+  }
+
+  /// The most recent sample recorded.
+  pub fn last(&self) -> Option<&RawTelemetrySample> {
+    self.samples.back()
+  }
+
+  /// The grid point for the tick at `tick`, or `None` before the first sample of the connection.
+  pub fn at(&mut self, config: &ResampleConfig, tick: DateTime<Utc>) -> Option<RawTelemetrySample> {
+    let interval = TimeDelta::milliseconds(config.interval_ms as i64);
+    match config.mode {
+      ResampleMode::Hold => {
+        while self.samples.len() > 1 {
+          self.samples.pop_front();
+        }
+        let latest = self.samples.back()?;
+        let mut point = latest.clone();
+        // Fresh only if it arrived within the interval the point closes.
+        if latest.received_at <= tick - interval {
+          mark_held(&mut point, channels::keys(latest));
+        }
+        point.ts = tick;
+        point.device_ts = false;
+        Some(point)
+      }
+      ResampleMode::Linear => {
+        let at = tick - interval;
+        while self.samples.get(1).is_some_and(|next| next.received_at <= at) {
+          self.samples.pop_front();
+        }
+        let before = self.samples.front().filter(|before| before.received_at <= at)?;
+        let mut point = match self.samples.get(1) {
+          Some(after) => interpolate(before, after, at),
+          None => {
+            let mut point = before.clone();
+            mark_held(&mut point, channels::keys(before));
+            point
+          }
+        };
+        point.ts = at;
+        point.device_ts = false;
+        Some(point)
+      }
+    }
+  }
+
+  pub fn reset(&mut self) {
+    self.samples.clear();
+  }
+}
+
+fn mark_held(point: &mut RawTelemetrySample, keys: Vec<String>) {
+  for key in keys {
+    point.quality.insert(key, Quality::Held);
+  }
+}
+
+/// `before` with each numeric reading moved along the line to `after` as of `at`; readings `after` lacks are held.
+fn interpolate(before: &RawTelemetrySample, after: &RawTelemetrySample, at: DateTime<Utc>) -> RawTelemetrySample {
+  let span = (after.received_at - before.received_at).num_milliseconds().max(1) as f64;
+  let fraction = (at - before.received_at).num_milliseconds() as f64 / span;
+  let mut point = before.clone();
+  for key in channels::keys(before) {
+    match (channels::reading(before, &key), channels::reading(after, &key)) {
+      (Some(from), Some(to)) => {
+        channels::set_reading(&mut point, &key, from + (to - from) * fraction, Quality::Interpolated);
+      }
+      _ => {
+        point.quality.insert(key, Quality::Held);
+      }
+    }
+  }
+  point
+}
+
+/// Emits the driver's `resample` grid points at every multiple of `intervalMs` on the wall clock, until dropped.
+pub(crate) struct GridTicker {
+  handle: JoinHandle<()>,
+}
+
+impl GridTicker {
+  pub fn spawn(inner: Arc<DriverInner>) -> Self {
+    let handle = tokio::spawn(async move {
+      loop {
+        let interval_ms = inner.config().resample.as_ref().map(|resample| resample.interval_ms as i64);
+        let Some(interval_ms) = interval_ms.filter(|interval_ms| *interval_ms > 0) else {
+          tokio::time::sleep(IDLE_POLL).await;
+          continue;
+        };
+        let now = Utc::now().timestamp_millis();
+        let next = (now / interval_ms + 1) * interval_ms;
+        tokio::time::sleep(Duration::from_millis((next - now) as u64)).await;
+        if let Some(tick) = DateTime::from_timestamp_millis(next) {
+          inner.emit_grid(tick);
+        }
+      }
+    });
+    Self { handle }
+  }
+}
+
+impl Drop for GridTicker {
+  fn drop(&mut self) {
+    self.handle.abort();
+  }
+}
@@ -0,0 +1,165 @@
+use std::collections::VecDeque;
+
+use chrono::{DateTime, Utc};
+use napi_derive::napi;
+use schemars::JsonSchema;
+use serde::Deserialize;
+
+#[derive(Debug, Clone, PartialEq, Deserialize, JsonSchema)]
+#[serde(rename_all = "camelCase", default)]
+pub(crate) struct RoastEventConfig {
+  /// BT drop below the recent peak that counts as CHARGE (beans hitting the probe).
+  pub charge_drop_c: f64,
+  /// How far back the peak for `chargeDropC` is looked for.
+  pub charge_window_s: f64,
+  /// Span the rate of rise is computed over; longer is steadier but later.
+  pub ror_window_s: f64,
+  pub dry_end_c: f64,
+  pub first_crack_c: f64,
+}
+
+impl Default for RoastEventConfig {
+  fn default() -> Self {
+    Self { charge_drop_c: 10.0, charge_window_s: 30.0, ror_window_s: 30.0, dry_end_c: 150.0, first_crack_c: 196.0 }
+  }
+}
+
+#[derive(Debug, PartialEq, Eq)]
+#[napi(string_enum)]
+pub enum RoastEventType {
+  #[napi(value = "CHARGE")]
+  Charge,
+  #[napi(value = "TURNING_POINT")]
+  TurningPoint,
+  #[napi(value = "DRY_END")]
+  DryEnd,
+  #[napi(value = "FC")]
+  FirstCrack,
+}
+
+#[derive(Debug, Clone)]
+#[napi(object)]
+pub struct RoastEvent {
+  #[napi(js_name = "type")]
+  pub eventType: RoastEventType,
+  /// When the event happened, which can precede detection: CHARGE is the BT peak before the drop, TURNING_POINT the
+  /// BT minimum.
+  pub ts: String,
+  pub machineId: String,
+  pub btC: f64,
+  /// Rate of rise at detection, in °C/min; absent until `rorWindowS` of samples is available.
+  pub rorCPerMin: Option<f64>,
+  pub sessionId: Option<String>,
+}
+
+pub(crate) struct DetectedEvent {
+  pub event_type: RoastEventType,
+  pub ts: DateTime<Utc>,
+  pub bt_c: f64,
+  pub ror_c_per_min: Option<f64>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Phase {
+  AwaitCharge,
+  AwaitTurningPoint,
+  AwaitDryEnd,
+  AwaitFirstCrack,
+  Done,
+}
+
+/// Walks one roast through CHARGE → TURNING_POINT → DRY_END → FC, each detected once. `reset` starts over.
+pub(crate) struct RoastEventDetector {
+  config: RoastEventConfig,
+  phase: Phase,
+  window: VecDeque<(DateTime<Utc>, f64)>,
+  minimum: Option<(DateTime<Utc>, f64)>,
+  falling: bool,
+}
+
+impl RoastEventDetector {
+  pub fn new(config: RoastEventConfig) -> Self {
+    Self { config, phase: Phase::AwaitCharge, window: VecDeque::new(), minimum: None, falling: false }
+  }
+
+  pub fn config(&self) -> &RoastEventConfig {
+    &self.config
+  }
+
+  pub fn reset(&mut self) {
+    self.phase = Phase::AwaitCharge;
+    self.window.clear();
+    self.minimum = None;
+    self.falling = false;
+  }
+
+  pub fn observe(&mut self, ts: DateTime<Utc>, bt_c: f64) -> Option<DetectedEvent> {
+    if self.window.back().is_some_and(|(last, _)| ts <= *last) {
+      return None;
+    }
+    self.window.push_back((ts, bt_c));
+    let keep_s = self.config.charge_window_s.max(self.config.ror_window_s);
+    while self.window.front().is_some_and(|(at, _)| seconds_between(*at, ts) > keep_s) {
+      self.window.pop_front();
+    }
+    let ror = self.rate_of_rise(ts, bt_c);
+    let detected = |event_type, ts, bt_c| Some(DetectedEvent { event_type, ts, bt_c, ror_c_per_min: ror });
+
+    match self.phase {
+      Phase::AwaitCharge => {
+        let (peak_ts, peak_c) = self
+          .window
+          .iter()
+          .filter(|(at, _)| seconds_between(*at, ts) <= self.config.charge_window_s)
+          .copied()
+          .max_by(|a, b| a.1.total_cmp(&b.1))?;
+        if peak_c - bt_c < self.config.charge_drop_c {
+          return None;
+        }
+        self.phase = Phase::AwaitTurningPoint;
+        self.minimum = Some((ts, bt_c));
+        detected(RoastEventType::Charge, peak_ts, peak_c)
+      }
+      Phase::AwaitTurningPoint => {
+        if self.minimum.is_none_or(|(_, min_c)| bt_c < min_c) {
+          self.minimum = Some((ts, bt_c));
+        }
+        let ror = ror?;
+        if ror < 0.0 {
+          self.falling = true;
+          return None;
+        }
+        if !self.falling {
+          return None;
+        }
+        self.phase = Phase::AwaitDryEnd;
+        let (min_ts, min_c) = self.minimum?;
+        detected(RoastEventType::TurningPoint, min_ts, min_c)
+      }
+      Phase::AwaitDryEnd if bt_c >= self.config.dry_end_c => {
+        self.phase = Phase::AwaitFirstCrack;
+        detected(RoastEventType::DryEnd, ts, bt_c)
+      }
+      Phase::AwaitFirstCrack if bt_c >= self.config.first_crack_c => {
+        self.phase = Phase::Done;
+        detected(RoastEventType::FirstCrack, ts, bt_c)
+      }
+      _ => None,
+    }
+  }
+
+  /// Slope between the oldest sample within `rorWindowS` and now; needs at least half the window to be covered.
+  fn rate_of_rise(&self, ts: DateTime<Utc>, bt_c: f64) -> Option<f64> {
+    let (first_ts, first_c) =
+      self.window.iter().find(|(at, _)| seconds_between(*at, ts) <= self.config.ror_window_s).copied()?;
+    let span_s = seconds_between(first_ts, ts);
+    if span_s < self.config.ror_window_s / 2.0 || span_s <= 0.0 {
+      return None;
+    }
+    Some((bt_c - first_c) / span_s * 60.0)
+  }
+}
+
+fn seconds_between(from: DateTime<Utc>, to: DateTime<Utc>) -> f64 {
+  to.signed_duration_since(from).num_milliseconds() as f64 / 1000.0
+}
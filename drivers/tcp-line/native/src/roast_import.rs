@@ -0,0 +1,275 @@
+use std::fs;
+use std::path::Path;
+
+use chrono::{DateTime, NaiveDate, SecondsFormat, Utc};
+use napi::bindgen_prelude::*;
+use napi_derive::napi;
+use serde_json::{Map, Value};
+
+use crate::parse_timestamp;
+use crate::recorder::RecordedLine;
+
+/// Column names, lowercased, read into a point channel; other numeric columns become extras under their own name.
+const CHANNELS: [(&str, &[&str]); 5] = [
+  ("btC", &["bt", "bean temperature", "bean temp", "bean"]),
+  ("etC", &["et", "environment temperature", "environmental temperature", "env temp"]),
+  ("gasPct", &["gas", "burner", "power", "heater"]),
+  ("fanPct", &["fan", "air", "airflow"]),
+  ("drumRpm", &["drum", "drum speed"]),
+];
+const TIME_COLUMNS: [&str; 5] = ["time1", "time", "time (s)", "seconds", "elapsed"];
+const EVENT_COLUMN: &str = "event";
+
+/// An event marked in the log, under the log's own name (e.g. Artisan's `Charge`, `Dry End`, `FCs`).
+#[derive(Debug, Clone)]
+#[napi(object)]
+pub struct ImportedEvent {
+  pub name: String,
+  pub elapsedSeconds: f64,
+}
+
+/// What `importRoastLog` wrote.
+#[derive(Debug, Clone)]
+#[napi(object)]
+pub struct RoastLogImport {
+  pub path: String,
+  /// `artisan` or `cropster`.
+  pub format: String,
+  pub samples: u32,
+  /// The timestamp of the first sample.
+  pub startedAt: String,
+  pub durationSeconds: f64,
+  /// `F` when the log had temperatures in °F, `C` otherwise; the capture has them in °C.
+  pub unit: String,
+  pub events: Vec<ImportedEvent>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum LogFormat {
+  Artisan,
+  Cropster,
+}
+
+impl LogFormat {
+  fn resolve(format: Option<&str>, text: &str) -> std::result::Result<Self, String> {
+    match format {
+      Some("artisan") => Ok(Self::Artisan),
+      Some("cropster") => Ok(Self::Cropster),
+      Some(other) => Err(format!("unknown roast log format {:?}; expected artisan or cropster", other)),
+      None if text.trim_start_matches('\u{feff}').starts_with("Date:") => Ok(Self::Artisan),
+      None => Ok(Self::Cropster),
+    }
+  }
+
+  fn name(self) -> &'static str {
+    match self {
+      Self::Artisan => "artisan",
+      Self::Cropster => "cropster",
+    }
+  }
+}
+
+/// Converts an Artisan CSV export or a Cropster curve CSV at `input_path` into a raw-line capture at `output_path`,
+/// for a replay driver with `format: "jsonl"`: one `{ ts, btC, etC, ... }` line per row, received at its `ts`.
+/// `format` is guessed from the file when omitted. Rows are timed from `startAt`, by default the date in an Artisan
+/// header (else now).
+#[napi]
+pub async fn import_roast_log(
+  input_path: String,
+  output_path: String,
+  format: Option<String>,
+  start_at: Option<String>,
+) -> Result<RoastLogImport> {
+  let start_at = start_at
+    .map(|start_at| parse_timestamp(&start_at).map_err(|err| Error::from_reason(format!("startAt: {}", err))))
+    .transpose()?;
+  let imported = tokio::task::spawn_blocking(move || import(&input_path, &output_path, format.as_deref(), start_at))
+    .await
+    .map_err(|err| Error::from_reason(format!("import failed: {}", err)))?;
+  imported.map_err(Error::from_reason)
+}
+
+fn import(
+  input_path: &str,
+  output_path: &str,
+  format: Option<&str>,
+  start_at: Option<DateTime<Utc>>,
+) -> std::result::Result<RoastLogImport, String> {
+  let text = fs::read_to_string(input_path).map_err(|err| format!("reading {} failed: {}", input_path, err))?;
+  let format = LogFormat::resolve(format, &text)?;
+  let mut lines = text.trim_start_matches('\u{feff}').lines();
+  let (log_date, unit_f) = match format {
+    LogFormat::Artisan => artisan_header(lines.next().unwrap_or_default()),
+    LogFormat::Cropster => (None, false),
+  };
+  let body: Vec<&str> = lines.filter(|line| !line.trim().is_empty()).collect();
+  let header = body.first().ok_or_else(|| "no column header".to_string())?;
+  let rows = read_rows(&body.join("\n"), delimiter_of(header))?;
+  let columns = rows.first().cloned().unwrap_or_default();
+  let keys: Vec<Column> = columns.iter().map(|column| Column::of(column)).collect();
+  // A column is in °F when its header says so, and Artisan's BT and ET are under `Unit:F`.
+  let in_f: Vec<bool> = columns
+    .iter()
+    .zip(&keys)
+    .map(|(column, key)| {
+      column.contains("°F") || column.ends_with("(F)") || (unit_f && matches!(key, Column::Channel("btC" | "etC")))
+    })
+    .collect();
+  let fahrenheit = in_f.iter().any(|in_f| *in_f);
+  let time = keys
+    .iter()
+    .position(|key| matches!(key, Column::Time))
+    .ok_or_else(|| format!("no time column; expected one of {}", TIME_COLUMNS.join(", ")))?;
+  let start = start_at.or(log_date).unwrap_or_else(Utc::now);
+
+  let mut recorded = Vec::new();
+  let mut events = Vec::new();
+  let mut span: Option<(f64, f64)> = None;
+  for (row, cells) in rows.iter().enumerate().skip(1) {
+    let cell = cells.get(time).map(String::as_str).unwrap_or_default();
+    let Some(seconds) = elapsed_seconds(cell) else {
+      return Err(format!("row {}: unreadable time {:?}", row + 1, cell));
+    };
+    let mut fields = Map::new();
+    for ((key, cell), in_f) in keys.iter().zip(cells).zip(&in_f) {
+      let reading = || cell.parse::<f64>().ok().map(|value| if *in_f { celsius(value) } else { value });
+      match key {
+        Column::Event if !cell.is_empty() => {
+          events.push(ImportedEvent { name: cell.to_string(), elapsedSeconds: seconds });
+        }
+        Column::Channel(channel) => {
+          if let Some(value) = reading() {
+            fields.insert(channel.to_string(), Value::from(value));
+          }
+        }
+        Column::Extra(name) => {
+          if let Some(value) = reading() {
+            fields.insert(name.clone(), Value::from(value));
+          }
+        }
+        _ => {}
+      }
+    }
+    if fields.is_empty() {
+      continue;
+    }
+    let at = start + chrono::Duration::milliseconds((seconds * 1000.0).round() as i64);
+    let mut line = Map::new();
+    line.insert("ts".to_string(), Value::from(at.to_rfc3339_opts(SecondsFormat::Millis, true)));
+    line.extend(fields);
+    recorded.push(RecordedLine::new(at, Value::Object(line).to_string().as_bytes()));
+    span = Some((span.map_or(seconds, |(first, _)| first), seconds));
+  }
+  let (Some(first), Some((first_seconds, last_seconds))) = (recorded.first(), span) else {
+    return Err("no samples".to_string());
+  };
+  let started_at = first.received_at.clone();
+
+  let mut encoded = Vec::new();
+  for entry in &recorded {
+    serde_json::to_writer(&mut encoded, entry).map_err(|err| err.to_string())?;
+    encoded.push(b'\n');
+  }
+  let path = Path::new(output_path);
+  if let Some(parent) = path.parent() {
+    if !parent.as_os_str().is_empty() {
+      fs::create_dir_all(parent).map_err(|err| format!("creating {} failed: {}", parent.display(), err))?;
+    }
+  }
+  fs::write(path, encoded).map_err(|err| format!("writing {} failed: {}", output_path, err))?;
+  Ok(RoastLogImport {
+    path: output_path.to_string(),
+    format: format.name().to_string(),
+    samples: recorded.len() as u32,
+    startedAt: started_at,
+    durationSeconds: last_seconds - first_seconds,
+    unit: if fahrenheit { "F" } else { "C" }.to_string(),
+    events,
+  })
+}
+
+/// What a column holds.
+enum Column {
+  Time,
+  Event,
+  Channel(&'static str),
+  Extra(String),
+  /// Artisan's `Time2`, counted from CHARGE rather than the start.
+  Ignored,
+}
+
+impl Column {
+  fn of(name: &str) -> Self {
+    // A unit in the header (`BT (°C)`, `Time (s)`) is left out of the match.
+    let lower = name.trim().to_lowercase();
+    let bare = lower.split(" (").next().unwrap_or_default().trim();
+    let name = name.split(" (").next().unwrap_or_default().trim();
+    if TIME_COLUMNS.contains(&lower.as_str()) || TIME_COLUMNS.contains(&bare) {
+      return Self::Time;
+    }
+    if bare == "time2" {
+      return Self::Ignored;
+    }
+    if bare == EVENT_COLUMN {
+      return Self::Event;
+    }
+    match CHANNELS.iter().find(|(_, names)| names.contains(&bare)) {
+      Some((channel, _)) => Self::Channel(channel),
+      None => Self::Extra(name.to_string()),
+    }
+  }
+}
+
+/// The date and the `F` unit from an Artisan header, `Date:dd.MM.yyyy<TAB>Unit:C<TAB>CHARGE:...`.
+fn artisan_header(line: &str) -> (Option<DateTime<Utc>>, bool) {
+  let mut date = None;
+  let mut fahrenheit = false;
+  for field in line.split('\t') {
+    match field.trim().split_once(':') {
+      Some(("Date", value)) => {
+        date = NaiveDate::parse_from_str(value.trim(), "%d.%m.%Y")
+          .ok()
+          .and_then(|date| date.and_hms_opt(0, 0, 0))
+          .map(|midnight| midnight.and_utc());
+      }
+      Some(("Unit", value)) => fahrenheit = value.trim() == "F",
+      _ => {}
+    }
+  }
+  (date, fahrenheit)
+}
+
+/// Tab, semicolon or comma, whichever the header has most of.
+fn delimiter_of(header: &str) -> u8 {
+  [b'\t', b';', b',']
+    .into_iter()
+    .max_by_key(|delimiter| header.matches(char::from(*delimiter)).count())
+    .unwrap_or(b',')
+}
+
+/// The header row and the data rows, trimmed, rows of any length.
+fn read_rows(text: &str, delimiter: u8) -> std::result::Result<Vec<Vec<String>>, String> {
+  let mut reader = csv::ReaderBuilder::new()
+    .delimiter(delimiter)
+    .has_headers(false)
+    .flexible(true)
+    .trim(csv::Trim::All)
+    .from_reader(text.as_bytes());
+  let rows = reader.records().map(|record| record.map(|record| record.iter().map(str::to_string).collect()));
+  rows.collect::<std::result::Result<_, _>>().map_err(|err| format!("unreadable CSV: {}", err))
+}
+
+/// °F to °C, to a hundredth of a degree.
+fn celsius(fahrenheit: f64) -> f64 {
+  ((fahrenheit - 32.0) / 1.8 * 100.0).round() / 100.0
+}
+
+/// `mm:ss`, `h:mm:ss` or plain seconds.
+fn elapsed_seconds(cell: &str) -> Option<f64> {
+  let mut seconds = 0.0;
+  for part in cell.split(':') {
+    let value = part.trim().parse::<f64>().ok().filter(|value| value.is_finite() && *value >= 0.0)?;
+    seconds = seconds * 60.0 + value;
+  }
+  Some(seconds)
+}
@@ -0,0 +1,74 @@
+use std::collections::HashMap;
+use std::future::Future;
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+use napi::bindgen_prelude::*;
+use napi_derive::napi;
+use parking_lot::Mutex;
+use tokio::runtime::{Builder, Handle, Runtime};
+use tokio::task::JoinHandle;
+
+/// Name `initRuntime` registers under when none is given; drivers without `runtime` use it once it exists.
+const DEFAULT_RUNTIME: &str = "default";
+const DEFAULT_THREAD_NAME_PREFIX: &str = "tcp-line";
+
+/// Runtimes registered by `initRuntime`. They are never dropped: tasks of running drivers live on them.
+static RUNTIMES: Mutex<Option<HashMap<String, Runtime>>> = parking_lot::const_mutex(None);
+
+/// Creates a multi-threaded runtime with `workerThreads` workers named `<threadNamePrefix>-<n>` (`tcp-line-<n>`)
+/// and registers it as `name`. Without `name` it becomes the runtime every driver without a `runtime` setting runs
+/// its connection loop and background tasks on, instead of the Node binding's built-in one; with a name, only
+/// drivers whose config sets `runtime` to it use it. Call it before constructing the drivers. A name can only be
+/// registered once.
+#[napi]
+pub fn init_runtime(worker_threads: u32, thread_name_prefix: Option<String>, name: Option<String>) -> Result<()> {
+  if worker_threads == 0 {
+    return Err(Error::from_reason("workerThreads must be positive"));
+  }
+  let name = name.unwrap_or_else(|| DEFAULT_RUNTIME.to_string());
+  let mut runtimes = RUNTIMES.lock();
+  let runtimes = runtimes.get_or_insert_with(HashMap::new);
+  if runtimes.contains_key(&name) {
+    return Err(Error::from_reason(format!("runtime \"{}\" is already initialised", name)));
+  }
+  let prefix = thread_name_prefix.unwrap_or_else(|| DEFAULT_THREAD_NAME_PREFIX.to_string());
+  let next_thread = AtomicUsize::new(0);
+  let runtime = Builder::new_multi_thread()
+    .worker_threads(worker_threads as usize)
+    .thread_name_fn(move || format!("{}-{}", prefix, next_thread.fetch_add(1, Ordering::Relaxed)))
+    .enable_all()
+    .build()
+    .map_err(|err| Error::from_reason(format!("failed to start runtime \"{}\": {}", name, err)))?;
+  runtimes.insert(name, runtime);
+  Ok(())
+}
+
+/// Handle of the runtime registered as `name` (or the default one), `None` if there is none.
+fn handle(name: Option<&str>) -> Option<Handle> {
+  let runtimes = RUNTIMES.lock();
+  let runtime = runtimes.as_ref()?.get(name.unwrap_or(DEFAULT_RUNTIME))?;
+  Some(runtime.handle().clone())
+}
+
+/// Rejects a driver `runtime` that `initRuntime` hasn't registered.
+pub(crate) fn check(name: Option<&str>) -> Result<()> {
+  match name {
+    Some(name) if handle(Some(name)).is_none() => {
+      Err(Error::from_reason(format!("unknown runtime \"{}\": register it with initRuntime first", name)))
+    }
+    _ => Ok(()),
+  }
+}
+
+/// Spawns a driver task onto `runtime`, the default registered runtime, or else the current one. Tasks it spawns in
+/// turn (sinks, heartbeats) stay on the same runtime.
+pub(crate) fn spawn<F>(runtime: Option<&str>, task: F) -> JoinHandle<F::Output>
+where
+  F: Future + Send + 'static,
+  F::Output: Send + 'static,
+{
+  match handle(runtime) {
+    Some(handle) => handle.spawn(task),
+    None => tokio::spawn(task),
+  }
+}
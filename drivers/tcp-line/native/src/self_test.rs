@@ -0,0 +1,193 @@
+use std::time::{Duration, Instant};
+
+use chrono::{SecondsFormat, Utc};
+use napi_derive::napi;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::TcpListener;
+use tokio::sync::broadcast::{self, error::RecvError};
+
+use crate::csv_record::ColumnMismatch;
+use crate::{CsvConfig, DriverInner, FrameFormat, PipelineConfig, TcpLineDriverConfig, TelemetryPoint};
+
+const DEFAULT_TIMEOUT_MS: u32 = 5000;
+/// The self-test driver's machine id, as `listActiveDrivers` shows it while the test runs.
+const MACHINE_ID: &str = "self-test";
+/// Fixture readings as (btC, etC, fanPct, drumRpm), sent as JSONL lines 100 ms apart on their timestamps.
+const FIXTURES: [(f64, f64, f64, f64); 5] = [
+  (180.5, 210.0, 40.0, 55.0),
+  (150.25, 205.5, 40.0, 55.0),
+  (152.0, 206.0, 45.0, 55.0),
+  (155.75, 207.25, 45.0, 56.0),
+  (160.0, 208.0, 50.0, 56.0),
+];
+/// Sent after the second fixture: the driver has to count it as a parse error and read on.
+const MALFORMED_LINE: &str = "{\"ts\": not json";
+
+/// One stage of `selfTest`.
+#[derive(Debug, Clone)]
+#[napi(object)]
+pub struct SelfTestCheck {
+  /// `loopback`, `connect`, `telemetry`, `parseErrors` or `disconnect`.
+  pub name: String,
+  pub passed: bool,
+  /// What went wrong, for a failed check.
+  pub detail: Option<String>,
+  pub elapsedMs: f64,
+}
+
+/// What `selfTest` found. The checks run in order and stop at the first failure, apart from `disconnect`, which runs
+/// whenever the driver was started.
+#[derive(Debug, Clone)]
+#[napi(object)]
+pub struct SelfTestReport {
+  pub passed: bool,
+  pub checks: Vec<SelfTestCheck>,
+  pub elapsedMs: f64,
+}
+
+/// Streams fixture lines from an in-process loopback server through a TCP driver, over the same socket, framing,
+/// parsing and emit path a device's lines take, and checks every point comes out as sent. For validating an install
+/// on new hardware: resolves with a report rather than rejecting. Each stage gets `timeoutMs` (5000).
+#[napi]
+pub async fn self_test(timeout_ms: Option<u32>) -> SelfTestReport {
+  let started = Instant::now();
+  let mut checks = Checks { done: Vec::new(), since: started };
+  run(Duration::from_millis(timeout_ms.unwrap_or(DEFAULT_TIMEOUT_MS).into()), &mut checks).await;
+  SelfTestReport {
+    passed: checks.done.iter().all(|check| check.passed),
+    checks: checks.done,
+    elapsedMs: elapsed_ms(started),
+  }
+}
+
+struct Checks {
+  done: Vec<SelfTestCheck>,
+  /// When the previous check ended.
+  since: Instant,
+}
+
+impl Checks {
+  /// Records the check `name` and hands back what it produced, if it passed.
+  fn record<T>(&mut self, name: &str, outcome: Result<T, String>) -> Option<T> {
+    let (value, detail) = match outcome {
+      Ok(value) => (Some(value), None),
+      Err(detail) => (None, Some(detail)),
+    };
+    self.done.push(SelfTestCheck {
+      name: name.to_string(),
+      passed: value.is_some(),
+      detail,
+      elapsedMs: elapsed_ms(self.since),
+    });
+    self.since = Instant::now();
+    value
+  }
+}
+
+async fn run(timeout: Duration, checks: &mut Checks) {
+  let bound = match TcpListener::bind(("127.0.0.1", 0)).await {
+    Ok(listener) => listener.local_addr().map(|addr| (listener, addr.port())),
+    Err(err) => Err(err),
+  };
+  let Some((listener, port)) = checks.record("loopback", bound.map_err(|err| format!("bind failed: {}", err))) else {
+    return;
+  };
+
+  let base = Utc::now();
+  let timestamps: Vec<String> = (0..FIXTURES.len() as i64)
+    .map(|idx| (base + chrono::Duration::milliseconds(idx * 100)).to_rfc3339_opts(SecondsFormat::Millis, true))
+    .collect();
+  let mut lines: Vec<String> = FIXTURES
+    .iter()
+    .zip(&timestamps)
+    .map(|((bt_c, et_c, fan_pct, drum_rpm), ts)| {
+      format!("{{\"ts\":\"{}\",\"btC\":{},\"etC\":{},\"fanPct\":{},\"drumRpm\":{}}}", ts, bt_c, et_c, fan_pct, drum_rpm)
+    })
+    .collect();
+  lines.insert(2, MALFORMED_LINE.to_string());
+  let server = tokio::spawn(serve(listener, lines));
+
+  let config = TcpLineDriverConfig {
+    host: "127.0.0.1".to_string(),
+    port,
+    ..TcpLineDriverConfig::for_source(
+      FrameFormat::Jsonl,
+      CsvConfig {
+        has_header: false,
+        columns: Vec::new(),
+        delimiter: ",".to_string(),
+        column_mismatch: ColumnMismatch::default(),
+      },
+      PipelineConfig::new(0, 0),
+    )
+  };
+  let inner = DriverInner::new(config, MACHINE_ID.to_string());
+  // Before connecting, so no point is emitted ahead of the subscription.
+  let mut points = inner.subscribe_points();
+  let connected = match tokio::time::timeout(timeout, inner.connect()).await {
+    Ok(connected) => connected.map_err(|err| err.reason.clone()),
+    Err(_) => Err(format!("not connected within {} ms", timeout.as_millis())),
+  };
+  if checks.record("connect", connected).is_some()
+    && checks.record("telemetry", receive(&mut points, &timestamps, timeout).await).is_some()
+  {
+    let errors = inner.get_status().metrics.parseErrors;
+    let counted = if errors == 1 { Ok(()) } else { Err(format!("{} parse errors counted, expected 1", errors)) };
+    checks.record("parseErrors", counted);
+  }
+
+  let stopped = tokio::time::timeout(timeout, inner.disconnect()).await;
+  checks.record("disconnect", stopped.map_err(|_| format!("not stopped within {} ms", timeout.as_millis())));
+  server.abort();
+}
+
+/// Sends `lines` to the first client, then holds the connection until it hangs up.
+async fn serve(listener: TcpListener, lines: Vec<String>) {
+  let Ok((mut stream, _)) = listener.accept().await else {
+    return;
+  };
+  for line in lines {
+    if stream.write_all(format!("{}\n", line).as_bytes()).await.is_err() {
+      return;
+    }
+  }
+  let mut buf = [0u8; 256];
+  while let Ok(read) = stream.read(&mut buf).await {
+    if read == 0 {
+      break;
+    }
+  }
+}
+
+/// Waits for a point per fixture, each within `timeout`, and compares it with what was sent.
+async fn receive(
+  points: &mut broadcast::Receiver<TelemetryPoint>,
+  timestamps: &[String],
+  timeout: Duration,
+) -> Result<(), String> {
+  for (idx, (fixture, ts)) in FIXTURES.iter().zip(timestamps).enumerate() {
+    let point = match tokio::time::timeout(timeout, points.recv()).await {
+      Ok(Ok(point)) => point,
+      Ok(Err(RecvError::Lagged(count))) => return Err(format!("{} points skipped", count)),
+      Ok(Err(RecvError::Closed)) => return Err("driver closed".to_string()),
+      Err(_) => {
+        let (point, of) = (idx + 1, FIXTURES.len());
+        return Err(format!("point {} of {} not received within {} ms", point, of, timeout.as_millis()));
+      }
+    };
+    let (bt_c, et_c, fan_pct, drum_rpm) = *fixture;
+    let expected = [("btC", bt_c), ("etC", et_c), ("fanPct", fan_pct), ("drumRpm", drum_rpm)];
+    let received = [point.btC, point.etC, point.fanPct, point.drumRpm];
+    if point.ts != *ts {
+      return Err(format!("point {}: ts {}, expected {}", idx + 1, point.ts, ts));
+    }
+    if let Some(((name, want), got)) = expected.iter().zip(received).find(|((_, want), got)| *got != Some(*want)) {
+      return Err(format!("point {}: {} {:?}, expected {}", idx + 1, name, got, want));
+    }
+  }
+  Ok(())
+}
+
+fn elapsed_ms(since: Instant) -> f64 {
+  since.elapsed().as_secs_f64() * 1000.0
+}
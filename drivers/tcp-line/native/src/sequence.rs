@@ -0,0 +1,76 @@
+use napi_derive::napi;
+use schemars::JsonSchema;
+use serde::Deserialize;
+
+#[derive(Debug, Clone, PartialEq, Deserialize, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct SequenceConfig {
+  /// Channel or extra carrying the device's frame counter, e.g. `seq`.
+  pub field: String,
+  /// A step back by more than this is taken as the device restarting (or wrapping) its counter rather than a late
+  /// frame, and counting starts over from it.
+  #[serde(default = "default_reorder_window")]
+  pub reorder_window: u64,
+}
+
+fn default_reorder_window() -> u64 {
+  16
+}
+
+#[derive(Debug, Clone)]
+#[napi(object)]
+pub struct SequenceGapEvent {
+  pub ts: String,
+  /// The counter value that was due.
+  pub expected: f64,
+  pub received: f64,
+  /// Frames skipped, `received - expected`.
+  pub missing: f64,
+  /// Remote `ip:port` of the device in listen mode.
+  pub peer: Option<String>,
+}
+
+/// How a frame's counter relates to the ones before it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum Step {
+  /// The next value, or the first one of a connection.
+  InOrder,
+  /// Past the next value, which was `expected`.
+  Gap { expected: u64 },
+  /// The value of the frame before.
+  Duplicate,
+  /// Behind the highest value seen, within `reorderWindow`.
+  Reordered,
+  /// Further behind: the device restarted its counter.
+  Restarted,
+}
+
+/// The highest counter value seen on one connection.
+#[derive(Debug, Default)]
+pub(crate) struct SequenceTracker {
+  last: Option<u64>,
+}
+
+impl SequenceTracker {
+  pub fn observe(&mut self, config: &SequenceConfig, seq: u64) -> Step {
+    let Some(last) = self.last else {
+      self.last = Some(seq);
+      return Step::InOrder;
+    };
+    let step = match seq.checked_sub(last) {
+      Some(1) => Step::InOrder,
+      Some(0) => Step::Duplicate,
+      Some(_) => Step::Gap { expected: last + 1 },
+      None if last - seq <= config.reorder_window => Step::Reordered,
+      None => Step::Restarted,
+    };
+    if seq > last || step == Step::Restarted {
+      self.last = Some(seq);
+    }
+    step
+  }
+
+  pub fn reset(&mut self) {
+    self.last = None;
+  }
+}
@@ -0,0 +1,20 @@
+use chrono::{SecondsFormat, Utc};
+use napi_derive::napi;
+
+#[derive(Debug, Clone)]
+#[napi(object)]
+pub struct SessionInfo {
+  pub sessionId: String,
+  pub startedAt: String,
+}
+
+impl SessionInfo {
+  /// `session_id` defaults to the machine id plus the start time, e.g. `roaster-1-20250101T080000123Z`.
+  pub(crate) fn start(machine_id: &str, session_id: Option<String>) -> Self {
+    let now = Utc::now();
+    let sessionId = session_id
+      .filter(|id| !id.is_empty())
+      .unwrap_or_else(|| format!("{}-{}", machine_id, now.format("%Y%m%dT%H%M%S%3fZ")));
+    Self { sessionId, startedAt: now.to_rfc3339_opts(SecondsFormat::Millis, true) }
+  }
+}
@@ -0,0 +1,429 @@
+use std::fs::{self, File};
+use std::path::Path;
+use std::sync::Arc;
+
+use chrono::DateTime;
+use napi_derive::napi;
+use parquet::basic::{Compression, LogicalType, Repetition, TimeUnit, Type as PhysicalType};
+use parquet::data_type::{ByteArray, ByteArrayType, DoubleType, Int64Type};
+use parquet::file::properties::WriterProperties;
+use parquet::file::writer::SerializedFileWriter;
+use parquet::schema::types::Type;
+
+use crate::TelemetryPoint;
+
+/// Columns every log starts with; probes and then extras follow.
+const BASE_COLUMNS: [&str; 17] = [
+  "ts",
+  "machineId",
+  "sessionId",
+  "elapsedSeconds",
+  "btC",
+  "etC",
+  "gasPct",
+  "fanPct",
+  "drumRpm",
+  "ambientC",
+  "humidityPct",
+  "pressureHpa",
+  "exhaustC",
+  "inletC",
+  "drumPressurePa",
+  "airflowPct",
+  "energyKwh",
+];
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(crate) enum LogFormat {
+  Csv,
+  Parquet,
+}
+
+impl LogFormat {
+  /// `format` as given to `startLogging`, or guessed from the file extension when omitted.
+  pub fn resolve(format: Option<&str>, path: &str) -> Result<Self, String> {
+    match format {
+      Some("csv") => Ok(Self::Csv),
+      Some("parquet") => Ok(Self::Parquet),
+      Some(other) => Err(format!("unknown log format {:?}; expected csv or parquet", other)),
+      None if path.ends_with(".parquet") => Ok(Self::Parquet),
+      None => Ok(Self::Csv),
+    }
+  }
+
+  fn name(self) -> &'static str {
+    match self {
+      Self::Csv => "csv",
+      Self::Parquet => "parquet",
+    }
+  }
+}
+
+/// Returned by `stopLogging`.
+#[derive(Debug, Clone)]
+#[napi(object)]
+pub struct SessionLogSummary {
+  pub path: String,
+  pub format: String,
+  pub rows: u32,
+  /// Header of the written file, extras included.
+  pub columns: Vec<String>,
+}
+
+/// Emitted points collected between `startLogging` and `stopLogging`. They are kept in memory and written in one go
+/// on stop, because the extras columns are only known once every point has been seen.
+pub(crate) struct SessionLog {
+  path: String,
+  format: LogFormat,
+  /// Names of the configured probes, which get a column whether or not they read.
+  probes: Vec<String>,
+  points: Vec<TelemetryPoint>,
+}
+
+impl SessionLog {
+  /// Creates `path` (and its directory) right away so a bad path fails on start rather than after the roast.
+  pub fn create(path: String, format: LogFormat, probes: Vec<String>) -> std::io::Result<Self> {
+    let file_path = Path::new(&path);
+    if let Some(parent) = file_path.parent() {
+      if !parent.as_os_str().is_empty() {
+        fs::create_dir_all(parent)?;
+      }
+    }
+    File::create(file_path)?;
+    Ok(Self { path, format, probes, points: Vec::new() })
+  }
+
+  pub fn path(&self) -> &str {
+    &self.path
+  }
+
+  pub fn record(&mut self, point: &TelemetryPoint) {
+    self.points.push(point.clone());
+  }
+
+  pub fn finish(self) -> Result<SessionLogSummary, String> {
+    let probes = probe_columns(&self.probes, &self.points);
+    let extras = extra_columns(&self.points, &probes);
+    let file = File::create(&self.path).map_err(|err| err.to_string())?;
+    match self.format {
+      LogFormat::Csv => write_csv(file, &self.points, &probes, &extras)?,
+      LogFormat::Parquet => write_parquet(file, &self.points, &probes, &extras)?,
+    }
+    let columns = BASE_COLUMNS.iter().map(|name| name.to_string());
+    let columns = columns.chain(probes.iter().map(|probe| probe.column.clone()));
+    let columns = columns.chain(extras.iter().map(|extra| extra.column.clone()));
+    Ok(SessionLogSummary {
+      path: self.path,
+      format: self.format.name().to_string(),
+      rows: self.points.len() as u32,
+      columns: columns.collect(),
+    })
+  }
+}
+
+struct ProbeColumn {
+  name: String,
+  /// `probes.` and the probe's name, so a probe named after its channel doesn't clash with it.
+  column: String,
+}
+
+/// The configured probes, then any other probe a point reported (a composite's) in order of first appearance.
+fn probe_columns(configured: &[String], points: &[TelemetryPoint]) -> Vec<ProbeColumn> {
+  let mut names: Vec<&str> = configured.iter().map(String::as_str).collect();
+  for probe in points.iter().flat_map(|point| point.probes.iter().flatten()) {
+    if !names.contains(&probe.name.as_str()) {
+      names.push(&probe.name);
+    }
+  }
+  let column = |name: &str| ProbeColumn { name: name.to_string(), column: format!("probes.{}", name) };
+  names.into_iter().map(column).collect()
+}
+
+fn probe_value(point: &TelemetryPoint, name: &str) -> Option<f64> {
+  point.probes.iter().flatten().find(|probe| probe.name == name).map(|probe| probe.value)
+}
+
+struct ExtraColumn {
+  key: String,
+  /// `key`, prefixed with `extras.` if it would clash with a base or probe column.
+  column: String,
+  /// Only numbers were seen, so Parquet stores it as a double.
+  numeric: bool,
+}
+
+fn extra_columns(points: &[TelemetryPoint], probes: &[ProbeColumn]) -> Vec<ExtraColumn> {
+  let mut columns: Vec<ExtraColumn> = Vec::new();
+  for entry in points.iter().flat_map(|point| point.extras.iter().flatten()) {
+    let numeric = entry.text_value.is_none() && entry.bool_value.is_none();
+    match columns.iter_mut().find(|column| column.key == entry.key) {
+      Some(column) => column.numeric &= numeric,
+      None => {
+        let probe = probes.iter().any(|probe| probe.column == entry.key);
+        let clashes = probe || BASE_COLUMNS.contains(&entry.key.as_str());
+        let column = if clashes { format!("extras.{}", entry.key) } else { entry.key.clone() };
+        columns.push(ExtraColumn { key: entry.key.clone(), column, numeric });
+      }
+    }
+  }
+  columns
+}
+
+/// The extra's value as a cell: numbers as-is, text verbatim, booleans as `true` or `false`.
+fn extra_text(point: &TelemetryPoint, key: &str) -> Option<String> {
+  let entry = point.extras.iter().flatten().find(|entry| entry.key == key)?;
+  let value = entry.number_value.map(|value| value.to_string()).or_else(|| entry.text_value.clone());
+  value.or_else(|| entry.bool_value.map(|flag| flag.to_string()))
+}
+
+fn extra_number(point: &TelemetryPoint, key: &str) -> Option<f64> {
+  point.extras.iter().flatten().find(|entry| entry.key == key).and_then(|entry| entry.number_value)
+}
+
+fn write_csv(
+  file: File,
+  points: &[TelemetryPoint],
+  probes: &[ProbeColumn],
+  extras: &[ExtraColumn],
+) -> Result<(), String> {
+  let mut writer = csv::Writer::from_writer(file);
+  let header = BASE_COLUMNS.iter().copied().chain(probes.iter().map(|probe| probe.column.as_str()));
+  let header = header.chain(extras.iter().map(|extra| extra.column.as_str()));
+  writer.write_record(header).map_err(|err| err.to_string())?;
+  let number = |value: Option<f64>| value.map(|value| value.to_string()).unwrap_or_default();
+  for point in points {
+    let mut row = vec![
+      point.ts.clone(),
+      point.machineId.clone(),
+      point.sessionId.clone().unwrap_or_default(),
+      point.elapsedSeconds.to_string(),
+      number(point.btC),
+      number(point.etC),
+      number(point.power()),
+      number(point.fanPct),
+      number(point.drumRpm),
+      number(point.ambientC),
+      number(point.humidityPct),
+      number(point.pressureHpa),
+      number(point.exhaustC),
+      number(point.inletC),
+      number(point.drumPressurePa),
+      number(point.airflowPct),
+      number(point.energyKwh),
+    ];
+    row.extend(probes.iter().map(|probe| number(probe_value(point, &probe.name))));
+    row.extend(extras.iter().map(|extra| extra_text(point, &extra.key).unwrap_or_default()));
+    writer.write_record(&row).map_err(|err| err.to_string())?;
+  }
+  writer.flush().map_err(|err| err.to_string())
+}
+
+/// One value per row, `None` for a null cell.
+enum ColumnValues {
+  Timestamp(Vec<i64>),
+  Text(Vec<Option<String>>),
+  Number(Vec<Option<f64>>),
+}
+
+fn write_parquet(
+  file: File,
+  points: &[TelemetryPoint],
+  probes: &[ProbeColumn],
+  extras: &[ExtraColumn],
+) -> Result<(), String> {
+  let err = |err: parquet::errors::ParquetError| err.to_string();
+  let number = |field: fn(&TelemetryPoint) -> Option<f64>| ColumnValues::Number(points.iter().map(field).collect());
+  let mut columns = vec![
+    (
+      "ts".to_string(),
+      ColumnValues::Timestamp(
+        points
+          .iter()
+          .map(|point| DateTime::parse_from_rfc3339(&point.ts).map_or(0, |ts| ts.timestamp_millis()))
+          .collect(),
+      ),
+    ),
+    ("machineId".to_string(), ColumnValues::Text(points.iter().map(|point| Some(point.machineId.clone())).collect())),
+    ("sessionId".to_string(), ColumnValues::Text(points.iter().map(|point| point.sessionId.clone()).collect())),
+    ("elapsedSeconds".to_string(), number(|point| Some(point.elapsedSeconds))),
+    ("btC".to_string(), number(|point| point.btC)),
+    ("etC".to_string(), number(|point| point.etC)),
+    ("gasPct".to_string(), number(|point| point.power())),
+    ("fanPct".to_string(), number(|point| point.fanPct)),
+    ("drumRpm".to_string(), number(|point| point.drumRpm)),
+    ("ambientC".to_string(), number(|point| point.ambientC)),
+    ("humidityPct".to_string(), number(|point| point.humidityPct)),
+    ("pressureHpa".to_string(), number(|point| point.pressureHpa)),
+    ("exhaustC".to_string(), number(|point| point.exhaustC)),
+    ("inletC".to_string(), number(|point| point.inletC)),
+    ("drumPressurePa".to_string(), number(|point| point.drumPressurePa)),
+    ("airflowPct".to_string(), number(|point| point.airflowPct)),
+    ("energyKwh".to_string(), number(|point| point.energyKwh)),
+  ];
+  for probe in probes {
+    let values = ColumnValues::Number(points.iter().map(|point| probe_value(point, &probe.name)).collect());
+    columns.push((probe.column.clone(), values));
+  }
+  for extra in extras {
+    let values = if extra.numeric {
+      ColumnValues::Number(points.iter().map(|point| extra_number(point, &extra.key)).collect())
+    } else {
+      ColumnValues::Text(points.iter().map(|point| extra_text(point, &extra.key)).collect())
+    };
+    columns.push((extra.column.clone(), values));
+  }
+
+  let mut fields = Vec::with_capacity(columns.len());
+  for (name, values) in &columns {
+    let (physical, logical, repetition) = match values {
+      ColumnValues::Timestamp(_) => {
+        (PhysicalType::INT64, Some(LogicalType::timestamp(true, TimeUnit::MILLIS)), Repetition::REQUIRED)
+      }
+      ColumnValues::Text(_) => (PhysicalType::BYTE_ARRAY, Some(LogicalType::String), Repetition::OPTIONAL),
+      ColumnValues::Number(_) => (PhysicalType::DOUBLE, None, Repetition::OPTIONAL),
+    };
+    let field = Type::primitive_type_builder(name, physical)
+      .with_repetition(repetition)
+      .with_logical_type(logical)
+      .build()
+      .map_err(err)?;
+    fields.push(Arc::new(field));
+  }
+  let schema = Type::group_type_builder("telemetry").with_fields(fields).build().map_err(err)?;
+  let properties = WriterProperties::builder().set_compression(Compression::SNAPPY).build();
+  let mut writer = SerializedFileWriter::new(file, Arc::new(schema), Arc::new(properties)).map_err(err)?;
+  let mut row_group = writer.next_row_group().map_err(err)?;
+  for (_, values) in &columns {
+    let Some(mut column) = row_group.next_column().map_err(err)? else {
+      break;
+    };
+    match values {
+      ColumnValues::Timestamp(values) => {
+        column.typed::<Int64Type>().write_batch(values, None, None).map_err(err)?;
+      }
+      ColumnValues::Text(values) => {
+        let levels = definition_levels(values);
+        let present = values.iter().flatten().map(|value| ByteArray::from(value.as_str())).collect::<Vec<_>>();
+        column.typed::<ByteArrayType>().write_batch(&present, Some(&levels), None).map_err(err)?;
+      }
+      ColumnValues::Number(values) => {
+        let levels = definition_levels(values);
+        let present = values.iter().flatten().copied().collect::<Vec<_>>();
+        column.typed::<DoubleType>().write_batch(&present, Some(&levels), None).map_err(err)?;
+      }
+    }
+    column.close().map_err(err)?;
+  }
+  row_group.close().map_err(err)?;
+  writer.close().map_err(err)?;
+  Ok(())
+}
+
+fn definition_levels<T>(values: &[Option<T>]) -> Vec<i16> {
+  values.iter().map(|value| i16::from(value.is_some())).collect()
+}
+
+#[cfg(test)]
+mod tests {
+  use std::collections::BTreeMap;
+
+  use parquet::file::reader::{FileReader, SerializedFileReader};
+  use parquet::record::Field;
+  use serde_json::json;
+
+  use super::*;
+
+  /// Logs `points` to a fresh file in `format` and returns its path.
+  fn log(name: &str, format: LogFormat, points: &[TelemetryPoint]) -> String {
+    log_with_probes(name, format, Vec::new(), points)
+  }
+
+  fn log_with_probes(name: &str, format: LogFormat, probes: Vec<String>, points: &[TelemetryPoint]) -> String {
+    let dir = std::env::temp_dir().join(format!("tcp-line-session-log-{}-{}", std::process::id(), name));
+    let path = dir.join("roast").to_string_lossy().into_owned();
+    let mut log = SessionLog::create(path.clone(), format, probes).expect("create");
+    for point in points {
+      log.record(point);
+    }
+    log.finish().expect("finish");
+    path
+  }
+
+  fn csv_rows(path: &str) -> Vec<BTreeMap<String, String>> {
+    let mut reader = csv::Reader::from_path(path).expect("open");
+    let header = reader.headers().expect("header").clone();
+    let rows = reader.records().map(|record| record.expect("row"));
+    rows.map(|row| header.iter().map(str::to_string).zip(row.iter().map(str::to_string)).collect()).collect()
+  }
+
+  fn parquet_rows(path: &str) -> Vec<BTreeMap<String, Field>> {
+    let reader = SerializedFileReader::new(File::open(path).expect("open")).expect("parquet");
+    let rows = reader.get_row_iter(None).expect("rows");
+    let rows = rows.map(|row| row.expect("row"));
+    rows.map(|row| row.get_column_iter().map(|(name, field)| (name.clone(), field.clone())).collect()).collect()
+  }
+
+  #[test]
+  fn writes_the_ambient_channels() {
+    let points = [
+      TelemetryPoint::test(json!({ "ambientC": 21.5, "humidityPct": 40.0, "pressureHpa": 1013.2 })),
+      TelemetryPoint::test(json!({})),
+    ];
+    let rows = csv_rows(&log("ambient-csv", LogFormat::Csv, &points));
+    assert_eq!(rows[0]["ambientC"], "21.5");
+    assert_eq!(rows[0]["humidityPct"], "40");
+    assert_eq!(rows[0]["pressureHpa"], "1013.2");
+    assert_eq!(rows[1]["ambientC"], "");
+
+    let rows = parquet_rows(&log("ambient-parquet", LogFormat::Parquet, &points));
+    assert_eq!(rows[0]["ambientC"], Field::Double(21.5));
+    assert_eq!(rows[0]["humidityPct"], Field::Double(40.0));
+    assert_eq!(rows[0]["pressureHpa"], Field::Double(1013.2));
+    assert_eq!(rows[1]["pressureHpa"], Field::Null);
+  }
+
+  #[test]
+  fn writes_the_registered_channels() {
+    let points = [TelemetryPoint::test(json!({ "exhaustC": 180.0, "inletC": 250.5, "drumPressurePa": -12.0 }))];
+    let rows = csv_rows(&log("registered-csv", LogFormat::Csv, &points));
+    assert_eq!(rows[0]["exhaustC"], "180");
+    assert_eq!(rows[0]["inletC"], "250.5");
+    assert_eq!(rows[0]["drumPressurePa"], "-12");
+    assert_eq!(rows[0]["airflowPct"], "");
+
+    let rows = parquet_rows(&log("registered-parquet", LogFormat::Parquet, &points));
+    assert_eq!(rows[0]["exhaustC"], Field::Double(180.0));
+    assert_eq!(rows[0]["inletC"], Field::Double(250.5));
+    assert_eq!(rows[0]["drumPressurePa"], Field::Double(-12.0));
+    assert_eq!(rows[0]["airflowPct"], Field::Null);
+  }
+
+  #[test]
+  fn writes_the_energy_used() {
+    let points = [TelemetryPoint::test(json!({ "energyKwh": 0.25 }))];
+    assert_eq!(csv_rows(&log("energy-csv", LogFormat::Csv, &points))[0]["energyKwh"], "0.25");
+    assert_eq!(parquet_rows(&log("energy-parquet", LogFormat::Parquet, &points))[0]["energyKwh"], Field::Double(0.25));
+  }
+
+  #[test]
+  fn writes_a_column_per_probe() {
+    let probes = vec!["bt1".to_string(), "bt2".to_string(), "btC".to_string()];
+    let points = [
+      TelemetryPoint::test(json!({ "probes": [
+        { "name": "bt1", "channel": "btC", "value": 200.5 },
+        { "name": "btC", "channel": "btC", "value": 201.0 },
+      ] })),
+      TelemetryPoint::test(json!({ "probes": [{ "name": "et9", "channel": "etC", "value": 230.0 }] })),
+    ];
+    let path = log_with_probes("probes-csv", LogFormat::Csv, probes.clone(), &points);
+    let rows = csv_rows(&path);
+    assert_eq!(rows[0]["probes.bt1"], "200.5");
+    assert_eq!(rows[0]["probes.bt2"], "");
+    assert_eq!(rows[0]["probes.btC"], "201");
+    assert_eq!(rows[1]["probes.et9"], "230");
+
+    let rows = parquet_rows(&log_with_probes("probes-parquet", LogFormat::Parquet, probes, &points));
+    assert_eq!(rows[0]["probes.bt1"], Field::Double(200.5));
+    assert_eq!(rows[0]["probes.bt2"], Field::Null);
+    assert_eq!(rows[1]["probes.et9"], Field::Double(230.0));
+  }
+}
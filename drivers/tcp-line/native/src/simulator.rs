@@ -0,0 +1,393 @@
+use std::collections::BTreeMap;
+use std::sync::atomic::Ordering;
+use std::sync::Arc;
+use std::time::Duration;
+
+use chrono::{DateTime, SecondsFormat, Utc};
+use napi::bindgen_prelude::*;
+use napi_derive::napi;
+use serde::Deserialize;
+use serde_json::Value;
+use tokio::time::sleep;
+
+use crate::aggregate::TelemetryAggregate;
+use crate::cancel::{self, Cancel};
+use crate::csv_record::ColumnMismatch;
+use crate::faults::{FaultConfig, FaultInjector};
+use crate::phases::SessionSummary;
+use crate::rng::Rng;
+use crate::recent_errors::RecentParseError;
+use crate::session_log::SessionLogSummary;
+use crate::stats::DetailedMetrics;
+use crate::stream::TelemetryStream;
+use crate::subscription::TelemetrySubscription;
+use crate::{
+  CsvConfig, DriverInner, DriverState, DriverStatus, ExtraEntry, FrameFormat, PipelineConfig, RawTelemetrySample,
+  TcpLineDriverConfig, TelemetryPoint,
+};
+
+const DEFAULT_EMIT_INTERVAL_MS: u64 = 1000;
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+struct SimulatedDriverConfig {
+  duration_seconds: f64,
+  sample_interval_ms: u64,
+  /// Standard deviation of the gaussian noise added to BT/ET, in °C.
+  noise_c: f64,
+  /// Simulated seconds per wall-clock second.
+  speed: f64,
+  #[serde(rename = "loop")]
+  loop_roast: bool,
+  seed: Option<u64>,
+  charge_temp_c: f64,
+  turning_point_c: f64,
+  dry_end_c: f64,
+  first_crack_c: f64,
+  drop_temp_c: f64,
+  #[serde(flatten)]
+  pipeline: PipelineConfig,
+  faults: Option<FaultConfig>,
+}
+
+impl Default for SimulatedDriverConfig {
+  fn default() -> Self {
+    Self {
+      duration_seconds: 600.0,
+      sample_interval_ms: 1000,
+      noise_c: 0.3,
+      speed: 1.0,
+      loop_roast: false,
+      seed: None,
+      charge_temp_c: 200.0,
+      turning_point_c: 95.0,
+      dry_end_c: 150.0,
+      first_crack_c: 196.0,
+      drop_temp_c: 208.0,
+      pipeline: PipelineConfig::new(DEFAULT_EMIT_INTERVAL_MS, 0),
+      faults: None,
+    }
+  }
+}
+
+impl SimulatedDriverConfig {
+  /// Parses `config_json`, every field optional; serde leaves the flattened pipeline out of the container default,
+  /// so its two required fields are filled in first.
+  fn parse(config_json: &str) -> serde_json::Result<Self> {
+    let mut value: Value = serde_json::from_str(config_json)?;
+    if let Some(object) = value.as_object_mut() {
+      object.entry("emitIntervalMs").or_insert(DEFAULT_EMIT_INTERVAL_MS.into());
+      object.entry("dedupeWithinMs").or_insert(0.into());
+    }
+    serde_json::from_value(value)
+  }
+
+  fn line_config(&self) -> TcpLineDriverConfig {
+    TcpLineDriverConfig::for_source(
+      FrameFormat::Jsonl,
+      CsvConfig {
+        has_header: false,
+        columns: Vec::new(),
+        delimiter: ",".to_string(),
+        column_mismatch: ColumnMismatch::default(),
+      },
+      self.pipeline.clone(),
+    )
+  }
+
+  fn validate(&self) -> std::result::Result<(), String> {
+    if !(self.duration_seconds.is_finite() && self.duration_seconds > 0.0) {
+      return Err("durationSeconds must be positive".to_string());
+    }
+    if self.sample_interval_ms == 0 {
+      return Err("sampleIntervalMs must be positive".to_string());
+    }
+    if !(self.speed.is_finite() && self.speed > 0.0) {
+      return Err("speed must be positive".to_string());
+    }
+    if !(self.noise_c.is_finite() && self.noise_c >= 0.0) {
+      return Err("noiseC must be non-negative".to_string());
+    }
+    let ordered = self.turning_point_c < self.dry_end_c
+      && self.dry_end_c < self.first_crack_c
+      && self.first_crack_c < self.drop_temp_c;
+    if !ordered {
+      return Err("expected turningPointC < dryEndC < firstCrackC < dropTempC".to_string());
+    }
+    if let Some(setting) = self.pipeline.reading_setting() {
+      return Err(format!("{} is not supported by the simulator", setting));
+    }
+    if let Some(faults) = &self.faults {
+      faults.validate()?;
+    }
+    Ok(())
+  }
+}
+
+/// Synthesizes a roast curve (charge, turning point, drying, maillard, development after first crack) and feeds
+/// it through the same sample pipeline as `TcpLineDriverNative`. The current phase is reported as the `phase` extra.
+#[napi]
+pub struct SimulatedDriverNative {
+  config: SimulatedDriverConfig,
+  pub(crate) inner: Arc<DriverInner>,
+}
+
+#[napi]
+impl SimulatedDriverNative {
+  #[napi(constructor)]
+  pub fn new(config_json: String, machine_id: String) -> Result<Self> {
+    let config = SimulatedDriverConfig::parse(&config_json)
+      .map_err(|err| Error::from_reason(format!("invalid config: {}", err)))?;
+    config.validate().map_err(|err| Error::from_reason(format!("invalid config: {}", err)))?;
+    let inner = DriverInner::new(config.line_config(), machine_id);
+    Ok(Self { config, inner })
+  }
+
+  #[napi(ts_args_type = "timeoutMs?: number, cancel?: CancelToken")]
+  pub async fn connect(&self, timeout_ms: Option<u32>, cancel: Option<Cancel>) -> Result<()> {
+    let config = self.config.clone();
+    let since = self.inner.connected_seq.load(Ordering::Relaxed);
+    self.inner.ensure_source(move |inner| run_simulation(inner, config));
+    cancel::bounded("connect", self.inner.wait_for_connected(since), timeout_ms, cancel).await
+  }
+
+  #[napi(ts_args_type = "timeoutMs?: number, cancel?: CancelToken")]
+  pub async fn read_telemetry(&self, timeout_ms: Option<u32>, cancel: Option<Cancel>) -> Result<TelemetryPoint> {
+    self.inner.read_telemetry(timeout_ms, cancel).await
+  }
+
+  #[napi(ts_args_type = "timeoutMs: number, cancel?: CancelToken")]
+  pub async fn read_telemetry_with_timeout(
+    &self,
+    timeout_ms: u32,
+    cancel: Option<Cancel>,
+  ) -> Result<Option<TelemetryPoint>> {
+    self.inner.read_telemetry_with_timeout(timeout_ms, cancel).await
+  }
+
+  #[napi]
+  pub async fn peek_telemetry(&self) -> Result<TelemetryPoint> {
+    self.inner.peek_telemetry().await
+  }
+
+  #[napi]
+  pub async fn disconnect(&self) -> Result<()> {
+    self.inner.disconnect().await;
+    Ok(())
+  }
+
+  #[napi]
+  pub fn get_status(&self) -> Result<DriverStatus> {
+    Ok(self.inner.get_status())
+  }
+
+  #[napi]
+  pub fn get_metrics_detailed(&self) -> DetailedMetrics {
+    self.inner.get_metrics_detailed()
+  }
+
+  /// The last 50 parse failures, oldest first, with the offending line cut to 256 characters.
+  #[napi]
+  pub fn get_recent_errors(&self) -> Vec<RecentParseError> {
+    self.inner.get_recent_errors()
+  }
+
+  /// Async-iterator-shaped stream of samples; see `TelemetryStream`.
+  #[napi]
+  pub fn telemetry_stream(&self) -> TelemetryStream {
+    TelemetryStream::new(Arc::clone(&self.inner))
+  }
+
+  /// A handle of its own onto every point published from now on; see `TelemetrySubscription`.
+  #[napi]
+  pub fn subscribe(&self) -> TelemetrySubscription {
+    TelemetrySubscription::new(Arc::clone(&self.inner))
+  }
+
+  /// Takes up to `maxSamples` (default all) queued samples, oldest first; empty unless `queue` is configured.
+  #[napi]
+  pub fn drain_samples(&self, max_samples: Option<u32>) -> Vec<TelemetryPoint> {
+    self.inner.drain_samples(max_samples)
+  }
+
+  /// Per-channel min/max/mean/count over the samples of the last `windowSeconds` (by sample timestamp).
+  #[napi]
+  pub fn read_aggregate(&self, window_seconds: f64) -> Result<TelemetryAggregate> {
+    self.inner.read_aggregate(window_seconds)
+  }
+
+  /// The current roast's phases from CHARGE, with their durations, end temperatures and mean RoR; `null` before
+  /// CHARGE. Needs `roastEvents`.
+  #[napi]
+  pub fn get_session_summary(&self) -> Option<SessionSummary> {
+    self.inner.session_summary()
+  }
+
+  /// Collects every emitted point from now on and writes them to `path` on `stopLogging`. `format` is `csv` or
+  /// `parquet` (default: from the extension, else csv); the header lists the point fields followed by one column per
+  /// extra.
+  #[napi]
+  pub fn start_logging(&self, path: String, format: Option<String>) -> Result<()> {
+    self.inner.start_logging(path, format)
+  }
+
+  /// Writes the log begun by `startLogging` and returns what was written (`null` when not logging).
+  #[napi]
+  pub fn stop_logging(&self) -> Result<Option<SessionLogSummary>> {
+    self.inner.stop_logging()
+  }
+
+  /// Registers a callback for detected roast events (requires `roastEvents` in the config).
+  #[napi(ts_args_type = "callback: (event: RoastEvent) => void")]
+  pub fn on_event(&self, env: Env, callback: JsFunction) -> Result<()> {
+    self.inner.roast_events.subscribe(&env, callback)
+  }
+}
+
+async fn run_simulation(inner: Arc<DriverInner>, config: SimulatedDriverConfig) {
+  let mut noise = config.seed.map(Rng::new).unwrap_or_else(Rng::from_time);
+  let profile = RoastProfile::from_config(&config);
+  let step_s = config.sample_interval_ms as f64 / 1000.0;
+  let tick = Duration::from_secs_f64(step_s / config.speed);
+  let mut faults = FaultInjector::new(config.faults.clone());
+
+  inner.metrics.lock().lastError = None;
+  inner.set_state(DriverState::CONNECTED);
+
+  loop {
+    inner.reset_connection_state();
+    inner.reset_roast_events();
+    let charge_at = Utc::now();
+    let mut t = 0.0;
+    while t <= config.duration_seconds {
+      inner.wait_for_queue_space().await;
+      if inner.stop_flag.load(Ordering::Relaxed) {
+        return;
+      }
+      // The roast runs on while the connection is down; its samples are lost.
+      if let Some(down) = faults.disconnect(&inner).await {
+        t += down.as_secs_f64() * config.speed;
+        continue;
+      }
+      let mut sample = profile.sample_at(t, charge_at + chrono::Duration::milliseconds((t * 1000.0) as i64));
+      sample.bt_c = sample.bt_c.map(|v| v + noise.gaussian() * config.noise_c);
+      sample.et_c = sample.et_c.map(|v| v + noise.gaussian() * config.noise_c);
+      faults.delay().await;
+      if faults.corrupts() {
+        // A corrupted reading goes through the parser as the JSONL line a device would have sent.
+        let line = faults.garble(&sample_line(&sample));
+        inner.count_line(line.len() + 1);
+        match inner.parse_line(&line) {
+          Ok(samples) => samples.into_iter().for_each(|sample| inner.accept_sample(sample)),
+          Err(err) => inner.count_parse_error(&err, &line),
+        }
+      } else {
+        inner.count_line(0);
+        inner.accept_sample(sample);
+      }
+      t += step_s;
+      sleep(tick).await;
+    }
+    inner.flush_downsample();
+    if !config.loop_roast {
+      break;
+    }
+  }
+
+  inner.metrics.lock().lastError = Some("simulation finished".to_string());
+  inner.set_state(DriverState::DISCONNECTED);
+}
+
+/// `sample` as a JSONL line.
+fn sample_line(sample: &RawTelemetrySample) -> String {
+  let mut line = serde_json::json!({
+    "ts": sample.ts.to_rfc3339_opts(SecondsFormat::Millis, true),
+    "btC": sample.bt_c,
+    "etC": sample.et_c,
+    "powerPct": sample.power_pct,
+    "fanPct": sample.fan_pct,
+    "drumRpm": sample.drum_rpm,
+  });
+  for extra in sample.extras.iter().flatten() {
+    line[extra.key.as_str()] = extra.text_value.clone().into();
+  }
+  line.to_string()
+}
+
+#[derive(Debug, Clone, Copy)]
+struct RoastProfile {
+  duration_s: f64,
+  turning_point_s: f64,
+  charge_c: f64,
+  turning_point_c: f64,
+  dry_end_c: f64,
+  first_crack_c: f64,
+  drop_c: f64,
+}
+
+impl RoastProfile {
+  fn from_config(config: &SimulatedDriverConfig) -> Self {
+    Self {
+      duration_s: config.duration_seconds,
+      turning_point_s: (config.duration_seconds * 0.15).max(1.0),
+      charge_c: config.charge_temp_c,
+      turning_point_c: config.turning_point_c,
+      dry_end_c: config.dry_end_c,
+      first_crack_c: config.first_crack_c,
+      drop_c: config.drop_temp_c,
+    }
+  }
+
+  /// Bean temperature falls from charge to the turning point, then climbs with a steadily declining rate of rise.
+  fn bean_temp(&self, t: f64) -> f64 {
+    if t <= self.turning_point_s {
+      let remaining = 1.0 - t / self.turning_point_s;
+      return self.turning_point_c + (self.charge_c - self.turning_point_c) * remaining * remaining;
+    }
+    let progress = ((t - self.turning_point_s) / (self.duration_s - self.turning_point_s)).clamp(0.0, 1.0);
+    self.turning_point_c + (self.drop_c - self.turning_point_c) * (1.0 - (1.0 - progress).powf(1.6))
+  }
+
+  fn sample_at(&self, t: f64, ts: DateTime<Utc>) -> RawTelemetrySample {
+    let bt = self.bean_temp(t);
+    let progress = (t / self.duration_s).clamp(0.0, 1.0);
+    let et = self.charge_c + 35.0 * progress + 20.0 - 25.0 * (-t / 30.0).exp();
+
+    let (phase, power, fan) = if t < self.turning_point_s {
+      ("charge", 70.0, 40.0)
+    } else if bt < self.dry_end_c {
+      ("drying", 70.0, 40.0)
+    } else if bt < self.first_crack_c {
+      ("maillard", 60.0, 50.0)
+    } else {
+      ("development", 45.0, 65.0)
+    };
+
+    RawTelemetrySample {
+      ts,
+      device_ts: false,
+      bt_c: Some(bt),
+      et_c: Some(et),
+      power_pct: Some(power),
+      fan_pct: Some(fan),
+      drum_rpm: Some(55.0),
+      ambient_c: None,
+      humidity_pct: None,
+      pressure_hpa: None,
+      exhaust_c: None,
+      inlet_c: None,
+      drum_pressure_pa: None,
+      airflow_pct: None,
+      extras: Some(vec![ExtraEntry {
+        key: "phase".to_string(),
+        number_value: None,
+        text_value: Some(phase.to_string()),
+        bool_value: None,
+      }]),
+      probes: Vec::new(),
+      received_at: Utc::now(),
+      host_received_at: None,
+      quality: BTreeMap::new(),
+    }
+  }
+}
@@ -0,0 +1,592 @@
+use std::collections::BTreeMap;
+use std::sync::atomic::Ordering;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use napi::bindgen_prelude::*;
+use napi_derive::napi;
+use serde::Deserialize;
+use snmp2::{v3, AsyncSession, Oid, Value};
+use tokio::time::{sleep, timeout, MissedTickBehavior};
+use tracing::{debug, error, info};
+
+use crate::aggregate::TelemetryAggregate;
+use crate::cancel::{self, Cancel};
+use crate::csv_record::ColumnMismatch;
+use crate::events::{DriverError, DriverErrorCode};
+use crate::phases::SessionSummary;
+use crate::recent_errors::RecentParseError;
+use crate::session_log::SessionLogSummary;
+use crate::stats::DetailedMetrics;
+use crate::stream::TelemetryStream;
+use crate::subscription::TelemetrySubscription;
+use crate::{
+  CsvConfig, DriverInner, DriverState, DriverStatus, FrameFormat, PipelineConfig, ReconnectConfig, TcpLineDriverConfig,
+  TelemetryPoint,
+};
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+enum SnmpVersion {
+  V1,
+  #[default]
+  V2c,
+  V3,
+}
+
+#[derive(Debug, Clone, Copy, Default, Deserialize)]
+#[serde(rename_all = "lowercase")]
+enum SnmpAuthProtocol {
+  Md5,
+  #[default]
+  Sha1,
+  Sha224,
+  Sha256,
+  Sha384,
+  Sha512,
+}
+
+#[derive(Debug, Clone, Copy, Default, Deserialize)]
+#[serde(rename_all = "lowercase")]
+enum SnmpPrivProtocol {
+  Des,
+  #[default]
+  Aes128,
+  Aes192,
+  Aes256,
+}
+
+#[derive(Debug, Clone, Copy, Deserialize)]
+#[serde(rename_all = "lowercase")]
+enum SnmpKeyExtension {
+  Blumenthal,
+  Reeder,
+}
+
+/// User-based security; the level follows from the passwords given: none is noAuthNoPriv, `authPassword` alone is
+/// authNoPriv, both are authPriv.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct SnmpV3Config {
+  username: String,
+  #[serde(default)]
+  auth_protocol: SnmpAuthProtocol,
+  #[serde(default)]
+  auth_password: Option<String>,
+  #[serde(default)]
+  priv_protocol: SnmpPrivProtocol,
+  #[serde(default)]
+  priv_password: Option<String>,
+  #[serde(default)]
+  context_name: Option<String>,
+  /// How the privacy key is stretched when AES-192/256 needs more key than the auth hash yields; agents differ.
+  #[serde(default)]
+  key_extension: Option<SnmpKeyExtension>,
+}
+
+impl SnmpV3Config {
+  fn security(&self) -> v3::Security {
+    let auth_password = self.auth_password.as_deref().unwrap_or_default();
+    let auth = match (&self.auth_password, &self.priv_password) {
+      (Some(_), Some(privacy_password)) => v3::Auth::AuthPriv {
+        cipher: match self.priv_protocol {
+          SnmpPrivProtocol::Des => v3::Cipher::Des,
+          SnmpPrivProtocol::Aes128 => v3::Cipher::Aes128,
+          SnmpPrivProtocol::Aes192 => v3::Cipher::Aes192,
+          SnmpPrivProtocol::Aes256 => v3::Cipher::Aes256,
+        },
+        privacy_password: privacy_password.as_bytes().to_vec(),
+      },
+      (Some(_), None) => v3::Auth::AuthNoPriv,
+      (None, _) => v3::Auth::NoAuthNoPriv,
+    };
+    let mut security = v3::Security::new(self.username.as_bytes(), auth_password.as_bytes())
+      .with_auth(auth)
+      .with_auth_protocol(match self.auth_protocol {
+        SnmpAuthProtocol::Md5 => v3::AuthProtocol::Md5,
+        SnmpAuthProtocol::Sha1 => v3::AuthProtocol::Sha1,
+        SnmpAuthProtocol::Sha224 => v3::AuthProtocol::Sha224,
+        SnmpAuthProtocol::Sha256 => v3::AuthProtocol::Sha256,
+        SnmpAuthProtocol::Sha384 => v3::AuthProtocol::Sha384,
+        SnmpAuthProtocol::Sha512 => v3::AuthProtocol::Sha512,
+      });
+    if let Some(context_name) = &self.context_name {
+      security = security.with_context_name(context_name);
+    }
+    if let Some(method) = self.key_extension {
+      security = security.with_key_extension_method(match method {
+        SnmpKeyExtension::Blumenthal => v3::KeyExtension::Blumenthal,
+        SnmpKeyExtension::Reeder => v3::KeyExtension::Reeder,
+      });
+    }
+    security
+  }
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct SnmpOidMapping {
+  /// Numeric, dotted: `1.3.6.1.4.1.318.1.1.10.2.3.2.1.4.1`.
+  oid: String,
+  /// Applied to numeric values as `value * scale + offset`, for agents that report e.g. tenths of a degree.
+  #[serde(default = "default_scale")]
+  scale: f64,
+  #[serde(default)]
+  offset: f64,
+}
+
+fn default_scale() -> f64 {
+  1.0
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct SnmpDriverConfig {
+  host: String,
+  #[serde(default = "default_port")]
+  port: u16,
+  #[serde(default)]
+  version: SnmpVersion,
+  /// For v1 and v2c.
+  #[serde(default = "default_community")]
+  community: String,
+  /// Required for (and only used by) v3.
+  #[serde(default)]
+  v3: Option<SnmpV3Config>,
+  /// Channel (`btC`, `etC`, ..., or any other name for an extra) to the OID read for it. All OIDs go out in one GET.
+  oids: BTreeMap<String, SnmpOidMapping>,
+  /// Time between the starts of two polls; a slow agent delays the next poll rather than overlapping it.
+  #[serde(default = "default_poll_interval_ms")]
+  poll_interval_ms: u64,
+  /// Per request, including v3 engine discovery.
+  #[serde(default = "default_request_timeout_ms")]
+  request_timeout_ms: u64,
+  reconnect: ReconnectConfig,
+  #[serde(flatten)]
+  pipeline: PipelineConfig,
+}
+
+fn default_port() -> u16 {
+  161
+}
+
+fn default_community() -> String {
+  "public".to_string()
+}
+
+fn default_poll_interval_ms() -> u64 {
+  5000
+}
+
+fn default_request_timeout_ms() -> u64 {
+  2000
+}
+
+impl SnmpDriverConfig {
+  fn line_config(&self) -> TcpLineDriverConfig {
+    TcpLineDriverConfig {
+      reconnect: self.reconnect.clone(),
+      ..TcpLineDriverConfig::for_source(
+        FrameFormat::Jsonl,
+        CsvConfig {
+          has_header: false,
+          columns: Vec::new(),
+          delimiter: ",".to_string(),
+          column_mismatch: ColumnMismatch::default(),
+        },
+        self.pipeline.clone(),
+      )
+    }
+  }
+
+  fn validate(&self) -> std::result::Result<Vec<MappedOid>, String> {
+    if self.host.trim().is_empty() {
+      return Err("host is required".to_string());
+    }
+    if self.poll_interval_ms == 0 {
+      return Err("pollIntervalMs must be positive".to_string());
+    }
+    if self.request_timeout_ms == 0 {
+      return Err("requestTimeoutMs must be positive".to_string());
+    }
+    match (&self.version, &self.v3) {
+      (SnmpVersion::V3, None) => return Err("v3 is required for version v3".to_string()),
+      (SnmpVersion::V3, Some(v3)) => {
+        if v3.username.is_empty() {
+          return Err("v3.username is required".to_string());
+        }
+        // RFC 3414 keys are derived from at least 8 characters; agents reject shorter passwords.
+        if v3.auth_password.as_ref().is_some_and(|password| password.len() < 8) {
+          return Err("v3.authPassword must be at least 8 characters".to_string());
+        }
+        if v3.priv_password.as_ref().is_some_and(|password| password.len() < 8) {
+          return Err("v3.privPassword must be at least 8 characters".to_string());
+        }
+        if v3.priv_password.is_some() && v3.auth_password.is_none() {
+          return Err("v3.privPassword requires v3.authPassword".to_string());
+        }
+      }
+      _ => {}
+    }
+    if self.oids.is_empty() {
+      return Err("oids must map at least one channel".to_string());
+    }
+    self
+      .oids
+      .iter()
+      .map(|(channel, mapping)| match parse_oid(&mapping.oid) {
+        Some(oid) => Ok(MappedOid { channel: channel.clone(), oid, scale: mapping.scale, offset: mapping.offset }),
+        None => Err(format!("oids.{}: invalid oid {:?}", channel, mapping.oid)),
+      })
+      .collect()
+  }
+}
+
+/// A dotted numeric OID (a leading dot is allowed); the first arc is 0-2, and there are at least two.
+fn parse_oid(text: &str) -> Option<Oid<'static>> {
+  let arcs = text
+    .strip_prefix('.')
+    .unwrap_or(text)
+    .split('.')
+    .map(|arc| if arc.bytes().all(|byte| byte.is_ascii_digit()) { arc.parse::<u64>().ok() } else { None })
+    .collect::<Option<Vec<_>>>()?;
+  if arcs.len() < 2 || arcs[0] > 2 || (arcs[0] < 2 && arcs[1] >= 40) {
+    return None;
+  }
+  Oid::from(&arcs).ok()
+}
+
+#[derive(Debug, Clone)]
+struct MappedOid {
+  channel: String,
+  oid: Oid<'static>,
+  scale: f64,
+  offset: f64,
+}
+
+/// Polls an SNMP agent's OIDs and feeds the values through the same sample pipeline as `TcpLineDriverNative`: every
+/// successful GET becomes one sample.
+#[napi]
+pub struct SnmpDriverNative {
+  config: SnmpDriverConfig,
+  oids: Vec<MappedOid>,
+  pub(crate) inner: Arc<DriverInner>,
+}
+
+#[napi]
+impl SnmpDriverNative {
+  #[napi(constructor)]
+  pub fn new(config_json: String, machine_id: String) -> Result<Self> {
+    let config: SnmpDriverConfig = serde_json::from_str(&config_json)
+      .map_err(|err| Error::from_reason(format!("invalid config: {}", err)))?;
+    let oids = config.validate().map_err(|err| Error::from_reason(format!("invalid config: {}", err)))?;
+    let inner = DriverInner::new(config.line_config(), machine_id);
+    Ok(Self { config, oids, inner })
+  }
+
+  #[napi(ts_args_type = "timeoutMs?: number, cancel?: CancelToken")]
+  pub async fn connect(&self, timeout_ms: Option<u32>, cancel: Option<Cancel>) -> Result<()> {
+    let poller = Poller { config: self.config.clone(), oids: self.oids.clone() };
+    let since = self.inner.connected_seq.load(Ordering::Relaxed);
+    self.inner.ensure_source(move |inner| run_polling(inner, poller));
+    cancel::bounded("connect", self.inner.wait_for_connected(since), timeout_ms, cancel).await
+  }
+
+  #[napi(ts_args_type = "timeoutMs?: number, cancel?: CancelToken")]
+  pub async fn read_telemetry(&self, timeout_ms: Option<u32>, cancel: Option<Cancel>) -> Result<TelemetryPoint> {
+    self.inner.read_telemetry(timeout_ms, cancel).await
+  }
+
+  #[napi(ts_args_type = "timeoutMs: number, cancel?: CancelToken")]
+  pub async fn read_telemetry_with_timeout(
+    &self,
+    timeout_ms: u32,
+    cancel: Option<Cancel>,
+  ) -> Result<Option<TelemetryPoint>> {
+    self.inner.read_telemetry_with_timeout(timeout_ms, cancel).await
+  }
+
+  #[napi]
+  pub async fn peek_telemetry(&self) -> Result<TelemetryPoint> {
+    self.inner.peek_telemetry().await
+  }
+
+  #[napi]
+  pub async fn disconnect(&self) -> Result<()> {
+    self.inner.disconnect().await;
+    Ok(())
+  }
+
+  #[napi]
+  pub fn get_status(&self) -> Result<DriverStatus> {
+    Ok(self.inner.get_status())
+  }
+
+  #[napi]
+  pub fn get_metrics_detailed(&self) -> DetailedMetrics {
+    self.inner.get_metrics_detailed()
+  }
+
+  /// The last 50 parse failures, oldest first, with the offending line cut to 256 characters.
+  #[napi]
+  pub fn get_recent_errors(&self) -> Vec<RecentParseError> {
+    self.inner.get_recent_errors()
+  }
+
+  /// Async-iterator-shaped stream of samples; see `TelemetryStream`.
+  #[napi]
+  pub fn telemetry_stream(&self) -> TelemetryStream {
+    TelemetryStream::new(Arc::clone(&self.inner))
+  }
+
+  /// A handle of its own onto every point published from now on; see `TelemetrySubscription`.
+  #[napi]
+  pub fn subscribe(&self) -> TelemetrySubscription {
+    TelemetrySubscription::new(Arc::clone(&self.inner))
+  }
+
+  /// Takes up to `maxSamples` (default all) queued samples, oldest first; empty unless `queue` is configured.
+  #[napi]
+  pub fn drain_samples(&self, max_samples: Option<u32>) -> Vec<TelemetryPoint> {
+    self.inner.drain_samples(max_samples)
+  }
+
+  /// Per-channel min/max/mean/count over the samples of the last `windowSeconds` (by sample timestamp).
+  #[napi]
+  pub fn read_aggregate(&self, window_seconds: f64) -> Result<TelemetryAggregate> {
+    self.inner.read_aggregate(window_seconds)
+  }
+
+  /// The current roast's phases from CHARGE, with their durations, end temperatures and mean RoR; `null` before
+  /// CHARGE. Needs `roastEvents`.
+  #[napi]
+  pub fn get_session_summary(&self) -> Option<SessionSummary> {
+    self.inner.session_summary()
+  }
+
+  /// Collects every emitted point from now on and writes them to `path` on `stopLogging`. `format` is `csv` or
+  /// `parquet` (default: from the extension, else csv); the header lists the point fields followed by one column per
+  /// extra.
+  #[napi]
+  pub fn start_logging(&self, path: String, format: Option<String>) -> Result<()> {
+    self.inner.start_logging(path, format)
+  }
+
+  /// Writes the log begun by `startLogging` and returns what was written (`null` when not logging).
+  #[napi]
+  pub fn stop_logging(&self) -> Result<Option<SessionLogSummary>> {
+    self.inner.stop_logging()
+  }
+
+  /// Registers a callback for state transitions (CONNECTING, CONNECTED, DISCONNECTED, ...).
+  #[napi(ts_args_type = "callback: (event: StateChangeEvent) => void")]
+  pub fn on_state_change(&self, env: Env, callback: JsFunction) -> Result<()> {
+    self.inner.state_events.subscribe(&env, callback)
+  }
+
+  /// Registers a callback for failed polls.
+  #[napi(ts_args_type = "callback: (error: DriverError) => void")]
+  pub fn on_error(&self, env: Env, callback: JsFunction) -> Result<()> {
+    self.inner.error_events.subscribe(&env, callback)
+  }
+
+  /// Registers a callback for detected roast events (requires `roastEvents` in the config).
+  #[napi(ts_args_type = "callback: (event: RoastEvent) => void")]
+  pub fn on_event(&self, env: Env, callback: JsFunction) -> Result<()> {
+    self.inner.roast_events.subscribe(&env, callback)
+  }
+}
+
+struct Poller {
+  config: SnmpDriverConfig,
+  oids: Vec<MappedOid>,
+}
+
+/// Polls until a request fails, then backs off per `reconnect` like the TCP loop and starts over with a fresh
+/// session; the driver is CONNECTED from the first good response of each round.
+async fn run_polling(inner: Arc<DriverInner>, poller: Poller) {
+  let reconnect = &poller.config.reconnect;
+  inner.apply_backoff(reconnect);
+  inner.backoff.lock().reset();
+  let mut attempts: u32 = 0;
+  let mut outage_started: Option<Instant> = None;
+  let mut exhausted = false;
+  loop {
+    if inner.stop_flag.load(Ordering::Relaxed) {
+      break;
+    }
+    inner.set_state(DriverState::CONNECTING);
+    inner.reset_connection_state();
+    inner.reset_roast_events();
+
+    debug!(machine_id = %inner.machine_id, host = %poller.config.host, attempt = attempts + 1, "polling");
+    let (connected, err) = poll_until_failure(&inner, &poller).await;
+    if connected {
+      attempts = 0;
+      outage_started = None;
+    }
+    inner.handle_failure(err).await;
+
+    if inner.stop_flag.load(Ordering::Relaxed) || !reconnect.enabled {
+      break;
+    }
+    attempts = attempts.saturating_add(1);
+    let outage = outage_started.get_or_insert_with(Instant::now).elapsed();
+    if reconnect.exhausted(attempts, outage) {
+      let outage_ms = outage.as_millis() as u64;
+      error!(machine_id = %inner.machine_id, attempts, outage_ms, "reconnect policy exhausted");
+      exhausted = true;
+      break;
+    }
+    {
+      let mut metrics = inner.metrics.lock();
+      metrics.reconnects = metrics.reconnects.saturating_add(1);
+    }
+    let delay = { inner.backoff.lock().next_delay() };
+    debug!(machine_id = %inner.machine_id, delay_ms = delay, attempt = attempts, "repolling after backoff");
+    sleep(Duration::from_millis(delay)).await;
+  }
+
+  inner.set_state(if inner.stop_flag.load(Ordering::Relaxed) {
+    DriverState::STOPPED
+  } else if exhausted {
+    DriverState::FAILED
+  } else {
+    DriverState::DISCONNECTED
+  });
+}
+
+/// Opens a session (running v3 engine discovery) and polls on it; returns whether any poll succeeded, and the
+/// failure that ended the round.
+async fn poll_until_failure(inner: &Arc<DriverInner>, poller: &Poller) -> (bool, DriverError) {
+  let config = &poller.config;
+  let request_timeout = Duration::from_millis(config.request_timeout_ms);
+  let timed_out = || format!("timed out after {} ms", config.request_timeout_ms);
+  let mut session = match timeout(request_timeout, open_session(config)).await {
+    Ok(Ok(session)) => session,
+    Ok(Err(message)) => return (false, poll_failed(config, false, DriverErrorCode::ConnectFailed, message)),
+    Err(_) => return (false, poll_failed(config, false, DriverErrorCode::Timeout, timed_out())),
+  };
+  let oids: Vec<&Oid<'_>> = poller.oids.iter().map(|mapped| &mapped.oid).collect();
+  let mut ticks = tokio::time::interval(Duration::from_millis(config.poll_interval_ms));
+  ticks.set_missed_tick_behavior(MissedTickBehavior::Delay);
+  let mut connected = false;
+  loop {
+    ticks.tick().await;
+    // The request is retried once when a v3 agent has just updated the session's engine time.
+    let mut response = timeout(request_timeout, session.get_many(&oids)).await;
+    if matches!(response, Ok(Err(snmp2::Error::AuthUpdated))) {
+      drop(response);
+      response = timeout(request_timeout, session.get_many(&oids)).await;
+    }
+    let record = match response {
+      Ok(Ok(pdu)) if pdu.error_status != 0 => {
+        let message = agent_error(pdu.error_status, pdu.error_index, &poller.oids);
+        return (connected, poll_failed(config, connected, DriverErrorCode::ConnectFailed, message));
+      }
+      Ok(Ok(pdu)) => {
+        poller.oids.iter().zip(pdu.varbinds).filter_map(|(mapped, (_, value))| reading(mapped, &value)).collect()
+      }
+      Ok(Err(err)) => {
+        return (connected, poll_failed(config, connected, DriverErrorCode::ConnectFailed, err.to_string()));
+      }
+      Err(_) => return (connected, poll_failed(config, connected, DriverErrorCode::Timeout, timed_out())),
+    };
+    if !connected {
+      connected = true;
+      inner.backoff.lock().reset();
+      inner.metrics.lock().lastError = None;
+      info!(machine_id = %inner.machine_id, host = %config.host, "connected");
+      inner.set_state(DriverState::CONNECTED);
+    }
+    inner.wait_for_queue_space().await;
+    accept_record(inner, record);
+  }
+}
+
+async fn open_session(config: &SnmpDriverConfig) -> std::result::Result<AsyncSession, String> {
+  let destination = (config.host.as_str(), config.port);
+  let community = config.community.as_bytes();
+  let opened = match (config.version, &config.v3) {
+    (SnmpVersion::V1, _) => AsyncSession::new_v1(destination, community, 0).await,
+    (SnmpVersion::V2c, _) => AsyncSession::new_v2c(destination, community, 0).await,
+    (SnmpVersion::V3, Some(v3)) => AsyncSession::new_v3(destination, 0, v3.security()).await,
+    (SnmpVersion::V3, None) => unreachable!("validated"),
+  };
+  let mut session = opened.map_err(|err| err.to_string())?;
+  session.init().await.map_err(|err| err.to_string())?;
+  Ok(session)
+}
+
+fn poll_failed(config: &SnmpDriverConfig, connected: bool, code: DriverErrorCode, message: String) -> DriverError {
+  if connected {
+    DriverError::new(code, format!("poll failed: {}", message))
+  } else {
+    DriverError::new(code, format!("connection failure: {}:{}: {}", config.host, config.port, message))
+  }
+}
+
+/// Names the failed status and, when the agent points at one, the channel whose OID it rejected.
+fn agent_error(status: u32, index: u32, oids: &[MappedOid]) -> String {
+  const STATUS_NAMES: [&str; 19] = [
+    "noError",
+    "tooBig",
+    "noSuchName",
+    "badValue",
+    "readOnly",
+    "genErr",
+    "noAccess",
+    "wrongType",
+    "wrongLength",
+    "wrongEncoding",
+    "wrongValue",
+    "noCreation",
+    "inconsistentValue",
+    "resourceUnavailable",
+    "commitFailed",
+    "undoFailed",
+    "authorizationError",
+    "notWritable",
+    "inconsistentName",
+  ];
+  let name =
+    STATUS_NAMES.get(status as usize).map_or_else(|| format!("error status {}", status), |name| name.to_string());
+  match (index as usize).checked_sub(1).and_then(|idx| oids.get(idx)) {
+    Some(mapped) => format!("agent returned {} for oids.{}", name, mapped.channel),
+    None => format!("agent returned {}", name),
+  }
+}
+
+/// Numbers are scaled into readings, as are octet strings that read as one; other strings and addresses become
+/// text extras. Missing objects and anything else leave the channel out.
+fn reading(mapped: &MappedOid, value: &Value<'_>) -> Option<(String, serde_json::Value)> {
+  let scaled = |number: f64| Some(serde_json::Value::from(number * mapped.scale + mapped.offset));
+  let value = match value {
+    Value::Integer(number) => scaled(*number as f64),
+    Value::Counter32(number) | Value::Unsigned32(number) | Value::Timeticks(number) => scaled(f64::from(*number)),
+    Value::Counter64(number) => scaled(*number as f64),
+    Value::Boolean(flag) => scaled(f64::from(u8::from(*flag))),
+    Value::OctetString(bytes) => {
+      let text = String::from_utf8_lossy(bytes);
+      match text.trim().parse::<f64>() {
+        Ok(number) if number.is_finite() => scaled(number),
+        _ => Some(serde_json::Value::String(text.into_owned())),
+      }
+    }
+    Value::IpAddress(octets) => Some(serde_json::Value::String(std::net::Ipv4Addr::from(*octets).to_string())),
+    Value::ObjectIdentifier(oid) => Some(serde_json::Value::String(oid.to_id_string())),
+    _ => None,
+  }?;
+  Some((mapped.channel.clone(), value))
+}
+
+fn accept_record(inner: &DriverInner, record: Vec<(String, serde_json::Value)>) {
+  inner.count_line(0);
+  let raw = serde_json::to_string(&serde_json::Map::from_iter(record.iter().cloned())).unwrap_or_default();
+  let parsed = inner.record_to_sample(record, || raw.clone());
+  match parsed {
+    Ok(Some(sample)) => inner.accept_sample(sample),
+    Ok(None) => {}
+    Err(err) => inner.count_parse_error(&err, &raw),
+  }
+}
@@ -0,0 +1,212 @@
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, BufRead, BufReader, Read, Seek, SeekFrom, Write};
+use std::path::{Path, PathBuf};
+
+use schemars::JsonSchema;
+use serde::Deserialize;
+use tracing::warn;
+
+use crate::TelemetryPoint;
+
+#[derive(Debug, Clone, PartialEq, Deserialize, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct SpoolConfig {
+  /// NDJSON file the samples are appended to; the acknowledged offset is kept next to it in `<path>.ack`.
+  pub path: String,
+  /// Unacknowledged bytes kept on disk; samples beyond it are dropped and counted in `droppedSamples`.
+  #[serde(default = "default_max_bytes")]
+  pub max_bytes: u64,
+}
+
+fn default_max_bytes() -> u64 {
+  100 * 1024 * 1024
+}
+
+/// Append-only spool of emitted points that survives process restarts. `drain` hands out points from the
+/// acknowledged offset onwards and persists the new offset before returning; once everything is acknowledged the file
+/// is truncated. Delivery is at least once: a crash before the new offset is on disk hands the same points out again.
+/// A plain file and an offset rather than sled or SQLite: the spool is only ever appended to and read from the front,
+/// so an embedded store would add a native dependency without adding anything it needs.
+pub(crate) struct SampleSpool {
+  config: SpoolConfig,
+  file: File,
+  written: u64,
+  acked: u64,
+}
+
+impl SampleSpool {
+  pub fn open(config: SpoolConfig) -> io::Result<Self> {
+    let path = PathBuf::from(&config.path);
+    if let Some(parent) = path.parent() {
+      if !parent.as_os_str().is_empty() {
+        fs::create_dir_all(parent)?;
+      }
+    }
+    let mut file = OpenOptions::new().create(true).read(true).append(true).open(&path)?;
+    let written = complete_len(&mut file)?;
+    file.set_len(written)?;
+    let acked = match fs::read_to_string(ack_path(&config.path)) {
+      Ok(text) => text.trim().parse::<u64>().unwrap_or(0).min(written),
+      Err(err) if err.kind() == io::ErrorKind::NotFound => 0,
+      Err(err) => return Err(err),
+    };
+    Ok(Self { config, file, written, acked })
+  }
+
+  pub fn path(&self) -> &str {
+    &self.config.path
+  }
+
+  /// Flushes appended points to disk.
+  pub fn sync(&self) -> io::Result<()> {
+    self.file.sync_data()
+  }
+
+  /// Appends `point`; returns `false` if it was dropped because the spool is full.
+  pub fn append(&mut self, point: &TelemetryPoint) -> io::Result<bool> {
+    let mut encoded = serde_json::to_vec(point).map_err(io::Error::other)?;
+    encoded.push(b'\n');
+    if self.written - self.acked + encoded.len() as u64 > self.config.max_bytes {
+      return Ok(false);
+    }
+    self.file.write_all(&encoded)?;
+    self.written += encoded.len() as u64;
+    Ok(true)
+  }
+
+  /// Takes up to `max` (default all) unacknowledged points, oldest first, and acknowledges them.
+  pub fn drain(&mut self, max: Option<usize>) -> io::Result<Vec<TelemetryPoint>> {
+    let mut reader = BufReader::new(File::open(&self.config.path)?);
+    reader.seek(SeekFrom::Start(self.acked))?;
+    let mut points = Vec::new();
+    let mut offset = self.acked;
+    let mut line = String::new();
+    while offset < self.written && max.is_none_or(|max| points.len() < max) {
+      line.clear();
+      let read = reader.read_line(&mut line)?;
+      if read == 0 {
+        break;
+      }
+      offset += read as u64;
+      match serde_json::from_str(&line) {
+        Ok(point) => points.push(point),
+        Err(err) => warn!(path = %self.config.path, error = %err, "skipping unreadable spooled sample"),
+      }
+    }
+    self.acknowledge(offset)?;
+    Ok(points)
+  }
+
+  fn acknowledge(&mut self, offset: u64) -> io::Result<()> {
+    if offset == self.acked {
+      return Ok(());
+    }
+    if offset == self.written {
+      // The offset goes back to 0 before the file is truncated, so a crash in between redelivers the drained points
+      // instead of leaving an offset into whatever is appended next.
+      self.persist_ack(0)?;
+      match self.file.set_len(0) {
+        Ok(()) => {
+          self.written = 0;
+          self.acked = 0;
+          return Ok(());
+        }
+        // Acknowledged by offset instead; the next drain that empties the spool truncates it.
+        Err(err) => warn!(path = %self.config.path, error = %err, "truncating the spool failed"),
+      }
+    }
+    self.persist_ack(offset)?;
+    self.acked = offset;
+    Ok(())
+  }
+
+  /// Written to a temp file, synced and renamed over the old one, then the directory synced, so even a power loss
+  /// leaves either the old or the new offset, never a torn or lost one.
+  fn persist_ack(&self, offset: u64) -> io::Result<()> {
+    let ack = ack_path(&self.config.path);
+    let temp = PathBuf::from(format!("{}.tmp", ack.display()));
+    let mut file = File::create(&temp)?;
+    file.write_all(offset.to_string().as_bytes())?;
+    file.sync_all()?;
+    fs::rename(&temp, &ack)?;
+    sync_dir(&ack)
+  }
+}
+
+/// Makes a rename into `path`'s directory durable; Windows has no directory handles to sync and orders it anyway.
+fn sync_dir(path: &Path) -> io::Result<()> {
+  #[cfg(unix)]
+  {
+    let dir = path.parent().filter(|parent| !parent.as_os_str().is_empty()).unwrap_or(Path::new("."));
+    File::open(dir)?.sync_all()?;
+  }
+  #[cfg(not(unix))]
+  let _ = path;
+  Ok(())
+}
+
+fn ack_path(path: &str) -> PathBuf {
+  PathBuf::from(format!("{}.ack", path))
+}
+
+/// Length up to the last complete line, so a sample torn by a crash mid-write is discarded.
+fn complete_len(file: &mut File) -> io::Result<u64> {
+  let len = file.metadata()?.len();
+  if len == 0 {
+    return Ok(0);
+  }
+  file.seek(SeekFrom::Start(len - 1))?;
+  let mut last = [0u8; 1];
+  file.read_exact(&mut last)?;
+  if last[0] == b'\n' {
+    return Ok(len);
+  }
+  let mut contents = Vec::new();
+  file.seek(SeekFrom::Start(0))?;
+  file.read_to_end(&mut contents)?;
+  Ok(contents.iter().rposition(|byte| *byte == b'\n').map_or(0, |idx| idx as u64 + 1))
+}
+
+#[cfg(test)]
+mod tests {
+  use serde_json::json;
+
+  use super::*;
+
+  fn open(name: &str) -> (SampleSpool, String) {
+    let dir = std::env::temp_dir().join(format!("tcp-line-spool-{}-{}", std::process::id(), name));
+    let _ = fs::remove_dir_all(&dir);
+    let path = dir.join("m1.ndjson").to_string_lossy().into_owned();
+    (reopen(&path), path)
+  }
+
+  fn reopen(path: &str) -> SampleSpool {
+    SampleSpool::open(SpoolConfig { path: path.to_string(), max_bytes: default_max_bytes() }).expect("open")
+  }
+
+  fn bt(points: &[TelemetryPoint]) -> Vec<Option<f64>> {
+    points.iter().map(|point| point.btC).collect()
+  }
+
+  #[test]
+  fn resumes_after_the_acknowledged_offset() {
+    let (mut spool, path) = open("resume");
+    for bt_c in [200.0, 201.0, 202.0] {
+      assert!(spool.append(&TelemetryPoint::test(json!({ "btC": bt_c }))).expect("append"));
+    }
+    spool.sync().expect("sync");
+    assert_eq!(bt(&spool.drain(Some(1)).expect("drain")), [Some(200.0)]);
+    let acked = fs::read_to_string(ack_path(&path)).expect("ack");
+    assert!(!PathBuf::from(format!("{}.ack.tmp", path)).exists());
+    drop(spool);
+
+    // A torn last line, as a crash mid-append leaves it, is discarded on reopening.
+    OpenOptions::new().append(true).open(&path).expect("spool file").write_all(b"{\"ts\":").expect("write");
+    let mut spool = reopen(&path);
+    assert_eq!(acked.parse::<u64>().expect("offset"), spool.acked);
+    assert_eq!(bt(&spool.drain(None).expect("drain")), [Some(201.0), Some(202.0)]);
+    assert_eq!(fs::read_to_string(ack_path(&path)).expect("ack"), "0");
+    assert_eq!(fs::metadata(&path).expect("spool file").len(), 0);
+    assert!(spool.drain(None).expect("drain").is_empty());
+  }
+}
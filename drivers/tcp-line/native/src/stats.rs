@@ -0,0 +1,239 @@
+use std::collections::{BTreeMap, VecDeque};
+use std::time::{Duration, Instant};
+
+use napi_derive::napi;
+
+use crate::{DriverMetrics, DriverState};
+
+const RATE_WINDOW: Duration = Duration::from_secs(60);
+const INTERVAL_SAMPLES: usize = 512;
+/// Bounds the rate window at high sample rates.
+const RATE_INTERVALS: usize = 8192;
+/// Histogram bins per doubling of the interval, so each bin is about 19% wide.
+const BINS_PER_OCTAVE: f64 = 4.0;
+/// Upper bounds of the parse latency buckets, in microseconds; the last bucket is unbounded.
+const LATENCY_BOUNDS_US: &[f64] = &[10.0, 25.0, 50.0, 100.0, 250.0, 500.0, 1000.0, 5000.0];
+
+#[derive(Debug, Clone)]
+#[napi(object)]
+pub struct LatencyHistogram {
+  /// Bucket upper bounds in microseconds; `counts` has one extra entry for slower parses.
+  pub boundsUs: Vec<f64>,
+  pub counts: Vec<i64>,
+  pub count: i64,
+  pub sumUs: f64,
+}
+
+#[derive(Debug, Clone)]
+#[napi(object)]
+pub struct IntervalPercentiles {
+  /// Number of intervals the percentiles are computed over (the most recent 512).
+  pub count: u32,
+  pub minMs: f64,
+  pub p50Ms: f64,
+  pub p90Ms: f64,
+  pub p99Ms: f64,
+  pub maxMs: f64,
+}
+
+#[derive(Debug, Clone)]
+#[napi(object)]
+pub struct SampleRate {
+  /// Samples per second at the typical interval: the most common histogram bin of the last 60 seconds' intervals,
+  /// so occasional gaps and bursts don't skew it.
+  pub hz: f64,
+  /// Standard deviation of the intervals within a factor of two of the typical one.
+  pub jitterMs: f64,
+  /// Longest wait between samples in the window, including the current one.
+  pub longestGapMs: f64,
+  /// Number of intervals in the window.
+  pub intervals: u32,
+}
+
+#[derive(Debug, Clone, Default)]
+#[napi(object)]
+pub struct StateDurations {
+  pub disconnected: f64,
+  pub connecting: f64,
+  pub connected: f64,
+  pub paused: f64,
+  pub stopped: f64,
+  pub failed: f64,
+}
+
+#[derive(Debug, Clone)]
+#[napi(object)]
+pub struct DetailedMetrics {
+  pub state: DriverState,
+  pub metrics: DriverMetrics,
+  pub parseLatency: LatencyHistogram,
+  /// Wall-clock gaps between accepted samples; absent until two samples have arrived.
+  pub sampleInterval: Option<IntervalPercentiles>,
+  /// Total milliseconds spent in each state since the driver was created.
+  pub stateDurationsMs: StateDurations,
+}
+
+/// Derived timing and rate statistics kept alongside the plain `DriverMetrics` counters.
+pub(crate) struct DriverStats {
+  created_at: Instant,
+  line_times: VecDeque<Instant>,
+  latency_counts: Vec<i64>,
+  latency_sum_us: f64,
+  last_sample_at: Option<Instant>,
+  intervals_ms: VecDeque<f64>,
+  /// The intervals of the last `RATE_WINDOW`, with the time each ended.
+  rate_intervals: VecDeque<(Instant, f64)>,
+  state_since: Instant,
+  state_durations: StateDurations,
+}
+
+impl DriverStats {
+  pub fn new() -> Self {
+    let now = Instant::now();
+    Self {
+      created_at: now,
+      line_times: VecDeque::new(),
+      latency_counts: vec![0; LATENCY_BOUNDS_US.len() + 1],
+      latency_sum_us: 0.0,
+      last_sample_at: None,
+      intervals_ms: VecDeque::with_capacity(INTERVAL_SAMPLES),
+      rate_intervals: VecDeque::new(),
+      state_since: now,
+      state_durations: StateDurations::default(),
+    }
+  }
+
+  pub fn record_line(&mut self) {
+    let now = Instant::now();
+    self.line_times.push_back(now);
+    self.trim_lines(now);
+  }
+
+  pub fn record_parse(&mut self, elapsed: Duration) {
+    let us = elapsed.as_secs_f64() * 1_000_000.0;
+    let bucket = LATENCY_BOUNDS_US.iter().position(|bound| us <= *bound).unwrap_or(LATENCY_BOUNDS_US.len());
+    self.latency_counts[bucket] = self.latency_counts[bucket].saturating_add(1);
+    self.latency_sum_us += us;
+  }
+
+  pub fn record_sample(&mut self) {
+    let now = Instant::now();
+    if let Some(last) = self.last_sample_at.replace(now) {
+      if self.intervals_ms.len() == INTERVAL_SAMPLES {
+        self.intervals_ms.pop_front();
+      }
+      let interval_ms = now.duration_since(last).as_secs_f64() * 1000.0;
+      self.intervals_ms.push_back(interval_ms);
+      if self.rate_intervals.len() == RATE_INTERVALS {
+        self.rate_intervals.pop_front();
+      }
+      self.rate_intervals.push_back((now, interval_ms));
+    }
+  }
+
+  /// Intervals don't span a reconnect: the first sample on a new connection starts a fresh gap.
+  pub fn reset_sample_clock(&mut self) {
+    self.last_sample_at = None;
+  }
+
+  pub fn record_transition(&mut self, previous: DriverState) {
+    let now = Instant::now();
+    let elapsed = now.duration_since(self.state_since).as_secs_f64() * 1000.0;
+    *state_slot(&mut self.state_durations, previous) += elapsed;
+    self.state_since = now;
+  }
+
+  pub fn lines_per_second(&mut self) -> f64 {
+    let now = Instant::now();
+    self.trim_lines(now);
+    let covered = now.duration_since(self.created_at).min(RATE_WINDOW).as_secs_f64();
+    if covered <= 0.0 {
+      return 0.0;
+    }
+    self.line_times.len() as f64 / covered
+  }
+
+  /// Absent until two samples have arrived within the window.
+  pub fn sample_rate(&mut self) -> Option<SampleRate> {
+    let now = Instant::now();
+    while self.rate_intervals.front().is_some_and(|(at, _)| now.duration_since(*at) > RATE_WINDOW) {
+      self.rate_intervals.pop_front();
+    }
+    let intervals: Vec<f64> = self.rate_intervals.iter().map(|(_, interval_ms)| *interval_ms).collect();
+    let bin = |interval_ms: f64| (interval_ms.max(0.1).log2() * BINS_PER_OCTAVE).floor() as i64;
+    let mut histogram: BTreeMap<i64, u32> = BTreeMap::new();
+    for interval_ms in &intervals {
+      *histogram.entry(bin(*interval_ms)).or_default() += 1;
+    }
+    // The fullest bin, counted with its neighbours so a steady rate right at a bin edge isn't split in two.
+    let around = |modal: i64| (modal - 1..=modal + 1).filter_map(|at| histogram.get(&at)).sum::<u32>();
+    let modal = histogram.keys().copied().max_by_key(|at| (around(*at), std::cmp::Reverse(*at)))?;
+    let typical = intervals.iter().copied().filter(|interval_ms| bin(*interval_ms).abs_diff(modal) <= 1);
+    let typical_ms = mean(typical);
+    let steady = intervals.iter().filter(|interval_ms| (typical_ms / 2.0..=typical_ms * 2.0).contains(*interval_ms));
+    let variance = mean(steady.map(|interval_ms| (interval_ms - typical_ms).powi(2)));
+    let current_ms = self.last_sample_at.map_or(0.0, |last| now.duration_since(last).as_secs_f64() * 1000.0);
+    Some(SampleRate {
+      hz: if typical_ms > 0.0 { 1000.0 / typical_ms } else { 0.0 },
+      jitterMs: variance.sqrt(),
+      longestGapMs: intervals.iter().copied().fold(current_ms, f64::max),
+      intervals: intervals.len() as u32,
+    })
+  }
+
+  pub fn snapshot(&mut self, state: DriverState, metrics: DriverMetrics) -> DetailedMetrics {
+    let mut durations = self.state_durations.clone();
+    *state_slot(&mut durations, state) += self.state_since.elapsed().as_secs_f64() * 1000.0;
+    DetailedMetrics {
+      state,
+      metrics,
+      parseLatency: LatencyHistogram {
+        boundsUs: LATENCY_BOUNDS_US.to_vec(),
+        counts: self.latency_counts.clone(),
+        count: self.latency_counts.iter().sum(),
+        sumUs: self.latency_sum_us,
+      },
+      sampleInterval: self.interval_percentiles(),
+      stateDurationsMs: durations,
+    }
+  }
+
+  fn interval_percentiles(&self) -> Option<IntervalPercentiles> {
+    if self.intervals_ms.is_empty() {
+      return None;
+    }
+    let mut sorted: Vec<f64> = self.intervals_ms.iter().copied().collect();
+    sorted.sort_by(|a, b| a.total_cmp(b));
+    let at = |q: f64| sorted[((sorted.len() - 1) as f64 * q).round() as usize];
+    Some(IntervalPercentiles {
+      count: sorted.len() as u32,
+      minMs: sorted[0],
+      p50Ms: at(0.5),
+      p90Ms: at(0.9),
+      p99Ms: at(0.99),
+      maxMs: sorted[sorted.len() - 1],
+    })
+  }
+
+  fn trim_lines(&mut self, now: Instant) {
+    while self.line_times.front().is_some_and(|at| now.duration_since(*at) > RATE_WINDOW) {
+      self.line_times.pop_front();
+    }
+  }
+}
+
+fn mean(values: impl Iterator<Item = f64>) -> f64 {
+  let (sum, count) = values.fold((0.0, 0), |(sum, count), value| (sum + value, count + 1));
+  sum / count.max(1) as f64
+}
+
+fn state_slot(durations: &mut StateDurations, state: DriverState) -> &mut f64 {
+  match state {
+    DriverState::DISCONNECTED => &mut durations.disconnected,
+    DriverState::CONNECTING => &mut durations.connecting,
+    DriverState::CONNECTED => &mut durations.connected,
+    DriverState::PAUSED => &mut durations.paused,
+    DriverState::STOPPED => &mut durations.stopped,
+    DriverState::FAILED => &mut durations.failed,
+  }
+}
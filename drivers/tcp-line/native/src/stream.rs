@@ -0,0 +1,164 @@
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use napi::bindgen_prelude::*;
+use napi::JsUnknown;
+use napi_derive::napi;
+use parking_lot::Mutex;
+use tokio::sync::Notify;
+
+use crate::{DriverInner, DriverState, TelemetryPoint};
+
+#[derive(Debug, Clone)]
+#[napi(object)]
+pub struct TelemetryStreamResult {
+  pub done: bool,
+  pub value: Option<TelemetryPoint>,
+}
+
+impl TelemetryStreamResult {
+  pub(crate) fn done() -> Self {
+    Self { done: true, value: None }
+  }
+}
+
+/// Tells when a stream of `inner`'s samples is over: the driver stopped or gave up after running.
+pub(crate) struct RunWatch {
+  /// `run_seq` when the stream was created; a stream opened before `connect()` must not end on the idle state.
+  start_run: u64,
+  /// The driver was running when the stream was created (or has been started since).
+  armed: bool,
+}
+
+impl RunWatch {
+  pub fn new(inner: &DriverInner) -> Self {
+    let start_run = inner.run_seq.load(Ordering::Relaxed);
+    let armed = matches!(*inner.state.lock(), DriverState::CONNECTING | DriverState::CONNECTED | DriverState::PAUSED);
+    Self { start_run, armed }
+  }
+
+  pub fn ended(&mut self, inner: &DriverInner) -> bool {
+    self.armed |= inner.run_seq.load(Ordering::Relaxed) > self.start_run;
+    if !self.armed {
+      return false;
+    }
+    match *inner.state.lock() {
+      DriverState::CONNECTING | DriverState::CONNECTED | DriverState::PAUSED => false,
+      DriverState::STOPPED | DriverState::FAILED => true,
+      DriverState::DISCONNECTED => !inner.config().reconnect.enabled,
+    }
+  }
+}
+
+struct Cursor {
+  last_seq: u64,
+  last_yield: Option<Instant>,
+  run: RunWatch,
+}
+
+/// Pull-based iterator over a driver's samples, shaped like a JS async iterator (`next`/`return`/`throw`). Each
+/// `next()` resolves with a sample newer than the previous one, no sooner than `emitIntervalMs` after it; nothing is
+/// buffered, so a slow consumer just sees the latest sample. The stream ends once the driver stops or gives up.
+#[napi]
+pub struct TelemetryStream {
+  inner: Arc<DriverInner>,
+  cursor: Mutex<Cursor>,
+  closed: AtomicBool,
+  notify_close: Notify,
+}
+
+impl TelemetryStream {
+  pub(crate) fn new(inner: Arc<DriverInner>) -> Self {
+    let run = RunWatch::new(&inner);
+    Self {
+      inner,
+      cursor: Mutex::new(Cursor { last_seq: 0, last_yield: None, run }),
+      closed: AtomicBool::new(false),
+      notify_close: Notify::new(),
+    }
+  }
+
+  fn finished(&self) -> bool {
+    if self.closed.load(Ordering::Relaxed) {
+      return true;
+    }
+    self.cursor.lock().run.ended(&self.inner)
+  }
+
+  fn close(&self) {
+    self.closed.store(true, Ordering::Relaxed);
+    self.notify_close.notify_waiters();
+  }
+}
+
+#[napi]
+impl TelemetryStream {
+  #[napi]
+  pub async fn next(&self) -> Result<TelemetryStreamResult> {
+    let interval_ms = self.inner.config().pipeline.emit_interval_ms;
+    let pace = { self.cursor.lock().last_yield }
+      .filter(|_| interval_ms > 0)
+      .map(|last| (last + Duration::from_millis(interval_ms)).saturating_duration_since(Instant::now()));
+    if let Some(pace) = pace.filter(|pace| !pace.is_zero()) {
+      tokio::select! {
+        _ = tokio::time::sleep(pace) => {}
+        _ = self.notify_close.notified() => return Ok(TelemetryStreamResult::done()),
+      }
+    }
+
+    loop {
+      // Register before checking so a sample, state change or close in between isn't missed.
+      let sample_notified = self.inner.notify_sample.notified();
+      let state_notified = self.inner.notify_state.notified();
+      let close_notified = self.notify_close.notified();
+      tokio::pin!(sample_notified, state_notified, close_notified);
+      sample_notified.as_mut().enable();
+      state_notified.as_mut().enable();
+      close_notified.as_mut().enable();
+
+      if self.closed.load(Ordering::Relaxed) {
+        return Ok(TelemetryStreamResult::done());
+      }
+      let seq = self.inner.sample_seq.load(Ordering::Relaxed);
+      if seq > self.cursor.lock().last_seq {
+        if let Some(sample) = self.inner.latest_sample.lock().clone() {
+          {
+            let mut cursor = self.cursor.lock();
+            cursor.last_seq = seq;
+            cursor.last_yield = Some(Instant::now());
+          }
+          return Ok(TelemetryStreamResult { done: false, value: Some(self.inner.telemetry_point(sample)) });
+        }
+      }
+      // Checked after the sample so the final sample of a finished source is still delivered.
+      if self.finished() {
+        return Ok(TelemetryStreamResult::done());
+      }
+
+      tokio::select! {
+        _ = sample_notified => {}
+        _ = state_notified => {}
+        _ = close_notified => {}
+      }
+    }
+  }
+
+  /// Ends the stream; a pending `next()` resolves with `done: true`. Called by `for await` on `break`.
+  #[napi(js_name = "return")]
+  pub fn finish(&self) -> TelemetryStreamResult {
+    self.close();
+    TelemetryStreamResult::done()
+  }
+
+  /// Ends the stream and rethrows `error`, per the async iterator protocol.
+  #[napi(js_name = "throw")]
+  pub fn abort(&self, error: Option<JsUnknown>) -> Result<TelemetryStreamResult> {
+    self.close();
+    let message = match error {
+      Some(error) => error.coerce_to_string()?.into_utf8()?.into_owned()?,
+      None => "telemetry stream aborted".to_string(),
+    };
+    Err(Error::from_reason(message))
+  }
+}
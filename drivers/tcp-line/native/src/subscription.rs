@@ -0,0 +1,152 @@
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::Arc;
+
+use napi::bindgen_prelude::*;
+use napi::JsUnknown;
+use napi_derive::napi;
+use parking_lot::Mutex;
+use tokio::sync::broadcast::error::{RecvError, TryRecvError};
+use tokio::sync::{broadcast, Notify};
+
+use crate::stream::{RunWatch, TelemetryStreamResult};
+use crate::{DriverInner, TelemetryPoint};
+
+/// One consumer's own view of a driver's points, for several panels fed by one connection. Unlike `TelemetryStream`,
+/// which only ever sees the latest sample, every point published after `subscribe()` is kept for each handle until
+/// it reads it, up to 1024 behind; past that the oldest are skipped and counted in `missed`. Handles don't affect
+/// each other or `readTelemetry`. Like the stream, `next()` ends once the driver stops or gives up.
+#[napi]
+pub struct TelemetrySubscription {
+  inner: Arc<DriverInner>,
+  /// Dropped on close so the driver stops building points for this handle.
+  points: tokio::sync::Mutex<Option<broadcast::Receiver<TelemetryPoint>>>,
+  run: Mutex<RunWatch>,
+  missed: AtomicU64,
+  closed: AtomicBool,
+  notify_close: Notify,
+}
+
+impl TelemetrySubscription {
+  pub(crate) fn new(inner: Arc<DriverInner>) -> Self {
+    let run = RunWatch::new(&inner);
+    let points = inner.subscribe_points();
+    Self {
+      inner,
+      points: tokio::sync::Mutex::new(Some(points)),
+      run: Mutex::new(run),
+      missed: AtomicU64::new(0),
+      closed: AtomicBool::new(false),
+      notify_close: Notify::new(),
+    }
+  }
+
+  fn skip(&self, count: u64) {
+    self.missed.fetch_add(count, Ordering::Relaxed);
+  }
+
+  fn close(&self) {
+    self.closed.store(true, Ordering::Relaxed);
+    self.notify_close.notify_waiters();
+    // A pending `next()` holds the receiver; it drops it on the way out.
+    if let Ok(mut points) = self.points.try_lock() {
+      points.take();
+    }
+  }
+}
+
+#[napi]
+impl TelemetrySubscription {
+  /// The next point after the one this handle last read, waiting for it if need be.
+  #[napi]
+  pub async fn next(&self) -> Result<TelemetryStreamResult> {
+    let mut guard = self.points.lock().await;
+    loop {
+      // Register before checking so a state change or close in between isn't missed.
+      let state_notified = self.inner.notify_state.notified();
+      let close_notified = self.notify_close.notified();
+      tokio::pin!(state_notified, close_notified);
+      state_notified.as_mut().enable();
+      close_notified.as_mut().enable();
+
+      let Some(points) = guard.as_mut().filter(|_| !self.closed.load(Ordering::Relaxed)) else {
+        guard.take();
+        return Ok(TelemetryStreamResult::done());
+      };
+      match points.try_recv() {
+        Ok(point) => {
+          self.inner.count_emitted(1);
+          return Ok(TelemetryStreamResult { done: false, value: Some(point) });
+        }
+        Err(TryRecvError::Lagged(count)) => {
+          self.skip(count);
+          continue;
+        }
+        Err(TryRecvError::Empty | TryRecvError::Closed) => {}
+      }
+      // Checked after the buffer so the final points of a finished source are still delivered.
+      if self.run.lock().ended(&self.inner) {
+        return Ok(TelemetryStreamResult::done());
+      }
+
+      tokio::select! {
+        point = points.recv() => match point {
+          Ok(point) => {
+            self.inner.count_emitted(1);
+            return Ok(TelemetryStreamResult { done: false, value: Some(point) });
+          }
+          Err(RecvError::Lagged(count)) => self.skip(count),
+          Err(RecvError::Closed) => return Ok(TelemetryStreamResult::done()),
+        },
+        _ = state_notified => {}
+        _ = close_notified => {}
+      }
+    }
+  }
+
+  /// Takes up to `maxSamples` (default all) points this handle hasn't read yet, oldest first, without waiting. Empty
+  /// while a `next()` is pending.
+  #[napi]
+  pub fn drain(&self, max_samples: Option<u32>) -> Vec<TelemetryPoint> {
+    let max = max_samples.map_or(usize::MAX, |max| max as usize);
+    let Ok(mut guard) = self.points.try_lock() else {
+      return Vec::new();
+    };
+    let Some(points) = guard.as_mut() else {
+      return Vec::new();
+    };
+    let mut drained = Vec::new();
+    while drained.len() < max {
+      match points.try_recv() {
+        Ok(point) => drained.push(point),
+        Err(TryRecvError::Lagged(count)) => self.skip(count),
+        Err(TryRecvError::Empty | TryRecvError::Closed) => break,
+      }
+    }
+    self.inner.count_emitted(drained.len());
+    drained
+  }
+
+  /// Points this handle fell too far behind to see.
+  #[napi(getter)]
+  pub fn missed(&self) -> f64 {
+    self.missed.load(Ordering::Relaxed) as f64
+  }
+
+  /// Ends the subscription; a pending `next()` resolves with `done: true`. Called by `for await` on `break`.
+  #[napi(js_name = "return")]
+  pub fn finish(&self) -> TelemetryStreamResult {
+    self.close();
+    TelemetryStreamResult::done()
+  }
+
+  /// Ends the subscription and rethrows `error`, per the async iterator protocol.
+  #[napi(js_name = "throw")]
+  pub fn abort(&self, error: Option<JsUnknown>) -> Result<TelemetryStreamResult> {
+    self.close();
+    let message = match error {
+      Some(error) => error.coerce_to_string()?.into_utf8()?.into_owned()?,
+      None => "telemetry subscription aborted".to_string(),
+    };
+    Err(Error::from_reason(message))
+  }
+}
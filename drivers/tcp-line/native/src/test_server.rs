@@ -0,0 +1,185 @@
+use std::sync::atomic::{AtomicI64, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+
+use chrono::{SecondsFormat, Utc};
+use napi::bindgen_prelude::*;
+use napi_derive::napi;
+use parking_lot::Mutex;
+use serde::Deserialize;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::{TcpListener, TcpStream};
+use tokio::task::JoinHandle;
+use tokio::time::sleep;
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+struct TestServerConfig {
+  host: String,
+  /// `0` binds an ephemeral port; `start()` returns the actual one.
+  port: u16,
+  /// Sent once at the start of every connection, e.g. a CSV header.
+  header_lines: Vec<String>,
+  /// Fixture lines; `{ts}` is replaced with the send time.
+  lines: Vec<String>,
+  interval_ms: u64,
+  #[serde(rename = "loop")]
+  loop_lines: bool,
+  /// Closes each connection after this many fixture lines, to exercise reconnects.
+  close_after_lines: Option<u64>,
+}
+
+impl Default for TestServerConfig {
+  fn default() -> Self {
+    Self {
+      host: "127.0.0.1".to_string(),
+      port: 0,
+      header_lines: Vec::new(),
+      lines: Vec::new(),
+      interval_ms: 100,
+      loop_lines: true,
+      close_after_lines: None,
+    }
+  }
+}
+
+#[derive(Debug, Clone)]
+#[napi(object)]
+pub struct TestServerStatus {
+  pub listening: bool,
+  pub port: u32,
+  pub connections: i64,
+  pub activeConnections: u32,
+  pub linesSent: i64,
+}
+
+#[derive(Default)]
+struct ServerShared {
+  connections: AtomicI64,
+  lines_sent: AtomicI64,
+  clients: Mutex<Vec<JoinHandle<()>>>,
+}
+
+/// Fixture TCP server for end-to-end tests and demos: streams JSONL/CSV lines to every client at a fixed rate.
+#[napi]
+pub struct TcpLineTestServer {
+  config: TestServerConfig,
+  shared: Arc<ServerShared>,
+  port: Mutex<Option<u16>>,
+  accept_handle: Mutex<Option<JoinHandle<()>>>,
+}
+
+#[napi]
+impl TcpLineTestServer {
+  #[napi(constructor)]
+  pub fn new(config_json: String) -> Result<Self> {
+    let config: TestServerConfig = serde_json::from_str(&config_json)
+      .map_err(|err| Error::from_reason(format!("invalid config: {}", err)))?;
+    Ok(Self {
+      config,
+      shared: Arc::new(ServerShared::default()),
+      port: Mutex::new(None),
+      accept_handle: Mutex::new(None),
+    })
+  }
+
+  /// Binds and starts accepting clients, returning the bound port.
+  #[napi]
+  pub async fn start(&self) -> Result<u32> {
+    if let Some(port) = *self.port.lock() {
+      return Ok(port as u32);
+    }
+    let listener = TcpListener::bind((self.config.host.as_str(), self.config.port))
+      .await
+      .map_err(|err| Error::from_reason(format!("bind failed: {}", err)))?;
+    let port = listener.local_addr().map_err(|err| Error::from_reason(err.to_string()))?.port();
+
+    let config = self.config.clone();
+    let shared = Arc::clone(&self.shared);
+    let handle = tokio::spawn(async move {
+      while let Ok((stream, _)) = listener.accept().await {
+        shared.connections.fetch_add(1, Ordering::Relaxed);
+        let client = tokio::spawn(serve_client(stream, config.clone(), Arc::clone(&shared)));
+        let mut clients = shared.clients.lock();
+        clients.retain(|handle| !handle.is_finished());
+        clients.push(client);
+      }
+    });
+
+    *self.accept_handle.lock() = Some(handle);
+    *self.port.lock() = Some(port);
+    Ok(port as u32)
+  }
+
+  /// Closes every connected client but keeps listening, so drivers see a socket close and reconnect.
+  #[napi]
+  pub fn drop_connections(&self) {
+    for client in self.shared.clients.lock().drain(..) {
+      client.abort();
+    }
+  }
+
+  #[napi]
+  pub async fn stop(&self) -> Result<()> {
+    if let Some(handle) = self.accept_handle.lock().take() {
+      handle.abort();
+    }
+    self.drop_connections();
+    *self.port.lock() = None;
+    Ok(())
+  }
+
+  #[napi]
+  pub fn get_status(&self) -> TestServerStatus {
+    let port = *self.port.lock();
+    let active = self.shared.clients.lock().iter().filter(|handle| !handle.is_finished()).count();
+    TestServerStatus {
+      listening: port.is_some(),
+      port: port.unwrap_or(0) as u32,
+      connections: self.shared.connections.load(Ordering::Relaxed),
+      activeConnections: active as u32,
+      linesSent: self.shared.lines_sent.load(Ordering::Relaxed),
+    }
+  }
+}
+
+async fn serve_client(mut stream: TcpStream, config: TestServerConfig, shared: Arc<ServerShared>) {
+  for line in config.header_lines.iter() {
+    if write_line(&mut stream, line).await.is_err() {
+      return;
+    }
+  }
+
+  let mut sent: u64 = 0;
+  loop {
+    for line in config.lines.iter() {
+      if config.close_after_lines.is_some_and(|limit| sent >= limit) {
+        let _ = stream.shutdown().await;
+        return;
+      }
+      sleep(Duration::from_millis(config.interval_ms)).await;
+      if write_line(&mut stream, line).await.is_err() {
+        return;
+      }
+      sent += 1;
+      shared.lines_sent.fetch_add(1, Ordering::Relaxed);
+    }
+    if !config.loop_lines || config.lines.is_empty() {
+      break;
+    }
+  }
+
+  // Hold the connection open after a single pass so the driver keeps its last sample; exit once the client hangs up.
+  let mut buf = [0u8; 256];
+  while let Ok(read) = stream.read(&mut buf).await {
+    if read == 0 {
+      break;
+    }
+  }
+}
+
+async fn write_line(stream: &mut TcpStream, line: &str) -> std::io::Result<()> {
+  let line = line.replace("{ts}", &Utc::now().to_rfc3339_opts(SecondsFormat::Millis, true));
+  stream.write_all(line.as_bytes()).await?;
+  stream.write_all(b"\n").await
+}
@@ -0,0 +1,236 @@
+use napi::bindgen_prelude::*;
+use napi_derive::napi;
+use serde_json::Value;
+use tcp_line_core::validation::{deserialize, schema_value, unknown_keys, ConfigIssue};
+
+use crate::http_client::HttpUrl;
+use crate::listen::ConnectionMode;
+use crate::proxy::ProxyKind;
+use crate::TcpLineDriverConfig;
+
+/// Checks a TCP driver config without constructing a driver. Returns every problem found (unknown keys, wrong
+/// types, out-of-range values); an empty list means the config is accepted.
+#[napi]
+pub fn validate_config(config_json: String) -> Vec<ConfigIssue> {
+  let value: Value = match serde_json::from_str(&config_json) {
+    Ok(value) => value,
+    Err(err) => return vec![ConfigIssue::new("", format!("invalid json: {}", err))],
+  };
+  let mut issues = Vec::new();
+  unknown_keys(&schema_value::<TcpLineDriverConfig>(), &value, "", &mut issues);
+  match deserialize(value) {
+    Ok(config) => issues.extend(check_ranges(&config)),
+    Err(issue) => issues.push(issue),
+  }
+  issues
+}
+
+/// JSON Schema (draft 7, subschemas inlined) of the TCP driver config, for building config forms.
+#[napi]
+pub fn config_schema() -> String {
+  schema_value::<TcpLineDriverConfig>().to_string()
+}
+
+/// Parses a config the way the constructor and `update_config` do, naming the offending field on failure.
+pub(crate) fn parse_config(config_json: &str) -> Result<TcpLineDriverConfig> {
+  let value: Value =
+    serde_json::from_str(config_json).map_err(|err| Error::from_reason(format!("invalid config: {}", err)))?;
+  let config = deserialize(value).map_err(|issue| Error::from_reason(format!("invalid config: {}", issue.describe())))?;
+  check(&config)?;
+  Ok(config)
+}
+
+/// The range checks of `validate_config` for an already deserialized config, failing on the first issue.
+pub(crate) fn check(config: &TcpLineDriverConfig) -> Result<()> {
+  match check_ranges(config).first() {
+    Some(issue) => Err(Error::from_reason(format!("invalid config: {}", issue.describe()))),
+    None => Ok(()),
+  }
+}
+
+/// The parsing checks alone, for sources without a connection to validate (replay).
+pub(crate) fn check_parsing(config: &TcpLineDriverConfig) -> Result<()> {
+  match config.parser().issues().first() {
+    Some(issue) => Err(Error::from_reason(format!("invalid config: {}", issue.describe()))),
+    None => Ok(()),
+  }
+}
+
+fn check_ranges(config: &TcpLineDriverConfig) -> Vec<ConfigIssue> {
+  let mut issues = Vec::new();
+  let mut check = |ok: bool, path: &str, message: &str| {
+    if !ok {
+      issues.push(ConfigIssue::new(path, message));
+    }
+  };
+  let listen = config.mode == ConnectionMode::Listen;
+  check(config.port > 0 || listen, "port", "must be between 1 and 65535");
+  check(config.max_connections > 0, "maxConnections", "must be positive");
+  check(!listen || config.endpoints.is_empty(), "endpoints", "is not supported in listen mode");
+  check(!listen || config.proxy.is_none(), "proxy", "is not supported in listen mode");
+  check(!listen || config.handshake.is_empty(), "handshake", "is not supported in listen mode");
+  for (idx, step) in config.handshake.iter().enumerate() {
+    check(step.send.is_some() || step.expect.is_some(), &format!("handshake.{}", idx), "needs send or expect");
+    check(step.timeout_ms > 0, &format!("handshake.{}.timeoutMs", idx), "must be positive");
+    check(step.expect.as_deref() != Some(""), &format!("handshake.{}.expect", idx), "must not be empty");
+  }
+  for (idx, endpoint) in config.endpoints.iter().enumerate() {
+    check(!endpoint.host.is_empty(), &format!("endpoints.{}.host", idx), "must not be empty");
+    check(endpoint.port > 0, &format!("endpoints.{}.port", idx), "must be between 1 and 65535");
+  }
+  check(!config.line_delimiter.is_empty(), "lineDelimiter", "must not be empty");
+  if let Some(proxy) = &config.proxy {
+    check(!proxy.host.is_empty(), "proxy.host", "must not be empty");
+    check(proxy.port > 0, "proxy.port", "must be between 1 and 65535");
+    check(proxy.password.is_none() || proxy.username.is_some(), "proxy.password", "requires proxy.username");
+    if proxy.kind == ProxyKind::Socks5 {
+      let too_long = |value: &Option<String>| value.as_ref().is_some_and(|value| value.len() > 255);
+      check(!too_long(&proxy.username), "proxy.username", "must be at most 255 bytes for socks5");
+      check(!too_long(&proxy.password), "proxy.password", "must be at most 255 bytes for socks5");
+    }
+  }
+  check(config.max_line_bytes > 0, "maxLineBytes", "must be positive");
+  check(config.max_bytes_per_second != Some(0), "maxBytesPerSecond", "must be positive");
+  let reconnect = &config.reconnect;
+  check(
+    reconnect.min_backoff_ms <= reconnect.max_backoff_ms,
+    "reconnect.minBackoffMs",
+    "must not exceed reconnect.maxBackoffMs",
+  );
+  check(!reconnect.enabled || reconnect.max_backoff_ms > 0, "reconnect.maxBackoffMs", "must be positive");
+  check(reconnect.max_total_duration_ms != Some(0), "reconnect.maxTotalDurationMs", "must be positive");
+  check(config.read_timeout_ms != Some(0), "readTimeoutMs", "must be positive");
+  check(config.stale_data_alarm_ms != Some(0), "staleDataAlarmMs", "must be positive");
+  check(config.read_wait.timeout_ms != Some(0), "readWait.timeoutMs", "must be positive");
+  check(config.keepalive.idle_ms != Some(0), "keepalive.idleMs", "must be positive");
+  check(config.keepalive.interval_ms != Some(0), "keepalive.intervalMs", "must be positive");
+  check(config.commands.ack_timeout_ms > 0, "commands.ackTimeoutMs", "must be positive");
+  check(config.commands.interlock_timeout_ms > 0, "commands.interlockTimeoutMs", "must be positive");
+  check(config.commands.ack_prefix.as_deref() != Some(""), "commands.ackPrefix", "must not be empty");
+  check(config.commands.nak_prefix.as_deref() != Some(""), "commands.nakPrefix", "must not be empty");
+  check(config.clock.calibration_samples > 0, "clock.calibrationSamples", "must be positive");
+  let pipeline = &config.pipeline;
+  check(
+    pipeline.downsample.is_none() || pipeline.emit_interval_ms > 0,
+    "downsample",
+    "requires a positive emitIntervalMs",
+  );
+  if let Some(resample) = &config.resample {
+    check(resample.interval_ms > 0, "resample.intervalMs", "must be positive");
+  }
+  for (key, channel) in &pipeline.channels {
+    check(channel.deadband >= 0.0, &format!("channels.{}.deadband", key), "must not be negative");
+    let ordered = match (channel.min, channel.max) {
+      (Some(min), Some(max)) => min <= max,
+      _ => true,
+    };
+    check(ordered, &format!("channels.{}.min", key), &format!("must not exceed channels.{}.max", key));
+    check(channel.max_hold_ms > 0, &format!("channels.{}.maxHoldMs", key), "must be positive");
+  }
+  for (idx, rule) in config.alarms.iter().enumerate() {
+    check(!rule.name.is_empty(), &format!("alarms.{}.name", idx), "must not be empty");
+    check(rule.name != "staleData", &format!("alarms.{}.name", idx), "is reserved");
+    let duplicate = config.alarms[..idx].iter().any(|other| other.name == rule.name);
+    check(!duplicate, &format!("alarms.{}.name", idx), "must be unique");
+    check(!rule.channel.is_empty(), &format!("alarms.{}.channel", idx), "must not be empty");
+    check(rule.threshold.is_finite(), &format!("alarms.{}.threshold", idx), "must be finite");
+    check(rule.hysteresis >= 0.0, &format!("alarms.{}.hysteresis", idx), "must not be negative");
+    check(rule.command.as_deref() != Some(""), &format!("alarms.{}.command", idx), "must not be empty");
+  }
+  if let Some(energy) = &config.energy {
+    check(energy.rated_kw.is_finite() && energy.rated_kw > 0.0, "energy.ratedKw", "must be positive");
+    check(energy.max_gap_ms > 0, "energy.maxGapMs", "must be positive");
+  }
+  if let Some(events) = &pipeline.roast_events {
+    check(events.charge_drop_c > 0.0, "roastEvents.chargeDropC", "must be positive");
+    check(events.charge_window_s > 0.0, "roastEvents.chargeWindowS", "must be positive");
+    check(events.ror_window_s > 0.0, "roastEvents.rorWindowS", "must be positive");
+    check(events.dry_end_c < events.first_crack_c, "roastEvents.dryEndC", "must be below roastEvents.firstCrackC");
+  }
+  if let Some(queue) = &pipeline.queue {
+    check(queue.capacity > 0, "queue.capacity", "must be positive");
+  }
+  if let Some(sequence) = &config.sequence {
+    check(!sequence.field.is_empty(), "sequence.field", "must not be empty");
+  }
+  if let Some(heartbeat) = &config.heartbeat {
+    check(heartbeat.interval_ms > 0, "heartbeat.intervalMs", "must be positive");
+  }
+  if let Some(record) = &config.record {
+    check(!record.path.is_empty(), "record.path", "must not be empty");
+    check(record.max_files > 0, "record.maxFiles", "must be positive");
+  }
+  if let Some(capture) = &config.capture {
+    check(capture.max_entries > 0, "capture.maxEntries", "must be positive");
+    check(capture.metrics_interval_ms > 0, "capture.metricsIntervalMs", "must be positive");
+  }
+  if let Some(spool) = &config.spool {
+    check(!spool.path.is_empty(), "spool.path", "must not be empty");
+    check(spool.max_bytes > 0, "spool.maxBytes", "must be positive");
+  }
+  if let Some(influx) = &config.influx {
+    match HttpUrl::parse(&influx.url) {
+      Ok(url) => {
+        let exposed = influx.token.is_some() && !url.tls && !url.is_loopback();
+        check(!exposed, "influx.url", "must be https:// to send a token to another machine");
+      }
+      Err(err) => check(false, "influx.url", &err),
+    }
+    if let Some(token) = &influx.token {
+      check(!token.contains(['\r', '\n']), "influx.token", "must be a single line");
+    }
+    check(!influx.org.is_empty(), "influx.org", "must not be empty");
+    check(!influx.bucket.is_empty(), "influx.bucket", "must not be empty");
+    check(influx.batch_size > 0, "influx.batchSize", "must be positive");
+    check(influx.flush_interval_ms > 0, "influx.flushIntervalMs", "must be positive");
+    check(influx.max_buffered_points > 0, "influx.maxBufferedPoints", "must be positive");
+  }
+  if let Some(grpc) = &config.grpc {
+    check(!grpc.host.is_empty(), "grpc.host", "must not be empty");
+  }
+  if let Some(kafka) = &config.kafka {
+    check(!kafka.brokers.is_empty(), "kafka.brokers", "must list at least one broker");
+    for (idx, broker) in kafka.brokers.iter().enumerate() {
+      let valid = broker.rsplit_once(':').is_some_and(|(host, port)| !host.is_empty() && port.parse::<u16>().is_ok());
+      check(valid, &format!("kafka.brokers.{}", idx), "must be host:port");
+    }
+    check(!kafka.topic.is_empty(), "kafka.topic", "must not be empty");
+    check(kafka.batch_size > 0, "kafka.batchSize", "must be positive");
+    check(kafka.linger_ms > 0, "kafka.lingerMs", "must be positive");
+    check(kafka.delivery_timeout_ms > 0, "kafka.deliveryTimeoutMs", "must be positive");
+    check(kafka.max_buffered_points > 0, "kafka.maxBufferedPoints", "must be positive");
+  }
+  issues.extend(config.parser().issues());
+  issues
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  fn issues(config: Value) -> Vec<String> {
+    validate_config(config.to_string()).iter().map(ConfigIssue::describe).collect()
+  }
+
+  #[test]
+  fn checks_the_flattened_pipeline_settings() {
+    let config = serde_json::json!({
+      "host": "127.0.0.1",
+      "port": 9000,
+      "format": "jsonl",
+      "csv": { "hasHeader": false, "columns": [], "delimiter": "," },
+      "reconnect": { "enabled": false, "minBackoffMs": 0, "maxBackoffMs": 0 },
+      "emitIntervalMs": 100,
+      "dedupeWithinMs": 0,
+      "strictness": "warn",
+      "channels": { "btC": { "deadband": 0.5 } },
+    });
+    assert_eq!(issues(config.clone()), Vec::<String>::new());
+    let mut unknown = config.clone();
+    unknown["emitIntervalMillis"] = 100.into();
+    assert_eq!(issues(unknown), ["emitIntervalMillis: unknown key"]);
+    let mut negative = config;
+    negative["channels"]["btC"]["deadband"] = (-1.0).into();
+    assert_eq!(issues(negative), ["channels.btC.deadband: must not be negative"]);
+  }
+}
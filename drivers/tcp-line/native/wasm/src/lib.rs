@@ -0,0 +1,114 @@
+//! Browser build of the tcp-line parser, for validating configs and previewing how pasted device lines parse with
+//! the same code the driver runs. There are no sockets here: lines go in, samples come out. Configs are passed and
+//! results returned as JSON strings, like the Node binding does.
+
+use chrono::SecondsFormat;
+use serde_json::{json, Value};
+use tcp_line_core::validation::{self, ConfigIssue};
+use tcp_line_core::{ParseError, ParserConfig, RawTelemetrySample, TcpLineParser};
+use wasm_bindgen::prelude::*;
+
+/// Checks the parsing settings of a config, either a parser config or a whole driver config (its connection
+/// settings are ignored). Returns a JSON array of `{ path, message }`; `[]` means the parser accepts it.
+#[wasm_bindgen(js_name = validateParserConfig)]
+pub fn validate_parser_config(config_json: &str) -> String {
+  let issues = match serde_json::from_str::<Value>(config_json) {
+    Ok(value) => validation::validate_parser_config(&value),
+    Err(err) => vec![ConfigIssue::new("", format!("invalid json: {}", err))],
+  };
+  let issues = issues.iter().map(|issue| json!({ "path": issue.path, "message": issue.message }));
+  Value::Array(issues.collect()).to_string()
+}
+
+/// JSON Schema (draft 7, subschemas inlined) of the parser config, for building config forms.
+#[wasm_bindgen(js_name = parserConfigSchema)]
+pub fn parser_config_schema() -> String {
+  validation::schema_value::<ParserConfig>().to_string()
+}
+
+/// Parses `text` line by line with a fresh parser and returns a JSON array with one `parseLine` result per line.
+#[wasm_bindgen(js_name = previewLines)]
+pub fn preview_lines(config_json: &str, text: &str) -> Result<String, JsError> {
+  let mut parser = LineParser::new(config_json)?;
+  let results = text.lines().map(|line| parser.parse(line)).collect();
+  Ok(Value::Array(results).to_string())
+}
+
+/// A parser that keeps its state between lines (a CSV header, a partly assembled record, the detected format),
+/// like the driver's parser for one connection.
+#[wasm_bindgen]
+pub struct LineParser {
+  parser: TcpLineParser,
+}
+
+#[wasm_bindgen]
+impl LineParser {
+  /// Throws on the first problem `validateParserConfig` would report.
+  #[wasm_bindgen(constructor)]
+  pub fn new(config_json: &str) -> Result<LineParser, JsError> {
+    let value: Value =
+      serde_json::from_str(config_json).map_err(|err| JsError::new(&format!("invalid config: {}", err)))?;
+    if let Some(issue) = validation::validate_parser_config(&value).first() {
+      return Err(JsError::new(&format!("invalid config: {}", issue.describe())));
+    }
+    let config = validation::deserialize::<ParserConfig>(value)
+      .map_err(|issue| JsError::new(&format!("invalid config: {}", issue.describe())))?;
+    Ok(Self { parser: TcpLineParser::new(config) })
+  }
+
+  /// Parses one line and returns `{ line, outcome, sample, batch, error, errorKind, fieldErrors }` as JSON. `outcome`
+  /// is `parsed` (it completed a sample), `skipped` (it parsed without one, e.g. a CSV header or part of a record) or
+  /// `error`. `batch` holds the samples after the first of a JSON array line. `fieldErrors` lists the fields
+  /// `strictness: warn` left out of the sample.
+  #[wasm_bindgen(js_name = parseLine)]
+  pub fn parse_line(&mut self, line: &str) -> String {
+    self.parse(line).to_string()
+  }
+
+  /// Forgets the CSV header, partial record and undecided lines, as a reconnect does.
+  pub fn reset(&mut self) {
+    self.parser.reset();
+  }
+
+  /// What `autodetect` settled on, as JSON `{ format, delimiter, hasHeader }`; `undefined` until it has decided.
+  #[wasm_bindgen(js_name = detectedFormat)]
+  pub fn detected_format(&self) -> Option<String> {
+    let status = self.parser.detected()?.status();
+    let detected = json!({ "format": status.format, "delimiter": status.delimiter, "hasHeader": status.hasHeader });
+    Some(detected.to_string())
+  }
+}
+
+impl LineParser {
+  fn parse(&mut self, line: &str) -> Value {
+    let parsed = self.parser.parse_line(line);
+    let batch = self.parser.take_batch().map(|batch| batch.iter().map(sample_json).collect::<Vec<_>>());
+    let (outcome, sample, error): (_, _, Option<&ParseError>) = match &parsed {
+      Ok(Some(sample)) => ("parsed", Some(sample_json(sample)), None),
+      Ok(None) => ("skipped", None, None),
+      Err(err) => ("error", None, Some(err)),
+    };
+    json!({
+      "line": line,
+      "outcome": outcome,
+      "sample": sample,
+      "batch": batch,
+      "error": error.map(ParseError::to_string),
+      "errorKind": error.map(ParseError::kind),
+      "fieldErrors": self.parser.field_errors().iter().map(ToString::to_string).collect::<Vec<_>>(),
+    })
+  }
+}
+
+fn sample_json(sample: &RawTelemetrySample) -> Value {
+  json!({
+    "ts": sample.ts.to_rfc3339_opts(SecondsFormat::Millis, true),
+    "deviceTs": sample.device_ts,
+    "btC": sample.bt_c,
+    "etC": sample.et_c,
+    "powerPct": sample.power_pct,
+    "fanPct": sample.fan_pct,
+    "drumRpm": sample.drum_rpm,
+    "extras": sample.extras,
+  })
+}
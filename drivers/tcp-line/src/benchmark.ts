@@ -0,0 +1,24 @@
+import { loadNative } from "./native";
+
+/** What `benchmarkParser` measured. */
+export interface ParserBenchmark {
+  /** Lines parsed: every sample line once per iteration. */
+  lines: number;
+  /** Lines that completed a sample. */
+  samples: number;
+  /** Lines that completed none, e.g. a CSV header or part of a record. */
+  skipped: number;
+  errors: number;
+  elapsedMs: number;
+  linesPerSecond: number;
+  bytesPerSecond: number;
+}
+
+/**
+ * Parses `sampleLines` `iterations` times with the parsing settings of a native TCP driver config (the shape
+ * `TcpLineDriverConfigSchema` produces) and reports the throughput, for sizing a host or catching a parsing
+ * regression. Each pass starts like a new connection; only the parser is timed. Runs off the event loop.
+ */
+export function benchmarkParser(config: unknown, sampleLines: string[], iterations = 1000): Promise<ParserBenchmark> {
+  return loadNative().benchmarkParser(JSON.stringify(config), sampleLines, iterations);
+}
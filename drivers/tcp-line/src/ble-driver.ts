@@ -0,0 +1,27 @@
+import type { DriverConfig } from "@sim-corp/driver-core";
+import { BleDriverConfigSchema } from "./config";
+import type { DriverError, StateChangeEvent } from "./metrics";
+import { loadNative } from "./native";
+import { NativeBackedDriver } from "./native-driver";
+
+type BleNative = InstanceType<ReturnType<typeof loadNative>["BleDriverNative"]>;
+
+export class BleDriver extends NativeBackedDriver<BleNative> {
+  constructor(cfg: DriverConfig) {
+    const config = BleDriverConfigSchema.parse({
+      ...(cfg.connection ?? {})
+    });
+    const { BleDriverNative } = loadNative();
+    super(new BleDriverNative(JSON.stringify(config), cfg.machineId));
+  }
+
+  /** Subscribes to state transitions, e.g. `CONNECTED` → `DISCONNECTED` when the probe goes out of range. */
+  onStateChange(callback: (event: StateChangeEvent) => void): void {
+    this.native.onStateChange(callback);
+  }
+
+  /** Subscribes to connection failures and lost devices. */
+  onError(callback: (error: DriverError) => void): void {
+    this.native.onError(callback);
+  }
+}
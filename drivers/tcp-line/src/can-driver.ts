@@ -0,0 +1,30 @@
+import type { DriverConfig } from "@sim-corp/driver-core";
+import { CanDriverConfigSchema } from "./config";
+import type { DriverError, StateChangeEvent } from "./metrics";
+import { loadNative } from "./native";
+import { NativeBackedDriver } from "./native-driver";
+
+type CanNative = InstanceType<NonNullable<ReturnType<typeof loadNative>["CanDriverNative"]>>;
+
+export class CanDriver extends NativeBackedDriver<CanNative> {
+  constructor(cfg: DriverConfig) {
+    const config = CanDriverConfigSchema.parse({
+      ...(cfg.connection ?? {})
+    });
+    const { CanDriverNative } = loadNative();
+    if (!CanDriverNative) {
+      throw new Error("CanDriver needs Linux SocketCAN; this native build doesn't include it");
+    }
+    super(new CanDriverNative(JSON.stringify(config), cfg.machineId));
+  }
+
+  /** Subscribes to state transitions, e.g. `CONNECTED` → `DISCONNECTED` when the interface goes away. */
+  onStateChange(callback: (event: StateChangeEvent) => void): void {
+    this.native.onStateChange(callback);
+  }
+
+  /** Subscribes to socket errors and read timeouts. */
+  onError(callback: (error: DriverError) => void): void {
+    this.native.onError(callback);
+  }
+}
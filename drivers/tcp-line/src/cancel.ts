@@ -0,0 +1,30 @@
+import { loadNative, type NativeCancelToken } from "./native";
+
+/** Bounds one `connect()` or `readTelemetry()` call; the driver itself keeps running either way. */
+export interface CallOptions {
+  /** Rejects the call once this many milliseconds have passed. */
+  timeoutMs?: number;
+  /** Rejects the call with `signal.reason` once aborted. */
+  signal?: AbortSignal;
+}
+
+/** Runs a native call with a cancel token tied to `signal`, rethrowing the signal's reason if it aborted the call. */
+export async function withSignal<T>(
+  signal: AbortSignal | undefined,
+  call: (cancel?: NativeCancelToken) => Promise<T>
+): Promise<T> {
+  if (!signal) {
+    return call();
+  }
+  signal.throwIfAborted();
+  const token = new (loadNative().CancelToken)();
+  const abort = () => token.cancel(String(signal.reason));
+  signal.addEventListener("abort", abort, { once: true });
+  try {
+    return await call(token);
+  } catch (error) {
+    throw signal.aborted ? signal.reason : error;
+  } finally {
+    signal.removeEventListener("abort", abort);
+  }
+}
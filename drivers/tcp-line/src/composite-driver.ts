@@ -0,0 +1,41 @@
+import type { DriverConfig } from "@sim-corp/driver-core";
+import { CompositeDriverConfigSchema } from "./config";
+import type { DriverError, DriverMetrics, DriverState, StateChangeEvent } from "./metrics";
+import { loadNative } from "./native";
+import { NativeBackedDriver } from "./native-driver";
+
+export interface CompositeSourceStatus {
+  name: string;
+  kind: string;
+  required: boolean;
+  state: DriverState;
+  metrics: DriverMetrics;
+}
+
+type CompositeNative = InstanceType<ReturnType<typeof loadNative>["CompositeDriverNative"]>;
+
+/** Merges several drivers for one machine into one stream; see docs/drivers/tcp-line.md. */
+export class CompositeDriver extends NativeBackedDriver<CompositeNative> {
+  constructor(cfg: DriverConfig) {
+    const config = CompositeDriverConfigSchema.parse({
+      ...(cfg.connection ?? {})
+    });
+    const { CompositeDriverNative } = loadNative();
+    super(new CompositeDriverNative(JSON.stringify(config), cfg.machineId));
+  }
+
+  /** One entry per source, in config order. */
+  getSourceStatuses(): CompositeSourceStatus[] {
+    return this.native.getSourceStatuses();
+  }
+
+  /** Subscribes to state transitions, e.g. `CONNECTED` → `DISCONNECTED` when a source drops. */
+  onStateChange(callback: (event: StateChangeEvent) => void): void {
+    this.native.onStateChange(callback);
+  }
+
+  /** Subscribes to every source's errors, and to the composite failing. */
+  onError(callback: (error: DriverError) => void): void {
+    this.native.onError(callback);
+  }
+}
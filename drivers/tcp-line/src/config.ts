@@ -1,31 +1,528 @@
 import { z } from "zod";
 
+const RoastEventConfigSchema = z.object({
+  chargeDropC: z.number().positive().default(10),
+  chargeWindowS: z.number().positive().default(30),
+  rorWindowS: z.number().positive().default(30),
+  dryEndC: z.number().default(150),
+  firstCrackC: z.number().default(196)
+});
+
+const AggregationSchema = z.enum(["last", "mean", "min", "max"]);
+
+const DownsampleConfigSchema = z.object({
+  mode: AggregationSchema.default("last"),
+  channels: z.record(AggregationSchema).default({})
+});
+
+const ResampleConfigSchema = z.object({
+  intervalMs: z.number().int().positive(),
+  mode: z.enum(["hold", "linear"]).default("hold")
+});
+
+const ChannelConfigSchema = z.object({
+  emitOnChangeOnly: z.boolean().default(false),
+  deadband: z.number().nonnegative().default(0),
+  min: z.number().optional(),
+  max: z.number().optional(),
+  fill: z.enum(["none", "holdLast", "linear"]).default("none"),
+  maxHoldMs: z.number().int().positive().default(5000)
+});
+
+const AlarmRuleSchema = z.object({
+  name: z.string().min(1),
+  channel: z.string().min(1),
+  comparator: z.enum(["above", "below"]),
+  threshold: z.number(),
+  hysteresis: z.number().nonnegative().default(0),
+  debounceMs: z.number().int().nonnegative().default(0),
+  command: z.string().min(1).optional()
+});
+
+const QueueConfigSchema = z.object({
+  capacity: z.number().int().positive().default(1024),
+  policy: z.enum(["dropOldest", "dropNewest", "block"]).default("dropOldest")
+});
+
+const FaultConfigSchema = z.object({
+  disconnectEverySeconds: z.number().positive().optional(),
+  disconnectedForMs: z.number().int().nonnegative().default(2000),
+  corruptLineProbability: z.number().min(0).max(1).default(0),
+  maxDelayMs: z.number().int().nonnegative().default(0),
+  seed: z.number().int().nonnegative().optional()
+});
+
+export const GrpcConfigSchema = z.object({
+  host: z.string().min(1).default("127.0.0.1"),
+  port: z.number().int().min(0).max(65535).default(50051)
+});
+
 export const TcpLineDriverConfigSchema = z.object({
+  mode: z.enum(["connect", "listen"]).default("connect"),
   host: z.string().default("127.0.0.1"),
-  port: z.number().int().positive(),
-  format: z.enum(["jsonl", "csv"]).default("jsonl"),
+  port: z.number().int().nonnegative(),
+  maxConnections: z.number().int().positive().default(1),
+  endpoints: z
+    .array(z.object({ host: z.string().min(1), port: z.number().int().positive() }))
+    .default([]),
+  failover: z.enum(["ordered", "roundRobin"]).default("ordered"),
+  onDuplicate: z.enum(["allow", "error", "share"]).default("allow"),
+  framing: z.enum(["newline", "cobs", "slip", "lengthPrefix"]).default("newline"),
+  lineDelimiter: z.string().min(1).default("\n"),
+  maxLineBytes: z.number().int().positive().default(65536),
+  maxBytesPerSecond: z.number().int().positive().optional(),
+  encoding: z.enum(["utf8", "latin1", "lossy"]).default("utf8"),
+  format: z.enum(["jsonl", "csv", "regex", "xml", "keyValue", "autodetect"]).default("jsonl"),
   csv: z
     .object({
       hasHeader: z.boolean().default(false),
       columns: z.array(z.string()).default([]),
-      delimiter: z.string().default(",")
+      delimiter: z.string().default(","),
+      columnMismatch: z.enum(["lenient", "strict"]).default("lenient")
+    })
+    .default({}),
+  regex: z.object({ pattern: z.string().min(1) }).optional(),
+  xml: z.object({ fields: z.record(z.string().min(1)) }).optional(),
+  json: z
+    .object({
+      flatten: z
+        .object({
+          maxDepth: z.number().int().positive().default(3),
+          separator: z.string().min(1).default(".")
+        })
+        .optional(),
+      fields: z.record(z.string().min(1)).default({})
     })
     .default({}),
+  checksum: z.enum(["none", "nmea", "crc16"]).default("none"),
+  assembly: z
+    .object({
+      startMarker: z.string().min(1).optional(),
+      endMarker: z.string().min(1).optional(),
+      lineCount: z.number().int().positive().optional(),
+      separator: z.string().default(" "),
+      maxLines: z.number().int().positive().default(64)
+    })
+    .optional(),
   emitIntervalMs: z.number().int().positive().default(1000),
+  downsample: DownsampleConfigSchema.optional(),
+  resample: ResampleConfigSchema.optional(),
+  readWait: z
+    .object({
+      mode: z.enum(["fail", "waitForever"]).default("fail"),
+      timeoutMs: z.number().int().positive().optional()
+    })
+    .default({}),
+  readMode: z.enum(["latest", "consume"]).default("latest"),
   dedupeWithinMs: z.number().int().nonnegative().default(200),
+  dedupeStrategy: z.enum(["timestamp", "identicalValues", "off"]).default("timestamp"),
+  channels: z.record(ChannelConfigSchema).default({}),
+  powerField: z.enum(["gasPct", "powerPct", "both"]).default("gasPct"),
   offsets: z
     .object({
       btC: z.number().default(0),
-      etC: z.number().default(0)
+      etC: z.number().default(0),
+      ambientC: z.number().default(0),
+      humidityPct: z.number().default(0),
+      pressureHpa: z.number().default(0)
+    })
+    .default({}),
+  probes: z
+    .object({
+      sensors: z
+        .array(
+          z.object({
+            key: z.string().min(1),
+            channel: z.enum(["btC", "etC"]),
+            gain: z.number().default(1),
+            offset: z.number().default(0)
+          })
+        )
+        .default([]),
+      combine: z.enum(["first", "mean", "min", "max"]).default("first")
     })
-    .default({ btC: 0, etC: 0 }),
+    .default({}),
+  environment: z
+    .object({
+      temperatureUnit: z.enum(["c", "f", "k"]).default("c"),
+      pressureUnit: z.enum(["hPa", "kPa", "Pa", "inHg", "mmHg", "psi"]).default("hPa")
+    })
+    .default({}),
+  channelRegistry: z
+    .record(
+      z.enum(["exhaustC", "inletC", "drumPressurePa", "airflowPct"]),
+      z.object({
+        key: z.string().min(1).optional(),
+        scale: z.number().default(1),
+        offset: z.number().default(0)
+      })
+    )
+    .default({}),
+  extras: z
+    .object({
+      include: z.array(z.string()).optional(),
+      exclude: z.array(z.string()).default([]),
+      rename: z.record(z.string().min(1)).default({}),
+      maxPerSample: z.number().int().nonnegative().optional(),
+      reserved: z
+        .array(z.string().min(1))
+        .default(["ts", "btC", "etC", "powerPct", "fanPct", "drumRpm", "ambientC", "humidityPct", "pressureHpa"]),
+      onReserved: z.enum(["drop", "prefix", "error"]).default("drop"),
+      enums: z.record(z.record(z.union([z.boolean(), z.number(), z.string()]))).default({})
+    })
+    .default({}),
   reconnect: z
     .object({
       enabled: z.boolean().default(true),
       minBackoffMs: z.number().default(250),
-      maxBackoffMs: z.number().default(5000)
+      maxBackoffMs: z.number().default(5000),
+      jitter: z.enum(["none", "full", "equal"]).default("none"),
+      maxAttempts: z.number().int().nonnegative().optional(),
+      maxTotalDurationMs: z.number().int().positive().optional()
+    })
+    .default({ enabled: true, minBackoffMs: 250, maxBackoffMs: 5000, jitter: "none" }),
+  record: z
+    .object({
+      path: z.string().min(1),
+      maxBytes: z.number().int().nonnegative().default(10 * 1024 * 1024),
+      maxFiles: z.number().int().positive().default(5)
+    })
+    .optional(),
+  capture: z
+    .object({
+      maxEntries: z.number().int().positive().default(100000),
+      metricsIntervalMs: z.number().int().positive().default(1000)
+    })
+    .optional(),
+  spool: z
+    .object({
+      path: z.string().min(1),
+      maxBytes: z.number().int().positive().default(100 * 1024 * 1024)
+    })
+    .optional(),
+  influx: z
+    .object({
+      url: z.string().regex(/^https?:\/\//, "must be an http:// or https:// URL"),
+      org: z.string().min(1),
+      bucket: z.string().min(1),
+      token: z.string().regex(/^[^\r\n]*$/, "must be a single line").optional(),
+      measurement: z.string().min(1).default("roaster_telemetry"),
+      batchSize: z.number().int().positive().default(500),
+      flushIntervalMs: z.number().int().positive().default(1000),
+      maxRetries: z.number().int().nonnegative().default(3),
+      retryBackoffMs: z.number().int().nonnegative().default(500),
+      maxBufferedPoints: z.number().int().positive().default(10000)
+    })
+    .optional(),
+  kafka: z
+    .object({
+      brokers: z.array(z.string().regex(/^.+:\d+$/, "must be host:port")).min(1),
+      topic: z.string().min(1),
+      format: z.enum(["json", "avro"]).default("json"),
+      clientId: z.string().min(1).default("tcp-line-driver"),
+      batchSize: z.number().int().positive().default(100),
+      lingerMs: z.number().int().positive().default(100),
+      deliveryTimeoutMs: z.number().int().positive().default(30000),
+      maxBufferedPoints: z.number().int().positive().default(10000)
+    })
+    .optional(),
+  grpc: GrpcConfigSchema.optional(),
+  commands: z
+    .object({
+      lineEnding: z.string().default("\n"),
+      echo: z.boolean().default(false),
+      ackPrefix: z.string().min(1).optional(),
+      nakPrefix: z.string().min(1).optional(),
+      ackTimeoutMs: z.number().int().positive().default(1000),
+      interlockTimeoutMs: z.number().int().positive().default(250),
+      templates: z
+        .object({
+          powerPct: z.string().min(1).optional(),
+          fanPct: z.string().min(1).optional(),
+          drumRpm: z.string().min(1).optional()
+        })
+        .default({})
+    })
+    .default({}),
+  keepalive: z
+    .object({
+      enabled: z.boolean().default(false),
+      idleMs: z.number().int().positive().optional(),
+      intervalMs: z.number().int().positive().optional(),
+      retries: z.number().int().positive().optional()
+    })
+    .default({}),
+  readTimeoutMs: z.number().int().positive().optional(),
+  staleDataAlarmMs: z.number().int().positive().optional(),
+  alarms: z.array(AlarmRuleSchema).default([]),
+  energy: z
+    .object({
+      ratedKw: z.number().positive(),
+      maxGapMs: z.number().int().positive().default(10000)
+    })
+    .optional(),
+  connectTimeoutMs: z.number().int().nonnegative().default(5000),
+  shutdownTimeoutMs: z.number().int().nonnegative().default(2000),
+  proxy: z
+    .object({
+      type: z.enum(["socks5", "http"]),
+      host: z.string().min(1),
+      port: z.number().int().positive(),
+      username: z.string().optional(),
+      password: z.string().optional()
+    })
+    .optional(),
+  handshake: z
+    .array(
+      z.object({
+        send: z.string().optional(),
+        expect: z.string().min(1).optional(),
+        timeoutMs: z.number().int().positive().default(5000)
+      })
+    )
+    .default([]),
+  heartbeat: z
+    .object({
+      intervalMs: z.number().int().positive(),
+      payload: z.string().default("")
+    })
+    .optional(),
+  timestamp: z
+    .object({
+      format: z.string().min(1).default("rfc3339"),
+      timezone: z.string().min(1).default("UTC")
+    })
+    .default({}),
+  strictness: z.enum(["lenient", "warn", "strict"]).default("lenient"),
+  clock: z
+    .object({
+      mode: z.enum(["device", "host", "corrected"]).default("device"),
+      calibrationSamples: z.number().int().positive().default(10),
+      elapsed: z.enum(["device", "host"]).default("device")
+    })
+    .default({}),
+  roastEvents: RoastEventConfigSchema.optional(),
+  sequence: z
+    .object({
+      field: z.string().min(1),
+      reorderWindow: z.number().int().nonnegative().default(16)
+    })
+    .optional(),
+  ordering: z
+    .object({
+      policy: z.enum(["accept", "dropOlder", "reorder"]).default("accept"),
+      windowMs: z.number().int().nonnegative().default(500)
     })
-    .default({ enabled: true, minBackoffMs: 250, maxBackoffMs: 5000 })
+    .default({}),
+  queue: QueueConfigSchema.optional(),
+  runtime: z.string().min(1).optional()
 });
 
 export type TcpLineDriverConfig = z.infer<typeof TcpLineDriverConfigSchema>;
+
+/** How samples become points; every driver config takes these, like `PipelineConfig` on the native side. */
+const pipelineFields = {
+  emitIntervalMs: true,
+  downsample: true,
+  dedupeWithinMs: true,
+  dedupeStrategy: true,
+  channels: true,
+  powerField: true,
+  roastEvents: true,
+  queue: true
+} as const;
+
+/** The pipeline settings that shape parsed readings; the simulator and composites don't parse any. */
+const readingFields = {
+  offsets: true,
+  probes: true,
+  environment: true,
+  channelRegistry: true,
+  extras: true,
+  strictness: true
+} as const;
+
+export const ReplayDriverConfigSchema = TcpLineDriverConfigSchema.pick({
+  format: true,
+  csv: true,
+  regex: true,
+  xml: true,
+  json: true,
+  checksum: true,
+  encoding: true,
+  assembly: true,
+  timestamp: true,
+  ...pipelineFields,
+  ...readingFields
+}).extend({
+  path: z.string().min(1),
+  speed: z.number().nonnegative().default(1),
+  loop: z.boolean().default(false),
+  faults: FaultConfigSchema.optional()
+});
+
+export type ReplayDriverConfig = z.infer<typeof ReplayDriverConfigSchema>;
+
+export const SimulatedDriverConfigSchema = TcpLineDriverConfigSchema.pick(pipelineFields).extend({
+  durationSeconds: z.number().positive().default(600),
+  sampleIntervalMs: z.number().int().positive().default(1000),
+  noiseC: z.number().nonnegative().default(0.3),
+  speed: z.number().positive().default(1),
+  loop: z.boolean().default(false),
+  seed: z.number().int().nonnegative().optional(),
+  chargeTempC: z.number().default(200),
+  turningPointC: z.number().default(95),
+  dryEndC: z.number().default(150),
+  firstCrackC: z.number().default(196),
+  dropTempC: z.number().default(208),
+  dedupeWithinMs: z.number().int().nonnegative().default(0),
+  faults: FaultConfigSchema.optional()
+});
+
+export type SimulatedDriverConfig = z.infer<typeof SimulatedDriverConfigSchema>;
+
+export const OpcUaDriverConfigSchema = TcpLineDriverConfigSchema.pick({
+  reconnect: true,
+  ...pipelineFields,
+  ...readingFields
+}).extend({
+  endpointUrl: z.string().startsWith("opc.tcp://"),
+  securityPolicy: z
+    .enum(["None", "Basic128Rsa15", "Basic256", "Basic256Sha256", "Aes128Sha256RsaOaep", "Aes256Sha256RsaPss"])
+    .default("None"),
+  securityMode: z.enum(["None", "Sign", "SignAndEncrypt"]).optional(),
+  username: z.string().min(1).optional(),
+  password: z.string().optional(),
+  nodes: z.record(z.string().min(1)),
+  publishingIntervalMs: z.number().int().positive().default(1000),
+  pkiDir: z.string().min(1).optional(),
+  trustServerCerts: z.boolean().default(false),
+  connectTimeoutMs: z.number().int().positive().default(10000)
+});
+
+export type OpcUaDriverConfig = z.infer<typeof OpcUaDriverConfigSchema>;
+
+export const HttpPollDriverConfigSchema = TcpLineDriverConfigSchema.pick({
+  reconnect: true,
+  ...pipelineFields,
+  ...readingFields
+}).extend({
+  url: z.string().regex(/^https?:\/\//, "must be an http:// or https:// URL"),
+  headers: z.record(z.string().regex(/^[^\r\n]*$/, "must be a single line")).default({}),
+  pollIntervalMs: z.number().int().positive().default(1000),
+  requestTimeoutMs: z.number().int().positive().default(5000),
+  mapping: z.record(z.string().min(1))
+});
+
+export type HttpPollDriverConfig = z.infer<typeof HttpPollDriverConfigSchema>;
+
+const BleFieldSchema = z.object({
+  channel: z.string().min(1),
+  byte: z.number().int().nonnegative().default(0),
+  type: z.enum(["u8", "i8", "u16le", "u16be", "i16le", "i16be", "u32le", "u32be", "i32le", "i32be", "f32le", "f32be"]),
+  scale: z.number().finite().default(1),
+  offset: z.number().finite().default(0)
+});
+
+export const BleDriverConfigSchema = TcpLineDriverConfigSchema.pick({
+  reconnect: true,
+  ...pipelineFields,
+  ...readingFields
+}).extend({
+  address: z.string().min(1).optional(),
+  namePrefix: z.string().min(1).optional(),
+  serviceUuid: z.string().min(1),
+  characteristics: z.array(z.object({ uuid: z.string().min(1), fields: z.array(BleFieldSchema).min(1) })).min(1),
+  scanTimeoutMs: z.number().int().positive().default(30000),
+  connectTimeoutMs: z.number().int().positive().default(10000)
+});
+
+export type BleDriverConfig = z.infer<typeof BleDriverConfigSchema>;
+
+const CanSignalSchema = z.object({
+  channel: z.string().min(1),
+  frameId: z.number().int().nonnegative(),
+  extended: z.boolean().default(false),
+  startBit: z.number().int().min(0).max(63),
+  length: z.number().int().min(1).max(64),
+  byteOrder: z.enum(["little", "big"]).default("little"),
+  signed: z.boolean().default(false),
+  scale: z.number().finite().default(1),
+  offset: z.number().finite().default(0)
+});
+
+export const CanDriverConfigSchema = TcpLineDriverConfigSchema.pick({
+  reconnect: true,
+  ...pipelineFields,
+  ...readingFields
+}).extend({
+  interface: z.string().min(1),
+  signals: z.array(CanSignalSchema).min(1),
+  readTimeoutMs: z.number().int().positive().optional()
+});
+
+export type CanDriverConfig = z.infer<typeof CanDriverConfigSchema>;
+
+const SnmpOidSchema = z.object({
+  oid: z.string().min(1),
+  scale: z.number().finite().default(1),
+  offset: z.number().finite().default(0)
+});
+
+export const SnmpDriverConfigSchema = TcpLineDriverConfigSchema.pick({
+  reconnect: true,
+  ...pipelineFields,
+  ...readingFields
+}).extend({
+  host: z.string().min(1),
+  port: z.number().int().min(1).max(65535).default(161),
+  version: z.enum(["v1", "v2c", "v3"]).default("v2c"),
+  community: z.string().default("public"),
+  v3: z
+    .object({
+      username: z.string().min(1),
+      authProtocol: z.enum(["md5", "sha1", "sha224", "sha256", "sha384", "sha512"]).default("sha1"),
+      authPassword: z.string().min(8).optional(),
+      privProtocol: z.enum(["des", "aes128", "aes192", "aes256"]).default("aes128"),
+      privPassword: z.string().min(8).optional(),
+      contextName: z.string().optional(),
+      keyExtension: z.enum(["blumenthal", "reeder"]).optional()
+    })
+    .optional(),
+  oids: z.record(SnmpOidSchema),
+  pollIntervalMs: z.number().int().positive().default(5000),
+  requestTimeoutMs: z.number().int().positive().default(2000)
+});
+
+export type SnmpDriverConfig = z.infer<typeof SnmpDriverConfigSchema>;
+
+function compositeSource<K extends string, T extends z.ZodTypeAny>(kind: K, connection: T) {
+  return z.object({
+    name: z.string().min(1),
+    kind: z.literal(kind),
+    connection,
+    channels: z.array(z.string().min(1)).min(1).optional(),
+    required: z.boolean().default(true)
+  });
+}
+
+export const CompositeDriverConfigSchema = TcpLineDriverConfigSchema.pick(pipelineFields).extend({
+  sources: z
+    .array(
+      z.discriminatedUnion("kind", [
+        compositeSource("tcp", TcpLineDriverConfigSchema),
+        compositeSource("replay", ReplayDriverConfigSchema),
+        compositeSource("simulated", SimulatedDriverConfigSchema),
+        compositeSource("opcua", OpcUaDriverConfigSchema),
+        compositeSource("httpPoll", HttpPollDriverConfigSchema),
+        compositeSource("ble", BleDriverConfigSchema),
+        compositeSource("can", CanDriverConfigSchema),
+        compositeSource("snmp", SnmpDriverConfigSchema)
+      ])
+    )
+    .min(1),
+  emitOn: z.enum(["any", "first"]).default("any"),
+  maxSkewMs: z.number().int().nonnegative().default(2000)
+});
+
+export type CompositeDriverConfig = z.infer<typeof CompositeDriverConfigSchema>;
@@ -0,0 +1,25 @@
+import { loadNative } from "./native";
+
+/** A gateway that answered an mDNS browse. */
+export interface DiscoveredDevice {
+  /** Instance name, e.g. `Roaster 1._roaster._tcp.local.`. */
+  name: string;
+  /** Advertised hostname, e.g. `roaster-1.local.`. */
+  host: string;
+  /** Resolved IP addresses, IPv4 first. */
+  addresses: string[];
+  port: number;
+  /** TXT record key/value pairs. */
+  txt: Record<string, string>;
+}
+
+/**
+ * Browses mDNS for `serviceType` (e.g. `_roaster._tcp`) for `timeoutMs` (3000) and returns every instance that
+ * resolved in that time, sorted by name. Rejects an invalid service type.
+ */
+export async function discoverDevices(
+  serviceType: string,
+  timeoutMs?: number
+): Promise<DiscoveredDevice[]> {
+  return await loadNative().discoverDevices(serviceType, timeoutMs);
+}
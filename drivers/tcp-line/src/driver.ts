@@ -1,11 +1,36 @@
 import type { Driver, DriverConfig } from "@sim-corp/driver-core";
 import type { TelemetryPoint } from "@sim-corp/schemas";
 import { TcpLineDriverConfigSchema, type TcpLineDriverConfig } from "./config";
-import type { DriverStatus } from "./metrics";
-import { convertExtras, loadNative } from "./native";
+import type {
+  AlarmEvent,
+  ConnectionEvent,
+  DetailedMetrics,
+  DriverError,
+  DriverStatus,
+  RawLineEvent,
+  RecentParseError,
+  RoastEvent,
+  SequenceGapEvent,
+  SessionBundleSummary,
+  SessionInfo,
+  SessionLogFormat,
+  SessionLogSummary,
+  SessionSummary,
+  StateChangeEvent,
+  TelemetryAggregate
+} from "./metrics";
+import { type CallOptions, withSignal } from "./cancel";
+import {
+  convertAggregate,
+  convertExtras,
+  loadNative,
+  type TelemetrySubscription,
+  wrapTelemetryStream,
+  wrapTelemetrySubscription
+} from "./native";
 
 export class TcpLineDriver implements Driver {
-  private readonly config: TcpLineDriverConfig;
+  private config: TcpLineDriverConfig;
   private readonly native: InstanceType<ReturnType<typeof loadNative>["TcpLineDriverNative"]>;
 
   constructor(private readonly cfg: DriverConfig) {
@@ -16,18 +41,48 @@ export class TcpLineDriver implements Driver {
     this.native = new TcpLineDriverNative(JSON.stringify(this.config), cfg.machineId);
   }
 
-  async connect(): Promise<void> {
-    await this.native.connect();
+  /** `timeoutMs` and `signal` only bound the wait: a driver that hasn't connected keeps trying until `disconnect()`. */
+  async connect(options: CallOptions = {}): Promise<void> {
+    await withSignal(options.signal, (cancel) => this.native.connect(options.timeoutMs, cancel));
   }
 
-  async readTelemetry(): Promise<TelemetryPoint> {
-    const point = await this.native.readTelemetry();
+  /** Port of the `grpc` server once `connect()` has started it, else `null`. */
+  grpcPort(): number | null {
+    return this.native.grpcPort();
+  }
+
+  /** Port bound in `listen` mode once `connect()` has started listening, else `null`. */
+  listenPort(): number | null {
+    return this.native.listenPort();
+  }
+
+  /**
+   * Points carry `sessionId` while a session is active. Waits for the first sample per `readWait`, or up to
+   * `timeoutMs` when given.
+   */
+  async readTelemetry(options: CallOptions = {}): Promise<TelemetryPoint & { sessionId?: string }> {
+    const point = await withSignal(options.signal, (cancel) => this.native.readTelemetry(options.timeoutMs, cancel));
     return {
       ...point,
       extras: convertExtras(point.extras)
     };
   }
 
+  /** The latest sample, or `null` if none arrives within `timeoutMs`, whatever `readWait` says. */
+  async readTelemetryWithTimeout(
+    timeoutMs: number,
+    options: Pick<CallOptions, "signal"> = {}
+  ): Promise<(TelemetryPoint & { sessionId?: string }) | null> {
+    const point = await withSignal(options.signal, (cancel) => this.native.readTelemetryWithTimeout(timeoutMs, cancel));
+    return point && { ...point, extras: convertExtras(point.extras) };
+  }
+
+  /** The latest sample, without taking it under `readMode: "consume"` or counting it in `telemetryEmitted`. */
+  async peekTelemetry(): Promise<TelemetryPoint & { sessionId?: string }> {
+    const point = await this.native.peekTelemetry();
+    return { ...point, extras: convertExtras(point.extras) };
+  }
+
   async disconnect(): Promise<void> {
     await this.native.disconnect();
   }
@@ -35,4 +90,164 @@ export class TcpLineDriver implements Driver {
   getStatus(): DriverStatus {
     return this.native.getStatus();
   }
+
+  getMetricsDetailed(): DetailedMetrics {
+    return this.native.getMetricsDetailed();
+  }
+
+  /** Stops emitting samples (e.g. during a cleaning cycle) while the connection stays up; `readTelemetry` waits. */
+  pause(): void {
+    this.native.pause();
+  }
+
+  resume(): void {
+    this.native.resume();
+  }
+
+  /** Clears metrics, parser, dedupe, backoff and elapsed state and reconnects at once, without a new driver. */
+  async reset(): Promise<void> {
+    await this.native.reset();
+  }
+
+  /** The last 100 connection attempts, connects, failures, drops and stops, oldest first. */
+  getConnectionHistory(): ConnectionEvent[] {
+    return this.native.getConnectionHistory();
+  }
+
+  /** The last 50 parse failures, oldest first, for a diagnostics panel. */
+  getRecentErrors(): RecentParseError[] {
+    return this.native.getRecentErrors();
+  }
+
+  /** `for await (const point of driver.telemetryStream())`: yields each new sample, paced by `emitIntervalMs`. */
+  telemetryStream(): AsyncIterableIterator<TelemetryPoint> {
+    return wrapTelemetryStream(this.native.telemetryStream());
+  }
+
+  /** An independent cursor onto every point from now on, e.g. one per UI panel; see `TelemetrySubscription`. */
+  subscribe(): TelemetrySubscription {
+    return wrapTelemetrySubscription(this.native.subscribe());
+  }
+
+  /** Takes up to `maxSamples` (default all) queued samples, oldest first; empty unless `queue` is configured. */
+  drainSamples(maxSamples?: number): TelemetryPoint[] {
+    return this.native.drainSamples(maxSamples).map((point) => ({ ...point, extras: convertExtras(point.extras) }));
+  }
+
+  /** Per-channel min/max/mean/count over the last `windowSeconds` of samples, by sample timestamp. */
+  readAggregate(windowSeconds: number): TelemetryAggregate {
+    return convertAggregate(this.native.readAggregate(windowSeconds));
+  }
+
+  /**
+   * The current roast's phases from CHARGE, with their durations, end temperatures and mean RoR; `null` before
+   * CHARGE. Needs `roastEvents`.
+   */
+  getSessionSummary(): SessionSummary | null {
+    return this.native.getSessionSummary();
+  }
+
+  /**
+   * Collects every emitted point until `stopLogging()`, which writes them to `path` as CSV or Parquet (default: from
+   * the extension) with one column per extra.
+   */
+  startLogging(path: string, format?: SessionLogFormat): void {
+    this.native.startLogging(path, format);
+  }
+
+  stopLogging(): SessionLogSummary | null {
+    return this.native.stopLogging();
+  }
+
+  /**
+   * Writes what `capture` kept since the driver started or the latest `startSession()` to the tar archive `path`, for
+   * reproducing a field issue with a `ReplayDriver`. Needs `capture`.
+   */
+  exportSession(path: string): SessionBundleSummary {
+    return this.native.exportSession(path);
+  }
+
+  /**
+   * Takes up to `maxSamples` (default all) points from the on-disk `spool`, oldest first, and acknowledges them.
+   * Delivery is at least once: a crash before the acknowledgement reaches disk returns the same points again after the
+   * restart, so tolerate duplicates (same `ts`). The points are the caller's once this returns.
+   */
+  drainPersisted(maxSamples?: number): TelemetryPoint[] {
+    return this.native.drainPersisted(maxSamples).map((point) => ({ ...point, extras: convertExtras(point.extras) }));
+  }
+
+  /** Writes a raw command line to the device; resolves with the ack line when `commands.ackPrefix` is set. */
+  async sendCommand(line: string): Promise<string | null> {
+    return await this.native.sendCommand(line);
+  }
+
+  async setPowerPct(value: number): Promise<string | null> {
+    return await this.native.setPowerPct(value);
+  }
+
+  async setFanPct(value: number): Promise<string | null> {
+    return await this.native.setFanPct(value);
+  }
+
+  async setDrumRpm(value: number): Promise<string | null> {
+    return await this.native.setDrumRpm(value);
+  }
+
+  /** Starts a roast session: `elapsedSeconds` restarts at 0 and points carry `sessionId` until `endSession()`. */
+  startSession(sessionId?: string): SessionInfo {
+    return this.native.startSession(sessionId);
+  }
+
+  endSession(): SessionInfo | null {
+    return this.native.endSession();
+  }
+
+  /**
+   * Applies new connection settings in place. Offsets, parser, dedupe and pacing changes keep the socket open;
+   * host, port, keepalive, connect timeout or heartbeat changes restart it. Resolves with whether it reconnected.
+   */
+  async updateConfig(connection: Record<string, unknown>): Promise<boolean> {
+    const config = TcpLineDriverConfigSchema.parse({ ...connection });
+    const reconnected = await this.native.updateConfig(JSON.stringify(config));
+    this.config = config;
+    return reconnected;
+  }
+
+  startRecording(path?: string): void {
+    this.native.startRecording(path);
+  }
+
+  stopRecording(): void {
+    this.native.stopRecording();
+  }
+
+  /** Subscribes to state transitions (e.g. `CONNECTED` → `DISCONNECTED` on a dropped socket) instead of polling `getStatus()`. */
+  onStateChange(callback: (event: StateChangeEvent) => void): void {
+    this.native.onStateChange(callback);
+  }
+
+  /** Subscribes to categorized errors; unlike `metrics.lastError`, none are lost to overwrites. */
+  onError(callback: (error: DriverError) => void): void {
+    this.native.onError(callback);
+  }
+
+  /** Detected roast events (CHARGE, TURNING_POINT, DRY_END, FC); needs `roastEvents` in the config. */
+  onEvent(callback: (event: RoastEvent) => void): void {
+    this.native.onEvent(callback);
+  }
+
+  /** Jumps of the frame counter named by `sequence` in the config, with how many frames were skipped. */
+  onGap(callback: (event: SequenceGapEvent) => void): void {
+    this.native.onGap(callback);
+  }
+
+  /** `staleData` and `alarms` rules being raised and clearing. */
+  onAlarm(callback: (event: AlarmEvent) => void): void {
+    this.native.onAlarm(callback);
+  }
+
+  /** Every frame as received with its parse outcome, at most `maxPerSecond` (20) a second, for live debugging. */
+  onRawLine(callback: (event: RawLineEvent) => void, maxPerSecond?: number): void {
+    this.native.onRawLine(callback, maxPerSecond);
+  }
 }
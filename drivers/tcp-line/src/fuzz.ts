@@ -0,0 +1,19 @@
+import { loadNative } from "./native";
+
+/** What the lines of one `parseLineForFuzzing` input parsed into. */
+export interface FuzzOutcome {
+  samples: number;
+  /** Lines that completed no sample, e.g. a CSV header or part of a record. */
+  skipped: number;
+  errors: number;
+}
+
+/**
+ * Runs `bytes` as device output (split at `\n`, decoded lossily) through a fresh native parser built from `config`,
+ * a parser or driver config. For fuzzing from JS: malformed output only ever counts as errors, so a rejection with
+ * `parser panicked: ...` is a parser bug. An invalid config rejects with its first issue.
+ */
+export function parseLineForFuzzing(config: unknown, bytes: Uint8Array | string): FuzzOutcome {
+  const buffer = typeof bytes === "string" ? Buffer.from(bytes) : Buffer.from(bytes);
+  return loadNative().parseLineForFuzzing(JSON.stringify(config), buffer);
+}
@@ -0,0 +1,27 @@
+import type { DriverConfig } from "@sim-corp/driver-core";
+import { HttpPollDriverConfigSchema } from "./config";
+import type { DriverError, StateChangeEvent } from "./metrics";
+import { loadNative } from "./native";
+import { NativeBackedDriver } from "./native-driver";
+
+type HttpPollNative = InstanceType<ReturnType<typeof loadNative>["HttpPollDriverNative"]>;
+
+export class HttpPollDriver extends NativeBackedDriver<HttpPollNative> {
+  constructor(cfg: DriverConfig) {
+    const config = HttpPollDriverConfigSchema.parse({
+      ...(cfg.connection ?? {})
+    });
+    const { HttpPollDriverNative } = loadNative();
+    super(new HttpPollDriverNative(JSON.stringify(config), cfg.machineId));
+  }
+
+  /** Subscribes to state transitions, e.g. `CONNECTED` → `DISCONNECTED` when a poll fails. */
+  onStateChange(callback: (event: StateChangeEvent) => void): void {
+    this.native.onStateChange(callback);
+  }
+
+  /** Subscribes to failed polls. */
+  onError(callback: (error: DriverError) => void): void {
+    this.native.onError(callback);
+  }
+}
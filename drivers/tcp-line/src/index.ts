@@ -1,6 +1,76 @@
 import type { DriverConfig, DriverFactory } from "@sim-corp/driver-core";
+import { BleDriver } from "./ble-driver";
+import { CanDriver } from "./can-driver";
+import { CompositeDriver } from "./composite-driver";
 import { TcpLineDriver } from "./driver";
+import { HttpPollDriver } from "./http-poll-driver";
+import { OpcUaDriver } from "./opcua-driver";
+import { ReplayDriver } from "./replay-driver";
+import { SimulatedDriver } from "./simulated-driver";
+import { SnmpDriver } from "./snmp-driver";
 
 export const createTcpLineDriver: DriverFactory = (cfg: DriverConfig) => new TcpLineDriver(cfg);
+export const createReplayDriver: DriverFactory = (cfg: DriverConfig) => new ReplayDriver(cfg);
+export const createSimulatedDriver: DriverFactory = (cfg: DriverConfig) => new SimulatedDriver(cfg);
+export const createOpcUaDriver: DriverFactory = (cfg: DriverConfig) => new OpcUaDriver(cfg);
+export const createHttpPollDriver: DriverFactory = (cfg: DriverConfig) => new HttpPollDriver(cfg);
+export const createBleDriver: DriverFactory = (cfg: DriverConfig) => new BleDriver(cfg);
+export const createCanDriver: DriverFactory = (cfg: DriverConfig) => new CanDriver(cfg);
+export const createSnmpDriver: DriverFactory = (cfg: DriverConfig) => new SnmpDriver(cfg);
+export const createCompositeDriver: DriverFactory = (cfg: DriverConfig) => new CompositeDriver(cfg);
+export type {
+  AlarmEvent,
+  ChannelAggregate,
+  ConnectionEvent,
+  ConnectionEventKind,
+  DetailedMetrics,
+  DriverError,
+  DriverErrorCode,
+  DriverState,
+  DriverStatus,
+  FormatDetection,
+  PeerStatus,
+  PhaseSummary,
+  RawLineEvent,
+  RecentParseError,
+  RoastEvent,
+  RoastEventType,
+  RoastPhase,
+  SequenceGapEvent,
+  SessionBundleSummary,
+  SessionInfo,
+  SessionLogFormat,
+  SessionLogSummary,
+  SessionSummary,
+  StateChangeEvent,
+  TelemetryAggregate
+} from "./metrics";
+export { benchmarkParser, type ParserBenchmark } from "./benchmark";
+export type { CallOptions } from "./cancel";
+export type { CompositeSourceStatus } from "./composite-driver";
+export { discoverDevices, type DiscoveredDevice } from "./discovery";
+export { parseLineForFuzzing, type FuzzOutcome } from "./fuzz";
+export { setLogCallback, type LogLevel, type LogRecord } from "./logging";
+export { probeEndpoint, type ProbeResult, type ProbeSample } from "./probe";
+export { listActiveDrivers, type ActiveDriver } from "./registry";
+export {
+  importRoastLog,
+  type ImportedEvent,
+  type RoastLogFormat,
+  type RoastLogImport,
+  type RoastLogImportOptions
+} from "./roast-import";
+export { initRuntime, type RuntimeOptions } from "./runtime";
+export { selfTest, type SelfTestCheck, type SelfTestReport } from "./self-test";
+export {
+  TcpLineDriverManager,
+  type ManagedMachineConfig,
+  type MachineStatus,
+  type TcpLineDriverManagerOptions
+} from "./manager";
+export type { TelemetrySubscription } from "./native";
+export { MetricsServer, type MetricsServerOptions } from "./metrics-server";
+export { TcpLineTestServer, type TcpLineTestServerOptions } from "./test-server";
+export { configSchema, validateConfig, type ConfigIssue } from "./validation";
 
 export default createTcpLineDriver;
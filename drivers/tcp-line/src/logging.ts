@@ -0,0 +1,20 @@
+import { loadNative } from "./native";
+
+export type LogLevel = "error" | "warn" | "info" | "debug" | "trace" | "off";
+
+export interface LogRecord {
+  level: Exclude<LogLevel, "off">;
+  target: string;
+  message: string;
+  ts: string;
+  /** Structured fields, e.g. `machine_id`, `attempt`, `delay_ms`, `line`. */
+  fields: Record<string, string>;
+}
+
+/**
+ * Forwards native driver logs at `level` and above to `callback` (process-wide, replacing any previous callback).
+ * Call with `"off"` or without a callback to stop.
+ */
+export function setLogCallback(level: LogLevel, callback?: (record: LogRecord) => void): void {
+  loadNative().setLogCallback(level, callback);
+}
@@ -0,0 +1,88 @@
+import type { TelemetryPoint } from "@sim-corp/schemas";
+import { GrpcConfigSchema, TcpLineDriverConfigSchema } from "./config";
+import type { DriverMetrics, DriverState, DriverStatus, SessionInfo } from "./metrics";
+import { convertExtras, loadNative } from "./native";
+
+export interface ManagedMachineConfig {
+  machineId: string;
+  /** Same shape as a single `TcpLineDriver` connection config. */
+  connection: unknown;
+}
+
+export interface TcpLineDriverManagerOptions {
+  /** One gRPC server for every machine, started with the first `connect` or `connectAll`. */
+  grpc?: { host?: string; port?: number };
+}
+
+export interface MachineStatus {
+  machineId: string;
+  state: DriverState;
+  metrics: DriverMetrics;
+}
+
+/** Runs many TCP line drivers behind one native object; see docs/drivers/tcp-line.md. */
+export class TcpLineDriverManager {
+  private readonly native: InstanceType<ReturnType<typeof loadNative>["DriverManagerNative"]>;
+
+  constructor(machines: ManagedMachineConfig[], options: TcpLineDriverManagerOptions = {}) {
+    const configs = machines.map((machine) => ({
+      machineId: machine.machineId,
+      connection: TcpLineDriverConfigSchema.parse(machine.connection ?? {})
+    }));
+    const grpc = options.grpc ? GrpcConfigSchema.parse(options.grpc) : undefined;
+    const { DriverManagerNative } = loadNative();
+    this.native = new DriverManagerNative(JSON.stringify({ machines: configs, grpc }));
+  }
+
+  machineIds(): string[] {
+    return this.native.machineIds();
+  }
+
+  /** Port of the manager-wide gRPC server once a connect has started it, else `null`. */
+  grpcPort(): number | null {
+    return this.native.grpcPort();
+  }
+
+  async connect(machineId: string): Promise<void> {
+    await this.native.connect(machineId);
+  }
+
+  /** Resolves once every machine connected; rejects listing the ones that didn't (they keep retrying). */
+  async connectAll(): Promise<void> {
+    await this.native.connectAll();
+  }
+
+  async disconnect(machineId: string): Promise<void> {
+    await this.native.disconnect(machineId);
+  }
+
+  async disconnectAll(): Promise<void> {
+    await this.native.disconnectAll();
+  }
+
+  async readTelemetry(machineId: string): Promise<TelemetryPoint> {
+    const point = await this.native.readTelemetry(machineId);
+    return { ...point, extras: convertExtras(point.extras) };
+  }
+
+  getStatus(machineId: string): DriverStatus {
+    return this.native.getStatus(machineId);
+  }
+
+  startSession(machineId: string, sessionId?: string): SessionInfo {
+    return this.native.startSession(machineId, sessionId);
+  }
+
+  endSession(machineId: string): SessionInfo | null {
+    return this.native.endSession(machineId);
+  }
+
+  getStatuses(): MachineStatus[] {
+    return this.native.getStatuses();
+  }
+
+  /** Every machine's telemetry through one callback, each point tagged with its `machineId`. */
+  onTelemetry(callback: (point: TelemetryPoint) => void): void {
+    this.native.onTelemetry((point) => callback({ ...point, extras: convertExtras(point.extras) }));
+  }
+}
@@ -0,0 +1,32 @@
+import { loadNative } from "./native";
+
+export interface MetricsServerOptions {
+  /** Bind address; defaults to `127.0.0.1`. */
+  host?: string;
+  /** Defaults to 9464; `0` binds an ephemeral port. */
+  port?: number;
+  path?: string;
+}
+
+/** Prometheus scrape endpoint covering every line driver in the process, labelled by `machine_id`. */
+export class MetricsServer {
+  private readonly native: InstanceType<ReturnType<typeof loadNative>["MetricsServer"]>;
+
+  constructor(options: MetricsServerOptions = {}) {
+    const { MetricsServer: NativeServer } = loadNative();
+    this.native = new NativeServer(JSON.stringify(options));
+  }
+
+  start(): Promise<number> {
+    return this.native.start();
+  }
+
+  stop(): Promise<void> {
+    return this.native.stop();
+  }
+
+  /** The exposition text a scrape would return right now. */
+  render(): string {
+    return this.native.render();
+  }
+}
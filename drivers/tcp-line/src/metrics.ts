@@ -2,13 +2,305 @@ export interface DriverMetrics {
   linesReceived: number;
   linesParsed: number;
   parseErrors: number;
+  /** Lines rejected for a missing or wrong checksum; also counted in `parseErrors`. */
+  checksumErrors: number;
+  /** Channel fields that weren't numbers, left out of their samples under `strictness: "warn"`. */
+  fieldErrors: number;
+  /** Lines holding a JSON array of readings. */
+  batchLines: number;
+  /** Samples those lines completed, one per object; `batchSamples / batchLines` is the mean batch size. */
+  batchSamples: number;
+  /** Jumps of the `sequence` counter past the next value. */
+  sequenceGaps: number;
+  /** Frames those jumps skipped. */
+  sequenceMissing: number;
+  /** Frames repeating the counter of the frame before. */
+  sequenceDuplicates: number;
+  /** Frames arriving after one with a higher counter. */
+  sequenceReordered: number;
+  /** Samples timestamped before the latest one, whatever `ordering` did with them. */
+  outOfOrderSamples: number;
   telemetryEmitted: number;
   reconnects: number;
+  commandsSent: number;
+  staleTimeouts: number;
+  /** Samples discarded because the `queue`, `spool`, `influx` or `kafka` buffer was full. */
+  droppedSamples: number;
+  /** Points acknowledged by InfluxDB. */
+  influxPointsWritten: number;
+  /** Influx writes rejected or out of retries; each drops its batch. */
+  influxWriteErrors: number;
+  /** Messages acknowledged by the Kafka brokers. */
+  kafkaMessagesDelivered: number;
+  /** Messages dropped after their batch was retried for `deliveryTimeoutMs` without being delivered. */
+  kafkaDeliveryErrors: number;
+  bytesReceived: number;
+  /** Bytes written to the device: the handshake, commands and heartbeats. */
+  bytesSent: number;
+  /** Lines received per second over the last minute. */
+  linesPerSecond: number;
   lastError?: string;
   lastLineAt?: string;
 }
 
+export interface ChannelAggregate {
+  min: number;
+  max: number;
+  mean: number;
+  /** Samples in the window that carried this channel. */
+  count: number;
+}
+
+/** Per-channel statistics over the last `windowSeconds` of samples; channels no sample carried are absent. */
+export interface TelemetryAggregate {
+  machineId: string;
+  windowSeconds: number;
+  sampleCount: number;
+  /** Timestamps of the oldest and newest sample in the window. */
+  from?: string;
+  to?: string;
+  btC?: ChannelAggregate;
+  etC?: ChannelAggregate;
+  gasPct?: ChannelAggregate;
+  fanPct?: ChannelAggregate;
+  drumRpm?: ChannelAggregate;
+  ambientC?: ChannelAggregate;
+  humidityPct?: ChannelAggregate;
+  pressureHpa?: ChannelAggregate;
+  exhaustC?: ChannelAggregate;
+  inletC?: ChannelAggregate;
+  drumPressurePa?: ChannelAggregate;
+  airflowPct?: ChannelAggregate;
+  /** Numeric extras only. */
+  extras: Record<string, ChannelAggregate>;
+}
+
+export interface SessionInfo {
+  sessionId: string;
+  startedAt: string;
+}
+
+export type SessionLogFormat = "csv" | "parquet";
+
+/** What `stopLogging()` wrote. */
+export interface SessionLogSummary {
+  path: string;
+  format: SessionLogFormat;
+  rows: number;
+  /** Header of the file: the point fields, then one column per extra. */
+  columns: string[];
+}
+
+/** What `exportSession()` wrote. */
+export interface SessionBundleSummary {
+  path: string;
+  sessionId?: string;
+  lines: number;
+  points: number;
+  metricsSnapshots: number;
+  /** Lines and points that fell off the front of the capture for `maxEntries`. */
+  droppedEntries: number;
+  bytes: number;
+}
+
+export type RoastEventType = "CHARGE" | "TURNING_POINT" | "DRY_END" | "FC";
+
+export interface RoastEvent {
+  type: RoastEventType;
+  /** When it happened: the BT peak before the drop for CHARGE, the BT minimum for TURNING_POINT. */
+  ts: string;
+  machineId: string;
+  btC: number;
+  rorCPerMin?: number;
+  sessionId?: string;
+}
+
+/** The roast phases, starting at CHARGE, DRY_END and FC respectively. */
+export type RoastPhase = "drying" | "maillard" | "development";
+
+export interface PhaseSummary {
+  phase: RoastPhase;
+  startedAt: string;
+  /** When the next phase started; absent for the running phase, which is counted to the latest sample. */
+  endedAt?: string;
+  durationSeconds: number;
+  /** Share of the time since CHARGE, in percent. */
+  pct: number;
+  startBtC: number;
+  endBtC: number;
+  /** BT's mean rate of rise over the phase (°C/min); absent for a phase that hasn't lasted yet. */
+  meanRorCPerMin?: number;
+}
+
+/** What `getSessionSummary()` reports of the current roast, from CHARGE to the latest sample. */
+export interface SessionSummary {
+  machineId: string;
+  sessionId?: string;
+  chargedAt: string;
+  totalSeconds: number;
+  /** The phases reached so far, in order. */
+  phases: PhaseSummary[];
+  /** With `energy`: the energy used so far in the session, in kWh. */
+  energyKwh?: number;
+}
+
+/** `PAUSED`: connected, but `pause()` holds back samples. */
+export type DriverState = "DISCONNECTED" | "CONNECTING" | "CONNECTED" | "PAUSED" | "STOPPED" | "FAILED";
+
 export interface DriverStatus {
-  state: "DISCONNECTED" | "CONNECTING" | "CONNECTED" | "STOPPED";
+  state: DriverState;
   metrics: DriverMetrics;
+  /** Device clock minus gateway clock (ms), from the first samples after connecting; absent until measured. */
+  clockSkewMs?: number | null;
+  /** Active session from `startSession()`, if any. */
+  session?: SessionInfo | null;
+  /** `host:port` connected to, or being tried while reconnecting. */
+  activeEndpoint?: string | null;
+  /** Devices connected in `listen` mode; empty in `connect` mode. */
+  peers?: PeerStatus[];
+  /** What `format: "autodetect"` settled on (for the first device in listen mode); absent until decided. */
+  detectedFormat?: FormatDetection | null;
+  /** Sample rate over the last 60 seconds; absent until two samples have arrived. */
+  sampleRate?: SampleRate | null;
+  /** Set while the `staleDataAlarmMs` alarm is raised. */
+  staleDataAlarm?: boolean;
+  /** The raising event of each alarm currently raised, `staleData` included, oldest first. */
+  activeAlarms?: AlarmEvent[];
+}
+
+export interface SampleRate {
+  /** Samples per second at the typical interval, from the fullest bin of an interval histogram. */
+  hz: number;
+  /** Standard deviation of the intervals within a factor of two of the typical one. */
+  jitterMs: number;
+  /** Longest wait between samples in the window, including the current one. */
+  longestGapMs: number;
+  intervals: number;
+}
+
+export interface FormatDetection {
+  format: "jsonl" | "csv" | "keyValue";
+  /** CSV delimiter. */
+  delimiter?: string;
+  /** Whether the first CSV line of a connection is a header. */
+  hasHeader?: boolean;
+}
+
+/** One device connected to a listen-mode driver. */
+export interface PeerStatus {
+  /** Remote `ip:port` of the device. */
+  address: string;
+  connectedAt: string;
+  linesReceived: number;
+  linesParsed: number;
+  parseErrors: number;
+  bytesReceived: number;
+  lastLineAt?: string | null;
+}
+
+export interface DetailedMetrics {
+  state: DriverState;
+  metrics: DriverMetrics;
+  /** `counts` has one more entry than `boundsUs`: the last bucket holds parses slower than the largest bound. */
+  parseLatency: { boundsUs: number[]; counts: number[]; count: number; sumUs: number };
+  /** Wall-clock gaps between accepted samples (most recent 512); absent until two samples arrived. */
+  sampleInterval?: { count: number; minMs: number; p50Ms: number; p90Ms: number; p99Ms: number; maxMs: number };
+  stateDurationsMs: {
+    disconnected: number;
+    connecting: number;
+    connected: number;
+    paused: number;
+    stopped: number;
+    failed: number;
+  };
+}
+
+export interface StateChangeEvent {
+  previous: DriverState;
+  state: DriverState;
+  ts: string;
+  lastError?: string;
+}
+
+export type DriverErrorCode = "ConnectFailed" | "SocketClosed" | "ParseError" | "Timeout";
+
+export interface DriverError {
+  code: DriverErrorCode;
+  message: string;
+  ts: string;
+  rawLine?: string;
+}
+
+export type ConnectionEventKind =
+  | "Connecting"
+  | "Connected"
+  | "ConnectFailed"
+  | "Disconnected"
+  | "Stopped"
+  | "Interlock"
+  | "InterlockFailed";
+
+/** An entry of `getConnectionHistory()`. */
+export interface ConnectionEvent {
+  ts: string;
+  kind: ConnectionEventKind;
+  /** `host:port` dialed, or the device's `ip:port` in listen mode. */
+  endpoint?: string;
+  /** Why the attempt failed or the connection ended; for interlocks, the rule, the line and the outcome. */
+  reason?: string;
+  /** For `Connecting`: tries since `connect()` or since the connection was last up, from 1. */
+  attempt?: number;
+}
+
+/** A parse failure, from `getRecentErrors()`. */
+export interface RecentParseError {
+  ts: string;
+  /** Category, e.g. `invalidJson`, `checksumMismatch` or `columnMismatch`. */
+  kind: string;
+  message: string;
+  /** The offending line, cut to 256 characters. */
+  line: string;
+  /** Whether `line` was cut. */
+  truncated: boolean;
+}
+
+/** A jump of the `sequence` counter, from `onGap`. */
+export interface SequenceGapEvent {
+  ts: string;
+  /** The counter value that was due. */
+  expected: number;
+  received: number;
+  /** Frames skipped, `received - expected`. */
+  missing: number;
+  /** Remote `ip:port` of the device in listen mode. */
+  peer?: string;
+}
+
+/** An alarm raised or cleared, from `onAlarm`. */
+export interface AlarmEvent {
+  ts: string;
+  /** `staleData`, or the `name` of an `alarms` rule. */
+  alarm: string;
+  /** `true` when the alarm is raised, `false` when it clears. */
+  active: boolean;
+  message: string;
+  /** The rule's channel and threshold, and the reading that raised or cleared it; absent for `staleData`. */
+  channel?: string | null;
+  value?: number | null;
+  threshold?: number | null;
+}
+
+/** A frame as received, from `onRawLine`. */
+export interface RawLineEvent {
+  ts: string;
+  /** The decoded frame without its delimiter. */
+  line: string;
+  /** `skipped`: parsed without completing a sample (e.g. a CSV header); `reply`: a command reply. */
+  outcome: "parsed" | "skipped" | "error" | "reply";
+  /** Why the line didn't parse, for `error`. */
+  error?: string;
+  /** Remote `ip:port` of the device in listen mode. */
+  peer?: string;
+  /** Lines left out by the rate limit since the previous event. */
+  dropped: number;
 }
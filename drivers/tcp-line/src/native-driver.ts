@@ -0,0 +1,117 @@
+import type { Driver } from "@sim-corp/driver-core";
+import type { TelemetryPoint } from "@sim-corp/schemas";
+import type {
+  DetailedMetrics,
+  DriverStatus,
+  RecentParseError,
+  RoastEvent,
+  SessionLogFormat,
+  SessionLogSummary,
+  SessionSummary,
+  TelemetryAggregate
+} from "./metrics";
+import { type CallOptions, withSignal } from "./cancel";
+import {
+  convertAggregate,
+  convertExtras,
+  type NativeLineDriver,
+  type TelemetrySubscription,
+  wrapTelemetryStream,
+  wrapTelemetrySubscription
+} from "./native";
+
+/** Adapts any native line-driver class (replay, simulator) to the driver-core `Driver` contract. */
+export abstract class NativeBackedDriver<T extends NativeLineDriver = NativeLineDriver> implements Driver {
+  protected constructor(protected readonly native: T) {}
+
+  /** `timeoutMs` and `signal` only bound the wait: a driver that hasn't connected keeps trying until `disconnect()`. */
+  async connect(options: CallOptions = {}): Promise<void> {
+    await withSignal(options.signal, (cancel) => this.native.connect(options.timeoutMs, cancel));
+  }
+
+  /** Waits up to `timeoutMs` for the first sample when given (default: twice the emit interval, at least 500 ms). */
+  async readTelemetry(options: CallOptions = {}): Promise<TelemetryPoint> {
+    const point = await withSignal(options.signal, (cancel) => this.native.readTelemetry(options.timeoutMs, cancel));
+    return {
+      ...point,
+      extras: convertExtras(point.extras)
+    };
+  }
+
+  /** The latest sample, or `null` if none arrives within `timeoutMs`. */
+  async readTelemetryWithTimeout(
+    timeoutMs: number,
+    options: Pick<CallOptions, "signal"> = {}
+  ): Promise<TelemetryPoint | null> {
+    const point = await withSignal(options.signal, (cancel) => this.native.readTelemetryWithTimeout(timeoutMs, cancel));
+    return point && { ...point, extras: convertExtras(point.extras) };
+  }
+
+  /** The latest sample, without counting it in `telemetryEmitted`. */
+  async peekTelemetry(): Promise<TelemetryPoint> {
+    const point = await this.native.peekTelemetry();
+    return { ...point, extras: convertExtras(point.extras) };
+  }
+
+  async disconnect(): Promise<void> {
+    await this.native.disconnect();
+  }
+
+  getStatus(): DriverStatus {
+    return this.native.getStatus();
+  }
+
+  getMetricsDetailed(): DetailedMetrics {
+    return this.native.getMetricsDetailed();
+  }
+
+  /** The last 50 parse failures, oldest first, for a diagnostics panel. */
+  getRecentErrors(): RecentParseError[] {
+    return this.native.getRecentErrors();
+  }
+
+  /** `for await (const point of driver.telemetryStream())`: yields each new sample, paced by `emitIntervalMs`. */
+  telemetryStream(): AsyncIterableIterator<TelemetryPoint> {
+    return wrapTelemetryStream(this.native.telemetryStream());
+  }
+
+  /** An independent cursor onto every point from now on, e.g. one per UI panel; see `TelemetrySubscription`. */
+  subscribe(): TelemetrySubscription {
+    return wrapTelemetrySubscription(this.native.subscribe());
+  }
+
+  /** Takes up to `maxSamples` (default all) queued samples, oldest first; empty unless `queue` is configured. */
+  drainSamples(maxSamples?: number): TelemetryPoint[] {
+    return this.native.drainSamples(maxSamples).map((point) => ({ ...point, extras: convertExtras(point.extras) }));
+  }
+
+  /** Per-channel min/max/mean/count over the last `windowSeconds` of samples, by sample timestamp. */
+  readAggregate(windowSeconds: number): TelemetryAggregate {
+    return convertAggregate(this.native.readAggregate(windowSeconds));
+  }
+
+  /**
+   * The current roast's phases from CHARGE, with their durations, end temperatures and mean RoR; `null` before
+   * CHARGE. Needs `roastEvents`.
+   */
+  getSessionSummary(): SessionSummary | null {
+    return this.native.getSessionSummary();
+  }
+
+  /**
+   * Collects every emitted point until `stopLogging()`, which writes them to `path` as CSV or Parquet (default: from
+   * the extension) with one column per extra.
+   */
+  startLogging(path: string, format?: SessionLogFormat): void {
+    this.native.startLogging(path, format);
+  }
+
+  stopLogging(): SessionLogSummary | null {
+    return this.native.stopLogging();
+  }
+
+  /** Detected roast events; needs `roastEvents` in the config. */
+  onEvent(callback: (event: RoastEvent) => void): void {
+    this.native.onEvent(callback);
+  }
+}
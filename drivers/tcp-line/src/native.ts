@@ -1,20 +1,179 @@
 import { createRequire } from "node:module";
 import type { TelemetryPoint } from "@sim-corp/schemas";
-import type { DriverStatus } from "./metrics";
+import type {
+  AlarmEvent,
+  ChannelAggregate,
+  ConnectionEvent,
+  DetailedMetrics,
+  DriverError,
+  DriverStatus,
+  RawLineEvent,
+  RecentParseError,
+  RoastEvent,
+  SequenceGapEvent,
+  SessionBundleSummary,
+  SessionInfo,
+  SessionLogFormat,
+  SessionLogSummary,
+  SessionSummary,
+  StateChangeEvent,
+  TelemetryAggregate
+} from "./metrics";
+import type { ParserBenchmark } from "./benchmark";
+import type { ActiveDriver } from "./registry";
+import type { RoastLogImport } from "./roast-import";
+import type { CompositeSourceStatus } from "./composite-driver";
+import type { DiscoveredDevice } from "./discovery";
+import type { FuzzOutcome } from "./fuzz";
+import type { LogLevel, LogRecord } from "./logging";
+import type { MachineStatus } from "./manager";
+import type { NativeProbeResult } from "./probe";
+import type { SelfTestReport } from "./self-test";
+import type { TcpLineTestServerStatus } from "./test-server";
+import type { ConfigIssue } from "./validation";
 
 const require = createRequire(import.meta.url);
 
 type NativeTelemetry = TelemetryPoint & {
-  extras?: Array<{ key: string; number_value?: number; text_value?: string }>;
+  extras?: Array<{ key: string; numberValue?: number; textValue?: string; boolValue?: boolean }>;
+  sessionId?: string;
+};
+
+type NativeTelemetryAggregate = Omit<TelemetryAggregate, "extras"> & {
+  extras: Array<ChannelAggregate & { key: string }>;
+};
+
+type NativeTelemetryStream = {
+  next(): Promise<{ done: boolean; value?: NativeTelemetry }>;
+  return(): { done: boolean };
+  throw(error?: unknown): never;
+};
+
+type NativeTelemetrySubscription = NativeTelemetryStream & {
+  drain(maxSamples?: number): NativeTelemetry[];
+  readonly missed: number;
+};
+
+export type NativeCancelToken = {
+  cancel(reason?: string): void;
+  readonly cancelled: boolean;
+};
+
+export type NativeLineDriver = {
+  connect(timeoutMs?: number, cancel?: NativeCancelToken): Promise<void>;
+  disconnect(): Promise<void>;
+  readTelemetry(timeoutMs?: number, cancel?: NativeCancelToken): Promise<NativeTelemetry>;
+  readTelemetryWithTimeout(timeoutMs: number, cancel?: NativeCancelToken): Promise<NativeTelemetry | null>;
+  peekTelemetry(): Promise<NativeTelemetry>;
+  getStatus(): DriverStatus;
+  getMetricsDetailed(): DetailedMetrics;
+  getRecentErrors(): RecentParseError[];
+  telemetryStream(): NativeTelemetryStream;
+  subscribe(): NativeTelemetrySubscription;
+  drainSamples(maxSamples?: number): NativeTelemetry[];
+  readAggregate(windowSeconds: number): NativeTelemetryAggregate;
+  getSessionSummary(): SessionSummary | null;
+  startLogging(path: string, format?: SessionLogFormat): void;
+  stopLogging(): SessionLogSummary | null;
+  onEvent(callback: (event: RoastEvent) => void): void;
 };
 
 type NativeModule = {
-  TcpLineDriverNative: new (configJson: string, machineId: string) => {
-    connect(): Promise<void>;
-    disconnect(): Promise<void>;
-    readTelemetry(): Promise<NativeTelemetry>;
-    getStatus(): DriverStatus;
+  TcpLineDriverNative: new (configJson: string, machineId: string) => NativeLineDriver & {
+    sendCommand(line: string): Promise<string | null>;
+    setPowerPct(value: number): Promise<string | null>;
+    setFanPct(value: number): Promise<string | null>;
+    setDrumRpm(value: number): Promise<string | null>;
+    startRecording(path?: string): void;
+    stopRecording(): void;
+    exportSession(path: string): SessionBundleSummary;
+    grpcPort(): number | null;
+    listenPort(): number | null;
+    getConnectionHistory(): ConnectionEvent[];
+    pause(): void;
+    resume(): void;
+    reset(): Promise<void>;
+    onRawLine(callback: (event: RawLineEvent) => void, maxPerSecond?: number): void;
+    /** At least once: points drained just before a crash, unacknowledged on disk, come back after the restart. */
+    drainPersisted(maxSamples?: number): NativeTelemetry[];
+    updateConfig(configJson: string): Promise<boolean>;
+    startSession(sessionId?: string): SessionInfo;
+    endSession(): SessionInfo | null;
+    onStateChange(callback: (event: StateChangeEvent) => void): void;
+    onError(callback: (error: DriverError) => void): void;
+    onGap(callback: (event: SequenceGapEvent) => void): void;
+    onAlarm(callback: (event: AlarmEvent) => void): void;
+  };
+  ReplayDriverNative: new (configJson: string, machineId: string) => NativeLineDriver;
+  SimulatedDriverNative: new (configJson: string, machineId: string) => NativeLineDriver;
+  OpcUaDriverNative: new (configJson: string, machineId: string) => NativeLineDriver & {
+    onStateChange(callback: (event: StateChangeEvent) => void): void;
+    onError(callback: (error: DriverError) => void): void;
+  };
+  HttpPollDriverNative: new (configJson: string, machineId: string) => NativeLineDriver & {
+    onStateChange(callback: (event: StateChangeEvent) => void): void;
+    onError(callback: (error: DriverError) => void): void;
+  };
+  BleDriverNative: new (configJson: string, machineId: string) => NativeLineDriver & {
+    onStateChange(callback: (event: StateChangeEvent) => void): void;
+    onError(callback: (error: DriverError) => void): void;
+  };
+  SnmpDriverNative: new (configJson: string, machineId: string) => NativeLineDriver & {
+    onStateChange(callback: (event: StateChangeEvent) => void): void;
+    onError(callback: (error: DriverError) => void): void;
+  };
+  CompositeDriverNative: new (configJson: string, machineId: string) => NativeLineDriver & {
+    getSourceStatuses(): CompositeSourceStatus[];
+    onStateChange(callback: (event: StateChangeEvent) => void): void;
+    onError(callback: (error: DriverError) => void): void;
+  };
+  /** Only in Linux builds. */
+  CanDriverNative?: new (configJson: string, machineId: string) => NativeLineDriver & {
+    onStateChange(callback: (event: StateChangeEvent) => void): void;
+    onError(callback: (error: DriverError) => void): void;
   };
+  CancelToken: new () => NativeCancelToken;
+  TcpLineTestServer: new (configJson: string) => {
+    start(): Promise<number>;
+    dropConnections(): void;
+    stop(): Promise<void>;
+    getStatus(): TcpLineTestServerStatus;
+  };
+  MetricsServer: new (configJson: string) => {
+    start(): Promise<number>;
+    stop(): Promise<void>;
+    render(): string;
+  };
+  DriverManagerNative: new (configsJson: string) => {
+    machineIds(): string[];
+    grpcPort(): number | null;
+    connect(machineId: string): Promise<void>;
+    connectAll(): Promise<void>;
+    disconnect(machineId: string): Promise<void>;
+    disconnectAll(): Promise<void>;
+    readTelemetry(machineId: string): Promise<NativeTelemetry>;
+    getStatus(machineId: string): DriverStatus;
+    startSession(machineId: string, sessionId?: string): SessionInfo;
+    endSession(machineId: string): SessionInfo | null;
+    getStatuses(): MachineStatus[];
+    onTelemetry(callback: (point: NativeTelemetry) => void): void;
+  };
+  setLogCallback(level: LogLevel, callback?: (record: LogRecord) => void): void;
+  validateConfig(configJson: string): ConfigIssue[];
+  configSchema(): string;
+  benchmarkParser(configJson: string, sampleLines: string[], iterations: number): Promise<ParserBenchmark>;
+  parseLineForFuzzing(configJson: string, bytes: Buffer): FuzzOutcome;
+  discoverDevices(serviceType: string, timeoutMs?: number): Promise<DiscoveredDevice[]>;
+  probeEndpoint(
+    host: string,
+    port: number,
+    format?: string,
+    timeoutMs?: number
+  ): Promise<NativeProbeResult>;
+  selfTest(timeoutMs?: number): Promise<SelfTestReport>;
+  importRoastLog(inputPath: string, outputPath: string, format?: string, startAt?: string): Promise<RoastLogImport>;
+  initRuntime(workerThreads: number, threadNamePrefix?: string, name?: string): void;
+  listActiveDrivers(): ActiveDriver[];
 };
 
 let cached: NativeModule | null = null;
@@ -29,12 +188,78 @@ export function loadNative(): NativeModule {
 
 export function convertExtras(extras?: NativeTelemetry["extras"]): TelemetryPoint["extras"] {
   if (!extras || extras.length === 0) return {};
-  return extras.reduce<Record<string, number | string>>((acc, entry) => {
-    if (entry.number_value !== undefined) {
-      acc[entry.key] = entry.number_value;
-    } else if (entry.text_value !== undefined) {
-      acc[entry.key] = entry.text_value;
+  return extras.reduce<Record<string, number | string | boolean>>((acc, entry) => {
+    if (entry.numberValue !== undefined) {
+      acc[entry.key] = entry.numberValue;
+    } else if (entry.textValue !== undefined) {
+      acc[entry.key] = entry.textValue;
+    } else if (entry.boolValue !== undefined) {
+      acc[entry.key] = entry.boolValue;
     }
     return acc;
   }, {});
 }
+
+export function convertAggregate(aggregate: NativeTelemetryAggregate): TelemetryAggregate {
+  const extras: Record<string, ChannelAggregate> = {};
+  for (const { key, ...channel } of aggregate.extras) {
+    extras[key] = channel;
+  }
+  return { ...aggregate, extras };
+}
+
+/**
+ * One consumer's own cursor into a driver's published points: `for await` it, or `drain()` it on a timer. Each
+ * handle sees every point from `subscribe()` on, independently of the others, until it falls more than 1024 behind.
+ */
+export interface TelemetrySubscription extends AsyncIterableIterator<TelemetryPoint> {
+  /** Takes up to `maxSamples` (default all) unread points, oldest first, without waiting. */
+  drain(maxSamples?: number): TelemetryPoint[];
+  /** Points skipped because this handle fell too far behind. */
+  readonly missed: number;
+  /** Ends the subscription; a pending `next()` resolves as done. */
+  close(): void;
+}
+
+/** Adapts the native pull-based stream to `for await`, converting extras on the way out. */
+export function wrapTelemetryStream(stream: NativeTelemetryStream): AsyncIterableIterator<TelemetryPoint> {
+  return {
+    async next(): Promise<IteratorResult<TelemetryPoint>> {
+      const result = await stream.next();
+      if (result.done || !result.value) {
+        return { done: true, value: undefined };
+      }
+      return { done: false, value: { ...result.value, extras: convertExtras(result.value.extras) } };
+    },
+    async return(): Promise<IteratorResult<TelemetryPoint>> {
+      stream.return();
+      return { done: true, value: undefined };
+    },
+    async throw(error?: unknown): Promise<IteratorResult<TelemetryPoint>> {
+      stream.return();
+      throw error;
+    },
+    [Symbol.asyncIterator]() {
+      return this;
+    }
+  };
+}
+
+export function wrapTelemetrySubscription(subscription: NativeTelemetrySubscription): TelemetrySubscription {
+  const stream = wrapTelemetryStream(subscription);
+  return {
+    ...stream,
+    drain(maxSamples?: number): TelemetryPoint[] {
+      return subscription.drain(maxSamples).map((point) => ({ ...point, extras: convertExtras(point.extras) }));
+    },
+    get missed(): number {
+      return subscription.missed;
+    },
+    close(): void {
+      subscription.return();
+    },
+    [Symbol.asyncIterator]() {
+      return this;
+    }
+  };
+}
@@ -0,0 +1,27 @@
+import type { DriverConfig } from "@sim-corp/driver-core";
+import { OpcUaDriverConfigSchema } from "./config";
+import type { DriverError, StateChangeEvent } from "./metrics";
+import { loadNative } from "./native";
+import { NativeBackedDriver } from "./native-driver";
+
+type OpcUaNative = InstanceType<ReturnType<typeof loadNative>["OpcUaDriverNative"]>;
+
+export class OpcUaDriver extends NativeBackedDriver<OpcUaNative> {
+  constructor(cfg: DriverConfig) {
+    const config = OpcUaDriverConfigSchema.parse({
+      ...(cfg.connection ?? {})
+    });
+    const { OpcUaDriverNative } = loadNative();
+    super(new OpcUaDriverNative(JSON.stringify(config), cfg.machineId));
+  }
+
+  /** Subscribes to state transitions, e.g. `CONNECTED` → `DISCONNECTED` when the session is lost. */
+  onStateChange(callback: (event: StateChangeEvent) => void): void {
+    this.native.onStateChange(callback);
+  }
+
+  /** Subscribes to connection failures and lost sessions. */
+  onError(callback: (error: DriverError) => void): void {
+    this.native.onError(callback);
+  }
+}
@@ -0,0 +1,55 @@
+import { convertExtras, loadNative } from "./native";
+
+/** The last line of a probe that parsed, with extras keyed by name. */
+export interface ProbeSample {
+  ts: string;
+  btC?: number;
+  etC?: number;
+  gasPct?: number;
+  fanPct?: number;
+  drumRpm?: number;
+  extras: Record<string, number | string | boolean>;
+}
+
+/** What `probeEndpoint` read from a device. */
+export interface ProbeResult {
+  /** Format the lines were parsed as; `null` when none was given and nothing was detected. */
+  format: "jsonl" | "csv" | "keyValue" | null;
+  /** CSV delimiter, detected from the lines. */
+  delimiter?: string;
+  /** Whether the first CSV line is a header. */
+  hasHeader?: boolean;
+  /** JSON keys seen, the CSV header, or the positional columns headerless CSV is read as. */
+  columns: string[];
+  /** Raw lines received, without line endings. */
+  lines: string[];
+  sample?: ProbeSample;
+  parseErrors: number;
+  /** Why the first failing line didn't parse. */
+  firstError?: string;
+}
+
+type NativeExtras = Array<{ key: string; numberValue?: number; textValue?: string; boolValue?: boolean }>;
+
+export type NativeProbeResult = Omit<ProbeResult, "format" | "sample"> & {
+  format?: string;
+  sample?: Omit<ProbeSample, "extras"> & { extras?: NativeExtras };
+};
+
+/**
+ * Connects to `host:port`, reads up to five lines within `timeoutMs` (5000) and parses them as `format`, or as the
+ * format detected from the lines. Rejects when the connection fails or no line arrives in time.
+ */
+export async function probeEndpoint(
+  host: string,
+  port: number,
+  options: { format?: "jsonl" | "csv" | "keyValue"; timeoutMs?: number } = {}
+): Promise<ProbeResult> {
+  const result = await loadNative().probeEndpoint(host, port, options.format, options.timeoutMs);
+  const { sample, ...rest } = result;
+  return {
+    ...rest,
+    format: (result.format as ProbeResult["format"]) ?? null,
+    sample: sample && { ...sample, extras: convertExtras(sample.extras) }
+  };
+}
@@ -0,0 +1,17 @@
+import type { DriverState } from "./metrics";
+import { loadNative } from "./native";
+
+export interface ActiveDriver {
+  machineId: string;
+  /** `host:port` of a connect-mode TCP driver; absent for listen mode and the other sources. */
+  endpoint?: string;
+  state: DriverState;
+}
+
+/**
+ * The drivers of this process whose connection loop or source is running, e.g. to spot two pointed at one device.
+ * Shared handles (`onDuplicate: "share"`) appear once.
+ */
+export function listActiveDrivers(): ActiveDriver[] {
+  return loadNative().listActiveDrivers();
+}
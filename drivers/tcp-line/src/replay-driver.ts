@@ -0,0 +1,14 @@
+import type { DriverConfig } from "@sim-corp/driver-core";
+import { ReplayDriverConfigSchema } from "./config";
+import { loadNative } from "./native";
+import { NativeBackedDriver } from "./native-driver";
+
+export class ReplayDriver extends NativeBackedDriver {
+  constructor(cfg: DriverConfig) {
+    const config = ReplayDriverConfigSchema.parse({
+      ...(cfg.connection ?? {})
+    });
+    const { ReplayDriverNative } = loadNative();
+    super(new ReplayDriverNative(JSON.stringify(config), cfg.machineId));
+  }
+}
@@ -0,0 +1,42 @@
+import { loadNative } from "./native";
+
+export type RoastLogFormat = "artisan" | "cropster";
+
+/** An event marked in the log, under the log's own name (e.g. Artisan's `Charge`, `Dry End`, `FCs`). */
+export interface ImportedEvent {
+  name: string;
+  elapsedSeconds: number;
+}
+
+/** What `importRoastLog()` wrote. */
+export interface RoastLogImport {
+  path: string;
+  format: RoastLogFormat;
+  samples: number;
+  /** The timestamp of the first sample. */
+  startedAt: string;
+  durationSeconds: number;
+  /** `F` when the log had temperatures in °F, `C` otherwise; the capture has them in °C. */
+  unit: "C" | "F";
+  events: ImportedEvent[];
+}
+
+export interface RoastLogImportOptions {
+  /** Guessed from the file when omitted: Artisan exports start with a `Date:` header. */
+  format?: RoastLogFormat;
+  /** When the first row was taken; by default the date in an Artisan header, else now. */
+  startAt?: string;
+}
+
+/**
+ * Converts an Artisan CSV export or a Cropster curve CSV at `inputPath` into a raw-line capture at `outputPath`, for
+ * a `ReplayDriver` with `format: "jsonl"`, so a historical roast streams through the usual driver API. Runs off the
+ * event loop.
+ */
+export function importRoastLog(
+  inputPath: string,
+  outputPath: string,
+  options: RoastLogImportOptions = {}
+): Promise<RoastLogImport> {
+  return loadNative().importRoastLog(inputPath, outputPath, options.format, options.startAt);
+}
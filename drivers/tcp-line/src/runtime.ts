@@ -0,0 +1,18 @@
+import { loadNative } from "./native";
+
+export interface RuntimeOptions {
+  workerThreads: number;
+  /** Worker threads are named `<threadNamePrefix>-<n>`; defaults to `tcp-line`. */
+  threadNamePrefix?: string;
+  /** Registers a runtime that only drivers with `connection.runtime` set to this name use. */
+  name?: string;
+}
+
+/**
+ * Starts a tokio runtime with `workerThreads` workers for the drivers' connection loops and background tasks.
+ * Without `name` every driver uses it instead of the addon's built-in runtime. Call it before constructing drivers;
+ * each name can be registered once per process.
+ */
+export function initRuntime(options: RuntimeOptions): void {
+  loadNative().initRuntime(options.workerThreads, options.threadNamePrefix, options.name);
+}
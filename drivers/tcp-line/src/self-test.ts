@@ -0,0 +1,26 @@
+import { loadNative } from "./native";
+
+/** One stage of `selfTest()`: `loopback`, `connect`, `telemetry`, `parseErrors` or `disconnect`. */
+export interface SelfTestCheck {
+  name: string;
+  passed: boolean;
+  /** What went wrong, for a failed check. */
+  detail?: string;
+  elapsedMs: number;
+}
+
+/** What `selfTest()` found; the checks stop at the first failure, but `disconnect` runs whenever a driver started. */
+export interface SelfTestReport {
+  passed: boolean;
+  checks: SelfTestCheck[];
+  elapsedMs: number;
+}
+
+/**
+ * Streams fixture lines from an in-process loopback server through a native TCP driver (socket, framing, parser and
+ * emit path) and checks every point comes out as sent, for validating an install on new hardware. Resolves with a
+ * report rather than rejecting; each stage gets `timeoutMs` (5000).
+ */
+export function selfTest(timeoutMs?: number): Promise<SelfTestReport> {
+  return loadNative().selfTest(timeoutMs);
+}
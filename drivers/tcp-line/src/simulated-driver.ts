@@ -0,0 +1,14 @@
+import type { DriverConfig } from "@sim-corp/driver-core";
+import { SimulatedDriverConfigSchema } from "./config";
+import { loadNative } from "./native";
+import { NativeBackedDriver } from "./native-driver";
+
+export class SimulatedDriver extends NativeBackedDriver {
+  constructor(cfg: DriverConfig) {
+    const config = SimulatedDriverConfigSchema.parse({
+      ...(cfg.connection ?? {})
+    });
+    const { SimulatedDriverNative } = loadNative();
+    super(new SimulatedDriverNative(JSON.stringify(config), cfg.machineId));
+  }
+}
@@ -0,0 +1,27 @@
+import type { DriverConfig } from "@sim-corp/driver-core";
+import { SnmpDriverConfigSchema } from "./config";
+import type { DriverError, StateChangeEvent } from "./metrics";
+import { loadNative } from "./native";
+import { NativeBackedDriver } from "./native-driver";
+
+type SnmpNative = InstanceType<ReturnType<typeof loadNative>["SnmpDriverNative"]>;
+
+export class SnmpDriver extends NativeBackedDriver<SnmpNative> {
+  constructor(cfg: DriverConfig) {
+    const config = SnmpDriverConfigSchema.parse({
+      ...(cfg.connection ?? {})
+    });
+    const { SnmpDriverNative } = loadNative();
+    super(new SnmpDriverNative(JSON.stringify(config), cfg.machineId));
+  }
+
+  /** Subscribes to state transitions, e.g. `CONNECTED` → `DISCONNECTED` when a GET fails. */
+  onStateChange(callback: (event: StateChangeEvent) => void): void {
+    this.native.onStateChange(callback);
+  }
+
+  /** Subscribes to failed polls. */
+  onError(callback: (error: DriverError) => void): void {
+    this.native.onError(callback);
+  }
+}
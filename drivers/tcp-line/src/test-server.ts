@@ -0,0 +1,46 @@
+import { loadNative } from "./native";
+
+export interface TcpLineTestServerOptions {
+  host?: string;
+  port?: number;
+  headerLines?: string[];
+  /** Fixture lines; `{ts}` is replaced with the send time. */
+  lines: string[];
+  intervalMs?: number;
+  loop?: boolean;
+  closeAfterLines?: number;
+}
+
+export interface TcpLineTestServerStatus {
+  listening: boolean;
+  port: number;
+  connections: number;
+  activeConnections: number;
+  linesSent: number;
+}
+
+/** Native fixture server that streams lines to every client; handy for e2e tests and demos without hardware. */
+export class TcpLineTestServer {
+  private readonly native: InstanceType<ReturnType<typeof loadNative>["TcpLineTestServer"]>;
+
+  constructor(options: TcpLineTestServerOptions) {
+    const { TcpLineTestServer: NativeServer } = loadNative();
+    this.native = new NativeServer(JSON.stringify(options));
+  }
+
+  start(): Promise<number> {
+    return this.native.start();
+  }
+
+  dropConnections(): void {
+    this.native.dropConnections();
+  }
+
+  stop(): Promise<void> {
+    return this.native.stop();
+  }
+
+  getStatus(): TcpLineTestServerStatus {
+    return this.native.getStatus();
+  }
+}
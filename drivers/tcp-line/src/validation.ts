@@ -0,0 +1,20 @@
+import { loadNative } from "./native";
+
+export interface ConfigIssue {
+  /** Dotted field path, e.g. `reconnect.minBackoffMs`; empty for the config as a whole. */
+  path: string;
+  message: string;
+}
+
+/**
+ * Checks a native TCP driver config (the shape `TcpLineDriverConfigSchema` produces, defaults filled in) and returns
+ * every problem found: unknown keys, wrong types and out-of-range values. An empty list means it is accepted.
+ */
+export function validateConfig(config: unknown): ConfigIssue[] {
+  return loadNative().validateConfig(JSON.stringify(config));
+}
+
+/** JSON Schema (draft 7) of the native TCP driver config, for generating config forms. */
+export function configSchema(): Record<string, unknown> {
+  return JSON.parse(loadNative().configSchema()) as Record<string, unknown>;
+}
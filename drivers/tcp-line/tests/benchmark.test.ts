@@ -0,0 +1,20 @@
+import { describe, expect, it } from "vitest";
+import { benchmarkParser } from "../src/benchmark";
+import { TcpLineDriverConfigSchema } from "../src/config";
+
+describe("benchmarkParser", () => {
+  it("counts every pass over the sample lines and reports the rate", async () => {
+    const config = TcpLineDriverConfigSchema.parse({ port: 5555, format: "csv", csv: { hasHeader: true } });
+    const result = await benchmarkParser(config, ["ts,btC,etC", "2024-05-01T10:00:00Z,180.5,200", "x,y,\"z"], 50);
+    expect(result).toMatchObject({ lines: 150, samples: 50, skipped: 50, errors: 50 });
+    expect(result.linesPerSecond).toBeGreaterThan(0);
+    expect(result.bytesPerSecond).toBeGreaterThan(result.linesPerSecond);
+  });
+
+  it("rejects an invalid config or an empty run", async () => {
+    const config = TcpLineDriverConfigSchema.parse({ port: 5555 });
+    await expect(benchmarkParser({ ...config, format: "yaml" }, ["{}"], 1)).rejects.toThrow(/invalid config/);
+    await expect(benchmarkParser(config, [], 1)).rejects.toThrow("sampleLines must not be empty");
+    await expect(benchmarkParser(config, ["{}"], 0)).rejects.toThrow("iterations must be positive");
+  });
+});
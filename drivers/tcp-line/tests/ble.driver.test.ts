@@ -0,0 +1,35 @@
+import { describe, expect, it } from "vitest";
+import type { DriverConfig } from "@sim-corp/driver-core";
+import { BleDriver } from "../src/ble-driver";
+
+function bleConfig(connection: Record<string, unknown>): DriverConfig {
+  return {
+    orgId: "o",
+    siteId: "s",
+    machineId: "m",
+    connection: {
+      namePrefix: "iGrill",
+      serviceUuid: "fff0",
+      characteristics: [{ uuid: "fff1", fields: [{ channel: "btC", type: "i16le", scale: 0.1 }] }],
+      ...connection
+    }
+  };
+}
+
+describe("BleDriver", () => {
+  it("needs an address or a name to look for", () => {
+    expect(() => new BleDriver(bleConfig({ namePrefix: undefined }))).toThrow("address or namePrefix is required");
+  });
+
+  it("rejects a malformed device address", () => {
+    expect(() => new BleDriver(bleConfig({ address: "AA:BB" }))).toThrow('address: invalid bluetooth address "AA:BB"');
+  });
+
+  it("rejects a channel decoded by two fields", () => {
+    const characteristics = [
+      { uuid: "fff1", fields: [{ channel: "btC", type: "i16le" }] },
+      { uuid: "fff2", fields: [{ channel: "btC", type: "u8" }] }
+    ];
+    expect(() => new BleDriver(bleConfig({ characteristics }))).toThrow("channel btC is decoded more than once");
+  });
+});
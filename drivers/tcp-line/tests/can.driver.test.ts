@@ -0,0 +1,35 @@
+import { describe, expect, it } from "vitest";
+import type { DriverConfig } from "@sim-corp/driver-core";
+import { CanDriver } from "../src/can-driver";
+
+function canConfig(connection: Record<string, unknown>): DriverConfig {
+  return {
+    orgId: "o",
+    siteId: "s",
+    machineId: "m",
+    connection: {
+      interface: "tlcan-missing",
+      signals: [{ channel: "drumRpm", frameId: 0x123, startBit: 0, length: 16, scale: 0.25 }],
+      ...connection
+    }
+  };
+}
+
+describe.skipIf(process.platform !== "linux")("CanDriver", () => {
+  it("rejects a signal that runs past the frame", () => {
+    const signals = [{ channel: "drumRpm", frameId: 0x123, startBit: 60, length: 8 }];
+    expect(() => new CanDriver(canConfig({ signals }))).toThrow("signals[0] does not fit in an 8-byte frame");
+  });
+
+  it("rejects a standard frame id above 11 bits", () => {
+    const signals = [{ channel: "drumRpm", frameId: 0x800, startBit: 0, length: 8 }];
+    expect(() => new CanDriver(canConfig({ signals }))).toThrow("is out of range for 11-bit standard ids");
+  });
+
+  it("fails to connect to a missing interface", async () => {
+    const driver = new CanDriver(canConfig({ reconnect: { enabled: false } }));
+    await expect(driver.connect()).rejects.toThrow("connection failure: tlcan-missing");
+    expect(driver.getStatus().state).toBe("DISCONNECTED");
+    await driver.disconnect();
+  });
+});
@@ -0,0 +1,134 @@
+import { createServer, type Server } from "node:http";
+import type { AddressInfo } from "node:net";
+import { afterEach, describe, expect, it } from "vitest";
+import type { DriverConfig } from "@sim-corp/driver-core";
+import { CompositeDriver } from "../src/composite-driver";
+
+async function waitFor(fn: () => boolean, timeoutMs = 5000, intervalMs = 20): Promise<void> {
+  const start = Date.now();
+  while (!fn()) {
+    if (Date.now() - start > timeoutMs) {
+      throw new Error("waitFor timed out");
+    }
+    await new Promise((res) => setTimeout(res, intervalMs));
+  }
+}
+
+async function listen(server: Server): Promise<number> {
+  await new Promise<void>((res) => server.listen(0, "127.0.0.1", res));
+  return (server.address() as AddressInfo).port;
+}
+
+describe.sequential("CompositeDriver", () => {
+  let driver: CompositeDriver | undefined;
+  let server: Server | undefined;
+
+  afterEach(async () => {
+    await driver?.disconnect?.();
+    driver = undefined;
+    server?.close();
+    server = undefined;
+  });
+
+  it("merges the latest points of its sources", async () => {
+    let failing = false;
+    server = createServer((_req, res) => {
+      if (failing) {
+        res.writeHead(503);
+        res.end("busy");
+        return;
+      }
+      res.writeHead(200, { "Content-Type": "application/json" });
+      res.end(JSON.stringify({ p: 3.2, bt: 999 }));
+    });
+    const port = await listen(server);
+
+    const cfg: DriverConfig = {
+      orgId: "o",
+      siteId: "s",
+      machineId: "m",
+      connection: {
+        sources: [
+          {
+            name: "sim",
+            kind: "simulated",
+            connection: { sampleIntervalMs: 100, emitIntervalMs: 100, seed: 1 },
+            channels: ["btC", "etC"]
+          },
+          {
+            name: "gas",
+            kind: "httpPoll",
+            connection: {
+              url: `http://127.0.0.1:${port}/`,
+              pollIntervalMs: 100,
+              mapping: { gasPressure: "$.p", btC: "$.bt" },
+              reconnect: { minBackoffMs: 20, maxBackoffMs: 50 }
+            }
+          }
+        ],
+        emitOn: "first",
+        dedupeWithinMs: 0
+      }
+    };
+    driver = new CompositeDriver(cfg);
+    await driver.connect();
+    await waitFor(() => driver!.getStatus().metrics.linesParsed >= 3);
+
+    const point = await driver.readTelemetry();
+    expect(point.btC).toBeGreaterThan(150);
+    expect(point.btC).not.toBe(999);
+    expect(point.extras?.gasPressure).toBe(3.2);
+    expect(driver.getSourceStatuses().map((source) => [source.name, source.state])).toEqual([
+      ["sim", "CONNECTED"],
+      ["gas", "CONNECTED"]
+    ]);
+
+    failing = true;
+    await waitFor(() => driver!.getStatus().state === "DISCONNECTED");
+    failing = false;
+    await waitFor(() => driver!.getStatus().state === "CONNECTED");
+  }, 20000);
+
+  it("fails once a required source is down for good", async () => {
+    const cfg: DriverConfig = {
+      orgId: "o",
+      siteId: "s",
+      machineId: "m",
+      connection: {
+        sources: [
+          { name: "sim", kind: "simulated", connection: { sampleIntervalMs: 100 } },
+          {
+            name: "gas",
+            kind: "httpPoll",
+            connection: {
+              url: "http://127.0.0.1:1/",
+              mapping: { gasPressure: "$.p" },
+              reconnect: { enabled: false }
+            }
+          }
+        ]
+      }
+    };
+    driver = new CompositeDriver(cfg);
+    await expect(driver.connect()).rejects.toThrow("source gas: connection failure");
+    expect(driver.getStatus().state).toBe("FAILED");
+  }, 20000);
+
+  it("names the source whose config is invalid", () => {
+    const cfg: DriverConfig = {
+      orgId: "o",
+      siteId: "s",
+      machineId: "m",
+      connection: {
+        sources: [
+          {
+            name: "gas",
+            kind: "httpPoll",
+            connection: { url: "http://127.0.0.1:1/", mapping: { btC: "$..p" } }
+          }
+        ]
+      }
+    };
+    expect(() => new CompositeDriver(cfg)).toThrow('sources.gas: mapping.btC: invalid path "$..p"');
+  });
+});
@@ -0,0 +1,14 @@
+import { describe, expect, it } from "vitest";
+import { discoverDevices } from "../src/discovery";
+
+describe("device discovery", () => {
+  it("returns no devices when nothing answers within the timeout", async () => {
+    const started = Date.now();
+    expect(await discoverDevices("_simcorp-none._tcp", 300)).toEqual([]);
+    expect(Date.now() - started).toBeGreaterThanOrEqual(250);
+  });
+
+  it("rejects malformed service types", async () => {
+    await expect(discoverDevices("roaster", 100)).rejects.toThrow('invalid service type "roaster"');
+  });
+});
@@ -0,0 +1,53 @@
+import { describe, expect, it } from "vitest";
+import { TcpLineDriverConfigSchema } from "../src/config";
+import { parseLineForFuzzing } from "../src/fuzz";
+
+const SEEDS = [
+  '{"ts":"2024-05-01T10:00:00Z","btC":180.5,"etC":"200","x":[1,{"a":2}],"y":true}',
+  'ts,btC,etC\n2024-05-01T10:00:00Z,180.5,200\n"a""b,1',
+  "btC=1 etC=2;x=3,ts=1714557600",
+  "<r bt=\"1.5\"><ts>2024-05-01 10:00:00</ts></r>"
+];
+const SPECIALS = ['"', ",", "\t", "*", "$", "\n", "=", "{", "}", "\\", "ÿ", "9999999999", "-"];
+
+/** Deterministic xorshift, so a failure reproduces. */
+function random(seed: number): () => number {
+  let state = seed;
+  return () => {
+    state ^= state << 13;
+    state ^= state >>> 17;
+    state ^= state << 5;
+    return (state >>> 0) / 2 ** 32;
+  };
+}
+
+describe("parseLineForFuzzing", () => {
+  it("counts the lines of device output by outcome", () => {
+    const config = TcpLineDriverConfigSchema.parse({ port: 5555, format: "csv", csv: { hasHeader: true } });
+    expect(parseLineForFuzzing(config, "ts,btC\n2024-05-01T10:00:00Z,180.5\nx,\"y")).toEqual({
+      samples: 1,
+      skipped: 1,
+      errors: 1
+    });
+    expect(() => parseLineForFuzzing({ ...config, format: "yaml" }, "")).toThrow(/invalid config: format/);
+  });
+
+  it("never panics on mutated device output", () => {
+    const next = random(0x2545f491);
+    for (const format of ["jsonl", "csv", "keyValue", "autodetect"]) {
+      for (const checksum of ["none", "nmea", "crc16"]) {
+        const config = TcpLineDriverConfigSchema.parse({ port: 5555, format, checksum });
+        for (let i = 0; i < 200; i++) {
+          const bytes = Buffer.from(SEEDS[Math.floor(next() * SEEDS.length)]);
+          const input = [...bytes];
+          for (let edits = Math.floor(next() * 6); edits > 0; edits--) {
+            const at = Math.floor(next() * (input.length + 1));
+            if (next() < 0.5) input.splice(at, 0, ...Buffer.from(SPECIALS[Math.floor(next() * SPECIALS.length)]));
+            else input[at] = Math.floor(next() * 256);
+          }
+          expect(() => parseLineForFuzzing(config, Uint8Array.from(input))).not.toThrow();
+        }
+      }
+    }
+  });
+});
@@ -0,0 +1,114 @@
+import { createServer, type Server } from "node:http";
+import type { AddressInfo } from "node:net";
+import { afterEach, describe, expect, it } from "vitest";
+import type { DriverConfig } from "@sim-corp/driver-core";
+import { HttpPollDriver } from "../src/http-poll-driver";
+
+async function waitFor(fn: () => boolean, timeoutMs = 5000, intervalMs = 20): Promise<void> {
+  const start = Date.now();
+  while (!fn()) {
+    if (Date.now() - start > timeoutMs) {
+      throw new Error("waitFor timed out");
+    }
+    await new Promise((res) => setTimeout(res, intervalMs));
+  }
+}
+
+async function listen(server: Server): Promise<number> {
+  await new Promise<void>((res) => server.listen(0, "127.0.0.1", res));
+  return (server.address() as AddressInfo).port;
+}
+
+describe.sequential("HttpPollDriver", () => {
+  let driver: HttpPollDriver | undefined;
+  let server: Server | undefined;
+
+  afterEach(async () => {
+    await driver?.disconnect?.();
+    driver = undefined;
+    server?.close();
+    server = undefined;
+  });
+
+  it("maps fields of the polled document onto channels", async () => {
+    const seenKeys: Array<string | undefined> = [];
+    server = createServer((req, res) => {
+      seenKeys.push(req.headers["x-key"] as string | undefined);
+      res.writeHead(200, { "Content-Type": "application/json" });
+      res.end(JSON.stringify({ temps: { bean: 180, env: 230 }, "fan %": 40, outputs: [{ level: 55 }] }));
+    });
+    const port = await listen(server);
+
+    const cfg: DriverConfig = {
+      orgId: "o",
+      siteId: "s",
+      machineId: "m",
+      connection: {
+        url: `http://127.0.0.1:${port}/status`,
+        headers: { "X-Key": "abc" },
+        pollIntervalMs: 50,
+        mapping: { btC: "$.temps.bean", etC: "temps.env", fanPct: "$['fan %']", burner: "$.outputs[0].level" },
+        offsets: { btC: 1.5, etC: 0 },
+        dedupeWithinMs: 0
+      }
+    };
+    driver = new HttpPollDriver(cfg);
+    await driver.connect();
+    await waitFor(() => driver!.getStatus().metrics.linesParsed >= 2);
+
+    const point = await driver.readTelemetry();
+    expect(point.btC).toBe(181.5);
+    expect(point.etC).toBe(230);
+    expect(point.fanPct).toBe(40);
+    expect(point.extras?.burner).toBe(55);
+    expect(seenKeys[0]).toBe("abc");
+  }, 20000);
+
+  it("goes DISCONNECTED on a failed poll and recovers", async () => {
+    let failing = false;
+    server = createServer((_req, res) => {
+      if (failing) {
+        res.writeHead(503);
+        res.end("busy");
+        return;
+      }
+      res.writeHead(200, { "Content-Type": "application/json" });
+      res.end(JSON.stringify({ bt: 190 }));
+    });
+    const port = await listen(server);
+
+    const cfg: DriverConfig = {
+      orgId: "o",
+      siteId: "s",
+      machineId: "m",
+      connection: {
+        url: `http://127.0.0.1:${port}/`,
+        pollIntervalMs: 50,
+        mapping: { btC: "$.bt" },
+        reconnect: { minBackoffMs: 20, maxBackoffMs: 50 },
+        dedupeWithinMs: 0
+      }
+    };
+    driver = new HttpPollDriver(cfg);
+    const errors: string[] = [];
+    driver.onError((error) => errors.push(error.message));
+    await driver.connect();
+
+    failing = true;
+    await waitFor(() => errors.length > 0);
+    expect(errors[0]).toBe("poll failed: HTTP 503: busy");
+    failing = false;
+    await waitFor(() => driver!.getStatus().state === "CONNECTED" && driver!.getStatus().metrics.reconnects >= 1);
+    expect((await driver.readTelemetry()).btC).toBe(190);
+  }, 20000);
+
+  it("rejects a mapping path it cannot parse", () => {
+    const cfg: DriverConfig = {
+      orgId: "o",
+      siteId: "s",
+      machineId: "m",
+      connection: { url: "http://127.0.0.1:1/", mapping: { btC: "$..bt" } }
+    };
+    expect(() => new HttpPollDriver(cfg)).toThrow('mapping.btC: invalid path "$..bt"');
+  });
+});
@@ -0,0 +1,68 @@
+import net from "node:net";
+import { afterEach, describe, expect, it } from "vitest";
+import { TcpLineDriverManager } from "../src/manager";
+import { TcpLineTestServer } from "../src/test-server";
+
+async function waitFor(fn: () => boolean, timeoutMs = 5000, intervalMs = 20): Promise<void> {
+  const start = Date.now();
+  while (!fn()) {
+    if (Date.now() - start > timeoutMs) {
+      throw new Error("waitFor timed out");
+    }
+    await new Promise((res) => setTimeout(res, intervalMs));
+  }
+}
+
+describe.sequential("TcpLineDriverManager", () => {
+  let manager: TcpLineDriverManager;
+  const servers: TcpLineTestServer[] = [];
+
+  afterEach(async () => {
+    await manager?.disconnectAll();
+    await Promise.all(servers.splice(0).map((server) => server.stop()));
+  });
+
+  it("multiplexes several machines into one telemetry callback", async () => {
+    const machines = [];
+    for (const [idx, machineId] of ["r1", "r2"].entries()) {
+      const server = new TcpLineTestServer({ lines: [`{"ts":"{ts}","btC":${180 + idx}}`], intervalMs: 20 });
+      servers.push(server);
+      machines.push({ machineId, connection: { host: "127.0.0.1", port: await server.start(), emitIntervalMs: 50, dedupeWithinMs: 0 } });
+    }
+    manager = new TcpLineDriverManager(machines);
+    const seen = new Map<string, number>();
+    manager.onTelemetry((point) => seen.set(point.machineId, point.btC ?? 0));
+    await manager.connectAll();
+
+    await waitFor(() => seen.size === 2);
+    expect(seen.get("r1")).toBe(180);
+    expect(seen.get("r2")).toBe(181);
+    expect((await manager.readTelemetry("r2")).btC).toBe(181);
+    expect(manager.getStatuses().map((status) => status.state)).toEqual(["CONNECTED", "CONNECTED"]);
+    await expect(manager.readTelemetry("nope")).rejects.toThrow("unknown machine");
+  }, 20000);
+
+  it("starts one grpc server for every machine on connectAll", async () => {
+    const server = new TcpLineTestServer({ lines: ['{"ts":"{ts}","btC":180}'], intervalMs: 20 });
+    servers.push(server);
+    const port = await server.start();
+    manager = new TcpLineDriverManager(
+      [
+        { machineId: "r1", connection: { host: "127.0.0.1", port } },
+        { machineId: "r2", connection: { host: "127.0.0.1", port } }
+      ],
+      { grpc: { port: 0 } }
+    );
+    expect(manager.grpcPort()).toBeNull();
+    await manager.connectAll();
+    const grpcPort = manager.grpcPort();
+    expect(grpcPort).toBeGreaterThan(0);
+    await new Promise<void>((resolve, reject) => {
+      const socket = net.connect(grpcPort ?? 0, "127.0.0.1", () => {
+        socket.destroy();
+        resolve();
+      });
+      socket.on("error", reject);
+    });
+  }, 20000);
+});
@@ -0,0 +1,35 @@
+import { afterEach, describe, expect, it } from "vitest";
+import type { DriverConfig } from "@sim-corp/driver-core";
+import { MetricsServer } from "../src/metrics-server";
+import { SimulatedDriver } from "../src/simulated-driver";
+
+describe.sequential("MetricsServer", () => {
+  let driver: SimulatedDriver;
+  let server: MetricsServer;
+
+  afterEach(async () => {
+    await driver?.disconnect?.();
+    await server?.stop?.();
+  });
+
+  it("exposes driver counters and state labelled by machine id", async () => {
+    const cfg: DriverConfig = {
+      orgId: "o",
+      siteId: "s",
+      machineId: "metrics-machine",
+      connection: { sampleIntervalMs: 100, speed: 50, seed: 1 }
+    };
+    driver = new SimulatedDriver(cfg);
+    await driver.connect();
+
+    server = new MetricsServer({ port: 0 });
+    const port = await server.start();
+    const res = await fetch(`http://127.0.0.1:${port}/metrics`);
+    const body = await res.text();
+
+    expect(res.headers.get("content-type")).toContain("text/plain");
+    expect(body).toContain('tcp_line_state{machine_id="metrics-machine",state="CONNECTED"} 1');
+    expect(body).toMatch(/tcp_line_lines_received_total\{machine_id="metrics-machine"\} \d+/);
+    expect((await fetch(`http://127.0.0.1:${port}/other`)).status).toBe(404);
+  }, 20000);
+});
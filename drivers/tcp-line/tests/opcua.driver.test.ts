@@ -0,0 +1,43 @@
+import { afterEach, describe, expect, it } from "vitest";
+import type { DriverConfig } from "@sim-corp/driver-core";
+import { OpcUaDriver } from "../src/opcua-driver";
+
+describe.sequential("OpcUaDriver", () => {
+  let driver: OpcUaDriver | undefined;
+
+  afterEach(async () => {
+    await driver?.disconnect?.();
+    driver = undefined;
+  });
+
+  it("rejects a node id it cannot parse", () => {
+    const cfg: DriverConfig = {
+      orgId: "o",
+      siteId: "s",
+      machineId: "m",
+      connection: { endpointUrl: "opc.tcp://127.0.0.1:4840", nodes: { btC: "not a node" } }
+    };
+    expect(() => new OpcUaDriver(cfg)).toThrow('nodes.btC: invalid node id "not a node"');
+  });
+
+  it("fails to connect to an endpoint nobody listens on", async () => {
+    const cfg: DriverConfig = {
+      orgId: "o",
+      siteId: "s",
+      machineId: "m",
+      connection: {
+        endpointUrl: "opc.tcp://127.0.0.1:1",
+        nodes: { btC: "ns=2;s=BT" },
+        connectTimeoutMs: 2000,
+        reconnect: { enabled: false }
+      }
+    };
+    driver = new OpcUaDriver(cfg);
+    const errors: string[] = [];
+    driver.onError((error) => errors.push(error.code));
+
+    await expect(driver.connect()).rejects.toThrow("connection failure");
+    expect(driver.getStatus().state).toBe("DISCONNECTED");
+    expect(errors).toContain("ConnectFailed");
+  }, 20000);
+});
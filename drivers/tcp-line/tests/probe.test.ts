@@ -0,0 +1,48 @@
+import { createServer, type Server } from "node:net";
+import type { AddressInfo } from "node:net";
+import { afterEach, describe, expect, it } from "vitest";
+import { probeEndpoint } from "../src/probe";
+
+let server: Server | undefined;
+
+afterEach(async () => {
+  await new Promise<void>((resolve) => (server ? server.close(() => resolve()) : resolve()));
+  server = undefined;
+});
+
+async function serveLines(text: string): Promise<number> {
+  server = createServer((socket) => socket.write(text));
+  await new Promise<void>((resolve) => server!.listen(0, "127.0.0.1", resolve));
+  return (server!.address() as AddressInfo).port;
+}
+
+describe("probeEndpoint", () => {
+  it("detects csv with a header and returns a parsed sample", async () => {
+    const port = await serveLines("time;btC;etC\n0;180.5;200\n1;181.5;202\n");
+    const result = await probeEndpoint("127.0.0.1", port, { timeoutMs: 500 });
+    expect(result).toMatchObject({
+      format: "csv",
+      delimiter: ";",
+      hasHeader: true,
+      columns: ["time", "btC", "etC"],
+      lines: ["time;btC;etC", "0;180.5;200", "1;181.5;202"],
+      parseErrors: 0
+    });
+    expect(result.sample).toMatchObject({ btC: 181.5, etC: 202, extras: { time: 1 } });
+  });
+
+  it("counts parse errors when lines don't match the requested format", async () => {
+    const port = await serveLines("a,b\n1,2\n");
+    const result = await probeEndpoint("127.0.0.1", port, { format: "jsonl", timeoutMs: 300 });
+    expect(result.format).toBe("jsonl");
+    expect(result.sample).toBeUndefined();
+    expect(result.parseErrors).toBe(2);
+    expect(result.firstError).toBeDefined();
+  });
+
+  it("rejects when the device can't be reached", async () => {
+    await expect(probeEndpoint("127.0.0.1", 1, { timeoutMs: 300 })).rejects.toThrow(
+      "connection failure"
+    );
+  });
+});
@@ -0,0 +1,71 @@
+import { mkdtempSync, writeFileSync } from "node:fs";
+import { tmpdir } from "node:os";
+import path from "node:path";
+import { afterEach, describe, expect, it } from "vitest";
+import type { DriverConfig } from "@sim-corp/driver-core";
+import { ReplayDriver } from "../src/replay-driver";
+
+async function waitFor(fn: () => boolean, timeoutMs = 5000, intervalMs = 20): Promise<void> {
+  const start = Date.now();
+  while (!fn()) {
+    if (Date.now() - start > timeoutMs) {
+      throw new Error("waitFor timed out");
+    }
+    await new Promise((res) => setTimeout(res, intervalMs));
+  }
+}
+
+describe.sequential("ReplayDriver", () => {
+  let driver: ReplayDriver;
+
+  afterEach(async () => {
+    await driver?.disconnect?.();
+  });
+
+  it("replays a recorded capture through the parser", async () => {
+    const dir = mkdtempSync(path.join(tmpdir(), "tcp-line-replay-"));
+    const file = path.join(dir, "capture.ndjson");
+    const start = Date.parse("2025-01-01T00:00:00.000Z");
+    const lines = [0, 1, 2].map((idx) =>
+      JSON.stringify({
+        receivedAt: new Date(start + idx * 1000).toISOString(),
+        line: JSON.stringify({ ts: new Date(start + idx * 1000).toISOString(), btC: 180 + idx })
+      })
+    );
+    writeFileSync(file, `${lines.join("\n")}\nnot-json\n`);
+
+    const cfg: DriverConfig = {
+      orgId: "o",
+      siteId: "s",
+      machineId: "m",
+      connection: { path: file, speed: 0, dedupeWithinMs: 0 }
+    };
+    driver = new ReplayDriver(cfg);
+    await driver.connect();
+    await waitFor(() => driver.getStatus().metrics.parseErrors >= 1);
+
+    const point = await driver.readTelemetry();
+    expect(point.btC).toBe(182);
+    expect(point.elapsedSeconds).toBe(2);
+    expect(driver.getStatus().metrics.linesParsed).toBe(3);
+  }, 20000);
+
+  it("reports bytes, parse latency and time in state", async () => {
+    const dir = mkdtempSync(path.join(tmpdir(), "tcp-line-replay-"));
+    const file = path.join(dir, "plain.jsonl");
+    const lines = [0, 1, 2, 3].map((idx) => JSON.stringify({ ts: new Date(Date.UTC(2025, 0, 1, 0, 0, idx)).toISOString(), btC: 180 }));
+    writeFileSync(file, `${lines.join("\n")}\n`);
+
+    const cfg: DriverConfig = { orgId: "o", siteId: "s", machineId: "m", connection: { path: file, speed: 0, dedupeWithinMs: 0 } };
+    driver = new ReplayDriver(cfg);
+    await driver.connect();
+    await waitFor(() => driver.getStatus().metrics.linesParsed >= 4);
+
+    const detailed = driver.getMetricsDetailed();
+    expect(detailed.metrics.bytesReceived).toBe(lines.reduce((sum, line) => sum + line.length + 1, 0));
+    expect(detailed.parseLatency.count).toBe(4);
+    expect(detailed.parseLatency.counts).toHaveLength(detailed.parseLatency.boundsUs.length + 1);
+    expect(detailed.sampleInterval?.count).toBe(3);
+    expect(detailed.stateDurationsMs.connecting).toBeGreaterThanOrEqual(0);
+  }, 20000);
+});
@@ -0,0 +1,79 @@
+import { mkdtempSync, readFileSync, rmSync, writeFileSync } from "node:fs";
+import { tmpdir } from "node:os";
+import path from "node:path";
+import { afterEach, describe, expect, it } from "vitest";
+import { ReplayDriver } from "../src/replay-driver";
+import { importRoastLog } from "../src/roast-import";
+
+async function waitFor(fn: () => boolean, timeoutMs = 5000, intervalMs = 20): Promise<void> {
+  const start = Date.now();
+  while (!fn()) {
+    if (Date.now() - start > timeoutMs) {
+      throw new Error("waitFor timed out");
+    }
+    await new Promise((res) => setTimeout(res, intervalMs));
+  }
+}
+
+describe.sequential("importRoastLog", () => {
+  let dir: string;
+  let driver: ReplayDriver | undefined;
+
+  afterEach(async () => {
+    await driver?.disconnect();
+    driver = undefined;
+    rmSync(dir, { recursive: true, force: true });
+  });
+
+  it("converts an Artisan export in °F and replays it", async () => {
+    dir = mkdtempSync(path.join(tmpdir(), "tcp-line-import-"));
+    const input = path.join(dir, "roast.csv");
+    writeFileSync(
+      input,
+      [
+        "Date:14.10.2025\tUnit:F\tCHARGE:00:00\tTP:01:00\tDRYe:04:00\tFCs:\tFCe:\tSCs:\tSCe:\tDROP:\tCOOL:\tTime:04:00",
+        "Time1\tTime2\tET\tBT\tEvent\tGas",
+        "00:00\t00:00\t410.0\t392.0\tCharge\t50",
+        "01:00\t01:00\t392.0\t212.0\tTP\t",
+        "04:00\t04:00\t428.0\t302.0\tDry End\t70"
+      ].join("\n")
+    );
+    const imported = await importRoastLog(input, path.join(dir, "capture.ndjson"));
+    expect(imported).toMatchObject({ format: "artisan", samples: 3, unit: "F", durationSeconds: 240 });
+    expect(imported.startedAt).toBe("2025-10-14T00:00:00.000Z");
+    expect(imported.events).toEqual([
+      { name: "Charge", elapsedSeconds: 0 },
+      { name: "TP", elapsedSeconds: 60 },
+      { name: "Dry End", elapsedSeconds: 240 }
+    ]);
+
+    driver = new ReplayDriver({
+      orgId: "o",
+      siteId: "s",
+      machineId: "m",
+      connection: { path: imported.path, format: "jsonl", speed: 0, dedupeWithinMs: 0, queue: {} }
+    });
+    await driver.connect();
+    await waitFor(() => driver!.getStatus().metrics.linesParsed >= 3);
+    const points = driver.drainSamples();
+    expect(points.map((point) => [point.elapsedSeconds, point.btC, point.etC])).toEqual([
+      [0, 200, 210],
+      [60, 100, 200],
+      [240, 150, 220]
+    ]);
+    expect(points.map((point) => point.gasPct)).toEqual([50, undefined, 70]);
+  }, 20000);
+
+  it("reads a Cropster curve CSV from startAt, keeping unknown columns as extras", async () => {
+    dir = mkdtempSync(path.join(tmpdir(), "tcp-line-import-"));
+    const input = path.join(dir, "cropster.csv");
+    writeFileSync(input, "Time (s),Bean temperature (°C),Exhaust temperature (°C)\n0,210.5,180\n1.5,205,181\n");
+    const output = path.join(dir, "capture.ndjson");
+    const imported = await importRoastLog(input, output, { startAt: "2025-01-01T06:00:00Z" });
+    expect(imported).toMatchObject({ format: "cropster", samples: 2, unit: "C", durationSeconds: 1.5, events: [] });
+    const lines = readFileSync(output, "utf8").trim().split("\n").map((line) => JSON.parse(line));
+    expect(lines[1].receivedAt).toBe("2025-01-01T06:00:01.500Z");
+    expect(JSON.parse(lines[1].line)).toEqual({ ts: "2025-01-01T06:00:01.500Z", btC: 205, "Exhaust temperature": 181 });
+    await expect(importRoastLog(input, output, { format: "artisan" })).rejects.toThrow("no time column");
+  });
+});
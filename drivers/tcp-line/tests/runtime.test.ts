@@ -0,0 +1,25 @@
+import { describe, expect, it } from "vitest";
+import { TcpLineDriver } from "../src/driver";
+import { initRuntime } from "../src/runtime";
+import { TcpLineTestServer } from "../src/test-server";
+
+describe("initRuntime", () => {
+  it("runs a driver bound to a named runtime", async () => {
+    expect(() => initRuntime({ workerThreads: 0, name: "runtime-test" })).toThrow("workerThreads must be positive");
+    const config = { orgId: "o", siteId: "s", machineId: "m" };
+    expect(
+      () => new TcpLineDriver({ ...config, connection: { host: "127.0.0.1", port: 1, runtime: "runtime-test" } })
+    ).toThrow('unknown runtime "runtime-test"');
+
+    initRuntime({ workerThreads: 1, threadNamePrefix: "rt-test", name: "runtime-test" });
+    expect(() => initRuntime({ workerThreads: 1, name: "runtime-test" })).toThrow("already initialised");
+    const server = new TcpLineTestServer({ lines: ['{"ts":"{ts}","btC":190}'], intervalMs: 20 });
+    const port = await server.start();
+    const driver = new TcpLineDriver({ ...config, connection: { host: "127.0.0.1", port, runtime: "runtime-test" } });
+    await driver.connect();
+    expect((await driver.readTelemetry()).btC).toBe(190);
+    await driver.disconnect();
+    expect(driver.getStatus().state).toBe("STOPPED");
+    await server.stop();
+  }, 10000);
+});
@@ -0,0 +1,18 @@
+import { describe, expect, it } from "vitest";
+import { selfTest } from "../src/self-test";
+
+describe("selfTest", () => {
+  it("streams the fixtures through a loopback driver and passes every check", async () => {
+    const report = await selfTest();
+    expect(report.checks.map((check) => check.name)).toEqual([
+      "loopback",
+      "connect",
+      "telemetry",
+      "parseErrors",
+      "disconnect"
+    ]);
+    expect(report.checks.filter((check) => !check.passed)).toEqual([]);
+    expect(report.passed).toBe(true);
+    expect(report.elapsedMs).toBeGreaterThan(0);
+  });
+});
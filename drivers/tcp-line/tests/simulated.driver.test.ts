@@ -0,0 +1,135 @@
+import { afterEach, describe, expect, it } from "vitest";
+import type { DriverConfig } from "@sim-corp/driver-core";
+import type { RoastEvent } from "../src/metrics";
+import { SimulatedDriver } from "../src/simulated-driver";
+
+async function waitFor(fn: () => boolean, timeoutMs = 5000, intervalMs = 20): Promise<void> {
+  const start = Date.now();
+  while (!fn()) {
+    if (Date.now() - start > timeoutMs) {
+      throw new Error("waitFor timed out");
+    }
+    await new Promise((res) => setTimeout(res, intervalMs));
+  }
+}
+
+describe.sequential("SimulatedDriver", () => {
+  let driver: SimulatedDriver;
+
+  afterEach(async () => {
+    await driver?.disconnect?.();
+  });
+
+  it("synthesizes a rising roast curve after the turning point", async () => {
+    const cfg: DriverConfig = {
+      orgId: "o",
+      siteId: "s",
+      machineId: "m",
+      connection: { durationSeconds: 600, speed: 200, seed: 7, noiseC: 0 }
+    };
+    driver = new SimulatedDriver(cfg);
+    await driver.connect();
+
+    await new Promise((res) => setTimeout(res, 600));
+    const early = await driver.readTelemetry();
+    await new Promise((res) => setTimeout(res, 1200));
+    const late = await driver.readTelemetry();
+
+    expect(late.elapsedSeconds).toBeGreaterThan(early.elapsedSeconds);
+    expect(late.btC).toBeGreaterThan(early.btC ?? 0);
+    expect(typeof late.extras?.phase).toBe("string");
+    expect(driver.getStatus().state).toBe("CONNECTED");
+  }, 20000);
+
+  it("streams successive samples through for await", async () => {
+    const cfg: DriverConfig = {
+      orgId: "o",
+      siteId: "s",
+      machineId: "m",
+      connection: { sampleIntervalMs: 100, speed: 10, seed: 3, noiseC: 0, emitIntervalMs: 20 }
+    };
+    driver = new SimulatedDriver(cfg);
+    await driver.connect();
+
+    const elapsed: number[] = [];
+    for await (const point of driver.telemetryStream()) {
+      elapsed.push(point.elapsedSeconds);
+      if (elapsed.length === 3) break;
+    }
+
+    expect(elapsed).toHaveLength(3);
+    expect(elapsed[2]).toBeGreaterThan(elapsed[0]);
+  }, 20000);
+
+  it("detects roast events along the simulated curve", async () => {
+    const cfg: DriverConfig = {
+      orgId: "o",
+      siteId: "s",
+      machineId: "m",
+      connection: { durationSeconds: 600, speed: 300, seed: 1, emitIntervalMs: 10, roastEvents: {} }
+    };
+    driver = new SimulatedDriver(cfg);
+    const events: RoastEvent[] = [];
+    driver.onEvent((event) => events.push(event));
+    await driver.connect();
+
+    await waitFor(() => events.length >= 4, 10000);
+    expect(events.map((event) => event.type)).toEqual(["CHARGE", "TURNING_POINT", "DRY_END", "FC"]);
+    expect(events[1].btC).toBeLessThan(100);
+  }, 20000);
+
+  it("injects disconnects and corrupted lines when faults are configured", async () => {
+    const cfg: DriverConfig = {
+      orgId: "o",
+      siteId: "s",
+      machineId: "m",
+      connection: {
+        durationSeconds: 600,
+        speed: 100,
+        loop: true,
+        seed: 1,
+        emitIntervalMs: 10,
+        faults: { disconnectEverySeconds: 0.3, disconnectedForMs: 50, corruptLineProbability: 0.5, seed: 7 }
+      }
+    };
+    driver = new SimulatedDriver(cfg);
+    await driver.connect();
+
+    await waitFor(() => {
+      const { metrics } = driver.getStatus();
+      return metrics.reconnects >= 1 && metrics.parseErrors >= 1;
+    }, 10000);
+    const point = await driver.readTelemetry();
+    expect(point.btC).toBeGreaterThan(0);
+  }, 20000);
+
+  it("summarises the roast phases from the detected events", async () => {
+    const cfg: DriverConfig = {
+      orgId: "o",
+      siteId: "s",
+      machineId: "m",
+      connection: { durationSeconds: 900, speed: 300, seed: 1, emitIntervalMs: 10, roastEvents: {} }
+    };
+    driver = new SimulatedDriver(cfg);
+    const events: RoastEvent[] = [];
+    driver.onEvent((event) => events.push(event));
+    expect(driver.getSessionSummary()).toBeNull();
+    await driver.connect();
+
+    await waitFor(() => events.some((event) => event.type === "FC"), 10000);
+    const summary = driver.getSessionSummary();
+    expect(summary?.chargedAt).toBe(events[0].ts);
+    expect(summary?.phases.map((phase) => phase.phase)).toEqual(["drying", "maillard", "development"]);
+    const [drying, maillard, development] = summary!.phases;
+    expect(drying.endedAt).toBe(events.find((event) => event.type === "DRY_END")?.ts);
+    expect(maillard.endBtC).toBe(development.startBtC);
+    expect(development.endedAt).toBeUndefined();
+    expect(maillard.meanRorCPerMin).toBeGreaterThan(0);
+    const total = summary!.phases.reduce((sum, phase) => sum + phase.durationSeconds, 0);
+    expect(total).toBeCloseTo(summary!.totalSeconds, 6);
+
+    const point = await driver.readTelemetry();
+    expect(point.phases?.current).toBe("development");
+    expect(point.phases!.dryingPct + point.phases!.maillardPct + point.phases!.developmentPct).toBeCloseTo(100, 6);
+  }, 20000);
+});
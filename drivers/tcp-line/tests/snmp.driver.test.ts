@@ -0,0 +1,178 @@
+import { createSocket, type Socket } from "node:dgram";
+import type { AddressInfo } from "node:net";
+import { afterEach, describe, expect, it } from "vitest";
+import type { DriverConfig } from "@sim-corp/driver-core";
+import { SnmpDriver } from "../src/snmp-driver";
+
+type AgentValue = number | string;
+
+function tlv(tag: number, body: Buffer): Buffer {
+  const length = body.length < 128 ? [body.length] : [0x82, body.length >> 8, body.length & 0xff];
+  return Buffer.concat([Buffer.from([tag, ...length]), body]);
+}
+
+function integer(value: number): Buffer {
+  const bytes: number[] = [];
+  let rest = value;
+  do {
+    bytes.unshift(rest & 0xff);
+    rest = Math.floor(rest / 256);
+  } while (rest > 0);
+  if (bytes[0] & 0x80) {
+    bytes.unshift(0);
+  }
+  return tlv(0x02, Buffer.from(bytes));
+}
+
+function children(body: Buffer): Buffer[][] {
+  const out: Buffer[][] = [];
+  let offset = 0;
+  while (offset < body.length) {
+    let length = body[offset + 1];
+    let header = 2;
+    if (length & 0x80) {
+      const count = length & 0x7f;
+      length = body
+        .subarray(offset + 2, offset + 2 + count)
+        .reduce((acc, byte) => acc * 256 + byte, 0);
+      header += count;
+    }
+    out.push([
+      body.subarray(offset, offset + 1),
+      body.subarray(offset + header, offset + header + length)
+    ]);
+    offset += header + length;
+  }
+  return out;
+}
+
+function oidText(body: Buffer): string {
+  const arcs = [Math.floor(body[0] / 40), body[0] % 40];
+  let arc = 0;
+  for (const byte of body.subarray(1)) {
+    arc = arc * 128 + (byte & 0x7f);
+    if (!(byte & 0x80)) {
+      arcs.push(arc);
+      arc = 0;
+    }
+  }
+  return arcs.join(".");
+}
+
+/** A v1/v2c agent that answers GETs from `values`; OIDs it lacks come back as noSuchObject. */
+async function startAgent(values: Record<string, AgentValue>): Promise<Socket> {
+  const socket = createSocket("udp4");
+  socket.on("message", (message, peer) => {
+    const [version, community, pdu] = children(children(message)[0][1]).map(([, body]) => body);
+    const [requestId, , , bindings] = children(pdu).map(([, body]) => body);
+    const answers = children(bindings).map(([, binding]) => {
+      const oid = children(binding)[0][1];
+      const value = values[oidText(oid)];
+      const encoded =
+        value === undefined
+          ? tlv(0x80, Buffer.alloc(0))
+          : typeof value === "string"
+            ? tlv(0x04, Buffer.from(value))
+            : integer(value);
+      return tlv(0x30, Buffer.concat([tlv(0x06, oid), encoded]));
+    });
+    const response = tlv(
+      0xa2,
+      Buffer.concat([tlv(0x02, requestId), integer(0), integer(0), tlv(0x30, Buffer.concat(answers))])
+    );
+    const reply = tlv(0x30, Buffer.concat([tlv(0x02, version), tlv(0x04, community), response]));
+    socket.send(reply, peer.port, peer.address);
+  });
+  await new Promise<void>((res) => socket.bind(0, "127.0.0.1", res));
+  return socket;
+}
+
+describe.sequential("SnmpDriver", () => {
+  let driver: SnmpDriver | undefined;
+  let agent: Socket | undefined;
+
+  afterEach(async () => {
+    await driver?.disconnect?.();
+    driver = undefined;
+    agent?.close();
+    agent = undefined;
+  });
+
+  it("maps polled OIDs onto channels and extras", async () => {
+    agent = await startAgent({
+      "1.3.6.1.4.1.9999.1.0": 1815,
+      "1.3.6.1.4.1.9999.2.0": "62.5",
+      "1.3.6.1.4.1.9999.3.0": "on"
+    });
+    const cfg: DriverConfig = {
+      orgId: "o",
+      siteId: "s",
+      machineId: "m",
+      connection: {
+        host: "127.0.0.1",
+        port: (agent.address() as AddressInfo).port,
+        pollIntervalMs: 50,
+        oids: {
+          btC: { oid: ".1.3.6.1.4.1.9999.1.0", scale: 0.1 },
+          fanPct: { oid: "1.3.6.1.4.1.9999.2.0" },
+          afterburner: { oid: "1.3.6.1.4.1.9999.3.0" },
+          missing: { oid: "1.3.6.1.4.1.9999.9.0" }
+        },
+        dedupeWithinMs: 0
+      }
+    };
+    driver = new SnmpDriver(cfg);
+    await driver.connect();
+
+    const point = await driver.readTelemetry();
+    expect(point.btC).toBe(181.5);
+    expect(point.fanPct).toBe(62.5);
+    expect(point.extras?.afterburner).toBe("on");
+    expect(point.extras).not.toHaveProperty("missing");
+  }, 20000);
+
+  it("fails to connect to an agent that is not there", async () => {
+    const cfg: DriverConfig = {
+      orgId: "o",
+      siteId: "s",
+      machineId: "m",
+      connection: {
+        host: "127.0.0.1",
+        port: 1,
+        oids: { btC: { oid: "1.3.6.1.4.1.9999.1.0" } },
+        requestTimeoutMs: 500,
+        reconnect: { enabled: false }
+      }
+    };
+    driver = new SnmpDriver(cfg);
+    const errors: string[] = [];
+    driver.onError((error) => errors.push(error.code));
+    await expect(driver.connect()).rejects.toThrow("connection failure: 127.0.0.1:1");
+    expect(driver.getStatus().state).toBe("DISCONNECTED");
+    expect(errors.length).toBeGreaterThan(0);
+  }, 20000);
+
+  it("rejects an OID it cannot parse", () => {
+    const cfg: DriverConfig = {
+      orgId: "o",
+      siteId: "s",
+      machineId: "m",
+      connection: { host: "127.0.0.1", oids: { btC: { oid: "1.3.x" } } }
+    };
+    expect(() => new SnmpDriver(cfg)).toThrow('oids.btC: invalid oid "1.3.x"');
+  });
+
+  it("requires v3 settings for version v3", () => {
+    const cfg: DriverConfig = {
+      orgId: "o",
+      siteId: "s",
+      machineId: "m",
+      connection: {
+        host: "127.0.0.1",
+        version: "v3",
+        oids: { btC: { oid: "1.3.6.1.4.1.9999.1.0" } }
+      }
+    };
+    expect(() => new SnmpDriver(cfg)).toThrow("v3 is required for version v3");
+  });
+});
@@ -1,7 +1,16 @@
+import { execFileSync } from "node:child_process";
+import { mkdtempSync, readFileSync, rmSync } from "node:fs";
+import http from "node:http";
 import net from "node:net";
+import { tmpdir } from "node:os";
+import path from "node:path";
 import { afterEach, describe, expect, it } from "vitest";
 import type { DriverConfig } from "@sim-corp/driver-core";
 import { TcpLineDriver } from "../src/driver";
+import { listActiveDrivers } from "../src/registry";
+import { ReplayDriver } from "../src/replay-driver";
+import type { AlarmEvent, DriverError, RawLineEvent, SequenceGapEvent, StateChangeEvent } from "../src/metrics";
+import { TcpLineTestServer } from "../src/test-server";
 
 function createServer(
   lines: string[],
@@ -47,6 +56,52 @@ function createServer(
   });
 }
 
+/** A CONNECT-only HTTP proxy that tunnels to loopback and records each request head. */
+function startConnectProxy(
+  credentials?: string
+): Promise<{ port: number; requests: string[]; close: () => Promise<void> }> {
+  return new Promise((resolve) => {
+    const requests: string[] = [];
+    const sockets: net.Socket[] = [];
+    const server = net.createServer((client) => {
+      sockets.push(client);
+      let head = "";
+      const onData = (chunk: Buffer) => {
+        head += chunk.toString("latin1");
+        const end = head.indexOf("\r\n\r\n");
+        if (end < 0) return;
+        client.off("data", onData);
+        requests.push(head.slice(0, end));
+        const expected = credentials && `Proxy-Authorization: Basic ${Buffer.from(credentials).toString("base64")}`;
+        if (expected && !head.includes(expected)) {
+          client.end("HTTP/1.1 407 Proxy Authentication Required\r\n\r\n");
+          return;
+        }
+        const port = Number(head.split(" ")[1].split(":")[1]);
+        const upstream = net.connect(port, "127.0.0.1", () => {
+          client.write("HTTP/1.1 200 Connection established\r\n\r\n");
+          client.pipe(upstream).pipe(client);
+        });
+        sockets.push(upstream);
+      };
+      client.on("data", onData);
+      client.on("error", () => undefined);
+    });
+    server.listen(0, "127.0.0.1", () => {
+      const addr = server.address();
+      resolve({
+        port: typeof addr === "object" && addr ? addr.port : 0,
+        requests,
+        close: async () =>
+          await new Promise<void>((res) => {
+            sockets.forEach((s) => s.destroy());
+            server.close(() => res());
+          })
+      });
+    });
+  });
+}
+
 async function waitFor(fn: () => boolean, timeoutMs = 5000, intervalMs = 20, onTimeout?: () => string): Promise<void> {
   const start = Date.now();
   // eslint-disable-next-line no-constant-condition
@@ -221,4 +276,1615 @@ describe.sequential("TcpLineDriver", () => {
     expect(point.extras).toEqual({});
     await server.close();
   }, 20000);
+
+  it("parses quoted csv fields and rejects short rows in strict mode", async () => {
+    const server = await createServer(['185\t"tab\there, ""quoted"""\t195', "186\t196"]);
+    driver = new TcpLineDriver({
+      orgId: "o",
+      siteId: "s",
+      machineId: "m",
+      connection: {
+        host: "127.0.0.1",
+        port: server.port,
+        format: "csv",
+        csv: { hasHeader: false, delimiter: "\\t", columns: ["btC", "note", "etC"], columnMismatch: "strict" }
+      }
+    });
+    await driver.connect();
+    await waitFor(() => driver.getStatus().metrics.parseErrors >= 1, 5000, () => JSON.stringify(driver.getStatus()));
+    const point = await driver.readTelemetry();
+    expect(point.btC).toBe(185);
+    expect(point.etC).toBe(195);
+    expect(point.extras).toEqual({ note: 'tab\there, "quoted"' });
+    await server.close();
+  }, 20000);
+
+  it("detects a semicolon csv with a header under autodetect", async () => {
+    const server = await createServer(["time;btC;etC", "0;180;200", "1;181;201", "2;182;202"]);
+    driver = new TcpLineDriver({
+      orgId: "o",
+      siteId: "s",
+      machineId: "m",
+      connection: { host: "127.0.0.1", port: server.port, format: "autodetect" }
+    });
+    await driver.connect();
+    await waitFor(() => driver.getStatus().metrics.linesParsed >= 2, 5000, () => JSON.stringify(driver.getStatus()));
+    expect(driver.getStatus().detectedFormat).toEqual({ format: "csv", delimiter: ";", hasHeader: true });
+    expect(driver.getStatus().metrics.parseErrors).toBe(0);
+    const point = await driver.readTelemetry();
+    expect(point.btC).toBe(182);
+    expect(point.extras).toEqual({ time: 2 });
+    await server.close();
+  }, 20000);
+
+  it("extracts channels from prose with a regex", async () => {
+    const server = await createServer(["T1: 198.2C T2: 210.5C", "no reading"]);
+    driver = new TcpLineDriver({
+      orgId: "o",
+      siteId: "s",
+      machineId: "m",
+      connection: {
+        host: "127.0.0.1",
+        port: server.port,
+        format: "regex",
+        regex: { pattern: "T1: (?<btC>[\\d.]+)C T2: (?<etC>[\\d.]+)C" }
+      }
+    });
+    await driver.connect();
+    await waitFor(() => driver.getStatus().metrics.parseErrors >= 1, 5000, () => JSON.stringify(driver.getStatus()));
+    const point = await driver.readTelemetry();
+    expect(point.btC).toBe(198.2);
+    expect(point.etC).toBe(210.5);
+    await server.close();
+  }, 20000);
+
+  it("maps xml elements and attributes to channels", async () => {
+    const server = await createServer([
+      '<reading ts="2025-01-01T00:00:00.000Z"><temps><bt>198.5</bt><et>210</et></temps><line>A3</line></reading>',
+      "<reading><bt>"
+    ]);
+    driver = new TcpLineDriver({
+      orgId: "o",
+      siteId: "s",
+      machineId: "m",
+      connection: {
+        host: "127.0.0.1",
+        port: server.port,
+        format: "xml",
+        xml: { fields: { ts: "/reading/@ts", btC: "/reading/temps/bt", etC: "/reading/*/et", line: "/reading/line" } }
+      }
+    });
+    await driver.connect();
+    await waitFor(() => driver.getStatus().metrics.parseErrors >= 1, 5000, () => JSON.stringify(driver.getStatus()));
+    const point = await driver.readTelemetry();
+    expect(point.ts).toBe("2025-01-01T00:00:00.000Z");
+    expect(point.btC).toBe(198.5);
+    expect(point.etC).toBe(210);
+    expect(point.extras).toEqual({ line: "A3" });
+    await server.close();
+  }, 20000);
+
+  it("flattens nested json objects onto channels and extras", async () => {
+    const server = await createServer(['{"temps":{"bt":200,"et":210,"probe":{"t3":150,"deep":{"x":1}}},"fan":45}']);
+    driver = new TcpLineDriver({
+      orgId: "o",
+      siteId: "s",
+      machineId: "m",
+      connection: {
+        host: "127.0.0.1",
+        port: server.port,
+        json: { flatten: { maxDepth: 2 }, fields: { btC: "temps.bt", etC: "temps.et" } }
+      }
+    });
+    await driver.connect();
+    const point = await driver.readTelemetry();
+    expect(point.btC).toBe(200);
+    expect(point.etC).toBe(210);
+    expect(point.extras).toEqual({ fan: 45, "temps.probe.t3": 150 });
+    await server.close();
+  }, 20000);
+
+  it("emits each object of a json array line as its own sample", async () => {
+    const server = await createServer([
+      '[{"ts":"2025-01-01T00:00:00.000Z","btC":190},{"ts":"2025-01-01T00:00:01.000Z","btC":191},{"ts":"2025-01-01T00:00:02.000Z","btC":192}]'
+    ]);
+    driver = new TcpLineDriver({
+      orgId: "o",
+      siteId: "s",
+      machineId: "m",
+      connection: { host: "127.0.0.1", port: server.port, queue: { capacity: 10 } }
+    });
+    await driver.connect();
+    await waitFor(() => driver.getStatus().metrics.batchSamples >= 3, 5000, () => JSON.stringify(driver.getStatus()));
+    expect(driver.drainSamples().map((point) => point.btC)).toEqual([190, 191, 192]);
+    expect(driver.getStatus().metrics.batchLines).toBe(1);
+    await server.close();
+  }, 20000);
+
+  it("puts late samples back in timestamp order under ordering reorder", async () => {
+    const start = Date.UTC(2025, 0, 1);
+    const seconds = [0, 2, 1, 3, 4, 0.5];
+    const server = await createServer(
+      seconds.map((second, idx) => JSON.stringify({ ts: new Date(start + second * 1000), btC: 190 + idx }))
+    );
+    driver = new TcpLineDriver({
+      orgId: "o",
+      siteId: "s",
+      machineId: "m",
+      connection: {
+        host: "127.0.0.1",
+        port: server.port,
+        dedupeWithinMs: 0,
+        ordering: { policy: "reorder", windowMs: 1500 },
+        queue: {}
+      }
+    });
+    await driver.connect();
+    await waitFor(() => driver.getStatus().metrics.linesReceived >= 6, 5000, () => JSON.stringify(driver.getStatus()));
+    // 0.5 s arrived after 2 s had been let out, too late to place; 3 s and 4 s are still held.
+    expect(driver.drainSamples().map((point) => point.btC)).toEqual([190, 192, 191]);
+    expect(driver.getStatus().metrics.outOfOrderSamples).toBe(2);
+    await server.close();
+  }, 20000);
+
+  it("counts sequence gaps, duplicates and late frames", async () => {
+    const server = await createServer([1, 2, 5, 5, 4, 6].map((seq) => JSON.stringify({ btC: 190, seq })));
+    driver = new TcpLineDriver({
+      orgId: "o",
+      siteId: "s",
+      machineId: "m",
+      connection: { host: "127.0.0.1", port: server.port, dedupeWithinMs: 0, sequence: { field: "seq" } }
+    });
+    const gaps: SequenceGapEvent[] = [];
+    driver.onGap((event) => gaps.push(event));
+    await driver.connect();
+    await waitFor(() => driver.getStatus().metrics.linesParsed >= 6, 5000, () => JSON.stringify(driver.getStatus()));
+    const metrics = driver.getStatus().metrics;
+    expect([metrics.sequenceGaps, metrics.sequenceMissing]).toEqual([1, 2]);
+    expect([metrics.sequenceDuplicates, metrics.sequenceReordered]).toEqual([1, 1]);
+    await new Promise((res) => setTimeout(res, 50));
+    expect(gaps.map((gap) => [gap.expected, gap.received, gap.missing])).toEqual([[3, 5, 2]]);
+    await server.close();
+  }, 20000);
+
+  it("rejects or shares a second connection to the same endpoint per onDuplicate", async () => {
+    const server = await createServer(['{"btC":190}']);
+    const connection = { host: "127.0.0.1", port: server.port };
+    driver = new TcpLineDriver({ orgId: "o", siteId: "s", machineId: "m1", connection });
+    await driver.connect();
+    const rejected = new TcpLineDriver({
+      orgId: "o",
+      siteId: "s",
+      machineId: "m2",
+      connection: { ...connection, onDuplicate: "error" }
+    });
+    await expect(rejected.connect()).rejects.toThrow(`duplicate connection: 127.0.0.1:${server.port}`);
+    await rejected.disconnect();
+
+    const shared = new TcpLineDriver({
+      orgId: "o",
+      siteId: "s",
+      machineId: "m3",
+      connection: { ...connection, onDuplicate: "share" }
+    });
+    await shared.connect();
+    expect((await shared.readTelemetry()).btC).toBe(190);
+    expect(server.connections()).toBe(1);
+    expect(listActiveDrivers().filter((active) => active.endpoint === `127.0.0.1:${server.port}`)).toHaveLength(1);
+    await shared.disconnect();
+    expect(driver.getStatus().state).toBe("CONNECTED");
+    await server.close();
+  }, 20000);
+
+  it("verifies and strips nmea checksums", async () => {
+    const payload = '{"btC":190}';
+    const checksum = [...payload].reduce((acc, ch) => acc ^ ch.charCodeAt(0), 0);
+    const server = await createServer([`$${payload}*${checksum.toString(16).padStart(2, "0")}`, '{"btC":1}*00']);
+    driver = new TcpLineDriver({
+      orgId: "o",
+      siteId: "s",
+      machineId: "m",
+      connection: { host: "127.0.0.1", port: server.port, checksum: "nmea" }
+    });
+    await driver.connect();
+    await waitFor(() => driver.getStatus().metrics.checksumErrors >= 1, 5000, () => JSON.stringify(driver.getStatus()));
+    expect((await driver.readTelemetry()).btC).toBe(190);
+    await server.close();
+  }, 20000);
+
+  it("reports or rejects malformed channel fields per strictness", async () => {
+    const lines = ['{"btC":"ERR","etC":210}', '{"btC":195,"etC":211}'];
+    const warnServer = await createServer(lines);
+    driver = new TcpLineDriver({
+      orgId: "o",
+      siteId: "s",
+      machineId: "m",
+      connection: { host: "127.0.0.1", port: warnServer.port, strictness: "warn" }
+    });
+    await driver.connect();
+    await waitFor(() => driver.getStatus().metrics.fieldErrors >= 1, 5000, () => JSON.stringify(driver.getStatus()));
+    const [warning] = driver.getRecentErrors();
+    expect(warning.kind).toBe("invalidField");
+    expect(warning.message).toBe('invalid field btC: "ERR" is not a number');
+    expect(driver.getStatus().metrics.parseErrors).toBe(0);
+    await driver.disconnect();
+    await warnServer.close();
+
+    const strictServer = await createServer(lines);
+    driver = new TcpLineDriver({
+      orgId: "o",
+      siteId: "s",
+      machineId: "m",
+      connection: { host: "127.0.0.1", port: strictServer.port, strictness: "strict" }
+    });
+    await driver.connect();
+    await waitFor(() => driver.getStatus().metrics.parseErrors >= 1, 5000, () => JSON.stringify(driver.getStatus()));
+    expect(driver.getRecentErrors()[0].kind).toBe("invalidField");
+    expect((await driver.readTelemetry()).btC).toBe(195);
+    await strictServer.close();
+  }, 20000);
+
+  it("assembles a reading split across lines", async () => {
+    const server = await createServer(["BT=190.5", "ET=210", "END"]);
+    driver = new TcpLineDriver({
+      orgId: "o",
+      siteId: "s",
+      machineId: "m",
+      connection: {
+        host: "127.0.0.1",
+        port: server.port,
+        format: "regex",
+        regex: { pattern: "BT=(?<btC>[\\d.]+) ET=(?<etC>[\\d.]+)" },
+        assembly: { endMarker: "END" }
+      }
+    });
+    await driver.connect();
+    const point = await driver.readTelemetry();
+    expect(point.btC).toBe(190.5);
+    expect(point.etC).toBe(210);
+    expect(driver.getStatus().metrics.parseErrors).toBe(0);
+    await server.close();
+  }, 20000);
+
+  it("decodes slip framed payloads", async () => {
+    const slip = (payload: string) => {
+      const bytes = [...Buffer.from(payload)].flatMap((b) => (b === 0xc0 ? [0xdb, 0xdc] : b === 0xdb ? [0xdb, 0xdd] : [b]));
+      return Buffer.from([0xc0, ...bytes, 0xc0]);
+    };
+    const server = net.createServer((socket) => {
+      socket.write(Buffer.concat([slip('{"btC":190}'), Buffer.from([0xdb, 0x01, 0xc0]), slip('{"btC":191}')]));
+    });
+    await new Promise<void>((res) => server.listen(0, "127.0.0.1", () => res()));
+    driver = new TcpLineDriver({
+      orgId: "o",
+      siteId: "s",
+      machineId: "m",
+      connection: { host: "127.0.0.1", port: (server.address() as net.AddressInfo).port, framing: "slip" }
+    });
+    await driver.connect();
+    await waitFor(() => driver.getStatus().metrics.linesParsed >= 2, 5000, () => JSON.stringify(driver.getStatus()));
+    expect((await driver.readTelemetry()).btC).toBe(191);
+    expect(driver.getStatus().metrics.parseErrors).toBe(1);
+    await driver.disconnect();
+    await new Promise<void>((res) => server.close(() => res()));
+  }, 20000);
+
+  it("splits on a custom delimiter and rejects oversized frames", async () => {
+    const server = await createServer([`{"btC":190,"pad":"${"x".repeat(100)}"};{"btC":191};`]);
+    driver = new TcpLineDriver({
+      orgId: "o",
+      siteId: "s",
+      machineId: "m",
+      connection: { host: "127.0.0.1", port: server.port, lineDelimiter: ";", maxLineBytes: 64 }
+    });
+    await driver.connect();
+    await waitFor(() => driver.getStatus().metrics.parseErrors >= 1, 5000, () => JSON.stringify(driver.getStatus()));
+    expect((await driver.readTelemetry()).btC).toBe(191);
+    await server.close();
+  }, 20000);
+
+  it("finishes a half-received line on disconnect", async () => {
+    let device: net.Socket | undefined;
+    const server = net.createServer((socket) => {
+      device = socket;
+      socket.write('{"btC":190}\n');
+    });
+    await new Promise<void>((res) => server.listen(0, "127.0.0.1", () => res()));
+    driver = new TcpLineDriver({
+      orgId: "o",
+      siteId: "s",
+      machineId: "m",
+      connection: {
+        host: "127.0.0.1",
+        port: (server.address() as net.AddressInfo).port,
+        queue: { capacity: 10 }
+      }
+    });
+    await driver.connect();
+    await driver.readTelemetry();
+    device?.write('{"btC":1');
+    await new Promise((resolve) => setTimeout(resolve, 50));
+    const stopping = driver.disconnect();
+    await new Promise((resolve) => setTimeout(resolve, 100));
+    device?.write('91}\n');
+    await stopping;
+    expect(driver.drainSamples().map((point) => point.btC)).toEqual([190, 191]);
+    expect(driver.getStatus().state).toBe("STOPPED");
+    await new Promise<void>((res) => server.close(() => res()));
+  }, 20000);
+
+  it("decodes latin1 frames", async () => {
+    const server = net.createServer((socket) => {
+      socket.write(Buffer.from("190,\xb0C\n", "latin1"));
+    });
+    await new Promise<void>((res) => server.listen(0, "127.0.0.1", () => res()));
+    driver = new TcpLineDriver({
+      orgId: "o",
+      siteId: "s",
+      machineId: "m",
+      connection: {
+        host: "127.0.0.1",
+        port: (server.address() as net.AddressInfo).port,
+        format: "csv",
+        csv: { hasHeader: false, delimiter: ",", columns: ["btC", "unit"] },
+        encoding: "latin1"
+      }
+    });
+    await driver.connect();
+    const point = await driver.readTelemetry();
+    expect(point.btC).toBe(190);
+    expect(point.extras).toEqual({ unit: "°C" });
+    await driver.disconnect();
+    await new Promise<void>((res) => server.close(() => res()));
+  }, 20000);
+
+  it("consolidates samples per emit interval when downsampling", async () => {
+    const server = await createServer([
+      '{"ts":"2025-01-01T00:00:00.000Z","btC":100,"etC":210}',
+      '{"ts":"2025-01-01T00:00:00.500Z","btC":102,"etC":200}',
+      '{"ts":"2025-01-01T00:00:01.000Z","btC":110,"etC":190}'
+    ]);
+    driver = new TcpLineDriver({
+      orgId: "o",
+      siteId: "s",
+      machineId: "m",
+      connection: {
+        host: "127.0.0.1",
+        port: server.port,
+        emitIntervalMs: 1000,
+        downsample: { mode: "mean", channels: { etC: "min" } }
+      }
+    });
+    await driver.connect();
+    await waitFor(() => driver.getStatus().metrics.linesParsed >= 3, 5000, () => JSON.stringify(driver.getStatus()));
+    const point = await driver.readTelemetry();
+    expect(point.ts).toBe("2025-01-01T00:00:00.500Z");
+    expect(point.btC).toBe(101);
+    expect(point.etC).toBe(200);
+    await server.close();
+  }, 20000);
+
+  it("emits emitOnChangeOnly channels only when they move past the deadband", async () => {
+    const server = await createServer([
+      '{"ts":"2025-01-01T00:00:00.000Z","drumRpm":60}',
+      '{"ts":"2025-01-01T00:00:01.000Z","drumRpm":60.4}',
+      '{"ts":"2025-01-01T00:00:02.000Z","btC":150,"drumRpm":60.3}',
+      '{"ts":"2025-01-01T00:00:03.000Z","drumRpm":61}'
+    ]);
+    driver = new TcpLineDriver({
+      orgId: "o",
+      siteId: "s",
+      machineId: "m",
+      connection: {
+        host: "127.0.0.1",
+        port: server.port,
+        dedupeWithinMs: 0,
+        channels: { drumRpm: { emitOnChangeOnly: true, deadband: 0.5 } },
+        queue: {}
+      }
+    });
+    await driver.connect();
+    await waitFor(() => driver.getStatus().metrics.linesParsed >= 4, 5000, () => JSON.stringify(driver.getStatus()));
+    const points = driver.drainSamples();
+    expect(points.map((point) => [point.btC ?? null, point.drumRpm ?? null])).toEqual([
+      [null, 60],
+      [150, null],
+      [null, 61]
+    ]);
+    await server.close();
+  }, 20000);
+
+  it("fills channel gaps by holding or interpolating within maxHoldMs", async () => {
+    const server = await createServer([
+      '{"ts":"2025-01-01T00:00:00.000Z","btC":100,"etC":200}',
+      '{"ts":"2025-01-01T00:00:01.000Z","btC":101}',
+      '{"ts":"2025-01-01T00:00:02.000Z","btC":102,"etC":220}',
+      '{"ts":"2025-01-01T00:00:09.000Z","btC":103}',
+      '{"ts":"2025-01-01T00:00:10.000Z","btC":104,"etC":240}'
+    ]);
+    for (const fill of ["holdLast", "linear"] as const) {
+      driver = new TcpLineDriver({
+        orgId: "o",
+        siteId: "s",
+        machineId: "m",
+        connection: {
+          host: "127.0.0.1",
+          port: server.port,
+          dedupeWithinMs: 0,
+          channels: { etC: { fill, maxHoldMs: 3000 } },
+          queue: {}
+        }
+      });
+      await driver.connect();
+      await waitFor(() => driver.getStatus().metrics.linesParsed >= 5, 5000, () => JSON.stringify(driver.getStatus()));
+      const points = driver.drainSamples();
+      expect(points.map((point) => [point.etC ?? null, point.quality?.etC ?? null])).toEqual([
+        [200, "fresh"],
+        fill === "holdLast" ? [200, "held"] : [210, "interpolated"],
+        [220, "fresh"],
+        [null, null],
+        [240, "fresh"]
+      ]);
+      await driver.disconnect();
+    }
+    await server.close();
+  }, 20000);
+
+  it("resamples onto a wall-clock grid while drainSamples keeps the raw samples", async () => {
+    const server = await createServer(['{"btC":150}']);
+    driver = new TcpLineDriver({
+      orgId: "o",
+      siteId: "s",
+      machineId: "m",
+      connection: { host: "127.0.0.1", port: server.port, resample: { intervalMs: 200, mode: "hold" }, queue: {} }
+    });
+    await driver.connect();
+    const points: Array<{ ts: string; btC?: number; quality?: Record<string, string> }> = [];
+    for await (const point of driver.telemetryStream()) {
+      points.push(point);
+      if (points.length === 3) break;
+    }
+    expect(points.map((point) => Date.parse(point.ts) % 200)).toEqual([0, 0, 0]);
+    expect(points.map((point) => point.btC)).toEqual([150, 150, 150]);
+    expect(points[2].quality).toEqual({ btC: "held" });
+    expect(driver.drainSamples().map((point) => point.btC)).toEqual([150]);
+    await server.close();
+  }, 20000);
+
+  it("gives each subscription its own cursor onto the published points", async () => {
+    const server = await createServer(['{"btC":150}', '{"btC":151}', '{"btC":152}']);
+    driver = new TcpLineDriver({
+      orgId: "o",
+      siteId: "s",
+      machineId: "m",
+      connection: { host: "127.0.0.1", port: server.port, emitIntervalMs: 0, dedupeWithinMs: 0 }
+    });
+    const chart = driver.subscribe();
+    const table = driver.subscribe();
+    await driver.connect();
+    const charted: Array<number | undefined> = [];
+    for await (const point of chart) {
+      charted.push(point.btC);
+      if (charted.length === 3) break;
+    }
+    expect(charted).toEqual([150, 151, 152]);
+    expect(table.drain(2).map((point) => point.btC)).toEqual([150, 151]);
+    expect(table.drain().map((point) => point.btC)).toEqual([152]);
+    expect(table.missed).toBe(0);
+    table.close();
+    expect(await table.next()).toEqual({ done: true, value: undefined });
+    expect((await driver.readTelemetry()).btC).toBe(152);
+    await server.close();
+  }, 20000);
+
+  it("times out or aborts a pending read without disconnecting", async () => {
+    const server = await createServer([]);
+    driver = new TcpLineDriver({
+      orgId: "o",
+      siteId: "s",
+      machineId: "m",
+      connection: { host: "127.0.0.1", port: server.port }
+    });
+    await driver.connect({ timeoutMs: 5000 });
+    await expect(driver.readTelemetry({ timeoutMs: 800 })).rejects.toThrow("readTelemetry timed out after 800 ms");
+
+    const controller = new AbortController();
+    setTimeout(() => controller.abort(new Error("panel closed")), 100);
+    await expect(driver.readTelemetry({ timeoutMs: 5000, signal: controller.signal })).rejects.toThrow("panel closed");
+    await expect(driver.readTelemetry({ signal: controller.signal })).rejects.toThrow("panel closed");
+    expect(driver.getStatus().state).toBe("CONNECTED");
+    await server.close();
+  }, 20000);
+
+  it("waits for the first sample per readWait or resolves null on timeout", async () => {
+    const server = await createServer(['{"btC":190}'], { intervalMs: 0 });
+    driver = new TcpLineDriver({
+      orgId: "o",
+      siteId: "s",
+      machineId: "m",
+      connection: { host: "127.0.0.1", port: server.port, readWait: { mode: "waitForever" } }
+    });
+    expect(await driver.readTelemetryWithTimeout(1)).toBeNull();
+    await driver.connect();
+    expect((await driver.readTelemetry()).btC).toBe(190);
+    expect((await driver.readTelemetryWithTimeout(1000))?.btC).toBe(190);
+    await server.close();
+  }, 20000);
+
+  it("returns each sample once under readMode consume", async () => {
+    const server = await createServer(['{"btC":190}', '{"btC":191}'], { intervalMs: 400 });
+    driver = new TcpLineDriver({
+      orgId: "o",
+      siteId: "s",
+      machineId: "m",
+      connection: { host: "127.0.0.1", port: server.port, readMode: "consume", readWait: { timeoutMs: 2000 } }
+    });
+    await driver.connect();
+    expect((await driver.readTelemetry()).btC).toBe(190);
+    expect((await driver.peekTelemetry()).btC).toBe(190);
+    expect((await driver.readTelemetry()).btC).toBe(191);
+    await expect(driver.readTelemetry({ timeoutMs: 300 })).rejects.toThrow("timed out");
+    expect(driver.getStatus().metrics.telemetryEmitted).toBe(2);
+    await server.close();
+  }, 20000);
+
+  it("raises the staleData alarm while no valid sample arrives and clears it on the next", async () => {
+    const server = await createServer(['{"btC":190}', "garbage", '{"btC":191}'], { intervalMs: 600 });
+    driver = new TcpLineDriver({
+      orgId: "o",
+      siteId: "s",
+      machineId: "m",
+      connection: { host: "127.0.0.1", port: server.port, staleDataAlarmMs: 300 }
+    });
+    const events: AlarmEvent[] = [];
+    driver.onAlarm((event) => events.push(event));
+    await driver.connect();
+    await waitFor(() => events.length >= 1, 5000, () => JSON.stringify(driver.getStatus()));
+    expect(driver.getStatus().staleDataAlarm).toBe(true);
+    await waitFor(() => events.length >= 2, 5000, () => JSON.stringify(events));
+    expect(events.map((event) => [event.alarm, event.active])).toEqual([
+      ["staleData", true],
+      ["staleData", false]
+    ]);
+    expect(driver.getStatus().staleDataAlarm).toBe(false);
+    await server.close();
+  }, 20000);
+
+  it("raises a threshold alarm after its debounce and clears it past the hysteresis", async () => {
+    const readings = [241, 239, 242, 243, 244, 238, 234, 233];
+    const server = await createServer(
+      readings.map((btC, s) => JSON.stringify({ ts: `2025-01-01T00:00:0${s}.000Z`, btC }))
+    );
+    const alarms = [
+      { name: "btHigh", channel: "btC", comparator: "above", threshold: 240, hysteresis: 5, debounceMs: 1000 }
+    ];
+    driver = new TcpLineDriver({
+      orgId: "o",
+      siteId: "s",
+      machineId: "m",
+      connection: { host: "127.0.0.1", port: server.port, alarms }
+    });
+    const events: AlarmEvent[] = [];
+    const active: string[][] = [];
+    driver.onAlarm((event) => {
+      events.push(event);
+      active.push((driver.getStatus().activeAlarms ?? []).map((alarm) => alarm.alarm));
+    });
+    await driver.connect();
+    await waitFor(() => events.length >= 2, 5000, () => JSON.stringify(events));
+    expect(events.map((event) => [event.alarm, event.active, event.value])).toEqual([
+      ["btHigh", true, 243],
+      ["btHigh", false, 233]
+    ]);
+    expect(events[0]).toMatchObject({ channel: "btC", threshold: 240 });
+    expect(active).toEqual([["btHigh"], []]);
+    await server.close();
+  }, 20000);
+
+  it("writes a rule's interlock command ahead of a command waiting for its ack", async () => {
+    const received: string[] = [];
+    const server = net.createServer((socket) => {
+      socket.on("data", (chunk) => {
+        for (const line of chunk.toString().split("\n").filter(Boolean)) {
+          received.push(line);
+          if (line === "SET FAN 70") {
+            socket.write('{"btC":250}\n');
+          }
+          if (line === "OT1;0") {
+            socket.write("OK\n");
+          }
+        }
+      });
+    });
+    await new Promise<void>((res) => server.listen(0, "127.0.0.1", () => res()));
+    const addr = server.address();
+    const port = typeof addr === "object" && addr ? addr.port : 0;
+    driver = new TcpLineDriver({
+      orgId: "o",
+      siteId: "s",
+      machineId: "m",
+      connection: {
+        host: "127.0.0.1",
+        port,
+        reconnect: { enabled: false },
+        commands: { ackPrefix: "OK", ackTimeoutMs: 5000 },
+        alarms: [{ name: "btHigh", channel: "btC", comparator: "above", threshold: 240, command: "OT1;0" }]
+      }
+    });
+    await driver.connect();
+
+    await driver.sendCommand("SET FAN 70");
+    expect(received).toEqual(["SET FAN 70", "OT1;0"]);
+    const interlocks = () => driver.getConnectionHistory().filter((event) => event.kind.startsWith("Interlock"));
+    await waitFor(() => interlocks().length === 1, 5000, () => JSON.stringify(driver.getConnectionHistory()));
+    expect(interlocks()[0].kind).toBe("Interlock");
+    expect(interlocks()[0].reason).toContain('btHigh: sent "OT1;0"');
+    await driver.disconnect();
+    await new Promise<void>((res) => server.close(() => res()));
+  }, 20000);
+
+  it("clamps channels to their limits and reports quality and age", async () => {
+    const server = await createServer(['{"ts":"2025-01-01T00:00:00.000Z","btC":1200,"etC":210}']);
+    driver = new TcpLineDriver({
+      orgId: "o",
+      siteId: "s",
+      machineId: "m",
+      connection: { host: "127.0.0.1", port: server.port, channels: { btC: { min: 0, max: 600 } } }
+    });
+    await driver.connect();
+    const point = await driver.readTelemetry();
+    expect(point.btC).toBe(600);
+    expect(point.quality).toEqual({ btC: "clamped", etC: "fresh" });
+    expect(Date.parse(point.sourceReceivedAt!)).toBeGreaterThan(Date.parse("2025-01-01T00:00:00.000Z"));
+    expect(point.ageMs).toBeGreaterThanOrEqual(0);
+    await server.close();
+  }, 20000);
+
+  it("accepts gasPct as an alias of powerPct and reports it under powerField", async () => {
+    const server = await createServer(['{"ts":"2025-01-01T00:00:00.000Z","btC":190,"gasPct":95}']);
+    driver = new TcpLineDriver({
+      orgId: "o",
+      siteId: "s",
+      machineId: "m",
+      connection: { host: "127.0.0.1", port: server.port, powerField: "powerPct", channels: { gasPct: { max: 80 } } }
+    });
+    await driver.connect();
+    const point = await driver.readTelemetry();
+    expect(point.powerPct).toBe(80);
+    expect(point.gasPct).toBeUndefined();
+    expect(point.extras).toBeUndefined();
+    expect(point.quality).toEqual({ btC: "fresh", powerPct: "clamped" });
+    await server.close();
+  }, 20000);
+
+  it("reads ambient channels in their configured units", async () => {
+    const server = await createServer([
+      '{"ts":"2025-01-01T00:00:00.000Z","btC":150,"ambientC":77,"humidityPct":40,"pressureHpa":30}'
+    ]);
+    driver = new TcpLineDriver({
+      orgId: "o",
+      siteId: "s",
+      machineId: "m",
+      connection: {
+        host: "127.0.0.1",
+        port: server.port,
+        environment: { temperatureUnit: "f", pressureUnit: "inHg" },
+        offsets: { humidityPct: -2 }
+      }
+    });
+    await driver.connect();
+    const point = await driver.readTelemetry();
+    expect(point.ambientC).toBeCloseTo(25);
+    expect(point.humidityPct).toBe(38);
+    expect(point.pressureHpa).toBeCloseTo(1015.9, 1);
+    expect(point.extras).toBeUndefined();
+    await server.close();
+  }, 20000);
+
+  it("promotes registered channels from device keys to point fields", async () => {
+    const server = await createServer(['{"ts":"2025-01-01T00:00:00.000Z","btC":150,"T2":180,"dp":-0.5,"rssi":3}']);
+    driver = new TcpLineDriver({
+      orgId: "o",
+      siteId: "s",
+      machineId: "m",
+      connection: {
+        host: "127.0.0.1",
+        port: server.port,
+        channelRegistry: { exhaustC: { key: "T2" }, drumPressurePa: { key: "dp", scale: 100 } }
+      }
+    });
+    await driver.connect();
+    const point = await driver.readTelemetry();
+    expect(point.exhaustC).toBe(180);
+    expect(point.drumPressurePa).toBe(-50);
+    expect(point.extras).toEqual({ rssi: 3 });
+    expect(point.quality).toEqual({ btC: "fresh", exhaustC: "fresh", drumPressurePa: "fresh" });
+    await server.close();
+  }, 20000);
+
+  it("combines calibrated bean probes into btC and reports each probe", async () => {
+    const server = await createServer(['{"ts":"2025-01-01T00:00:00.000Z","bt1":190,"bt2":196,"etC":230}']);
+    driver = new TcpLineDriver({
+      orgId: "o",
+      siteId: "s",
+      machineId: "m",
+      connection: {
+        host: "127.0.0.1",
+        port: server.port,
+        probes: {
+          sensors: [
+            { key: "bt1", channel: "btC", offset: 2 },
+            { key: "bt2", channel: "btC" }
+          ],
+          combine: "mean"
+        }
+      }
+    });
+    await driver.connect();
+    const point = await driver.readTelemetry();
+    expect(point.btC).toBe(194);
+    expect(point.etC).toBe(230);
+    expect(point.probes).toEqual([
+      { name: "bt1", channel: "btC", value: 192 },
+      { name: "bt2", channel: "btC", value: 196 }
+    ]);
+    expect(point.extras).toBeUndefined();
+    await server.close();
+  }, 20000);
+
+  it("integrates powerPct into the session's energy and resets it with startSession", async () => {
+    const lines = [0, 6, 12, 18, 24, 30, 36].map((second) =>
+      JSON.stringify({
+        ts: new Date(Date.UTC(2025, 0, 1, 0, 0, second)).toISOString(),
+        btC: 150 + second,
+        powerPct: 50
+      })
+    );
+    const server = await createServer(lines);
+    driver = new TcpLineDriver({
+      orgId: "o",
+      siteId: "s",
+      machineId: "m",
+      connection: { host: "127.0.0.1", port: server.port, dedupeWithinMs: 0, energy: { ratedKw: 10 } }
+    });
+    await driver.connect();
+    await waitFor(() => driver.getStatus().metrics.linesParsed >= 7, 5000, () => JSON.stringify(driver.getStatus()));
+    // 10 kW at 50% for 36 s.
+    expect((await driver.readTelemetry()).energyKwh).toBeCloseTo(0.05);
+    driver.startSession("roast-1");
+    expect((await driver.readTelemetry()).energyKwh).toBe(0);
+    await server.close();
+  }, 20000);
+
+  it("estimates the sample rate from the gaps between samples", async () => {
+    const lines = Array.from({ length: 8 }, (_, idx) => JSON.stringify({ btC: 150 + idx }));
+    const server = await createServer(lines, { intervalMs: 100 });
+    driver = new TcpLineDriver({
+      orgId: "o",
+      siteId: "s",
+      machineId: "m",
+      connection: { host: "127.0.0.1", port: server.port, dedupeWithinMs: 0 }
+    });
+    expect(driver.getStatus().sampleRate).toBeUndefined();
+    await driver.connect();
+    await waitFor(() => driver.getStatus().metrics.linesParsed >= 8, 5000, () => JSON.stringify(driver.getStatus()));
+    const rate = driver.getStatus().sampleRate!;
+    expect(rate.intervals).toBe(7);
+    expect(rate.hz).toBeGreaterThan(5);
+    expect(rate.hz).toBeLessThan(20);
+    expect(rate.longestGapMs).toBeGreaterThanOrEqual(50);
+    await server.close();
+  }, 20000);
+
+  it("aggregates channels over the most recent window", async () => {
+    const server = await createServer([
+      '{"ts":"2025-01-01T00:00:00.000Z","btC":150,"etC":200}',
+      '{"ts":"2025-01-01T00:00:10.000Z","btC":180,"etC":210,"inletC":300}',
+      '{"ts":"2025-01-01T00:00:15.000Z","btC":190,"inletC":310}'
+    ]);
+    driver = new TcpLineDriver({
+      orgId: "o",
+      siteId: "s",
+      machineId: "m",
+      connection: { host: "127.0.0.1", port: server.port }
+    });
+    await driver.connect();
+    await waitFor(() => driver.getStatus().metrics.linesParsed >= 3, 5000, () => JSON.stringify(driver.getStatus()));
+    const aggregate = driver.readAggregate(10);
+    expect(aggregate.sampleCount).toBe(2);
+    expect(aggregate.from).toBe("2025-01-01T00:00:10.000Z");
+    expect(aggregate.btC).toEqual({ min: 180, max: 190, mean: 185, count: 2 });
+    expect(aggregate.etC).toEqual({ min: 210, max: 210, mean: 210, count: 1 });
+    expect(aggregate.extras).toEqual({ inletC: { min: 300, max: 310, mean: 305, count: 2 } });
+    expect(() => driver.readAggregate(0)).toThrow("windowSeconds must be positive");
+    await server.close();
+  }, 20000);
+
+  it("queues samples and drops the oldest once full", async () => {
+    const server = await createServer(['{"btC":1}', '{"btC":2}', '{"btC":3}', '{"btC":4}']);
+    driver = new TcpLineDriver({
+      orgId: "o",
+      siteId: "s",
+      machineId: "m",
+      connection: { host: "127.0.0.1", port: server.port, queue: { capacity: 2 } }
+    });
+    await driver.connect();
+    await waitFor(() => driver.getStatus().metrics.droppedSamples >= 2, 5000, () => JSON.stringify(driver.getStatus()));
+    expect(driver.drainSamples().map((point) => point.btC)).toEqual([3, 4]);
+    expect(driver.drainSamples()).toEqual([]);
+    await server.close();
+  }, 20000);
+
+  it("spools samples to disk and drains them after a restart", async () => {
+    const dir = mkdtempSync(path.join(tmpdir(), "tcp-line-spool-"));
+    const spoolPath = path.join(dir, "spool.ndjson");
+    const server = await createServer(['{"btC":190}', '{"btC":191}', '{"btC":192}']);
+    const connection = { host: "127.0.0.1", port: server.port, spool: { path: spoolPath } };
+    driver = new TcpLineDriver({ orgId: "o", siteId: "s", machineId: "m", connection });
+    await driver.connect();
+    await waitFor(() => driver.getStatus().metrics.linesParsed >= 3, 5000, () => JSON.stringify(driver.getStatus()));
+    expect(driver.drainPersisted(1).map((point) => point.btC)).toEqual([190]);
+    await driver.disconnect();
+
+    driver = new TcpLineDriver({ orgId: "o", siteId: "s", machineId: "m", connection });
+    expect(driver.drainPersisted().map((point) => point.btC)).toEqual([191, 192]);
+    expect(driver.drainPersisted()).toEqual([]);
+    await server.close();
+    rmSync(dir, { recursive: true, force: true });
+  }, 20000);
+
+  it("writes points to influx in line protocol", async () => {
+    const writes: Array<{ url?: string; auth?: string; body: string }> = [];
+    const influx = http.createServer((req, res) => {
+      let body = "";
+      req.on("data", (chunk) => (body += chunk));
+      req.on("end", () => {
+        writes.push({ url: req.url, auth: req.headers.authorization, body });
+        res.writeHead(204).end();
+      });
+    });
+    await new Promise<void>((resolve) => influx.listen(0, "127.0.0.1", resolve));
+    const influxPort = (influx.address() as net.AddressInfo).port;
+    const server = await createServer(['{"ts":"2025-01-01T00:00:00.000Z","btC":190.5,"note":"ok"}']);
+    driver = new TcpLineDriver({
+      orgId: "o",
+      siteId: "s",
+      machineId: "m1",
+      connection: {
+        host: "127.0.0.1",
+        port: server.port,
+        influx: { url: `http://127.0.0.1:${influxPort}`, org: "plant", bucket: "roasts", token: "secret", flushIntervalMs: 50 }
+      }
+    });
+    await driver.connect();
+    await waitFor(() => driver.getStatus().metrics.influxPointsWritten >= 1, 5000, () => JSON.stringify(driver.getStatus()));
+    expect(writes[0].url).toBe("/api/v2/write?org=plant&bucket=roasts&precision=ms");
+    expect(writes[0].auth).toBe("Token secret");
+    expect(writes[0].body).toBe('roaster_telemetry,machine_id=m1 elapsedSeconds=0,btC=190.5,note="ok" 1735689600000\n');
+    await server.close();
+    await new Promise((resolve) => influx.close(resolve));
+  }, 20000);
+
+  it("counts kafka messages it could not deliver", async () => {
+    const closed = net.createServer();
+    await new Promise<void>((resolve) => closed.listen(0, "127.0.0.1", resolve));
+    const deadPort = (closed.address() as net.AddressInfo).port;
+    await new Promise((resolve) => closed.close(resolve));
+    const server = await createServer(['{"ts":"2025-01-01T00:00:00.000Z","btC":190.5}']);
+    driver = new TcpLineDriver({
+      orgId: "o",
+      siteId: "s",
+      machineId: "m1",
+      connection: {
+        host: "127.0.0.1",
+        port: server.port,
+        kafka: { brokers: [`127.0.0.1:${deadPort}`], topic: "telemetry", lingerMs: 50, deliveryTimeoutMs: 200 }
+      }
+    });
+    await driver.connect();
+    await waitFor(() => driver.getStatus().metrics.kafkaDeliveryErrors >= 1, 5000, () => JSON.stringify(driver.getStatus()));
+    expect(driver.getStatus().metrics.kafkaMessagesDelivered).toBe(0);
+    await server.close();
+  }, 20000);
+
+  it("writes a session log with extras columns on stopLogging", async () => {
+    const dir = mkdtempSync(path.join(tmpdir(), "tcp-line-log-"));
+    const file = path.join(dir, "roast.csv");
+    const server = await createServer([
+      '{"ts":"2025-01-01T00:00:00.000Z","btC":190.5,"note":"ok, fine"}',
+      '{"ts":"2025-01-01T00:00:01.000Z","etC":200,"ror":8.5}'
+    ]);
+    driver = new TcpLineDriver({
+      orgId: "o",
+      siteId: "s",
+      machineId: "m1",
+      connection: { host: "127.0.0.1", port: server.port, dedupeWithinMs: 0 }
+    });
+    driver.startLogging(file);
+    expect(() => driver.startLogging(path.join(dir, "other.csv"))).toThrow("already logging");
+    driver.startSession("roast-1");
+    await driver.connect();
+    await waitFor(() => driver.getStatus().metrics.linesParsed >= 2, 5000, () => JSON.stringify(driver.getStatus()));
+    await driver.disconnect();
+    const summary = driver.stopLogging();
+    expect(summary?.format).toBe("csv");
+    expect(summary?.columns.slice(-2)).toEqual(["note", "ror"]);
+    const rows = readFileSync(file, "utf8").trim().split("\n");
+    expect(rows[0]).toBe("ts,machineId,sessionId,elapsedSeconds,btC,etC,gasPct,fanPct,drumRpm,note,ror");
+    expect(rows[1]).toBe('2025-01-01T00:00:00.000Z,m1,roast-1,0,190.5,,,,,"ok, fine",');
+    expect(rows[2]).toBe("2025-01-01T00:00:01.000Z,m1,roast-1,1,,200,,,,,8.5");
+    expect(driver.stopLogging()).toBeNull();
+    await server.close();
+    rmSync(dir, { recursive: true, force: true });
+  }, 20000);
+
+  it("records frames as received, invalid UTF-8 included, and replays them the same", async () => {
+    const dir = mkdtempSync(path.join(tmpdir(), "tcp-line-record-"));
+    const file = path.join(dir, "capture.ndjson");
+    const server = net.createServer((socket) => {
+      socket.on("error", () => undefined);
+      socket.write(Buffer.concat([Buffer.from('{"btC":190}\r\n'), Buffer.from([0xff, 0xfe, 0x0a])]));
+    });
+    await new Promise<void>((res) => server.listen(0, "127.0.0.1", () => res()));
+    const addr = server.address();
+    driver = new TcpLineDriver({
+      orgId: "o",
+      siteId: "s",
+      machineId: "m",
+      connection: {
+        host: "127.0.0.1",
+        port: typeof addr === "object" && addr ? addr.port : 0,
+        dedupeWithinMs: 0,
+        record: { path: file }
+      }
+    });
+    await driver.connect();
+    await waitFor(() => driver.getStatus().metrics.parseErrors >= 1, 5000, () => JSON.stringify(driver.getStatus()));
+    const live = driver.getStatus().metrics;
+    await driver.disconnect();
+    const entries = readFileSync(file, "utf8").trim().split("\n").map((line) => JSON.parse(line));
+    expect(entries.map((entry) => [entry.line, entry.base64])).toEqual([
+      ['{"btC":190}\r', undefined],
+      ["��", "//4="]
+    ]);
+
+    const replay = new ReplayDriver({
+      orgId: "o",
+      siteId: "s",
+      machineId: "m",
+      connection: { path: file, format: "jsonl", speed: 0, dedupeWithinMs: 0, queue: {} }
+    });
+    await replay.connect();
+    await waitFor(() => replay.getStatus().metrics.parseErrors >= 1, 5000, () => JSON.stringify(replay.getStatus()));
+    const replayed = replay.getStatus().metrics;
+    expect(replay.drainSamples().map((point) => point.btC)).toEqual([190]);
+    expect([replayed.linesReceived, replayed.bytesReceived, replayed.linesParsed]).toEqual([
+      live.linesReceived,
+      live.bytesReceived,
+      live.linesParsed
+    ]);
+    await replay.disconnect();
+    await new Promise<void>((res) => server.close(() => res()));
+    rmSync(dir, { recursive: true, force: true });
+  }, 20000);
+
+  it("exports a session bundle that replays to the same points", async () => {
+    const dir = mkdtempSync(path.join(tmpdir(), "tcp-line-bundle-"));
+    const server = await createServer([
+      '{"ts":"2025-01-01T00:00:00.000Z","btC":190}',
+      "not json",
+      '{"ts":"2025-01-01T00:00:01.000Z","btC":191}'
+    ]);
+    driver = new TcpLineDriver({
+      orgId: "o",
+      siteId: "s",
+      machineId: "m1",
+      connection: {
+        host: "127.0.0.1",
+        port: server.port,
+        dedupeWithinMs: 0,
+        capture: {},
+        influx: { url: "http://127.0.0.1:9", org: "o", bucket: "b", token: "secret" }
+      }
+    });
+    driver.startSession("roast-1");
+    await driver.connect();
+    await waitFor(() => driver.getStatus().metrics.linesParsed >= 2, 5000, () => JSON.stringify(driver.getStatus()));
+    const summary = driver.exportSession(path.join(dir, "bundle.tar"));
+    expect([summary.sessionId, summary.lines, summary.points]).toEqual(["roast-1", 3, 2]);
+    expect(summary.metricsSnapshots).toBeGreaterThanOrEqual(1);
+
+    execFileSync("tar", ["-xf", summary.path, "-C", dir]);
+    expect(JSON.parse(readFileSync(path.join(dir, "manifest.json"), "utf8")).sessionId).toBe("roast-1");
+    expect(JSON.parse(readFileSync(path.join(dir, "config.json"), "utf8")).influx.token).toBe("[redacted]");
+    const replayConfig = JSON.parse(readFileSync(path.join(dir, "replay.json"), "utf8"));
+    const replay = new ReplayDriver({
+      orgId: "o",
+      siteId: "s",
+      machineId: "m1",
+      connection: { ...replayConfig, path: path.join(dir, "lines.ndjson"), speed: 0, queue: {} }
+    });
+    await replay.connect();
+    await waitFor(() => replay.getStatus().metrics.linesParsed >= 2, 5000, () => JSON.stringify(replay.getStatus()));
+    expect(replay.drainSamples().map((point) => point.btC)).toEqual([190, 191]);
+    expect(replay.getStatus().metrics.parseErrors).toBe(1);
+    await replay.disconnect();
+    await server.close();
+    rmSync(dir, { recursive: true, force: true });
+  }, 20000);
+
+  it("reconnects when the embedded test server drops clients", async () => {
+    const server = new TcpLineTestServer({ lines: ['{"ts":"{ts}","btC":182}'], intervalMs: 20 });
+    const port = await server.start();
+    const cfg: DriverConfig = {
+      orgId: "o",
+      siteId: "s",
+      machineId: "m",
+      connection: { host: "127.0.0.1", port, reconnect: { minBackoffMs: 10, maxBackoffMs: 20 }, dedupeWithinMs: 0 }
+    };
+    driver = new TcpLineDriver(cfg);
+    await driver.connect();
+    await waitFor(() => driver.getStatus().metrics.linesParsed > 0, 8000, () => JSON.stringify(driver.getStatus()));
+
+    server.dropConnections();
+    await waitFor(() => server.getStatus().connections >= 2, 8000, () => JSON.stringify(server.getStatus()));
+    await waitFor(() => driver.getStatus().state === "CONNECTED", 5000, () => JSON.stringify(driver.getStatus()));
+
+    const point = await driver.readTelemetry();
+    expect(point.btC).toBe(182);
+    expect(driver.getStatus().metrics.reconnects).toBeGreaterThanOrEqual(1);
+    await server.stop();
+  }, 20000);
+
+  it("emits state change events across a reconnect", async () => {
+    const server = new TcpLineTestServer({ lines: ['{"ts":"{ts}","btC":182}'], intervalMs: 20 });
+    const port = await server.start();
+    const cfg: DriverConfig = {
+      orgId: "o",
+      siteId: "s",
+      machineId: "m",
+      connection: { host: "127.0.0.1", port, reconnect: { minBackoffMs: 10, maxBackoffMs: 20 }, dedupeWithinMs: 0 }
+    };
+    driver = new TcpLineDriver(cfg);
+    const events: StateChangeEvent[] = [];
+    driver.onStateChange((event) => events.push(event));
+    await driver.connect();
+
+    server.dropConnections();
+    await waitFor(() => events.filter((event) => event.state === "CONNECTED").length >= 2, 8000, () => JSON.stringify(events));
+
+    const dropped = events.find((event) => event.previous === "CONNECTED" && event.state === "DISCONNECTED");
+    expect(dropped?.lastError).toBeTruthy();
+    expect(events[0]).toMatchObject({ previous: "DISCONNECTED", state: "CONNECTING" });
+    await server.stop();
+  }, 20000);
+
+  it("reports parse errors and socket closes through onError", async () => {
+    const server = await createServer(['{"ts":"2025-01-01T00:00:00.000Z","btC":180}', "not-json"], { closeAfter: 300, intervalMs: 20 });
+    const cfg: DriverConfig = {
+      orgId: "o",
+      siteId: "s",
+      machineId: "m",
+      connection: { host: "127.0.0.1", port: server.port, reconnect: { minBackoffMs: 10, maxBackoffMs: 20 }, dedupeWithinMs: 0 }
+    };
+    driver = new TcpLineDriver(cfg);
+    const errors: DriverError[] = [];
+    driver.onError((error) => errors.push(error));
+    await driver.connect();
+
+    await waitFor(() => errors.some((error) => error.code === "SocketClosed"), 5000, () => JSON.stringify(errors));
+    expect(errors.find((error) => error.code === "ParseError")?.rawLine).toBe("not-json");
+    await server.close();
+  }, 20000);
+
+  it("keeps recent parse errors with the offending lines", async () => {
+    const long = `{"btC":${"1".repeat(300)}`;
+    const server = await createServer(["not-json", '{"btC":180}', long]);
+    driver = new TcpLineDriver({
+      orgId: "o",
+      siteId: "s",
+      machineId: "m",
+      connection: { host: "127.0.0.1", port: server.port }
+    });
+    await driver.connect();
+
+    await waitFor(() => driver.getRecentErrors().length === 2, 5000, () => JSON.stringify(driver.getStatus()));
+    const [first, second] = driver.getRecentErrors();
+    expect(first).toMatchObject({ kind: "invalidJson", message: "invalid json", line: "not-json", truncated: false });
+    expect(second.line).toBe(long.slice(0, 256));
+    expect(second.truncated).toBe(true);
+    await server.close();
+  }, 20000);
+
+  it("taps raw lines with their parse outcome up to maxPerSecond", async () => {
+    const server = await createServer(["btC,etC", "180,200", 'bad,"x', "181,201", "182,202"], { intervalMs: 5 });
+    driver = new TcpLineDriver({
+      orgId: "o",
+      siteId: "s",
+      machineId: "m",
+      connection: {
+        host: "127.0.0.1",
+        port: server.port,
+        format: "csv",
+        csv: { hasHeader: true, delimiter: ",", columns: [] }
+      }
+    });
+    const events: RawLineEvent[] = [];
+    driver.onRawLine((event) => events.push(event), 3);
+    await driver.connect();
+
+    await waitFor(() => driver.getStatus().metrics.linesReceived >= 5, 5000, () => JSON.stringify(driver.getStatus()));
+    await new Promise((res) => setTimeout(res, 100));
+    expect(events.map((event) => [event.line, event.outcome])).toEqual([
+      ["btC,etC", "skipped"],
+      ["180,200", "parsed"],
+      ['bad,"x', "error"]
+    ]);
+    expect(events[2].error).toBe("unterminated quoted field");
+    await server.close();
+  }, 20000);
+
+  it("applies offset changes live and reconnects on a port change", async () => {
+    const first = new TcpLineTestServer({ lines: ['{"ts":"{ts}","btC":190}'], intervalMs: 20 });
+    const second = new TcpLineTestServer({ lines: ['{"ts":"{ts}","btC":210}'], intervalMs: 20 });
+    const firstPort = await first.start();
+    const secondPort = await second.start();
+    const connection = { host: "127.0.0.1", port: firstPort, dedupeWithinMs: 0 };
+    driver = new TcpLineDriver({ orgId: "o", siteId: "s", machineId: "m", connection });
+    await driver.connect();
+    expect((await driver.readTelemetry()).btC).toBe(190);
+
+    expect(await driver.updateConfig({ ...connection, offsets: { btC: 5 } })).toBe(false);
+    await new Promise((res) => setTimeout(res, 100));
+    expect((await driver.readTelemetry()).btC).toBe(195);
+
+    expect(await driver.updateConfig({ ...connection, port: secondPort })).toBe(true);
+    await new Promise((res) => setTimeout(res, 200));
+    expect((await driver.readTelemetry()).btC).toBe(210);
+    await first.stop();
+    await second.stop();
+  }, 20000);
+
+  it("fails over from a dead primary to a backup endpoint", async () => {
+    const backup = new TcpLineTestServer({ lines: ['{"ts":"{ts}","btC":210}'], intervalMs: 20 });
+    const backupPort = await backup.start();
+    driver = new TcpLineDriver({
+      orgId: "o",
+      siteId: "s",
+      machineId: "m",
+      connection: {
+        host: "127.0.0.1",
+        port: 1,
+        endpoints: [{ host: "127.0.0.1", port: backupPort }],
+        reconnect: { minBackoffMs: 10, maxBackoffMs: 20 },
+        dedupeWithinMs: 0
+      }
+    });
+    await driver.connect();
+
+    expect((await driver.readTelemetry()).btC).toBe(210);
+    expect(driver.getStatus().activeEndpoint).toBe(`127.0.0.1:${backupPort}`);
+    expect(driver.getStatus().metrics.reconnects).toBe(1);
+    await backup.stop();
+  }, 20000);
+
+  it("logs connects, failures and drops in the connection history", async () => {
+    const server = await createServer(['{"btC":180}'], { closeAfter: 150 });
+    driver = new TcpLineDriver({
+      orgId: "o",
+      siteId: "s",
+      machineId: "m",
+      connection: {
+        host: "127.0.0.1",
+        port: 1,
+        endpoints: [{ host: "127.0.0.1", port: server.port }],
+        reconnect: { minBackoffMs: 10, maxBackoffMs: 20 }
+      }
+    });
+    await driver.connect();
+
+    const connects = () => driver.getConnectionHistory().filter((event) => event.kind === "Connected");
+    await waitFor(() => connects().length >= 2, 5000, () => JSON.stringify(driver.getConnectionHistory()));
+    await driver.disconnect();
+    const history = driver.getConnectionHistory();
+    expect(history.slice(0, 5).map((event) => [event.kind, event.endpoint, event.attempt])).toEqual([
+      ["Connecting", "127.0.0.1:1", 1],
+      ["ConnectFailed", "127.0.0.1:1", undefined],
+      ["Connecting", `127.0.0.1:${server.port}`, 2],
+      ["Connected", `127.0.0.1:${server.port}`, undefined],
+      ["Disconnected", `127.0.0.1:${server.port}`, undefined]
+    ]);
+    expect(history[1].reason).toContain("connection failure");
+    expect(history[4].reason).toBe("socket closed");
+    expect(history[history.length - 1].kind).toBe("Stopped");
+    await server.close();
+  }, 20000);
+
+  it("keeps reading lines while paused and emits again after resume", async () => {
+    const server = new TcpLineTestServer({ lines: ['{"ts":"{ts}","btC":190}'], intervalMs: 20 });
+    const port = await server.start();
+    driver = new TcpLineDriver({
+      orgId: "o",
+      siteId: "s",
+      machineId: "m",
+      connection: { host: "127.0.0.1", port }
+    });
+    await driver.connect();
+    await driver.readTelemetry();
+
+    driver.pause();
+    expect(driver.getStatus().state).toBe("PAUSED");
+    const before = driver.getStatus().metrics;
+    let read = false;
+    const pending = driver.readTelemetry().then((point) => {
+      read = true;
+      return point;
+    });
+    await new Promise((resolve) => setTimeout(resolve, 300));
+    const after = driver.getStatus().metrics;
+    expect(after.linesReceived).toBeGreaterThan(before.linesReceived);
+    expect(after.linesParsed).toBe(before.linesParsed);
+    expect(read).toBe(false);
+
+    driver.resume();
+    expect(driver.getStatus().state).toBe("CONNECTED");
+    expect((await pending).btC).toBe(190);
+    expect(driver.getMetricsDetailed().stateDurationsMs.paused).toBeGreaterThan(0);
+    await server.stop();
+  }, 10000);
+
+  it("clears metrics and reconnects on reset", async () => {
+    const server = await createServer(['{"btC":180}', "not json"], { closeAfter: 200 });
+    driver = new TcpLineDriver({
+      orgId: "o",
+      siteId: "s",
+      machineId: "m",
+      connection: { host: "127.0.0.1", port: server.port, reconnect: { enabled: false } }
+    });
+    await driver.connect();
+    await waitFor(() => driver.getStatus().state === "DISCONNECTED", 5000, () => JSON.stringify(driver.getStatus()));
+    expect(driver.getStatus().metrics.parseErrors).toBe(1);
+
+    await driver.reset();
+    expect(driver.getStatus().metrics.linesReceived).toBe(0);
+    expect((await driver.readTelemetry()).btC).toBe(180);
+    expect(server.connections()).toBe(2);
+    await server.close();
+  }, 20000);
+
+  it("rotates endpoints after a drop under roundRobin failover", async () => {
+    const first = new TcpLineTestServer({ lines: ['{"ts":"{ts}","btC":190}'], intervalMs: 20 });
+    const second = new TcpLineTestServer({ lines: ['{"ts":"{ts}","btC":210}'], intervalMs: 20 });
+    const firstPort = await first.start();
+    const secondPort = await second.start();
+    driver = new TcpLineDriver({
+      orgId: "o",
+      siteId: "s",
+      machineId: "m",
+      connection: {
+        host: "127.0.0.1",
+        port: firstPort,
+        endpoints: [{ host: "127.0.0.1", port: secondPort }],
+        failover: "roundRobin",
+        reconnect: { minBackoffMs: 10, maxBackoffMs: 20 },
+        dedupeWithinMs: 0
+      }
+    });
+    await driver.connect();
+    expect((await driver.readTelemetry()).btC).toBe(190);
+
+    first.dropConnections();
+    await waitFor(
+      () => driver.getStatus().activeEndpoint === `127.0.0.1:${secondPort}` && driver.getStatus().state === "CONNECTED",
+      5000,
+      20,
+      () => JSON.stringify(driver.getStatus())
+    );
+    await new Promise((res) => setTimeout(res, 200));
+    expect((await driver.readTelemetry()).btC).toBe(210);
+    await first.stop();
+    await second.stop();
+  }, 20000);
+
+  it("tunnels through an http CONNECT proxy with credentials", async () => {
+    const server = new TcpLineTestServer({ lines: ['{"ts":"{ts}","btC":190}'], intervalMs: 20 });
+    const port = await server.start();
+    const proxy = await startConnectProxy("gw:secret");
+    const connection = {
+      host: "127.0.0.1",
+      port,
+      proxy: { type: "http", host: "127.0.0.1", port: proxy.port, username: "gw", password: "secret" },
+      reconnect: { enabled: false },
+      dedupeWithinMs: 0
+    };
+    driver = new TcpLineDriver({ orgId: "o", siteId: "s", machineId: "m", connection });
+    await driver.connect();
+
+    expect((await driver.readTelemetry()).btC).toBe(190);
+    expect(proxy.requests[0]).toContain(`CONNECT 127.0.0.1:${port} HTTP/1.1`);
+    await driver.disconnect();
+
+    const rejected = { ...connection, proxy: { ...connection.proxy, password: "wrong" } };
+    driver = new TcpLineDriver({ orgId: "o", siteId: "s", machineId: "m", connection: rejected });
+    await expect(driver.connect()).rejects.toThrow("407 Proxy Authentication Required");
+    await proxy.close();
+    await server.stop();
+  }, 20000);
+
+  it("throttles reads to maxBytesPerSecond", async () => {
+    const lines = Array.from({ length: 30 }, (_, idx) => JSON.stringify({ btC: 100 + idx }));
+    const server = await createServer(lines, { intervalMs: 0 });
+    driver = new TcpLineDriver({
+      orgId: "o",
+      siteId: "s",
+      machineId: "m",
+      connection: { host: "127.0.0.1", port: server.port, maxBytesPerSecond: 200, dedupeWithinMs: 0 }
+    });
+    await driver.connect();
+    await new Promise((res) => setTimeout(res, 400));
+    expect(driver.getStatus().metrics.linesReceived).toBeLessThan(30);
+    await waitFor(() => driver.getStatus().metrics.linesReceived === 30, 5000, () => JSON.stringify(driver.getStatus()));
+    await server.close();
+  }, 20000);
+
+  it("logs in with a send/expect handshake before streaming", async () => {
+    const received: string[] = [];
+    const server = net.createServer((socket) => {
+      socket.write("WELCOME gateway\n");
+      socket.on("data", (chunk) => {
+        const line = chunk.toString().trim();
+        received.push(line);
+        socket.write(line === "AUTH secret" ? 'OK\n{"ts":"2025-01-01T00:00:00.000Z","btC":190}\n' : "ERR bad token\n");
+      });
+    });
+    await new Promise<void>((res) => server.listen(0, "127.0.0.1", () => res()));
+    const addr = server.address();
+    const port = typeof addr === "object" && addr ? addr.port : 0;
+    const connection = (token: string) => ({
+      host: "127.0.0.1",
+      port,
+      handshake: [{ expect: "WELCOME" }, { send: `AUTH ${token}`, expect: "OK", timeoutMs: 300 }],
+      reconnect: { enabled: false },
+      dedupeWithinMs: 0
+    });
+    driver = new TcpLineDriver({ orgId: "o", siteId: "s", machineId: "m", connection: connection("secret") });
+    await driver.connect();
+
+    expect((await driver.readTelemetry()).btC).toBe(190);
+    expect(received).toEqual(["AUTH secret"]);
+    expect(driver.getStatus().metrics.linesReceived).toBe(1);
+    expect(driver.getStatus().metrics.bytesSent).toBe("AUTH secret\n".length);
+    await driver.disconnect();
+
+    driver = new TcpLineDriver({ orgId: "o", siteId: "s", machineId: "m", connection: connection("wrong") });
+    await expect(driver.connect()).rejects.toThrow('(last: "ERR bad token")');
+    await new Promise<void>((res) => server.close(() => res()));
+  }, 20000);
+
+  it("counts a handshake that never gets its reply towards maxAttempts", async () => {
+    let connections = 0;
+    const server = net.createServer((socket) => {
+      connections += 1;
+      socket.on("error", () => undefined);
+    });
+    await new Promise<void>((res) => server.listen(0, "127.0.0.1", () => res()));
+    const addr = server.address();
+    driver = new TcpLineDriver({
+      orgId: "o",
+      siteId: "s",
+      machineId: "m",
+      connection: {
+        host: "127.0.0.1",
+        port: typeof addr === "object" && addr ? addr.port : 0,
+        handshake: [{ expect: "WELCOME", timeoutMs: 100 }],
+        reconnect: { enabled: true, maxAttempts: 2, minBackoffMs: 10, maxBackoffMs: 10 }
+      }
+    });
+    await expect(driver.connect()).rejects.toThrow("handshake failed");
+    await waitFor(() => driver.getStatus().state === "FAILED", 5000, () => JSON.stringify(driver.getStatus()));
+    expect(connections).toBe(3);
+    expect(driver.getStatus().metrics.lastError).toContain('no line starting with "WELCOME"');
+    await new Promise<void>((res) => server.close(() => res()));
+  }, 20000);
+
+  it("accepts device connections in listen mode with per-peer metrics", async () => {
+    driver = new TcpLineDriver({
+      orgId: "o",
+      siteId: "s",
+      machineId: "m",
+      connection: { mode: "listen", host: "127.0.0.1", port: 0, maxConnections: 2, dedupeWithinMs: 0 }
+    });
+    const connecting = driver.connect();
+    await waitFor(() => driver.listenPort() !== null);
+    const port = driver.listenPort() as number;
+    const first = net.connect(port, "127.0.0.1");
+    await connecting;
+    first.write('{"btC":180}\nnot-json\n');
+    const second = net.connect(port, "127.0.0.1");
+    second.write('{"btC":200}\n');
+    let thirdClosed = false;
+    const third = net.connect(port, "127.0.0.1").on("close", () => (thirdClosed = true));
+    third.on("error", () => undefined);
+
+    await waitFor(() => thirdClosed && (driver.getStatus().peers ?? []).every((peer) => peer.linesReceived > 0));
+    const peers = driver.getStatus().peers ?? [];
+    expect(peers).toHaveLength(2);
+    expect(peers.map((peer) => [peer.linesParsed, peer.parseErrors]).sort()).toEqual([
+      [1, 0],
+      [1, 1]
+    ]);
+
+    first.end();
+    await waitFor(() => driver.getStatus().peers?.length === 1);
+    expect(driver.getStatus().state).toBe("CONNECTED");
+    second.end();
+    await waitFor(() => driver.getStatus().state === "CONNECTING");
+    expect(driver.getStatus().metrics.lastError).toContain("socket closed");
+  }, 20000);
+
+  it("reads epoch millisecond timestamps", async () => {
+    const server = await createServer(['{"ts":1700000000123,"btC":190}']);
+    driver = new TcpLineDriver({
+      orgId: "o",
+      siteId: "s",
+      machineId: "m",
+      connection: { host: "127.0.0.1", port: server.port, timestamp: { format: "epochMillis" } }
+    });
+    await driver.connect();
+    const point = await driver.readTelemetry();
+    expect(point.ts).toBe("2023-11-14T22:13:20.123Z");
+    await server.close();
+  }, 20000);
+
+  it("measures device clock skew and corrects it", async () => {
+    const ahead = new Date(Date.now() + 60 * 60 * 1000).toISOString();
+    const server = await createServer([`{"ts":"${ahead}","btC":190}`]);
+    driver = new TcpLineDriver({
+      orgId: "o",
+      siteId: "s",
+      machineId: "m",
+      connection: { host: "127.0.0.1", port: server.port, clock: { mode: "corrected" } }
+    });
+    await driver.connect();
+    const point = await driver.readTelemetry();
+    expect(Math.abs(Date.parse(point.ts) - Date.now())).toBeLessThan(5000);
+    expect(driver.getStatus().clockSkewMs).toBeGreaterThan(59 * 60 * 1000);
+    await server.close();
+  }, 20000);
+
+  it("counts elapsedSeconds on the host clock through a device clock step", async () => {
+    const server = await createServer(
+      [
+        '{"ts":"2025-01-01T00:10:00.000Z","btC":150}',
+        '{"ts":"2025-01-01T00:10:00.100Z","btC":151}',
+        '{"ts":"2025-01-01T00:00:00.000Z","btC":152}'
+      ],
+      { intervalMs: 100 }
+    );
+    driver = new TcpLineDriver({
+      orgId: "o",
+      siteId: "s",
+      machineId: "m",
+      connection: {
+        host: "127.0.0.1",
+        port: server.port,
+        emitIntervalMs: 0,
+        dedupeWithinMs: 0,
+        clock: { elapsed: "host" },
+        queue: {}
+      }
+    });
+    await driver.connect();
+    const points: Array<{ elapsedSeconds: number; deviceElapsedSeconds?: number; hostElapsedSeconds?: number }> = [];
+    for await (const point of driver.telemetryStream()) {
+      points.push(point);
+      if (points.length === 3) break;
+    }
+    // The device clock stepped back ten minutes before the third sample; the host clock kept counting.
+    expect(points[2].deviceElapsedSeconds).toBe(0);
+    expect(points[2].hostElapsedSeconds).toBeGreaterThan(points[1].hostElapsedSeconds ?? Infinity);
+    expect(points.map((point) => point.elapsedSeconds)).toEqual(points.map((point) => point.hostElapsedSeconds));
+    await server.close();
+  }, 20000);
+
+  it("keeps changing values with identical timestamps under identicalValues dedupe", async () => {
+    const ts = "2025-01-01T00:00:00.000Z";
+    const server = new TcpLineTestServer({
+      lines: [`{"ts":"${ts}","btC":190}`, `{"ts":"${ts}","btC":190}`, `{"ts":"${ts}","btC":191}`],
+      intervalMs: 30
+    });
+    const port = await server.start();
+    driver = new TcpLineDriver({
+      orgId: "o",
+      siteId: "s",
+      machineId: "m",
+      connection: { host: "127.0.0.1", port, dedupeWithinMs: 5000, dedupeStrategy: "identicalValues" }
+    });
+    await driver.connect();
+    // The exact repeat is dropped but every change of value is kept, though all lines share one timestamp.
+    await waitFor(() => driver.getStatus().metrics.linesParsed >= 3, 5000, () => JSON.stringify(driver.getStatus()));
+    await server.stop();
+  }, 20000);
+
+  it("filters, renames and caps extras", async () => {
+    const server = await createServer(['{"btC":190,"rssi":-60,"T3":201.5,"a":1,"b":2}']);
+    driver = new TcpLineDriver({
+      orgId: "o",
+      siteId: "s",
+      machineId: "m",
+      connection: {
+        host: "127.0.0.1",
+        port: server.port,
+        extras: { exclude: ["rssi"], rename: { T3: "inletC" }, maxPerSample: 2 }
+      }
+    });
+    await driver.connect();
+    const point = await driver.readTelemetry();
+    expect(point.extras).toEqual({ inletC: 201.5, a: 1 });
+    await server.close();
+  }, 20000);
+
+  it("keeps reserved-key fields as extras per onReserved", async () => {
+    const server = await createServer(["B7,190.5,3"]);
+    driver = new TcpLineDriver({
+      orgId: "o",
+      siteId: "s",
+      machineId: "m",
+      connection: {
+        host: "127.0.0.1",
+        port: server.port,
+        format: "csv",
+        csv: { hasHeader: false, columns: ["ts", "btC", "seq"], delimiter: "," },
+        extras: { reserved: ["btC", "seq"], onReserved: "prefix" }
+      }
+    });
+    await driver.connect();
+    const point = await driver.readTelemetry();
+    expect(point.btC).toBe(190.5);
+    expect(point.extras).toEqual({ ts: "B7", x_seq: 3 });
+    await server.close();
+  }, 20000);
+
+  it("reports boolean and enumerated extras as typed values", async () => {
+    const server = await createServer(['{"btC":190,"heaterOn":true,"heater":"OFF","mode":"roast","note":"ok"}']);
+    driver = new TcpLineDriver({
+      orgId: "o",
+      siteId: "s",
+      machineId: "m",
+      connection: {
+        host: "127.0.0.1",
+        port: server.port,
+        extras: { enums: { heater: { ON: true, OFF: false }, mode: { idle: 0, roast: 1 } } }
+      }
+    });
+    await driver.connect();
+    const point = await driver.readTelemetry();
+    expect(point.extras).toEqual({ heaterOn: true, heater: false, mode: 1, note: "ok" });
+    await server.close();
+  }, 20000);
+
+  it("restarts elapsed time and tags points per session", async () => {
+    const server = new TcpLineTestServer({ lines: ['{"btC":190}'], intervalMs: 50 });
+    const port = await server.start();
+    driver = new TcpLineDriver({
+      orgId: "o",
+      siteId: "s",
+      machineId: "m",
+      connection: { host: "127.0.0.1", port, emitIntervalMs: 50, dedupeWithinMs: 0 }
+    });
+    await driver.connect();
+    await new Promise((res) => setTimeout(res, 600));
+    expect((await driver.readTelemetry()).elapsedSeconds).toBeGreaterThan(0.3);
+
+    const session = driver.startSession("batch-1");
+    await new Promise((res) => setTimeout(res, 120));
+    const point = await driver.readTelemetry();
+    expect(point.sessionId).toBe("batch-1");
+    expect(point.elapsedSeconds).toBeLessThan(0.3);
+    expect(driver.getStatus().session).toEqual(session);
+
+    expect(driver.endSession()?.sessionId).toBe("batch-1");
+    expect(driver.getStatus().session).toBeFalsy();
+    await server.stop();
+  }, 20000);
 });
@@ -0,0 +1,23 @@
+import { describe, expect, it } from "vitest";
+import { TcpLineDriverConfigSchema } from "../src/config";
+import { configSchema, validateConfig } from "../src/validation";
+
+describe("config validation", () => {
+  it("accepts a config with defaults filled in", () => {
+    expect(validateConfig(TcpLineDriverConfigSchema.parse({ port: 5555 }))).toEqual([]);
+  });
+
+  it("reports unknown keys and out-of-range values by field", () => {
+    const config = TcpLineDriverConfigSchema.parse({ port: 5555, csv: { delimiter: "" } });
+    const issues = validateConfig({
+      ...config,
+      reconnect: { ...config.reconnect, minBackoffMs: 9000, maxBackoffMs: 100, minBackofMs: 10 }
+    });
+    expect(issues.map((issue) => issue.path)).toEqual(["reconnect.minBackofMs", "csv.delimiter", "reconnect.minBackoffMs"]);
+  });
+
+  it("exports a JSON Schema listing the config fields", () => {
+    const schema = configSchema() as { properties: Record<string, unknown> };
+    expect(Object.keys(schema.properties)).toEqual(expect.arrayContaining(["host", "port", "reconnect", "csv"]));
+  });
+});
@@ -8,19 +8,55 @@ import {
   NonNegativeNumberSchema
 } from "../common/scalars";
 
+export const ChannelQualitySchema = z.enum(["fresh", "held", "interpolated", "clamped"]);
+
+export type ChannelQuality = z.infer<typeof ChannelQualitySchema>;
+
+export const ProbeReadingSchema = z.object({
+  name: NonEmptyStringSchema,
+  channel: z.enum(["btC", "etC"]),
+  value: z.number()
+});
+
+export type ProbeReading = z.infer<typeof ProbeReadingSchema>;
+
+export const PhaseProgressSchema = z.object({
+  current: z.enum(["drying", "maillard", "development"]),
+  dryingPct: BoundedPercentageSchema,
+  maillardPct: BoundedPercentageSchema,
+  developmentPct: BoundedPercentageSchema
+});
+
+export type PhaseProgress = z.infer<typeof PhaseProgressSchema>;
+
 export const TelemetryPointSchema = z.object({
   ts: IsoDateTimeSchema,
   machineId: IdentifierSchema,
   batchId: IdentifierSchema.optional(),
   elapsedSeconds: NonNegativeNumberSchema,
+  deviceElapsedSeconds: NonNegativeNumberSchema.optional(),
+  hostElapsedSeconds: NonNegativeNumberSchema.optional(),
   btC: z.number().optional(),
   etC: z.number().optional(),
   rorCPerMin: z.number().optional(),
   gasPct: BoundedPercentageSchema.optional(),
+  powerPct: BoundedPercentageSchema.optional(),
   fanPct: BoundedPercentageSchema.optional(),
   drumRpm: z.number().optional(),
   ambientC: z.number().optional(),
-  extras: z.record(z.union([z.number(), z.string()])).default({})
+  humidityPct: BoundedPercentageSchema.optional(),
+  pressureHpa: z.number().positive().optional(),
+  exhaustC: z.number().optional(),
+  inletC: z.number().optional(),
+  drumPressurePa: z.number().optional(),
+  airflowPct: BoundedPercentageSchema.optional(),
+  energyKwh: NonNegativeNumberSchema.optional(),
+  extras: z.record(z.union([z.number(), z.string(), z.boolean()])).default({}),
+  probes: z.array(ProbeReadingSchema).optional(),
+  phases: PhaseProgressSchema.optional(),
+  sourceReceivedAt: IsoDateTimeSchema.optional(),
+  ageMs: NonNegativeNumberSchema.optional(),
+  quality: z.record(ChannelQualitySchema).optional()
 });
 
 export type TelemetryPoint = z.infer<typeof TelemetryPointSchema>;